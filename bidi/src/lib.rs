@@ -54,6 +54,26 @@ impl ParagraphDirectionHint {
             }
         }
     }
+
+    /// Resolves an auto-detecting hint to an explicit direction for
+    /// `paragraph`, using the first strong character outside of any
+    /// isolates (rules P2 and P3), or the fallback direction of the
+    /// hint if there is no such character.
+    /// Explicit hints are returned unchanged.
+    pub fn resolve_for_paragraph(self, paragraph: impl IntoIterator<Item = char>) -> Self {
+        let fallback = match self {
+            ParagraphDirectionHint::LeftToRight | ParagraphDirectionHint::RightToLeft => {
+                return self
+            }
+            ParagraphDirectionHint::AutoLeftToRight => Direction::LeftToRight,
+            ParagraphDirectionHint::AutoRightToLeft => Direction::RightToLeft,
+        };
+        let types = paragraph.into_iter().map(bidi_class_for_char);
+        match paragraph_level(types, false, fallback).direction() {
+            Direction::LeftToRight => ParagraphDirectionHint::LeftToRight,
+            Direction::RightToLeft => ParagraphDirectionHint::RightToLeft,
+        }
+    }
 }
 
 #[derive(Debug, Default)]
@@ -460,12 +480,16 @@ impl BidiContext {
         self.base_level = match hint {
             ParagraphDirectionHint::LeftToRight => Level(0),
             ParagraphDirectionHint::RightToLeft => Level(1),
-            ParagraphDirectionHint::AutoLeftToRight => {
-                paragraph_level(&self.char_types, false, Direction::LeftToRight)
-            }
-            ParagraphDirectionHint::AutoRightToLeft => {
-                paragraph_level(&self.char_types, false, Direction::RightToLeft)
-            }
+            ParagraphDirectionHint::AutoLeftToRight => paragraph_level(
+                self.char_types.iter().copied(),
+                false,
+                Direction::LeftToRight,
+            ),
+            ParagraphDirectionHint::AutoRightToLeft => paragraph_level(
+                self.char_types.iter().copied(),
+                false,
+                Direction::RightToLeft,
+            ),
         };
 
         self.dump_state("before X1-X8");
@@ -1413,8 +1437,11 @@ impl BidiContext {
                 }
                 // X5c
                 BidiClass::FirstStrongIsolate => {
-                    let level =
-                        paragraph_level(&self.char_types[idx + 1..], true, Direction::LeftToRight);
+                    let level = paragraph_level(
+                        self.char_types[idx + 1..].iter().copied(),
+                        true,
+                        Direction::LeftToRight,
+                    );
                     self.levels[idx] = stack.embedding_level();
                     stack.apply_override(&mut self.char_types[idx]);
                     let level = if level.0 == 1 {
@@ -1843,9 +1870,13 @@ fn span_one_run(types: &[BidiClass], levels: &[Level], start: usize) -> (Level,
 /// 3.3.1 Paragraph level.
 /// We've been fed a single paragraph, which takes care of rule P1.
 /// This function implements rules P2 and P3.
-fn paragraph_level(types: &[BidiClass], respect_pdi: bool, fallback: Direction) -> Level {
+fn paragraph_level(
+    types: impl IntoIterator<Item = BidiClass>,
+    respect_pdi: bool,
+    fallback: Direction,
+) -> Level {
     let mut isolate_count = 0;
-    for &t in types {
+    for t in types {
         match t {
             BidiClass::RightToLeftIsolate
             | BidiClass::LeftToRightIsolate
@@ -2050,6 +2081,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn resolve_for_paragraph() {
+        assert_eq!(
+            ParagraphDirectionHint::AutoLeftToRight.resolve_for_paragraph("123 שלום abc".chars()),
+            ParagraphDirectionHint::RightToLeft
+        );
+        assert_eq!(
+            ParagraphDirectionHint::AutoRightToLeft.resolve_for_paragraph("ls שלום".chars()),
+            ParagraphDirectionHint::LeftToRight
+        );
+        assert_eq!(
+            ParagraphDirectionHint::AutoRightToLeft.resolve_for_paragraph("123 ...".chars()),
+            ParagraphDirectionHint::RightToLeft
+        );
+        assert_eq!(
+            ParagraphDirectionHint::AutoLeftToRight
+                .resolve_for_paragraph("\u{2067}abc\u{2069} مرحبا".chars()),
+            ParagraphDirectionHint::RightToLeft
+        );
+        assert_eq!(
+            ParagraphDirectionHint::LeftToRight.resolve_for_paragraph("שלום".chars()),
+            ParagraphDirectionHint::LeftToRight
+        );
+    }
+
     #[test]
     fn mirror() {
         assert_eq!(lookup_closing('{'), Some(('}', BracketType::Open)));
@@ -470,6 +470,7 @@ TOC = [
             Page("wezterm replay", "cli/replay.md"),
             Page("wezterm serial", "cli/serial.md"),
             Page("wezterm set-working-directory", "cli/set-working-directory.md"),
+            Page("wezterm show-gpu-info", "cli/show-gpu-info.md"),
             Page("wezterm show-keys", "cli/show-keys.md"),
             Page("wezterm ssh", "cli/ssh.md"),
             Page("wezterm start", "cli/start.md"),
@@ -30,7 +30,7 @@ use std::ops::Range;
 use std::path::PathBuf;
 use std::sync::Arc;
 use termwiz::hyperlink::Hyperlink;
-use termwiz::image::{ImageData, TextureCoordinate};
+use termwiz::image::{DisplayCells, ImageData, ImageProtocol, TextureCoordinate};
 use termwiz::surface::{Line, SequenceNo};
 use thiserror::Error;
 use wezterm_term::color::ColorPalette;
@@ -441,7 +441,7 @@ macro_rules! pdu {
 /// The overall version of the codec.
 /// This must be bumped when backwards incompatible changes
 /// are made to the types and protocol.
-pub const CODEC_VERSION: usize = 45;
+pub const CODEC_VERSION: usize = 49;
 
 // Defines the Pdu enum.
 // Each struct has an explicit identifying number.
@@ -502,6 +502,8 @@ pdu! {
     GetPaneDirection: 60,
     GetPaneDirectionResponse: 61,
     AdjustPaneSize: 62,
+    CaptureImage: 63,
+    CaptureImageResponse: 64,
 }
 
 impl Pdu {
@@ -683,6 +685,9 @@ pub struct SpawnV2 {
     pub command_dir: Option<String>,
     pub size: TerminalSize,
     pub workspace: String,
+    /// An xdg-activation token that was given to the program making
+    /// the request, which the gui uses to focus the window
+    pub activation_token: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
@@ -876,6 +881,20 @@ pub struct AdjustPaneSize {
     pub amount: usize,
 }
 
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct CaptureImage {
+    pub pane_id: PaneId,
+    /// Capture the whole gui window that contains the pane,
+    /// rather than just the pane
+    pub whole_window: bool,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct CaptureImageResponse {
+    /// PNG encoded image data
+    pub png: Vec<u8>,
+}
+
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub struct GetPaneDirectionResponse {
     pub pane_id: Option<PaneId>,
@@ -961,6 +980,8 @@ pub struct SerializedImageCell {
     pub padding_bottom: u16,
     pub image_id: Option<u32>,
     pub placement_id: Option<u32>,
+    pub protocol: ImageProtocol,
+    pub display_cells: Option<DisplayCells>,
 }
 
 /// What's all this?
@@ -1077,6 +1098,8 @@ impl From<Vec<(StableRowIndex, Line)>> for SerializedLines {
                             padding_bottom,
                             image_id: imcell.image_id(),
                             placement_id: imcell.placement_id(),
+                            protocol: imcell.protocol(),
+                            display_cells: imcell.display_cells(),
                             data_hash: imcell.image_data().hash(),
                         });
                     }
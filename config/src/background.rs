@@ -1,4 +1,6 @@
-use crate::{default_one_point_oh, Config, Dimension, HsbTransform, PixelUnit, RgbaColor};
+use crate::{
+    default_one_point_oh, default_true, Config, Dimension, HsbTransform, PixelUnit, RgbaColor,
+};
 use luahelper::impl_lua_conversion_dynamic;
 use termwiz::color::SrgbaTuple;
 use wezterm_dynamic::{FromDynamic, FromDynamicOptions, ToDynamic, Value};
@@ -7,9 +9,14 @@ use wezterm_dynamic::{FromDynamic, FromDynamicOptions, ToDynamic, Value};
 pub struct ImageFileSource {
     pub path: String,
 
-    /// Adjust the animation rate for animated images
+    /// Adjust the animation rate for animated images and videos
     #[dynamic(default = "default_one_point_oh")]
     pub speed: f32,
+
+    /// Stop animating while the window doesn't have focus,
+    /// to save power
+    #[dynamic(default = "default_true")]
+    pub pause_when_unfocused: bool,
 }
 
 #[derive(Debug, Clone, ToDynamic)]
@@ -35,6 +42,7 @@ impl FromDynamic for ImageFileSourceWrap {
                 inner: ImageFileSource {
                     path: path.to_string(),
                     speed: 1.0,
+                    pause_when_unfocused: true,
                 },
             }),
             _ => {
@@ -96,6 +104,10 @@ pub struct BackgroundLayer {
 
     #[dynamic(default)]
     pub height: BackgroundSize,
+
+    /// How the image is resampled when it is scaled
+    #[dynamic(default)]
+    pub filter: ImageFilter,
 }
 
 impl BackgroundLayer {
@@ -107,6 +119,7 @@ impl BackgroundLayer {
                 inner: ImageFileSource {
                     path: path.to_string_lossy().to_string(),
                     speed: 1.0,
+                    pause_when_unfocused: true,
                 },
             })
         } else {
@@ -128,6 +141,7 @@ impl BackgroundLayer {
             horizontal_offset: None,
             width: BackgroundSize::Dimension(Dimension::Percent(1.)),
             height: BackgroundSize::Dimension(Dimension::Percent(1.)),
+            filter: Default::default(),
         })
     }
 }
@@ -301,6 +315,30 @@ pub enum SystemBackdrop {
     Tabbed,
 }
 
+/// Which part of the window kde_window_background_blur applies to
+#[derive(Debug, Copy, Clone, FromDynamic, ToDynamic, PartialEq, Eq, Default)]
+pub enum BlurRegion {
+    /// The whole window, including padding and the tab bar
+    #[default]
+    Window,
+    /// Only the area occupied by terminal cells
+    Content,
+}
+
+/// How an image is resampled when it is displayed at a size
+/// other than its native size
+#[derive(Debug, Copy, Clone, FromDynamic, ToDynamic, PartialEq, Eq, Default)]
+pub enum ImageFilter {
+    /// Use the nearest pixel, keeping hard edges, which suits pixel art
+    Nearest,
+    /// Bilinear interpolation
+    #[default]
+    Linear,
+    /// Bicubic (Catmull-Rom) interpolation, which is sharper than
+    /// Linear when enlarging photographic images
+    Cubic,
+}
+
 pub fn default_win32_acrylic_accent_color() -> RgbaColor {
     SrgbaTuple(0.156863, 0.156863, 0.156863, 0.003922).into()
 }
@@ -56,12 +56,53 @@ pub struct VisualBell {
     pub fade_out_function: EasingFunction,
     #[dynamic(default)]
     pub target: VisualBellTarget,
+    /// Additional effects that are shown alongside the one
+    /// described by the fields above
+    #[dynamic(default)]
+    pub effects: Vec<VisualBellEffect>,
+}
+
+impl VisualBell {
+    /// Returns all of the effects that are shown when the bell rings
+    pub fn all_effects(&self) -> impl Iterator<Item = VisualBellEffect> + '_ {
+        std::iter::once(VisualBellEffect {
+            fade_in_duration_ms: self.fade_in_duration_ms,
+            fade_in_function: self.fade_in_function,
+            fade_out_duration_ms: self.fade_out_duration_ms,
+            fade_out_function: self.fade_out_function,
+            target: self.target.clone(),
+        })
+        .chain(self.effects.iter().cloned())
+    }
+}
+
+#[derive(Default, Debug, Clone, FromDynamic, ToDynamic)]
+pub struct VisualBellEffect {
+    #[dynamic(default)]
+    pub fade_in_duration_ms: u64,
+    #[dynamic(default)]
+    pub fade_in_function: EasingFunction,
+    #[dynamic(default)]
+    pub fade_out_duration_ms: u64,
+    #[dynamic(default)]
+    pub fade_out_function: EasingFunction,
+    #[dynamic(default)]
+    pub target: VisualBellTarget,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, FromDynamic, ToDynamic)]
 pub enum VisualBellTarget {
     BackgroundColor,
     CursorColor,
+    /// Flash a frame around the edges of the pane
+    Border,
+    /// Flash the cell under the cursor, whatever the cursor style
+    CursorCell,
+    /// Flash the title of the tab containing the pane, when
+    /// that tab is not the active tab
+    TabTitle,
+    /// Shake the contents of the window from side to side
+    ScreenShake,
 }
 
 impl Default for VisualBellTarget {
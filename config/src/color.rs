@@ -28,6 +28,29 @@ impl Default for HsbTransform {
     }
 }
 
+/// An effect that is applied to the rendered region of a pane
+/// when compositing the window
+#[derive(Debug, Copy, Clone, Default, FromDynamic, ToDynamic)]
+pub struct PaneEffect {
+    #[dynamic(flatten)]
+    pub hsb: HsbTransform,
+    /// The radius of a blur, in pixels.  0 disables blurring.
+    #[dynamic(default)]
+    pub blur: f32,
+}
+impl_lua_conversion_dynamic!(PaneEffect);
+
+impl PaneEffect {
+    /// Returns true if applying the effect would leave
+    /// the pane unchanged
+    pub fn is_identity(&self) -> bool {
+        self.hsb.hue == 1.
+            && self.hsb.saturation == 1.
+            && self.hsb.brightness == 1.
+            && self.blur <= 0.
+    }
+}
+
 #[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash, FromDynamic, ToDynamic)]
 #[dynamic(try_from = "String", into = "String")]
 pub struct RgbaColor {
@@ -153,8 +176,25 @@ pub struct Palette {
     /// The color of the "thumb" of the scrollbar; the segment that
     /// represents the current viewable area
     pub scrollbar_thumb: Option<RgbaColor>,
+    /// The colors of the markers on the scrollbar for search
+    /// matches, prompts and commands that failed
+    pub scrollbar_search_marker: Option<RgbaColor>,
+    pub scrollbar_prompt_marker: Option<RgbaColor>,
+    pub scrollbar_error_marker: Option<RgbaColor>,
+    /// The background of the minimap.  If unspecified, the
+    /// background color is used with some transparency.
+    pub minimap_background: Option<RgbaColor>,
+    /// The color of the region of the minimap that is in the viewport.
+    /// If unspecified, the foreground color is used with some transparency.
+    pub minimap_viewport: Option<RgbaColor>,
     /// The color of the split line between panes
     pub split: Option<RgbaColor>,
+    /// The color of the portion of the split line that borders
+    /// the active pane.  If unspecified, the split color is used.
+    pub split_active: Option<RgbaColor>,
+    /// The color of the shadow cast by the active pane when
+    /// active_pane_shadow_size is set
+    pub active_pane_shadow: Option<RgbaColor>,
     /// The color of the visual bell. If unspecified, the foreground
     /// color is used instead.
     pub visual_bell: Option<RgbaColor>,
@@ -170,6 +210,8 @@ pub struct Palette {
     pub quick_select_label_bg: Option<ColorSpec>,
     pub quick_select_match_fg: Option<ColorSpec>,
     pub quick_select_match_bg: Option<ColorSpec>,
+    pub quick_select_tagged_fg: Option<ColorSpec>,
+    pub quick_select_tagged_bg: Option<ColorSpec>,
 
     pub input_selector_label_fg: Option<ColorSpec>,
     pub input_selector_label_bg: Option<ColorSpec>,
@@ -214,7 +256,14 @@ impl Palette {
                 map
             },
             scrollbar_thumb: overlay!(scrollbar_thumb),
+            scrollbar_search_marker: overlay!(scrollbar_search_marker),
+            scrollbar_prompt_marker: overlay!(scrollbar_prompt_marker),
+            scrollbar_error_marker: overlay!(scrollbar_error_marker),
+            minimap_background: overlay!(minimap_background),
+            minimap_viewport: overlay!(minimap_viewport),
             split: overlay!(split),
+            split_active: overlay!(split_active),
+            active_pane_shadow: overlay!(active_pane_shadow),
             visual_bell: overlay!(visual_bell),
             compose_cursor: overlay!(compose_cursor),
             copy_mode_active_highlight_fg: overlay!(copy_mode_active_highlight_fg),
@@ -225,6 +274,8 @@ impl Palette {
             quick_select_label_bg: overlay!(quick_select_label_bg),
             quick_select_match_fg: overlay!(quick_select_match_fg),
             quick_select_match_bg: overlay!(quick_select_match_bg),
+            quick_select_tagged_fg: overlay!(quick_select_tagged_fg),
+            quick_select_tagged_bg: overlay!(quick_select_tagged_bg),
             input_selector_label_fg: overlay!(input_selector_label_fg),
             input_selector_label_bg: overlay!(input_selector_label_bg),
             launcher_label_fg: overlay!(launcher_label_fg),
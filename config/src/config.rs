@@ -1,7 +1,7 @@
-use crate::background::{BackgroundLayer, Gradient};
+use crate::background::{BackgroundLayer, BlurRegion, Gradient, ImageFilter};
 use crate::bell::{AudibleBell, EasingFunction, VisualBell};
 use crate::color::{
-    ColorSchemeFile, HsbTransform, Palette, SrgbaTuple, TabBarStyle, WindowFrameConfig,
+    ColorSchemeFile, HsbTransform, Palette, PaneEffect, SrgbaTuple, TabBarStyle, WindowFrameConfig,
 };
 use crate::daemon::DaemonOptions;
 use crate::exec_domain::ExecDomain;
@@ -9,9 +9,10 @@ use crate::font::{
     AllowSquareGlyphOverflow, DisplayPixelGeometry, FontLocatorSelection, FontRasterizerSelection,
     FontShaperSelection, FreeTypeLoadFlags, FreeTypeLoadTarget, StyleRule, TextStyle,
 };
-use crate::frontend::FrontEndSelection;
+use crate::frontend::{FrontEndSelection, HdrMode};
 use crate::keyassignment::{
-    KeyAssignment, KeyTable, KeyTableEntry, KeyTables, MouseEventTrigger, SpawnCommand,
+    KeyAssignment, KeyTable, KeyTableEntry, KeyTables, MouseEventTrigger, QuickSelectPattern,
+    SpawnCommand,
 };
 use crate::keys::{Key, LeaderKey, Mouse};
 use crate::lua::make_lua_context;
@@ -19,13 +20,14 @@ use crate::ssh::{SshBackend, SshDomain};
 use crate::tls::{TlsDomainClient, TlsDomainServer};
 use crate::units::Dimension;
 use crate::unix::UnixDomain;
+use crate::window::{PresentationMode, WaylandFrameTiming, WaylandLayerShell};
 use crate::wsl::WslDomain;
 use crate::{
     default_config_with_overrides_applied, default_one_point_oh, default_one_point_oh_f64,
-    default_true, default_win32_acrylic_accent_color, CellWidth, GpuInfo,
+    default_true, default_win32_acrylic_accent_color, CellWidth, GpuPreferenceList,
     IntegratedTitleButtonColor, KeyMapPreference, LoadedConfig, MouseEventTriggerMods, RgbaColor,
-    SerialDomain, SystemBackdrop, WebGpuPowerPreference, CONFIG_DIRS, CONFIG_FILE_OVERRIDE,
-    CONFIG_OVERRIDES, CONFIG_SKIP, HOME_DIR,
+    SerialDomain, SystemBackdrop, WebGpuPowerPreference, CACHE_DIR, CONFIG_DIRS,
+    CONFIG_FILE_OVERRIDE, CONFIG_OVERRIDES, CONFIG_SKIP, HOME_DIR,
 };
 use anyhow::Context;
 use luahelper::impl_lua_conversion_dynamic;
@@ -44,7 +46,7 @@ use wezterm_config_derive::ConfigMeta;
 use wezterm_dynamic::{FromDynamic, ToDynamic};
 use wezterm_input_types::{
     IntegratedTitleButton, IntegratedTitleButtonAlignment, IntegratedTitleButtonStyle, Modifiers,
-    UIKeyCapRendering, WindowDecorations,
+    MousePress, UIKeyCapRendering, WindowDecorations,
 };
 use wezterm_term::TerminalSize;
 
@@ -75,6 +77,12 @@ pub struct Config {
     #[dynamic(try_from = "crate::units::OptPixelUnit", default)]
     pub strikethrough_position: Option<Dimension>,
 
+    #[dynamic(try_from = "crate::units::OptPixelUnit", default)]
+    pub double_underline_gap: Option<Dimension>,
+
+    #[dynamic(try_from = "crate::units::OptPixelUnit", default)]
+    pub curly_underline_height: Option<Dimension>,
+
     #[dynamic(default)]
     pub allow_square_glyphs_to_overflow_width: AllowSquareGlyphOverflow,
 
@@ -203,6 +211,21 @@ pub struct Config {
     )]
     pub scrollback_lines: usize,
 
+    /// Whether lines that have scrolled far back into the scrollback
+    /// are held in compressed form to reduce memory usage
+    #[dynamic(default = "default_true")]
+    pub compress_scrollback: bool,
+
+    /// Keyed by domain name; enables disk backed scrollback for
+    /// the panes that are spawned into that domain
+    #[dynamic(default)]
+    pub disk_scrollback: HashMap<String, DiskScrollback>,
+
+    /// How many lines that scroll off the top of the alternate
+    /// screen are retained so that they can be reviewed; 0 disables this
+    #[dynamic(default)]
+    pub alternate_screen_capture_lines: usize,
+
     /// If no `prog` is specified on the command line, use this
     /// instead of running the user's shell.
     /// For example, to have `wezterm` always run `top` by default,
@@ -252,6 +275,16 @@ pub struct Config {
     #[dynamic(default)]
     pub enable_kitty_keyboard: bool,
 
+    /// How images placed using the kitty, sixel and iTerm2 image
+    /// protocols are resampled when displayed at other than their
+    /// native size
+    #[dynamic(default = "default_image_filter")]
+    pub kitty_image_filter: ImageFilter,
+    #[dynamic(default = "default_image_filter")]
+    pub sixel_image_filter: ImageFilter,
+    #[dynamic(default = "default_image_filter")]
+    pub iterm_image_filter: ImageFilter,
+
     /// Whether the terminal should respond to requests to read the
     /// title string.
     /// Disabled by default for security concerns with shells that might
@@ -334,6 +367,12 @@ pub struct Config {
     #[dynamic(default = "default_harfbuzz_features")]
     pub harfbuzz_features: Vec<String>,
 
+    /// Ligatures are turned off in panes whose foreground process
+    /// has one of these names, unless overridden for the pane
+    /// via window:set_pane_ligatures
+    #[dynamic(default)]
+    pub disable_ligatures_for_processes_named: Vec<String>,
+
     #[dynamic(default)]
     pub front_end: FrontEndSelection,
 
@@ -346,8 +385,27 @@ pub struct Config {
     #[dynamic(default)]
     pub webgpu_force_fallback_adapter: bool,
 
+    /// The adapters to try, in order of preference, before
+    /// falling back to webgpu_power_preference
     #[dynamic(default)]
-    pub webgpu_preferred_adapter: Option<GpuInfo>,
+    pub webgpu_preferred_adapter: GpuPreferenceList,
+
+    /// Whether to render into a floating point, extended range
+    /// (scRGB) surface on HDR displays.  Only applicable to
+    /// the WebGpu front end.
+    #[dynamic(default)]
+    pub hdr: HdrMode,
+
+    /// An ICC profile describing the display.  When set, colors are
+    /// converted from sRGB into the color space of the display.
+    /// Only applicable to the WebGpu front end.
+    #[dynamic(default)]
+    pub icc_profile: Option<PathBuf>,
+
+    /// Whether to use the ICC profile that the windowing system
+    /// publishes for the display when icc_profile is not set
+    #[dynamic(default)]
+    pub use_display_icc_profile: bool,
 
     #[dynamic(default)]
     pub wsl_domains: Option<Vec<WslDomain>>,
@@ -405,6 +463,12 @@ pub struct Config {
     #[dynamic(default = "default_mux_output_parser_coalesce_delay_ms")]
     pub mux_output_parser_coalesce_delay_ms: u64,
 
+    /// The longest that an application may hold its output using
+    /// synchronized output (DEC private mode 2026) before it is
+    /// displayed regardless.  0 allows output to be held indefinitely.
+    #[dynamic(default = "default_synchronized_output_timeout_ms")]
+    pub synchronized_output_timeout_ms: u64,
+
     #[dynamic(default = "default_mux_env_remove")]
     pub mux_env_remove: Vec<String>,
 
@@ -419,9 +483,18 @@ pub struct Config {
     #[dynamic(default)]
     pub debug_key_events: bool,
 
+    /// When true, each damage rectangle submitted to the windowing
+    /// system is tinted with a translucent color that changes from
+    /// one frame to the next
+    #[dynamic(default)]
+    pub debug_visualize_damage: bool,
+
     #[dynamic(default)]
     pub normalize_output_to_unicode_nfc: bool,
 
+    #[dynamic(default = "default_true")]
+    pub grapheme_clustering: bool,
+
     #[dynamic(default)]
     pub disable_default_key_bindings: bool,
     pub leader: Option<LeaderKey>,
@@ -432,7 +505,7 @@ pub struct Config {
     #[dynamic(default)]
     pub disable_default_quick_select_patterns: bool,
     #[dynamic(default)]
-    pub quick_select_patterns: Vec<String>,
+    pub quick_select_patterns: Vec<QuickSelectPattern>,
     #[dynamic(default = "default_alphabet")]
     pub quick_select_alphabet: String,
     #[dynamic(default)]
@@ -492,6 +565,15 @@ pub struct Config {
     #[dynamic(default = "default_true")]
     pub show_close_tab_button_in_tabs: bool,
 
+    /// How the progress reported by OSC 9;4 is shown in tab titles
+    #[dynamic(default)]
+    pub tab_bar_progress_style: TabBarProgressStyle,
+
+    /// If true, inactive tabs with output that has not yet been
+    /// seen show a badge with the number of new lines
+    #[dynamic(default)]
+    pub show_tab_unseen_output_badge: bool,
+
     /// If true, show_tab_index_in_tab_bar uses a zero-based index.
     /// The default is false and the tab shows a one-based index.
     #[dynamic(default)]
@@ -512,6 +594,27 @@ pub struct Config {
     #[dynamic(try_from = "crate::units::PixelUnit", default = "default_half_cell")]
     pub min_scroll_bar_height: Dimension,
 
+    /// If true, the scroll bar marks the positions of search
+    /// matches, prompts and failed commands
+    #[dynamic(default = "default_true")]
+    pub show_scroll_bar_markers: bool,
+
+    /// If true, the prompts of commands that reported their exit
+    /// status are marked in the left gutter of the pane
+    #[dynamic(default = "default_true")]
+    pub show_command_status_markers: bool,
+
+    /// If true, show a miniature view of the scrollback along the
+    /// right edge of the active pane
+    #[dynamic(default)]
+    pub enable_minimap: bool,
+
+    #[dynamic(
+        try_from = "crate::units::PixelUnit",
+        default = "default_minimap_width"
+    )]
+    pub minimap_width: Dimension,
+
     /// If false, do not try to use a Wayland protocol connection
     /// when starting the gui frontend, and instead use X11.
     /// This option is only considered on X11/Wayland systems and
@@ -522,6 +625,29 @@ pub struct Config {
     #[dynamic(default)]
     pub enable_zwlr_output_manager: bool,
 
+    /// When set, and the compositor supports zwlr_layer_shell_v1,
+    /// windows are created as layer surfaces rather than as
+    /// regular toplevel windows.
+    #[dynamic(default)]
+    pub wayland_layer_shell: Option<WaylandLayerShell>,
+
+    /// Controls the target presentation time that we request for
+    /// each frame when the compositor supports wp_commit_timing_v1.
+    #[dynamic(default)]
+    pub wayland_frame_timing: WaylandFrameTiming,
+
+    /// The mouse buttons that the barrel buttons of a graphics tablet
+    /// stylus act as, starting with the lower button.
+    /// This is currently only implemented on Wayland.
+    #[dynamic(default = "default_stylus_barrel_buttons")]
+    pub stylus_barrel_buttons: Vec<MousePress>,
+
+    /// Whether to allow the compositor to present frames as soon as
+    /// they are ready, at the cost of tearing.  Requires
+    /// wp_tearing_control_v1 support on Wayland.
+    #[dynamic(default)]
+    pub presentation_mode: PresentationMode,
+
     /// Whether to prefer EGL over other GL implementations.
     /// EGL on Windows has jankier resize behavior than WGL (which
     /// is used if EGL is unavailable), but EGL survives graphics
@@ -557,6 +683,24 @@ pub struct Config {
     #[dynamic(default)]
     pub foreground_text_hsb: HsbTransform,
 
+    /// Adjusts the antialiasing coverage of text to even out its
+    /// perceived weight across themes.  Values above 1.0 make dark
+    /// text heavier and light text thinner, values below 1.0 do the
+    /// opposite.
+    #[dynamic(default = "default_one_point_oh", validate = "validate_text_gamma")]
+    pub text_gamma: f32,
+
+    /// Scales the antialiasing coverage of text; values above 1.0
+    /// make the edges of the strokes more solid
+    #[dynamic(default = "default_one_point_oh")]
+    pub text_contrast: f32,
+
+    /// Specifies the path to a fragment shader that is applied to
+    /// the rendered window as a final full screen pass.  The WebGpu
+    /// front end uses WGSL shaders, whereas OpenGL uses GLSL.
+    #[dynamic(default)]
+    pub post_process_shader: Option<PathBuf>,
+
     #[dynamic(default)]
     pub background: Vec<BackgroundLayer>,
 
@@ -568,6 +712,14 @@ pub struct Config {
     #[dynamic(default)]
     pub kde_window_background_blur: bool,
 
+    /// Only works on KDE Wayland
+    #[dynamic(default)]
+    pub kde_window_background_blur_region: BlurRegion,
+
+    /// Only works on KDE Wayland
+    #[dynamic(default)]
+    pub kde_window_background_blur_corner_radius: u16,
+
     /// Only works on Windows
     #[dynamic(default)]
     pub win32_system_backdrop: SystemBackdrop,
@@ -620,6 +772,27 @@ pub struct Config {
     #[dynamic(default = "default_inactive_pane_hsb")]
     pub inactive_pane_hsb: HsbTransform,
 
+    /// The radius, in pixels, of a blur that is applied to inactive
+    /// panes together with inactive_pane_hsb.  0 disables blurring.
+    #[dynamic(default)]
+    pub inactive_pane_blur: f32,
+
+    /// The thickness of the split lines between panes.
+    /// Defaults to the underline thickness.
+    #[dynamic(try_from = "crate::units::OptPixelUnit", default)]
+    pub pane_split_thickness: Option<Dimension>,
+
+    /// When true, the portion of a split line that borders the active
+    /// pane is shaded from the split_active color on the side of the
+    /// active pane to the split color on the other side
+    #[dynamic(default)]
+    pub pane_split_gradient: bool,
+
+    /// If set, the active pane casts a shadow of this size over
+    /// the panes on the other side of the splits that border it
+    #[dynamic(try_from = "crate::units::OptPixelUnit", default)]
+    pub active_pane_shadow_size: Option<Dimension>,
+
     #[dynamic(default = "default_one_point_oh")]
     pub text_background_opacity: f32,
 
@@ -636,9 +809,25 @@ pub struct Config {
     #[dynamic(default = "linear_ease")]
     pub cursor_blink_ease_out: EasingFunction,
 
+    /// How long, in milliseconds, the text cursor takes to glide
+    /// from one cell to another when it moves.
+    /// Setting this to 0 (the default) makes it jump instantly.
+    #[dynamic(default)]
+    pub cursor_animation_duration_ms: u64,
+    #[dynamic(default)]
+    pub cursor_animation_ease: EasingFunction,
+
     #[dynamic(default = "default_anim_fps")]
     pub animation_fps: u8,
 
+    /// How many milliseconds of user inactivity are required before
+    /// we stop animating (cursor blinking, animated images and so on)
+    /// until the user is active again.
+    /// Setting this to 0 disables pausing.
+    /// This is currently only implemented on Wayland.
+    #[dynamic(default = "default_idle_animation_pause_delay_ms")]
+    pub idle_animation_pause_delay_ms: u64,
+
     #[dynamic(default)]
     pub text_min_contrast_ratio: Option<f32>,
 
@@ -658,6 +847,11 @@ pub struct Config {
     #[dynamic(default)]
     pub default_cursor_style: DefaultCursorStyle,
 
+    /// Overrides how the cursor is drawn, separately for normal
+    /// panes, copy mode and while composing with an input method
+    #[dynamic(default)]
+    pub cursor_styles: CursorStyles,
+
     /// Specifies how often blinking text (normal speed) transitions
     /// between visible and invisible, expressed in milliseconds.
     /// Setting this to 0 disables slow text blinking.  Note that this
@@ -702,6 +896,21 @@ pub struct Config {
     #[dynamic(default = "default_true")]
     pub scroll_to_bottom_on_input: bool,
 
+    /// How long, in milliseconds, scrolling the viewport with the
+    /// mouse wheel, touchpad or ScrollByLine/ScrollByPage takes to
+    /// glide to its new position.
+    /// Setting this to 0 (the default) makes it jump by whole rows.
+    #[dynamic(default)]
+    pub smooth_scroll_duration_ms: u64,
+    #[dynamic(default)]
+    pub smooth_scroll_ease: EasingFunction,
+
+    /// When the fingers are lifted from a touchpad while scrolling,
+    /// keep scrolling and gradually slow down, as GTK applications do.
+    /// Only supported on Wayland.
+    #[dynamic(default = "default_true")]
+    pub kinetic_scrolling: bool,
+
     #[dynamic(default = "default_true")]
     pub use_ime: bool,
     #[dynamic(default)]
@@ -712,6 +921,16 @@ pub struct Config {
     #[dynamic(default)]
     pub notification_handling: NotificationHandling,
 
+    /// Which of wezterm's own events are reported using toasts
+    /// that are shown inside the window
+    #[dynamic(default)]
+    pub inline_toast_events: Vec<InlineToastEvent>,
+
+    /// How long toasts that are shown inside the window remain
+    /// visible before they are dismissed automatically
+    #[dynamic(default = "default_inline_toast_duration_ms")]
+    pub inline_toast_duration_ms: u64,
+
     #[dynamic(default = "default_true")]
     pub use_dead_keys: bool,
 
@@ -756,6 +975,11 @@ pub struct Config {
     #[dynamic(default = "default_word_boundary")]
     pub selection_word_boundary: String,
 
+    /// Patterns that word selection treats as a single word,
+    /// regardless of selection_word_boundary
+    #[dynamic(default)]
+    pub selection_word_rules: Vec<SelectionWordRule>,
+
     #[dynamic(default = "default_enq_answerback")]
     pub enq_answerback: String,
 
@@ -813,6 +1037,9 @@ pub struct Config {
     #[dynamic(default)]
     pub pane_focus_follows_mouse: bool,
 
+    #[dynamic(default = "default_true")]
+    pub enable_drag_out: bool,
+
     #[dynamic(default = "default_true")]
     pub unzoom_on_switch_pane: bool,
 
@@ -829,6 +1056,11 @@ pub struct Config {
     pub line_to_ele_shape_cache_size: usize,
     #[dynamic(default = "default_glyph_cache_image_cache_size")]
     pub glyph_cache_image_cache_size: usize,
+    /// The maximum number of bytes used by the cache of shaped text
+    /// and rasterized glyphs that is kept on disk so that subsequent
+    /// startups don't need to redo that work.  0 disables the cache.
+    #[dynamic(default = "default_font_disk_cache_size")]
+    pub font_disk_cache_size: usize,
 
     #[dynamic(default)]
     pub visual_bell: VisualBell,
@@ -839,6 +1071,16 @@ pub struct Config {
     #[dynamic(default)]
     pub canonicalize_pasted_newlines: Option<NewlineCanon>,
 
+    /// Controls whether applications may read the clipboard
+    /// using OSC 52
+    #[dynamic(default)]
+    pub osc52_read_access: Osc52ReadAccess,
+
+    /// The maximum size, in bytes, of clipboard data that may be
+    /// written or read via OSC 52
+    #[dynamic(default = "default_osc52_max_bytes")]
+    pub osc52_max_bytes: usize,
+
     #[dynamic(default = "default_unicode_version")]
     pub unicode_version: u8,
 
@@ -875,6 +1117,29 @@ pub struct Config {
     #[dynamic(default)]
     pub quote_dropped_files: DroppedFileQuoting,
 
+    #[dynamic(default)]
+    pub drop_behavior: DropBehavior,
+
+    #[dynamic(default)]
+    pub copy_as_html: bool,
+
+    #[dynamic(default)]
+    pub paste_image_behavior: PasteImageBehavior,
+
+    /// Transformations that are applied to text pasted from the clipboard
+    #[dynamic(default)]
+    pub paste_transforms: PasteTransforms,
+
+    /// Controls when pasting into a shell prompt must be confirmed
+    #[dynamic(default)]
+    pub paste_confirmation: PasteConfirmation,
+
+    /// Pastes larger than this many bytes are sent to the pane in
+    /// chunks of this size, waiting for the application to consume
+    /// each chunk before sending the next
+    #[dynamic(default = "default_paste_chunk_size")]
+    pub paste_chunk_size: usize,
+
     #[dynamic(default)]
     pub ui_key_cap_rendering: UIKeyCapRendering,
 
@@ -893,6 +1158,10 @@ fn default_one() -> usize {
     1
 }
 
+fn default_paste_chunk_size() -> usize {
+    4096
+}
+
 fn default_ulimit_nofile() -> u64 {
     2048
 }
@@ -915,6 +1184,15 @@ impl Default for Config {
 }
 
 impl Config {
+    /// The effect applied to inactive panes, unless the pane
+    /// has its own effect
+    pub fn inactive_pane_effect(&self) -> PaneEffect {
+        PaneEffect {
+            hsb: self.inactive_pane_hsb,
+            blur: self.inactive_pane_blur,
+        }
+    }
+
     pub fn load() -> LoadedConfig {
         Self::load_with_overrides(&wezterm_dynamic::Value::default())
     }
@@ -1329,6 +1607,12 @@ impl Config {
                     cfg.window_background_image.replace(config_dir.join(path));
                 }
             }
+
+            if let Some(path) = &self.post_process_shader {
+                if !path.is_absolute() {
+                    cfg.post_process_shader.replace(config_dir.join(path));
+                }
+            }
         }
 
         // Add some reasonable default font rules
@@ -1657,6 +1941,10 @@ fn default_mux_output_parser_coalesce_delay_ms() -> u64 {
     3
 }
 
+fn default_synchronized_output_timeout_ms() -> u64 {
+    1000
+}
+
 fn default_mux_output_parser_buffer_size() -> usize {
     128 * 1024
 }
@@ -1701,6 +1989,10 @@ fn default_initial_rows() -> u16 {
     24
 }
 
+fn default_image_filter() -> ImageFilter {
+    ImageFilter::Nearest
+}
+
 fn default_initial_cols() -> u16 {
     80
 }
@@ -1802,6 +2094,10 @@ fn default_anim_fps() -> u8 {
     10
 }
 
+fn default_osc52_max_bytes() -> usize {
+    1024 * 1024
+}
+
 fn default_max_fps() -> u8 {
     60
 }
@@ -1839,6 +2135,18 @@ fn default_stateless_process_list() -> Vec<String> {
     .collect()
 }
 
+fn default_inline_toast_duration_ms() -> u64 {
+    4000
+}
+
+fn default_idle_animation_pause_delay_ms() -> u64 {
+    60_000
+}
+
+fn default_stylus_barrel_buttons() -> Vec<MousePress> {
+    vec![MousePress::Middle, MousePress::Right]
+}
+
 fn default_status_update_interval() -> u64 {
     1_000
 }
@@ -1915,6 +2223,43 @@ impl DefaultCursorStyle {
     }
 }
 
+/// Shapes that the cursor can be drawn as, in place of the
+/// shape that was requested by the application
+#[derive(FromDynamic, ToDynamic, Clone, Debug, PartialEq, Eq)]
+pub enum CursorStyleShape {
+    Block,
+    HollowBlock,
+    HollowRing,
+    Bar,
+    Underline,
+    /// Draws this text in the cursor color, on top of the cell
+    Glyph(String),
+}
+
+/// How the cursor is drawn in one of the states in `CursorStyles`
+#[derive(FromDynamic, ToDynamic, Clone, Debug, Default, PartialEq)]
+pub struct CursorStyle {
+    /// Overrides the shape; whether the cursor blinks still
+    /// follows the shape requested by the application
+    #[dynamic(default)]
+    pub shape: Option<CursorStyleShape>,
+    /// Overrides cursor_thickness for the bar, underline and
+    /// hollow shapes
+    #[dynamic(try_from = "crate::units::OptPixelUnit", default)]
+    pub thickness: Option<Dimension>,
+}
+
+#[derive(FromDynamic, ToDynamic, Clone, Debug, Default, PartialEq)]
+pub struct CursorStyles {
+    #[dynamic(default)]
+    pub normal: CursorStyle,
+    #[dynamic(default)]
+    pub copy_mode: CursorStyle,
+    /// Used while an input method or dead key is composing text
+    #[dynamic(default)]
+    pub ime_preedit: CursorStyle,
+}
+
 const fn linear_ease() -> EasingFunction {
     EasingFunction::Linear
 }
@@ -1927,6 +2272,10 @@ const fn default_half_cell() -> Dimension {
     Dimension::Cells(0.5)
 }
 
+const fn default_minimap_width() -> Dimension {
+    Dimension::Cells(12.)
+}
+
 const fn default_reverse_video_cursor_min_contrast() -> f32 {
     2.5
 }
@@ -2080,6 +2429,113 @@ impl DroppedFileQuoting {
     }
 }
 
+/// What to do when files or urls are dropped into a pane, unless
+/// a `file-dropped` event handler returns false
+#[derive(Debug, FromDynamic, ToDynamic, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DropBehavior {
+    /// Paste local file names quoted according to quote_dropped_files,
+    /// and other urls as-is
+    #[default]
+    PasteQuoted,
+    /// Paste the urls themselves
+    PasteUrls,
+    /// Do nothing; leave it to the file-dropped event
+    None,
+}
+
+/// What to do when pasting from a clipboard that holds an image
+/// rather than text
+#[derive(Debug, FromDynamic, ToDynamic, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PasteImageBehavior {
+    /// Ignore the paste
+    #[default]
+    None,
+    /// Save the image to a file in the temporary directory and paste
+    /// its quoted file name
+    SaveToFile,
+    /// Display the image inline in the pane
+    Display,
+}
+
+/// Transformations that are applied to text pasted from the clipboard
+#[derive(Debug, FromDynamic, ToDynamic, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PasteTransforms {
+    /// Remove the line breaks from the end of the text, so that
+    /// pasting a command doesn't immediately run it
+    #[dynamic(default)]
+    pub strip_trailing_newline: bool,
+    /// Replace each run of line breaks (CR, LF or CRLF) with a single
+    /// space, so that the text is pasted as a single line
+    #[dynamic(default)]
+    pub collapse_newlines: bool,
+    /// Remove the C0 control characters, other than tab, CR and LF
+    #[dynamic(default)]
+    pub filter_control_characters: bool,
+}
+
+impl PasteTransforms {
+    /// Applies the enabled transformations to `text`
+    pub fn apply(&self, text: &str) -> String {
+        let mut text = if self.filter_control_characters {
+            text.chars()
+                .filter(|&c| c >= ' ' || matches!(c, '\t' | '\r' | '\n'))
+                .collect()
+        } else {
+            text.to_string()
+        };
+
+        if self.strip_trailing_newline {
+            let len = text.trim_end_matches(|c| c == '\r' || c == '\n').len();
+            text.truncate(len);
+        }
+
+        if self.collapse_newlines {
+            let mut collapsed = String::with_capacity(text.len());
+            let mut in_break = false;
+            for c in text.chars() {
+                if c == '\r' || c == '\n' {
+                    if !in_break {
+                        collapsed.push(' ');
+                    }
+                    in_break = true;
+                } else {
+                    collapsed.push(c);
+                    in_break = false;
+                }
+            }
+            text = collapsed;
+        }
+
+        text
+    }
+}
+
+/// Controls when pasting into a shell prompt must be confirmed
+#[derive(Debug, FromDynamic, ToDynamic, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PasteConfirmation {
+    /// Confirm pastes that hold more than one line of text
+    #[dynamic(default)]
+    pub multi_line: bool,
+    /// Confirm pastes that are larger than this many bytes;
+    /// 0 disables this check
+    #[dynamic(default)]
+    pub size_threshold: usize,
+}
+
+impl PasteConfirmation {
+    /// Returns true if pasting `text` must be confirmed.
+    /// A single trailing line break doesn't make the text multi-line.
+    pub fn is_required_for(&self, text: &str) -> bool {
+        let body = text
+            .strip_suffix("\r\n")
+            .or_else(|| text.strip_suffix('\n'))
+            .or_else(|| text.strip_suffix('\r'))
+            .unwrap_or(text);
+        (self.multi_line && body.contains(|c| c == '\r' || c == '\n'))
+            || (self.size_threshold > 0 && text.len() > self.size_threshold)
+    }
+}
+
 fn default_glyph_cache_image_cache_size() -> usize {
     256
 }
@@ -2088,6 +2544,10 @@ fn default_shape_cache_size() -> usize {
     1024
 }
 
+fn default_font_disk_cache_size() -> usize {
+    32 * 1024 * 1024
+}
+
 fn default_line_state_cache_size() -> usize {
     1024
 }
@@ -2134,6 +2594,17 @@ impl FromDynamic for BoldBrightening {
     }
 }
 
+#[derive(Debug, FromDynamic, ToDynamic, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TabBarProgressStyle {
+    /// A partially filled circle
+    #[default]
+    Glyph,
+    /// A small horizontal progress bar
+    Bar,
+    /// Don't show progress in the tab bar
+    None,
+}
+
 #[derive(Debug, FromDynamic, ToDynamic, Clone, Copy, PartialEq, Eq, Default)]
 pub enum ImePreeditRendering {
     /// IME preedit is rendered by WezTerm itself
@@ -2153,6 +2624,67 @@ pub enum NotificationHandling {
     SuppressFromFocusedWindow,
 }
 
+#[derive(Debug, FromDynamic, ToDynamic, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Osc52ReadAccess {
+    /// Ignore read requests, replying with an empty clipboard
+    Deny,
+    /// Ask the user each time, unless they chose to always allow
+    /// reads from that pane
+    #[default]
+    Prompt,
+    /// Always allow reads
+    Allow,
+}
+
+#[derive(Debug, FromDynamic, ToDynamic, Clone, PartialEq, Eq, Default)]
+pub struct DiskScrollback {
+    /// The maximum number of lines of scrollback to retain,
+    /// including those in memory.  Unlimited if not set.
+    #[dynamic(default)]
+    pub max_lines: Option<usize>,
+    /// Where the spill files are created
+    #[dynamic(default)]
+    pub directory: Option<PathBuf>,
+}
+
+impl DiskScrollback {
+    pub fn directory(&self) -> PathBuf {
+        self.directory
+            .clone()
+            .unwrap_or_else(|| CACHE_DIR.join("scrollback"))
+    }
+}
+
+#[derive(Debug, FromDynamic, ToDynamic, Clone, PartialEq, Eq)]
+pub struct SelectionWordRule {
+    /// A regex that is matched against the logical line; a match
+    /// that contains the clicked cell is selected as a word
+    pub regex: String,
+}
+
+/// Overrides the word selection configuration for a pane
+#[derive(Debug, FromDynamic, ToDynamic, Clone, PartialEq, Eq, Default)]
+pub struct SelectionWordConfig {
+    /// Used in place of selection_word_boundary, if set
+    #[dynamic(default)]
+    pub word_boundary: Option<String>,
+    /// Used in place of selection_word_rules, if set
+    #[dynamic(default)]
+    pub rules: Option<Vec<SelectionWordRule>>,
+}
+
+/// The events that can be reported by toasts shown inside the window
+#[derive(Debug, FromDynamic, ToDynamic, Clone, Copy, PartialEq, Eq)]
+pub enum InlineToastEvent {
+    /// An error while reloading the configuration, instead of
+    /// showing it in the configuration error window
+    ConfigError,
+    /// Text was copied to the clipboard by the CopyTo action
+    Copy,
+    /// The bell rang in a tab other than the active tab
+    BackgroundBell,
+}
+
 fn validate_row_or_col(value: &u16) -> Result<(), String> {
     if *value < 1 {
         Err("initial_cols and initial_rows must be non-zero".to_string())
@@ -2161,6 +2693,16 @@ fn validate_row_or_col(value: &u16) -> Result<(), String> {
     }
 }
 
+fn validate_text_gamma(value: &f32) -> Result<(), String> {
+    if *value <= 0.0 {
+        Err(format!(
+            "Illegal value {value} for text_gamma; it must be positive and greater than zero!"
+        ))
+    } else {
+        Ok(())
+    }
+}
+
 fn validate_line_height(value: &f64) -> Result<(), String> {
     if *value <= 0.0 {
         Err(format!(
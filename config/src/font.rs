@@ -226,6 +226,7 @@ pub enum DisplayPixelGeometry {
 }
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, FromDynamic, ToDynamic)]
+#[dynamic(try_from = "String", into = "String")]
 pub enum FreeTypeLoadTarget {
     /// This corresponds to the default hinting algorithm, optimized
     /// for standard gray-level rendering.
@@ -246,6 +247,43 @@ pub enum FreeTypeLoadTarget {
     VerticalLcd,
 }
 
+impl FreeTypeLoadTarget {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Normal => "Normal",
+            Self::Light => "Light",
+            Self::Mono => "Mono",
+            Self::HorizontalLcd => "HorizontalLcd",
+            Self::VerticalLcd => "VerticalLcd",
+        }
+    }
+}
+
+impl TryFrom<String> for FreeTypeLoadTarget {
+    type Error = String;
+    fn try_from(s: String) -> Result<Self, String> {
+        // "lcd" is accepted as a shorthand for the common case
+        // of a horizontally decimated display
+        match s.to_lowercase().as_str() {
+            "normal" => Ok(Self::Normal),
+            "light" => Ok(Self::Light),
+            "mono" => Ok(Self::Mono),
+            "horizontallcd" | "lcd" => Ok(Self::HorizontalLcd),
+            "verticallcd" => Ok(Self::VerticalLcd),
+            _ => Err(format!(
+                "invalid FreeTypeLoadTarget {s}, possible values are {:?}",
+                Self::variants()
+            )),
+        }
+    }
+}
+
+impl From<&FreeTypeLoadTarget> for String {
+    fn from(target: &FreeTypeLoadTarget) -> String {
+        target.as_str().to_string()
+    }
+}
+
 bitflags! {
     // Note that these are strongly coupled with deps/freetype/src/lib.rs,
     // but we can't directly reference that from here without making config
@@ -471,6 +509,22 @@ impl Default for TextStyle {
 }
 
 impl TextStyle {
+    /// Make a version of this style in which each font is shaped with
+    /// `features` following its own harfbuzz_features, or following
+    /// `default_features` if it doesn't specify any
+    pub fn with_harfbuzz_features(&self, features: &[&str], default_features: &[String]) -> Self {
+        let mut style = self.clone();
+        for attr in &mut style.font {
+            let mut combined = attr
+                .harfbuzz_features
+                .clone()
+                .unwrap_or_else(|| default_features.to_vec());
+            combined.extend(features.iter().map(|f| f.to_string()));
+            attr.harfbuzz_features.replace(combined);
+        }
+        style
+    }
+
     /// Make a version of this style where the first entry
     /// has any explicitly named bold/italic components
     /// removed.  The intent is to set it up for make_bold
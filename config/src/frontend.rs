@@ -1,5 +1,5 @@
 use luahelper::impl_lua_conversion_dynamic;
-use wezterm_dynamic::{FromDynamic, ToDynamic};
+use wezterm_dynamic::{FromDynamic, FromDynamicOptions, ToDynamic, Value};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, FromDynamic, ToDynamic, Default)]
 pub enum FrontEndSelection {
@@ -10,7 +10,7 @@ pub enum FrontEndSelection {
 }
 
 /// Corresponds to <https://docs.rs/wgpu/latest/wgpu/struct.AdapterInfo.html>
-#[derive(Debug, Clone, FromDynamic, ToDynamic)]
+#[derive(Debug, Clone, PartialEq, FromDynamic, ToDynamic)]
 pub struct GpuInfo {
     pub name: String,
     pub device_type: String,
@@ -44,6 +44,184 @@ impl ToString for GpuInfo {
     }
 }
 
+/// An entry in the webgpu_preferred_adapter list
+#[derive(Debug, Clone, PartialEq)]
+pub enum GpuPreference {
+    /// A specific adapter, as returned by wezterm.gui.enumerate_gpus()
+    Adapter(GpuInfo),
+    /// Any adapter of the given kind, written as eg: "discrete-vulkan".
+    /// None matches any device type or backend.
+    Class {
+        device_type: Option<String>,
+        backend: Option<String>,
+    },
+}
+
+impl GpuPreference {
+    /// Parses a class such as "discrete-vulkan", "integrated" or "gl"
+    pub fn parse_class(s: &str) -> Result<Self, String> {
+        let mut device_type = None;
+        let mut backend = None;
+        for word in s.split(|c: char| c == '-' || c.is_whitespace()) {
+            if word.is_empty() {
+                continue;
+            }
+            let (slot, value) = match word.to_ascii_lowercase().as_str() {
+                "discrete" | "discretegpu" => (&mut device_type, "DiscreteGpu"),
+                "integrated" | "integratedgpu" => (&mut device_type, "IntegratedGpu"),
+                "virtual" | "virtualgpu" => (&mut device_type, "VirtualGpu"),
+                "cpu" => (&mut device_type, "Cpu"),
+                "other" => (&mut device_type, "Other"),
+                "vulkan" => (&mut backend, "Vulkan"),
+                "metal" => (&mut backend, "Metal"),
+                "dx12" => (&mut backend, "Dx12"),
+                "gl" | "opengl" => (&mut backend, "Gl"),
+                "any" => continue,
+                _ => {
+                    return Err(format!(
+                        "unknown gpu class `{word}` in `{s}`; expected a device type \
+                         (discrete, integrated, virtual, cpu, other), \
+                         a backend (vulkan, metal, dx12, gl) or `any`"
+                    ))
+                }
+            };
+            if slot.is_some() {
+                return Err(format!("`{s}` specifies `{word}` more than once"));
+            }
+            slot.replace(value.to_string());
+        }
+        Ok(Self::Class {
+            device_type,
+            backend,
+        })
+    }
+
+    pub fn matches(&self, info: &GpuInfo) -> bool {
+        match self {
+            Self::Adapter(pref) => {
+                pref.name == info.name
+                    && pref.device_type == info.device_type
+                    && pref.backend == info.backend
+                    && (pref.driver.is_none() || pref.driver == info.driver)
+                    && (pref.vendor.is_none() || pref.vendor == info.vendor)
+                    && (pref.device.is_none() || pref.device == info.device)
+            }
+            Self::Class {
+                device_type,
+                backend,
+            } => {
+                device_type
+                    .as_ref()
+                    .map(|t| *t == info.device_type)
+                    .unwrap_or(true)
+                    && backend.as_ref().map(|b| *b == info.backend).unwrap_or(true)
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for GpuPreference {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Adapter(info) => write!(fmt, "{}", info.to_string()),
+            Self::Class {
+                device_type: None,
+                backend: None,
+            } => write!(fmt, "any"),
+            Self::Class {
+                device_type,
+                backend,
+            } => {
+                let device_type = device_type
+                    .as_ref()
+                    .map(|t| t.trim_end_matches("Gpu").to_ascii_lowercase());
+                let backend = backend.as_ref().map(|b| b.to_ascii_lowercase());
+                let words: Vec<String> = device_type.into_iter().chain(backend).collect();
+                write!(fmt, "{}", words.join("-"))
+            }
+        }
+    }
+}
+
+/// The ordered list of adapters in webgpu_preferred_adapter.
+/// It can be specified as a single adapter, a string holding a comma
+/// separated list of classes, or an array of adapters and classes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GpuPreferenceList(pub Vec<GpuPreference>);
+
+impl GpuPreferenceList {
+    /// Returns the index of the first entry that matches `info`
+    pub fn position(&self, info: &GpuInfo) -> Option<usize> {
+        self.0.iter().position(|pref| pref.matches(info))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &GpuPreference> {
+        self.0.iter()
+    }
+
+    fn push_value(
+        &mut self,
+        value: &Value,
+        options: FromDynamicOptions,
+    ) -> Result<(), wezterm_dynamic::Error> {
+        match value {
+            Value::String(s) => {
+                for class in s.split(',') {
+                    let class = class.trim();
+                    if class.is_empty() {
+                        continue;
+                    }
+                    self.0.push(
+                        GpuPreference::parse_class(class)
+                            .map_err(wezterm_dynamic::Error::Message)?,
+                    );
+                }
+            }
+            _ => {
+                let info = GpuInfo::from_dynamic(value, options)?;
+                self.0.push(GpuPreference::Adapter(info));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromDynamic for GpuPreferenceList {
+    fn from_dynamic(
+        value: &Value,
+        options: FromDynamicOptions,
+    ) -> Result<Self, wezterm_dynamic::Error> {
+        let mut list = Self::default();
+        match value {
+            Value::Null => {}
+            Value::Array(items) => {
+                for item in items.iter() {
+                    list.push_value(item, options)?;
+                }
+            }
+            _ => list.push_value(value, options)?,
+        }
+        Ok(list)
+    }
+}
+
+impl ToDynamic for GpuPreferenceList {
+    fn to_dynamic(&self) -> Value {
+        self.0
+            .iter()
+            .map(|pref| match pref {
+                GpuPreference::Adapter(info) => info.to_dynamic(),
+                GpuPreference::Class { .. } => pref.to_string().to_dynamic(),
+            })
+            .collect::<Vec<_>>()
+            .to_dynamic()
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, FromDynamic, ToDynamic)]
 pub enum WebGpuPowerPreference {
     LowPower,
@@ -55,3 +233,16 @@ impl Default for WebGpuPowerPreference {
         Self::LowPower
     }
 }
+
+/// Whether to render in HDR, when the display supports it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromDynamic, ToDynamic, Default)]
+pub enum HdrMode {
+    /// Always render in SDR
+    #[default]
+    Off,
+    /// Render in HDR while the window is on an HDR display
+    Auto,
+    /// Render in HDR whenever the compositor can accept HDR content,
+    /// even if the display is SDR
+    On,
+}
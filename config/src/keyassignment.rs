@@ -1,3 +1,4 @@
+use crate::color::ColorSpec;
 use crate::default_true;
 use crate::keys::KeyNoAction;
 use crate::window::WindowLevel;
@@ -361,6 +362,24 @@ pub struct PaneSelectArguments {
     pub show_pane_ids: bool,
 }
 
+/// Which tabs are shown by the tab overview
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, FromDynamic, ToDynamic)]
+pub enum TabOverviewScope {
+    /// The tabs in the current window
+    #[default]
+    Window,
+    /// The tabs in all of the windows in the active workspace
+    Workspace,
+    /// The tabs in all windows, in all workspaces
+    AllWorkspaces,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, FromDynamic, ToDynamic)]
+pub struct TabOverviewArguments {
+    #[dynamic(default)]
+    pub scope: TabOverviewScope,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, FromDynamic, ToDynamic)]
 pub enum CharSelectGroup {
     RecentlyUsed,
@@ -439,6 +458,82 @@ impl Default for CharSelectArguments {
     }
 }
 
+/// What QuickSelect does with the text of the match that is selected
+#[derive(Debug, Clone, PartialEq, FromDynamic, ToDynamic)]
+pub enum QuickSelectAction {
+    /// Copy the text to the clipboard and primary selection
+    Copy,
+    /// Send the text to the pane as though it were pasted
+    Paste,
+    /// Open the text as a URL with the default handler
+    Open,
+    /// Select the text and then perform the key assignment,
+    /// such as one created by `wezterm.action_callback`
+    Action(Box<KeyAssignment>),
+}
+
+/// A QuickSelect pattern.  It may be specified as just the regex,
+/// or as a table that also specifies what to do with its matches
+/// and how to color their labels.
+#[derive(Default, Debug, Clone, PartialEq, ToDynamic)]
+pub struct QuickSelectPattern {
+    pub pattern: String,
+    /// Overrides the action of the QuickSelect for matches of this pattern
+    pub action: Option<QuickSelectAction>,
+    /// Override quick_select_label_fg and quick_select_label_bg
+    /// for matches of this pattern
+    pub label_fg: Option<ColorSpec>,
+    pub label_bg: Option<ColorSpec>,
+}
+
+impl QuickSelectPattern {
+    /// Returns true if this pattern overrides any of the defaults
+    /// for its matches, and so must be searched for on its own
+    pub fn has_overrides(&self) -> bool {
+        self.action.is_some() || self.label_fg.is_some() || self.label_bg.is_some()
+    }
+}
+
+impl From<&str> for QuickSelectPattern {
+    fn from(pattern: &str) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            ..Self::default()
+        }
+    }
+}
+
+#[derive(FromDynamic)]
+struct QuickSelectPatternTable {
+    pattern: String,
+    #[dynamic(default)]
+    action: Option<QuickSelectAction>,
+    #[dynamic(default)]
+    label_fg: Option<ColorSpec>,
+    #[dynamic(default)]
+    label_bg: Option<ColorSpec>,
+}
+
+impl FromDynamic for QuickSelectPattern {
+    fn from_dynamic(
+        value: &Value,
+        options: FromDynamicOptions,
+    ) -> Result<Self, wezterm_dynamic::Error> {
+        match value {
+            Value::String(pattern) => Ok(Self::from(pattern.as_str())),
+            _ => {
+                let table = QuickSelectPatternTable::from_dynamic(value, options)?;
+                Ok(Self {
+                    pattern: table.pattern,
+                    action: table.action,
+                    label_fg: table.label_fg,
+                    label_bg: table.label_bg,
+                })
+            }
+        }
+    }
+}
+
 #[derive(Default, Debug, Clone, PartialEq, FromDynamic, ToDynamic)]
 pub struct QuickSelectArguments {
     /// Overrides the main quick_select_alphabet config
@@ -446,7 +541,7 @@ pub struct QuickSelectArguments {
     pub alphabet: String,
     /// Overrides the main quick_select_patterns config
     #[dynamic(default)]
-    pub patterns: Vec<String>,
+    pub patterns: Vec<QuickSelectPattern>,
     #[dynamic(default)]
     pub action: Option<Box<KeyAssignment>>,
     /// Skip triggering `action` after paste is performed (capital selection)
@@ -531,20 +626,38 @@ fn default_message() -> String {
     "🛑 Really continue?".to_string()
 }
 
+/// Identifies one of the screens reported by wezterm.gui.screens()
+#[derive(Debug, Clone, PartialEq, Eq, FromDynamic, ToDynamic)]
+pub enum ScreenSelector {
+    /// The name of the screen, such as "DP-3"
+    Name(String),
+    /// The position of the screen when ordered from left to right,
+    /// starting from 0
+    Index(usize),
+}
+impl_lua_conversion_dynamic!(ScreenSelector);
+
 #[derive(Debug, Clone, PartialEq, FromDynamic, ToDynamic)]
 pub enum KeyAssignment {
     SpawnTab(SpawnTabDomain),
     SpawnWindow,
     ToggleFullScreen,
+    ToggleFullScreenOnScreen(ScreenSelector),
     ToggleAlwaysOnTop,
     ToggleAlwaysOnBottom,
     SetWindowLevel(WindowLevel),
+    ToggleShortcutInhibit,
+    TogglePresentationMode,
+    CaptureWindow,
+    CapturePane,
+    CapturePaneToClipboard,
     CopyTo(ClipboardCopyDestination),
     CopyTextTo {
         text: String,
         destination: ClipboardCopyDestination,
     },
     PasteFrom(ClipboardPasteSource),
+    CancelPaste,
     ActivateTabRelative(isize),
     ActivateTabRelativeNoWrap(isize),
     IncreaseFontSize,
@@ -569,10 +682,15 @@ pub enum KeyAssignment {
     ScrollByLine(isize),
     ScrollByCurrentEventWheelDelta,
     ScrollToPrompt(isize),
+    SelectLastCommandOutput,
+    CopyLastCommandOutput(ClipboardCopyDestination),
+    ShowLastCommandOutput,
     ScrollToTop,
     ScrollToBottom,
     ShowTabNavigator,
     ShowDebugOverlay,
+    ToggleDebugOverlay,
+    ToggleMinimap,
     HideApplication,
     QuitApplication,
     SpawnCommandInNewTab(SpawnCommand),
@@ -636,6 +754,7 @@ pub enum KeyAssignment {
     SplitPane(SplitPane),
     PaneSelect(PaneSelectArguments),
     CharSelect(CharSelectArguments),
+    ShowTabOverview(TabOverviewArguments),
 
     ResetTerminal,
     OpenUri(String),
@@ -709,6 +828,9 @@ pub enum CopyModeAssignment {
     PriorMatchPage,
     NextMatchPage,
     CycleMatchType,
+    ToggleSmartCase,
+    ToggleWholeWord,
+    ToggleSearchOutputZone,
     ClearPattern,
     EditPattern,
     AcceptPattern,
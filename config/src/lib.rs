@@ -632,6 +632,12 @@ impl ConfigInner {
             }
         }
 
+        // Reload when the post processing shader is edited, so
+        // that the effect of changes to it can be seen right away
+        if let Some(path) = &self.config.post_process_shader {
+            watch_paths.push(path.clone());
+        }
+
         self.notify();
         if self.config.automatically_reload_config {
             for path in watch_paths {
@@ -798,6 +804,22 @@ impl ConfigHandle {
         }
     }
 
+    /// Returns a copy of the configuration in which the fonts are
+    /// shaped with their ligatures turned off
+    pub fn with_ligatures_disabled(&self) -> Self {
+        const NO_LIGATURES: &[&str] = &["calt=0", "clig=0", "liga=0"];
+        let mut config = (*self.config).clone();
+        let defaults = config.harfbuzz_features.clone();
+        config.font = config.font.with_harfbuzz_features(NO_LIGATURES, &defaults);
+        for rule in &mut config.font_rules {
+            rule.font = rule.font.with_harfbuzz_features(NO_LIGATURES, &defaults);
+        }
+        Self {
+            config: Arc::new(config),
+            generation: self.generation,
+        }
+    }
+
     pub fn unicode_version(&self) -> UnicodeVersion {
         UnicodeVersion {
             version: self.config.unicode_version,
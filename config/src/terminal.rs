@@ -35,6 +35,15 @@ impl TermConfig {
         self.client_palette.lock().unwrap().replace(palette);
     }
 
+    /// Returns a copy of this configuration that uses `palette`
+    /// in place of the configured palette
+    pub fn with_client_palette(&self, palette: ColorPalette) -> Self {
+        Self {
+            config: Mutex::new(self.config.lock().unwrap().clone()),
+            client_palette: Mutex::new(Some(palette)),
+        }
+    }
+
     fn configuration(&self) -> ConfigHandle {
         match self.config.lock().unwrap().as_ref() {
             Some(h) => h.clone(),
@@ -52,6 +61,14 @@ impl wezterm_term::TerminalConfiguration for TermConfig {
         self.configuration().scrollback_lines
     }
 
+    fn compress_scrollback(&self) -> bool {
+        self.configuration().compress_scrollback
+    }
+
+    fn alternate_screen_capture_lines(&self) -> usize {
+        self.configuration().alternate_screen_capture_lines
+    }
+
     fn enable_csi_u_key_encoding(&self) -> bool {
         self.configuration().enable_csi_u_key_encoding
     }
@@ -113,10 +130,18 @@ impl wezterm_term::TerminalConfiguration for TermConfig {
         self.configuration().log_unknown_escape_sequences
     }
 
+    fn osc52_max_bytes(&self) -> usize {
+        self.configuration().osc52_max_bytes
+    }
+
     fn normalize_output_to_unicode_nfc(&self) -> bool {
         self.configuration().normalize_output_to_unicode_nfc
     }
 
+    fn grapheme_clustering(&self) -> bool {
+        self.configuration().grapheme_clustering
+    }
+
     fn bidi_mode(&self) -> BidiMode {
         let config = self.configuration();
         BidiMode {
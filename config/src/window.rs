@@ -7,3 +7,101 @@ pub enum WindowLevel {
     Normal = 0,
     AlwaysOnTop = 3,
 }
+
+/// The z-order band into which a wlr-layer-shell surface is placed
+#[derive(Debug, Default, Clone, Copy, ToDynamic, PartialEq, Eq, FromDynamic)]
+pub enum WaylandLayer {
+    Background,
+    Bottom,
+    #[default]
+    Top,
+    Overlay,
+}
+
+/// An edge of the output to which a layer surface is anchored
+#[derive(Debug, Clone, Copy, ToDynamic, PartialEq, Eq, FromDynamic)]
+pub enum WaylandLayerAnchor {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+#[derive(Debug, Default, Clone, Copy, ToDynamic, PartialEq, Eq, FromDynamic)]
+pub enum WaylandKeyboardInteractivity {
+    None,
+    Exclusive,
+    #[default]
+    OnDemand,
+}
+
+/// Whether frames are presented in sync with the display refresh,
+/// or as soon as they are ready, even if that causes tearing
+#[derive(Debug, Default, Clone, Copy, ToDynamic, PartialEq, Eq, FromDynamic)]
+pub enum PresentationMode {
+    #[default]
+    Vsync,
+    Async,
+    /// Async while the window is fullscreen, Vsync otherwise
+    Auto,
+}
+
+/// How wezterm asks the compositor to time the presentation of
+/// its frames, when the compositor supports wp_commit_timing_v1
+#[derive(Debug, Default, Clone, Copy, ToDynamic, PartialEq, Eq, FromDynamic)]
+pub enum WaylandFrameTiming {
+    /// Target each frame at the next refresh of the display,
+    /// as predicted from presentation feedback
+    #[default]
+    NextRefresh,
+    /// Don't constrain presentation time, so that each frame
+    /// is shown as soon as possible; best for VRR displays
+    EarliestPresent,
+}
+
+/// When set, wezterm creates its windows as zwlr_layer_shell_v1
+/// surfaces rather than as regular xdg toplevels, which allows
+/// for quake-style dropdown terminals on wlroots compositors.
+#[derive(Debug, Clone, ToDynamic, PartialEq, Eq, FromDynamic)]
+pub struct WaylandLayerShell {
+    #[dynamic(default)]
+    pub layer: WaylandLayer,
+    #[dynamic(default = "default_layer_anchor")]
+    pub anchor: Vec<WaylandLayerAnchor>,
+    #[dynamic(default)]
+    pub keyboard_interactivity: WaylandKeyboardInteractivity,
+    /// Margins in surface coordinates, in top, right, bottom, left order
+    #[dynamic(default)]
+    pub margin: [i32; 4],
+    #[dynamic(default)]
+    pub exclusive_zone: i32,
+    /// The namespace reported to the compositor, which may use it
+    /// to apply rules to the surface
+    #[dynamic(default = "default_layer_namespace")]
+    pub namespace: String,
+}
+
+impl Default for WaylandLayerShell {
+    fn default() -> Self {
+        Self {
+            layer: WaylandLayer::default(),
+            anchor: default_layer_anchor(),
+            keyboard_interactivity: WaylandKeyboardInteractivity::default(),
+            margin: [0; 4],
+            exclusive_zone: 0,
+            namespace: default_layer_namespace(),
+        }
+    }
+}
+
+fn default_layer_anchor() -> Vec<WaylandLayerAnchor> {
+    vec![
+        WaylandLayerAnchor::Top,
+        WaylandLayerAnchor::Left,
+        WaylandLayerAnchor::Right,
+    ]
+}
+
+fn default_layer_namespace() -> String {
+    "wezterm".to_string()
+}
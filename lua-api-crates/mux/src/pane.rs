@@ -1,4 +1,5 @@
 use super::*;
+use config::{configuration, Palette, SelectionWordConfig};
 use luahelper::mlua::LuaSerdeExt;
 use luahelper::{dynamic_to_lua_value, from_lua, to_lua};
 use mlua::Value;
@@ -6,9 +7,10 @@ use mux::pane::CachePolicy;
 use std::cmp::Ordering;
 use std::sync::Arc;
 use termwiz::cell::SemanticType;
+use termwiz::hyperlink::Rule;
 use termwiz_funcs::lines_to_escapes;
 use url_funcs::Url;
-use wezterm_term::{SemanticZone, StableRowIndex};
+use wezterm_term::{PaneBackground, PaneBidiMode, SemanticZone, StableRowIndex};
 
 #[derive(Clone, Copy, Debug)]
 pub struct MuxPane(pub PaneId);
@@ -320,6 +322,12 @@ impl UserData for MuxPane {
             Ok(zones)
         });
 
+        methods.add_method("get_command_history", |lua, this, _: ()| {
+            let mux = get_mux()?;
+            let pane = this.resolve(&mux)?;
+            to_lua(lua, pane.get_command_history())
+        });
+
         methods.add_method(
             "get_semantic_zone_at",
             |lua, this, (x, y): (usize, StableRowIndex)| {
@@ -433,6 +441,129 @@ impl UserData for MuxPane {
             let pane = this.resolve(&mux)?;
             Ok(pane.tty_name())
         });
+
+        methods.add_async_method("capture_image", |lua, this, _: ()| async move {
+            let png = Mux::capture_image(this.0, false)
+                .await
+                .map_err(|e| mlua::Error::external(format!("{:#}", e)))?;
+            lua.create_string(&png)
+        });
+
+        methods.add_method("get_background", |lua, this, _: ()| {
+            let mux = get_mux()?;
+            let pane = this.resolve(&mux)?;
+            to_lua(lua, pane.get_pane_background())
+        });
+
+        methods.add_method("set_background", |_lua, this, background: Value| {
+            let background: PaneBackground = from_lua(background)?;
+            if let Some(opacity) = background.opacity {
+                if !(0.0..=1.0).contains(&opacity) {
+                    return Err(mlua::Error::external(format!(
+                        "opacity {opacity} must be in the range 0.0-1.0"
+                    )));
+                }
+            }
+            let mux = get_mux()?;
+            let pane = this.resolve(&mux)?;
+            pane.set_pane_background(background);
+            Ok(())
+        });
+
+        methods.add_method("get_synchronized_output_stats", |lua, this, _: ()| {
+            let mux = get_mux()?;
+            let pane = this.resolve(&mux)?;
+            to_lua(lua, pane.get_synchronized_output_stats())
+        });
+
+        methods.add_method("set_palette", |_lua, this, colors: Value| {
+            let colors: Option<Palette> = from_lua(colors)?;
+            let palette = colors.map(|colors| {
+                configuration()
+                    .resolved_palette
+                    .overlay_with(&colors)
+                    .into()
+            });
+            let mux = get_mux()?;
+            let pane = this.resolve(&mux)?;
+            pane.set_pane_palette(palette);
+            Ok(())
+        });
+
+        methods.add_method("get_hyperlink_rules", |lua, this, _: ()| {
+            let mux = get_mux()?;
+            let pane = this.resolve(&mux)?;
+            to_lua(lua, pane.get_hyperlink_rules().map(|rules| rules.to_vec()))
+        });
+
+        methods.add_method("set_hyperlink_rules", |_lua, this, rules: Value| {
+            let rules: Option<Vec<Rule>> = from_lua(rules)?;
+            let mux = get_mux()?;
+            let pane = this.resolve(&mux)?;
+            pane.set_hyperlink_rules(rules);
+            Ok(())
+        });
+
+        methods.add_method("get_bidi_mode", |lua, this, _: ()| {
+            let mux = get_mux()?;
+            let pane = this.resolve(&mux)?;
+            to_lua(lua, pane.get_pane_bidi_mode())
+        });
+
+        methods.add_method("set_bidi_mode", |_lua, this, mode: Value| {
+            let mode: PaneBidiMode = from_lua(mode)?;
+            let mux = get_mux()?;
+            let pane = this.resolve(&mux)?;
+            pane.set_pane_bidi_mode(mode);
+            Ok(())
+        });
+
+        methods.add_method("get_selection_word_config", |lua, this, _: ()| {
+            let mux = get_mux()?;
+            let pane = this.resolve(&mux)?;
+            to_lua(lua, pane.get_selection_word_config())
+        });
+
+        methods.add_method("set_selection_word_config", |_lua, this, config: Value| {
+            let config: Option<SelectionWordConfig> = from_lua(config)?;
+            let mux = get_mux()?;
+            let pane = this.resolve(&mux)?;
+            pane.set_selection_word_config(config);
+            Ok(())
+        });
+
+        methods.add_method("get_alternate_screen_capture_lines", |_, this, _: ()| {
+            let mux = get_mux()?;
+            let pane = this.resolve(&mux)?;
+            Ok(pane.get_alternate_screen_capture_lines())
+        });
+
+        methods.add_method(
+            "set_alternate_screen_capture_lines",
+            |_, this, lines: Option<usize>| {
+                let mux = get_mux()?;
+                let pane = this.resolve(&mux)?;
+                pane.set_alternate_screen_capture_lines(lines);
+                Ok(())
+            },
+        );
+
+        methods.add_method("get_alternate_screen_capture_as_text", |_, this, _: ()| {
+            let mux = get_mux()?;
+            let pane = this.resolve(&mux)?;
+            let mut text = String::new();
+            for line in pane.get_alternate_screen_capture() {
+                for cell in line.visible_cells() {
+                    text.push_str(cell.str());
+                }
+                let trimmed = text.trim_end().len();
+                text.truncate(trimmed);
+                text.push('\n');
+            }
+            let trimmed = text.trim_end().len();
+            text.truncate(trimmed);
+            Ok(text)
+        });
     }
 }
 
@@ -22,6 +22,15 @@ pub struct ScreenInfo {
     pub scale: f64,
     pub max_fps: Option<usize>,
     pub effective_dpi: Option<f64>,
+    pub description: Option<String>,
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub physical_width_mm: Option<isize>,
+    pub physical_height_mm: Option<isize>,
+    pub logical_x: Option<isize>,
+    pub logical_y: Option<isize>,
+    pub logical_width: Option<isize>,
+    pub logical_height: Option<isize>,
 }
 impl_lua_conversion_dynamic!(ScreenInfo);
 
@@ -48,6 +57,15 @@ impl From<window::screen::ScreenInfo> for ScreenInfo {
             scale: info.scale,
             max_fps: info.max_fps,
             effective_dpi: info.effective_dpi,
+            description: info.description,
+            make: info.make,
+            model: info.model,
+            physical_width_mm: info.physical_size_mm.map(|(w, _)| w),
+            physical_height_mm: info.physical_size_mm.map(|(_, h)| h),
+            logical_x: info.logical_rect.map(|r| r.min_x()),
+            logical_y: info.logical_rect.map(|r| r.min_y()),
+            logical_width: info.logical_rect.map(|r| r.width()),
+            logical_height: info.logical_rect.map(|r| r.height()),
         }
     }
 }
@@ -74,6 +92,86 @@ impl From<window::screen::Screens> for Screens {
     }
 }
 
+#[derive(Debug, Clone, FromDynamic, ToDynamic)]
+pub struct ToplevelInfo {
+    pub identifier: Option<String>,
+    pub app_id: String,
+    pub title: String,
+    pub focused: bool,
+}
+impl_lua_conversion_dynamic!(ToplevelInfo);
+
+impl From<window::toplevel::ToplevelInfo> for ToplevelInfo {
+    fn from(info: window::toplevel::ToplevelInfo) -> Self {
+        Self {
+            identifier: info.identifier,
+            app_id: info.app_id,
+            title: info.title,
+            focused: info.focused,
+        }
+    }
+}
+
+#[derive(Debug, Clone, FromDynamic, ToDynamic)]
+pub struct OutputModeInfo {
+    pub width: i32,
+    pub height: i32,
+    pub refresh: f64,
+    pub preferred: bool,
+    pub current: bool,
+}
+impl_lua_conversion_dynamic!(OutputModeInfo);
+
+impl From<window::output::OutputModeInfo> for OutputModeInfo {
+    fn from(info: window::output::OutputModeInfo) -> Self {
+        Self {
+            width: info.width,
+            height: info.height,
+            refresh: info.refresh,
+            preferred: info.preferred,
+            current: info.current,
+        }
+    }
+}
+
+#[derive(Debug, Clone, FromDynamic, ToDynamic)]
+pub struct OutputInfo {
+    pub name: String,
+    pub description: String,
+    pub make: String,
+    pub model: String,
+    pub serial_number: String,
+    pub enabled: bool,
+    pub x: i32,
+    pub y: i32,
+    pub scale: f64,
+    pub transform: String,
+    pub physical_width: i32,
+    pub physical_height: i32,
+    pub modes: Vec<OutputModeInfo>,
+}
+impl_lua_conversion_dynamic!(OutputInfo);
+
+impl From<window::output::OutputInfo> for OutputInfo {
+    fn from(info: window::output::OutputInfo) -> Self {
+        Self {
+            name: info.name,
+            description: info.description,
+            make: info.make,
+            model: info.model,
+            serial_number: info.serial_number,
+            enabled: info.enabled,
+            x: info.x,
+            y: info.y,
+            scale: info.scale,
+            transform: info.transform,
+            physical_width: info.physical_width,
+            physical_height: info.physical_height,
+            modes: info.modes.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
 pub fn register(lua: &Lua) -> anyhow::Result<()> {
     let window_mod = get_or_create_sub_module(lua, "gui")?;
 
@@ -89,6 +187,34 @@ pub fn register(lua: &Lua) -> anyhow::Result<()> {
         })?,
     )?;
 
+    window_mod.set(
+        "enumerate_toplevels",
+        lua.create_function(|_, _: ()| {
+            let conn = get_conn()?;
+            let toplevels: Vec<ToplevelInfo> = conn
+                .toplevels()
+                .map_err(|err| mlua::Error::external(format!("{err:#}")))?
+                .into_iter()
+                .map(Into::into)
+                .collect();
+            Ok(toplevels)
+        })?,
+    )?;
+
+    window_mod.set(
+        "outputs",
+        lua.create_function(|_, _: ()| {
+            let conn = get_conn()?;
+            let outputs: Vec<OutputInfo> = conn
+                .outputs()
+                .map_err(|err| mlua::Error::external(format!("{err:#}")))?
+                .into_iter()
+                .map(Into::into)
+                .collect();
+            Ok(outputs)
+        })?,
+    )?;
+
     window_mod.set(
         "get_appearance",
         lua.create_function(|_, _: ()| {
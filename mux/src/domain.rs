@@ -37,6 +37,25 @@ pub fn alloc_domain_id() -> DomainId {
     DOMAIN_ID.fetch_add(1, ::std::sync::atomic::Ordering::Relaxed)
 }
 
+/// Enables disk backed scrollback for a pane that is being spawned
+/// into the domain named `domain_name`, if the configuration calls for it
+pub(crate) fn enable_disk_scrollback(
+    terminal: &mut wezterm_term::Terminal,
+    domain_name: &str,
+    pane_id: PaneId,
+) {
+    let config = configuration();
+    if let Some(disk) = config.disk_scrollback.get(domain_name) {
+        if let Err(err) = terminal.enable_disk_scrollback(
+            &disk.directory(),
+            &format!("pane-{pane_id}-"),
+            disk.max_lines,
+        ) {
+            log::error!("Unable to enable disk scrollback for pane {pane_id}: {err:#}");
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum SplitSource {
     Spawn {
@@ -628,6 +647,7 @@ impl Domain for LocalDomain {
         if self.is_conpty() {
             terminal.enable_conpty_quirks();
         }
+        enable_disk_scrollback(&mut terminal, &self.name, pane_id);
 
         let pane: Arc<dyn Pane> = match child_result {
             Ok(child) => Arc::new(LocalPane::new(
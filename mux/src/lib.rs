@@ -73,6 +73,10 @@ pub enum MuxNotification {
         selection: ClipboardSelection,
         clipboard: Option<String>,
     },
+    QueryClipboard {
+        pane_id: PaneId,
+        selection: ClipboardSelection,
+    },
     SaveToDownloads {
         name: Option<String>,
         data: Arc<Vec<u8>>,
@@ -95,6 +99,20 @@ pub enum MuxNotification {
         old_workspace: String,
         new_workspace: String,
     },
+    /// Asks the gui window that is showing pane_id to capture
+    /// an image of it, and to send the PNG encoded result
+    CaptureImage {
+        pane_id: PaneId,
+        whole_window: bool,
+        result: smol::channel::Sender<Result<Vec<u8>, String>>,
+    },
+    /// Asks the gui to focus window_id using an activation token that
+    /// was handed to another program, such as a `wezterm start` that
+    /// asked us to spawn into this window
+    ActivateWindow {
+        window_id: WindowId,
+        activation_token: String,
+    },
 }
 
 static SUB_ID: AtomicUsize = AtomicUsize::new(0);
@@ -137,16 +155,52 @@ fn send_actions_to_mux(pane: &Weak<dyn Pane>, dead: &Arc<AtomicBool>, actions: V
     histogram!("send_actions_to_mux.rate").record(1.);
 }
 
+fn end_synchronized_update(pane: &Weak<dyn Pane>, timed_out: bool) {
+    if let Some(pane) = pane.upgrade() {
+        pane.end_synchronized_update(timed_out);
+    }
+}
+
 fn parse_buffered_data(pane: Weak<dyn Pane>, dead: &Arc<AtomicBool>, mut rx: FileDescriptor) {
     let mut buf = vec![0; configuration().mux_output_parser_buffer_size];
     let mut parser = termwiz::escape::parser::Parser::new();
     let mut actions = vec![];
-    let mut hold = false;
+    // The time at which the application began holding its output
+    // using synchronized output, if it is doing so
+    let mut hold: Option<Instant> = None;
     let mut action_size = 0;
     let mut delay = Duration::from_millis(configuration().mux_output_parser_coalesce_delay_ms);
+    let mut hold_timeout = Duration::from_millis(configuration().synchronized_output_timeout_ms);
     let mut deadline = None;
 
     loop {
+        if let Some(held_since) = hold {
+            if !hold_timeout.is_zero() {
+                let readable =
+                    match (held_since + hold_timeout).checked_duration_since(Instant::now()) {
+                        Some(remaining) => {
+                            let mut pfd = [pollfd {
+                                fd: rx.as_socket_descriptor(),
+                                events: POLLIN,
+                                revents: 0,
+                            }];
+                            matches!(poll(&mut pfd, Some(remaining)), Ok(1))
+                        }
+                        None => false,
+                    };
+                if !readable {
+                    // The application has held its output for too long;
+                    // show what we have rather than leaving the pane frozen
+                    hold = None;
+                    end_synchronized_update(&pane, true);
+                    if !actions.is_empty() {
+                        send_actions_to_mux(&pane, &dead, std::mem::take(&mut actions));
+                        action_size = 0;
+                    }
+                }
+            }
+        }
+
         match rx.read(&mut buf) {
             Ok(size) if size == 0 => {
                 dead.store(true, Ordering::Relaxed);
@@ -163,7 +217,12 @@ fn parse_buffered_data(pane: Weak<dyn Pane>, dead: &Arc<AtomicBool>, mut rx: Fil
                         Action::CSI(CSI::Mode(Mode::SetDecPrivateMode(DecPrivateMode::Code(
                             DecPrivateModeCode::SynchronizedOutput,
                         )))) => {
-                            hold = true;
+                            if hold.is_none() {
+                                hold = Some(Instant::now());
+                                if let Some(pane) = pane.upgrade() {
+                                    pane.begin_synchronized_update();
+                                }
+                            }
 
                             // Flush prior actions
                             if !actions.is_empty() {
@@ -174,11 +233,15 @@ fn parse_buffered_data(pane: Weak<dyn Pane>, dead: &Arc<AtomicBool>, mut rx: Fil
                         Action::CSI(CSI::Mode(Mode::ResetDecPrivateMode(
                             DecPrivateMode::Code(DecPrivateModeCode::SynchronizedOutput),
                         ))) => {
-                            hold = false;
+                            if hold.take().is_some() {
+                                end_synchronized_update(&pane, false);
+                            }
                             flush = true;
                         }
                         Action::CSI(CSI::Device(dev)) if matches!(**dev, Device::SoftReset) => {
-                            hold = false;
+                            if hold.take().is_some() {
+                                end_synchronized_update(&pane, false);
+                            }
                             flush = true;
                         }
                         _ => {}
@@ -191,7 +254,7 @@ fn parse_buffered_data(pane: Weak<dyn Pane>, dead: &Arc<AtomicBool>, mut rx: Fil
                     }
                 });
                 action_size += size;
-                if !actions.is_empty() && !hold {
+                if !actions.is_empty() && hold.is_none() {
                     // If we haven't accumulated too much data,
                     // pause for a short while to increase the chances
                     // that we coalesce a full "frame" from an unoptimized
@@ -229,6 +292,7 @@ fn parse_buffered_data(pane: Weak<dyn Pane>, dead: &Arc<AtomicBool>, mut rx: Fil
                 let config = configuration();
                 buf.resize(config.mux_output_parser_buffer_size, 0);
                 delay = Duration::from_millis(config.mux_output_parser_coalesce_delay_ms);
+                hold_timeout = Duration::from_millis(config.synchronized_output_timeout_ms);
             }
         }
     }
@@ -719,6 +783,31 @@ impl Mux {
         .detach();
     }
 
+    /// Asks the gui window that is showing `pane_id` to capture an image
+    /// of the pane, or of the whole window if `whole_window` is true,
+    /// and returns it encoded as PNG.
+    /// Must be called on the main thread.
+    pub async fn capture_image(pane_id: PaneId, whole_window: bool) -> anyhow::Result<Vec<u8>> {
+        let (tx, rx) = smol::channel::bounded(1);
+        {
+            let mux = Mux::get();
+            mux.resolve_pane_id(pane_id)
+                .ok_or_else(|| anyhow!("pane_id {} invalid", pane_id))?;
+
+            // Only a gui window can produce the image; if no gui window
+            // is showing the pane, the sender is dropped unanswered
+            mux.notify(MuxNotification::CaptureImage {
+                pane_id,
+                whole_window,
+                result: tx,
+            });
+        }
+        rx.recv()
+            .await
+            .map_err(|_| anyhow!("pane {} is not displayed in a gui window", pane_id))?
+            .map_err(|err| anyhow!("{err}"))
+    }
+
     pub fn default_domain(&self) -> Arc<dyn Domain> {
         self.default_domain.read().as_ref().map(Arc::clone).unwrap()
     }
@@ -1450,6 +1539,16 @@ impl Clipboard for MuxClipboard {
         });
         Ok(())
     }
+
+    fn query_contents(&self, selection: ClipboardSelection) -> anyhow::Result<()> {
+        let mux = Mux::try_get()
+            .ok_or_else(|| anyhow::anyhow!("MuxClipboard::query_contents: no Mux?"))?;
+        mux.notify(MuxNotification::QueryClipboard {
+            pane_id: self.pane_id,
+            selection,
+        });
+        Ok(())
+    }
 }
 
 struct MuxDownloader {}
@@ -1,7 +1,7 @@
 use crate::domain::DomainId;
 use crate::pane::{
     CachePolicy, CloseReason, ForEachPaneLogicalLine, LogicalLine, Pane, PaneId, Pattern,
-    SearchResult, WithPaneLines,
+    SearchResult, SynchronizedOutputStats, WithPaneLines,
 };
 use crate::renderable::*;
 use crate::tmux::{TmuxDomain, TmuxDomainState};
@@ -9,7 +9,7 @@ use crate::{Domain, Mux, MuxNotification};
 use anyhow::Error;
 use async_trait::async_trait;
 use config::keyassignment::ScrollbackEraseMode;
-use config::{configuration, ExitBehavior, ExitBehaviorMessaging};
+use config::{configuration, ExitBehavior, ExitBehaviorMessaging, SelectionWordConfig};
 use fancy_regex::Regex;
 use parking_lot::{MappedMutexGuard, Mutex, MutexGuard};
 use portable_pty::{Child, ChildKiller, ExitStatus, MasterPty, PtySize};
@@ -17,22 +17,26 @@ use procinfo::LocalProcessInfo;
 use rangeset::RangeSet;
 use smol::channel::{bounded, Receiver, TryRecvError};
 use std::borrow::Cow;
+use std::cell::Cell;
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::convert::TryInto;
 use std::io::{Result as IoResult, Write};
 use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use termwiz::escape::csi::{Sgr, CSI};
 use termwiz::escape::{Action, DeviceControlMode};
+use termwiz::hyperlink::Rule;
 use termwiz::input::KeyboardEncoding;
 use termwiz::surface::{Line, SequenceNo};
 use url::Url;
 use wezterm_dynamic::Value;
 use wezterm_term::color::ColorPalette;
 use wezterm_term::{
-    Alert, AlertHandler, Clipboard, DownloadHandler, KeyCode, KeyModifiers, MouseEvent, Progress,
-    SemanticZone, StableRowIndex, Terminal, TerminalConfiguration, TerminalSize,
+    Alert, AlertHandler, Clipboard, ClipboardSelection, CommandRecord, DownloadHandler, KeyCode,
+    KeyModifiers, MouseEvent, PaneBackground, PaneBidiMode, Progress, SemanticZone, StableRowIndex,
+    Terminal, TerminalConfiguration, TerminalSize,
 };
 
 const PROC_INFO_CACHE_TTL: Duration = Duration::from_millis(300);
@@ -123,7 +127,7 @@ pub enum LocalPaneConnectionState {
 
 pub struct LocalPane {
     pane_id: PaneId,
-    terminal: Mutex<Terminal>,
+    terminal: Arc<Mutex<Terminal>>,
     process: Mutex<ProcessState>,
     pty: Mutex<Box<dyn MasterPty>>,
     writer: Mutex<Box<dyn Write + Send>>,
@@ -133,8 +137,42 @@ pub struct LocalPane {
     #[cfg(unix)]
     leader: Arc<Mutex<Option<CachedLeaderInfo>>>,
     command_description: String,
+    selection_word_config: Mutex<Option<SelectionWordConfig>>,
+    /// true while a background thread is rewrapping the scrollback
+    rewrapping: Arc<AtomicBool>,
+    /// The progress of a large paste that is being sent in chunks
+    paste: Arc<Mutex<Option<PasteProgress>>>,
+    synchronized_output: Mutex<SynchronizedOutputState>,
 }
 
+/// Tracks the use of synchronized output, in order to
+/// produce SynchronizedOutputStats
+#[derive(Default)]
+struct SynchronizedOutputState {
+    updates: usize,
+    timeouts: usize,
+    total_hold: Duration,
+    max_hold: Duration,
+    held_since: Option<Instant>,
+}
+
+struct PasteProgress {
+    percentage: u8,
+    cancelled: bool,
+}
+
+/// The number of lines of scrollback that are rewrapped while holding
+/// the terminal lock, when rewrapping following a resize
+const REWRAP_CHUNK_ROWS: usize = 500;
+
+/// How long to wait for the application to consume its input
+/// before sending the next chunk of a large paste
+const PASTE_FLOW_CONTROL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The number of rows that are searched while holding the terminal
+/// lock, before releasing it to give other users a chance to run
+const SEARCH_BATCH_ROWS: StableRowIndex = 1000;
+
 #[async_trait(?Send)]
 impl Pane for LocalPane {
     fn pane_id(&self) -> PaneId {
@@ -421,7 +459,11 @@ impl Pane for LocalPane {
             pixel_width: size.pixel_width.try_into()?,
             pixel_height: size.pixel_height.try_into()?,
         })?;
-        self.terminal.lock().resize(size);
+        let mut terminal = self.terminal.lock();
+        terminal.resize(size);
+        if terminal.has_pending_rewrap() {
+            self.rewrap_in_background();
+        }
         Ok(())
     }
 
@@ -440,9 +482,22 @@ impl Pane for LocalPane {
     fn send_paste(&self, text: &str) -> Result<(), Error> {
         Mux::get().record_input_for_current_identity();
         if self.tmux_domain.lock().is_some() {
-            Ok(())
-        } else {
+            return Ok(());
+        }
+        if self.paste.lock().is_some() {
+            anyhow::bail!("a paste is still being sent to pane {}", self.pane_id);
+        }
+        let chunk_size = configuration().paste_chunk_size;
+        if chunk_size == 0 || text.len() <= chunk_size {
             self.terminal.lock().send_paste(text)
+        } else {
+            self.paste_in_background(text, chunk_size)
+        }
+    }
+
+    fn cancel_paste(&self) {
+        if let Some(paste) = self.paste.lock().as_mut() {
+            paste.cancelled = true;
         }
     }
 
@@ -463,7 +518,10 @@ impl Pane for LocalPane {
     }
 
     fn get_progress(&self) -> Progress {
-        self.terminal.lock().get_progress()
+        match self.paste.lock().as_ref() {
+            Some(paste) => Progress::Percentage(paste.percentage),
+            None => self.terminal.lock().get_progress(),
+        }
     }
 
     fn palette(&self) -> ColorPalette {
@@ -493,6 +551,10 @@ impl Pane for LocalPane {
         self.terminal.lock().has_unseen_output()
     }
 
+    fn unseen_output_lines(&self) -> usize {
+        self.terminal.lock().unseen_output_lines()
+    }
+
     fn is_mouse_grabbed(&self) -> bool {
         if self.tmux_domain.lock().is_some() {
             false
@@ -643,185 +705,135 @@ impl Pane for LocalPane {
         term.get_semantic_zones()
     }
 
-    async fn search(
-        &self,
-        pattern: Pattern,
-        range: Range<StableRowIndex>,
-        limit: Option<u32>,
-    ) -> anyhow::Result<Vec<SearchResult>> {
-        let term = self.terminal.lock();
-        let screen = term.screen();
+    fn get_failed_command_rows(&self) -> Vec<StableRowIndex> {
+        self.terminal.lock().failed_command_rows()
+    }
 
-        enum CompiledPattern {
-            CaseSensitiveString(String),
-            CaseInSensitiveString(String),
-            Regex(Regex),
-        }
+    fn get_command_history(&self) -> Vec<CommandRecord> {
+        self.terminal.lock().command_history()
+    }
 
-        let pattern = match pattern {
-            Pattern::CaseSensitiveString(s) => CompiledPattern::CaseSensitiveString(s),
-            Pattern::CaseInSensitiveString(s) => {
-                // normalize the case so we match everything lowercase
-                CompiledPattern::CaseInSensitiveString(s.to_lowercase())
-            }
-            Pattern::Regex(r) => CompiledPattern::Regex(Regex::new(&r)?),
-        };
+    fn get_pane_background(&self) -> PaneBackground {
+        self.terminal.lock().pane_background().clone()
+    }
 
-        let mut results = vec![];
-        let mut uniq_matches: HashMap<String, usize> = HashMap::new();
+    fn set_pane_background(&self, background: PaneBackground) {
+        self.terminal.lock().set_pane_background(background);
+    }
 
-        screen.for_each_logical_line_in_stable_range(range, |sr, lines| {
-            if let Some(limit) = limit {
-                if results.len() == limit as usize {
-                    // We've reach the limit, stop iteration.
-                    return false;
-                }
-            }
+    fn set_pane_palette(&self, palette: Option<ColorPalette>) {
+        self.terminal.lock().set_pane_palette(palette);
+    }
 
-            if lines.is_empty() {
-                // Nothing to do on this iteration, carry on with the next.
-                return true;
+    fn begin_synchronized_update(&self) {
+        self.synchronized_output
+            .lock()
+            .held_since
+            .get_or_insert_with(Instant::now);
+    }
+
+    fn end_synchronized_update(&self, timed_out: bool) {
+        let mut state = self.synchronized_output.lock();
+        if let Some(since) = state.held_since.take() {
+            let held = since.elapsed();
+            state.updates += 1;
+            state.total_hold += held;
+            state.max_hold = state.max_hold.max(held);
+            if timed_out {
+                state.timeouts += 1;
             }
-            let haystack = if lines.len() == 1 {
-                lines[0].as_str()
+        }
+    }
+
+    fn get_synchronized_output_stats(&self) -> Option<SynchronizedOutputStats> {
+        let state = self.synchronized_output.lock();
+        Some(SynchronizedOutputStats {
+            updates: state.updates,
+            timeouts: state.timeouts,
+            average_hold_ms: if state.updates == 0 {
+                0.
             } else {
-                let mut s = String::new();
-                for line in lines {
-                    s.push_str(&line.as_str());
-                }
-                Cow::Owned(s)
-            };
-            let stable_idx = sr.start;
+                state.total_hold.as_secs_f64() * 1000. / state.updates as f64
+            },
+            max_hold_ms: state.max_hold.as_secs_f64() * 1000.,
+            holding: state.held_since.is_some(),
+        })
+    }
 
-            if haystack.is_empty() {
-                return true;
-            }
+    fn get_hyperlink_rules(&self) -> Option<Arc<Vec<Rule>>> {
+        self.terminal.lock().hyperlink_rules()
+    }
 
-            let haystack = match &pattern {
-                CompiledPattern::CaseInSensitiveString(_) => Cow::Owned(haystack.to_lowercase()),
-                _ => haystack,
-            };
-            let mut coords = None;
+    fn set_hyperlink_rules(&self, rules: Option<Vec<Rule>>) {
+        self.terminal.lock().set_hyperlink_rules(rules);
+    }
 
-            match &pattern {
-                CompiledPattern::CaseInSensitiveString(s)
-                | CompiledPattern::CaseSensitiveString(s) => {
-                    for (idx, s) in haystack.match_indices(s) {
-                        found_match(
-                            s,
-                            idx,
-                            lines,
-                            stable_idx,
-                            &mut uniq_matches,
-                            &mut coords,
-                            &mut results,
-                        );
-                    }
-                }
-                CompiledPattern::Regex(re) => {
-                    // Allow for the regex to contain captures
-                    for capture_res in re.captures_iter(&haystack) {
-                        if let Ok(c) = capture_res {
-                            // Look for the captures in reverse order, as index==0 is
-                            // the whole matched string.  We can't just call
-                            // `c.iter().rev()` as the capture iterator isn't double-ended.
-                            for idx in (0..c.len()).rev() {
-                                if let Some(m) = c.get(idx) {
-                                    found_match(
-                                        m.as_str(),
-                                        m.start(),
-                                        lines,
-                                        stable_idx,
-                                        &mut uniq_matches,
-                                        &mut coords,
-                                        &mut results,
-                                    );
-                                    break;
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+    fn get_pane_bidi_mode(&self) -> PaneBidiMode {
+        self.terminal.lock().pane_bidi_mode()
+    }
 
-            // Keep iterating
-            true
-        });
+    fn set_pane_bidi_mode(&self, mode: PaneBidiMode) {
+        self.terminal.lock().set_pane_bidi_mode(mode);
+    }
 
-        #[derive(Copy, Clone, Debug)]
-        struct Coord {
-            byte_idx: usize,
-            grapheme_idx: usize,
-            stable_row: StableRowIndex,
-        }
+    fn send_clipboard_contents(&self, selection: ClipboardSelection, text: &str) {
+        self.terminal
+            .lock()
+            .send_clipboard_contents(selection, text);
+    }
 
-        fn found_match(
-            text: &str,
-            byte_idx: usize,
-            lines: &[&Line],
-            stable_idx: StableRowIndex,
-            uniq_matches: &mut HashMap<String, usize>,
-            coords: &mut Option<Vec<Coord>>,
-            results: &mut Vec<SearchResult>,
-        ) {
-            if coords.is_none() {
-                coords.replace(make_coords(lines, stable_idx));
-            }
-            let coords = coords.as_ref().unwrap();
+    fn get_selection_word_config(&self) -> Option<SelectionWordConfig> {
+        self.selection_word_config.lock().clone()
+    }
 
-            let match_id = match uniq_matches.get(text).copied() {
-                Some(id) => id,
-                None => {
-                    let id = uniq_matches.len();
-                    uniq_matches.insert(text.to_owned(), id);
-                    id
-                }
-            };
-            let (start_x, start_y) = haystack_idx_to_coord(byte_idx, coords);
-            let (end_x, end_y) = haystack_idx_to_coord(byte_idx + text.len(), coords);
-            results.push(SearchResult {
-                start_x,
-                start_y,
-                end_x,
-                end_y,
-                match_id,
-            });
-        }
+    fn set_selection_word_config(&self, config: Option<SelectionWordConfig>) {
+        *self.selection_word_config.lock() = config;
+    }
 
-        fn make_coords(lines: &[&Line], stable_row: StableRowIndex) -> Vec<Coord> {
-            let mut byte_idx = 0;
-            let mut coords = vec![];
+    fn get_alternate_screen_capture_lines(&self) -> usize {
+        self.terminal.lock().alternate_screen_capture_lines()
+    }
 
-            for (row_idx, line) in lines.iter().enumerate() {
-                for cell in line.visible_cells() {
-                    coords.push(Coord {
-                        byte_idx,
-                        grapheme_idx: cell.cell_index(),
-                        stable_row: stable_row + row_idx as StableRowIndex,
-                    });
-                    byte_idx += cell.str().len();
-                }
-            }
+    fn set_alternate_screen_capture_lines(&self, lines: Option<usize>) {
+        self.terminal
+            .lock()
+            .set_alternate_screen_capture_lines(lines);
+    }
 
-            coords
-        }
+    fn get_alternate_screen_capture(&self) -> Vec<Line> {
+        self.terminal.lock().alternate_screen_captured_lines()
+    }
 
-        fn haystack_idx_to_coord(idx: usize, coords: &[Coord]) -> (usize, StableRowIndex) {
-            let c = coords
-                .binary_search_by(|ele| ele.byte_idx.cmp(&idx))
-                .or_else(|i| -> Result<usize, usize> { Ok(i) })
-                .unwrap();
-            let coord = coords.get(c).map(|c| *c).unwrap_or_else(|| {
-                let last = coords.last().unwrap();
-                Coord {
-                    grapheme_idx: last.grapheme_idx + 1,
-                    ..*last
-                }
-            });
-            (coord.grapheme_idx, coord.stable_row)
-        }
+    async fn search(
+        &self,
+        pattern: Pattern,
+        range: Range<StableRowIndex>,
+        limit: Option<u32>,
+    ) -> anyhow::Result<Vec<SearchResult>> {
+        // Searching a large scrollback can take a while, so do it on
+        // a background thread rather than blocking the caller, which
+        // is typically the GUI thread.  If the caller drops the returned
+        // future, the guard flags the scan to stop at its next batch.
+        let terminal = Arc::clone(&self.terminal);
+        let cancel = CancelOnDrop::default();
+        let cancelled = Arc::clone(&cancel.0);
+        let result = smol::unblock(move || {
+            LocalPane::search_terminal(&terminal, pattern, range, limit, &cancelled)
+        })
+        .await;
+        drop(cancel);
+        result
+    }
+}
 
-        Ok(results)
+/// Sets its flag when dropped; used to cancel background work
+/// when the future awaiting it is dropped
+#[derive(Default)]
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
     }
 }
 
@@ -1004,7 +1016,7 @@ impl LocalPane {
 
         Self {
             pane_id,
-            terminal: Mutex::new(terminal),
+            terminal: Arc::new(Mutex::new(terminal)),
             process: Mutex::new(ProcessState::Running {
                 child_waiter: process,
                 pid,
@@ -1019,9 +1031,113 @@ impl LocalPane {
             #[cfg(unix)]
             leader: Arc::new(Mutex::new(None)),
             command_description,
+            selection_word_config: Mutex::new(None),
+            rewrapping: Arc::new(AtomicBool::new(false)),
+            paste: Arc::new(Mutex::new(None)),
+            synchronized_output: Mutex::new(SynchronizedOutputState::default()),
         }
     }
 
+    /// Rewraps the scrollback that was left at its prior width by
+    /// a resize on a background thread, a chunk at a time, so that
+    /// the pane remains responsive while a large scrollback is rewrapped.
+    /// The caller must hold the terminal lock, which is used to
+    /// synchronize with the completion of a prior background thread.
+    fn rewrap_in_background(&self) {
+        if self.rewrapping.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let terminal = Arc::downgrade(&self.terminal);
+        let rewrapping = Arc::clone(&self.rewrapping);
+        let pane_id = self.pane_id;
+        std::thread::spawn(move || {
+            while let Some(terminal) = terminal.upgrade() {
+                let mut term = terminal.lock();
+                if !term.rewrap_pending(REWRAP_CHUNK_ROWS) {
+                    rewrapping.store(false, Ordering::SeqCst);
+                    break;
+                }
+                // Let any thread that is waiting for the lock, such as
+                // one that is processing input or output for the pane,
+                // have it before we rewrap the next chunk
+                MutexGuard::unlock_fair(term);
+            }
+            Mux::notify_from_any_thread(MuxNotification::PaneOutput(pane_id));
+        });
+    }
+
+    /// Sends a large paste to the pane in chunks from a background thread.
+    /// Each chunk is sent only once the application has consumed the
+    /// previous one, so that the paste neither buffers an unbounded amount
+    /// of data nor holds the terminal lock for long, and so that it can
+    /// be cancelled part way through.  Key and mouse input that is sent
+    /// to the pane before the paste is complete cancels the rest of it.
+    /// The progress of the paste is reported as the progress of the pane.
+    fn paste_in_background(&self, text: &str, chunk_size: usize) -> anyhow::Result<()> {
+        let mut chunked = self.terminal.lock().begin_chunked_paste(text)?;
+        self.paste.lock().replace(PasteProgress {
+            percentage: 0,
+            cancelled: false,
+        });
+
+        let terminal = Arc::downgrade(&self.terminal);
+        let progress = Arc::clone(&self.paste);
+        let pane_id = self.pane_id;
+        let notify_progress = move |progress: Progress| {
+            Mux::notify_from_any_thread(MuxNotification::Alert {
+                pane_id,
+                alert: Alert::Progress(progress),
+            });
+        };
+        notify_progress(Progress::Percentage(0));
+
+        std::thread::spawn(move || {
+            while let Some(terminal) = terminal.upgrade() {
+                let cancelled = match progress.lock().as_ref() {
+                    Some(p) => p.cancelled,
+                    None => true,
+                };
+                let mut term = terminal.lock();
+                if cancelled {
+                    if let Err(err) = term.finish_chunked_paste(&mut chunked) {
+                        log::error!("cancelling paste: {err:#}");
+                    }
+                    break;
+                }
+                if term.pending_write_bytes() >= chunk_size {
+                    drop(term);
+                    std::thread::sleep(PASTE_FLOW_CONTROL_INTERVAL);
+                    continue;
+                }
+                match term.continue_chunked_paste(&mut chunked, chunk_size) {
+                    Ok(true) => {}
+                    Ok(false) => break,
+                    Err(err) => {
+                        log::error!("paste: {err:#}");
+                        break;
+                    }
+                }
+                drop(term);
+
+                let percentage = chunked.percentage();
+                if let Some(p) = progress.lock().as_mut() {
+                    if p.percentage != percentage {
+                        p.percentage = percentage;
+                        notify_progress(Progress::Percentage(percentage));
+                    }
+                }
+            }
+
+            progress.lock().take();
+            let restored = terminal
+                .upgrade()
+                .map(|terminal| terminal.lock().get_progress())
+                .unwrap_or_default();
+            notify_progress(restored);
+        });
+        Ok(())
+    }
+
     #[cfg(unix)]
     fn get_leader(&self, policy: CachePolicy) -> CachedLeaderInfo {
         let mut leader = self.leader.lock();
@@ -1132,6 +1248,214 @@ impl LocalPane {
             None
         }
     }
+
+    /// Searches `range` of the terminal for `pattern`, a batch of rows
+    /// at a time, stopping early if `cancelled` is set
+    fn search_terminal(
+        terminal: &Mutex<Terminal>,
+        pattern: Pattern,
+        range: Range<StableRowIndex>,
+        limit: Option<u32>,
+        cancelled: &AtomicBool,
+    ) -> anyhow::Result<Vec<SearchResult>> {
+        enum CompiledPattern {
+            CaseSensitiveString(String),
+            CaseInSensitiveString(String),
+            Regex(Regex),
+        }
+
+        let pattern = match pattern {
+            Pattern::CaseSensitiveString(s) => CompiledPattern::CaseSensitiveString(s),
+            Pattern::CaseInSensitiveString(s) => {
+                // normalize the case so we match everything lowercase
+                CompiledPattern::CaseInSensitiveString(s.to_lowercase())
+            }
+            Pattern::Regex(r) => CompiledPattern::Regex(Regex::new(&r)?),
+        };
+
+        let mut results = vec![];
+        let mut uniq_matches: HashMap<String, usize> = HashMap::new();
+
+        // A logical line that straddles the end of a batch is completed
+        // by that batch, so track how far we've got in order to avoid
+        // reporting it again from the next batch
+        let scanned_to = Cell::new(range.start);
+        let reached_limit = Cell::new(false);
+        let mut search_line = |sr: Range<StableRowIndex>, lines: &[&Line]| -> bool {
+            if sr.start < scanned_to.get() {
+                return true;
+            }
+            scanned_to.set(sr.end);
+
+            if let Some(limit) = limit {
+                if results.len() == limit as usize {
+                    // We've reach the limit, stop iteration.
+                    reached_limit.set(true);
+                    return false;
+                }
+            }
+
+            if lines.is_empty() {
+                // Nothing to do on this iteration, carry on with the next.
+                return true;
+            }
+            let haystack = if lines.len() == 1 {
+                lines[0].as_str()
+            } else {
+                let mut s = String::new();
+                for line in lines {
+                    s.push_str(&line.as_str());
+                }
+                Cow::Owned(s)
+            };
+            let stable_idx = sr.start;
+
+            if haystack.is_empty() {
+                return true;
+            }
+
+            let haystack = match &pattern {
+                CompiledPattern::CaseInSensitiveString(_) => Cow::Owned(haystack.to_lowercase()),
+                _ => haystack,
+            };
+            let mut coords = None;
+
+            match &pattern {
+                CompiledPattern::CaseInSensitiveString(s)
+                | CompiledPattern::CaseSensitiveString(s) => {
+                    for (idx, s) in haystack.match_indices(s) {
+                        found_match(
+                            s,
+                            idx,
+                            lines,
+                            stable_idx,
+                            &mut uniq_matches,
+                            &mut coords,
+                            &mut results,
+                        );
+                    }
+                }
+                CompiledPattern::Regex(re) => {
+                    // Allow for the regex to contain captures
+                    for capture_res in re.captures_iter(&haystack) {
+                        if let Ok(c) = capture_res {
+                            // Look for the captures in reverse order, as index==0 is
+                            // the whole matched string.  We can't just call
+                            // `c.iter().rev()` as the capture iterator isn't double-ended.
+                            for idx in (0..c.len()).rev() {
+                                if let Some(m) = c.get(idx) {
+                                    found_match(
+                                        m.as_str(),
+                                        m.start(),
+                                        lines,
+                                        stable_idx,
+                                        &mut uniq_matches,
+                                        &mut coords,
+                                        &mut results,
+                                    );
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            // Keep iterating
+            true
+        };
+
+        // Scan a batch of rows at a time, releasing the terminal lock
+        // in between so that the pane isn't blocked for the duration
+        // of the search, and so that we can stop if the search has
+        // been cancelled
+        let mut batch_start = range.start;
+        while batch_start < range.end && !reached_limit.get() {
+            if cancelled.load(Ordering::Relaxed) {
+                anyhow::bail!("search was cancelled");
+            }
+            let batch_end = (batch_start + SEARCH_BATCH_ROWS).min(range.end);
+            let term = terminal.lock();
+            term.screen()
+                .for_each_logical_line_in_stable_range(batch_start..batch_end, &mut search_line);
+            batch_start = batch_end.max(scanned_to.get());
+        }
+
+        #[derive(Copy, Clone, Debug)]
+        struct Coord {
+            byte_idx: usize,
+            grapheme_idx: usize,
+            stable_row: StableRowIndex,
+        }
+
+        fn found_match(
+            text: &str,
+            byte_idx: usize,
+            lines: &[&Line],
+            stable_idx: StableRowIndex,
+            uniq_matches: &mut HashMap<String, usize>,
+            coords: &mut Option<Vec<Coord>>,
+            results: &mut Vec<SearchResult>,
+        ) {
+            if coords.is_none() {
+                coords.replace(make_coords(lines, stable_idx));
+            }
+            let coords = coords.as_ref().unwrap();
+
+            let match_id = match uniq_matches.get(text).copied() {
+                Some(id) => id,
+                None => {
+                    let id = uniq_matches.len();
+                    uniq_matches.insert(text.to_owned(), id);
+                    id
+                }
+            };
+            let (start_x, start_y) = haystack_idx_to_coord(byte_idx, coords);
+            let (end_x, end_y) = haystack_idx_to_coord(byte_idx + text.len(), coords);
+            results.push(SearchResult {
+                start_x,
+                start_y,
+                end_x,
+                end_y,
+                match_id,
+            });
+        }
+
+        fn make_coords(lines: &[&Line], stable_row: StableRowIndex) -> Vec<Coord> {
+            let mut byte_idx = 0;
+            let mut coords = vec![];
+
+            for (row_idx, line) in lines.iter().enumerate() {
+                for cell in line.visible_cells() {
+                    coords.push(Coord {
+                        byte_idx,
+                        grapheme_idx: cell.cell_index(),
+                        stable_row: stable_row + row_idx as StableRowIndex,
+                    });
+                    byte_idx += cell.str().len();
+                }
+            }
+
+            coords
+        }
+
+        fn haystack_idx_to_coord(idx: usize, coords: &[Coord]) -> (usize, StableRowIndex) {
+            let c = coords
+                .binary_search_by(|ele| ele.byte_idx.cmp(&idx))
+                .or_else(|i| -> Result<usize, usize> { Ok(i) })
+                .unwrap();
+            let coord = coords.get(c).map(|c| *c).unwrap_or_else(|| {
+                let last = coords.last().unwrap();
+                Coord {
+                    grapheme_idx: last.grapheme_idx + 1,
+                    ..*last
+                }
+            });
+            (coord.grapheme_idx, coord.stable_row)
+        }
+
+        Ok(results)
+    }
 }
 
 impl Drop for LocalPane {
@@ -3,6 +3,7 @@ use crate::renderable::*;
 use crate::ExitBehavior;
 use async_trait::async_trait;
 use config::keyassignment::{KeyAssignment, ScrollbackEraseMode};
+use config::SelectionWordConfig;
 use downcast_rs::{impl_downcast, Downcast};
 use parking_lot::MappedMutexGuard;
 use rangeset::RangeSet;
@@ -14,11 +15,12 @@ use termwiz::hyperlink::Rule;
 use termwiz::input::KeyboardEncoding;
 use termwiz::surface::{Line, SequenceNo};
 use url::Url;
-use wezterm_dynamic::Value;
+use wezterm_dynamic::{ToDynamic, Value};
 use wezterm_term::color::ColorPalette;
 use wezterm_term::{
-    Clipboard, DownloadHandler, KeyCode, KeyModifiers, MouseEvent, Progress, SemanticZone,
-    StableRowIndex, TerminalConfiguration, TerminalSize,
+    Clipboard, ClipboardSelection, CommandRecord, DownloadHandler, KeyCode, KeyModifiers,
+    MouseEvent, PaneBackground, PaneBidiMode, Progress, SemanticZone, StableRowIndex,
+    TerminalConfiguration, TerminalSize,
 };
 
 static PANE_ID: ::std::sync::atomic::AtomicUsize = ::std::sync::atomic::AtomicUsize::new(0);
@@ -236,6 +238,8 @@ pub trait Pane: Downcast + Send + Sync {
         Progress::None
     }
     fn send_paste(&self, text: &str) -> anyhow::Result<()>;
+    /// Cancels a large paste that is still being sent to the pane
+    fn cancel_paste(&self) {}
     fn reader(&self) -> anyhow::Result<Option<Box<dyn std::io::Read + Send>>>;
     fn writer(&self) -> MappedMutexGuard<'_, dyn std::io::Write>;
     fn resize(&self, size: TerminalSize) -> anyhow::Result<()>;
@@ -275,6 +279,12 @@ pub trait Pane: Downcast + Send + Sync {
         false
     }
 
+    /// Returns the number of lines of output that have been produced
+    /// since the pane lost focus, if known
+    fn unseen_output_lines(&self) -> usize {
+        0
+    }
+
     /// Certain panes are OK to be closed with impunity (no prompts)
     fn can_close_without_prompting(&self, _reason: CloseReason) -> bool {
         false
@@ -304,6 +314,92 @@ pub trait Pane: Downcast + Send + Sync {
         Ok(vec![])
     }
 
+    /// Returns the rows on which a command reported a non-zero
+    /// exit status via OSC 133;D, if known
+    fn get_failed_command_rows(&self) -> Vec<StableRowIndex> {
+        vec![]
+    }
+
+    /// Returns the commands, as delimited by OSC 133, that were
+    /// run in this pane and whose prompts are still in the scrollback
+    fn get_command_history(&self) -> Vec<CommandRecord> {
+        vec![]
+    }
+
+    /// Returns the overrides for the background of this pane
+    fn get_pane_background(&self) -> PaneBackground {
+        PaneBackground::default()
+    }
+
+    /// Replaces the overrides for the background of this pane
+    fn set_pane_background(&self, _background: PaneBackground) {}
+
+    /// Assigns a palette to this pane in place of the configured
+    /// palette, or restores the configured palette if None
+    fn set_pane_palette(&self, _palette: Option<ColorPalette>) {}
+
+    /// Called when the application begins holding its output
+    /// using synchronized output
+    fn begin_synchronized_update(&self) {}
+
+    /// Called when the output held by a synchronized update is released.
+    /// `timed_out` is true if the update was ended by the hold timeout
+    /// rather than by the application.
+    fn end_synchronized_update(&self, _timed_out: bool) {}
+
+    /// Returns statistics about the use of synchronized output,
+    /// if they are tracked for this pane
+    fn get_synchronized_output_stats(&self) -> Option<SynchronizedOutputStats> {
+        None
+    }
+
+    /// Returns the hyperlink_rules that apply to this pane in place
+    /// of those from the configuration, if they have been overridden
+    fn get_hyperlink_rules(&self) -> Option<Arc<Vec<Rule>>> {
+        None
+    }
+
+    /// Overrides the hyperlink_rules for this pane; None reverts
+    /// to those from the configuration
+    fn set_hyperlink_rules(&self, _rules: Option<Vec<Rule>>) {}
+
+    /// Returns the overrides for the bidi configuration of this pane
+    fn get_pane_bidi_mode(&self) -> PaneBidiMode {
+        PaneBidiMode::default()
+    }
+
+    /// Replaces the overrides for the bidi configuration of this pane
+    fn set_pane_bidi_mode(&self, _mode: PaneBidiMode) {}
+
+    /// Returns the overrides for word selection in this pane
+    fn get_selection_word_config(&self) -> Option<SelectionWordConfig> {
+        None
+    }
+
+    /// Overrides the word selection configuration for this pane;
+    /// None reverts to that from the configuration
+    fn set_selection_word_config(&self, _config: Option<SelectionWordConfig>) {}
+
+    /// Returns the number of lines that are retained after scrolling
+    /// off the top of the alternate screen of this pane
+    fn get_alternate_screen_capture_lines(&self) -> usize {
+        0
+    }
+
+    /// Overrides the number of lines that are retained after scrolling
+    /// off the top of the alternate screen of this pane; None reverts
+    /// to alternate_screen_capture_lines from the configuration
+    fn set_alternate_screen_capture_lines(&self, _lines: Option<usize>) {}
+
+    /// Returns the lines that have been captured from the alternate screen
+    fn get_alternate_screen_capture(&self) -> Vec<Line> {
+        vec![]
+    }
+
+    /// Responds to an OSC 52 clipboard query previously issued
+    /// by the application running in this pane
+    fn send_clipboard_contents(&self, _selection: ClipboardSelection, _text: &str) {}
+
     /// Returns true if the terminal has grabbed the mouse and wants to
     /// give the embedded application a chance to process events.
     /// In practice this controls whether the gui will perform local
@@ -345,6 +441,23 @@ pub enum CachePolicy {
     AllowStale,
 }
 
+/// Statistics about the use of synchronized output
+/// (DEC private mode 2026) by the application in a pane
+#[derive(Debug, Clone, Default, PartialEq, ToDynamic)]
+pub struct SynchronizedOutputStats {
+    /// The number of synchronized updates that have ended
+    pub updates: usize,
+    /// The number of those updates that were ended by
+    /// `synchronized_output_timeout_ms` rather than by the application
+    pub timeouts: usize,
+    /// The average time for which output was held by an update
+    pub average_hold_ms: f64,
+    /// The longest time for which output was held by an update
+    pub max_hold_ms: f64,
+    /// true if output is currently being held
+    pub holding: bool,
+}
+
 /// This trait is used to implement/provide a callback that is used together
 /// with the Pane::with_lines_mut method.
 /// Ideally we'd simply pass an FnMut with the same signature as the trait
@@ -759,13 +759,14 @@ impl Domain for RemoteSshDomain {
 
         let writer = WriterWrapper::new(writer);
 
-        let terminal = wezterm_term::Terminal::new(
+        let mut terminal = wezterm_term::Terminal::new(
             size,
             std::sync::Arc::new(config::TermConfig::new()),
             "WezTerm",
             config::wezterm_version(),
             Box::new(writer.clone()),
         );
+        crate::domain::enable_disk_scrollback(&mut terminal, &self.name, pane_id);
 
         let pane: Arc<dyn Pane> = Arc::new(LocalPane::new(
             pane_id,
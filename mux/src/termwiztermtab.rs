@@ -205,7 +205,10 @@ impl Pane for TermWizTerminalPane {
             cols: size.cols as usize,
         })?;
 
-        self.terminal.lock().resize(size);
+        let mut terminal = self.terminal.lock();
+        terminal.resize(size);
+        // Overlays have little scrollback, so complete the rewrap now
+        while terminal.rewrap_pending(usize::MAX) {}
 
         Ok(())
     }
@@ -147,6 +147,22 @@ pub trait TerminalConfiguration: Downcast + std::fmt::Debug + Send + Sync {
         3500
     }
 
+    /// Returns true if lines that have scrolled far back into the
+    /// scrollback should be compressed to reduce memory usage.
+    /// They are transparently decompressed when accessed.
+    /// This requires the `use_serde` feature.
+    fn compress_scrollback(&self) -> bool {
+        false
+    }
+
+    /// Returns the number of lines that scroll off the top of the
+    /// alternate screen that are retained, so that the output of
+    /// full screen applications can be reviewed in the same way as
+    /// the scrollback of the primary screen.  0 disables this.
+    fn alternate_screen_capture_lines(&self) -> usize {
+        0
+    }
+
     /// Return true if the embedding application wants to use CSI-u encoding
     /// for keys that would otherwise be ambiguous.
     /// <http://www.leonerd.org.uk/hacks/fixterms/>
@@ -201,6 +217,13 @@ pub trait TerminalConfiguration: Downcast + std::fmt::Debug + Send + Sync {
         false
     }
 
+    /// Whether output is segmented into extended grapheme clusters
+    /// (DECSET 2027) by default, rather than placing each codepoint
+    /// that has a width into its own cell
+    fn grapheme_clustering(&self) -> bool {
+        true
+    }
+
     fn debug_key_events(&self) -> bool {
         false
     }
@@ -223,6 +246,12 @@ pub trait TerminalConfiguration: Downcast + std::fmt::Debug + Send + Sync {
     fn log_unknown_escape_sequences(&self) -> bool {
         false
     }
+
+    /// The maximum size, in bytes, of the text that may be written
+    /// to or read from the clipboard via OSC 52
+    fn osc52_max_bytes(&self) -> usize {
+        1024 * 1024
+    }
 }
 impl_downcast!(TerminalConfiguration);
 
@@ -35,6 +35,8 @@ pub use wezterm_surface::line::*;
 pub mod screen;
 pub use crate::screen::*;
 
+mod scrollback;
+
 pub mod terminal;
 pub use crate::terminal::*;
 
@@ -122,6 +124,61 @@ pub struct SemanticZone {
     pub semantic_type: SemanticType,
 }
 
+/// Overrides the background of an individual pane.  Fields that are
+/// None fall back to the background configured for the window.
+#[derive(Debug, Clone, Default, PartialEq, FromDynamic, ToDynamic)]
+pub struct PaneBackground {
+    /// The path to an image that fills the pane
+    #[dynamic(default)]
+    pub image: Option<String>,
+    /// The color that fills the pane, replacing the background
+    /// color from the palette
+    #[dynamic(default)]
+    pub color: Option<color::SrgbaTuple>,
+    /// The opacity of the background of the pane, in the range 0.0-1.0
+    #[dynamic(default)]
+    pub opacity: Option<f32>,
+}
+
+impl PaneBackground {
+    /// Returns true if none of the fields are overridden
+    pub fn is_default(&self) -> bool {
+        self.image.is_none() && self.color.is_none() && self.opacity.is_none()
+    }
+}
+
+/// Overrides the bidi configuration of an individual pane.  Fields
+/// that are None fall back to the bidi_enabled and bidi_direction
+/// configuration.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, FromDynamic, ToDynamic)]
+pub struct PaneBidiMode {
+    #[dynamic(default)]
+    pub enabled: Option<bool>,
+    #[dynamic(default)]
+    pub direction: Option<wezterm_bidi::ParagraphDirectionHint>,
+}
+
+/// A command that was run in the terminal, as delimited by the
+/// OSC 133 semantic prompt escapes emitted by the shell
+#[derive(Debug, Clone, PartialEq, FromDynamic, ToDynamic)]
+pub struct CommandRecord {
+    /// The row on which the prompt for the command started
+    pub prompt_row: StableRowIndex,
+    /// The command line that was entered at the prompt
+    pub command: String,
+    /// The working directory, as reported via OSC 7, at the time
+    /// that the command started
+    #[dynamic(default)]
+    pub cwd: Option<String>,
+    /// How long the command ran for; None while it is running
+    #[dynamic(default)]
+    pub duration: Option<std::time::Duration>,
+    /// The exit status reported via OSC 133;D; None while the
+    /// command is running, or if the shell didn't report it
+    #[dynamic(default)]
+    pub status: Option<i32>,
+}
+
 pub mod color;
 
 #[cfg(test)]
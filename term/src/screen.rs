@@ -1,6 +1,7 @@
 #![allow(clippy::range_plus_one)]
 use super::*;
 use crate::config::BidiMode;
+use crate::scrollback::{DiskSpill, LineStore};
 use log::debug;
 use std::collections::VecDeque;
 use std::sync::Arc;
@@ -20,14 +21,29 @@ pub struct Screen {
     /// Index 0 is the topmost line of the screen/scrollback (depending
     /// on the current window size) and will be the first line to be
     /// popped off the front of the screen when a new line is added that
-    /// would otherwise have exceeded the line capacity
-    lines: VecDeque<Line>,
+    /// would otherwise have exceeded the line capacity.
+    /// Lines that are far enough back in the scrollback may be
+    /// held in compressed form; see `TerminalConfiguration::compress_scrollback`.
+    lines: LineStore,
 
     /// Whenever we scroll a line off the top of the scrollback, we
     /// increment this.  We use this offset to translate between
     /// PhysRowIndex and StableRowIndex.
     stable_row_index_offset: usize,
 
+    /// The number of lines at the start of `lines` that have not yet
+    /// been rewrapped to `physical_cols` following a resize.
+    /// They are rewrapped incrementally by `rewrap_pending_lines`.
+    rewrap_pending: usize,
+
+    /// The widest that any of the pending lines were wrapped to
+    rewrap_pending_cols: usize,
+
+    /// The number of StableRowIndex values that were reserved ahead
+    /// of the pending lines, and not yet taken up by the lines that
+    /// rewrapping them added; see `reserve_rewrap_rows`.
+    rewrap_reserve: usize,
+
     /// config so we can access Maximum number of lines of scrollback
     config: Arc<dyn TerminalConfiguration>,
 
@@ -35,6 +51,15 @@ pub struct Screen {
     /// that we're the primary rather than the alternate screen.
     allow_scrollback: bool,
 
+    /// The number of lines of scrollback retained when disk backed
+    /// scrollback is enabled; usize::MAX for unlimited.
+    disk_scrollback_lines: usize,
+
+    /// For the alternate screen, overrides the number of lines that
+    /// are retained after scrolling off the top of the screen;
+    /// see `TerminalConfiguration::alternate_screen_capture_lines`.
+    capture_lines: Option<usize>,
+
     pub(crate) keyboard_stack: Vec<KeyboardEncoding>,
 
     /// Physical, visible height of the screen (not including scrollback)
@@ -46,6 +71,102 @@ pub struct Screen {
     pub(crate) saved_cursor: Option<SavedCursor>,
 }
 
+/// When resizing, the lines further than this many rows above the
+/// viewport are rewrapped incrementally rather than immediately;
+/// see `Screen::rewrap_pending_lines`.
+const IMMEDIATE_REWRAP_ROWS: usize = 1000;
+
+/// Joins the wrapped lines from `lines` into logical lines and then
+/// wraps those to `physical_cols`.  If `cursor` is the position of
+/// the cursor relative to the first of `lines`, the adjusted position
+/// of the cursor is returned along with the rewrapped lines.
+/// `prior_cols` is the width of the screen prior to the resize.
+fn rewrap(
+    lines: impl Iterator<Item = Line>,
+    physical_cols: usize,
+    prior_cols: usize,
+    cursor: Option<(usize, PhysRowIndex)>,
+    seqno: SequenceNo,
+) -> (VecDeque<Line>, Option<(usize, PhysRowIndex)>) {
+    let mut rewrapped = VecDeque::new();
+    let mut logical_line: Option<Line> = None;
+    let mut logical_cursor_x: Option<usize> = None;
+    let mut adjusted_cursor = None;
+
+    for (phys_idx, mut line) in lines.enumerate() {
+        line.update_last_change_seqno(seqno);
+        let was_wrapped = line.last_cell_was_wrapped();
+
+        if was_wrapped {
+            line.set_last_cell_was_wrapped(false, seqno);
+        }
+
+        let line = match logical_line.take() {
+            None => {
+                if let Some((cursor_x, cursor_y)) = cursor {
+                    if phys_idx == cursor_y {
+                        logical_cursor_x = Some(cursor_x);
+                    }
+                }
+                line
+            }
+            Some(mut prior) => {
+                if let Some((cursor_x, cursor_y)) = cursor {
+                    if phys_idx == cursor_y {
+                        logical_cursor_x = Some(cursor_x + prior.len());
+                    }
+                }
+                prior.append_line(line, seqno);
+                prior
+            }
+        };
+
+        if was_wrapped {
+            logical_line.replace(line);
+            continue;
+        }
+
+        if let Some(x) = logical_cursor_x.take() {
+            let num_lines = x / physical_cols;
+            let last_x = x - (num_lines * physical_cols);
+            let mut cursor_pos = (last_x, rewrapped.len() + num_lines);
+
+            // Special case: if the cursor lands in column zero, we'll
+            // lose track of its logical association with the wrapped
+            // line and it won't resize with the line correctly.
+            // Put it back on the prior line. The cursor is now
+            // technically outside of the viewport width.
+            // That isn't necessary when the width is unchanged, which
+            // is the case when a taller screen reveals lines that were
+            // pending a rewrap.
+            if cursor_pos.0 == 0 && cursor_pos.1 > 0 && physical_cols != prior_cols {
+                if physical_cols < prior_cols {
+                    // getting smaller: preserve its original position
+                    // on the prior line
+                    cursor_pos.0 = cursor.map(|(x, _)| x).unwrap_or(0);
+                } else {
+                    // getting larger; we were most likely in column 1
+                    // or somewhere close. Jump to the end of the
+                    // prior line.
+                    cursor_pos.0 = physical_cols;
+                }
+                cursor_pos.1 -= 1;
+            }
+            adjusted_cursor = Some(cursor_pos);
+        }
+
+        if line.len() <= physical_cols {
+            rewrapped.push_back(line);
+        } else {
+            for line in line.wrap(physical_cols, seqno) {
+                rewrapped.push_back(line);
+            }
+        }
+    }
+
+    (rewrapped, adjusted_cursor)
+}
+
 fn scrollback_size(config: &Arc<dyn TerminalConfiguration>, allow_scrollback: bool) -> usize {
     if allow_scrollback {
         config.scrollback_size()
@@ -69,7 +190,7 @@ impl Screen {
         let physical_cols = size.cols.max(1);
 
         let mut lines =
-            VecDeque::with_capacity(physical_rows + scrollback_size(config, allow_scrollback));
+            LineStore::with_capacity(physical_rows + scrollback_size(config, allow_scrollback));
         for _ in 0..physical_rows {
             let mut line = Line::new(seqno);
             bidi_mode.apply_to_line(&mut line, seqno);
@@ -80,9 +201,14 @@ impl Screen {
             lines,
             config: Arc::clone(config),
             allow_scrollback,
+            disk_scrollback_lines: 0,
+            capture_lines: None,
             physical_rows,
             physical_cols,
             stable_row_index_offset: 0,
+            rewrap_pending: 0,
+            rewrap_pending_cols: 0,
+            rewrap_reserve: 0,
             dpi: size.dpi,
             keyboard_stack: vec![],
             saved_cursor: None,
@@ -94,7 +220,128 @@ impl Screen {
     }
 
     fn scrollback_size(&self) -> usize {
-        scrollback_size(&self.config, self.allow_scrollback)
+        scrollback_size(&self.config, self.allow_scrollback).max(self.capture_lines())
+    }
+
+    /// Returns the number of lines that are retained after scrolling
+    /// off the top of the alternate screen, where they can be reviewed
+    /// in the same way as the scrollback of the primary screen.
+    /// This is always 0 for the primary screen.
+    pub fn capture_lines(&self) -> usize {
+        if self.allow_scrollback {
+            0
+        } else {
+            self.capture_lines
+                .unwrap_or_else(|| self.config.alternate_screen_capture_lines())
+        }
+    }
+
+    /// Returns the override for the number of lines that are captured
+    /// from the alternate screen, if any
+    pub(crate) fn capture_lines_override(&self) -> Option<usize> {
+        self.capture_lines
+    }
+
+    /// Overrides the number of lines that are captured from the
+    /// alternate screen; None reverts to the configured value
+    pub(crate) fn set_capture_lines(&mut self, lines: Option<usize>, seqno: SequenceNo) {
+        self.capture_lines = lines;
+        self.trim_captured_lines(seqno);
+    }
+
+    /// Discards the lines captured from the alternate screen that
+    /// exceed the number that are currently retained
+    fn trim_captured_lines(&mut self, seqno: SequenceNo) {
+        if self.allow_scrollback {
+            return;
+        }
+        let excess = self
+            .lines
+            .len()
+            .saturating_sub(self.physical_rows + self.capture_lines());
+        if excess > 0 {
+            self.lines.remove_front(excess);
+            self.stable_row_index_offset += excess;
+            // The lines that scrolled off were retained while the
+            // capture was enabled, so the StableRowIndex of the visible
+            // lines may have changed
+            for line in self.lines.iter_mut() {
+                line.update_last_change_seqno(seqno);
+            }
+        }
+    }
+
+    /// Returns copies of the lines that were captured from the
+    /// alternate screen; they are the lines above the visible portion
+    /// of the screen
+    pub fn captured_lines(&self) -> Vec<Line> {
+        if self.allow_scrollback {
+            return vec![];
+        }
+        self.lines_in_phys_range(0..self.phys_row(0))
+    }
+
+    /// Copies the visible lines, up to the last one that isn't blank,
+    /// into the captured lines, so that the final output of a full screen application is
+    /// retained when switching back to the primary screen
+    pub(crate) fn capture_visible_lines(&mut self, seqno: SequenceNo) {
+        if self.capture_lines() == 0 {
+            return;
+        }
+        let top = self.phys_row(0);
+        let end = match (top..self.lines.len())
+            .rev()
+            .find(|&idx| !self.lines[idx].is_whitespace())
+        {
+            Some(idx) => idx + 1,
+            None => return,
+        };
+        for (idx, line) in self.lines_in_phys_range(top..end).into_iter().enumerate() {
+            self.lines.insert(top + idx, line);
+        }
+        self.trim_captured_lines(seqno);
+    }
+
+    /// Returns the maximum number of lines of scrollback, including
+    /// those that are spilled to disk
+    fn max_scrollback_size(&self) -> usize {
+        if self.lines.is_disk_backed() {
+            self.scrollback_size().max(self.disk_scrollback_lines)
+        } else {
+            self.scrollback_size()
+        }
+    }
+
+    /// Retains up to `max_lines` of scrollback (or an unlimited amount
+    /// if None), spilling lines beyond the configured scrollback size
+    /// to `spill`
+    pub(crate) fn enable_disk_scrollback(
+        &mut self,
+        spill: Arc<DiskSpill>,
+        max_lines: Option<usize>,
+    ) {
+        if !self.allow_scrollback {
+            return;
+        }
+        self.lines.set_spill(spill);
+        self.disk_scrollback_lines = max_lines.unwrap_or(usize::MAX);
+    }
+
+    /// Returns the index of the first line that will be rewrapped
+    /// immediately by a resize; the lines prior to it are left for
+    /// `rewrap_pending_lines`.  That is the start of the logical line
+    /// that includes the row IMMEDIATE_REWRAP_ROWS above the viewport,
+    /// or the cursor, whichever is earlier.
+    fn rewrap_split(&self, physical_rows: usize, cursor_y: PhysRowIndex) -> PhysRowIndex {
+        let mut split = self
+            .lines
+            .len()
+            .saturating_sub(physical_rows.max(self.physical_rows) + IMMEDIATE_REWRAP_ROWS)
+            .min(cursor_y);
+        while split > 0 && self.lines[split - 1].last_cell_was_wrapped() {
+            split -= 1;
+        }
+        split
     }
 
     fn rewrap_lines(
@@ -105,81 +352,44 @@ impl Screen {
         cursor_y: PhysRowIndex,
         seqno: SequenceNo,
     ) -> (usize, PhysRowIndex) {
-        let mut rewrapped = VecDeque::new();
-        let mut logical_line: Option<Line> = None;
-        let mut logical_cursor_x: Option<usize> = None;
         let mut adjusted_cursor = (cursor_x, cursor_y);
 
-        for (phys_idx, mut line) in self.lines.drain(..).enumerate() {
-            line.update_last_change_seqno(seqno);
-            let was_wrapped = line.last_cell_was_wrapped();
-
-            if was_wrapped {
-                line.set_last_cell_was_wrapped(false, seqno);
-            }
-
-            let line = match logical_line.take() {
-                None => {
-                    if phys_idx == cursor_y {
-                        logical_cursor_x = Some(cursor_x);
-                    }
-                    line
-                }
-                Some(mut prior) => {
-                    if phys_idx == cursor_y {
-                        logical_cursor_x = Some(cursor_x + prior.len());
-                    }
-                    prior.append_line(line, seqno);
-                    prior
-                }
-            };
-
-            if was_wrapped {
-                logical_line.replace(line);
-                continue;
-            }
-
-            if let Some(x) = logical_cursor_x.take() {
-                let num_lines = x / physical_cols;
-                let last_x = x - (num_lines * physical_cols);
-                adjusted_cursor = (last_x, rewrapped.len() + num_lines);
-
-                // Special case: if the cursor lands in column zero, we'll
-                // lose track of its logical association with the wrapped
-                // line and it won't resize with the line correctly.
-                // Put it back on the prior line. The cursor is now
-                // technically outside of the viewport width.
-                if adjusted_cursor.0 == 0 && adjusted_cursor.1 > 0 {
-                    if physical_cols < self.physical_cols {
-                        // getting smaller: preserve its original position
-                        // on the prior line
-                        adjusted_cursor.0 = cursor_x;
-                    } else {
-                        // getting larger; we were most likely in column 1
-                        // or somewhere close. Jump to the end of the
-                        // prior line.
-                        adjusted_cursor.0 = physical_cols;
-                    }
-                    adjusted_cursor.1 -= 1;
-                }
-            }
-
-            if line.len() <= physical_cols {
-                rewrapped.push_back(line);
-            } else {
-                for line in line.wrap(physical_cols, seqno) {
-                    rewrapped.push_back(line);
-                }
+        // With a large scrollback, rewrapping all of it can take a
+        // noticeable amount of time, so only the lines near the viewport
+        // are rewrapped here, and the rest are left for rewrap_pending_lines.
+        // The pending lines are always at the start of the logical line
+        // following the split, so they can be rewrapped independently.
+        let split = self.rewrap_split(physical_rows, cursor_y);
+        let prior_cols = self.physical_cols;
+        self.lines.splice(split..self.lines.len(), |lines| {
+            let (rewrapped, cursor) = rewrap(
+                lines,
+                physical_cols,
+                prior_cols,
+                Some((cursor_x, cursor_y - split)),
+                seqno,
+            );
+            if let Some((x, y)) = cursor {
+                adjusted_cursor = (x, split + y);
             }
+            rewrapped
+        });
+        if self.rewrap_pending == 0 {
+            self.rewrap_pending_cols = 0;
+            self.rewrap_reserve = 0;
+        }
+        self.rewrap_pending = split;
+        if split > 0 {
+            self.rewrap_pending_cols = self.rewrap_pending_cols.max(prior_cols);
+            self.reserve_rewrap_rows(physical_cols);
         }
-        self.lines = rewrapped;
 
         // If we resized narrower and generated additional lines,
         // we may need to scroll the lines to make room.  However,
         // if the bottom line(s) are whitespace, we'll prune those
         // out first in the rewrap case so that we don't lose any
         // real information off the top of the scrollback
-        let capacity = physical_rows + self.scrollback_size();
+        let capacity = physical_rows.saturating_add(self.max_scrollback_size());
         while self.lines.len() > capacity
             && self.lines.back().map(Line::is_whitespace).unwrap_or(false)
         {
@@ -189,6 +399,99 @@ impl Screen {
         adjusted_cursor
     }
 
+    /// Ensures that enough StableRowIndex values are reserved ahead of
+    /// the pending lines for the most lines that rewrapping them to
+    /// `physical_cols` could add.  rewrap_pending_lines gives those
+    /// values to the lines that it adds, rather than moving the lines
+    /// that follow, so that the viewport stays where it is.
+    fn reserve_rewrap_rows(&mut self, physical_cols: usize) {
+        let rows_per_row = if physical_cols >= self.rewrap_pending_cols {
+            1
+        } else {
+            // A double width cell that doesn't fit at the end of a row
+            // moves to the next one, leaving a column unused
+            let usable_cols = physical_cols.saturating_sub(1).max(1);
+            (self.rewrap_pending_cols + usable_cols - 1) / usable_cols
+        };
+        let growth = self.rewrap_pending.saturating_mul(rows_per_row - 1);
+        if growth > self.rewrap_reserve {
+            self.stable_row_index_offset += growth - self.rewrap_reserve;
+            self.rewrap_reserve = growth;
+        }
+    }
+
+    /// Returns true if some of the scrollback has yet to be rewrapped
+    /// following a resize
+    pub fn has_pending_rewrap(&self) -> bool {
+        self.rewrap_pending > 0
+    }
+
+    /// Rewraps approximately `max_rows` of the lines that were left
+    /// at their prior width by a resize, starting with those that are
+    /// nearest to the viewport.  Any lines that this adds beyond the
+    /// scrollback limit are discarded from the top of the scrollback.
+    /// The StableRowIndex of the lines that follow those that are
+    /// rewrapped is preserved, so that the viewport doesn't jump around
+    /// while this happens; the lines that are added take the values
+    /// that were reserved for them by the resize.
+    /// Returns true if there are more lines to be rewrapped.
+    pub fn rewrap_pending_lines(&mut self, max_rows: usize, seqno: SequenceNo) -> bool {
+        let end = self.rewrap_pending.min(self.lines.len());
+        if end == 0 {
+            self.rewrap_pending = 0;
+            self.rewrap_reserve = 0;
+            return false;
+        }
+
+        let mut start = end.saturating_sub(max_rows.max(1));
+        while start > 0 && self.lines[start - 1].last_cell_was_wrapped() {
+            start -= 1;
+        }
+
+        let physical_cols = self.physical_cols;
+        let mut num_rewrapped = 0;
+        self.lines.splice(start..end, |lines| {
+            let (rewrapped, _) = rewrap(lines, physical_cols, physical_cols, None, seqno);
+            num_rewrapped = rewrapped.len();
+            rewrapped
+        });
+
+        // Rewrapping narrower adds lines, which may push the oldest
+        // of them beyond the limit of the scrollback
+        let max_scrollback = self.max_scrollback_size();
+        let capacity = max_scrollback.saturating_add(self.physical_rows);
+        let excess = self.lines.len().saturating_sub(capacity);
+        if excess > 0 {
+            self.lines.remove_front(excess);
+        }
+        self.rewrap_pending = start.saturating_sub(excess);
+
+        // Keep the StableRowIndex of the following lines unchanged.
+        // Lines that are added beyond those that were discarded take
+        // up the reserved values; should the reservation somehow fall
+        // short, the following lines move down rather than the
+        // StableRowIndex of the earlier lines going below zero.
+        let removed = end - start;
+        let added = num_rewrapped.saturating_sub(removed);
+        let shrunk = removed.saturating_sub(num_rewrapped);
+        let reserved = added.saturating_sub(excess).min(self.rewrap_reserve);
+        self.rewrap_reserve -= reserved;
+        self.stable_row_index_offset += shrunk + excess.saturating_sub(added);
+        self.stable_row_index_offset -= reserved;
+        if self.rewrap_pending == 0 {
+            self.rewrap_reserve = 0;
+        }
+
+        if self.config.compress_scrollback() || self.lines.is_disk_backed() {
+            self.lines.compress(
+                self.physical_rows,
+                self.physical_rows + self.scrollback_size(),
+            );
+        }
+
+        self.rewrap_pending > 0
+    }
+
     /// Resize the physical, viewable portion of the screen
     pub fn resize(
         &mut self,
@@ -222,7 +525,11 @@ impl Screen {
             }
         }
 
-        let (cursor_x, cursor_y) = if physical_cols != self.physical_cols {
+        // If making the screen taller would reveal lines that have
+        // not yet been rewrapped, rewrap them now
+        let reveals_pending = self.lines.len().saturating_sub(self.rewrap_pending) < physical_rows;
+
+        let (cursor_x, cursor_y) = if physical_cols != self.physical_cols || reveals_pending {
             // Check to see if we need to rewrap lines that were
             // wrapped due to reaching the right hand side of the terminal.
             // For each one that we find, we need to join it with its
@@ -233,7 +540,7 @@ impl Screen {
             if self.allow_scrollback {
                 self.rewrap_lines(physical_cols, physical_rows, cursor.x, cursor_phys, seqno)
             } else {
-                for line in &mut self.lines {
+                for line in self.lines.iter_mut() {
                     if physical_cols < self.physical_cols {
                         // Do a simple prune of the lines instead
                         line.resize(physical_cols, seqno);
@@ -362,7 +669,7 @@ impl Screen {
     /// Returns a copy of the lines in the screen (including scrollback)
     #[cfg(test)]
     pub fn all_lines(&self) -> Vec<Line> {
-        self.lines.iter().map(|l| l.clone()).collect()
+        self.lines.iter().cloned().collect()
     }
 
     pub fn insert_cell(
@@ -450,6 +757,134 @@ impl Screen {
         line.fill_range(cols, &Cell::blank_with_attrs(attr.clone()), seqno);
     }
 
+    /// Fill a rectangle with copies of `cell`.  The rows are relative
+    /// to the visible screen origin.
+    pub fn fill_rectangle(
+        &mut self,
+        rows: Range<VisibleRowIndex>,
+        cols: Range<usize>,
+        cell: &Cell,
+        seqno: SequenceNo,
+    ) {
+        for y in rows {
+            let line_idx = self.phys_row(y);
+            let line = self.line_mut(line_idx);
+            line.fill_range(cols.clone(), cell, seqno);
+        }
+    }
+
+    /// Replace the text in a rectangle with spaces, preserving
+    /// the attributes of each cell.  The rows are relative to the
+    /// visible screen origin.
+    pub fn erase_rectangle_text(
+        &mut self,
+        rows: Range<VisibleRowIndex>,
+        cols: Range<usize>,
+        seqno: SequenceNo,
+    ) {
+        for y in rows {
+            let line_idx = self.phys_row(y);
+            let line = self.line_mut(line_idx);
+            let blanks: Vec<(usize, Cell)> = line
+                .visible_cells()
+                .filter(|cell| cols.contains(&cell.cell_index()) && cell.str() != " ")
+                .map(|cell| {
+                    (
+                        cell.cell_index(),
+                        Cell::blank_with_attrs(cell.attrs().clone()),
+                    )
+                })
+                .collect();
+            for (x, cell) in blanks {
+                line.set_cell(x, cell, seqno);
+            }
+        }
+    }
+
+    /// Copy the cells of a rectangle so that its top left corner is
+    /// positioned at the start of `dest_rows` and `dest_cols`.
+    /// The rows are relative to the visible screen origin.  Cells that
+    /// would be copied beyond the end of `dest_rows` or `dest_cols` are
+    /// discarded.  The source is read in its entirety before the
+    /// destination is modified, so the two rectangles may overlap.
+    pub fn copy_rectangle(
+        &mut self,
+        rows: Range<VisibleRowIndex>,
+        cols: Range<usize>,
+        dest_rows: Range<VisibleRowIndex>,
+        dest_cols: Range<usize>,
+        seqno: SequenceNo,
+    ) {
+        let copied: Vec<Vec<Option<Cell>>> = rows
+            .map(|y| {
+                let line = &self.lines[self.phys_row(y)];
+                // Positions that are covered by the tail of
+                // a wide character are None
+                let mut cells = vec![Some(Cell::blank()); cols.len()];
+                for cell in line.visible_cells() {
+                    let idx = cell.cell_index();
+                    if !cols.contains(&idx) {
+                        continue;
+                    }
+                    let idx = idx - cols.start;
+                    if idx + cell.width() > cells.len() {
+                        // A wide character that is clipped by the
+                        // right edge of the rectangle is copied as
+                        // a blank
+                        continue;
+                    }
+                    cells[idx] = Some(cell.as_cell());
+                    for tail in idx + 1..idx + cell.width() {
+                        cells[tail] = None;
+                    }
+                }
+                cells
+            })
+            .collect();
+
+        for (y, cells) in dest_rows.zip(copied) {
+            let line_idx = self.phys_row(y);
+            let line = self.line_mut(line_idx);
+            for (x, cell) in dest_cols.clone().zip(cells) {
+                if let Some(cell) = cell {
+                    // Likewise for one that would be clipped by
+                    // the right edge of the destination
+                    let cell = if x + cell.width() > dest_cols.end {
+                        Cell::blank()
+                    } else {
+                        cell
+                    };
+                    line.set_cell(x, cell, seqno);
+                }
+            }
+        }
+    }
+
+    /// Apply `f` to the attributes of the cells in `cols` of row `y`,
+    /// which is relative to the visible screen origin.  The line is
+    /// padded with blanks as needed so that every position in `cols`
+    /// takes on the change.
+    pub fn change_cell_attributes<F>(
+        &mut self,
+        y: VisibleRowIndex,
+        cols: Range<usize>,
+        seqno: SequenceNo,
+        mut f: F,
+    ) where
+        F: FnMut(&mut CellAttributes),
+    {
+        let line_idx = self.phys_row(y);
+        let line = self.line_mut(line_idx);
+        if line.len() < cols.end {
+            line.resize(cols.end, seqno);
+        }
+        for cell in &mut line.cells_mut_for_attr_changes_only()[cols] {
+            f(cell.attrs_mut());
+        }
+        line.update_last_change_seqno(seqno);
+        line.mark_dirty();
+    }
+
     /// Ensure that row is within the range of the physical portion of
     /// the screen; 0 .. physical_rows by clamping it to the nearest
     /// boundary.
@@ -652,7 +1087,8 @@ impl Screen {
     ) {
         let phys_scroll = self.phys_range(scroll_region);
         let num_rows = num_rows.min(phys_scroll.end - phys_scroll.start);
-        let scrollback_ok = scroll_region.start == 0 && self.allow_scrollback;
+        let scrollback_ok =
+            scroll_region.start == 0 && (self.allow_scrollback || self.capture_lines() > 0);
         let insert_at_end = scroll_region.end as usize == self.physical_rows;
 
         debug!(
@@ -678,7 +1114,9 @@ impl Screen {
             // Remove the scrolled lines
             num_rows
         } else {
-            let max_allowed = self.physical_rows + self.scrollback_size();
+            let max_allowed = self
+                .physical_rows
+                .saturating_add(self.max_scrollback_size());
             if self.lines.len() + num_rows >= max_allowed {
                 (self.lines.len() + num_rows) - max_allowed
             } else {
@@ -731,6 +1169,7 @@ impl Screen {
 
         if remove_idx == 0 && scrollback_ok {
             self.stable_row_index_offset += lines_removed;
+            self.rewrap_pending = self.rewrap_pending.saturating_sub(lines_removed);
         }
 
         for _ in 0..to_add {
@@ -757,17 +1196,24 @@ impl Screen {
                 self.line_mut(y).update_last_change_seqno(seqno);
             }
         }
+
+        if scrollback_ok && (self.config.compress_scrollback() || self.lines.is_disk_backed()) {
+            self.lines.compress(
+                self.physical_rows,
+                self.physical_rows + self.scrollback_size(),
+            );
+        }
     }
 
     pub fn erase_scrollback(&mut self) {
         let len = self.lines.len();
         let to_clear = len - self.physical_rows;
-        for _ in 0..to_clear {
-            self.lines.pop_front();
-            if self.allow_scrollback {
-                self.stable_row_index_offset += 1;
-            }
+        self.lines.remove_front(to_clear);
+        if self.allow_scrollback || self.capture_lines() > 0 {
+            self.stable_row_index_offset += to_clear;
         }
+        self.rewrap_pending = 0;
+        self.rewrap_reserve = 0;
     }
 
     /// ```text
@@ -898,12 +1344,7 @@ impl Screen {
     }
 
     pub fn lines_in_phys_range(&self, phys_range: Range<PhysRowIndex>) -> Vec<Line> {
-        self.lines
-            .iter()
-            .skip(phys_range.start)
-            .take(phys_range.end - phys_range.start)
-            .cloned()
-            .collect()
+        self.lines.range(phys_range).into_iter().cloned().collect()
     }
 
     pub fn get_changed_stable_rows(
@@ -913,13 +1354,7 @@ impl Screen {
     ) -> Vec<StableRowIndex> {
         let phys = self.stable_range(&stable_lines);
         let mut set = vec![];
-        for (idx, line) in self
-            .lines
-            .iter()
-            .enumerate()
-            .skip(phys.start)
-            .take(phys.end - phys.start)
-        {
+        for (idx, line) in (phys.start..).zip(self.lines.range(phys)) {
             if line.changed_since(seqno) {
                 set.push(self.phys_to_stable_row_index(idx))
             }
@@ -931,52 +1366,21 @@ impl Screen {
     where
         F: FnMut(&[&Line]),
     {
-        let (first, second) = self.lines.as_slices();
-        let first_range = 0..first.len();
-        let second_range = first.len()..first.len() + second.len();
-        let first_range = phys_intersection(&first_range, &phys_range);
-        let second_range = phys_intersection(&second_range, &phys_range);
-
-        let mut lines: Vec<&Line> = Vec::with_capacity(phys_range.end - phys_range.start);
-        for line in &first[first_range] {
-            lines.push(line);
-        }
-        for line in &second[second_range] {
-            lines.push(line);
-        }
-        func(&lines)
+        func(&self.lines.range(phys_range))
     }
 
     pub fn with_phys_lines_mut<F>(&mut self, phys_range: Range<PhysRowIndex>, mut func: F)
     where
         F: FnMut(&mut [&mut Line]),
     {
-        let (first, second) = self.lines.as_mut_slices();
-        let first_len = first.len();
-        let first_range = 0..first.len();
-        let second_range = first.len()..first.len() + second.len();
-        let first_range = phys_intersection(&first_range, &phys_range);
-        let second_range = phys_intersection(&second_range, &phys_range);
-
-        let mut lines: Vec<&mut Line> = Vec::with_capacity(phys_range.end - phys_range.start);
-        for line in &mut first[first_range] {
-            lines.push(line);
-        }
-        for line in &mut second[second_range.start.saturating_sub(first_len)
-            ..second_range.end.saturating_sub(first_len)]
-        {
-            lines.push(line);
-        }
-        func(&mut lines)
+        func(&mut self.lines.range_mut(phys_range))
     }
 
-    pub fn for_each_phys_line<F>(&self, mut f: F)
+    pub fn for_each_phys_line<F>(&self, f: F)
     where
         F: FnMut(usize, &Line),
     {
-        for (idx, line) in self.lines.iter().enumerate() {
-            f(idx, line);
-        }
+        self.lines.for_each(f);
     }
 
     pub fn for_each_phys_line_mut<F>(&mut self, mut f: F)
@@ -988,6 +1392,28 @@ impl Screen {
         }
     }
 
+    /// Marks every line as changed as of `seqno`, without
+    /// decompressing the scrollback
+    pub fn make_all_lines_dirty(&mut self, seqno: SequenceNo) {
+        self.lines.make_all_dirty(seqno);
+    }
+
+    /// Removes the implicit hyperlinks from every line, so that they
+    /// will be scanned again, without decompressing the scrollback
+    pub fn invalidate_implicit_hyperlinks(&mut self, seqno: SequenceNo) {
+        self.lines.invalidate_implicit_hyperlinks(seqno);
+    }
+
+    /// Calls `f` with each of the semantic zones in the screen, in order,
+    /// along with the physical row that contains it.  Unlike
+    /// `for_each_phys_line_mut`, this avoids decompressing the scrollback.
+    pub fn for_each_phys_semantic_zone<F>(&mut self, f: F)
+    where
+        F: FnMut(PhysRowIndex, SemanticType, Range<u16>),
+    {
+        self.lines.for_each_semantic_zone(f);
+    }
+
     pub fn for_each_logical_line_in_stable_range_mut<F>(
         &mut self,
         stable_range: Range<StableRowIndex>,
@@ -1096,46 +1522,64 @@ impl Screen {
             phys_range.start -= 1
         }
 
+        // Lines are examined a window at a time, so that compressed
+        // scrollback is decompressed transiently rather than all
+        // being retained in memory when searching the scrollback.
+        // Rows beyond the window are included so that a logical line
+        // that starts within the window can be completed.
+        const WINDOW_ROWS: usize = 4096;
+
         let mut phys_row = phys_range.start;
-        let mut line_vec: Vec<&Line> = vec![];
         while phys_row < phys_range.end {
-            // Look forwards until we find the end of this logical line
-            let mut total_len = 0;
-            let mut end_inclusive = phys_row;
-            line_vec.clear();
-
-            for idx in phys_row.. {
-                if let Some(line) = self.lines.get(idx) {
-                    if total_len > 0 && total_len + line.len() > MAX_LOGICAL_LINE_LEN {
-                        break;
-                    }
-                    end_inclusive = idx;
-                    total_len += line.len();
-                    line_vec.push(line);
-                    if !line.last_cell_was_wrapped() {
-                        break;
+            let window_start = phys_row;
+            let window_end = (window_start + WINDOW_ROWS).min(phys_range.end);
+            let fetch = window_start..window_end + MAX_LOGICAL_LINE_LEN;
+
+            let continue_iteration = self.lines.with_range(fetch, |lines| {
+                let mut line_vec: Vec<&Line> = vec![];
+                while phys_row < window_end {
+                    // Look forwards until we find the end of this logical line
+                    let mut total_len = 0;
+                    let mut end_inclusive = phys_row;
+                    line_vec.clear();
+
+                    for idx in phys_row.. {
+                        if let Some(&line) = lines.get(idx - window_start) {
+                            if total_len > 0 && total_len + line.len() > MAX_LOGICAL_LINE_LEN {
+                                break;
+                            }
+                            end_inclusive = idx;
+                            total_len += line.len();
+                            line_vec.push(line);
+                            if !line.last_cell_was_wrapped() {
+                                break;
+                            }
+                        } else if idx == phys_row {
+                            // No more rows exist
+                            return false;
+                        } else {
+                            break;
+                        }
                     }
-                } else if idx == phys_row {
-                    // No more rows exist
-                    return;
-                } else {
-                    break;
-                }
-            }
 
-            let logical_stable_range = self.phys_to_stable_row_index(phys_row)
-                ..self.phys_to_stable_row_index(end_inclusive + 1);
+                    let logical_stable_range = self.phys_to_stable_row_index(phys_row)
+                        ..self.phys_to_stable_row_index(end_inclusive + 1);
 
-            phys_row = end_inclusive + 1;
+                    phys_row = end_inclusive + 1;
 
-            if logical_stable_range.end < stable_range.start {
-                continue;
-            }
-            if logical_stable_range.start > stable_range.end {
-                break;
-            }
+                    if logical_stable_range.end < stable_range.start {
+                        continue;
+                    }
+                    if logical_stable_range.start > stable_range.end {
+                        return false;
+                    }
 
-            let continue_iteration = f(logical_stable_range, &line_vec);
+                    if !f(logical_stable_range, &line_vec) {
+                        return false;
+                    }
+                }
+                true
+            });
 
             if !continue_iteration {
                 break;
@@ -1143,13 +1587,3 @@ impl Screen {
         }
     }
 }
-
-fn phys_intersection(r1: &Range<PhysRowIndex>, r2: &Range<PhysRowIndex>) -> Range<PhysRowIndex> {
-    let start = r1.start.max(r2.start);
-    let end = r1.end.min(r2.end);
-    if end > start {
-        start..end
-    } else {
-        0..0
-    }
-}
@@ -0,0 +1,878 @@
+//! Storage for the lines of a Screen.
+//!
+//! The most recent lines are held in a `VecDeque` so that they can be
+//! cheaply mutated.  When scrollback compression is enabled, lines that
+//! have scrolled sufficiently far back are grouped into blocks of
+//! `BLOCK_ROWS` lines and compressed.  A block is transparently
+//! decompressed when its lines are accessed, and the decompressed copy
+//! is discarded again the next time that more lines are compressed.
+//!
+//! When disk backed scrollback is enabled, compressed blocks beyond the
+//! in-memory scrollback size are written to a per-pane spill file.
+//! The blocks retain their position in that file, along with their
+//! semantic zones, so that the scrollback can be searched and retrieved
+//! a block at a time rather than loading all of it into memory.
+//!
+//! Changes that apply to every line, such as marking them all dirty
+//! when the palette changes, are made only to the lines that are held
+//! in memory; the lines of the other blocks are brought up to date as
+//! they are decompressed.
+use crate::PhysRowIndex;
+use anyhow::Context;
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::ops::{Index, IndexMut, Range};
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+use tempfile::NamedTempFile;
+use wezterm_cell::SemanticType;
+use wezterm_surface::line::Line;
+use wezterm_surface::{SequenceNo, SEQ_ZERO};
+
+/// The number of lines that are compressed together.  Compressing
+/// individual lines gains very little, as they are typically short.
+const BLOCK_ROWS: usize = 256;
+
+#[cfg(feature = "use_serde")]
+const COMPRESSION_LEVEL: i32 = 1;
+
+/// A semantic zone within a line of a compressed block, recorded
+/// when the block was compressed so that `get_semantic_zones` can
+/// be answered without decompressing the scrollback
+#[derive(Debug, Clone)]
+struct BlockZone {
+    row: u16,
+    semantic_type: SemanticType,
+    range: Range<u16>,
+}
+
+/// A temporary file holding the compressed blocks that have been
+/// spilled from memory.  The file is deleted when this is dropped,
+/// which happens when the pane that owns the scrollback is closed.
+pub struct DiskSpill {
+    inner: Mutex<SpillFile>,
+}
+
+struct SpillFile {
+    file: NamedTempFile,
+    /// Previously used extents that can be reused, ordered by offset
+    free: Vec<Range<u64>>,
+    /// The end of the used portion of the file
+    end: u64,
+}
+
+impl std::fmt::Debug for DiskSpill {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let inner = self.inner.lock().unwrap();
+        fmt.debug_struct("DiskSpill")
+            .field("path", &inner.file.path())
+            .field("end", &inner.end)
+            .finish()
+    }
+}
+
+impl DiskSpill {
+    /// Creates a spill file in `directory`, whose name starts with `prefix`
+    pub fn new(directory: &Path, prefix: &str) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(directory)
+            .with_context(|| format!("creating {}", directory.display()))?;
+        let file = tempfile::Builder::new()
+            .prefix(prefix)
+            .suffix(".scrollback")
+            .tempfile_in(directory)
+            .with_context(|| format!("creating spill file in {}", directory.display()))?;
+        Ok(Self {
+            inner: Mutex::new(SpillFile {
+                file,
+                free: vec![],
+                end: 0,
+            }),
+        })
+    }
+
+    /// Writes `data` to the file, returning the extent that holds it
+    fn write(&self, data: &[u8]) -> anyhow::Result<Range<u64>> {
+        let mut inner = self.inner.lock().unwrap();
+        let len = data.len() as u64;
+        let extent = match inner.free.iter().position(|r| r.end - r.start >= len) {
+            Some(idx) => {
+                let start = inner.free[idx].start;
+                inner.free[idx].start += len;
+                if inner.free[idx].is_empty() {
+                    inner.free.remove(idx);
+                }
+                start..start + len
+            }
+            None => {
+                let start = inner.end;
+                inner.end += len;
+                start..inner.end
+            }
+        };
+        let file = inner.file.as_file_mut();
+        let result = file
+            .seek(SeekFrom::Start(extent.start))
+            .and_then(|_| file.write_all(data));
+        if let Err(err) = result {
+            inner.release(extent);
+            return Err(err).context("writing scrollback spill file");
+        }
+        Ok(extent)
+    }
+
+    fn read(&self, extent: &Range<u64>) -> anyhow::Result<Vec<u8>> {
+        let mut inner = self.inner.lock().unwrap();
+        let file = inner.file.as_file_mut();
+        let mut data = vec![0u8; (extent.end - extent.start) as usize];
+        file.seek(SeekFrom::Start(extent.start))
+            .and_then(|_| file.read_exact(&mut data))
+            .context("reading scrollback spill file")?;
+        Ok(data)
+    }
+
+    fn release(&self, extent: Range<u64>) {
+        self.inner.lock().unwrap().release(extent);
+    }
+}
+
+impl SpillFile {
+    /// Marks `extent` as available for reuse, coalescing it with
+    /// any adjacent free extents
+    fn release(&mut self, extent: Range<u64>) {
+        if extent.is_empty() {
+            return;
+        }
+        let mut idx = self.free.partition_point(|r| r.start < extent.start);
+        self.free.insert(idx, extent);
+        if idx + 1 < self.free.len() && self.free[idx].end == self.free[idx + 1].start {
+            let next = self.free.remove(idx + 1);
+            self.free[idx].end = next.end;
+        }
+        if idx > 0 && self.free[idx - 1].end == self.free[idx].start {
+            let this = self.free.remove(idx);
+            self.free[idx - 1].end = this.end;
+            idx -= 1;
+        }
+
+        // Give back the space at the end of the file
+        if self.free[idx].end == self.end {
+            self.end = self.free.remove(idx).start;
+            if let Err(err) = self.file.as_file().set_len(self.end) {
+                log::trace!("failed to truncate scrollback spill file: {:#}", err);
+            }
+        }
+    }
+}
+
+/// Changes that were made to all of the lines in the store, which
+/// are applied to the lines of a compressed block when it is
+/// decompressed, rather than decompressing every block to make them
+#[derive(Debug, Default, Clone)]
+struct LineChanges {
+    /// Every line is considered changed as of this seqno
+    dirty_seqno: SequenceNo,
+    /// Incremented each time that the implicit hyperlinks are
+    /// invalidated
+    hyperlink_generation: usize,
+    /// The seqno at which they were most recently invalidated
+    hyperlink_seqno: SequenceNo,
+}
+
+impl LineChanges {
+    /// Brings `lines`, which were compressed when the implicit
+    /// hyperlinks were at `hyperlink_generation`, up to date
+    fn apply(&self, hyperlink_generation: usize, lines: &mut [Line]) {
+        for line in lines {
+            if hyperlink_generation != self.hyperlink_generation {
+                line.invalidate_implicit_hyperlinks(self.hyperlink_seqno);
+            }
+            line.update_last_change_seqno(self.dirty_seqno);
+        }
+    }
+}
+
+/// Where the compressed form of a block is held
+#[derive(Debug)]
+enum BlockData {
+    Memory(Box<[u8]>),
+    /// The extent of the spill file that holds the data
+    Disk(Range<u64>),
+}
+
+struct CompressedBlock {
+    /// The compressed form of the lines.  None if the lines
+    /// have been mutated since they were last compressed.
+    data: Option<BlockData>,
+    /// The semantic zones of the lines; only valid when
+    /// `data` is Some
+    zones: Box<[BlockZone]>,
+    /// The `LineChanges::hyperlink_generation` that the compressed
+    /// data reflects; only valid when `data` is Some
+    hyperlink_generation: usize,
+    /// The decompressed lines.  This is always populated
+    /// when `data` is None.
+    lines: OnceLock<Vec<Line>>,
+}
+
+impl std::fmt::Debug for CompressedBlock {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fmt.debug_struct("CompressedBlock")
+            .field("data", &self.data)
+            .field("decompressed", &self.lines.get().is_some())
+            .finish()
+    }
+}
+
+impl CompressedBlock {
+    fn new(lines: Vec<Line>) -> Self {
+        Self {
+            data: None,
+            zones: Box::new([]),
+            hyperlink_generation: 0,
+            lines: OnceLock::from(lines),
+        }
+    }
+
+    /// Returns the compressed data, reading it from the spill file
+    /// if necessary
+    fn load(&self, spill: Option<&DiskSpill>) -> anyhow::Result<Cow<'_, [u8]>> {
+        match self
+            .data
+            .as_ref()
+            .expect("lines are present when there is no compressed data")
+        {
+            BlockData::Memory(data) => Ok(Cow::Borrowed(data)),
+            BlockData::Disk(extent) => Ok(Cow::Owned(
+                spill
+                    .expect("spilled blocks have a spill file")
+                    .read(extent)?,
+            )),
+        }
+    }
+
+    fn decompress(&self, spill: Option<&DiskSpill>, changes: &LineChanges) -> Vec<Line> {
+        let mut lines = self
+            .load(spill)
+            .and_then(|data| decompress(&data))
+            .unwrap_or_else(|err| {
+                log::error!("failed to decompress scrollback: {:#}", err);
+                vec![Line::new(SEQ_ZERO); BLOCK_ROWS]
+            });
+        changes.apply(self.hyperlink_generation, &mut lines);
+        lines
+    }
+
+    fn lines(&self, spill: Option<&DiskSpill>, changes: &LineChanges) -> &[Line] {
+        self.lines.get_or_init(|| self.decompress(spill, changes))
+    }
+
+    /// Returns the lines for mutation; the compressed data is
+    /// discarded as it will no longer reflect the lines
+    fn lines_mut(&mut self, spill: Option<&DiskSpill>, changes: &LineChanges) -> &mut Vec<Line> {
+        self.lines(spill, changes);
+        self.discard_data(spill);
+        self.lines.get_mut().expect("lines were just populated")
+    }
+
+    /// Calls `func` with the lines, without retaining the
+    /// decompressed copy if it was not already present
+    fn with_lines<R>(
+        &self,
+        spill: Option<&DiskSpill>,
+        changes: &LineChanges,
+        func: impl FnOnce(&[Line]) -> R,
+    ) -> R {
+        match self.lines.get() {
+            Some(lines) => func(lines),
+            None => func(self.decompress(spill, changes).as_slice()),
+        }
+    }
+
+    fn into_lines(mut self, spill: Option<&DiskSpill>, changes: &LineChanges) -> Vec<Line> {
+        let lines = match self.lines.take() {
+            Some(lines) => lines,
+            None => self.decompress(spill, changes),
+        };
+        self.discard_data(spill);
+        lines
+    }
+
+    /// Discards the compressed data, releasing its space
+    /// in the spill file
+    fn discard_data(&mut self, spill: Option<&DiskSpill>) {
+        if let Some(BlockData::Disk(extent)) = self.data.take() {
+            if let Some(spill) = spill {
+                spill.release(extent);
+            }
+        }
+    }
+
+    /// Returns a copy of the block that holds its compressed
+    /// data in memory rather than in the spill file
+    fn clone_in_memory(&self, spill: Option<&DiskSpill>) -> Self {
+        let data = match &self.data {
+            None => None,
+            Some(BlockData::Memory(data)) => Some(BlockData::Memory(data.clone())),
+            Some(BlockData::Disk(_)) => match self.load(spill) {
+                Ok(data) => Some(BlockData::Memory(data.into_owned().into_boxed_slice())),
+                Err(err) => {
+                    log::error!("failed to read scrollback: {:#}", err);
+                    return Self::new(vec![Line::new(SEQ_ZERO); BLOCK_ROWS]);
+                }
+            },
+        };
+        Self {
+            data,
+            zones: self.zones.clone(),
+            hyperlink_generation: self.hyperlink_generation,
+            lines: self.lines.clone(),
+        }
+    }
+
+    /// Compresses the lines if they were mutated, writes the
+    /// compressed data to `spill` if provided, then releases
+    /// the decompressed copy
+    fn evict(&mut self, spill: Option<&DiskSpill>, changes: &LineChanges) {
+        if self.data.is_none() {
+            let lines = self.lines.get_mut().expect("lines are present");
+            let mut zones = vec![];
+            for (row, line) in lines.iter_mut().enumerate() {
+                for zone in line.semantic_zone_ranges() {
+                    zones.push(BlockZone {
+                        row: row as u16,
+                        semantic_type: zone.semantic_type,
+                        range: zone.range.clone(),
+                    });
+                }
+            }
+            match compress(lines) {
+                Ok(data) => {
+                    self.data.replace(BlockData::Memory(data));
+                    self.zones = zones.into_boxed_slice();
+                    self.hyperlink_generation = changes.hyperlink_generation;
+                }
+                Err(err) => {
+                    log::trace!("not compressing scrollback: {:#}", err);
+                    return;
+                }
+            }
+        }
+        if let (Some(spill), Some(BlockData::Memory(data))) = (spill, &self.data) {
+            match spill.write(data) {
+                Ok(extent) => {
+                    self.data.replace(BlockData::Disk(extent));
+                }
+                Err(err) => {
+                    log::error!("failed to spill scrollback to disk: {:#}", err);
+                }
+            }
+        }
+        self.lines.take();
+    }
+}
+
+#[cfg(feature = "use_serde")]
+fn compress(lines: &[Line]) -> anyhow::Result<Box<[u8]>> {
+    let mut compressed = Vec::new();
+    let mut compress = zstd::Encoder::new(&mut compressed, COMPRESSION_LEVEL)?;
+    let mut encode = varbincode::Serializer::new(&mut compress);
+    serde::Serialize::serialize(lines, &mut encode)?;
+    drop(encode);
+    compress.finish()?;
+    Ok(compressed.into_boxed_slice())
+}
+
+#[cfg(feature = "use_serde")]
+fn decompress(data: &[u8]) -> anyhow::Result<Vec<Line>> {
+    let mut decompress = zstd::Decoder::new(data)?;
+    let mut decode = varbincode::Deserializer::new(&mut decompress);
+    Ok(serde::Deserialize::deserialize(&mut decode)?)
+}
+
+#[cfg(not(feature = "use_serde"))]
+fn compress(_lines: &[Line]) -> anyhow::Result<Box<[u8]>> {
+    anyhow::bail!("scrollback compression requires the use_serde feature")
+}
+
+#[cfg(not(feature = "use_serde"))]
+fn decompress(_data: &[u8]) -> anyhow::Result<Vec<Line>> {
+    anyhow::bail!("scrollback compression requires the use_serde feature")
+}
+
+fn phys_intersection(r1: &Range<PhysRowIndex>, r2: &Range<PhysRowIndex>) -> Range<PhysRowIndex> {
+    let start = r1.start.max(r2.start);
+    let end = r1.end.min(r2.end);
+    if end > start {
+        start..end
+    } else {
+        0..0
+    }
+}
+
+/// Holds the lines of a Screen, indexed by PhysRowIndex.
+/// The oldest lines may be held in compressed blocks, with the
+/// remaining lines held in `lines`.
+#[derive(Debug, Default)]
+pub(crate) struct LineStore {
+    blocks: VecDeque<CompressedBlock>,
+    /// The number of lines at the start of the first block that
+    /// have been removed from the scrollback.  Every other block
+    /// holds exactly BLOCK_ROWS lines.
+    front_skip: usize,
+    lines: VecDeque<Line>,
+    /// Where blocks are spilled when disk backed scrollback is enabled
+    spill: Option<Arc<DiskSpill>>,
+    changes: LineChanges,
+}
+
+impl From<VecDeque<Line>> for LineStore {
+    fn from(lines: VecDeque<Line>) -> Self {
+        Self {
+            blocks: VecDeque::new(),
+            front_skip: 0,
+            lines,
+            spill: None,
+            changes: LineChanges::default(),
+        }
+    }
+}
+
+impl Clone for LineStore {
+    /// The spill file belongs to the original store, so the
+    /// clone holds its copy of any spilled blocks in memory
+    fn clone(&self) -> Self {
+        let spill = self.spill.as_deref();
+        Self {
+            blocks: self
+                .blocks
+                .iter()
+                .map(|block| block.clone_in_memory(spill))
+                .collect(),
+            front_skip: self.front_skip,
+            lines: self.lines.clone(),
+            spill: None,
+            changes: self.changes.clone(),
+        }
+    }
+}
+
+impl LineStore {
+    pub fn with_capacity(capacity: usize) -> Self {
+        VecDeque::with_capacity(capacity).into()
+    }
+
+    /// Spills compressed blocks to `spill` when they fall outside
+    /// of the in-memory portion of the scrollback
+    pub fn set_spill(&mut self, spill: Arc<DiskSpill>) {
+        let prior = self.spill.take();
+        for block in self.blocks.iter_mut() {
+            if let Some(BlockData::Disk(_)) = &block.data {
+                *block = block.clone_in_memory(prior.as_deref());
+            }
+        }
+        self.spill.replace(spill);
+    }
+
+    pub fn is_disk_backed(&self) -> bool {
+        self.spill.is_some()
+    }
+
+    fn compressed_len(&self) -> usize {
+        self.blocks.len() * BLOCK_ROWS - self.front_skip
+    }
+
+    pub fn len(&self) -> usize {
+        self.compressed_len() + self.lines.len()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.compressed_len() + self.lines.capacity()
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        self.lines.reserve(additional);
+    }
+
+    /// Returns the range of PhysRowIndex covered by the block at `idx`
+    fn block_range(&self, idx: usize) -> Range<PhysRowIndex> {
+        (idx * BLOCK_ROWS).saturating_sub(self.front_skip)..(idx + 1) * BLOCK_ROWS - self.front_skip
+    }
+
+    /// Returns the index of the block containing the row,
+    /// and the index of the row within that block
+    fn locate(&self, idx: PhysRowIndex) -> (usize, usize) {
+        let idx = idx + self.front_skip;
+        (idx / BLOCK_ROWS, idx % BLOCK_ROWS)
+    }
+
+    /// Returns the blocks that intersect `range`, along with
+    /// the range of rows within each block
+    fn blocks_in_range(&self, range: &Range<PhysRowIndex>) -> Vec<(usize, Range<usize>)> {
+        let mut result = vec![];
+        if range.start >= self.compressed_len() {
+            return result;
+        }
+        let first = self.locate(range.start).0;
+        for idx in first..self.blocks.len() {
+            let rows = phys_intersection(&self.block_range(idx), range);
+            if rows.is_empty() {
+                break;
+            }
+            let start = rows.start + self.front_skip - idx * BLOCK_ROWS;
+            result.push((idx, start..start + rows.len()));
+        }
+        result
+    }
+
+    /// Returns the range of `lines` that intersects `range`
+    fn lines_in_range(&self, range: &Range<PhysRowIndex>) -> Range<usize> {
+        let compressed_len = self.compressed_len();
+        let rows = phys_intersection(&(compressed_len..self.len()), range);
+        if rows.is_empty() {
+            0..0
+        } else {
+            rows.start - compressed_len..rows.end - compressed_len
+        }
+    }
+
+    pub fn get(&self, idx: PhysRowIndex) -> Option<&Line> {
+        let compressed_len = self.compressed_len();
+        if idx >= compressed_len {
+            self.lines.get(idx - compressed_len)
+        } else {
+            let (block, row) = self.locate(idx);
+            self.blocks[block]
+                .lines(self.spill.as_deref(), &self.changes)
+                .get(row)
+        }
+    }
+
+    pub fn get_mut(&mut self, idx: PhysRowIndex) -> Option<&mut Line> {
+        let compressed_len = self.compressed_len();
+        if idx >= compressed_len {
+            self.lines.get_mut(idx - compressed_len)
+        } else {
+            let (block, row) = self.locate(idx);
+            self.blocks[block]
+                .lines_mut(self.spill.as_deref(), &self.changes)
+                .get_mut(row)
+        }
+    }
+
+    pub fn back(&self) -> Option<&Line> {
+        self.get(self.len().checked_sub(1)?)
+    }
+
+    pub fn push_back(&mut self, line: Line) {
+        self.lines.push_back(line);
+    }
+
+    pub fn pop_back(&mut self) -> Option<Line> {
+        if self.lines.is_empty() {
+            self.decompress_all();
+        }
+        self.lines.pop_back()
+    }
+
+    pub fn insert(&mut self, idx: PhysRowIndex, line: Line) {
+        let compressed_len = self.compressed_len();
+        if idx >= compressed_len {
+            self.lines.insert(idx - compressed_len, line);
+        } else {
+            self.decompress_all();
+            self.lines.insert(idx, line);
+        }
+    }
+
+    pub fn remove(&mut self, idx: PhysRowIndex) -> Option<Line> {
+        let compressed_len = self.compressed_len();
+        if idx >= compressed_len {
+            self.lines.remove(idx - compressed_len)
+        } else if idx == 0 {
+            let line =
+                self.blocks[0].lines(self.spill.as_deref(), &self.changes)[self.front_skip].clone();
+            self.remove_front(1);
+            Some(line)
+        } else {
+            self.decompress_all();
+            self.lines.remove(idx)
+        }
+    }
+
+    /// Removes the first `num_rows` lines, without decompressing them
+    pub fn remove_front(&mut self, mut num_rows: usize) {
+        while num_rows > 0 && !self.blocks.is_empty() {
+            let available = BLOCK_ROWS - self.front_skip;
+            if num_rows >= available {
+                if let Some(mut block) = self.blocks.pop_front() {
+                    block.discard_data(self.spill.as_deref());
+                }
+                self.front_skip = 0;
+                num_rows -= available;
+            } else {
+                self.front_skip += num_rows;
+                num_rows = 0;
+            }
+        }
+        self.lines.drain(..num_rows.min(self.lines.len()));
+    }
+
+    /// Replaces the lines in `range` with those returned by `func`,
+    /// which is passed the lines that are being replaced.  Only the
+    /// compressed blocks that intersect `range`, and those after it,
+    /// are decompressed.
+    pub fn splice<F>(&mut self, range: Range<PhysRowIndex>, func: F)
+    where
+        F: FnOnce(std::collections::vec_deque::Drain<'_, Line>) -> VecDeque<Line>,
+    {
+        self.decompress_from(range.start);
+        let compressed_len = self.compressed_len();
+        let range = range.start - compressed_len..range.end - compressed_len;
+        let tail = self.lines.split_off(range.end);
+        let replacement = func(self.lines.drain(range.start..));
+        self.lines.extend(replacement);
+        self.lines.extend(tail);
+    }
+
+    /// Moves the lines from the compressed blocks back into `lines`
+    fn decompress_all(&mut self) {
+        self.decompress_from(0);
+    }
+
+    /// Moves the lines from the block containing `idx`, and from
+    /// all of the blocks after it, back into `lines`
+    fn decompress_from(&mut self, idx: PhysRowIndex) {
+        if idx >= self.compressed_len() {
+            return;
+        }
+        let first = self.locate(idx).0;
+        let mut lines = VecDeque::with_capacity(self.len() - idx);
+        let spill = self.spill.as_deref();
+        let changes = &self.changes;
+        for (idx, block) in self.blocks.drain(first..).enumerate() {
+            let skip = if first + idx == 0 { self.front_skip } else { 0 };
+            lines.extend(block.into_lines(spill, changes).into_iter().skip(skip));
+        }
+        lines.append(&mut self.lines);
+        self.lines = lines;
+        if self.blocks.is_empty() {
+            self.front_skip = 0;
+        }
+    }
+
+    /// Compresses lines into blocks, retaining at least `keep_rows`
+    /// lines in their uncompressed form.  When any lines are compressed,
+    /// the decompressed copies of previously accessed blocks are released.
+    /// If there is a spill file, blocks that are older than the most
+    /// recent `memory_rows` lines are written to it.
+    pub fn compress(&mut self, keep_rows: usize, memory_rows: usize) {
+        let mut compressed_any = false;
+        while self.lines.len() >= keep_rows + 2 * BLOCK_ROWS {
+            let lines: Vec<Line> = self.lines.drain(..BLOCK_ROWS).collect();
+            self.blocks.push_back(CompressedBlock::new(lines));
+            compressed_any = true;
+        }
+        if compressed_any {
+            let spill = self.spill.as_deref();
+            let num_spilled = self
+                .blocks
+                .len()
+                .saturating_sub(memory_rows.saturating_sub(self.lines.len()) / BLOCK_ROWS);
+            for (idx, block) in self.blocks.iter_mut().enumerate() {
+                block.evict(if idx < num_spilled { spill } else { None }, &self.changes);
+            }
+        }
+    }
+
+    #[cfg(test)]
+    pub fn iter(&self) -> impl Iterator<Item = &Line> {
+        let front_skip = self.front_skip;
+        let spill = self.spill.as_deref();
+        let changes = &self.changes;
+        self.blocks
+            .iter()
+            .enumerate()
+            .flat_map(move |(idx, block)| {
+                let skip = if idx == 0 { front_skip } else { 0 };
+                block.lines(spill, changes)[skip..].iter()
+            })
+            .chain(self.lines.iter())
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Line> {
+        let front_skip = self.front_skip;
+        let spill = self.spill.as_deref();
+        let changes = &self.changes;
+        self.blocks
+            .iter_mut()
+            .enumerate()
+            .flat_map(move |(idx, block)| {
+                let skip = if idx == 0 { front_skip } else { 0 };
+                block.lines_mut(spill, changes)[skip..].iter_mut()
+            })
+            .chain(self.lines.iter_mut())
+    }
+
+    /// Calls `func` for each of the lines that are held in memory.
+    /// The compressed data of the blocks is left as it is.
+    fn for_each_decompressed_mut(&mut self, mut func: impl FnMut(&mut Line)) {
+        for block in self.blocks.iter_mut() {
+            if let Some(lines) = block.lines.get_mut() {
+                lines.iter_mut().for_each(&mut func);
+            }
+        }
+        self.lines.iter_mut().for_each(func);
+    }
+
+    /// Marks every line as changed as of `seqno`.  The lines of
+    /// compressed blocks are marked when they are decompressed, so
+    /// that this doesn't decompress, or read back from the spill
+    /// file, the whole of the scrollback.
+    pub fn make_all_dirty(&mut self, seqno: SequenceNo) {
+        self.changes.dirty_seqno = self.changes.dirty_seqno.max(seqno);
+        self.for_each_decompressed_mut(|line| line.update_last_change_seqno(seqno));
+    }
+
+    /// Invalidates the implicit hyperlinks of every line.  As with
+    /// `make_all_dirty`, the lines of compressed blocks are updated
+    /// when they are decompressed.
+    pub fn invalidate_implicit_hyperlinks(&mut self, seqno: SequenceNo) {
+        self.changes.hyperlink_generation += 1;
+        self.changes.hyperlink_seqno = seqno;
+        self.for_each_decompressed_mut(|line| line.invalidate_implicit_hyperlinks(seqno));
+    }
+
+    /// Calls `func` for each line, without retaining decompressed
+    /// copies of the compressed blocks
+    pub fn for_each<F>(&self, mut func: F)
+    where
+        F: FnMut(PhysRowIndex, &Line),
+    {
+        for (idx, block) in self.blocks.iter().enumerate() {
+            let range = self.block_range(idx);
+            block.with_lines(self.spill.as_deref(), &self.changes, |lines| {
+                for (phys, line) in range.clone().zip(&lines[BLOCK_ROWS - range.len()..]) {
+                    func(phys, line);
+                }
+            });
+        }
+        let compressed_len = self.compressed_len();
+        for (idx, line) in self.lines.iter().enumerate() {
+            func(compressed_len + idx, line);
+        }
+    }
+
+    /// Calls `func` for each semantic zone, in order.  Compressed
+    /// blocks use the zones that were recorded when they were
+    /// compressed, so that they need not be decompressed.
+    pub fn for_each_semantic_zone<F>(&mut self, mut func: F)
+    where
+        F: FnMut(PhysRowIndex, SemanticType, Range<u16>),
+    {
+        let front_skip = self.front_skip;
+        let spill = self.spill.as_deref();
+        let changes = &self.changes;
+        for (idx, block) in self.blocks.iter_mut().enumerate() {
+            let skip = if idx == 0 { front_skip } else { 0 };
+            let first = (idx * BLOCK_ROWS) as isize - front_skip as isize;
+            if block.data.is_some() {
+                for zone in block.zones.iter().filter(|z| z.row as usize >= skip) {
+                    let phys = (first + zone.row as isize) as PhysRowIndex;
+                    func(phys, zone.semantic_type, zone.range.clone());
+                }
+            } else {
+                for (row, line) in block
+                    .lines_mut(spill, changes)
+                    .iter_mut()
+                    .enumerate()
+                    .skip(skip)
+                {
+                    let phys = (first + row as isize) as PhysRowIndex;
+                    for zone in line.semantic_zone_ranges() {
+                        func(phys, zone.semantic_type, zone.range.clone());
+                    }
+                }
+            }
+        }
+        let compressed_len = self.compressed_len();
+        for (idx, line) in self.lines.iter_mut().enumerate() {
+            for zone in line.semantic_zone_ranges() {
+                func(compressed_len + idx, zone.semantic_type, zone.range.clone());
+            }
+        }
+    }
+
+    /// Returns references to the lines in `range`; rows
+    /// outside of the store are ignored
+    pub fn range(&self, range: Range<PhysRowIndex>) -> Vec<&Line> {
+        let mut result = Vec::with_capacity(range.end.saturating_sub(range.start));
+        let spill = self.spill.as_deref();
+        for (idx, rows) in self.blocks_in_range(&range) {
+            result.extend(self.blocks[idx].lines(spill, &self.changes)[rows].iter());
+        }
+        result.extend(self.lines.range(self.lines_in_range(&range)));
+        result
+    }
+
+    /// Returns mutable references to the lines in `range`; rows
+    /// outside of the store are ignored
+    pub fn range_mut(&mut self, range: Range<PhysRowIndex>) -> Vec<&mut Line> {
+        let mut result = Vec::with_capacity(range.end.saturating_sub(range.start));
+        let block_rows = self.blocks_in_range(&range);
+        let line_rows = self.lines_in_range(&range);
+        let spill = self.spill.as_deref();
+        let changes = &self.changes;
+        // The intersecting blocks are contiguous
+        let first = block_rows.first().map(|(idx, _)| *idx).unwrap_or(0);
+        for (block, (_, rows)) in self.blocks.range_mut(first..).zip(block_rows) {
+            result.extend(block.lines_mut(spill, changes)[rows].iter_mut());
+        }
+        result.extend(self.lines.range_mut(line_rows));
+        result
+    }
+
+    /// Calls `func` with references to the lines in `range`; rows
+    /// outside of the store are ignored.  Unlike `range`, compressed
+    /// blocks are not retained in their decompressed form, so this
+    /// is suitable for scanning large portions of the scrollback.
+    pub fn with_range<R>(&self, range: Range<PhysRowIndex>, func: impl FnOnce(&[&Line]) -> R) -> R {
+        let spill = self.spill.as_deref();
+        let block_rows = self.blocks_in_range(&range);
+        let decompressed: Vec<Option<Vec<Line>>> = block_rows
+            .iter()
+            .map(|(idx, _)| {
+                let block = &self.blocks[*idx];
+                match block.lines.get() {
+                    Some(_) => None,
+                    None => Some(block.decompress(spill, &self.changes)),
+                }
+            })
+            .collect();
+
+        let mut result = Vec::with_capacity(range.end.saturating_sub(range.start));
+        for ((idx, rows), lines) in block_rows.into_iter().zip(decompressed.iter()) {
+            let lines = match lines {
+                Some(lines) => lines.as_slice(),
+                None => self.blocks[idx].lines(spill, &self.changes),
+            };
+            result.extend(lines[rows].iter());
+        }
+        result.extend(self.lines.range(self.lines_in_range(&range)));
+        func(&result)
+    }
+}
+
+impl Index<PhysRowIndex> for LineStore {
+    type Output = Line;
+
+    fn index(&self, idx: PhysRowIndex) -> &Line {
+        self.get(idx).expect("PhysRowIndex out of range")
+    }
+}
+
+impl IndexMut<PhysRowIndex> for LineStore {
+    fn index_mut(&mut self, idx: PhysRowIndex) -> &mut Line {
+        self.get_mut(idx).expect("PhysRowIndex out of range")
+    }
+}
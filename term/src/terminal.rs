@@ -16,6 +16,14 @@ pub trait Clipboard: Send + Sync {
         selection: ClipboardSelection,
         data: Option<String>,
     ) -> anyhow::Result<()>;
+
+    /// Called when the application requests the contents of the
+    /// clipboard via OSC 52.  If the request is granted, the contents
+    /// are sent to the application by calling
+    /// `TerminalState::send_clipboard_contents`.
+    fn query_contents(&self, _selection: ClipboardSelection) -> anyhow::Result<()> {
+        Ok(())
+    }
 }
 
 impl Clipboard for Box<dyn Clipboard> {
@@ -26,6 +34,10 @@ impl Clipboard for Box<dyn Clipboard> {
     ) -> anyhow::Result<()> {
         self.as_ref().set_contents(selection, data)
     }
+
+    fn query_contents(&self, selection: ClipboardSelection) -> anyhow::Result<()> {
+        self.as_ref().query_contents(selection)
+    }
 }
 
 pub trait DeviceControlHandler: Send + Sync {
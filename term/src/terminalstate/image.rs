@@ -4,7 +4,7 @@ use humansize::{SizeFormatter, DECIMAL};
 use num_traits::{One, Zero};
 use ordered_float::NotNan;
 use std::sync::Arc;
-use wezterm_cell::image::{ImageCell, ImageDataType};
+use wezterm_cell::image::{DisplayCells, ImageCell, ImageDataType, ImageProtocol};
 use wezterm_cell::Cell;
 use wezterm_surface::change::ImageData;
 use wezterm_surface::TextureCoordinate;
@@ -204,6 +204,15 @@ impl TerminalState {
                     padding_bottom,
                     params.image_id,
                     params.placement_id,
+                    match params.style {
+                        ImageAttachStyle::Sixel => ImageProtocol::Sixel,
+                        ImageAttachStyle::Iterm => ImageProtocol::Iterm,
+                        ImageAttachStyle::Kitty => ImageProtocol::Kitty,
+                    },
+                    DisplayCells::new(
+                        x_delta_divisor as f32 / cell_pixel_width as f32,
+                        y_delta_divisor as f32 / cell_pixel_height as f32,
+                    ),
                 ));
                 match params.style {
                     ImageAttachStyle::Kitty => cell.attrs_mut().attach_image(img),
@@ -51,6 +51,7 @@ impl TerminalState {
         } else {
             log::trace!("{}: sending {:?}, {:?} {:?}", label, to_send, key, mods);
         }
+        self.interrupt_chunked_paste()?;
         self.writer.write_all(to_send.as_bytes())?;
         self.writer.flush()?;
 
@@ -5,17 +5,173 @@ use ::image::{
     DynamicImage, GenericImage, GenericImageView, ImageBuffer, RgbImage, Rgba, RgbaImage,
 };
 use anyhow::Context;
+use ordered_float::NotNan;
 use std::collections::{HashMap, HashSet};
 use std::io::Write;
 use std::sync::Arc;
 use std::time::Duration;
-use wezterm_cell::image::ImageDataType;
+use wezterm_cell::color::ColorAttribute;
+use wezterm_cell::image::{
+    DisplayCells, ImageAnimation, ImageAnimationState, ImageCell, ImageDataType, ImageProtocol,
+};
+use wezterm_cell::CellAttributes;
 use wezterm_escape_parser::apc::{
-    KittyFrameCompositionMode, KittyImage, KittyImageCompression, KittyImageData, KittyImageDelete,
-    KittyImageFormat, KittyImageFrame, KittyImageFrameCompose, KittyImagePlacement,
-    KittyImageTransmit, KittyImageVerbosity,
+    KittyAnimationState, KittyFrameCompositionMode, KittyImage, KittyImageAnimation,
+    KittyImageCompression, KittyImageData, KittyImageDelete, KittyImageFormat, KittyImageFrame,
+    KittyImageFrameCompose, KittyImagePlacement, KittyImageTransmit, KittyImageVerbosity,
 };
 use wezterm_surface::change::ImageData;
+use wezterm_surface::TextureCoordinate;
+
+/// The character used to mark the cells that display a virtual placement
+pub(crate) const KITTY_PLACEHOLDER: char = '\u{10EEEE}';
+
+/// The combining characters used to encode the row and column of a
+/// Unicode placeholder cell; the index into this table is the value.
+/// <https://sw.kovidgoyal.net/kitty/graphics-protocol/#unicode-placeholders>
+const PLACEHOLDER_DIACRITICS: [u32; 297] = [
+    0x0305, 0x030D, 0x030E, 0x0310, 0x0312, 0x033D, 0x033E, 0x033F, 0x0346, 0x034A, 0x034B, 0x034C,
+    0x0350, 0x0351, 0x0352, 0x0357, 0x035B, 0x0363, 0x0364, 0x0365, 0x0366, 0x0367, 0x0368, 0x0369,
+    0x036A, 0x036B, 0x036C, 0x036D, 0x036E, 0x036F, 0x0483, 0x0484, 0x0485, 0x0486, 0x0487, 0x0592,
+    0x0593, 0x0594, 0x0595, 0x0597, 0x0598, 0x0599, 0x059C, 0x059D, 0x059E, 0x059F, 0x05A0, 0x05A1,
+    0x05A8, 0x05A9, 0x05AB, 0x05AC, 0x05AF, 0x05C4, 0x0610, 0x0611, 0x0612, 0x0613, 0x0614, 0x0615,
+    0x0616, 0x0617, 0x0657, 0x0658, 0x0659, 0x065A, 0x065B, 0x065D, 0x065E, 0x06D6, 0x06D7, 0x06D8,
+    0x06D9, 0x06DA, 0x06DB, 0x06DC, 0x06DF, 0x06E0, 0x06E1, 0x06E2, 0x06E4, 0x06E7, 0x06E8, 0x06EB,
+    0x06EC, 0x0730, 0x0732, 0x0733, 0x0735, 0x0736, 0x073A, 0x073D, 0x073F, 0x0740, 0x0741, 0x0743,
+    0x0745, 0x0747, 0x0749, 0x074A, 0x07EB, 0x07EC, 0x07ED, 0x07EE, 0x07EF, 0x07F0, 0x07F1, 0x07F3,
+    0x0816, 0x0817, 0x0818, 0x0819, 0x081B, 0x081C, 0x081D, 0x081E, 0x081F, 0x0820, 0x0821, 0x0822,
+    0x0823, 0x0825, 0x0826, 0x0827, 0x0829, 0x082A, 0x082B, 0x082C, 0x082D, 0x0951, 0x0953, 0x0954,
+    0x0F82, 0x0F83, 0x0F86, 0x0F87, 0x135D, 0x135E, 0x135F, 0x17DD, 0x193A, 0x1A17, 0x1A75, 0x1A76,
+    0x1A77, 0x1A78, 0x1A79, 0x1A7A, 0x1A7B, 0x1A7C, 0x1B6B, 0x1B6D, 0x1B6E, 0x1B6F, 0x1B70, 0x1B71,
+    0x1B72, 0x1B73, 0x1CD0, 0x1CD1, 0x1CD2, 0x1CDA, 0x1CDB, 0x1CE0, 0x1DC0, 0x1DC1, 0x1DC3, 0x1DC4,
+    0x1DC5, 0x1DC6, 0x1DC7, 0x1DC8, 0x1DC9, 0x1DCB, 0x1DCC, 0x1DD1, 0x1DD2, 0x1DD3, 0x1DD4, 0x1DD5,
+    0x1DD6, 0x1DD7, 0x1DD8, 0x1DD9, 0x1DDA, 0x1DDB, 0x1DDC, 0x1DDD, 0x1DDE, 0x1DDF, 0x1DE0, 0x1DE1,
+    0x1DE2, 0x1DE3, 0x1DE4, 0x1DE5, 0x1DE6, 0x1DFE, 0x20D0, 0x20D1, 0x20D4, 0x20D5, 0x20D6, 0x20D7,
+    0x20DB, 0x20DC, 0x20E1, 0x20E7, 0x20E9, 0x20F0, 0x2CEF, 0x2CF0, 0x2CF1, 0x2DE0, 0x2DE1, 0x2DE2,
+    0x2DE3, 0x2DE4, 0x2DE5, 0x2DE6, 0x2DE7, 0x2DE8, 0x2DE9, 0x2DEA, 0x2DEB, 0x2DEC, 0x2DED, 0x2DEE,
+    0x2DEF, 0x2DF0, 0x2DF1, 0x2DF2, 0x2DF3, 0x2DF4, 0x2DF5, 0x2DF6, 0x2DF7, 0x2DF8, 0x2DF9, 0x2DFA,
+    0x2DFB, 0x2DFC, 0x2DFD, 0x2DFE, 0x2DFF, 0xA66F, 0xA67C, 0xA67D, 0xA6F0, 0xA6F1, 0xA8E0, 0xA8E1,
+    0xA8E2, 0xA8E3, 0xA8E4, 0xA8E5, 0xA8E6, 0xA8E7, 0xA8E8, 0xA8E9, 0xA8EA, 0xA8EB, 0xA8EC, 0xA8ED,
+    0xA8EE, 0xA8EF, 0xA8F0, 0xA8F1, 0xAAB0, 0xAAB2, 0xAAB3, 0xAAB7, 0xAAB8, 0xAABE, 0xAABF, 0xAAC1,
+    0xFE20, 0xFE21, 0xFE22, 0xFE23, 0xFE24, 0xFE25, 0xFE26, 0x10A0F, 0x10A38, 0x1D185, 0x1D186,
+    0x1D187, 0x1D188, 0x1D189, 0x1D1AA, 0x1D1AB, 0x1D1AC, 0x1D1AD, 0x1D242, 0x1D243, 0x1D244,
+];
+
+fn placeholder_diacritic(c: char) -> Option<u32> {
+    PLACEHOLDER_DIACRITICS
+        .binary_search(&(c as u32))
+        .ok()
+        .map(|idx| idx as u32)
+}
+
+/// Placeholder cells encode the image id in the foreground color
+/// and the placement id in the underline color
+fn placeholder_color_id(color: ColorAttribute) -> Option<u32> {
+    match color {
+        ColorAttribute::TrueColorWithPaletteFallback(c, _)
+        | ColorAttribute::TrueColorWithDefaultFallback(c) => {
+            let (r, g, b, _) = c.to_tuple_rgba();
+            let byte = |v: f32| (v * 255.0).round() as u32;
+            Some((byte(r) << 16) | (byte(g) << 8) | byte(b))
+        }
+        ColorAttribute::PaletteIndex(idx) => Some(idx as u32),
+        ColorAttribute::Default => None,
+    }
+}
+
+/// A placement made with U=1.  Rather than being assigned to cells
+/// when it is placed, it is displayed by the cells into which the
+/// application prints placeholder characters.
+#[derive(Debug)]
+struct VirtualPlacement {
+    columns: u32,
+    rows: u32,
+    source_x: u32,
+    source_y: u32,
+    source_width: u32,
+    source_height: u32,
+    z_index: i32,
+    /// The rows into which placeholders for this placement have
+    /// been printed, so that they can be cleared on deletion
+    info: Option<PlacementInfo>,
+}
+
+impl VirtualPlacement {
+    /// Computes the portion of the image that is displayed by the
+    /// placeholder cell at row, col.  The image is scaled to fit the
+    /// placement, preserving its aspect ratio, and centered within it.
+    #[allow(clippy::too_many_arguments)]
+    fn image_cell(
+        &self,
+        row: u32,
+        col: u32,
+        cell_width: f32,
+        cell_height: f32,
+        image_width: u32,
+        image_height: u32,
+        data: Arc<ImageData>,
+        image_id: u32,
+        placement_id: Option<u32>,
+    ) -> Option<ImageCell> {
+        if row >= self.rows || col >= self.columns {
+            return None;
+        }
+        let source_width = self.source_width as f32;
+        let source_height = self.source_height as f32;
+        let box_width = self.columns as f32 * cell_width;
+        let box_height = self.rows as f32 * cell_height;
+        let scale = (box_width / source_width).min(box_height / source_height);
+        let origin_x = (box_width - source_width * scale) / 2.;
+        let origin_y = (box_height - source_height * scale) / 2.;
+
+        let cell_left = col as f32 * cell_width;
+        let cell_top = row as f32 * cell_height;
+        let left = cell_left.max(origin_x);
+        let right = (cell_left + cell_width).min(origin_x + source_width * scale);
+        let top = cell_top.max(origin_y);
+        let bottom = (cell_top + cell_height).min(origin_y + source_height * scale);
+        if right <= left || bottom <= top {
+            return None;
+        }
+
+        let tex_x = |x: f32| {
+            NotNan::new((self.source_x as f32 + (x - origin_x) / scale) / image_width as f32).ok()
+        };
+        let tex_y = |y: f32| {
+            NotNan::new((self.source_y as f32 + (y - origin_y) / scale) / image_height as f32).ok()
+        };
+
+        Some(ImageCell::with_z_index(
+            TextureCoordinate::new(tex_x(left)?, tex_y(top)?),
+            TextureCoordinate::new(tex_x(right)?, tex_y(bottom)?),
+            data,
+            self.z_index,
+            (left - cell_left).round() as u16,
+            (top - cell_top).round() as u16,
+            (cell_left + cell_width - right).round() as u16,
+            (cell_top + cell_height - bottom).round() as u16,
+            Some(image_id),
+            placement_id,
+            ImageProtocol::Kitty,
+            DisplayCells::new(
+                image_width as f32 * scale / cell_width,
+                image_height as f32 * scale / cell_height,
+            ),
+        ))
+    }
+}
+
+/// The most recently printed placeholder cell, used to infer the
+/// row and column of placeholders that omit their diacritics
+#[derive(Debug, Clone, Copy)]
+struct PlaceholderCell {
+    x: usize,
+    y: StableRowIndex,
+    image_id: u32,
+    placement_id: Option<u32>,
+    row: u32,
+    col: u32,
+}
 
 #[derive(Debug, Default)]
 pub struct KittyImageState {
@@ -24,6 +180,8 @@ pub struct KittyImageState {
     number_to_id: HashMap<u32, u32>,
     id_to_data: HashMap<u32, Arc<ImageData>>,
     placements: HashMap<(u32, Option<u32>), PlacementInfo>,
+    virtual_placements: HashMap<(u32, Option<u32>), VirtualPlacement>,
+    last_placeholder: Option<PlaceholderCell>,
     used_memory: usize,
 }
 
@@ -46,7 +204,12 @@ impl KittyImageState {
     fn prune_unreferenced(&mut self) {
         let budget = 320 * 1024 * 1024; // FIXME: make this configurable
         if self.used_memory > budget {
-            let referenced: HashSet<u32> = self.placements.keys().map(|(k, _)| *k).collect();
+            let referenced: HashSet<u32> = self
+                .placements
+                .keys()
+                .chain(self.virtual_placements.keys())
+                .map(|(k, _)| *k)
+                .collect();
             let target = self.used_memory - budget;
             let mut freed = 0;
             self.id_to_data.retain(|id, data| {
@@ -100,6 +263,9 @@ impl TerminalState {
             placement,
             verbosity
         );
+        if placement.virtual_placement {
+            return self.kitty_img_place_virtual(image_id, placement);
+        }
         if image_id != 0 {
             self.kitty_remove_placement(image_id, placement.placement_id);
         }
@@ -145,6 +311,193 @@ impl TerminalState {
         Ok(())
     }
 
+    fn kitty_img_place_virtual(
+        &mut self,
+        image_id: u32,
+        placement: KittyImagePlacement,
+    ) -> anyhow::Result<()> {
+        let img =
+            self.kitty_img.id_to_data.get(&image_id).ok_or_else(|| {
+                anyhow::anyhow!("no matching image id {} in id_to_data", image_id)
+            })?;
+        let (image_width, image_height) = img.data().dimensions()?;
+
+        let source_x = placement.x.unwrap_or(0).min(image_width);
+        let source_y = placement.y.unwrap_or(0).min(image_height);
+        let source_width = placement
+            .w
+            .unwrap_or(image_width)
+            .min(image_width - source_x);
+        let source_height = placement
+            .h
+            .unwrap_or(image_height)
+            .min(image_height - source_y);
+        anyhow::ensure!(
+            source_width > 0 && source_height > 0,
+            "virtual placement has an empty source rectangle"
+        );
+
+        // When the size of the placement is omitted, derive it from
+        // the size of the image in cells, preserving the aspect ratio
+        // when only one of columns or rows was specified
+        let cell_width = (self.pixel_width / self.screen().physical_cols).max(1) as f32;
+        let cell_height = (self.pixel_height / self.screen().physical_rows).max(1) as f32;
+        let cells = |pixels: f32, cell: f32| ((pixels / cell).ceil() as u32).max(1);
+        let (columns, rows) = match (placement.columns, placement.rows) {
+            (Some(columns), Some(rows)) => (columns, rows),
+            (Some(columns), None) => (
+                columns,
+                cells(
+                    columns as f32 * cell_width * source_height as f32 / source_width as f32,
+                    cell_height,
+                ),
+            ),
+            (None, Some(rows)) => (
+                cells(
+                    rows as f32 * cell_height * source_width as f32 / source_height as f32,
+                    cell_width,
+                ),
+                rows,
+            ),
+            (None, None) => (
+                cells(source_width as f32, cell_width),
+                cells(source_height as f32, cell_height),
+            ),
+        };
+
+        let key = (image_id, placement.placement_id);
+        let info = self
+            .kitty_img
+            .virtual_placements
+            .remove(&key)
+            .and_then(|prior| prior.info);
+        self.kitty_img.virtual_placements.insert(
+            key,
+            VirtualPlacement {
+                columns,
+                rows,
+                source_x,
+                source_y,
+                source_width,
+                source_height,
+                z_index: placement.z_index.unwrap_or(0),
+                info,
+            },
+        );
+        log::trace!(
+            "record virtual placement for {} {:?}: {}x{} cells",
+            image_id,
+            placement.placement_id,
+            columns,
+            rows
+        );
+
+        Ok(())
+    }
+
+    /// Resolves a placeholder grapheme that is about to be printed at
+    /// the cursor position, returning the portion of the image that
+    /// the cell should display.
+    pub(crate) fn kitty_placeholder_image(
+        &mut self,
+        grapheme: &str,
+        pen: &CellAttributes,
+    ) -> Option<ImageCell> {
+        if !self.config.enable_kitty_graphics() {
+            return None;
+        }
+        let mut chars = grapheme.chars();
+        if chars.next() != Some(KITTY_PLACEHOLDER) {
+            return None;
+        }
+        let mut diacritics = chars.filter_map(placeholder_diacritic);
+        let row = diacritics.next();
+        let col = diacritics.next();
+        let high_byte = diacritics.next();
+
+        let x = self.cursor.x;
+        let y = self.screen().visible_row_to_stable_row(self.cursor.y);
+        let image_id = placeholder_color_id(pen.foreground())? | (high_byte.unwrap_or(0) << 24);
+        let placement_id = placeholder_color_id(pen.underline_color()).filter(|&id| id != 0);
+
+        // Omitted values are inherited from the placeholder to the left,
+        // continuing along the same row of the image
+        let prior = self.kitty_img.last_placeholder.filter(|prior| {
+            prior.y == y
+                && prior.x + 1 == x
+                && prior.placement_id == placement_id
+                && match high_byte {
+                    Some(_) => prior.image_id == image_id,
+                    None => prior.image_id & 0xff_ffff == image_id,
+                }
+                && row.map(|row| row == prior.row).unwrap_or(true)
+        });
+        let (image_id, row, col) = match (col, prior) {
+            (None, Some(prior)) => (prior.image_id, prior.row, prior.col + 1),
+            _ => (image_id, row.unwrap_or(0), col.unwrap_or(0)),
+        };
+        self.kitty_img.last_placeholder.replace(PlaceholderCell {
+            x,
+            y,
+            image_id,
+            placement_id,
+            row,
+            col,
+        });
+
+        // Without a placement id, use any virtual placement of the image
+        let key = match placement_id {
+            Some(_) => (image_id, placement_id),
+            None if self
+                .kitty_img
+                .virtual_placements
+                .contains_key(&(image_id, None)) =>
+            {
+                (image_id, None)
+            }
+            None => *self
+                .kitty_img
+                .virtual_placements
+                .keys()
+                .find(|(id, _)| *id == image_id)?,
+        };
+
+        let data = Arc::clone(self.kitty_img.id_to_data.get(&image_id)?);
+        let (image_width, image_height) = data.data().dimensions().ok()?;
+        let cell_width = (self.pixel_width / self.screen().physical_cols).max(1) as f32;
+        let cell_height = (self.pixel_height / self.screen().physical_rows).max(1) as f32;
+
+        let placement = self.kitty_img.virtual_placements.get_mut(&key)?;
+        placement.info = Some(match placement.info {
+            None => PlacementInfo {
+                first_row: y,
+                rows: 1,
+                cols: placement.columns as usize,
+            },
+            Some(info) => {
+                let first_row = info.first_row.min(y);
+                let last_row = (info.first_row + info.rows as StableRowIndex - 1).max(y);
+                PlacementInfo {
+                    first_row,
+                    rows: (last_row - first_row + 1) as usize,
+                    cols: info.cols,
+                }
+            }
+        });
+
+        placement.image_cell(
+            row,
+            col,
+            cell_width,
+            cell_height,
+            image_width,
+            image_height,
+            data,
+            image_id,
+            key.1,
+        )
+    }
+
     fn kitty_img_inner(&mut self, img: KittyImage) -> anyhow::Result<()> {
         match self
             .coalesce_kitty_accumulation(img)
@@ -284,6 +637,17 @@ impl TerminalState {
                     log::error!("Error {:#} while handling KittyImage::ComposeFrame", err);
                 }
             }
+            KittyImage::AnimationControl {
+                animation,
+                verbosity,
+            } => {
+                if let Err(err) = self.kitty_animation_control(animation, verbosity) {
+                    log::error!(
+                        "Error {:#} while handling KittyImage::AnimationControl",
+                        err
+                    );
+                }
+            }
         };
 
         Ok(())
@@ -309,7 +673,57 @@ impl TerminalState {
         }
     }
 
+    /// Marks the rows that display an image as changed, so that
+    /// they are re-rendered
+    fn kitty_touch_image(&mut self, image_id: u32) {
+        let infos: Vec<PlacementInfo> = self
+            .kitty_img
+            .placements
+            .iter()
+            .filter(|((id, _), _)| *id == image_id)
+            .map(|(_, info)| *info)
+            .chain(
+                self.kitty_img
+                    .virtual_placements
+                    .iter()
+                    .filter(|((id, _), _)| *id == image_id)
+                    .filter_map(|(_, placement)| placement.info),
+            )
+            .collect();
+
+        let seqno = self.seqno;
+        let screen = self.screen_mut();
+        for info in infos {
+            let range = screen
+                .stable_range(&(info.first_row..info.first_row + info.rows as StableRowIndex));
+            for idx in range {
+                screen.line_mut(idx).update_last_change_seqno(seqno);
+            }
+        }
+    }
+
+    fn kitty_remove_virtual_placement(&mut self, image_id: u32, placement_id: Option<u32>) {
+        let keys: Vec<(u32, Option<u32>)> = self
+            .kitty_img
+            .virtual_placements
+            .keys()
+            .filter(|(id, p)| *id == image_id && (placement_id.is_none() || *p == placement_id))
+            .copied()
+            .collect();
+        for (id, p) in keys {
+            if let Some(info) = self
+                .kitty_img
+                .virtual_placements
+                .remove(&(id, p))
+                .and_then(|placement| placement.info)
+            {
+                self.kitty_remove_placement_from_model(id, p, info);
+            }
+        }
+    }
+
     fn kitty_remove_placement(&mut self, image_id: u32, placement_id: Option<u32>) {
+        self.kitty_remove_virtual_placement(image_id, placement_id);
         if placement_id.is_some() {
             if let Some(info) = self.kitty_img.placements.remove(&(image_id, placement_id)) {
                 log::trace!("removed placement {} {:?}", image_id, placement_id);
@@ -341,6 +755,14 @@ impl TerminalState {
         for ((image_id, p), info) in std::mem::take(&mut self.kitty_img.placements).into_iter() {
             self.kitty_remove_placement_from_model(image_id, p, info);
         }
+        for ((image_id, p), placement) in
+            std::mem::take(&mut self.kitty_img.virtual_placements).into_iter()
+        {
+            if let Some(info) = placement.info {
+                self.kitty_remove_placement_from_model(image_id, p, info);
+            }
+        }
+        self.kitty_img.last_placeholder.take();
         if delete {
             self.kitty_img.id_to_data.clear();
             self.kitty_img.used_memory = 0;
@@ -387,6 +809,74 @@ impl TerminalState {
         self.writer.flush().ok();
     }
 
+    fn kitty_animation_control(
+        &mut self,
+        animation: KittyImageAnimation,
+        verbosity: KittyImageVerbosity,
+    ) -> anyhow::Result<()> {
+        let image_id = match animation.image_number {
+            Some(no) => self.kitty_img.number_to_id.get(&no).copied(),
+            None => animation.image_id,
+        };
+        let img = match image_id.and_then(|id| self.kitty_img.id_to_data.get(&id)) {
+            Some(img) => Arc::clone(img),
+            None => {
+                self.kitty_send_response(
+                    verbosity,
+                    false,
+                    animation.image_id,
+                    animation.image_number,
+                    "ENOENT".to_string(),
+                );
+                anyhow::bail!(
+                    "no matching image for id {:?} number {:?}",
+                    animation.image_id,
+                    animation.image_number
+                );
+            }
+        };
+
+        if let (Some(frame_no), Some(duration_ms)) = (animation.frame_number, animation.duration_ms)
+        {
+            if let ImageDataType::AnimRgba8 { durations, .. } = &mut *img.data() {
+                let frame_no = frame_no as usize;
+                anyhow::ensure!(
+                    frame_no <= durations.len(),
+                    "attempted to set the gap of frame {} which is outside range 1-{}",
+                    frame_no,
+                    durations.len()
+                );
+                durations[frame_no - 1] = Duration::from_millis(duration_ms.into());
+            }
+        }
+
+        let mut control = img.animation();
+        if let Some(state) = animation.state {
+            control.state = match state {
+                KittyAnimationState::Stopped => ImageAnimationState::Stopped,
+                KittyAnimationState::Loading => ImageAnimationState::Loading,
+                KittyAnimationState::Running => ImageAnimationState::Running,
+            };
+        }
+        if let Some(loops) = animation.loops {
+            control.max_loops = match loops {
+                1 => None,
+                n => Some(n - 1),
+            };
+        }
+        if let Some(frame_no) = animation.current_frame {
+            let seq = control.current_frame.map(|(seq, _)| seq + 1).unwrap_or(0);
+            control.current_frame = Some((seq, frame_no as usize - 1));
+        }
+        img.set_animation(control);
+
+        if let Some(image_id) = image_id {
+            self.kitty_touch_image(image_id);
+        }
+
+        Ok(())
+    }
+
     fn kitty_frame_compose(
         &mut self,
         frame: KittyImageFrameCompose,
@@ -4,11 +4,14 @@
 use super::*;
 use crate::color::{ColorPalette, RgbColor};
 use crate::config::{BidiMode, NewlineCanon};
+use crate::scrollback::DiskSpill;
 use log::debug;
 use num_traits::ToPrimitive;
 use std::collections::HashMap;
 use std::io::{BufWriter, Write};
 use std::num::NonZeroUsize;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::mpsc::{channel, Sender};
 use std::sync::Arc;
 use terminfo::{Database, Value};
@@ -18,11 +21,14 @@ use wezterm_bidi::ParagraphDirectionHint;
 use wezterm_cell::image::ImageData;
 use wezterm_cell::UnicodeVersion;
 use wezterm_escape_parser::csi::{
-    Cursor, CursorStyle, DecPrivateMode, DecPrivateModeCode, Device, Edit, EraseInDisplay,
-    EraseInLine, Mode, Sgr, TabulationClear, TerminalMode, TerminalModeCode, Window, XtSmGraphics,
-    XtSmGraphicsAction, XtSmGraphicsItem, XtSmGraphicsStatus, XtermKeyModifierResource,
+    AttributeChangeExtent, ColorScheme, Cursor, CursorStyle, DecPrivateMode, DecPrivateModeCode,
+    Device, Edit, EraseInDisplay, EraseInLine, Mode, RectangularAreaAttribute, Sgr,
+    TabulationClear, TerminalMode, TerminalModeCode, Window, XtSmGraphics, XtSmGraphicsAction,
+    XtSmGraphicsItem, XtSmGraphicsStatus, XtermKeyModifierResource,
 };
+use wezterm_escape_parser::osc::Selection;
 use wezterm_escape_parser::{OneBased, OperatingSystemCommand, CSI};
+use wezterm_surface::hyperlink::Rule;
 use wezterm_surface::{CursorShape, CursorVisibility, SequenceNo};
 
 mod image;
@@ -208,6 +214,7 @@ impl ScreenOrAlt {
     }
 
     pub fn activate_primary_screen(&mut self, seqno: SequenceNo) {
+        self.alt_screen.capture_visible_lines(seqno);
         self.alt_screen_is_active = false;
         self.dirty_top_phys_rows(seqno);
     }
@@ -223,6 +230,26 @@ impl ScreenOrAlt {
                 .line_mut(line_idx)
                 .update_last_change_seqno(seqno);
         }
+
+        // When lines are captured from the alternate screen, it spans
+        // a larger range of StableRowIndex, so invalidate all of its
+        // lines and the corresponding rows of the primary screen
+        let alt_rows = self.alt_screen.scrollback_rows();
+        if alt_rows > self.alt_screen.physical_rows {
+            for line_idx in 0..alt_rows {
+                self.alt_screen
+                    .line_mut(line_idx)
+                    .update_last_change_seqno(seqno);
+            }
+            let first = self.alt_screen.phys_to_stable_row_index(0);
+            for stable in first..first + alt_rows as StableRowIndex {
+                if let Some(line_idx) = self.screen.stable_row_to_phys(stable) {
+                    self.screen
+                        .line_mut(line_idx)
+                        .update_last_change_seqno(seqno);
+                }
+            }
+        }
     }
 
     pub fn is_alt_screen_active(&self) -> bool {
@@ -284,6 +311,9 @@ pub struct TerminalState {
     left_and_right_margins: Range<usize>,
     left_and_right_margin_mode: bool,
 
+    /// How DECCARA and DECRARA apply, as selected by DECSACE
+    attribute_change_extent: AttributeChangeExtent,
+
     /// When set, modifies the sequence of bytes sent for keys
     /// designated as cursor keys.  This includes various navigation
     /// keys.  The code in key_down() is responsible for interpreting this.
@@ -308,6 +338,16 @@ pub struct TerminalState {
     /// designated marker characters.
     bracketed_paste: bool,
 
+    /// Identifies each chunked paste
+    next_chunked_paste_id: usize,
+    /// The id of the chunked paste that is being written, if any,
+    /// and whether it is bracketed
+    active_chunked_paste: Option<(usize, bool)>,
+
+    /// When set, the application is told when the palette changes
+    /// so that it can query the colors again
+    color_scheme_updates: bool,
+
     /// Movement events enabled
     any_event_mouse: bool,
     focus_tracking: bool,
@@ -338,6 +378,9 @@ pub struct TerminalState {
     progress: Progress,
 
     palette: Option<ColorPalette>,
+    /// The palette assigned to this pane, replacing the
+    /// palette from the configuration
+    pane_palette: Option<ColorPalette>,
 
     pixel_width: usize,
     pixel_height: usize,
@@ -366,6 +409,8 @@ pub struct TerminalState {
     /// The unicode version that is in effect
     unicode_version: UnicodeVersion,
     unicode_version_stack: Vec<UnicodeVersionStackEntry>,
+    /// Whether output is segmented into grapheme clusters (DECSET 2027)
+    grapheme_clustering: bool,
 
     enable_conpty_quirks: bool,
     /// On Windows, the ConPTY layer emits an OSC sequence to
@@ -380,8 +425,37 @@ pub struct TerminalState {
     lost_focus_seqno: SequenceNo,
     /// seqno when we last emitted Alert::OutputSinceFocusLost
     lost_focus_alerted_seqno: SequenceNo,
+    /// The number of new lines that have been output since
+    /// we last lost focus
+    unseen_output_lines: usize,
     focused: bool,
 
+    /// The rows on which a command reported a non-zero exit
+    /// status via OSC 133;D, in ascending order
+    failed_command_rows: Vec<StableRowIndex>,
+
+    /// The commands that were run, as delimited by OSC 133, in
+    /// ascending order of their prompt rows
+    command_history: Vec<CommandRecord>,
+    /// The row on which the most recent primary prompt started
+    prompt_start_row: Option<StableRowIndex>,
+    /// When the command at the end of command_history started,
+    /// if we are still waiting for it to report its status
+    command_started: Option<std::time::Instant>,
+
+    /// Overrides for the background of the pane, set either
+    /// via OSC 1337 or via the lua api
+    pane_background: PaneBackground,
+
+    /// The hyperlink_rules used for this pane in place of those
+    /// from the configuration, set via the lua api
+    hyperlink_rules: Option<Arc<Vec<Rule>>>,
+
+    /// Overrides for the bidi configuration of the pane, set
+    /// via the lua api.  Unlike the escape sequence state below,
+    /// this is not cleared by a reset.
+    pane_bidi_mode: PaneBidiMode,
+
     /// True if lines should be marked as bidi-enabled, and thus
     /// have the renderer apply the bidi algorithm.
     /// true is equivalent to "implicit" bidi mode as described in
@@ -400,6 +474,96 @@ struct UnicodeVersionStackEntry {
     label: Option<String>,
 }
 
+/// Turns an attribute on or off, as requested by DECCARA
+fn change_attribute(attrs: &mut CellAttributes, attr: RectangularAreaAttribute) {
+    match attr {
+        RectangularAreaAttribute::All => {
+            attrs
+                .set_intensity(Intensity::Normal)
+                .set_underline(Underline::None)
+                .set_blink(Blink::None)
+                .set_reverse(false)
+                .set_invisible(false);
+        }
+        RectangularAreaAttribute::Bold => {
+            attrs.set_intensity(Intensity::Bold);
+        }
+        RectangularAreaAttribute::Underline => {
+            attrs.set_underline(Underline::Single);
+        }
+        RectangularAreaAttribute::Blink => {
+            attrs.set_blink(Blink::Slow);
+        }
+        RectangularAreaAttribute::Inverse => {
+            attrs.set_reverse(true);
+        }
+        RectangularAreaAttribute::Invisible => {
+            attrs.set_invisible(true);
+        }
+        RectangularAreaAttribute::NormalIntensity => {
+            attrs.set_intensity(Intensity::Normal);
+        }
+        RectangularAreaAttribute::UnderlineOff => {
+            attrs.set_underline(Underline::None);
+        }
+        RectangularAreaAttribute::BlinkOff => {
+            attrs.set_blink(Blink::None);
+        }
+        RectangularAreaAttribute::InverseOff => {
+            attrs.set_reverse(false);
+        }
+        RectangularAreaAttribute::InvisibleOff => {
+            attrs.set_invisible(false);
+        }
+    }
+}
+
+/// Toggles an attribute, as requested by DECRARA
+fn reverse_attribute(attrs: &mut CellAttributes, attr: RectangularAreaAttribute) {
+    match attr {
+        RectangularAreaAttribute::All => {
+            for attr in [
+                RectangularAreaAttribute::Bold,
+                RectangularAreaAttribute::Underline,
+                RectangularAreaAttribute::Blink,
+                RectangularAreaAttribute::Inverse,
+                RectangularAreaAttribute::Invisible,
+            ] {
+                reverse_attribute(attrs, attr);
+            }
+        }
+        RectangularAreaAttribute::Bold => {
+            attrs.set_intensity(if attrs.intensity() == Intensity::Bold {
+                Intensity::Normal
+            } else {
+                Intensity::Bold
+            });
+        }
+        RectangularAreaAttribute::Underline => {
+            attrs.set_underline(if attrs.underline() == Underline::None {
+                Underline::Single
+            } else {
+                Underline::None
+            });
+        }
+        RectangularAreaAttribute::Blink => {
+            attrs.set_blink(if attrs.blink() == Blink::None {
+                Blink::Slow
+            } else {
+                Blink::None
+            });
+        }
+        RectangularAreaAttribute::Inverse => {
+            attrs.set_reverse(!attrs.reverse());
+        }
+        RectangularAreaAttribute::Invisible => {
+            attrs.set_invisible(!attrs.invisible());
+        }
+        // The parser only produces the attributes that can be reversed
+        _ => {}
+    }
+}
+
 fn default_color_map() -> HashMap<u16, RgbColor> {
     let mut color_map = HashMap::new();
     // Match colors to the VT340 color table:
@@ -427,6 +591,43 @@ fn default_color_map() -> HashMap<u16, RgbColor> {
     color_map
 }
 
+/// A paste that is being written to the terminal in chunks.
+/// See `TerminalState::begin_chunked_paste`.
+#[derive(Debug)]
+pub struct ChunkedPaste {
+    id: usize,
+    text: String,
+    sent: usize,
+    bracketed: bool,
+    finished: bool,
+}
+
+impl ChunkedPaste {
+    /// Returns the number of bytes of text that have been written
+    pub fn sent(&self) -> usize {
+        self.sent
+    }
+
+    /// Returns the total number of bytes of text in the paste
+    pub fn len(&self) -> usize {
+        self.text.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// Returns the proportion of the text that has been written,
+    /// as a percentage
+    pub fn percentage(&self) -> u8 {
+        if self.text.is_empty() {
+            100
+        } else {
+            (self.sent * 100 / self.text.len()) as u8
+        }
+    }
+}
+
 /// This struct implements a writer that sends the data across
 /// to another thread so that the write side of the terminal
 /// processing never blocks.
@@ -443,8 +644,13 @@ fn default_color_map() -> HashMap<u16, RgbColor> {
 /// back-pressure when there is a lot of data to read,
 /// and we're in control of the write side, which represents
 /// input from the interactive user, or pastes.
+///
+/// The number of bytes that have been queued but not yet written
+/// is tracked so that large pastes can be throttled to the rate
+/// at which the application is consuming its input.
 struct ThreadedWriter {
     sender: Sender<WriterMessage>,
+    backlog: Arc<AtomicUsize>,
 }
 
 enum WriterMessage {
@@ -455,14 +661,17 @@ enum WriterMessage {
 impl ThreadedWriter {
     fn new(mut writer: Box<dyn std::io::Write + Send>) -> Self {
         let (sender, receiver) = channel::<WriterMessage>();
+        let backlog = Arc::new(AtomicUsize::new(0));
+        let pending = Arc::clone(&backlog);
 
         std::thread::spawn(move || {
             while let Ok(msg) = receiver.recv() {
                 match msg {
                     WriterMessage::Data(buf) => {
-                        if writer.write(&buf).is_err() {
+                        if writer.write_all(&buf).is_err() {
                             break;
                         }
+                        pending.fetch_sub(buf.len(), Ordering::SeqCst);
                     }
                     WriterMessage::Flush => {
                         if writer.flush().is_err() {
@@ -473,12 +682,13 @@ impl ThreadedWriter {
             }
         });
 
-        Self { sender }
+        Self { sender, backlog }
     }
 }
 
 impl std::io::Write for ThreadedWriter {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.backlog.fetch_add(buf.len(), Ordering::SeqCst);
         self.sender
             .send(WriterMessage::Data(buf.to_vec()))
             .map_err(|err| std::io::Error::new(std::io::ErrorKind::BrokenPipe, err))?;
@@ -511,6 +721,7 @@ impl TerminalState {
         let color_map = default_color_map();
 
         let unicode_version = config.unicode_version();
+        let grapheme_clustering = config.grapheme_clustering();
 
         TerminalState {
             config,
@@ -520,6 +731,7 @@ impl TerminalState {
             top_and_bottom_margins: 0..size.rows as VisibleRowIndex,
             left_and_right_margins: 0..size.cols,
             left_and_right_margin_mode: false,
+            attribute_change_extent: AttributeChangeExtent::default(),
             wrap_next: false,
             clear_semantic_attribute_on_newline: false,
             // We default auto wrap to true even though the default for
@@ -537,6 +749,9 @@ impl TerminalState {
             color_map,
             application_keypad: false,
             bracketed_paste: false,
+            next_chunked_paste_id: 0,
+            active_chunked_paste: None,
+            color_scheme_updates: false,
             focus_tracking: false,
             mouse_encoding: MouseEncoding::X10,
             keyboard_encoding: KeyboardEncoding::Xterm,
@@ -555,6 +770,7 @@ impl TerminalState {
             title: "wezterm".to_string(),
             icon_title: None,
             palette: None,
+            pane_palette: None,
             pixel_height: size.pixel_height,
             pixel_width: size.pixel_width,
             dpi: size.dpi,
@@ -572,12 +788,21 @@ impl TerminalState {
             seqno,
             unicode_version,
             unicode_version_stack: vec![],
+            grapheme_clustering,
             suppress_initial_title_change: false,
             enable_conpty_quirks: false,
             accumulating_title: None,
             lost_focus_seqno: seqno,
             lost_focus_alerted_seqno: seqno,
+            unseen_output_lines: 0,
             focused: true,
+            failed_command_rows: vec![],
+            command_history: vec![],
+            prompt_start_row: None,
+            command_started: None,
+            pane_background: PaneBackground::default(),
+            hyperlink_rules: None,
+            pane_bidi_mode: PaneBidiMode::default(),
             bidi_enabled: None,
             bidi_hint: None,
             progress: Progress::default(),
@@ -598,7 +823,11 @@ impl TerminalState {
     }
 
     pub fn set_config(&mut self, config: Arc<dyn TerminalConfiguration>) {
+        let palette = self.palette();
         self.config = config;
+        if self.color_scheme_updates && self.palette() != palette {
+            self.report_color_scheme();
+        }
     }
 
     pub fn get_config(&self) -> Arc<dyn TerminalConfiguration> {
@@ -662,6 +891,16 @@ impl TerminalState {
     /// so that we can start tracking those changes.
     pub fn palette(&self) -> ColorPalette {
         self.palette
+            .as_ref()
+            .cloned()
+            .unwrap_or_else(|| self.base_palette())
+    }
+
+    /// Returns the palette that is in effect before any changes
+    /// made by dynamic color scheme escape sequences; either the
+    /// palette assigned to the pane, or the configured palette.
+    fn base_palette(&self) -> ColorPalette {
+        self.pane_palette
             .as_ref()
             .cloned()
             .unwrap_or_else(|| self.config.color_palette())
@@ -672,11 +911,36 @@ impl TerminalState {
     /// is the first of these escapes we've seen.
     pub fn palette_mut(&mut self) -> &mut ColorPalette {
         if self.palette.is_none() {
-            self.palette.replace(self.config.color_palette());
+            self.palette.replace(self.base_palette());
         }
         self.palette.as_mut().unwrap()
     }
 
+    /// Returns the palette assigned to the pane, if any
+    pub fn pane_palette(&self) -> Option<&ColorPalette> {
+        self.pane_palette.as_ref()
+    }
+
+    /// Assigns a palette to the pane, replacing the configured palette,
+    /// or restores the configured palette if `palette` is None.
+    /// Changes that the application made to the palette using
+    /// escape sequences are discarded, so that the new palette
+    /// takes effect in full.
+    pub fn set_pane_palette(&mut self, palette: Option<ColorPalette>) {
+        if self.pane_palette == palette {
+            return;
+        }
+        let prior = self.palette();
+        self.pane_palette = palette;
+        self.palette.take();
+        if self.palette() != prior {
+            if self.color_scheme_updates {
+                self.report_color_scheme();
+            }
+            self.palette_did_change();
+        }
+    }
+
     /// If the current overridden palette is effectively the same as
     /// the configured palette, remove the override and treat it as
     /// being the same as the configured state.
@@ -685,7 +949,7 @@ impl TerminalState {
         if self
             .palette
             .as_ref()
-            .map(|p| *p == self.config.color_palette())
+            .map(|p| *p == self.base_palette())
             .unwrap_or(false)
         {
             self.palette.take();
@@ -715,6 +979,43 @@ impl TerminalState {
         Ok(())
     }
 
+    fn query_clipboard_contents(&self, selection: ClipboardSelection) -> anyhow::Result<()> {
+        if let Some(clip) = self.clipboard.as_ref() {
+            clip.query_contents(selection)?;
+        }
+        Ok(())
+    }
+
+    /// Sends `text` to the application as the contents of the clipboard,
+    /// in response to an OSC 52 query.  Text that is larger than the
+    /// osc52_max_bytes limit is not sent; an empty response is sent
+    /// in its place, so that the application doesn't wait for one.
+    pub fn send_clipboard_contents(&mut self, selection: ClipboardSelection, text: &str) {
+        let max_bytes = self.config.osc52_max_bytes();
+        let text = if text.len() > max_bytes {
+            log::warn!(
+                "not sending {} bytes of clipboard contents, which exceeds \
+                 osc52_max_bytes={}",
+                text.len(),
+                max_bytes
+            );
+            ""
+        } else {
+            text
+        };
+        let selection = match selection {
+            ClipboardSelection::Clipboard => Selection::CLIPBOARD,
+            ClipboardSelection::PrimarySelection => Selection::PRIMARY,
+        };
+        write!(
+            self.writer,
+            "{}",
+            OperatingSystemCommand::SetSelection(selection, text.to_string())
+        )
+        .ok();
+        self.writer.flush().ok();
+    }
+
     pub fn erase_scrollback_and_viewport(&mut self) {
         // Since we may be called outside of perform_actions,
         // we need to ensure that we increment the seqno in
@@ -791,6 +1092,7 @@ impl TerminalState {
         self.focused = focused;
         if !focused {
             self.lost_focus_seqno = self.seqno;
+            self.unseen_output_lines = 0;
         }
     }
 
@@ -800,6 +1102,187 @@ impl TerminalState {
         !self.focused && self.seqno > self.lost_focus_seqno
     }
 
+    /// Returns the number of new lines that have been output since
+    /// the terminal lost focus, or 0 if it is focused
+    pub fn unseen_output_lines(&self) -> usize {
+        if self.focused {
+            0
+        } else {
+            self.unseen_output_lines
+        }
+    }
+
+    /// Returns the rows, which are still present in the scrollback,
+    /// on which a command reported a non-zero exit status
+    pub fn failed_command_rows(&self) -> Vec<StableRowIndex> {
+        let first_row = self.screen.phys_to_stable_row_index(0);
+        self.failed_command_rows
+            .iter()
+            .copied()
+            .filter(|&row| row >= first_row)
+            .collect()
+    }
+
+    /// Records the completion of a command that reported
+    /// a non-zero exit status
+    pub(crate) fn record_failed_command(&mut self) {
+        let row = self.screen.visible_row_to_stable_row(self.cursor.y);
+        // Forget the rows that have been scrolled out of the scrollback
+        let first_row = self.screen.phys_to_stable_row_index(0);
+        self.failed_command_rows.retain(|&r| r >= first_row);
+        if self.failed_command_rows.last() != Some(&row) {
+            self.failed_command_rows.push(row);
+        }
+    }
+
+    /// Returns the commands, whose prompts are still present in the
+    /// scrollback, that were run in the terminal
+    pub fn command_history(&self) -> Vec<CommandRecord> {
+        let first_row = self.screen.phys_to_stable_row_index(0);
+        self.command_history
+            .iter()
+            .filter(|cmd| cmd.prompt_row >= first_row)
+            .cloned()
+            .collect()
+    }
+
+    /// Remembers the row on which the prompt for the next
+    /// command starts
+    pub(crate) fn record_prompt_start(&mut self) {
+        self.prompt_start_row
+            .replace(self.screen.visible_row_to_stable_row(self.cursor.y));
+    }
+
+    /// Records the start of a command when the shell marks the
+    /// end of its input.  The command line is taken from the
+    /// Input zone that follows the prompt.
+    pub(crate) fn record_command_start(&mut self) {
+        let cursor_row = self.screen.visible_row_to_stable_row(self.cursor.y);
+        let prompt_row = self.prompt_start_row.take().unwrap_or(cursor_row);
+
+        let mut command = String::new();
+        let phys_range = self.screen.stable_range(&(prompt_row..cursor_row + 1));
+        for line in self.screen.lines_in_phys_range(phys_range) {
+            for cell in line.visible_cells() {
+                if cell.attrs().semantic_type() == SemanticType::Input {
+                    command.push_str(cell.str());
+                }
+            }
+            if !line.last_cell_was_wrapped() {
+                command.push('\n');
+            }
+        }
+
+        // Forget the commands that have been scrolled out of the scrollback
+        let first_row = self.screen.phys_to_stable_row_index(0);
+        self.command_history
+            .retain(|cmd| cmd.prompt_row >= first_row);
+
+        self.command_history.push(CommandRecord {
+            prompt_row,
+            command: command.trim().to_string(),
+            cwd: self.current_dir.as_ref().map(|url| url.to_string()),
+            duration: None,
+            status: None,
+        });
+        self.command_started.replace(std::time::Instant::now());
+    }
+
+    /// Records the exit status of the command that is running
+    pub(crate) fn record_command_status(&mut self, status: i32) {
+        if let Some(started) = self.command_started.take() {
+            if let Some(cmd) = self.command_history.last_mut() {
+                cmd.duration.replace(started.elapsed());
+                cmd.status.replace(status);
+            }
+        }
+    }
+
+    /// Returns the hyperlink_rules that apply to this pane, if they
+    /// have been overridden
+    pub fn hyperlink_rules(&self) -> Option<Arc<Vec<Rule>>> {
+        self.hyperlink_rules.clone()
+    }
+
+    /// Replaces the hyperlink_rules that apply to this pane; None
+    /// reverts to those from the configuration.  Any implicit links
+    /// that were created by the prior rules are removed, so that the
+    /// lines will be scanned again using the new rules.
+    pub fn set_hyperlink_rules(&mut self, rules: Option<Vec<Rule>>) {
+        self.hyperlink_rules = rules.map(Arc::new);
+        let seqno = self.seqno;
+        for screen in [&mut self.screen.screen, &mut self.screen.alt_screen] {
+            screen.invalidate_implicit_hyperlinks(seqno);
+        }
+    }
+
+    /// Retains up to `max_lines` of scrollback (or an unlimited amount
+    /// if None) for the primary screen.  Lines beyond the configured
+    /// scrollback_lines are compressed and spilled to a temporary file
+    /// in `directory` whose name starts with `prefix`; that file is
+    /// removed when the terminal is dropped.
+    pub fn enable_disk_scrollback(
+        &mut self,
+        directory: &Path,
+        prefix: &str,
+        max_lines: Option<usize>,
+    ) -> anyhow::Result<()> {
+        let spill = DiskSpill::new(directory, prefix)?;
+        self.screen
+            .screen
+            .enable_disk_scrollback(Arc::new(spill), max_lines);
+        Ok(())
+    }
+
+    /// Returns the overrides for the bidi configuration of the pane
+    pub fn pane_bidi_mode(&self) -> PaneBidiMode {
+        self.pane_bidi_mode
+    }
+
+    /// Replaces the overrides for the bidi configuration of the pane,
+    /// and applies the resulting mode to the lines on the screen
+    pub fn set_pane_bidi_mode(&mut self, mode: PaneBidiMode) {
+        if self.pane_bidi_mode != mode {
+            self.pane_bidi_mode = mode;
+            let rows = 0..self.screen().physical_rows as VisibleRowIndex;
+            self.apply_bidi_mode_to_rows(rows);
+        }
+    }
+
+    /// Applies the current bidi mode to the line containing the cursor,
+    /// so that BDSM and SCP take effect on the current line as well as
+    /// the lines that follow it
+    pub(crate) fn apply_bidi_mode_to_cursor_row(&mut self) {
+        let y = self.cursor.y;
+        self.apply_bidi_mode_to_rows(y..y + 1);
+    }
+
+    /// Applies the current bidi mode to the visible rows in `rows`
+    pub(crate) fn apply_bidi_mode_to_rows(&mut self, rows: Range<VisibleRowIndex>) {
+        let bidi_mode = self.get_bidi_mode();
+        let seqno = self.seqno;
+        let screen = self.screen_mut();
+        let phys = screen.phys_range(&rows);
+        screen.with_phys_lines_mut(phys, |lines| {
+            for line in lines {
+                bidi_mode.apply_to_line(line, seqno);
+            }
+        });
+    }
+
+    /// Returns the overrides for the background of the pane
+    pub fn pane_background(&self) -> &PaneBackground {
+        &self.pane_background
+    }
+
+    /// Replaces the overrides for the background of the pane
+    pub fn set_pane_background(&mut self, background: PaneBackground) {
+        if self.pane_background != background {
+            self.pane_background = background;
+            self.palette_did_change();
+        }
+    }
+
     pub(crate) fn trigger_unseen_output_notif(&mut self) {
         if self.has_unseen_output() {
             // We want to avoid over-notifying about output events,
@@ -826,6 +1309,18 @@ impl TerminalState {
             buf.push_str("\x1b[200~");
         }
 
+        buf.push_str(&self.de_fang_paste(text));
+
+        if self.bracketed_paste {
+            buf.push_str("\x1b[201~");
+        }
+
+        self.writer.write_all(buf.as_bytes())?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    fn de_fang_paste(&self, text: &str) -> String {
         let canon = if self.bracketed_paste {
             NewlineCanon::None
         } else {
@@ -833,18 +1328,99 @@ impl TerminalState {
         };
 
         let canon = canon.canonicalize(text);
-        let de_fanged = canon.replace("\x1b[200~", "").replace("\x1b[201~", "");
-        buf.push_str(&de_fanged);
+        canon.replace("\x1b[200~", "").replace("\x1b[201~", "")
+    }
+
+    /// Begins sending text that is the result of pasting, in the same
+    /// way as `send_paste`, but without writing the text itself.
+    /// The returned paste is written piecemeal by calling
+    /// `continue_chunked_paste`, which allows large pastes to be
+    /// throttled using `pending_write_bytes` and to be cancelled
+    /// part way through by calling `finish_chunked_paste`.
+    /// Key and mouse input that is sent to the application before the
+    /// paste is finished cancels it, so that the input isn't taken to
+    /// be part of the pasted text.
+    pub fn begin_chunked_paste(&mut self, text: &str) -> Result<ChunkedPaste, Error> {
+        self.interrupt_chunked_paste()?;
+        self.next_chunked_paste_id += 1;
+        let paste = ChunkedPaste {
+            id: self.next_chunked_paste_id,
+            text: self.de_fang_paste(text),
+            sent: 0,
+            bracketed: self.bracketed_paste,
+            finished: false,
+        };
+        if paste.bracketed {
+            self.writer.write_all(b"\x1b[200~")?;
+        }
+        self.active_chunked_paste = Some((paste.id, paste.bracketed));
+        Ok(paste)
+    }
 
-        if self.bracketed_paste {
-            buf.push_str("\x1b[201~");
+    /// Writes up to `max_bytes` of the remaining text of the paste.
+    /// Returns true if there is more text to write.
+    /// When all of the text has been written, the paste is finished.
+    pub fn continue_chunked_paste(
+        &mut self,
+        paste: &mut ChunkedPaste,
+        max_bytes: usize,
+    ) -> Result<bool, Error> {
+        if self.active_chunked_paste.map(|(id, _)| id) != Some(paste.id) {
+            // It was interrupted by input
+            paste.finished = true;
+        }
+        if paste.finished {
+            return Ok(false);
         }
 
-        self.writer.write_all(buf.as_bytes())?;
+        let mut end = paste.text.len().min(paste.sent + max_bytes.max(1));
+        // Avoid splitting a multi-byte character across chunks,
+        // so that cancelling the paste can't leave half of one behind
+        while !paste.text.is_char_boundary(end) {
+            end += 1;
+        }
+        let chunk = &paste.text[paste.sent..end];
+        self.writer.write_all(chunk.as_bytes())?;
+        paste.sent = end;
+
+        if paste.sent == paste.text.len() {
+            self.finish_chunked_paste(paste)?;
+            return Ok(false);
+        }
         self.writer.flush()?;
+        Ok(true)
+    }
+
+    /// Finishes the paste, closing the bracketing if bracketed paste
+    /// was enabled when the paste began.  Any text that has not yet
+    /// been written is discarded.
+    pub fn finish_chunked_paste(&mut self, paste: &mut ChunkedPaste) -> Result<(), Error> {
+        paste.finished = true;
+        if self.active_chunked_paste.map(|(id, _)| id) == Some(paste.id) {
+            self.interrupt_chunked_paste()?;
+        }
         Ok(())
     }
 
+    /// Finishes the chunked paste that is being written, if any,
+    /// ahead of writing some other input for the application.  The
+    /// rest of its text is discarded when it is next continued.
+    pub(crate) fn interrupt_chunked_paste(&mut self) -> Result<(), Error> {
+        if let Some((_, bracketed)) = self.active_chunked_paste.take() {
+            if bracketed {
+                self.writer.write_all(b"\x1b[201~")?;
+            }
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Returns the number of bytes that have been sent to the
+    /// application but that it has not yet consumed
+    pub fn pending_write_bytes(&self) -> usize {
+        self.writer.buffer().len() + self.writer.get_ref().backlog.load(Ordering::SeqCst)
+    }
+
     /// Informs the terminal that the viewport of the window has resized to the
     /// specified dimensions.
     /// We need to resize both the primary and alt screens, adjusting
@@ -913,6 +1489,50 @@ impl TerminalState {
         }
     }
 
+    /// Returns the number of lines that are retained after scrolling
+    /// off the top of the alternate screen
+    pub fn alternate_screen_capture_lines(&self) -> usize {
+        self.screen.alt_screen.capture_lines()
+    }
+
+    /// Overrides the number of lines that are retained after scrolling
+    /// off the top of the alternate screen; None reverts to the value
+    /// from the configuration.  While the alternate screen is active,
+    /// the captured lines are presented as its scrollback.
+    pub fn set_alternate_screen_capture_lines(&mut self, lines: Option<usize>) {
+        if self.screen.alt_screen.capture_lines_override() != lines {
+            self.increment_seqno();
+            let seqno = self.seqno;
+            self.screen.alt_screen.set_capture_lines(lines, seqno);
+        }
+    }
+
+    /// Returns copies of the lines that were captured from the alternate screen
+    pub fn alternate_screen_captured_lines(&self) -> Vec<Line> {
+        self.screen.alt_screen.captured_lines()
+    }
+
+    /// Returns true if some of the scrollback of the primary screen
+    /// has yet to be rewrapped following a resize.
+    /// `rewrap_pending` must be called until this returns false.
+    pub fn has_pending_rewrap(&self) -> bool {
+        self.screen.screen.has_pending_rewrap()
+    }
+
+    /// Rewraps approximately `max_rows` lines of the scrollback that
+    /// were deferred by a resize.  This is intended to be called
+    /// repeatedly in the background, so that resizing a terminal
+    /// with a large scrollback doesn't stall while all of it is rewrapped.
+    /// Returns true if there are more lines to be rewrapped.
+    pub fn rewrap_pending(&mut self, max_rows: usize) -> bool {
+        if !self.has_pending_rewrap() {
+            return false;
+        }
+        self.increment_seqno();
+        let seqno = self.seqno;
+        self.screen.screen.rewrap_pending_lines(max_rows, seqno)
+    }
+
     pub fn get_size(&self) -> TerminalSize {
         let screen = self.screen();
         TerminalSize {
@@ -924,6 +1544,23 @@ impl TerminalState {
         }
     }
 
+    /// Classifies the palette as dark or light, based on
+    /// the lightness of the background color
+    fn color_scheme(&self) -> ColorScheme {
+        let (lightness, _, _, _) = self.palette().background.to_laba();
+        if lightness < 50. {
+            ColorScheme::Dark
+        } else {
+            ColorScheme::Light
+        }
+    }
+
+    fn report_color_scheme(&mut self) {
+        let report = CSI::Device(Box::new(Device::ColorSchemeReport(self.color_scheme())));
+        write!(self.writer, "{}", report).ok();
+        self.writer.flush().ok();
+    }
+
     fn palette_did_change(&mut self) {
         self.make_all_lines_dirty();
         if let Some(handler) = self.alert_handler.as_mut() {
@@ -934,10 +1571,7 @@ impl TerminalState {
     /// When dealing with selection, mark a range of lines as dirty
     pub fn make_all_lines_dirty(&mut self) {
         let seqno = self.seqno;
-        let screen = self.screen_mut();
-        screen.for_each_phys_line_mut(|_, line| {
-            line.update_last_change_seqno(seqno);
-        });
+        self.screen_mut().make_all_lines_dirty(seqno);
     }
 
     /// Returns the 0-based cursor position relative to the top left of
@@ -1088,6 +1722,9 @@ impl TerminalState {
     }
 
     fn new_line(&mut self, move_to_first_column: bool) {
+        if !self.focused {
+            self.unseen_output_lines = self.unseen_output_lines.saturating_add(1);
+        }
         let x = if move_to_first_column {
             self.left_and_right_margins.start
         } else {
@@ -1192,50 +1829,34 @@ impl TerminalState {
         for name in &names {
             res.push_str("\x1bP");
 
-            let encoded_name = hex::encode_upper(&name);
-            match name.as_str() {
-                "TN" | "name" => {
-                    res.push_str("1+r");
-                    res.push_str(&encoded_name);
-                    res.push('=');
-
-                    let encoded_val = hex::encode_upper(&self.term_program);
-                    res.push_str(&encoded_val);
-                }
+            // Booleans are reported by name alone, while numbers
+            // and strings are followed by `=` and their value
+            let value = match name.as_str() {
+                "TN" | "name" => Some(Some(self.term_program.as_bytes().to_vec())),
+                "Co" | "colors" => Some(Some(b"256".to_vec())),
+                "RGB" => Some(Some(b"8/8/8".to_vec())),
+                _ => DB.raw(name).map(|value| match value {
+                    Value::True => None,
+                    Value::Number(n) => Some(n.to_string().into_bytes()),
+                    Value::String(s) => Some(s.to_vec()),
+                }),
+            };
 
-                "Co" | "colors" => {
-                    res.push_str("1+r");
-                    res.push_str(&encoded_name);
-                    res.push('=');
-                    let encoded_val = hex::encode_upper("256");
-                    res.push_str(&encoded_val);
-                }
-
-                "RGB" => {
+            let encoded_name = hex::encode_upper(&name);
+            match value {
+                Some(value) => {
                     res.push_str("1+r");
                     res.push_str(&encoded_name);
-                    res.push('=');
-                    let encoded_val = hex::encode_upper("8/8/8");
-                    res.push_str(&encoded_val);
-                }
-
-                _ => {
-                    if let Some(value) = DB.raw(name) {
-                        res.push_str("1+r");
-                        res.push_str(&encoded_name);
+                    if let Some(value) = value {
                         res.push('=');
-                        let value = match value {
-                            Value::True => hex::encode_upper("1"),
-                            Value::Number(n) => hex::encode_upper(&n.to_string()),
-                            Value::String(s) => hex::encode_upper(s),
-                        };
-                        res.push_str(&value);
-                    } else {
-                        log::trace!("xt_get_tcap: unknown name {}", name);
-                        res.push_str("0+r");
-                        res.push_str(&encoded_name);
+                        res.push_str(&hex::encode_upper(value));
                     }
                 }
+                None => {
+                    log::trace!("xt_get_tcap: unknown name {}", name);
+                    res.push_str("0+r");
+                    res.push_str(&encoded_name);
+                }
             }
             res.push_str("\x1b\\");
         }
@@ -1270,6 +1891,7 @@ impl TerminalState {
                 self.top_and_bottom_margins = 0..self.screen().physical_rows as i64;
                 self.left_and_right_margins = 0..self.screen().physical_cols;
                 self.left_and_right_margin_mode = false;
+                self.attribute_change_extent = AttributeChangeExtent::default();
                 self.screen.activate_alt_screen(self.seqno);
                 self.screen.saved_cursor().take();
                 self.screen.activate_primary_screen(self.seqno);
@@ -1290,6 +1912,7 @@ impl TerminalState {
                 ident.push_str(";6"); // Selective erase
                 ident.push_str(";18"); // windowing extensions
                 ident.push_str(";22"); // ANSI color, vt525
+                ident.push_str(";28"); // Rectangular editing
                 ident.push_str(";52"); // Clipboard access
                 ident.push('c');
 
@@ -1336,6 +1959,13 @@ impl TerminalState {
                 self.writer.write(b"\x1b[0n").ok();
                 self.writer.flush().ok();
             }
+            Device::RequestColorScheme => self.report_color_scheme(),
+            Device::ColorSchemeReport(scheme) => {
+                // This is a response rather than a request
+                if self.config.log_unknown_escape_sequences() {
+                    log::warn!("unhandled: ColorSchemeReport {:?}", scheme);
+                }
+            }
             Device::XtSmGraphics(g) => {
                 let response = if matches!(g.item, XtSmGraphicsItem::Unspecified(_)) {
                     XtSmGraphics {
@@ -1381,22 +2011,6 @@ impl TerminalState {
         }
     }
 
-    /// Indicates that mode is permanently enabled
-    fn decqrm_response_permanent(&mut self, mode: Mode) {
-        let (is_dec, number) = match &mode {
-            Mode::QueryDecPrivateMode(DecPrivateMode::Code(code)) => (true, code.to_u16().unwrap()),
-            Mode::QueryDecPrivateMode(DecPrivateMode::Unspecified(code)) => (true, *code),
-            Mode::QueryMode(TerminalMode::Code(code)) => (false, code.to_u16().unwrap()),
-            Mode::QueryMode(TerminalMode::Unspecified(code)) => (false, *code),
-            _ => unreachable!(),
-        };
-
-        let prefix = if is_dec { "?" } else { "" };
-
-        write!(self.writer, "\x1b[{prefix}{number};3$y").ok();
-        self.writer.flush().ok();
-    }
-
     fn decqrm_response(&mut self, mode: Mode, mut recognized: bool, enabled: bool) {
         let (is_dec, number) = match &mode {
             Mode::QueryDecPrivateMode(DecPrivateMode::Code(code)) => (true, code.to_u16().unwrap()),
@@ -1503,17 +2117,18 @@ impl TerminalState {
 
             Mode::SetDecPrivateMode(DecPrivateMode::Code(
                 DecPrivateModeCode::GraphemeClustering,
-            ))
-            | Mode::ResetDecPrivateMode(DecPrivateMode::Code(
+            )) => {
+                self.grapheme_clustering = true;
+            }
+            Mode::ResetDecPrivateMode(DecPrivateMode::Code(
                 DecPrivateModeCode::GraphemeClustering,
             )) => {
-                // Permanently enabled
+                self.grapheme_clustering = false;
             }
-
             Mode::QueryDecPrivateMode(DecPrivateMode::Code(
                 DecPrivateModeCode::GraphemeClustering,
             )) => {
-                self.decqrm_response_permanent(mode);
+                self.decqrm_response(mode, true, self.grapheme_clustering);
             }
 
             Mode::SetDecPrivateMode(DecPrivateMode::Code(DecPrivateModeCode::SaveCursor)) => {
@@ -1627,17 +2242,14 @@ impl TerminalState {
 
             Mode::SetMode(TerminalMode::Code(TerminalModeCode::BiDirectionalSupportMode)) => {
                 self.bidi_enabled.replace(true);
+                self.apply_bidi_mode_to_cursor_row();
             }
             Mode::ResetMode(TerminalMode::Code(TerminalModeCode::BiDirectionalSupportMode)) => {
                 self.bidi_enabled.replace(false);
+                self.apply_bidi_mode_to_cursor_row();
             }
             Mode::QueryMode(TerminalMode::Code(TerminalModeCode::BiDirectionalSupportMode)) => {
-                self.decqrm_response(
-                    mode,
-                    true,
-                    self.bidi_enabled
-                        .unwrap_or_else(|| self.config.bidi_mode().enabled),
-                );
+                self.decqrm_response(mode, true, self.get_bidi_mode().enabled);
             }
 
             Mode::SetMode(TerminalMode::Code(TerminalModeCode::Insert)) => {
@@ -1670,6 +2282,22 @@ impl TerminalState {
                 self.decqrm_response(mode, true, self.bracketed_paste);
             }
 
+            Mode::SetDecPrivateMode(DecPrivateMode::Code(
+                DecPrivateModeCode::ColorSchemeUpdates,
+            )) => {
+                self.color_scheme_updates = true;
+            }
+            Mode::ResetDecPrivateMode(DecPrivateMode::Code(
+                DecPrivateModeCode::ColorSchemeUpdates,
+            )) => {
+                self.color_scheme_updates = false;
+            }
+            Mode::QueryDecPrivateMode(DecPrivateMode::Code(
+                DecPrivateModeCode::ColorSchemeUpdates,
+            )) => {
+                self.decqrm_response(mode, true, self.color_scheme_updates);
+            }
+
             Mode::SetDecPrivateMode(DecPrivateMode::Code(
                 DecPrivateModeCode::OptEnableAlternateScreen,
             ))
@@ -1970,6 +2598,113 @@ impl TerminalState {
         }
     }
 
+    /// Returns the area that the rectangular area operations can
+    /// affect; the page, or the margins when origin mode is enabled
+    fn rectangular_area_limits(&self) -> (Range<VisibleRowIndex>, Range<usize>) {
+        if self.dec_origin_mode {
+            (
+                self.top_and_bottom_margins.clone(),
+                self.left_and_right_margins.clone(),
+            )
+        } else {
+            (
+                0..self.screen().physical_rows as VisibleRowIndex,
+                0..self.screen().physical_cols,
+            )
+        }
+    }
+
+    /// Resolves the corners of a rectangle given to one of the
+    /// rectangular area operations into the rows and columns that
+    /// it covers, or None if the rectangle is empty
+    fn rectangular_area(
+        &self,
+        top: OneBased,
+        left: OneBased,
+        bottom: OneBased,
+        right: OneBased,
+    ) -> Option<(Range<VisibleRowIndex>, Range<usize>)> {
+        let (limit_rows, limit_cols) = self.rectangular_area_limits();
+        let rows = limit_rows
+            .start
+            .saturating_add(top.as_zero_based() as VisibleRowIndex)
+            ..limit_rows
+                .start
+                .saturating_add(bottom.as_one_based() as VisibleRowIndex)
+                .min(limit_rows.end);
+        let cols = limit_cols
+            .start
+            .saturating_add(left.as_zero_based() as usize)
+            ..limit_cols
+                .start
+                .saturating_add(right.as_one_based() as usize)
+                .min(limit_cols.end);
+        if rows.is_empty() || cols.is_empty() {
+            None
+        } else {
+            Some((rows, cols))
+        }
+    }
+
+    /// Applies `f` to the attributes of the cells in the area given
+    /// to DECCARA or DECRARA.  DECSACE selects whether that is the
+    /// rectangle with the given corners, or the stream of cells from
+    /// the top left position to the bottom right position.
+    fn change_area_attributes<F>(
+        &mut self,
+        top: OneBased,
+        left: OneBased,
+        bottom: OneBased,
+        right: OneBased,
+        mut f: F,
+    ) where
+        F: FnMut(&mut CellAttributes),
+    {
+        let area: Vec<(VisibleRowIndex, Range<usize>)> = match self.attribute_change_extent {
+            AttributeChangeExtent::Rectangle => {
+                match self.rectangular_area(top, left, bottom, right) {
+                    Some((rows, cols)) => rows.map(|y| (y, cols.clone())).collect(),
+                    None => return,
+                }
+            }
+            AttributeChangeExtent::Stream => {
+                // The rows are resolved as for a rectangle spanning
+                // the full width; the stream then starts and ends
+                // part way along its first and last rows
+                let (rows, cols) = match self.rectangular_area(
+                    top,
+                    OneBased::new(1),
+                    bottom,
+                    OneBased::new(u32::MAX),
+                ) {
+                    Some(area) => area,
+                    None => return,
+                };
+                let start = cols.start.saturating_add(left.as_zero_based() as usize);
+                let end = cols
+                    .start
+                    .saturating_add(right.as_one_based() as usize)
+                    .min(cols.end);
+                let first = rows.start;
+                let last = rows.end - 1;
+                rows.map(|y| {
+                    let from = if y == first { start } else { cols.start };
+                    let to = if y == last { end } else { cols.end };
+                    (y, from..to)
+                })
+                .collect()
+            }
+        };
+
+        let seqno = self.seqno;
+        for (y, cols) in area {
+            if !cols.is_empty() {
+                self.screen_mut()
+                    .change_cell_attributes(y, cols, seqno, &mut f);
+            }
+        }
+    }
+
     fn checksum_rectangle(&mut self, left: u32, top: u32, right: u32, bottom: u32) -> u16 {
         let y_origin = if self.dec_origin_mode {
             self.top_and_bottom_margins.start
@@ -2082,6 +2817,100 @@ impl TerminalState {
                 write!(self.writer, "\x1bP{}!~{:04x}\x1b\\", request_id, checksum).ok();
                 self.writer.flush().ok();
             }
+            Window::CopyRectangularArea {
+                top,
+                left,
+                bottom,
+                right,
+                dest_top,
+                dest_left,
+                ..
+            } => {
+                if let Some((rows, cols)) = self.rectangular_area(top, left, bottom, right) {
+                    let (limit_rows, limit_cols) = self.rectangular_area_limits();
+                    let dest_top = limit_rows
+                        .start
+                        .saturating_add(dest_top.as_zero_based() as VisibleRowIndex);
+                    let dest_left = limit_cols
+                        .start
+                        .saturating_add(dest_left.as_zero_based() as usize);
+                    let seqno = self.seqno;
+                    self.screen_mut().copy_rectangle(
+                        rows,
+                        cols,
+                        dest_top..limit_rows.end,
+                        dest_left..limit_cols.end,
+                        seqno,
+                    );
+                }
+            }
+            Window::FillRectangularArea {
+                ch,
+                top,
+                left,
+                bottom,
+                right,
+            } => {
+                if let Some((rows, cols)) = self.rectangular_area(top, left, bottom, right) {
+                    let seqno = self.seqno;
+                    let cell = Cell::new(ch, self.pen.clone_sgr_only());
+                    self.screen_mut().fill_rectangle(rows, cols, &cell, seqno);
+                }
+            }
+            Window::EraseRectangularArea {
+                top,
+                left,
+                bottom,
+                right,
+            } => {
+                if let Some((rows, cols)) = self.rectangular_area(top, left, bottom, right) {
+                    let seqno = self.seqno;
+                    let cell = Cell::blank_with_attrs(self.pen.clone_sgr_only());
+                    self.screen_mut().fill_rectangle(rows, cols, &cell, seqno);
+                }
+            }
+            Window::SelectiveEraseRectangularArea {
+                top,
+                left,
+                bottom,
+                right,
+            } => {
+                // We don't support DECSCA, so no characters are protected
+                // from being erased
+                if let Some((rows, cols)) = self.rectangular_area(top, left, bottom, right) {
+                    let seqno = self.seqno;
+                    self.screen_mut().erase_rectangle_text(rows, cols, seqno);
+                }
+            }
+            Window::SelectAttributeChangeExtent(extent) => {
+                self.attribute_change_extent = extent;
+            }
+            Window::ChangeAttributesInRectangularArea {
+                top,
+                left,
+                bottom,
+                right,
+                attributes,
+            } => {
+                self.change_area_attributes(top, left, bottom, right, |attrs| {
+                    for &attr in &attributes {
+                        change_attribute(attrs, attr);
+                    }
+                });
+            }
+            Window::ReverseAttributesInRectangularArea {
+                top,
+                left,
+                bottom,
+                right,
+                attributes,
+            } => {
+                self.change_area_attributes(top, left, bottom, right, |attrs| {
+                    for &attr in &attributes {
+                        reverse_attribute(attrs, attr);
+                    }
+                });
+            }
             Window::ResizeWindowCells { .. } => {
                 // We don't allow the application to change the window size; that's
                 // up to the user!
@@ -2137,6 +2966,12 @@ impl TerminalState {
 
     fn get_bidi_mode(&self) -> BidiMode {
         let mut mode = self.config.bidi_mode();
+        if let Some(enabled) = self.pane_bidi_mode.enabled {
+            mode.enabled = enabled;
+        }
+        if let Some(hint) = self.pane_bidi_mode.direction {
+            mode.hint = hint;
+        }
         if let Some(enabled) = &self.bidi_enabled {
             mode.enabled = *enabled;
         }
@@ -2713,33 +3548,31 @@ impl TerminalState {
         let mut zones = vec![];
 
         let first_stable_row = screen.phys_to_stable_row_index(0);
-        screen.for_each_phys_line_mut(|idx, line| {
+        screen.for_each_phys_semantic_zone(|idx, semantic_type, range| {
             let stable_row = first_stable_row + idx as StableRowIndex;
 
-            for zone_range in line.semantic_zone_ranges() {
-                let new_zone = match current_zone.as_ref() {
-                    None => true,
-                    Some(zone) => zone.semantic_type != zone_range.semantic_type,
-                };
-
-                if new_zone {
-                    if let Some(zone) = current_zone.take() {
-                        zones.push(zone);
-                    }
+            let new_zone = match current_zone.as_ref() {
+                None => true,
+                Some(zone) => zone.semantic_type != semantic_type,
+            };
 
-                    current_zone.replace(SemanticZone {
-                        start_x: zone_range.range.start as usize,
-                        start_y: stable_row,
-                        end_x: zone_range.range.end as usize,
-                        end_y: stable_row,
-                        semantic_type: zone_range.semantic_type,
-                    });
+            if new_zone {
+                if let Some(zone) = current_zone.take() {
+                    zones.push(zone);
                 }
 
-                if let Some(zone) = current_zone.as_mut() {
-                    zone.end_x = zone_range.range.end as usize;
-                    zone.end_y = stable_row;
-                }
+                current_zone.replace(SemanticZone {
+                    start_x: range.start as usize,
+                    start_y: stable_row,
+                    end_x: range.end as usize,
+                    end_y: stable_row,
+                    semantic_type,
+                });
+            }
+
+            if let Some(zone) = current_zone.as_mut() {
+                zone.end_x = range.end as usize;
+                zone.end_y = stable_row;
             }
         });
         if let Some(zone) = current_zone.take() {
@@ -5,6 +5,45 @@ use anyhow::bail;
 use std::io::Write;
 
 impl TerminalState {
+    /// Computes the 1-based pixel position that is reported for `event`
+    /// when SGR-Pixels (mode 1016) mouse reporting is enabled.
+    /// The size of a cell is derived from the pixel dimensions of the
+    /// terminal rather than being rounded to a whole number of pixels,
+    /// so that the reported position stays within the pixel size that
+    /// is reported to the application.
+    fn sgr_pixel_position(&mut self, event: &MouseEvent) -> (usize, usize) {
+        let cols = self.screen().physical_cols.max(1);
+        let rows = self.screen().physical_rows.max(1);
+        let (pixel_width, pixel_height) = (self.pixel_width, self.pixel_height);
+
+        // The cells of a double-width line are twice as wide as the
+        // column number suggests, and the gui layer reports the offset
+        // within the wider cell
+        let screen = self.screen_mut();
+        let line_idx = screen.phys_row(event.y);
+        let x = if screen.line_mut(line_idx).is_single_width() {
+            event.x
+        } else {
+            event.x * 2
+        };
+
+        let x = (x * pixel_width / cols) + event.x_pixel_offset.max(0) as usize;
+        let y =
+            (event.y.max(0) as usize * pixel_height / rows) + event.y_pixel_offset.max(0) as usize;
+
+        // Keep the position within the terminal, unless its
+        // pixel dimensions are unknown
+        let clamp = |value: usize, limit: usize| {
+            if limit == 0 {
+                value
+            } else {
+                value.min(limit - 1)
+            }
+        };
+
+        (clamp(x, pixel_width) + 1, clamp(y, pixel_height) + 1)
+    }
+
     /// Encode a coordinate value using X10 encoding or Utf8 encoding.
     /// Out of bounds coords are reported as the 0 byte value.
     fn encode_coord(&self, value: i64, dest: &mut Vec<u8>) {
@@ -75,6 +114,9 @@ impl TerminalState {
 
     fn mouse_wheel(&mut self, event: MouseEvent) -> anyhow::Result<()> {
         let (button, _button) = self.mouse_report_button_number(&event);
+        if self.mouse_tracking || self.button_event_mouse || self.any_event_mouse {
+            self.interrupt_chunked_paste()?;
+        }
 
         if self.mouse_encoding == MouseEncoding::SGR
             && (self.mouse_tracking || self.button_event_mouse || self.any_event_mouse)
@@ -96,25 +138,9 @@ impl TerminalState {
         } else if self.mouse_encoding == MouseEncoding::SgrPixels
             && (self.mouse_tracking || self.button_event_mouse || self.any_event_mouse)
         {
-            let height = self.screen.physical_rows as usize;
-            let width = self.screen.physical_cols as usize;
-            log::trace!(
-                "wheel {event:?} ESC [<{};{};{}M",
-                button,
-                (event.x * (self.pixel_width / width)) + event.x_pixel_offset.max(0) as usize + 1,
-                (event.y as usize * (self.pixel_height / height))
-                    + event.y_pixel_offset.max(0) as usize
-                    + 1
-            );
-            write!(
-                self.writer,
-                "\x1b[<{};{};{}M",
-                button,
-                (event.x * (self.pixel_width / width)) + event.x_pixel_offset.max(0) as usize + 1,
-                (event.y as usize * (self.pixel_height / height))
-                    + event.y_pixel_offset.max(0) as usize
-                    + 1
-            )?;
+            let (x, y) = self.sgr_pixel_position(&event);
+            log::trace!("wheel {event:?} ESC [<{};{};{}M", button, x, y);
+            write!(self.writer, "\x1b[<{};{};{}M", button, x, y)?;
             self.writer.flush()?;
         } else if self.mouse_tracking || self.button_event_mouse || self.any_event_mouse {
             self.encode_x10_or_utf8(event, button)?;
@@ -144,6 +170,7 @@ impl TerminalState {
         if !(self.mouse_tracking || self.button_event_mouse || self.any_event_mouse) {
             return Ok(());
         }
+        self.interrupt_chunked_paste()?;
 
         if self.mouse_encoding == MouseEncoding::SGR {
             log::trace!(
@@ -161,25 +188,9 @@ impl TerminalState {
             )?;
             self.writer.flush()?;
         } else if self.mouse_encoding == MouseEncoding::SgrPixels {
-            let height = self.screen.physical_rows as usize;
-            let width = self.screen.physical_cols as usize;
-            log::trace!(
-                "press {event:?} ESC [<{};{};{}M",
-                button,
-                (event.x * (self.pixel_width / width)) + event.x_pixel_offset.max(0) as usize + 1,
-                (event.y as usize * (self.pixel_height / height))
-                    + event.y_pixel_offset.max(0) as usize
-                    + 1
-            );
-            write!(
-                self.writer,
-                "\x1b[<{};{};{}M",
-                button,
-                (event.x * (self.pixel_width / width)) + event.x_pixel_offset.max(0) as usize + 1,
-                (event.y as usize * (self.pixel_height / height))
-                    + event.y_pixel_offset.max(0) as usize
-                    + 1
-            )?;
+            let (x, y) = self.sgr_pixel_position(&event);
+            log::trace!("press {event:?} ESC [<{};{};{}M", button, x, y);
+            write!(self.writer, "\x1b[<{};{};{}M", button, x, y)?;
             self.writer.flush()?;
         } else {
             self.encode_x10_or_utf8(event, button)?;
@@ -193,6 +204,7 @@ impl TerminalState {
         if !self.current_mouse_buttons.is_empty() {
             self.current_mouse_buttons.retain(|&b| b != button);
             if self.mouse_tracking || self.button_event_mouse || self.any_event_mouse {
+                self.interrupt_chunked_paste()?;
                 if self.mouse_encoding == MouseEncoding::SGR {
                     log::trace!(
                         "release {event:?} ESC [<{};{};{}m",
@@ -209,29 +221,9 @@ impl TerminalState {
                     )?;
                     self.writer.flush()?;
                 } else if self.mouse_encoding == MouseEncoding::SgrPixels {
-                    let height = self.screen.physical_rows as usize;
-                    let width = self.screen.physical_cols as usize;
-                    log::trace!(
-                        "release {event:?} ESC [<{};{};{}m",
-                        release_button,
-                        (event.x * (self.pixel_width / width))
-                            + event.x_pixel_offset.max(0) as usize
-                            + 1,
-                        (event.y as usize * (self.pixel_height / height))
-                            + event.y_pixel_offset.max(0) as usize
-                            + 1
-                    );
-                    write!(
-                        self.writer,
-                        "\x1b[<{};{};{}m",
-                        release_button,
-                        (event.x * (self.pixel_width / width))
-                            + event.x_pixel_offset.max(0) as usize
-                            + 1,
-                        (event.y as usize * (self.pixel_height / height))
-                            + event.y_pixel_offset.max(0) as usize
-                            + 1
-                    )?;
+                    let (x, y) = self.sgr_pixel_position(&event);
+                    log::trace!("release {event:?} ESC [<{};{};{}m", release_button, x, y);
+                    write!(self.writer, "\x1b[<{};{};{}m", release_button, x, y)?;
                     self.writer.flush()?;
                 } else {
                     let release_button = 3;
@@ -265,6 +257,7 @@ impl TerminalState {
                 _ => {}
             }
             self.last_mouse_move.replace(event);
+            self.interrupt_chunked_paste()?;
 
             let (button, _button) = self.mouse_report_button_number(&event);
             let button = 32 + button;
@@ -285,29 +278,9 @@ impl TerminalState {
                 )?;
                 self.writer.flush()?;
             } else if self.mouse_encoding == MouseEncoding::SgrPixels {
-                let height = self.screen.physical_rows as usize;
-                let width = self.screen.physical_cols as usize;
-                log::trace!(
-                    "move {event:?} ESC [<{};{};{}M",
-                    button,
-                    (event.x * (self.pixel_width / width))
-                        + event.x_pixel_offset.max(0) as usize
-                        + 1,
-                    (event.y as usize * (self.pixel_height / height))
-                        + event.y_pixel_offset.max(0) as usize
-                        + 1
-                );
-                write!(
-                    self.writer,
-                    "\x1b[<{};{};{}M",
-                    button,
-                    (event.x * (self.pixel_width / width))
-                        + event.x_pixel_offset.max(0) as usize
-                        + 1,
-                    (event.y as usize * (self.pixel_height / height))
-                        + event.y_pixel_offset.max(0) as usize
-                        + 1
-                )?;
+                let (x, y) = self.sgr_pixel_position(&event);
+                log::trace!("move {event:?} ESC [<{};{};{}M", button, x, y);
+                write!(self.writer, "\x1b[<{};{};{}M", button, x, y)?;
                 self.writer.flush()?;
             } else {
                 self.encode_x10_or_utf8(event, button)?;
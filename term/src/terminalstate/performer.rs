@@ -1,4 +1,5 @@
 use crate::terminal::{Alert, Progress};
+use crate::terminalstate::kitty::KITTY_PLACEHOLDER;
 use crate::terminalstate::{
     default_color_map, CharSet, MouseEncoding, TabStop, UnicodeVersionStackEntry,
 };
@@ -14,19 +15,23 @@ use termwiz::input::KeyboardEncoding;
 use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
 use url::Url;
 use wezterm_bidi::ParagraphDirectionHint;
+use wezterm_cell::color::ColorAttribute;
 use wezterm_cell::{
-    grapheme_column_width, is_white_space_grapheme, Cell, CellAttributes, SemanticType,
+    grapheme_column_width, is_white_space_grapheme, Blink, Cell, CellAttributes, CodepointClusters,
+    Intensity, SemanticType, Underline,
 };
 use wezterm_escape_parser::csi::{
-    CharacterPath, EraseInDisplay, Keyboard, KittyKeyboardFlags, KittyKeyboardMode,
+    AttributeChangeExtent, CharacterPath, EraseInDisplay, Keyboard, KittyKeyboardFlags,
+    KittyKeyboardMode,
 };
 use wezterm_escape_parser::osc::{
-    ChangeColorPair, ColorOrQuery, FinalTermSemanticPrompt, ITermProprietary,
+    ChangeColorPair, ColorOrQuery, FinalTermPromptKind, FinalTermSemanticPrompt, ITermProprietary,
     ITermUnicodeVersionOp, Selection,
 };
 use wezterm_escape_parser::{
     Action, ControlCode, DeviceControlMode, Esc, EscCode, OperatingSystemCommand, CSI,
 };
+use wezterm_surface::CursorShape;
 
 /// A helper struct for implementing `vtparse::VTActor` while compartmentalizing
 /// the terminal state and the embedding/host terminal interface
@@ -131,7 +136,17 @@ impl<'a> Performer<'a> {
             p.as_str()
         };
 
-        for g in Graphemes::new(text) {
+        let clusters: Box<dyn Iterator<Item = &str> + '_> = if self.grapheme_clustering {
+            Box::new(Graphemes::new(text))
+        } else {
+            Box::new(CodepointClusters::new(text, self.unicode_version.clone()))
+        };
+
+        // The width available on the cursor row, which is only looked
+        // up again when wrapping moves the cursor onto another line
+        let mut row_width: Option<usize> = None;
+
+        for g in clusters {
             let g = self.remap_grapheme(g);
 
             let mut print_width = grapheme_column_width(g, Some(&self.unicode_version));
@@ -192,21 +207,53 @@ impl<'a> Performer<'a> {
                     }
                 }
                 self.new_line(true);
+                row_width = None;
             }
 
-            let x = self.cursor.x;
             let y = self.cursor.y;
-            let width = self.left_and_right_margins.end;
+            // Only the left half of the columns are available
+            // on a double-width line
+            let line_width = match row_width {
+                Some(line_width) => line_width,
+                None => {
+                    let screen = self.screen_mut();
+                    let idx = screen.phys_row(y);
+                    let line_width = if screen.line_mut(idx).is_single_width() {
+                        usize::MAX
+                    } else {
+                        (screen.physical_cols / 2).max(1)
+                    };
+                    row_width = Some(line_width);
+                    line_width
+                }
+            };
+            let width = self.left_and_right_margins.end.min(line_width);
+            if self.cursor.x >= width {
+                self.cursor.x = width - 1;
+            }
+            let x = self.cursor.x;
 
-            let pen = self.pen.clone();
+            let mut pen = self.pen.clone();
+
+            // A kitty image placeholder is displayed as a blank cell
+            // showing its portion of the virtual placement
+            let g = match self.kitty_placeholder_image(g, &pen) {
+                Some(image) => {
+                    pen.attach_image(Box::new(image));
+                    " "
+                }
+                None if g.starts_with(KITTY_PLACEHOLDER) && self.config.enable_kitty_graphics() => {
+                    " "
+                }
+                None => g,
+            };
 
             let wrappable = x + print_width >= width;
 
             if self.insert {
-                let margin = self.left_and_right_margins.end;
                 let screen = self.screen_mut();
                 for _ in x..x + print_width as usize {
-                    screen.insert_cell(x, y, margin, seqno);
+                    screen.insert_cell(x, y, width, seqno);
                 }
             }
 
@@ -334,6 +381,53 @@ impl<'a> Performer<'a> {
                                 .ok();
                                 self.writer.flush().ok();
                             }
+                            &[b'm'] => {
+                                // SGR - the current graphic rendition
+                                write!(self.writer, "{}1$r{}m{}", DCS, sgr_params(&self.pen), ST)
+                                    .ok();
+                                self.writer.flush().ok();
+                            }
+                            &[b' ', b'q'] => {
+                                // DECSCUSR - cursor style
+                                let style = match self.cursor.shape {
+                                    CursorShape::Default => 0,
+                                    CursorShape::BlinkingBlock => 1,
+                                    CursorShape::SteadyBlock => 2,
+                                    CursorShape::BlinkingUnderline => 3,
+                                    CursorShape::SteadyUnderline => 4,
+                                    CursorShape::BlinkingBar => 5,
+                                    CursorShape::SteadyBar => 6,
+                                };
+                                write!(self.writer, "{}1$r{} q{}", DCS, style, ST).ok();
+                                self.writer.flush().ok();
+                            }
+                            &[b't'] => {
+                                // DECSLPP - lines per page
+                                let rows = self.screen().physical_rows;
+                                write!(self.writer, "{}1$r{}t{}", DCS, rows, ST).ok();
+                                self.writer.flush().ok();
+                            }
+                            &[b'$', b'|'] => {
+                                // DECSCPP - columns per page
+                                let cols = self.screen().physical_cols;
+                                write!(self.writer, "{}1$r{}$|{}", DCS, cols, ST).ok();
+                                self.writer.flush().ok();
+                            }
+                            &[b'*', b'|'] => {
+                                // DECSNLS - lines per screen
+                                let rows = self.screen().physical_rows;
+                                write!(self.writer, "{}1$r{}*|{}", DCS, rows, ST).ok();
+                                self.writer.flush().ok();
+                            }
+                            &[b'*', b'x'] => {
+                                // DECSACE - attribute change extent
+                                let extent = match self.attribute_change_extent {
+                                    AttributeChangeExtent::Stream => 1,
+                                    AttributeChangeExtent::Rectangle => 2,
+                                };
+                                write!(self.writer, "{}1$r{}*x{}", DCS, extent, ST).ok();
+                                self.writer.flush().ok();
+                            }
                             _ => {
                                 if self.config.log_unknown_escape_sequences() {
                                     log::warn!("unhandled DECRQSS {:?}", s);
@@ -509,16 +603,19 @@ impl<'a> Performer<'a> {
             CSI::Window(window) => self.state.perform_csi_window(*window),
             CSI::SelectCharacterPath(CharacterPath::ImplementationDefault, _) => {
                 self.state.bidi_hint.take();
+                self.state.apply_bidi_mode_to_cursor_row();
             }
             CSI::SelectCharacterPath(CharacterPath::LeftToRightOrTopToBottom, _) => {
                 self.state
                     .bidi_hint
                     .replace(ParagraphDirectionHint::LeftToRight);
+                self.state.apply_bidi_mode_to_cursor_row();
             }
             CSI::SelectCharacterPath(CharacterPath::RightToLeftOrBottomToTop, _) => {
                 self.state
                     .bidi_hint
                     .replace(ParagraphDirectionHint::RightToLeft);
+                self.state.apply_bidi_mode_to_cursor_row();
             }
             CSI::Keyboard(Keyboard::SetKittyState { flags, mode }) => {
                 if self.config.enable_kitty_keyboard() {
@@ -686,6 +783,8 @@ impl<'a> Performer<'a> {
                 self.dec_auto_wrap = true;
                 self.reverse_wraparound_mode = false;
                 self.reverse_video_mode = false;
+                self.bidi_enabled.take();
+                self.bidi_hint.take();
                 self.dec_origin_mode = false;
                 self.use_private_color_registers_for_each_graphic = false;
                 self.color_map = default_color_map();
@@ -694,6 +793,7 @@ impl<'a> Performer<'a> {
                 self.dec_ansi_mode = false;
                 self.application_keypad = false;
                 self.bracketed_paste = false;
+                self.color_scheme_updates = false;
                 self.focus_tracking = false;
                 self.mouse_tracking = false;
                 self.mouse_encoding = MouseEncoding::X10;
@@ -713,6 +813,7 @@ impl<'a> Performer<'a> {
                 self.left_and_right_margins = 0..self.screen().physical_cols;
                 self.unicode_version = self.config.unicode_version();
                 self.unicode_version_stack.clear();
+                self.grapheme_clustering = self.config.grapheme_clustering();
                 self.suppress_initial_title_change = false;
                 self.accumulating_title.take();
                 self.progress = Progress::default();
@@ -785,12 +886,29 @@ impl<'a> Performer<'a> {
                 let selection = selection_to_selection(selection);
                 self.set_clipboard_contents(selection, None).ok();
             }
-            OperatingSystemCommand::QuerySelection(_) => {}
+            OperatingSystemCommand::QuerySelection(selection) => {
+                let selection = selection_to_selection(selection);
+                if let Err(err) = self.query_clipboard_contents(selection) {
+                    error!(
+                        "failed to query clipboard in response to OSC 52: {:#?}",
+                        err
+                    );
+                }
+            }
             OperatingSystemCommand::SetSelection(selection, selection_data) => {
                 let selection = selection_to_selection(selection);
-                match self.set_clipboard_contents(selection, Some(selection_data)) {
-                    Ok(_) => (),
-                    Err(err) => error!("failed to set clipboard in response to OSC 52: {:#?}", err),
+                let max_bytes = self.config.osc52_max_bytes();
+                if selection_data.len() > max_bytes {
+                    error!(
+                        "ignoring OSC 52 request to set clipboard to {} bytes, \
+                         which exceeds osc52_max_bytes={}",
+                        selection_data.len(),
+                        max_bytes
+                    );
+                } else if let Err(err) =
+                    self.set_clipboard_contents(selection, Some(selection_data))
+                {
+                    error!("failed to set clipboard in response to OSC 52: {:#?}", err);
                 }
             }
             OperatingSystemCommand::ITermProprietary(iterm) => match iterm {
@@ -853,6 +971,21 @@ impl<'a> Performer<'a> {
                         }
                     }
                 }
+                ITermProprietary::SetBackgroundImageFile(path) => {
+                    let mut background = self.pane_background().clone();
+                    background.image = if path.is_empty() { None } else { Some(path) };
+                    self.set_pane_background(background);
+                }
+                ITermProprietary::SetBackgroundColor(color) => {
+                    let mut background = self.pane_background().clone();
+                    background.color = color;
+                    self.set_pane_background(background);
+                }
+                ITermProprietary::SetBackgroundOpacity(opacity) => {
+                    let mut background = self.pane_background().clone();
+                    background.opacity = opacity.map(|o| o.into_inner());
+                    self.set_pane_background(background);
+                }
                 _ => {
                     if self.config.log_unknown_escape_sequences() {
                         log::warn!("unhandled iterm2: {:?}", iterm);
@@ -867,17 +1000,22 @@ impl<'a> Performer<'a> {
                 FinalTermSemanticPrompt::FreshLineAndStartPrompt { .. },
             ) => {
                 self.fresh_line();
+                self.record_prompt_start();
                 self.pen.set_semantic_type(SemanticType::Prompt);
             }
             OperatingSystemCommand::FinalTermSemanticPrompt(
-                FinalTermSemanticPrompt::StartPrompt(_),
+                FinalTermSemanticPrompt::StartPrompt(kind),
             ) => {
+                if kind == FinalTermPromptKind::Initial {
+                    self.record_prompt_start();
+                }
                 self.pen.set_semantic_type(SemanticType::Prompt);
             }
             OperatingSystemCommand::FinalTermSemanticPrompt(
                 FinalTermSemanticPrompt::MarkEndOfCommandWithFreshLine { .. },
             ) => {
                 self.fresh_line();
+                self.record_prompt_start();
                 self.pen.set_semantic_type(SemanticType::Prompt);
             }
             OperatingSystemCommand::FinalTermSemanticPrompt(
@@ -894,12 +1032,18 @@ impl<'a> Performer<'a> {
             OperatingSystemCommand::FinalTermSemanticPrompt(
                 FinalTermSemanticPrompt::MarkEndOfInputAndStartOfOutput { .. },
             ) => {
+                self.record_command_start();
                 self.pen.set_semantic_type(SemanticType::Output);
             }
 
             OperatingSystemCommand::FinalTermSemanticPrompt(
-                FinalTermSemanticPrompt::CommandStatus { .. },
-            ) => {}
+                FinalTermSemanticPrompt::CommandStatus { status, .. },
+            ) => {
+                self.record_command_status(status);
+                if status != 0 {
+                    self.record_failed_command();
+                }
+            }
 
             OperatingSystemCommand::SystemNotification(message) => {
                 if let Some(handler) = self.alert_handler.as_mut() {
@@ -941,6 +1085,7 @@ impl<'a> Performer<'a> {
             }
             OperatingSystemCommand::ChangeColorNumber(specs) => {
                 log::trace!("ChangeColorNumber: {:?}", specs);
+                let mut changed = false;
                 for pair in specs {
                     match pair.color {
                         ColorOrQuery::Query => {
@@ -956,11 +1101,15 @@ impl<'a> Performer<'a> {
                         }
                         ColorOrQuery::Color(c) => {
                             self.palette_mut().colors.0[pair.palette_index as usize] = c;
+                            changed = true;
                         }
                     }
                 }
-                self.implicit_palette_reset_if_same_as_configured();
-                self.palette_did_change();
+                // Queries alone don't need to repaint anything
+                if changed {
+                    self.implicit_palette_reset_if_same_as_configured();
+                    self.palette_did_change();
+                }
             }
 
             OperatingSystemCommand::ResetColors(colors) => {
@@ -973,7 +1122,7 @@ impl<'a> Performer<'a> {
                     if self.palette.is_none() {
                         // Already at the defaults
                     } else {
-                        let base = self.config.color_palette();
+                        let base = self.base_palette();
                         for c in colors {
                             let c = c as usize;
                             self.palette_mut().colors.0[c] = base.colors.0[c];
@@ -988,6 +1137,7 @@ impl<'a> Performer<'a> {
                 log::trace!("ChangeDynamicColors: {:?} {:?}", first_color, colors);
                 use wezterm_escape_parser::osc::DynamicColorNumber;
                 let mut idx: u8 = first_color as u8;
+                let mut changed = false;
                 for color in colors {
                     let which_color: Option<DynamicColorNumber> = FromPrimitive::from_u8(idx);
                     log::trace!("ChangeDynamicColors item: {:?}", which_color);
@@ -1004,7 +1154,10 @@ impl<'a> Performer<'a> {
                                         write!(self.writer, "{}", response).ok();
                                         self.writer.flush().ok();
                                     }
-                                    ColorOrQuery::Color(c) => self.palette_mut().$name = c.into(),
+                                    ColorOrQuery::Color(c) => {
+                                        self.palette_mut().$name = c.into();
+                                        changed = true;
+                                    }
                                 }
                             };
                         }
@@ -1035,8 +1188,10 @@ impl<'a> Performer<'a> {
                     }
                     idx += 1;
                 }
-                self.implicit_palette_reset_if_same_as_configured();
-                self.palette_did_change();
+                if changed {
+                    self.implicit_palette_reset_if_same_as_configured();
+                    self.palette_did_change();
+                }
             }
 
             OperatingSystemCommand::ResetDynamicColor(color) => {
@@ -1049,7 +1204,7 @@ impl<'a> Performer<'a> {
                             if self.palette.is_none() {
                                 // Already at the defaults
                             } else {
-                                let base = self.config.color_palette();
+                                let base = self.base_palette();
                                 self.palette_mut().$name = base.$name;
                             }
                         };
@@ -1107,3 +1262,67 @@ fn selection_to_selection(sel: Selection) -> ClipboardSelection {
         _ => ClipboardSelection::Clipboard,
     }
 }
+
+/// Returns the SGR parameters that select `attrs`, for reporting
+/// them in response to DECRQSS
+fn sgr_params(attrs: &CellAttributes) -> String {
+    fn color(
+        params: &mut Vec<String>,
+        color: ColorAttribute,
+        extended: u8,
+        short: Option<(u8, u8)>,
+    ) {
+        match color {
+            ColorAttribute::Default => {}
+            ColorAttribute::PaletteIndex(idx) => match short {
+                Some((base, _)) if idx < 8 => params.push((base + idx).to_string()),
+                Some((_, bright)) if idx < 16 => params.push((bright + idx - 8).to_string()),
+                _ => params.push(format!("{}:5:{}", extended, idx)),
+            },
+            ColorAttribute::TrueColorWithPaletteFallback(c, _)
+            | ColorAttribute::TrueColorWithDefaultFallback(c) => {
+                let (r, g, b, _) = c.to_srgb_u8();
+                params.push(format!("{}:2::{}:{}:{}", extended, r, g, b));
+            }
+        }
+    }
+
+    let mut params = vec!["0".to_string()];
+    match attrs.intensity() {
+        Intensity::Normal => {}
+        Intensity::Bold => params.push("1".to_string()),
+        Intensity::Half => params.push("2".to_string()),
+    }
+    if attrs.italic() {
+        params.push("3".to_string());
+    }
+    match attrs.underline() {
+        Underline::None => {}
+        Underline::Single => params.push("4".to_string()),
+        Underline::Double => params.push("21".to_string()),
+        Underline::Curly => params.push("4:3".to_string()),
+        Underline::Dotted => params.push("4:4".to_string()),
+        Underline::Dashed => params.push("4:5".to_string()),
+    }
+    match attrs.blink() {
+        Blink::None => {}
+        Blink::Slow => params.push("5".to_string()),
+        Blink::Rapid => params.push("6".to_string()),
+    }
+    if attrs.reverse() {
+        params.push("7".to_string());
+    }
+    if attrs.invisible() {
+        params.push("8".to_string());
+    }
+    if attrs.strikethrough() {
+        params.push("9".to_string());
+    }
+    if attrs.overline() {
+        params.push("53".to_string());
+    }
+    color(&mut params, attrs.foreground(), 38, Some((30, 90)));
+    color(&mut params, attrs.background(), 48, Some((40, 100)));
+    color(&mut params, attrs.underline_color(), 58, None);
+    params.join(";")
+}
@@ -1,4 +1,5 @@
 use super::*;
+use wezterm_bidi::ParagraphDirectionHint;
 
 /// In this issue, the `CSI 2 P` sequence incorrectly removed two
 /// cells from the line, leaving them effectively blank, when those
@@ -401,3 +402,221 @@ fn test_ed_erase_scrollback() {
     term.print("b");
     assert_all_contents(&term, file!(), line!(), &["111", "222", "ab"]);
 }
+
+#[test]
+fn test_decfra() {
+    let mut term = TestTerm::new(3, 5, 0);
+    term.print("\x1b[88;1;2;2;3$x");
+    assert_visible_contents(&term, file!(), line!(), &[" XX", " XX", ""]);
+    // The bottom right corner defaults to the end of the page
+    term.print("\x1b[46;3;4$x");
+    assert_visible_contents(&term, file!(), line!(), &[" XX", " XX", "   .."]);
+    // Control characters are not accepted
+    term.print("\x1b[10;1;1;1;1$x");
+    assert_visible_contents(&term, file!(), line!(), &[" XX", " XX", "   .."]);
+}
+
+#[test]
+fn test_deccra() {
+    let mut term = TestTerm::new(3, 5, 0);
+    term.print("abc\r\ndef");
+    // The source and destination overlap, and the destination
+    // extends beyond the right edge of the page
+    term.print("\x1b[1;1;2;3;1;2;3;1$v");
+    assert_visible_contents(&term, file!(), line!(), &["abc", "deabc", "  def"]);
+}
+
+#[test]
+fn test_deccra_origin_mode() {
+    let mut term = TestTerm::new(4, 5, 0);
+    term.print("abc\r\ndef\r\nghi");
+    term.set_scroll_region(1, 3);
+    term.print("\x1b[?6h");
+    // Coordinates are relative to the scroll region, and the copy
+    // is clipped to its bottom
+    term.print("\x1b[1;1;2;2;1;3;4$v");
+    assert_visible_contents(&term, file!(), line!(), &["abc", "def", "ghi", "   de"]);
+}
+
+#[test]
+fn test_decera() {
+    let mut term = TestTerm::new(3, 5, 0);
+    term.print("abcde\r\nfghij\r\nklmno");
+    term.print("\x1b[1;2;2;3$z");
+    assert_visible_contents(&term, file!(), line!(), &["a  de", "f  ij", "klmno"]);
+    // An empty rectangle is ignored
+    term.print("\x1b[3;3;2;2$z");
+    assert_visible_contents(&term, file!(), line!(), &["a  de", "f  ij", "klmno"]);
+}
+
+#[test]
+fn test_decsera() {
+    let mut term = TestTerm::new(1, 5, 0);
+    term.print("\x1b[1mbold\x1b[m");
+    term.print("\x1b[1;2;1;3${");
+
+    let bold = CellAttributes::default()
+        .set_intensity(Intensity::Bold)
+        .clone();
+    assert_lines_equal(
+        file!(),
+        line!(),
+        &term.screen().visible_lines(),
+        &[Line::from_text("b  d", &bold, SEQ_ZERO, None)],
+        Compare::TEXT | Compare::ATTRS,
+    );
+}
+
+#[test]
+fn test_decrqss_sgr() {
+    let mut term = TestTerm::new(1, 5, 0);
+    term.print("\x1bP$qm\x1b\\");
+    assert_eq!(term.take_output(), "\x1bP1$r0m\x1b\\");
+
+    term.print("\x1b[1;4:3;38:2::10:20:30;48;5;200m");
+    term.print("\x1bP$qm\x1b\\");
+    assert_eq!(
+        term.take_output(),
+        "\x1bP1$r0;1;4:3;38:2::10:20:30;48:5:200m\x1b\\"
+    );
+
+    // The first sixteen palette entries use the short forms,
+    // except for the underline color which has none
+    term.print("\x1b[0;2;3;21;31;105;58:5:9m");
+    term.print("\x1bP$qm\x1b\\");
+    assert_eq!(term.take_output(), "\x1bP1$r0;2;3;21;31;105;58:5:9m\x1b\\");
+
+    term.print("\x1b[0;4:5m");
+    term.print("\x1bP$qm\x1b\\");
+    assert_eq!(term.take_output(), "\x1bP1$r0;4:5m\x1b\\");
+}
+
+#[test]
+fn test_decrqss_cursor_style() {
+    let mut term = TestTerm::new(1, 5, 0);
+    term.print("\x1bP$q q\x1b\\");
+    assert_eq!(term.take_output(), "\x1bP1$r0 q\x1b\\");
+
+    term.print("\x1b[4 q");
+    term.print("\x1bP$q q\x1b\\");
+    assert_eq!(term.take_output(), "\x1bP1$r4 q\x1b\\");
+}
+
+#[test]
+fn test_decrqss_page_size() {
+    let mut term = TestTerm::new(5, 10, 0);
+    term.print("\x1bP$qt\x1b\\");
+    assert_eq!(term.take_output(), "\x1bP1$r5t\x1b\\");
+    term.print("\x1bP$q$|\x1b\\");
+    assert_eq!(term.take_output(), "\x1bP1$r10$|\x1b\\");
+    term.print("\x1bP$q*|\x1b\\");
+    assert_eq!(term.take_output(), "\x1bP1$r5*|\x1b\\");
+
+    // Unsupported settings are reported as invalid
+    term.print("\x1bP$qz\x1b\\");
+    assert_eq!(term.take_output(), "\x1bP0$r\x1b\\");
+}
+
+#[test]
+fn test_scp_applies_to_current_line() {
+    let mut term = TestTerm::new(2, 5, 0);
+    term.print("abc");
+    term.print("\x1b[2 k");
+
+    let lines = term.screen().visible_lines();
+    assert_eq!(
+        lines[0].bidi_info(),
+        (false, ParagraphDirectionHint::RightToLeft)
+    );
+    assert_eq!(
+        lines[1].bidi_info(),
+        (false, ParagraphDirectionHint::LeftToRight)
+    );
+
+    term.print("\x1b[ k");
+    assert_eq!(
+        term.screen().visible_lines()[0].bidi_info(),
+        (false, ParagraphDirectionHint::LeftToRight)
+    );
+}
+
+#[test]
+fn test_pane_bidi_mode() {
+    let mut term = TestTerm::new(2, 5, 0);
+    term.set_pane_bidi_mode(PaneBidiMode {
+        enabled: Some(true),
+        direction: Some(ParagraphDirectionHint::AutoRightToLeft),
+    });
+
+    for line in term.screen().visible_lines() {
+        assert_eq!(
+            line.bidi_info(),
+            (true, ParagraphDirectionHint::AutoRightToLeft)
+        );
+    }
+
+    // A reset clears the escape sequence state, but not the pane override
+    term.print("\x1b[8l\x1bc");
+    term.print("\r\n\r\n");
+    assert_eq!(
+        term.screen().visible_lines()[1].bidi_info(),
+        (true, ParagraphDirectionHint::AutoRightToLeft)
+    );
+}
+
+#[test]
+fn test_deccra_wide_chars() {
+    let mut term = TestTerm::new(2, 6, 0);
+    term.print("a\u{4e2d}bcd\r\nxxxxxx");
+    // A wide character that is clipped by the right edge
+    // of the source rectangle is copied as a blank
+    term.print("\x1b[1;1;1;2;1;2;1;1$v");
+    assert_visible_contents(&term, file!(), line!(), &["a\u{4e2d}bcd", "a xxxx"]);
+    // Likewise for one that would be clipped by the right
+    // margin at the destination
+    term.print("\x1b[?69h\x1b[1;4s\x1b[?6h");
+    term.print("\x1b[1;2;1;3;1;2;4;1$v");
+    assert_visible_contents(&term, file!(), line!(), &["a\u{4e2d}bcd", "a x xx"]);
+}
+
+/// Returns a string per visible line, with a `b` for each bold
+/// cell and a `.` for each of the others
+fn bold_cells(term: &TestTerm) -> Vec<String> {
+    term.screen()
+        .visible_lines()
+        .iter()
+        .map(|line| {
+            line.visible_cells()
+                .map(|cell| {
+                    if cell.attrs().intensity() == Intensity::Bold {
+                        'b'
+                    } else {
+                        '.'
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn test_deccara_decrara() {
+    let mut term = TestTerm::new(3, 4, 0);
+    term.print("abcd\r\nefgh\r\nijkl");
+
+    // By default, the area is a stream from the first
+    // position to the last
+    term.print("\x1b[1;3;2;2;1$r");
+    assert_eq!(bold_cells(&term), vec!["..bb", "bb..", "...."]);
+
+    // DECSACE selects a rectangle instead
+    term.print("\x1b[2*x\x1b[1;2;3;3;1$t");
+    assert_eq!(bold_cells(&term), vec![".b.b", "b.b.", ".bb."]);
+
+    // The attribute defaults to 0, which turns them all off
+    term.print("\x1b[1;1;2;4$r");
+    assert_eq!(bold_cells(&term), vec!["....", "....", ".bb."]);
+
+    // Only the attributes are changed
+    assert_visible_contents(&term, file!(), line!(), &["abcd", "efgh", "ijkl"]);
+}
@@ -38,19 +38,42 @@ impl Clipboard for LocalClip {
     }
 }
 
+/// Collects the bytes written by the terminal
+#[derive(Clone, Default)]
+struct SharedWriter {
+    buf: Arc<Mutex<Vec<u8>>>,
+}
+
+impl std::io::Write for SharedWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.buf.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
 struct TestTerm {
     term: Terminal,
+    output: SharedWriter,
 }
 
 #[derive(Debug)]
 struct TestTermConfig {
     scrollback: usize,
+    compress_scrollback: bool,
 }
 impl TerminalConfiguration for TestTermConfig {
     fn scrollback_size(&self) -> usize {
         self.scrollback
     }
 
+    fn compress_scrollback(&self) -> bool {
+        self.compress_scrollback
+    }
+
     fn color_palette(&self) -> ColorPalette {
         ColorPalette::default()
     }
@@ -58,11 +81,23 @@ impl TerminalConfiguration for TestTermConfig {
 
 impl TestTerm {
     fn new(height: usize, width: usize, scrollback: usize) -> Self {
+        Self::with_config(
+            height,
+            width,
+            TestTermConfig {
+                scrollback,
+                compress_scrollback: false,
+            },
+        )
+    }
+
+    fn with_config(height: usize, width: usize, config: TestTermConfig) -> Self {
         let _ = env_logger::Builder::new()
             .is_test(true)
             .filter_level(log::LevelFilter::Trace)
             .try_init();
 
+        let output = SharedWriter::default();
         let mut term = Terminal::new(
             TerminalSize {
                 rows: height,
@@ -71,15 +106,15 @@ impl TestTerm {
                 pixel_height: height * 16,
                 dpi: 0,
             },
-            Arc::new(TestTermConfig { scrollback }),
+            Arc::new(config),
             "WezTerm",
             "O_o",
-            Box::new(Vec::new()),
+            Box::new(output.clone()),
         );
         let clip: Arc<dyn Clipboard> = Arc::new(LocalClip::new());
         term.set_clipboard(&clip);
 
-        let mut term = Self { term };
+        let mut term = Self { term, output };
 
         term.set_auto_wrap(true);
 
@@ -90,6 +125,16 @@ impl TestTerm {
         self.term.advance_bytes(bytes);
     }
 
+    /// Returns, and clears, the replies that the terminal has
+    /// sent back to the application
+    fn take_output(&self) -> String {
+        while self.term.pending_write_bytes() > 0 {
+            std::thread::yield_now();
+        }
+        let buf = std::mem::take(&mut *self.output.buf.lock().unwrap());
+        String::from_utf8(buf).unwrap()
+    }
+
     fn set_mode(&mut self, mode: &str, enable: bool) {
         self.print(CSI);
         self.print(mode);
@@ -346,6 +391,47 @@ fn test_semantic_1539() {
     );
 }
 
+#[test]
+fn test_failed_command_rows() {
+    use wezterm_escape_parser::osc::FinalTermSemanticPrompt;
+    let mut term = TestTerm::new(5, 10, 0);
+    let status = |status| {
+        OperatingSystemCommand::FinalTermSemanticPrompt(FinalTermSemanticPrompt::CommandStatus {
+            status,
+            aid: None,
+        })
+        .to_string()
+    };
+    term.print(format!("ok\r\n{}oops\r\n", status(0)));
+    term.print(format!("{}failed\r\n{}", status(1), status(2)));
+
+    assert_eq!(term.failed_command_rows(), vec![2, 3]);
+}
+
+#[test]
+fn test_command_history() {
+    let mut term = TestTerm::new(5, 10, 0);
+    term.print("\x1b]7;file://host/tmp\x1b\\");
+    term.print("\x1b]133;A\x1b\\$ \x1b]133;B\x1b\\ls\r\n\x1b]133;C\x1b\\");
+    term.print("a b\r\n\x1b]133;D;1\x1b\\");
+    term.print("\x1b]133;A\x1b\\$ \x1b]133;B\x1b\\sleep 1\r\n\x1b]133;C\x1b\\");
+
+    let history = term.command_history();
+    assert_eq!(history.len(), 2);
+
+    assert_eq!(history[0].prompt_row, 0);
+    assert_eq!(history[0].command, "ls");
+    assert_eq!(history[0].cwd.as_deref(), Some("file://host/tmp"));
+    assert_eq!(history[0].status, Some(1));
+    assert!(history[0].duration.is_some());
+
+    // The second command is still running
+    assert_eq!(history[1].prompt_row, 2);
+    assert_eq!(history[1].command, "sleep 1");
+    assert_eq!(history[1].status, None);
+    assert_eq!(history[1].duration, None);
+}
+
 #[test]
 fn test_semantic() {
     use wezterm_escape_parser::osc::FinalTermSemanticPrompt;
@@ -755,6 +841,20 @@ fn test_dec_double_width() {
     assert!(lines[3].is_single_width());
 }
 
+/// Text on a double-width line wraps at half of the width
+#[test]
+fn test_dec_double_width_wrap() {
+    let mut term = TestTerm::new(3, 10, 0);
+
+    term.print("\u{1b}#6abcdefgh");
+    assert_visible_contents(&term, file!(), line!(), &["abcde", "fgh", ""]);
+
+    // Wrapping from the bottom row scrolls a single-width line
+    // into the same row, which has the full width available
+    term.print("\r\n\u{1b}#6ABCDEFGHIJKLM");
+    assert_visible_contents(&term, file!(), line!(), &["fgh", "ABCDE", "FGHIJKLM"]);
+}
+
 /// This test skips over an edge case with cursor positioning,
 /// while sizing down, but tries to trip over the same edge
 /// case while sizing back up again
@@ -1162,6 +1262,34 @@ fn test_emoji_with_modifier() {
     );
 }
 
+#[test]
+fn test_grapheme_clustering() {
+    let family = "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}";
+    let flag = "\u{1f1fa}\u{1f1f8}";
+    let thumbs_up_medium_tone = "\u{1f44d}\u{1f3fd}";
+
+    let mut term = TestTerm::new(3, 20, 0);
+    term.print(family);
+    term.assert_cursor_pos(2, 0, Some("ZWJ sequence is a single cluster"), None);
+    term.print(flag);
+    term.assert_cursor_pos(4, 0, Some("flag is a single cluster"), None);
+    term.print(thumbs_up_medium_tone);
+    term.assert_cursor_pos(6, 0, Some("skin tone is a single cluster"), None);
+
+    // With mode 2027 reset, each codepoint that has a width gets its own cell
+    term.print("\x1b[?2027l\r\n");
+    term.print(family);
+    term.assert_cursor_pos(6, 1, Some("ZWJ sequence is split"), None);
+    term.print(flag);
+    term.assert_cursor_pos(8, 1, Some("flag is split"), None);
+    term.print(thumbs_up_medium_tone);
+    term.assert_cursor_pos(12, 1, Some("skin tone is split"), None);
+
+    term.print("\x1b[?2027h\r\n");
+    term.print(family);
+    term.assert_cursor_pos(2, 2, Some("clustering is restored"), None);
+}
+
 #[test]
 fn test_1573() {
     let sequence = "\u{1112}\u{1161}\u{11ab}";
@@ -1289,6 +1417,360 @@ fn test_region_scrollback_limit() {
     assert_eq!(term.screen().visible_row_to_stable_row(4), 7);
 }
 
+#[test]
+fn test_compressed_scrollback() {
+    let mut term = TestTerm::with_config(
+        4,
+        10,
+        TestTermConfig {
+            scrollback: 1000,
+            compress_scrollback: true,
+        },
+    );
+    for i in 0..2000 {
+        term.print(format!("{}\r\n", i));
+    }
+
+    // The oldest lines have been removed from the front of the
+    // scrollback, and the most recent are uncompressed
+    let expect: Vec<String> = (997..2000)
+        .map(|i| i.to_string())
+        .chain(std::iter::once(String::new()))
+        .collect();
+    let expect: Vec<&str> = expect.iter().map(String::as_str).collect();
+    assert_all_contents(&term, file!(), line!(), &expect);
+    assert_eq!(term.screen().visible_row_to_stable_row(0), 1997);
+
+    let range = term.screen().stable_range(&(1100..1103));
+    let lines = term.screen().lines_in_phys_range(range);
+    let text: Vec<String> = lines.iter().map(|l| l.as_str().to_string()).collect();
+    assert_eq!(text, vec!["1100", "1101", "1102"]);
+
+    // Modifying compressed lines is reflected when they are next read
+    term.screen_mut()
+        .line_mut(3)
+        .set_cell(0, Cell::new('X', CellAttributes::default()), SEQ_ZERO);
+    term.print("2000\r\n");
+    assert_eq!(term.screen().lines_in_phys_range(2..3)[0].as_str(), "X000");
+
+    // Resizing decompresses the scrollback in order to rewrap it
+    term.resize(TerminalSize {
+        rows: 4,
+        cols: 2,
+        pixel_width: 0,
+        pixel_height: 0,
+        dpi: 0,
+    });
+    assert_visible_contents(&term, file!(), line!(), &["19", "99", "20", "00"]);
+}
+
+#[test]
+fn test_disk_scrollback() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut term = TestTerm::with_config(
+        4,
+        10,
+        TestTermConfig {
+            scrollback: 100,
+            compress_scrollback: false,
+        },
+    );
+    term.enable_disk_scrollback(dir.path(), "pane-0-", Some(5000))
+        .unwrap();
+    for i in 0..6000 {
+        term.print(format!("{}\r\n", i));
+    }
+
+    // Lines beyond scrollback_lines are retained up to the disk limit
+    assert_eq!(term.screen().scrollback_rows(), 5004);
+    assert_eq!(term.screen().visible_row_to_stable_row(0), 5997);
+
+    let range = term.screen().stable_range(&(1100..1103));
+    let lines = term.screen().lines_in_phys_range(range);
+    let text: Vec<String> = lines.iter().map(|l| l.as_str().to_string()).collect();
+    assert_eq!(text, vec!["1100", "1101", "1102"]);
+
+    // Logical lines are visited across the whole of the scrollback
+    let mut count = 0;
+    term.screen()
+        .for_each_logical_line_in_stable_range(0..6001, |stable_range, lines| {
+            assert_eq!(lines[0].as_str(), stable_range.start.to_string());
+            count += 1;
+            stable_range.start < 5999
+        });
+    assert_eq!(count, 5003);
+
+    // The spill file is removed along with the terminal
+    drop(term);
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+}
+
+#[test]
+fn test_disk_scrollback_palette_change() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut term = TestTerm::with_config(
+        4,
+        10,
+        TestTermConfig {
+            scrollback: 100,
+            compress_scrollback: false,
+        },
+    );
+    term.enable_disk_scrollback(dir.path(), "pane-0-", Some(5000))
+        .unwrap();
+    for i in 0..6000 {
+        term.print(format!("{}\r\n", i));
+    }
+    let spill_len = || -> u64 {
+        let entry = std::fs::read_dir(dir.path()).unwrap().next().unwrap();
+        entry.unwrap().metadata().unwrap().len()
+    };
+    let spilled = spill_len();
+    assert!(spilled > 0);
+
+    // Changing the palette and the hyperlink rules leaves the
+    // spilled blocks where they are
+    let seqno = term.current_seqno();
+    term.set_pane_palette(Some(ColorPalette {
+        background: crate::color::SrgbaTuple(0.3, 0., 0., 1.),
+        ..ColorPalette::default()
+    }));
+    term.set_hyperlink_rules(Some(vec![]));
+    assert_eq!(spill_len(), spilled);
+
+    // but their lines are dirty when they are next read
+    let range = term.screen().stable_range(&(1100..1103));
+    let lines = term.screen().lines_in_phys_range(range);
+    assert_eq!(lines[0].as_str(), "1100");
+    assert!(lines.iter().all(|line| line.current_seqno() >= seqno));
+    assert_eq!(spill_len(), spilled);
+}
+
+#[test]
+fn test_alternate_screen_capture() {
+    let mut term = TestTerm::new(4, 10, 100);
+    term.set_alternate_screen_capture_lines(Some(100));
+    term.print("\x1b[?1049h");
+    for i in 0..10 {
+        term.print(format!("{}\r\n", i));
+    }
+
+    // The lines that scrolled off are the scrollback of the alternate screen
+    assert_eq!(term.screen().scrollback_rows(), 11);
+    assert_visible_contents(&term, file!(), line!(), &["7", "8", "9", ""]);
+
+    // The final contents of the screen are captured when it is deactivated
+    term.print("\x1b[?1049l");
+    assert_eq!(term.screen().scrollback_rows(), 4);
+    let captured: Vec<String> = term
+        .alternate_screen_captured_lines()
+        .iter()
+        .map(|l| l.as_str().to_string())
+        .collect();
+    let expect: Vec<String> = (0..10).map(|i| i.to_string()).collect();
+    assert_eq!(captured, expect);
+
+    // Disabling the capture discards the captured lines
+    term.set_alternate_screen_capture_lines(Some(0));
+    assert!(term.alternate_screen_captured_lines().is_empty());
+}
+
+#[test]
+fn test_incremental_rewrap() {
+    let mut term = TestTerm::new(4, 10, 10000);
+    for i in 0..3000 {
+        term.print(format!("{:08}\r\n", i));
+    }
+    term.resize(TerminalSize {
+        rows: 4,
+        cols: 5,
+        pixel_width: 0,
+        pixel_height: 0,
+        dpi: 0,
+    });
+
+    // Only the lines near the viewport have been rewrapped
+    assert!(term.has_pending_rewrap());
+    let top = term.screen().visible_row_to_stable_row(0);
+    let visible_text = |term: &TestTerm| -> Vec<String> {
+        let range = term.screen().stable_range(&(top..top + 4));
+        let lines = term.screen().lines_in_phys_range(range);
+        lines.iter().map(|l| l.as_str().to_string()).collect()
+    };
+    let visible = visible_text(&term);
+    assert_eq!(visible, vec!["998", "00002", "999", ""]);
+
+    while term.rewrap_pending(100) {}
+
+    // Rewrapping the rest of the scrollback doesn't move the viewport
+    assert!(!term.has_pending_rewrap());
+    assert_eq!(term.screen().visible_row_to_stable_row(0), top);
+    assert_eq!(visible_text(&term), visible);
+
+    let screen = term.screen();
+    assert_eq!(screen.scrollback_rows(), 6001);
+    let lines = screen.lines_in_phys_range(0..6000);
+    for (i, pair) in lines.chunks(2).enumerate() {
+        let text = format!("{:08}", i);
+        assert_eq!(pair[0].as_str(), &text[0..5]);
+        assert_eq!(pair[1].as_str(), &text[5..]);
+    }
+}
+
+#[test]
+fn test_incremental_rewrap_full_scrollback() {
+    let mut term = TestTerm::new(4, 10, 3000);
+    for i in 0..5000 {
+        if i > 0 {
+            term.print("\r\n");
+        }
+        term.print(format!("{:08}", i));
+    }
+    term.resize(TerminalSize {
+        rows: 4,
+        cols: 5,
+        pixel_width: 0,
+        pixel_height: 0,
+        dpi: 0,
+    });
+    assert!(term.has_pending_rewrap());
+    let top = term.screen().visible_row_to_stable_row(0);
+
+    while term.rewrap_pending(100) {}
+    assert!(!term.has_pending_rewrap());
+
+    // The additional lines are discarded from the top of the
+    // scrollback, so the viewport doesn't move
+    assert_eq!(term.screen().visible_row_to_stable_row(0), top);
+    assert_visible_contents(&term, file!(), line!(), &["00004", "998", "00004", "999"]);
+
+    let screen = term.screen();
+    assert_eq!(screen.scrollback_rows(), 3004);
+    let lines = screen.lines_in_phys_range(0..3004);
+    for (i, pair) in lines.rchunks_exact(2).enumerate() {
+        let text = format!("{:08}", 4999 - i);
+        assert_eq!(pair[0].as_str(), &text[0..5]);
+        assert_eq!(pair[1].as_str(), &text[5..]);
+    }
+}
+
+#[test]
+fn test_pane_palette() {
+    let mut term = TestTerm::new(2, 10, 0);
+    let red = ColorPalette {
+        background: crate::color::SrgbaTuple(0.3, 0., 0., 1.),
+        ..ColorPalette::default()
+    };
+    term.set_pane_palette(Some(red.clone()));
+    assert_eq!(term.palette(), red);
+
+    // Changes made by the application are based on the pane palette,
+    // and resetting them restores the pane palette
+    term.print("\x1b]4;1;rgb:00/ff/00\x1b\\");
+    let palette = term.palette();
+    assert_eq!(palette.background, red.background);
+    assert!(palette.colors.0[1] != red.colors.0[1]);
+    term.print("\x1b]104\x1b\\");
+    assert_eq!(term.palette(), red);
+
+    term.set_pane_palette(None);
+    assert_eq!(term.palette(), ColorPalette::default());
+}
+
+#[test]
+fn test_chunked_paste() {
+    let writer = SharedWriter::default();
+    let mut term = Terminal::new(
+        TerminalSize::default(),
+        Arc::new(TestTermConfig {
+            scrollback: 0,
+            compress_scrollback: false,
+        }),
+        "WezTerm",
+        "O_o",
+        Box::new(writer.clone()),
+    );
+    term.advance_bytes("\x1b[?2004h");
+
+    let written = |term: &Terminal| -> String {
+        while term.pending_write_bytes() > 0 {
+            std::thread::yield_now();
+        }
+        String::from_utf8(writer.buf.lock().unwrap().clone()).unwrap()
+    };
+
+    // Chunks never split a multi-byte character
+    let mut paste = term.begin_chunked_paste("héllo\x1b[201~wörld").unwrap();
+    assert_eq!(paste.len(), "héllowörld".len());
+    assert!(term.continue_chunked_paste(&mut paste, 2).unwrap());
+    assert_eq!(written(&term), "\x1b[200~hé");
+    assert_eq!(paste.percentage(), 25);
+
+    while term.continue_chunked_paste(&mut paste, 2).unwrap() {}
+    assert_eq!(written(&term), "\x1b[200~héllowörld\x1b[201~");
+    assert!(!term.continue_chunked_paste(&mut paste, 2).unwrap());
+
+    // Cancelling a paste discards the rest, but closes the bracketing
+    writer.buf.lock().unwrap().clear();
+    let mut paste = term.begin_chunked_paste("one two three").unwrap();
+    assert!(term.continue_chunked_paste(&mut paste, 4).unwrap());
+    term.finish_chunked_paste(&mut paste).unwrap();
+    assert!(!term.continue_chunked_paste(&mut paste, 4).unwrap());
+    assert_eq!(written(&term), "\x1b[200~one \x1b[201~");
+
+    // Typing part way through a paste finishes it first, so that
+    // the key isn't taken to be part of the pasted text
+    writer.buf.lock().unwrap().clear();
+    let mut paste = term.begin_chunked_paste("one two three").unwrap();
+    assert!(term.continue_chunked_paste(&mut paste, 4).unwrap());
+    term.key_down(KeyCode::Char('x'), KeyModifiers::NONE)
+        .unwrap();
+    assert!(!term.continue_chunked_paste(&mut paste, 4).unwrap());
+    term.finish_chunked_paste(&mut paste).unwrap();
+    assert_eq!(written(&term), "\x1b[200~one \x1b[201~x");
+
+    // and so does a mouse event that is reported to the application
+    term.advance_bytes("\x1b[?1000h\x1b[?1006h");
+    writer.buf.lock().unwrap().clear();
+    let mut paste = term.begin_chunked_paste("one two three").unwrap();
+    assert!(term.continue_chunked_paste(&mut paste, 4).unwrap());
+    term.mouse_event(MouseEvent {
+        kind: MouseEventKind::Press,
+        x: 0,
+        y: 0,
+        x_pixel_offset: 0,
+        y_pixel_offset: 0,
+        button: MouseButton::Left,
+        modifiers: KeyModifiers::NONE,
+    })
+    .unwrap();
+    assert!(!term.continue_chunked_paste(&mut paste, 4).unwrap());
+    assert_eq!(written(&term), "\x1b[200~one \x1b[201~\x1b[<0;1;1M");
+}
+
+#[test]
+fn test_xt_get_tcap() {
+    let mut term = TestTerm::new(1, 10, 0);
+
+    // Booleans are reported by name alone
+    term.print("\x1bP+q5463\x1b\\");
+    assert_eq!(term.take_output(), "\x1bP1+r5463\x1b\\");
+    term.print("\x1bP+q5375\x1b\\");
+    assert_eq!(term.take_output(), "\x1bP1+r5375\x1b\\");
+
+    // Strings are followed by their hex encoded value
+    term.print("\x1bP+q544E\x1b\\");
+    assert_eq!(term.take_output(), "\x1bP1+r544E=57657A5465726D\x1b\\");
+
+    // Unknown names are reported as invalid
+    term.print("\x1bP+q78797A\x1b\\");
+    assert_eq!(term.take_output(), "\x1bP0+r78797A\x1b\\");
+
+    // Each name in a request gets its own reply
+    term.print("\x1bP+q5463;78797A\x1b\\");
+    assert_eq!(term.take_output(), "\x1bP1+r5463\x1b\\\x1bP0+r78797A\x1b\\");
+}
+
 #[test]
 fn test_hyperlinks() {
     let mut term = TestTerm::new(3, 5, 0);
@@ -1373,3 +1855,20 @@ fn test_hyperlinks() {
         Compare::TEXT | Compare::ATTRS,
     );
 }
+
+#[test]
+fn test_sixel_display_cells() {
+    // The cells are 8x16 pixels
+    let mut term = TestTerm::new(4, 10, 0);
+    term.print("\x1bPq\"1;1;24;12#0;2;100;0;0#0!24~-!24~\x1b\\");
+
+    let lines = term.screen().visible_lines();
+    let images = lines[0].get_cell(0).unwrap().attrs().images().unwrap();
+    let cells = images[0].display_cells().unwrap();
+    assert_eq!((*cells.columns, *cells.rows), (3., 0.75));
+
+    // When the cells are smaller than they were when the image was
+    // placed, it is displayed at less than its native 24x12 pixels
+    assert_eq!(cells.pixel_size(4., 8.), (12, 6));
+    assert_eq!(cells.pixel_size(8., 16.), (24, 12));
+}
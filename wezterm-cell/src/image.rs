@@ -71,6 +71,49 @@ impl TextureCoordinate {
     }
 }
 
+/// The size of the whole of an image, measured in cells, as it is
+/// displayed by a placement.  It is fractional, as an image need not
+/// fill its last column or row.
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DisplayCells {
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(
+            deserialize_with = "deserialize_notnan",
+            serialize_with = "serialize_notnan"
+        )
+    )]
+    pub columns: NotNan<f32>,
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(
+            deserialize_with = "deserialize_notnan",
+            serialize_with = "serialize_notnan"
+        )
+    )]
+    pub rows: NotNan<f32>,
+}
+
+impl DisplayCells {
+    /// Returns None if either dimension is NaN
+    pub fn new(columns: f32, rows: f32) -> Option<Self> {
+        Some(Self {
+            columns: NotNan::new(columns).ok()?,
+            rows: NotNan::new(rows).ok()?,
+        })
+    }
+
+    /// Returns the size, in pixels, at which the image is displayed
+    /// using cells of the given size
+    pub fn pixel_size(&self, cell_width: f32, cell_height: f32) -> (usize, usize) {
+        (
+            (*self.columns * cell_width).round() as usize,
+            (*self.rows * cell_height).round() as usize,
+        )
+    }
+}
+
 /// Tracks data for displaying an image in the place of the normal cell
 /// character data.  Since an Image can span multiple cells, we need to logically
 /// carve up the image and track each slice of it.  Each cell needs to know
@@ -97,6 +140,25 @@ pub struct ImageCell {
 
     image_id: Option<u32>,
     placement_id: Option<u32>,
+
+    protocol: ImageProtocol,
+
+    /// The size, in cells, at which the whole image was placed.
+    /// It is the same for every cell of a placement, so that the
+    /// renderer can scale the image just once for all of them.
+    display_cells: Option<DisplayCells>,
+}
+
+/// The escape sequence protocol through which an image was placed
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ImageProtocol {
+    /// Placed by some other means, such as the Surface API
+    #[default]
+    Other,
+    Sixel,
+    Iterm,
+    Kitty,
 }
 
 impl ImageCell {
@@ -105,7 +167,20 @@ impl ImageCell {
         bottom_right: TextureCoordinate,
         data: Arc<ImageData>,
     ) -> Self {
-        Self::with_z_index(top_left, bottom_right, data, 0, 0, 0, 0, 0, None, None)
+        Self::with_z_index(
+            top_left,
+            bottom_right,
+            data,
+            0,
+            0,
+            0,
+            0,
+            0,
+            None,
+            None,
+            ImageProtocol::Other,
+            None,
+        )
     }
 
     pub fn compute_shape_hash<H: Hasher>(&self, hasher: &mut H) {
@@ -119,6 +194,8 @@ impl ImageCell {
         self.padding_bottom.hash(hasher);
         self.image_id.hash(hasher);
         self.placement_id.hash(hasher);
+        self.protocol.hash(hasher);
+        self.display_cells.hash(hasher);
     }
 
     pub fn with_z_index(
@@ -132,6 +209,8 @@ impl ImageCell {
         padding_bottom: u16,
         image_id: Option<u32>,
         placement_id: Option<u32>,
+        protocol: ImageProtocol,
+        display_cells: Option<DisplayCells>,
     ) -> Self {
         Self {
             top_left,
@@ -144,6 +223,8 @@ impl ImageCell {
             padding_bottom,
             image_id,
             placement_id,
+            protocol,
+            display_cells,
         }
     }
 
@@ -163,6 +244,14 @@ impl ImageCell {
         self.placement_id
     }
 
+    pub fn protocol(&self) -> ImageProtocol {
+        self.protocol
+    }
+
+    pub fn display_cells(&self) -> Option<DisplayCells> {
+        self.display_cells
+    }
+
     pub fn top_left(&self) -> TextureCoordinate {
         self.top_left
     }
@@ -514,10 +603,38 @@ pub enum ImageCellError {
     ImageError(#[from] image::ImageError),
 }
 
+/// How an animated image should be played back, as controlled
+/// by the kitty image protocol animation commands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImageAnimationState {
+    /// Play the frames, looping back to the first one
+    #[default]
+    Running,
+    /// Hold the current frame
+    Stopped,
+    /// Play the frames, but wait on the last frame for more
+    /// frames to arrive rather than looping
+    Loading,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ImageAnimation {
+    pub state: ImageAnimationState,
+    /// The number of times to play through the frames, or None
+    /// to loop forever
+    pub max_loops: Option<u32>,
+    /// A request to jump to a specific frame index.  The first
+    /// element is a sequence number that is bumped for each request
+    /// so that the renderer can tell when a new request was made.
+    pub current_frame: Option<(u64, usize)>,
+}
+
 #[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
 pub struct ImageData {
     data: Mutex<ImageDataType>,
     hash: [u8; 32],
+    #[cfg_attr(feature = "use_serde", serde(skip))]
+    animation: Mutex<ImageAnimation>,
 }
 
 struct HexSlice<'a>(&'a [u8]);
@@ -557,6 +674,7 @@ impl ImageData {
         Self {
             data: Mutex::new(data),
             hash,
+            animation: Mutex::new(ImageAnimation::default()),
         }
     }
 
@@ -565,6 +683,7 @@ impl ImageData {
         Self {
             data: Mutex::new(data),
             hash,
+            animation: Mutex::new(ImageAnimation::default()),
         }
     }
 
@@ -585,4 +704,12 @@ impl ImageData {
     pub fn hash(&self) -> [u8; 32] {
         self.hash
     }
+
+    pub fn animation(&self) -> ImageAnimation {
+        *self.animation.lock().unwrap()
+    }
+
+    pub fn set_animation(&self, animation: ImageAnimation) {
+        *self.animation.lock().unwrap() = animation;
+    }
 }
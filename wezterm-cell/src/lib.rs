@@ -876,7 +876,7 @@ impl UnicodeVersion {
 }
 
 pub const LATEST_UNICODE_VERSION: UnicodeVersion = UnicodeVersion {
-    version: 14,
+    version: 16,
     ambiguous_are_wide: false,
     #[cfg(feature = "std")]
     cell_widths: None,
@@ -908,6 +908,41 @@ pub fn unicode_column_width(s: &str, version: Option<&UnicodeVersion>) -> usize
         .sum()
 }
 
+/// Segments text the way that terminals without grapheme clustering
+/// (DECRST 2027) do: each codepoint that has a width starts a new
+/// cluster, and zero-width codepoints, such as combining marks,
+/// ZWJ and variation selectors, are attached to the preceding one.
+/// This means that emoji ZWJ sequences, flags and skin tone modifiers
+/// are split into their individual, visible, components.
+pub struct CodepointClusters<'a> {
+    text: &'a str,
+    version: UnicodeVersion,
+}
+
+impl<'a> CodepointClusters<'a> {
+    pub fn new(text: &'a str, version: UnicodeVersion) -> Self {
+        Self { text, version }
+    }
+}
+
+impl<'a> Iterator for CodepointClusters<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let first = self.text.chars().next()?;
+        let mut end = first.len_utf8();
+        for c in self.text[end..].chars() {
+            if self.version.wcwidth(c) != 0 {
+                break;
+            }
+            end += c.len_utf8();
+        }
+        let (cluster, remainder) = self.text.split_at(end);
+        self.text = remainder;
+        Some(cluster)
+    }
+}
+
 /// Returns the number of cells visually occupied by a grapheme.
 /// The input string must be a single grapheme.
 ///
@@ -1001,6 +1036,34 @@ pub enum AttributeChange {
 mod test {
     use super::*;
 
+    #[test]
+    fn codepoint_clusters() {
+        let family = "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}";
+        assert_eq!(
+            CodepointClusters::new(family, LATEST_UNICODE_VERSION).collect::<Vec<_>>(),
+            vec!["\u{1f468}\u{200d}", "\u{1f469}\u{200d}", "\u{1f467}"]
+        );
+
+        let flag = "\u{1f1fa}\u{1f1f8}";
+        assert_eq!(
+            CodepointClusters::new(flag, LATEST_UNICODE_VERSION).collect::<Vec<_>>(),
+            vec!["\u{1f1fa}", "\u{1f1f8}"]
+        );
+
+        let thumbs_up_medium_tone = "\u{1f44d}\u{1f3fd}";
+        assert_eq!(
+            CodepointClusters::new(thumbs_up_medium_tone, LATEST_UNICODE_VERSION)
+                .collect::<Vec<_>>(),
+            vec!["\u{1f44d}", "\u{1f3fd}"]
+        );
+
+        let e_acute = "e\u{301}x";
+        assert_eq!(
+            CodepointClusters::new(e_acute, LATEST_UNICODE_VERSION).collect::<Vec<_>>(),
+            vec!["e\u{301}", "x"]
+        );
+    }
+
     #[test]
     fn teeny_string() {
         assert!(
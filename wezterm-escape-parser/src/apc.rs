@@ -609,6 +609,11 @@ pub struct KittyImagePlacement {
     pub placement_id: Option<u32>,
     /// z=...
     pub z_index: Option<i32>,
+    /// Rather than occupying cells, the image is displayed by
+    /// printing U+10EEEE placeholder characters whose colors and
+    /// diacritics identify the image and the part of it to show.
+    /// U=1
+    pub virtual_placement: bool,
 }
 
 impl KittyImagePlacement {
@@ -629,6 +634,11 @@ impl KittyImagePlacement {
                 _ => return None,
             },
             z_index: geti(keys, "z"),
+            virtual_placement: match get(keys, "U") {
+                None | Some("0") => false,
+                Some("1") => true,
+                _ => return None,
+            },
         })
     }
 
@@ -648,6 +658,10 @@ impl KittyImagePlacement {
         }
 
         set(keys, "z", &self.z_index);
+
+        if self.virtual_placement {
+            keys.insert("U", "1".to_string());
+        }
     }
 }
 
@@ -929,6 +943,92 @@ impl KittyImageFrameCompose {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KittyAnimationState {
+    /// Stop the animation
+    /// s=1
+    Stopped,
+    /// Run the animation, but wait for new frames rather than
+    /// looping once the last frame has been displayed
+    /// s=2
+    Loading,
+    /// Run the animation, looping
+    /// s=3
+    Running,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KittyImageAnimation {
+    /// i=...
+    pub image_id: Option<u32>,
+    /// I=...
+    pub image_number: Option<u32>,
+    /// s=...
+    pub state: Option<KittyAnimationState>,
+    /// 1-based number of the frame whose gap is set by duration_ms
+    /// r=...
+    pub frame_number: Option<u32>,
+    /// Gap in milliseconds of frame_number from the next one
+    /// z=...
+    pub duration_ms: Option<u32>,
+    /// 1-based number of the frame to make the current frame
+    /// c=...
+    pub current_frame: Option<u32>,
+    /// Number of times to play the animation; 1 loops forever,
+    /// and larger numbers loop that number minus 1 times
+    /// v=...
+    pub loops: Option<u32>,
+}
+
+impl KittyImageAnimation {
+    fn from_keys(keys: &BTreeMap<&str, &str>) -> Option<Self> {
+        Some(Self {
+            image_id: geti(keys, "i"),
+            image_number: geti(keys, "I"),
+            state: match geti(keys, "s") {
+                None | Some(0) => None,
+                Some(1) => Some(KittyAnimationState::Stopped),
+                Some(2) => Some(KittyAnimationState::Loading),
+                Some(3) => Some(KittyAnimationState::Running),
+                _ => return None,
+            },
+            frame_number: match geti(keys, "r") {
+                None | Some(0) => None,
+                n => n,
+            },
+            duration_ms: geti(keys, "z"),
+            current_frame: match geti(keys, "c") {
+                None | Some(0) => None,
+                n => n,
+            },
+            loops: match geti(keys, "v") {
+                None | Some(0) => None,
+                n => n,
+            },
+        })
+    }
+
+    fn to_keys(&self, keys: &mut BTreeMap<&'static str, String>) {
+        set(keys, "i", &self.image_id);
+        set(keys, "I", &self.image_number);
+        if let Some(state) = &self.state {
+            keys.insert(
+                "s",
+                match state {
+                    KittyAnimationState::Stopped => "1",
+                    KittyAnimationState::Loading => "2",
+                    KittyAnimationState::Running => "3",
+                }
+                .to_string(),
+            );
+        }
+        set(keys, "r", &self.frame_number);
+        set(keys, "z", &self.duration_ms);
+        set(keys, "c", &self.current_frame);
+        set(keys, "v", &self.loops);
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct KittyImageFrame {
     /// Left edge in pixels to update
@@ -1043,6 +1143,11 @@ pub enum KittyImage {
         frame: KittyImageFrameCompose,
         verbosity: KittyImageVerbosity,
     },
+    /// a='a'
+    AnimationControl {
+        animation: KittyImageAnimation,
+        verbosity: KittyImageVerbosity,
+    },
 }
 
 impl KittyImage {
@@ -1055,6 +1160,7 @@ impl KittyImage {
             Self::Delete { verbosity, .. } => *verbosity,
             Self::TransmitFrame { verbosity, .. } => *verbosity,
             Self::ComposeFrame { verbosity, .. } => *verbosity,
+            Self::AnimationControl { verbosity, .. } => *verbosity,
         }
     }
 
@@ -1108,6 +1214,10 @@ impl KittyImage {
                 frame: KittyImageFrameCompose::from_keys(&keys)?,
                 verbosity,
             }),
+            "a" => Some(Self::AnimationControl {
+                animation: KittyImageAnimation::from_keys(&keys)?,
+                verbosity,
+            }),
             _ => None,
         }
     }
@@ -1172,6 +1282,14 @@ impl KittyImage {
                 frame.to_keys(keys);
                 verbosity.to_keys(keys);
             }
+            Self::AnimationControl {
+                animation,
+                verbosity,
+            } => {
+                keys.insert("a", "a".to_string());
+                animation.to_keys(keys);
+                verbosity.to_keys(keys);
+            }
         }
     }
 }
@@ -1266,4 +1384,53 @@ mod test {
             }
         );
     }
+
+    #[test]
+    fn kitty_virtual_placement() {
+        let img = KittyImage::parse_apc("Ga=p,U=1,i=42,c=10,r=5,q=2".as_bytes()).unwrap();
+        assert_eq!(
+            img,
+            KittyImage::Display {
+                image_id: Some(42),
+                image_number: None,
+                placement: KittyImagePlacement {
+                    x: None,
+                    y: None,
+                    w: None,
+                    h: None,
+                    x_offset: None,
+                    y_offset: None,
+                    columns: Some(10),
+                    rows: Some(5),
+                    do_not_move_cursor: false,
+                    placement_id: None,
+                    z_index: None,
+                    virtual_placement: true,
+                },
+                verbosity: KittyImageVerbosity::Quiet,
+            }
+        );
+        assert_eq!(img.to_string(), "\x1b_GU=1,a=p,c=10,i=42,q=2,r=5");
+    }
+
+    #[test]
+    fn kitty_animation_control() {
+        let img = KittyImage::parse_apc("Ga=a,i=3,s=3,v=1,r=2,z=100".as_bytes()).unwrap();
+        assert_eq!(
+            img,
+            KittyImage::AnimationControl {
+                animation: KittyImageAnimation {
+                    image_id: Some(3),
+                    image_number: None,
+                    state: Some(KittyAnimationState::Running),
+                    frame_number: Some(2),
+                    duration_ms: Some(100),
+                    current_frame: None,
+                    loops: Some(1),
+                },
+                verbosity: KittyImageVerbosity::Verbose,
+            }
+        );
+        assert_eq!(img.to_string(), "\x1b_Ga=a,i=3,r=2,s=3,v=1,z=100");
+    }
 }
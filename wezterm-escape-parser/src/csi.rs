@@ -189,6 +189,54 @@ pub enum CharacterPath {
     RightToLeftOrBottomToTop,
 }
 
+/// The extent of the rectangular area attribute changes
+/// made by DECCARA and DECRARA, as selected by DECSACE
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AttributeChangeExtent {
+    /// 0 or 1; from the start position to the end position,
+    /// wrapping at the margins like a stream of text
+    #[default]
+    Stream,
+    /// 2; the rectangle with those start and end positions
+    /// as its corners
+    Rectangle,
+}
+
+/// An attribute that DECCARA turns on or off, or that DECRARA reverses,
+/// in a rectangular area.  The values are the corresponding SGR codes.
+/// Only the attributes that turn something on are valid for DECRARA.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+pub enum RectangularAreaAttribute {
+    /// All of the attributes below; DECCARA turns them
+    /// all off, and DECRARA reverses them all
+    All = 0,
+    Bold = 1,
+    Underline = 4,
+    Blink = 5,
+    Inverse = 7,
+    Invisible = 8,
+    NormalIntensity = 22,
+    UnderlineOff = 24,
+    BlinkOff = 25,
+    InverseOff = 27,
+    InvisibleOff = 28,
+}
+
+impl RectangularAreaAttribute {
+    /// Returns true if DECRARA can reverse this attribute
+    pub fn is_reversible(self) -> bool {
+        matches!(
+            self,
+            Self::All
+                | Self::Bold
+                | Self::Underline
+                | Self::Blink
+                | Self::Inverse
+                | Self::Invisible
+        )
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Unspecified {
     pub params: Vec<CsiParam>,
@@ -462,6 +510,21 @@ pub enum Device {
     RequestTerminalNameAndVersion,
     RequestTerminalParameters(i64),
     XtSmGraphics(XtSmGraphics),
+    /// Request the color scheme preference, as part of
+    /// <https://github.com/contour-terminal/contour/blob/master/docs/vt-extensions/color-palette-update-notifications.md>
+    RequestColorScheme,
+    /// Reports the color scheme preference, either in response to
+    /// RequestColorScheme or because the palette has changed
+    ColorSchemeReport(ColorScheme),
+}
+
+/// Whether the color palette is dark or light
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorScheme {
+    /// 1
+    Dark,
+    /// 2
+    Light,
 }
 
 impl Display for Device {
@@ -482,6 +545,9 @@ impl Display for Device {
             Device::RequestTerminalNameAndVersion => write!(f, ">q")?,
             Device::RequestTerminalParameters(n) => write!(f, "{};1;1;128;128;1;0x", n + 2)?,
             Device::StatusReport => write!(f, "5n")?,
+            Device::RequestColorScheme => write!(f, "?996n")?,
+            Device::ColorSchemeReport(ColorScheme::Dark) => write!(f, "?997;1n")?,
+            Device::ColorSchemeReport(ColorScheme::Light) => write!(f, "?997;2n")?,
             Device::XtSmGraphics(g) => {
                 write!(f, "?{};{}", g.item, g.action_or_status)?;
                 for v in &g.value {
@@ -587,6 +653,62 @@ pub enum Window {
         bottom: OneBased,
         right: OneBased,
     },
+    /// DECCRA; copy the contents of a rectangle so that its
+    /// top left corner is at dest_top, dest_left
+    CopyRectangularArea {
+        top: OneBased,
+        left: OneBased,
+        bottom: OneBased,
+        right: OneBased,
+        page: OneBased,
+        dest_top: OneBased,
+        dest_left: OneBased,
+        dest_page: OneBased,
+    },
+    /// DECFRA; fill a rectangle with a character, using the
+    /// current graphic rendition
+    FillRectangularArea {
+        ch: char,
+        top: OneBased,
+        left: OneBased,
+        bottom: OneBased,
+        right: OneBased,
+    },
+    /// DECERA; erase the characters and attributes in a rectangle
+    EraseRectangularArea {
+        top: OneBased,
+        left: OneBased,
+        bottom: OneBased,
+        right: OneBased,
+    },
+    /// DECSERA; erase the characters in a rectangle, leaving
+    /// their attributes intact
+    SelectiveEraseRectangularArea {
+        top: OneBased,
+        left: OneBased,
+        bottom: OneBased,
+        right: OneBased,
+    },
+    /// DECSACE
+    SelectAttributeChangeExtent(AttributeChangeExtent),
+    /// DECCARA; change the attributes of the characters in an area,
+    /// whose shape is selected by DECSACE
+    ChangeAttributesInRectangularArea {
+        top: OneBased,
+        left: OneBased,
+        bottom: OneBased,
+        right: OneBased,
+        attributes: Vec<RectangularAreaAttribute>,
+    },
+    /// DECRARA; reverse the attributes of the characters in an area,
+    /// whose shape is selected by DECSACE
+    ReverseAttributesInRectangularArea {
+        top: OneBased,
+        left: OneBased,
+        bottom: OneBased,
+        right: OneBased,
+        attributes: Vec<RectangularAreaAttribute>,
+    },
 }
 
 fn numstr_or_empty(x: &Option<i64>) -> String {
@@ -659,6 +781,69 @@ impl Display for Window {
                 "{};{};{};{};{};{}*y",
                 request_id, page_number, top, left, bottom, right,
             ),
+            Window::CopyRectangularArea {
+                top,
+                left,
+                bottom,
+                right,
+                page,
+                dest_top,
+                dest_left,
+                dest_page,
+            } => write!(
+                f,
+                "{};{};{};{};{};{};{};{}$v",
+                top, left, bottom, right, page, dest_top, dest_left, dest_page
+            ),
+            Window::FillRectangularArea {
+                ch,
+                top,
+                left,
+                bottom,
+                right,
+            } => write!(f, "{};{};{};{};{}$x", *ch as u32, top, left, bottom, right),
+            Window::EraseRectangularArea {
+                top,
+                left,
+                bottom,
+                right,
+            } => write!(f, "{};{};{};{}$z", top, left, bottom, right),
+            Window::SelectiveEraseRectangularArea {
+                top,
+                left,
+                bottom,
+                right,
+            } => write!(f, "{};{};{};{}${{", top, left, bottom, right),
+            Window::SelectAttributeChangeExtent(extent) => match extent {
+                AttributeChangeExtent::Stream => write!(f, "1*x"),
+                AttributeChangeExtent::Rectangle => write!(f, "2*x"),
+            },
+            Window::ChangeAttributesInRectangularArea {
+                top,
+                left,
+                bottom,
+                right,
+                attributes,
+            } => {
+                write!(f, "{};{};{};{}", top, left, bottom, right)?;
+                for attr in attributes {
+                    write!(f, ";{}", *attr as u8)?;
+                }
+                write!(f, "$r")
+            }
+            Window::ReverseAttributesInRectangularArea {
+                top,
+                left,
+                bottom,
+                right,
+                attributes,
+            } => {
+                write!(f, "{};{};{};{}", top, left, bottom, right)?;
+                for attr in attributes {
+                    write!(f, ";{}", *attr as u8)?;
+                }
+                write!(f, "$t")
+            }
         }
     }
 }
@@ -954,6 +1139,10 @@ pub enum DecPrivateModeCode {
     /// <https://gist.github.com/christianparpart/d8a62cc1ab659194337d73e399004036>
     SynchronizedOutput = 2026,
 
+    /// Report changes to the color palette with ColorSchemeReport
+    /// <https://github.com/contour-terminal/contour/blob/master/docs/vt-extensions/color-palette-update-notifications.md>
+    ColorSchemeUpdates = 2031,
+
     MinTTYApplicationEscapeKeyMode = 7727,
 
     /// xterm: adjust cursor positioning after emitting sixel
@@ -1832,6 +2021,13 @@ impl<'a> CSIParser<'a> {
             ('k', [.., CsiParam::P(b' ')]) => self.select_character_path(params),
             ('q', [.., CsiParam::P(b' ')]) => self.cursor_style(params),
             ('y', [.., CsiParam::P(b'*')]) => self.checksum_area(params),
+            ('v', [.., CsiParam::P(b'$')]) => self.copy_area(params),
+            ('x', [.., CsiParam::P(b'$')]) => self.fill_area(params),
+            ('z', [.., CsiParam::P(b'$')]) => self.erase_area(params, false),
+            ('{', [.., CsiParam::P(b'$')]) => self.erase_area(params, true),
+            ('x', [.., CsiParam::P(b'*')]) => self.attribute_change_extent(params),
+            ('r', [.., CsiParam::P(b'$')]) => self.area_attributes(params, false),
+            ('t', [.., CsiParam::P(b'$')]) => self.area_attributes(params, true),
 
             ('c', [CsiParam::P(b'='), ..]) => self
                 .req_tertiary_device_attributes(params)
@@ -1849,6 +2045,24 @@ impl<'a> CSIParser<'a> {
                 .map(|dev| CSI::Device(Box::new(dev))),
 
             ('S', [CsiParam::P(b'?'), ..]) => XtSmGraphics::parse(params),
+            ('n', [CsiParam::P(b'?'), CsiParam::Integer(996)]) => {
+                Ok(CSI::Device(Box::new(Device::RequestColorScheme)))
+            }
+            (
+                'n',
+                [
+                    CsiParam::P(b'?'),
+                    CsiParam::Integer(997),
+                    CsiParam::P(b';'),
+                    CsiParam::Integer(scheme),
+                ],
+            ) => Ok(CSI::Device(Box::new(Device::ColorSchemeReport(
+                match *scheme {
+                    1 => ColorScheme::Dark,
+                    2 => ColorScheme::Light,
+                    _ => return Err(()),
+                },
+            )))),
             ('p', [CsiParam::Integer(_), CsiParam::P(b'$')])
             | ('p', [CsiParam::P(b'?'), CsiParam::Integer(_), CsiParam::P(b'$')]) => {
                 self.decrqm(params)
@@ -2082,6 +2296,127 @@ impl<'a> CSIParser<'a> {
         })))
     }
 
+    /// Parses the top, left, bottom and right parameters of
+    /// a rectangular area operation, starting at `idx`.
+    /// The bottom and right edges default to the edges of the page.
+    fn rectangle(
+        params: &Cracked,
+        idx: usize,
+    ) -> Result<(OneBased, OneBased, OneBased, OneBased), ()> {
+        let edge = |idx: usize| {
+            OneBased::from_esc_param_with_big_default(
+                params.get(idx).unwrap_or(&CsiParam::Integer(0)),
+            )
+        };
+        let top = OneBased::from_optional_esc_param(params.get(idx))?;
+        let left = OneBased::from_optional_esc_param(params.get(idx + 1))?;
+        let bottom = edge(idx + 2)?;
+        let right = edge(idx + 3)?;
+        Ok((top, left, bottom, right))
+    }
+
+    fn copy_area(&mut self, params: &'a [CsiParam]) -> Result<CSI, ()> {
+        let params = Cracked::parse(&params[..params.len() - 1])?;
+
+        let (top, left, bottom, right) = Self::rectangle(&params, 0)?;
+        let page = OneBased::from_optional_esc_param(params.get(4))?;
+        let dest_top = OneBased::from_optional_esc_param(params.get(5))?;
+        let dest_left = OneBased::from_optional_esc_param(params.get(6))?;
+        let dest_page = OneBased::from_optional_esc_param(params.get(7))?;
+        Ok(CSI::Window(Box::new(Window::CopyRectangularArea {
+            top,
+            left,
+            bottom,
+            right,
+            page,
+            dest_top,
+            dest_left,
+            dest_page,
+        })))
+    }
+
+    fn fill_area(&mut self, params: &'a [CsiParam]) -> Result<CSI, ()> {
+        let params = Cracked::parse(&params[..params.len() - 1])?;
+
+        // Only the printable characters of the GL and GR
+        // sets can be used to fill the area
+        let ch = match params.int(0)? {
+            c @ (32..=126 | 160..=255) => char::from_u32(c as u32).ok_or(())?,
+            _ => return Err(()),
+        };
+        let (top, left, bottom, right) = Self::rectangle(&params, 1)?;
+        Ok(CSI::Window(Box::new(Window::FillRectangularArea {
+            ch,
+            top,
+            left,
+            bottom,
+            right,
+        })))
+    }
+
+    fn erase_area(&mut self, params: &'a [CsiParam], selective: bool) -> Result<CSI, ()> {
+        let params = Cracked::parse(&params[..params.len() - 1])?;
+
+        let (top, left, bottom, right) = Self::rectangle(&params, 0)?;
+        Ok(CSI::Window(Box::new(if selective {
+            Window::SelectiveEraseRectangularArea {
+                top,
+                left,
+                bottom,
+                right,
+            }
+        } else {
+            Window::EraseRectangularArea {
+                top,
+                left,
+                bottom,
+                right,
+            }
+        })))
+    }
+
+    fn attribute_change_extent(&mut self, params: &'a [CsiParam]) -> Result<CSI, ()> {
+        let params = Cracked::parse(&params[..params.len() - 1])?;
+
+        let extent = match params.opt_int(0).unwrap_or(0) {
+            0 | 1 => AttributeChangeExtent::Stream,
+            2 => AttributeChangeExtent::Rectangle,
+            _ => return Err(()),
+        };
+        Ok(CSI::Window(Box::new(Window::SelectAttributeChangeExtent(
+            extent,
+        ))))
+    }
+
+    fn area_attributes(&mut self, params: &'a [CsiParam], reverse: bool) -> Result<CSI, ()> {
+        let params = Cracked::parse(&params[..params.len() - 1])?;
+
+        let (top, left, bottom, right) = Self::rectangle(&params, 0)?;
+        // The attributes follow the rectangle; an omitted attribute
+        // is 0, and any that don't apply are ignored
+        let attributes = (4..params.len().max(5))
+            .filter_map(|idx| FromPrimitive::from_i64(params.opt_int(idx).unwrap_or(0)))
+            .filter(|attr: &RectangularAreaAttribute| !reverse || attr.is_reversible())
+            .collect();
+        Ok(CSI::Window(Box::new(if reverse {
+            Window::ReverseAttributesInRectangularArea {
+                top,
+                left,
+                bottom,
+                right,
+                attributes,
+            }
+        } else {
+            Window::ChangeAttributesInRectangularArea {
+                top,
+                left,
+                bottom,
+                right,
+                attributes,
+            }
+        })))
+    }
+
     fn dsr(&mut self, params: &'a [CsiParam]) -> Result<CSI, ()> {
         match params {
             [CsiParam::Integer(5)] => {
@@ -3211,6 +3546,125 @@ mod test {
         );
     }
 
+    #[test]
+    fn rectangular_area() {
+        fn parse_with(params: &[i64], intermediate: u8, control: char, expected: &str) -> Vec<CSI> {
+            let mut cparams = vec![];
+            for &p in params {
+                if !cparams.is_empty() {
+                    cparams.push(CsiParam::P(b';'));
+                }
+                cparams.push(CsiParam::Integer(p));
+            }
+            cparams.push(CsiParam::P(intermediate));
+            let res = CSI::parse(&cparams, false, control).collect();
+            assert_eq!(encode(&res), expected, "parsed -> {res:?}");
+            res
+        }
+
+        assert_eq!(
+            parse_with(
+                &[2, 3, 4, 5, 1, 6, 7, 1],
+                b'$',
+                'v',
+                "\x1b[2;3;4;5;1;6;7;1$v"
+            ),
+            vec![CSI::Window(Box::new(Window::CopyRectangularArea {
+                top: OneBased::new(2),
+                left: OneBased::new(3),
+                bottom: OneBased::new(4),
+                right: OneBased::new(5),
+                page: OneBased::new(1),
+                dest_top: OneBased::new(6),
+                dest_left: OneBased::new(7),
+                dest_page: OneBased::new(1),
+            }))]
+        );
+        assert_eq!(
+            parse_with(&[65, 1, 2, 3, 4], b'$', 'x', "\x1b[65;1;2;3;4$x"),
+            vec![CSI::Window(Box::new(Window::FillRectangularArea {
+                ch: 'A',
+                top: OneBased::new(1),
+                left: OneBased::new(2),
+                bottom: OneBased::new(3),
+                right: OneBased::new(4),
+            }))]
+        );
+        // Control characters cannot be used to fill an area
+        assert!(matches!(
+            parse_with(&[10, 1, 2, 3, 4], b'$', 'x', "\x1b[10;1;2;3;4$x").as_slice(),
+            [CSI::Unspecified(_)]
+        ));
+        // The bottom right corner defaults to the end of the page
+        assert_eq!(
+            parse_with(&[2, 3], b'$', 'z', "\x1b[2;3;4294967295;4294967295$z"),
+            vec![CSI::Window(Box::new(Window::EraseRectangularArea {
+                top: OneBased::new(2),
+                left: OneBased::new(3),
+                bottom: OneBased::new(u32::MAX),
+                right: OneBased::new(u32::MAX),
+            }))]
+        );
+        assert_eq!(
+            parse_with(&[1, 1, 2, 2], b'$', '{', "\x1b[1;1;2;2${"),
+            vec![CSI::Window(Box::new(
+                Window::SelectiveEraseRectangularArea {
+                    top: OneBased::new(1),
+                    left: OneBased::new(1),
+                    bottom: OneBased::new(2),
+                    right: OneBased::new(2),
+                }
+            ))]
+        );
+        assert_eq!(
+            parse_with(&[1, 2, 3, 4, 1, 27], b'$', 'r', "\x1b[1;2;3;4;1;27$r"),
+            vec![CSI::Window(Box::new(
+                Window::ChangeAttributesInRectangularArea {
+                    top: OneBased::new(1),
+                    left: OneBased::new(2),
+                    bottom: OneBased::new(3),
+                    right: OneBased::new(4),
+                    attributes: vec![
+                        RectangularAreaAttribute::Bold,
+                        RectangularAreaAttribute::InverseOff
+                    ],
+                }
+            ))]
+        );
+        // The attribute defaults to 0, and those that turn
+        // something off cannot be reversed
+        assert_eq!(
+            parse_with(&[1, 1, 2, 2], b'$', 't', "\x1b[1;1;2;2;0$t"),
+            vec![CSI::Window(Box::new(
+                Window::ReverseAttributesInRectangularArea {
+                    top: OneBased::new(1),
+                    left: OneBased::new(1),
+                    bottom: OneBased::new(2),
+                    right: OneBased::new(2),
+                    attributes: vec![RectangularAreaAttribute::All],
+                }
+            ))]
+        );
+        assert_eq!(
+            parse_with(&[1, 1, 2, 2, 7, 22], b'$', 't', "\x1b[1;1;2;2;7$t"),
+            vec![CSI::Window(Box::new(
+                Window::ReverseAttributesInRectangularArea {
+                    top: OneBased::new(1),
+                    left: OneBased::new(1),
+                    bottom: OneBased::new(2),
+                    right: OneBased::new(2),
+                    attributes: vec![RectangularAreaAttribute::Inverse],
+                }
+            ))]
+        );
+        assert_eq!(
+            parse_with(&[2], b'*', 'x', "\x1b[2*x"),
+            vec![CSI::Window(Box::new(Window::SelectAttributeChangeExtent(
+                AttributeChangeExtent::Rectangle
+            )))]
+        );
+    }
+
     #[test]
     fn cursor() {
         assert_eq!(
@@ -3350,6 +3804,33 @@ mod test {
         assert_eq!(res, vec![CSI::Device(Box::new(Device::SoftReset))],);
     }
 
+    #[test]
+    fn color_scheme() {
+        let res: Vec<_> =
+            CSI::parse(&[CsiParam::P(b'?'), CsiParam::Integer(996)], false, 'n').collect();
+        assert_eq!(encode(&res), "\x1b[?996n");
+        assert_eq!(res, vec![CSI::Device(Box::new(Device::RequestColorScheme))]);
+
+        let res: Vec<_> = CSI::parse(
+            &[
+                CsiParam::P(b'?'),
+                CsiParam::Integer(997),
+                CsiParam::P(b';'),
+                CsiParam::Integer(2),
+            ],
+            false,
+            'n',
+        )
+        .collect();
+        assert_eq!(encode(&res), "\x1b[?997;2n");
+        assert_eq!(
+            res,
+            vec![CSI::Device(Box::new(Device::ColorSchemeReport(
+                ColorScheme::Light
+            )))]
+        );
+    }
+
     #[test]
     fn device_attr() {
         let res: Vec<_> = CSI::parse(
@@ -73,6 +73,17 @@ impl Hyperlink {
         }
     }
 
+    pub fn new_implicit_with_params<S: Into<String>>(
+        uri: S,
+        params: impl IntoIterator<Item = (String, String)>,
+    ) -> Self {
+        Self {
+            uri: uri.into(),
+            params: params.into_iter().collect(),
+            implicit: true,
+        }
+    }
+
     pub fn parse(osc: &[&[u8]]) -> Result<Option<Hyperlink>> {
         ensure!(osc.len() == 3, "wrong param count");
         if osc[1].is_empty() && osc[2].is_empty() {
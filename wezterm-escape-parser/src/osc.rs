@@ -174,7 +174,7 @@ impl OperatingSystemCommand {
             Selection::try_parse(osc[1]).map(OperatingSystemCommand::QuerySelection)
         } else if osc.len() == 3 {
             let sel = Selection::try_parse(osc[1])?;
-            let bytes = base64_decode(osc[2])?;
+            let bytes = base64_decode_chunked(osc[2])?;
             let s = String::from_utf8(bytes)?;
             Ok(OperatingSystemCommand::SetSelection(sel, s))
         } else {
@@ -887,7 +887,7 @@ pub enum Progress {
     Paused,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ITermProprietary {
     /// The "Set Mark" command allows you to record a location and then jump back to it later
     SetMark,
@@ -935,6 +935,18 @@ pub enum ITermProprietary {
 
     /// Configure unicode version
     UnicodeVersion(ITermUnicodeVersionOp),
+
+    /// Set the background image of the session to the named file.
+    /// An empty file name removes the background image.
+    SetBackgroundImageFile(String),
+    /// Set the color of the background of the session, overriding the
+    /// configured background color.  None restores the default color.
+    /// This is a wezterm extension.
+    SetBackgroundColor(Option<SrgbaTuple>),
+    /// Set the opacity of the background of the session, overriding
+    /// `window_background_opacity`.  None restores the default opacity.
+    /// This is a wezterm extension.
+    SetBackgroundOpacity(Option<NotNan<f32>>),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -1304,6 +1316,37 @@ impl ITermProprietary {
             }
         }
 
+        if osc.len() == 2 && keyword == "SetBackgroundImageFile" {
+            if let Some(p1) = p1 {
+                return Ok(ITermProprietary::SetBackgroundImageFile(String::from_utf8(
+                    base64_decode(p1)?,
+                )?));
+            }
+        }
+
+        if osc.len() == 2 && keyword == "SetBackgroundColor" {
+            if p1_empty {
+                return Ok(ITermProprietary::SetBackgroundColor(None));
+            }
+            if let Some(p1) = p1 {
+                let color = SrgbaTuple::from_str(p1)
+                    .map_err(|()| format_err!("invalid background color {p1}"))?;
+                return Ok(ITermProprietary::SetBackgroundColor(Some(color)));
+            }
+        }
+
+        if osc.len() == 2 && keyword == "SetBackgroundOpacity" {
+            if p1_empty {
+                return Ok(ITermProprietary::SetBackgroundOpacity(None));
+            }
+            if let Some(p1) = p1 {
+                let opacity: f32 = p1.parse()?;
+                return Ok(ITermProprietary::SetBackgroundOpacity(Some(
+                    NotNan::new(opacity.max(0.).min(1.)).map_err(not_nan_err)?,
+                )));
+            }
+        }
+
         if keyword == "File" {
             return Ok(ITermProprietary::File(Box::new(ITermFileData::parse(osc)?)));
         }
@@ -1328,6 +1371,25 @@ pub(crate) fn base64_decode<T: AsRef<[u8]>>(s: T) -> Result<Vec<u8>> {
     .map_err(|err| crate::format_err!("base64_decode: {:#}", err))
 }
 
+/// Decodes base64 data that may be the concatenation of several
+/// independently encoded, and thus padded, chunks.  Tools that write
+/// large amounts of data to the clipboard via OSC 52 may encode it
+/// piecewise in this way.
+pub(crate) fn base64_decode_chunked<T: AsRef<[u8]>>(s: T) -> Result<Vec<u8>> {
+    let mut remain = s.as_ref();
+    let mut result = Vec::with_capacity(remain.len() * 3 / 4);
+    while !remain.is_empty() {
+        // Each chunk extends to the end of its padding, if any
+        let end = match remain.iter().position(|&b| b == b'=') {
+            Some(idx) => idx + remain[idx..].iter().take_while(|&&b| b == b'=').count(),
+            None => remain.len(),
+        };
+        result.extend_from_slice(&base64_decode(&remain[..end])?);
+        remain = &remain[end..];
+    }
+    Ok(result)
+}
+
 impl Display for ITermProprietary {
     fn fmt(&self, f: &mut Formatter) -> FmtResult {
         write!(f, "1337;")?;
@@ -1373,6 +1435,11 @@ impl Display for ITermProprietary {
                 write!(f, "UnicodeVersion=pop {}", label)?
             }
             UnicodeVersion(ITermUnicodeVersionOp::Pop(None)) => write!(f, "UnicodeVersion=pop")?,
+            SetBackgroundImageFile(s) => write!(f, "SetBackgroundImageFile={}", base64_encode(s))?,
+            SetBackgroundColor(Some(c)) => write!(f, "SetBackgroundColor={}", c.to_string())?,
+            SetBackgroundColor(None) => write!(f, "SetBackgroundColor=")?,
+            SetBackgroundOpacity(Some(o)) => write!(f, "SetBackgroundOpacity={o}")?,
+            SetBackgroundOpacity(None) => write!(f, "SetBackgroundOpacity=")?,
         }
         Ok(())
     }
@@ -1422,6 +1489,26 @@ mod test {
         );
     }
 
+    #[test]
+    fn selection() {
+        assert_eq!(
+            parse(&["52", "c", "?"], "\x1b]52;c;?\x1b\\"),
+            OperatingSystemCommand::QuerySelection(Selection::CLIPBOARD)
+        );
+        assert_eq!(
+            parse(&["52", "c", "aGVsbG8="], "\x1b]52;c;aGVsbG8=\x1b\\"),
+            OperatingSystemCommand::SetSelection(Selection::CLIPBOARD, "hello".into())
+        );
+        // Concatenated chunks that were encoded separately
+        assert_eq!(
+            parse(
+                &["52", "c", "aGVsbG8=IHdvcmxkIQ=="],
+                "\x1b]52;c;aGVsbG8gd29ybGQh\x1b\\"
+            ),
+            OperatingSystemCommand::SetSelection(Selection::CLIPBOARD, "hello world!".into())
+        );
+    }
+
     #[test]
     fn title() {
         assert_eq!(
@@ -1764,6 +1851,44 @@ mod test {
             })
         );
 
+        assert_eq!(
+            parse(
+                &["1337", "SetBackgroundImageFile=L3RtcC9iZy5wbmc="],
+                "\x1b]1337;SetBackgroundImageFile=L3RtcC9iZy5wbmc=\x1b\\"
+            ),
+            OperatingSystemCommand::ITermProprietary(ITermProprietary::SetBackgroundImageFile(
+                "/tmp/bg.png".into()
+            ))
+        );
+
+        assert_eq!(
+            parse(
+                &["1337", "SetBackgroundColor=#102030"],
+                "\x1b]1337;SetBackgroundColor=#102030\x1b\\"
+            ),
+            OperatingSystemCommand::ITermProprietary(ITermProprietary::SetBackgroundColor(Some(
+                SrgbaTuple::from_str("#102030").unwrap()
+            )))
+        );
+
+        assert_eq!(
+            parse(
+                &["1337", "SetBackgroundColor="],
+                "\x1b]1337;SetBackgroundColor=\x1b\\"
+            ),
+            OperatingSystemCommand::ITermProprietary(ITermProprietary::SetBackgroundColor(None))
+        );
+
+        assert_eq!(
+            parse(
+                &["1337", "SetBackgroundOpacity=0.5"],
+                "\x1b]1337;SetBackgroundOpacity=0.5\x1b\\"
+            ),
+            OperatingSystemCommand::ITermProprietary(ITermProprietary::SetBackgroundOpacity(Some(
+                NotNan::new(0.5).unwrap()
+            )))
+        );
+
         assert_eq!(
             parse(
                 &["1337", "SetBadgeFormat=", "aGVsbG8="],
@@ -0,0 +1,577 @@
+//! A cache of shaping and rasterization results that persists across
+//! runs, so that starting up or opening a new window doesn't need to
+//! redo that work for the text that is most commonly displayed.
+//!
+//! Only results for the primary font of a LoadedFont are cached:
+//! the shaping of short runs of ASCII text that is entirely covered
+//! by that font, and the glyphs rasterized from it.  Each font, along
+//! with the size, dpi and configuration that influence shaping and
+//! rasterization, is identified by a hash, and its results are stored
+//! in a file named after that hash.  The least recently used files are
+//! removed to keep the total size within `font_disk_cache_size`.
+use crate::locator::FontDataSource;
+use crate::parser::ParsedFont;
+use crate::rasterizer::RasterizedGlyph;
+use crate::shaper::GlyphInfo;
+use crate::units::PixelLength;
+use config::Config;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, Once};
+use std::time::{Duration, SystemTime};
+use termwiz::cell::Presentation;
+use wezterm_bidi::Direction;
+
+/// Bump this when changing the layout of CacheFile
+const CACHE_VERSION: u32 = 1;
+
+/// Only runs of text up to this many bytes are cached
+const MAX_SHAPE_TEXT_LEN: usize = 128;
+const MAX_SHAPES_PER_FONT: usize = 8192;
+const MAX_GLYPHS_PER_FONT: usize = 2048;
+
+/// How long to wait after adding results before writing the file,
+/// so that a burst of additions results in a single write
+const FLUSH_DELAY: Duration = Duration::from_secs(5);
+
+lazy_static::lazy_static! {
+    static ref CACHES: Mutex<HashMap<PathBuf, Arc<FontDiskCache>>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Serialize, Deserialize, Hash, PartialEq, Eq, Clone)]
+struct ShapeKey {
+    text: String,
+    emoji_presentation: Option<bool>,
+    right_to_left: bool,
+}
+
+impl ShapeKey {
+    fn new(text: &str, presentation: Option<Presentation>, direction: Direction) -> Self {
+        Self {
+            text: text.to_string(),
+            emoji_presentation: presentation.map(|p| p == Presentation::Emoji),
+            right_to_left: matches!(direction, Direction::RightToLeft),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct CachedGlyphInfo {
+    text: String,
+    only_char: Option<char>,
+    is_space: bool,
+    num_cells: u8,
+    cluster: u32,
+    glyph_pos: u32,
+    x_advance: f64,
+    y_advance: f64,
+    x_offset: f64,
+    y_offset: f64,
+}
+
+impl CachedGlyphInfo {
+    fn new(info: &GlyphInfo) -> Self {
+        Self {
+            #[cfg(any(debug_assertions, test))]
+            text: info.text.clone(),
+            #[cfg(not(any(debug_assertions, test)))]
+            text: String::new(),
+            only_char: info.only_char,
+            is_space: info.is_space,
+            num_cells: info.num_cells,
+            cluster: info.cluster,
+            glyph_pos: info.glyph_pos,
+            x_advance: info.x_advance.get(),
+            y_advance: info.y_advance.get(),
+            x_offset: info.x_offset.get(),
+            y_offset: info.y_offset.get(),
+        }
+    }
+
+    fn to_glyph_info(&self) -> GlyphInfo {
+        GlyphInfo {
+            #[cfg(any(debug_assertions, test))]
+            text: self.text.clone(),
+            only_char: self.only_char,
+            is_space: self.is_space,
+            num_cells: self.num_cells,
+            cluster: self.cluster,
+            font_idx: 0,
+            glyph_pos: self.glyph_pos,
+            x_advance: PixelLength::new(self.x_advance),
+            y_advance: PixelLength::new(self.y_advance),
+            x_offset: PixelLength::new(self.x_offset),
+            y_offset: PixelLength::new(self.y_offset),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct CachedRaster {
+    data: Vec<u8>,
+    height: usize,
+    width: usize,
+    bearing_x: f64,
+    bearing_y: f64,
+    has_color: bool,
+    is_scaled: bool,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct CacheFile {
+    version: u32,
+    wezterm_version: String,
+    shapes: HashMap<ShapeKey, Vec<CachedGlyphInfo>>,
+    glyphs: HashMap<u32, CachedRaster>,
+}
+
+struct CacheState {
+    file: CacheFile,
+    dirty: bool,
+    flush_scheduled: bool,
+}
+
+pub struct FontDiskCache {
+    path: PathBuf,
+    max_size: usize,
+    state: Mutex<CacheState>,
+}
+
+fn cache_dir() -> PathBuf {
+    config::CACHE_DIR.join("font-cache")
+}
+
+/// Removes the caches written by versions with a different layout
+fn remove_stale_versions() {
+    static ONCE: Once = Once::new();
+    ONCE.call_once(|| {
+        let current = format!("v{CACHE_VERSION}");
+        if let Ok(entries) = std::fs::read_dir(cache_dir()) {
+            for entry in entries.flatten() {
+                if entry.file_name() != current.as_str() {
+                    if let Err(err) = std::fs::remove_dir_all(entry.path()) {
+                        log::debug!("Failed to remove {}: {err:#}", entry.path().display());
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// Computes a hash of the font data and of everything that
+/// influences how it is shaped and rasterized
+fn identity(config: &Config, handle: &ParsedFont, font_size: f64, dpi: u32) -> Option<String> {
+    let mut hasher = Sha256::new();
+    hasher.update(config::wezterm_version());
+    hasher.update(format!("{handle:?}"));
+    match &handle.handle.source {
+        FontDataSource::OnDisk(path) => {
+            let meta = std::fs::metadata(path).ok()?;
+            hasher.update(meta.len().to_le_bytes());
+            hasher.update(format!("{:?}", meta.modified().ok()?));
+        }
+        // Built in fonts can only change along with the version
+        FontDataSource::BuiltIn { .. } => {}
+        FontDataSource::Memory { data, .. } => {
+            let data: &[u8] = data;
+            hasher.update(data);
+        }
+    }
+    hasher.update(format!(
+        "{:?}",
+        (
+            font_size.to_bits(),
+            dpi,
+            config.font_shaper,
+            config.font_rasterizer,
+            &config.harfbuzz_features,
+            config.freetype_load_target,
+            config.freetype_render_target,
+            config.freetype_load_flags,
+            config.freetype_interpreter_version,
+            config.display_pixel_geometry,
+            config.unicode_version,
+        )
+    ));
+    Some(format!("{:x}", hasher.finalize()))
+}
+
+fn load(path: &Path) -> anyhow::Result<CacheFile> {
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)?;
+    let mut reader = std::io::BufReader::new(&file);
+    let mut decode = varbincode::Deserializer::new(&mut reader);
+    let cache = CacheFile::deserialize(&mut decode)?;
+    anyhow::ensure!(
+        cache.version == CACHE_VERSION && cache.wezterm_version == config::wezterm_version(),
+        "cache was written by a different version"
+    );
+    // Record the use, so that pruning removes the least recently used files
+    if let Err(err) = file.set_modified(SystemTime::now()) {
+        log::debug!("Failed to update mtime of {}: {err:#}", path.display());
+    }
+    Ok(cache)
+}
+
+/// Removes the least recently used files from `dir` until
+/// their total size is no more than `max_size`
+fn prune(dir: &Path, max_size: usize) -> anyhow::Result<()> {
+    let mut files = vec![];
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let meta = entry.metadata()?;
+        if meta.is_file() {
+            let modified = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            files.push((modified, meta.len() as usize, entry.path()));
+        }
+    }
+    files.sort();
+
+    let mut total: usize = files.iter().map(|(_, len, _)| len).sum();
+    for (_, len, path) in files {
+        if total <= max_size {
+            break;
+        }
+        match std::fs::remove_file(&path) {
+            Ok(_) => total -= len,
+            Err(err) => log::debug!("Failed to remove {}: {err:#}", path.display()),
+        }
+    }
+    Ok(())
+}
+
+impl FontDiskCache {
+    /// Returns the cache for `handle` used as the primary font at the
+    /// given size and dpi, or None if the cache is disabled
+    pub fn open(
+        config: &Config,
+        handle: &ParsedFont,
+        font_size: f64,
+        dpi: u32,
+    ) -> Option<Arc<Self>> {
+        if config.font_disk_cache_size == 0 {
+            return None;
+        }
+        remove_stale_versions();
+
+        let identity = identity(config, handle, font_size, dpi)?;
+        let path = cache_dir()
+            .join(format!("v{CACHE_VERSION}"))
+            .join(format!("{identity}.bin"));
+
+        let mut caches = CACHES.lock().unwrap();
+        if let Some(cache) = caches.get(&path) {
+            return Some(Arc::clone(cache));
+        }
+
+        let file = match load(&path) {
+            Ok(file) => file,
+            Err(err) => {
+                log::trace!("Not using font cache {}: {err:#}", path.display());
+                CacheFile {
+                    version: CACHE_VERSION,
+                    wezterm_version: config::wezterm_version().to_string(),
+                    ..CacheFile::default()
+                }
+            }
+        };
+
+        let cache = Arc::new(Self {
+            path: path.clone(),
+            max_size: config.font_disk_cache_size,
+            state: Mutex::new(CacheState {
+                file,
+                dirty: false,
+                flush_scheduled: false,
+            }),
+        });
+        caches.insert(path, Arc::clone(&cache));
+        Some(cache)
+    }
+
+    fn is_cacheable_text(text: &str) -> bool {
+        text.is_ascii() && text.len() <= MAX_SHAPE_TEXT_LEN
+    }
+
+    pub fn shape(
+        &self,
+        text: &str,
+        presentation: Option<Presentation>,
+        direction: Direction,
+    ) -> Option<Vec<GlyphInfo>> {
+        if !Self::is_cacheable_text(text) {
+            return None;
+        }
+        let key = ShapeKey::new(text, presentation, direction);
+        let state = self.state.lock().unwrap();
+        match state.file.shapes.get(&key) {
+            Some(infos) => {
+                metrics::counter!("font.disk_cache.shape.hit").increment(1);
+                Some(infos.iter().map(CachedGlyphInfo::to_glyph_info).collect())
+            }
+            None => {
+                metrics::counter!("font.disk_cache.shape.miss").increment(1);
+                None
+            }
+        }
+    }
+
+    /// Records the result of shaping `text`.  Results that use
+    /// fallback fonts, or that are missing glyphs, are not recorded
+    pub fn store_shape(
+        self: &Arc<Self>,
+        text: &str,
+        presentation: Option<Presentation>,
+        direction: Direction,
+        infos: &[GlyphInfo],
+    ) {
+        if !Self::is_cacheable_text(text)
+            || !infos
+                .iter()
+                .all(|info| info.font_idx == 0 && info.glyph_pos != 0)
+        {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        if state.file.shapes.len() >= MAX_SHAPES_PER_FONT {
+            return;
+        }
+        state.file.shapes.insert(
+            ShapeKey::new(text, presentation, direction),
+            infos.iter().map(CachedGlyphInfo::new).collect(),
+        );
+        self.schedule_flush(&mut state);
+    }
+
+    pub fn glyph(&self, glyph_pos: u32) -> Option<RasterizedGlyph> {
+        let state = self.state.lock().unwrap();
+        let raster = match state.file.glyphs.get(&glyph_pos) {
+            Some(raster) => raster,
+            None => {
+                metrics::counter!("font.disk_cache.glyph.miss").increment(1);
+                return None;
+            }
+        };
+        metrics::counter!("font.disk_cache.glyph.hit").increment(1);
+        Some(RasterizedGlyph {
+            data: raster.data.clone(),
+            height: raster.height,
+            width: raster.width,
+            bearing_x: PixelLength::new(raster.bearing_x),
+            bearing_y: PixelLength::new(raster.bearing_y),
+            has_color: raster.has_color,
+            is_scaled: raster.is_scaled,
+        })
+    }
+
+    pub fn store_glyph(self: &Arc<Self>, glyph_pos: u32, glyph: &RasterizedGlyph) {
+        let mut state = self.state.lock().unwrap();
+        if state.file.glyphs.len() >= MAX_GLYPHS_PER_FONT {
+            return;
+        }
+        state.file.glyphs.insert(
+            glyph_pos,
+            CachedRaster {
+                data: glyph.data.clone(),
+                height: glyph.height,
+                width: glyph.width,
+                bearing_x: glyph.bearing_x.get(),
+                bearing_y: glyph.bearing_y.get(),
+                has_color: glyph.has_color,
+                is_scaled: glyph.is_scaled,
+            },
+        );
+        self.schedule_flush(&mut state);
+    }
+
+    fn schedule_flush(self: &Arc<Self>, state: &mut CacheState) {
+        state.dirty = true;
+        if state.flush_scheduled {
+            return;
+        }
+        let cache = Arc::clone(self);
+        match std::thread::Builder::new()
+            .name("font-disk-cache".to_string())
+            .spawn(move || {
+                std::thread::sleep(FLUSH_DELAY);
+                if let Err(err) = cache.flush() {
+                    log::warn!("Failed to write {}: {err:#}", cache.path.display());
+                }
+            }) {
+            Ok(_) => state.flush_scheduled = true,
+            Err(err) => log::warn!("Failed to schedule font cache write: {err:#}"),
+        }
+    }
+
+    fn flush(&self) -> anyhow::Result<()> {
+        let data = {
+            let mut state = self.state.lock().unwrap();
+            state.flush_scheduled = false;
+            if !state.dirty {
+                return Ok(());
+            }
+            state.dirty = false;
+
+            let mut data = vec![];
+            let mut encode = varbincode::Serializer::new(&mut data);
+            state.file.serialize(&mut encode)?;
+            drop(encode);
+            data
+        };
+
+        let dir = self
+            .path
+            .parent()
+            .ok_or_else(|| anyhow::anyhow!("font cache path has no parent"))?;
+        std::fs::create_dir_all(dir)?;
+        // Write and rename, so that a concurrent reader in another
+        // process never sees a partially written file
+        let temp = self
+            .path
+            .with_extension(format!("{}.tmp", std::process::id()));
+        std::fs::write(&temp, &data)?;
+        std::fs::rename(&temp, &self.path)?;
+
+        prune(dir, self.max_size)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::FontDatabase;
+    use config::{FontAttributes, FontWeight};
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    fn hash_of(key: &ShapeKey) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn write_cache(path: &Path, file: &CacheFile) {
+        let mut data = vec![];
+        let mut encode = varbincode::Serializer::new(&mut data);
+        file.serialize(&mut encode).unwrap();
+        drop(encode);
+        std::fs::write(path, data).unwrap();
+    }
+
+    fn set_modified(path: &Path, modified: SystemTime) {
+        std::fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+
+    #[test]
+    fn shape_key() {
+        let key = ShapeKey::new("abc", None, Direction::LeftToRight);
+        let same = ShapeKey::new("abc", None, Direction::LeftToRight);
+        assert!(key == same);
+        assert_eq!(hash_of(&key), hash_of(&same));
+
+        for other in [
+            ShapeKey::new("abd", None, Direction::LeftToRight),
+            ShapeKey::new("abc", Some(Presentation::Text), Direction::LeftToRight),
+            ShapeKey::new("abc", Some(Presentation::Emoji), Direction::LeftToRight),
+            ShapeKey::new("abc", None, Direction::RightToLeft),
+        ] {
+            assert!(key != other);
+        }
+    }
+
+    #[test]
+    fn font_identity() {
+        let db = FontDatabase::with_built_in().unwrap();
+        let font = db
+            .resolve(&FontAttributes::new("JetBrains Mono"), 14)
+            .unwrap();
+        let config = config::configuration();
+
+        let id = identity(&config, font, 12., 96).unwrap();
+        assert_eq!(identity(&config, font, 12., 96).unwrap(), id);
+        assert_ne!(identity(&config, font, 13., 96).unwrap(), id);
+        assert_ne!(identity(&config, font, 12., 144).unwrap(), id);
+
+        let mut other = (*config).clone();
+        other.harfbuzz_features = vec!["calt=0".to_string()];
+        assert_ne!(identity(&other, font, 12., 96).unwrap(), id);
+
+        let bold = FontAttributes {
+            weight: FontWeight::BOLD,
+            ..FontAttributes::new("JetBrains Mono")
+        };
+        let bold = db.resolve(&bold, 14).unwrap();
+        assert_ne!(identity(&config, bold, 12., 96).unwrap(), id);
+    }
+
+    #[test]
+    fn version_check() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("font.bin");
+
+        let mut file = CacheFile {
+            version: CACHE_VERSION,
+            wezterm_version: config::wezterm_version().to_string(),
+            ..CacheFile::default()
+        };
+        file.shapes
+            .insert(ShapeKey::new("a", None, Direction::LeftToRight), vec![]);
+        write_cache(&path, &file);
+
+        // Loading a file counts as using it
+        let a_while_ago = SystemTime::now() - Duration::from_secs(3600);
+        set_modified(&path, a_while_ago);
+        assert_eq!(load(&path).unwrap().shapes.len(), 1);
+        assert!(std::fs::metadata(&path).unwrap().modified().unwrap() > a_while_ago);
+
+        file.version = CACHE_VERSION + 1;
+        write_cache(&path, &file);
+        assert!(load(&path).is_err());
+
+        file.version = CACHE_VERSION;
+        file.wezterm_version = "20200101-000000-00000000".to_string();
+        write_cache(&path, &file);
+        assert!(load(&path).is_err());
+
+        std::fs::write(&path, b"junk").unwrap();
+        assert!(load(&path).is_err());
+    }
+
+    #[test]
+    fn prune_least_recently_used() {
+        let dir = tempfile::tempdir().unwrap();
+        let now = SystemTime::now();
+        for (age, name) in [(3, "a"), (1, "b"), (2, "c")] {
+            let path = dir.path().join(name);
+            std::fs::write(&path, [0u8; 100]).unwrap();
+            set_modified(&path, now - Duration::from_secs(age * 60));
+        }
+
+        let remaining = || {
+            let mut names: Vec<String> = std::fs::read_dir(dir.path())
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+                .collect();
+            names.sort();
+            names
+        };
+
+        prune(dir.path(), 300).unwrap();
+        assert_eq!(remaining(), vec!["a", "b", "c"]);
+        prune(dir.path(), 250).unwrap();
+        assert_eq!(remaining(), vec!["b", "c"]);
+        prune(dir.path(), 100).unwrap();
+        assert_eq!(remaining(), vec!["b"]);
+        prune(dir.path(), 0).unwrap();
+        assert!(remaining().is_empty());
+    }
+}
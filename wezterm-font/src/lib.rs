@@ -1,7 +1,9 @@
 use crate::db::FontDatabase;
+use crate::diskcache::FontDiskCache;
 use crate::locator::{new_locator, FontLocator};
 use crate::parser::ParsedFont;
 use crate::rasterizer::{new_rasterizer, FontRasterizer};
+use crate::shaper::pool::ShapeRequest;
 use crate::shaper::{new_shaper, FontShaper, PresentationWidth};
 use anyhow::{Context, Error};
 use config::{
@@ -17,11 +19,13 @@ use std::sync::mpsc::{channel, Sender};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use termwiz::cell::Presentation;
+use termwiz::cellcluster::CellCluster;
 use thiserror::Error;
 use wezterm_bidi::Direction;
 use wezterm_term::{CellAttributes, Intensity};
 use wezterm_toast_notification::ToastNotification;
 
+mod diskcache;
 mod hbwrap;
 
 pub mod db;
@@ -36,6 +40,7 @@ pub mod units;
 pub mod fcwrap;
 
 pub use crate::rasterizer::RasterizedGlyph;
+pub use crate::shaper::pool::BackgroundShapeResult;
 pub use crate::shaper::{FallbackIdx, FontMetrics, GlyphInfo};
 
 #[derive(Debug, Error)]
@@ -67,6 +72,9 @@ pub struct LoadedFont {
     /// Glyphs for which no font was found and for which we should
     /// stop searching
     tried_glyphs: RefCell<HashSet<char>>,
+    /// Shaping and rasterization results for the primary font
+    /// that persist across runs
+    disk_cache: Option<Arc<FontDiskCache>>,
 }
 
 impl std::fmt::Debug for LoadedFont {
@@ -203,6 +211,17 @@ impl LoadedFont {
             }
         }
 
+        let cacheable = range.is_none();
+        if cacheable {
+            if let Some(infos) = self
+                .disk_cache
+                .as_ref()
+                .and_then(|cache| cache.shape(text, presentation, direction))
+            {
+                return Ok((false, infos));
+            }
+        }
+
         let result = self.shaper.borrow().shape(
             text,
             self.font_size,
@@ -239,9 +258,44 @@ impl LoadedFont {
             }
         }
 
+        if cacheable && !async_resolve {
+            if let (Ok(infos), Some(cache)) = (&result, &self.disk_cache) {
+                cache.store_shape(text, presentation, direction, infos);
+            }
+        }
+
         result.map(|r| (async_resolve, r))
     }
 
+    /// Shapes `cluster` on a background thread, calling `completion`
+    /// on that thread with the result.  If the text needs font fallback
+    /// resolution the result is None, and the caller should use `shape`
+    /// instead.
+    pub fn shape_in_background<F: FnOnce(BackgroundShapeResult) + Send + 'static>(
+        &self,
+        cluster: CellCluster,
+        filter_out_synthetic: fn(&mut Vec<char>),
+        completion: F,
+    ) -> anyhow::Result<()> {
+        let font_config = self
+            .font_config
+            .upgrade()
+            .ok_or_else(|| anyhow::anyhow!("font configuration has been dropped"))?;
+        let config = font_config.config.borrow().clone();
+        crate::shaper::pool::submit(ShapeRequest {
+            font_id: self.id,
+            handles: self.clone_handles(),
+            config,
+            font_size: self.font_size,
+            dpi: self.dpi,
+            cluster,
+            tried_glyphs: self.tried_glyphs.borrow().clone(),
+            filter_out_synthetic,
+            completion: Box::new(completion),
+        });
+        Ok(())
+    }
+
     pub fn metrics_for_idx(&self, font_idx: usize) -> anyhow::Result<FontMetrics> {
         self.shaper
             .borrow()
@@ -267,8 +321,13 @@ impl LoadedFont {
         glyph_pos: u32,
         fallback: FallbackIdx,
     ) -> anyhow::Result<RasterizedGlyph> {
+        let disk_cache = self.disk_cache.as_ref().filter(|_| fallback == 0);
+        if let Some(glyph) = disk_cache.and_then(|cache| cache.glyph(glyph_pos)) {
+            return Ok(glyph);
+        }
+
         let mut rasterizers = self.rasterizers.borrow_mut();
-        if let Some(raster) = rasterizers.get(&fallback) {
+        let result = if let Some(raster) = rasterizers.get(&fallback) {
             raster.rasterize_glyph(glyph_pos, self.font_size, self.dpi)
         } else {
             let raster_selection = self
@@ -285,7 +344,12 @@ impl LoadedFont {
             let result = raster.rasterize_glyph(glyph_pos, self.font_size, self.dpi);
             rasterizers.insert(fallback, raster);
             result
+        };
+
+        if let (Ok(glyph), Some(cache)) = (&result, disk_cache) {
+            cache.store_glyph(glyph_pos, glyph);
         }
+        result
     }
 
     pub fn clone_handles(&self) -> Vec<ParsedFont> {
@@ -635,6 +699,10 @@ impl FontConfigInner {
             )
         })?;
 
+        let disk_cache = handles
+            .first()
+            .and_then(|handle| FontDiskCache::open(&config, handle, font_size, dpi));
+
         let loaded = Rc::new(LoadedFont {
             rasterizers: RefCell::new(HashMap::new()),
             handles: RefCell::new(handles),
@@ -648,6 +716,7 @@ impl FontConfigInner {
             id: alloc_font_id(),
             tried_glyphs: RefCell::new(HashSet::new()),
             pixel_geometry: config.display_pixel_geometry,
+            disk_cache,
         });
 
         Ok(loaded)
@@ -926,6 +995,10 @@ impl FontConfigInner {
             }
         }
 
+        let disk_cache = handles
+            .first()
+            .and_then(|handle| FontDiskCache::open(&config, handle, font_size, dpi));
+
         let loaded = Rc::new(LoadedFont {
             rasterizers: RefCell::new(HashMap::new()),
             handles: RefCell::new(handles),
@@ -939,6 +1012,7 @@ impl FontConfigInner {
             id: alloc_font_id(),
             tried_glyphs: RefCell::new(HashSet::new()),
             pixel_geometry: config.display_pixel_geometry,
+            disk_cache,
         });
 
         fonts.insert(style.clone(), Rc::clone(&loaded));
@@ -0,0 +1,127 @@
+//! Shaping text on a pool of background threads, so that shaping
+//! long runs of text doesn't hold up rendering.
+//!
+//! The shapers used by LoadedFont can't be shared between threads,
+//! so each worker builds and caches its own shapers from the font
+//! handles passed along with each request.
+use crate::parser::ParsedFont;
+use crate::shaper::{new_shaper, FontShaper, GlyphInfo, PresentationWidth};
+use crate::LoadedFontId;
+use config::ConfigHandle;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use termwiz::cellcluster::CellCluster;
+
+/// The maximum number of shapers cached by each worker thread
+const MAX_SHAPERS_PER_THREAD: usize = 16;
+
+/// The result of shaping in the background.  None means that some
+/// of the text needs font fallback resolution, which must happen
+/// on the thread that owns the LoadedFont.
+pub type BackgroundShapeResult = anyhow::Result<Option<Vec<GlyphInfo>>>;
+
+pub(crate) struct ShapeRequest {
+    pub font_id: LoadedFontId,
+    pub handles: Vec<ParsedFont>,
+    pub config: ConfigHandle,
+    pub font_size: f64,
+    pub dpi: u32,
+    pub cluster: CellCluster,
+    /// Glyphs that fallback resolution has already given up on
+    pub tried_glyphs: HashSet<char>,
+    pub filter_out_synthetic: fn(&mut Vec<char>),
+    pub completion: Box<dyn FnOnce(BackgroundShapeResult) + Send>,
+}
+
+thread_local! {
+    /// Shapers keyed by font id and number of fallback handles;
+    /// fallback resolution only ever appends handles
+    static SHAPERS: RefCell<HashMap<(LoadedFontId, usize), Box<dyn FontShaper>>> =
+        RefCell::new(HashMap::new());
+}
+
+lazy_static::lazy_static! {
+    static ref POOL: Mutex<Option<Sender<ShapeRequest>>> = Mutex::new(None);
+}
+
+impl ShapeRequest {
+    fn shape(&self) -> BackgroundShapeResult {
+        SHAPERS.with(|shapers| {
+            let mut shapers = shapers.borrow_mut();
+            let key = (self.font_id, self.handles.len());
+            if !shapers.contains_key(&key) {
+                if shapers.len() >= MAX_SHAPERS_PER_THREAD {
+                    shapers.clear();
+                }
+                shapers.insert(key, new_shaper(&self.config, &self.handles)?);
+            }
+            let shaper = &shapers[&key];
+
+            let mut no_glyphs = vec![];
+            let presentation_width = PresentationWidth::with_cluster(&self.cluster);
+            let info = shaper.shape(
+                &self.cluster.text,
+                self.font_size,
+                self.dpi,
+                &mut no_glyphs,
+                Some(self.cluster.presentation),
+                self.cluster.direction,
+                None,
+                Some(&presentation_width),
+            )?;
+
+            no_glyphs
+                .retain(|c| *c != '\u{FE0F}' && *c != '\u{FE0E}' && !self.tried_glyphs.contains(c));
+            (self.filter_out_synthetic)(&mut no_glyphs);
+
+            Ok(if no_glyphs.is_empty() {
+                Some(info)
+            } else {
+                None
+            })
+        })
+    }
+}
+
+fn worker(rx: Arc<Mutex<Receiver<ShapeRequest>>>) {
+    loop {
+        let request = match rx.lock().unwrap().recv() {
+            Ok(request) => request,
+            Err(_) => return,
+        };
+        let start = Instant::now();
+        let result = request.shape();
+        metrics::histogram!("shaper.background.latency").record(start.elapsed());
+        (request.completion)(result);
+    }
+}
+
+pub(crate) fn submit(request: ShapeRequest) {
+    let mut pool = POOL.lock().unwrap();
+    let tx = pool.get_or_insert_with(|| {
+        let (tx, rx) = channel();
+        let rx = Arc::new(Mutex::new(rx));
+        let num_threads = std::thread::available_parallelism()
+            .map(|n| n.get() / 2)
+            .unwrap_or(1)
+            .clamp(1, 4);
+        for idx in 0..num_threads {
+            let rx = Arc::clone(&rx);
+            if let Err(err) = std::thread::Builder::new()
+                .name(format!("shaper-{idx}"))
+                .spawn(move || worker(rx))
+            {
+                log::error!("Failed to spawn shaper thread: {err:#}");
+            }
+        }
+        tx
+    });
+    if let Err(err) = tx.send(request) {
+        (err.0.completion)(Err(anyhow::anyhow!(
+            "background shaping pool is unavailable"
+        )));
+    }
+}
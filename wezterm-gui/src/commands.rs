@@ -673,6 +673,14 @@ pub fn derive_command_from_key_assignment(action: &KeyAssignment) -> Option<Comm
             menubar: &["Edit"],
             icon: Some("md_content_paste"),
         },
+        CancelPaste => CommandDef {
+            brief: "Cancel paste".into(),
+            doc: "Cancels a large paste that is still being sent to the pane".into(),
+            keys: vec![],
+            args: &[ArgType::ActivePane],
+            menubar: &["Edit"],
+            icon: Some("md_cancel"),
+        },
         ToggleFullScreen => CommandDef {
             brief: "Toggle full screen mode".into(),
             doc: "Switch between normal and full screen mode".into(),
@@ -681,6 +689,22 @@ pub fn derive_command_from_key_assignment(action: &KeyAssignment) -> Option<Comm
             menubar: &["View"],
             icon: Some("md_fullscreen"),
         },
+        ToggleFullScreenOnScreen(selector) => {
+            let screen = match selector {
+                ScreenSelector::Name(name) => name.to_string(),
+                ScreenSelector::Index(n) => {
+                    format!("the {} screen", english_ordinal(*n as isize + 1))
+                }
+            };
+            CommandDef {
+                brief: format!("Toggle full screen mode on {screen}").into(),
+                doc: format!("Switch between normal and full screen mode on {screen}").into(),
+                keys: vec![],
+                args: &[ArgType::ActiveWindow],
+                menubar: &[],
+                icon: Some("md_fullscreen"),
+            }
+        }
         ToggleAlwaysOnTop => CommandDef {
             brief: "Toggle always on Top".into(),
             doc: "Toggles the window between floating and non-floating states to stay on top of other windows.".into(),
@@ -698,6 +722,46 @@ pub fn derive_command_from_key_assignment(action: &KeyAssignment) -> Option<Comm
             menubar: &["Window"],
             icon: None,
         },
+        ToggleShortcutInhibit => CommandDef {
+            brief: "Toggle compositor shortcut inhibit".into(),
+            doc: "Toggles whether keys bound to compositor shortcuts are passed through to the window".into(),
+            keys: vec![],
+            args: &[ArgType::ActiveWindow],
+            menubar: &["Window"],
+            icon: Some("md_keyboard"),
+        },
+        TogglePresentationMode => CommandDef {
+            brief: "Toggle tearing presentation".into(),
+            doc: "Toggles whether the window presents frames as soon as they are ready, allowing tearing, or in sync with the display".into(),
+            keys: vec![],
+            args: &[ArgType::ActiveWindow],
+            menubar: &["Window"],
+            icon: None,
+        },
+        CaptureWindow => CommandDef {
+            brief: "Capture window as image".into(),
+            doc: "Saves an image of the window to your downloads directory".into(),
+            keys: vec![],
+            args: &[ArgType::ActiveWindow],
+            menubar: &["Window"],
+            icon: Some("md_camera"),
+        },
+        CapturePane => CommandDef {
+            brief: "Capture pane as image".into(),
+            doc: "Saves an image of the active pane to your downloads directory".into(),
+            keys: vec![],
+            args: &[ArgType::ActivePane],
+            menubar: &["Window"],
+            icon: Some("md_camera"),
+        },
+        CapturePaneToClipboard => CommandDef {
+            brief: "Copy pane image to clipboard".into(),
+            doc: "Copies an image of the active pane to the clipboard".into(),
+            keys: vec![],
+            args: &[ArgType::ActivePane],
+            menubar: &["Edit"],
+            icon: Some("md_camera"),
+        },
         SetWindowLevel(WindowLevel::AlwaysOnTop) => CommandDef {
             brief: "Always on Top".into(),
             doc: "Set the window level to be on top of other windows.".into(),
@@ -798,6 +862,16 @@ pub fn derive_command_from_key_assignment(action: &KeyAssignment) -> Option<Comm
             menubar: &["Help"],
             icon: Some("cod_debug"),
         },
+        ToggleDebugOverlay => CommandDef {
+            brief: "Toggle render statistics overlay".into(),
+            doc: "Shows or hides the overlay with frame rate, frame timing \
+                  and other render statistics for the window"
+                .into(),
+            keys: vec![],
+            args: &[ArgType::ActiveWindow],
+            menubar: &["Help"],
+            icon: Some("cod_dashboard"),
+        },
         InputSelector(_) => CommandDef {
             brief: "Prompt the user to choose from a list".into(),
             doc: "Activates the selector overlay and wait for input".into(),
@@ -901,6 +975,14 @@ pub fn derive_command_from_key_assignment(action: &KeyAssignment) -> Option<Comm
             menubar: &["Window"],
             icon: Some("cod_multiple_windows"),
         },
+        ShowTabOverview(_) => CommandDef {
+            brief: "Show the tab overview".into(),
+            doc: "Shows a thumbnail of each tab, from which a tab can be activated".into(),
+            keys: vec![],
+            args: &[ArgType::ActiveWindow],
+            menubar: &["Window"],
+            icon: Some("md_view_grid"),
+        },
         DecreaseFontSize => CommandDef {
             brief: "Decrease font size".into(),
             doc: "Scales the font size smaller by 10%".into(),
@@ -1410,6 +1492,47 @@ pub fn derive_command_from_key_assignment(action: &KeyAssignment) -> Option<Comm
                 icon: Some("oct_terminal"),
             }
         }
+        SelectLastCommandOutput => CommandDef {
+            brief: "Select the output of the last command".into(),
+            doc: "Selects the most recent semantic output zone \
+                  and scrolls the viewport to show its start"
+                .into(),
+            keys: vec![],
+            args: &[ArgType::ActivePane],
+            menubar: &["Edit"],
+            icon: Some("md_select"),
+        },
+        CopyLastCommandOutput(dest) => {
+            let target = match dest {
+                ClipboardCopyDestination::Clipboard => "clipboard",
+                ClipboardCopyDestination::PrimarySelection => "primary selection",
+                ClipboardCopyDestination::ClipboardAndPrimarySelection => {
+                    "clipboard and primary selection"
+                }
+            };
+            CommandDef {
+                brief: format!("Copy the output of the last command to the {target}").into(),
+                doc: format!(
+                    "Copies the text of the most recent semantic \
+                     output zone to the {target}"
+                )
+                .into(),
+                keys: vec![],
+                args: &[ArgType::ActivePane],
+                menubar: &["Edit"],
+                icon: Some("md_content_copy"),
+            }
+        }
+        ShowLastCommandOutput => CommandDef {
+            brief: "Show the output of the last command".into(),
+            doc: "Shows the most recent semantic output zone \
+                  in a scrollable overlay"
+                .into(),
+            keys: vec![],
+            args: &[ArgType::ActivePane],
+            menubar: &["View"],
+            icon: Some("oct_terminal"),
+        },
         ScrollByCurrentEventWheelDelta => CommandDef {
             brief: "Scrolls based on the mouse wheel position \
                 in the current mouse event"
@@ -1422,6 +1545,16 @@ pub fn derive_command_from_key_assignment(action: &KeyAssignment) -> Option<Comm
             menubar: &[],
             icon: None,
         },
+        ToggleMinimap => CommandDef {
+            brief: "Toggle the minimap".into(),
+            doc: "Shows or hides the miniature view of the scrollback \
+                  along the right edge of the active pane"
+                .into(),
+            keys: vec![],
+            args: &[ArgType::ActiveWindow],
+            menubar: &["View"],
+            icon: Some("md_map"),
+        },
         ScrollToBottom => CommandDef {
             brief: "Scroll to the bottom".into(),
             doc: "Scrolls to the bottom of the viewport".into(),
@@ -2047,11 +2180,14 @@ fn compute_default_actions() -> Vec<KeyAssignment> {
         CopyTo(ClipboardCopyDestination::PrimarySelection),
         CopyTo(ClipboardCopyDestination::Clipboard),
         PasteFrom(ClipboardPasteSource::Clipboard),
+        CancelPaste,
         ClearScrollback(ScrollbackEraseMode::ScrollbackOnly),
         ClearScrollback(ScrollbackEraseMode::ScrollbackAndViewport),
         QuickSelect,
         CharSelect(CharSelectArguments::default()),
         ActivateCopyMode,
+        SelectLastCommandOutput,
+        CopyLastCommandOutput(ClipboardCopyDestination::Clipboard),
         ClearKeyTableStack,
         ActivateCommandPalette,
         // ----------------- View
@@ -2063,6 +2199,10 @@ fn compute_default_actions() -> Vec<KeyAssignment> {
         ScrollByPage(NotNan::new(1.0).unwrap()),
         ScrollToTop,
         ScrollToBottom,
+        ScrollToPrompt(-1),
+        ScrollToPrompt(1),
+        ShowLastCommandOutput,
+        ToggleMinimap,
         // ----------------- Window
         ToggleFullScreen,
         ToggleAlwaysOnTop,
@@ -2070,6 +2210,11 @@ fn compute_default_actions() -> Vec<KeyAssignment> {
         SetWindowLevel(WindowLevel::AlwaysOnBottom),
         SetWindowLevel(WindowLevel::Normal),
         SetWindowLevel(WindowLevel::AlwaysOnTop),
+        ToggleShortcutInhibit,
+        TogglePresentationMode,
+        CaptureWindow,
+        CapturePane,
+        CapturePaneToClipboard,
         Hide,
         Search(Pattern::CurrentSelectionOrEmptyString),
         PaneSelect(PaneSelectArguments {
@@ -2097,6 +2242,7 @@ fn compute_default_actions() -> Vec<KeyAssignment> {
             mode: PaneSelectMode::MoveToNewWindow,
             show_pane_ids: false,
         }),
+        ShowTabOverview(TabOverviewArguments::default()),
         RotatePanes(RotationDirection::Clockwise),
         RotatePanes(RotationDirection::CounterClockwise),
         ActivateTab(0),
@@ -2141,6 +2287,7 @@ fn compute_default_actions() -> Vec<KeyAssignment> {
         OpenUri("https://github.com/wezterm/wezterm/discussions/".to_string()),
         OpenUri("https://github.com/wezterm/wezterm/issues/".to_string()),
         ShowDebugOverlay,
+        ToggleDebugOverlay,
         // ----------------- Misc
         OpenLinkAtMouseCursor,
     ];
@@ -1,8 +1,8 @@
-use crate::glyphcache::{GlyphCache, SizedBlockKey};
+use crate::glyphcache::{CursorSpriteKey, GlyphCache, SizedBlockKey};
 use crate::utilsprites::RenderMetrics;
 use ::window::bitmaps::atlas::Sprite;
 use ::window::color::SrgbaPixel;
-use config::DimensionContext;
+use config::{Dimension, DimensionContext};
 use std::ops::Range;
 use termwiz::surface::CursorShape;
 use tiny_skia::{BlendMode, FillRule, Paint, Path, PathBuilder, PixmapMut, Stroke, Transform};
@@ -212,6 +212,7 @@ pub enum Block {
 /// <https://en.wikipedia.org/wiki/Block_Elements>
 /// <https://www.unicode.org/charts/PDF/U2580.pdf>
 /// <https://unicode.org/charts/PDF/U1FB00.pdf>
+/// <https://unicode.org/charts/PDF/U1CC00.pdf>
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
 pub enum BlockKey {
     /// List of block rectangles
@@ -224,6 +225,12 @@ pub enum BlockKey {
     Sextant(u8),
     /// An octant block pattern
     Octant(u8),
+    /// A separated quadrant pattern; bits are laid out as for quadrants
+    /// on a 2 by 2 grid, with a gap around each filled element
+    SeparatedQuadrant(u8),
+    /// A separated sextant pattern; bits are laid out as for `Sextant`,
+    /// with a gap around each filled element
+    SeparatedSextant(u8),
     /// A braille dot pattern
     Braille(u8),
     /// A progress bar pattern
@@ -3694,6 +3701,72 @@ impl BlockKey {
             0x1fbe6 => Self::Octant(0b00010100),
             // [🯧] MIDDLE RIGHT ONE QUARTER BLOCK (corresponds to OCTANT-46)
             0x1fbe7 => Self::Octant(0b00101000),
+            // [🯤] UPPER CENTRE ONE QUARTER BLOCK
+            0x1fbe4 => Self::Blocks(&[Block::Custom(2, 6, 0, 4, BlockAlpha::Full)]),
+            // [🯥] LOWER CENTRE ONE QUARTER BLOCK
+            0x1fbe5 => Self::Blocks(&[Block::Custom(2, 6, 4, 8, BlockAlpha::Full)]),
+            // Separated block quadrants
+            n @ 0x1cc21..=0x1cc2f => Self::SeparatedQuadrant((n - 0x1cc20) as u8),
+            // Separated block sextants
+            n @ 0x1ce51..=0x1ce8f => Self::SeparatedSextant((n - 0x1ce50) as u8),
+            // One sixteenth blocks, on a 4x4 grid, ordered left to right
+            // and then top to bottom
+            // [𜺐] UPPER LEFT ONE SIXTEENTH BLOCK
+            0x1ce90 => Self::Blocks(&[Block::Custom(0, 2, 0, 2, BlockAlpha::Full)]),
+            // [𜺑] UPPER CENTRE LEFT ONE SIXTEENTH BLOCK
+            0x1ce91 => Self::Blocks(&[Block::Custom(2, 4, 0, 2, BlockAlpha::Full)]),
+            // [𜺒] UPPER CENTRE RIGHT ONE SIXTEENTH BLOCK
+            0x1ce92 => Self::Blocks(&[Block::Custom(4, 6, 0, 2, BlockAlpha::Full)]),
+            // [𜺓] UPPER RIGHT ONE SIXTEENTH BLOCK
+            0x1ce93 => Self::Blocks(&[Block::Custom(6, 8, 0, 2, BlockAlpha::Full)]),
+            // [𜺔] UPPER MIDDLE LEFT ONE SIXTEENTH BLOCK
+            0x1ce94 => Self::Blocks(&[Block::Custom(0, 2, 2, 4, BlockAlpha::Full)]),
+            // [𜺕] UPPER MIDDLE CENTRE LEFT ONE SIXTEENTH BLOCK
+            0x1ce95 => Self::Blocks(&[Block::Custom(2, 4, 2, 4, BlockAlpha::Full)]),
+            // [𜺖] UPPER MIDDLE CENTRE RIGHT ONE SIXTEENTH BLOCK
+            0x1ce96 => Self::Blocks(&[Block::Custom(4, 6, 2, 4, BlockAlpha::Full)]),
+            // [𜺗] UPPER MIDDLE RIGHT ONE SIXTEENTH BLOCK
+            0x1ce97 => Self::Blocks(&[Block::Custom(6, 8, 2, 4, BlockAlpha::Full)]),
+            // [𜺘] LOWER MIDDLE LEFT ONE SIXTEENTH BLOCK
+            0x1ce98 => Self::Blocks(&[Block::Custom(0, 2, 4, 6, BlockAlpha::Full)]),
+            // [𜺙] LOWER MIDDLE CENTRE LEFT ONE SIXTEENTH BLOCK
+            0x1ce99 => Self::Blocks(&[Block::Custom(2, 4, 4, 6, BlockAlpha::Full)]),
+            // [𜺚] LOWER MIDDLE CENTRE RIGHT ONE SIXTEENTH BLOCK
+            0x1ce9a => Self::Blocks(&[Block::Custom(4, 6, 4, 6, BlockAlpha::Full)]),
+            // [𜺛] LOWER MIDDLE RIGHT ONE SIXTEENTH BLOCK
+            0x1ce9b => Self::Blocks(&[Block::Custom(6, 8, 4, 6, BlockAlpha::Full)]),
+            // [𜺜] LOWER LEFT ONE SIXTEENTH BLOCK
+            0x1ce9c => Self::Blocks(&[Block::Custom(0, 2, 6, 8, BlockAlpha::Full)]),
+            // [𜺝] LOWER CENTRE LEFT ONE SIXTEENTH BLOCK
+            0x1ce9d => Self::Blocks(&[Block::Custom(2, 4, 6, 8, BlockAlpha::Full)]),
+            // [𜺞] LOWER CENTRE RIGHT ONE SIXTEENTH BLOCK
+            0x1ce9e => Self::Blocks(&[Block::Custom(4, 6, 6, 8, BlockAlpha::Full)]),
+            // [𜺟] LOWER RIGHT ONE SIXTEENTH BLOCK
+            0x1ce9f => Self::Blocks(&[Block::Custom(6, 8, 6, 8, BlockAlpha::Full)]),
+            // [𜺡] RIGHT THREE QUARTERS LOWER ONE QUARTER BLOCK
+            0x1cea1 => Self::Blocks(&[Block::Custom(2, 8, 6, 8, BlockAlpha::Full)]),
+            // [𜺢] LEFT THREE QUARTERS LOWER ONE QUARTER BLOCK
+            0x1cea2 => Self::Blocks(&[Block::Custom(0, 6, 6, 8, BlockAlpha::Full)]),
+            // [𜺤] LOWER HALF LEFT ONE QUARTER BLOCK
+            0x1cea4 => Self::Blocks(&[Block::Custom(0, 2, 4, 8, BlockAlpha::Full)]),
+            // [𜺥] LOWER THREE QUARTERS LEFT ONE QUARTER BLOCK
+            0x1cea5 => Self::Blocks(&[Block::Custom(0, 2, 2, 8, BlockAlpha::Full)]),
+            // [𜺦] UPPER THREE QUARTERS LEFT ONE QUARTER BLOCK
+            0x1cea6 => Self::Blocks(&[Block::Custom(0, 2, 0, 6, BlockAlpha::Full)]),
+            // [𜺧] UPPER HALF LEFT ONE QUARTER BLOCK
+            0x1cea7 => Self::Blocks(&[Block::Custom(0, 2, 0, 4, BlockAlpha::Full)]),
+            // [𜺩] LEFT THREE QUARTERS UPPER ONE QUARTER BLOCK
+            0x1cea9 => Self::Blocks(&[Block::Custom(0, 6, 0, 2, BlockAlpha::Full)]),
+            // [𜺪] RIGHT THREE QUARTERS UPPER ONE QUARTER BLOCK
+            0x1ceaa => Self::Blocks(&[Block::Custom(2, 8, 0, 2, BlockAlpha::Full)]),
+            // [𜺬] UPPER HALF RIGHT ONE QUARTER BLOCK
+            0x1ceac => Self::Blocks(&[Block::Custom(6, 8, 0, 4, BlockAlpha::Full)]),
+            // [𜺭] UPPER THREE QUARTERS RIGHT ONE QUARTER BLOCK
+            0x1cead => Self::Blocks(&[Block::Custom(6, 8, 0, 6, BlockAlpha::Full)]),
+            // [𜺮] LOWER THREE QUARTERS RIGHT ONE QUARTER BLOCK
+            0x1ceae => Self::Blocks(&[Block::Custom(6, 8, 2, 8, BlockAlpha::Full)]),
+            // [𜺯] LOWER HALF RIGHT ONE QUARTER BLOCK
+            0x1ceaf => Self::Blocks(&[Block::Custom(6, 8, 4, 8, BlockAlpha::Full)]),
             // [🬼] LOWER LEFT BLOCK DIAGONAL LOWER MIDDLE LEFT TO LOWER CENTRE
             0x1fb3c => Self::Poly(&[Poly {
                 path: &[
@@ -4583,6 +4656,293 @@ impl BlockKey {
                 },
             ]),
 
+            // Thirds can't be expressed in the 1/8ths used by Block, so fill a path instead
+            // [🯎] LEFT TWO THIRDS BLOCK
+            0x1fbce => Self::Poly(&[Poly {
+                path: &[
+                    PolyCommand::MoveTo(BlockCoord::Zero, BlockCoord::Zero),
+                    PolyCommand::LineTo(BlockCoord::Frac(2, 3), BlockCoord::Zero),
+                    PolyCommand::LineTo(BlockCoord::Frac(2, 3), BlockCoord::One),
+                    PolyCommand::LineTo(BlockCoord::Zero, BlockCoord::One),
+                    PolyCommand::Close,
+                ],
+                intensity: BlockAlpha::Full,
+                style: PolyStyle::Fill,
+            }]),
+            // [🯏] LEFT ONE THIRD BLOCK
+            0x1fbcf => Self::Poly(&[Poly {
+                path: &[
+                    PolyCommand::MoveTo(BlockCoord::Zero, BlockCoord::Zero),
+                    PolyCommand::LineTo(BlockCoord::Frac(1, 3), BlockCoord::Zero),
+                    PolyCommand::LineTo(BlockCoord::Frac(1, 3), BlockCoord::One),
+                    PolyCommand::LineTo(BlockCoord::Zero, BlockCoord::One),
+                    PolyCommand::Close,
+                ],
+                intensity: BlockAlpha::Full,
+                style: PolyStyle::Fill,
+            }]),
+            // [🯐] BOX DRAWINGS LIGHT DIAGONAL MIDDLE RIGHT TO LOWER LEFT
+            0x1fbd0 => Self::Poly(&[Poly {
+                path: &[
+                    PolyCommand::MoveTo(BlockCoord::One, BlockCoord::Frac(1, 2)),
+                    PolyCommand::LineTo(BlockCoord::Zero, BlockCoord::One),
+                ],
+                intensity: BlockAlpha::Full,
+                style: PolyStyle::Outline,
+            }]),
+            // [🯑] BOX DRAWINGS LIGHT DIAGONAL UPPER RIGHT TO MIDDLE LEFT
+            0x1fbd1 => Self::Poly(&[Poly {
+                path: &[
+                    PolyCommand::MoveTo(BlockCoord::One, BlockCoord::Zero),
+                    PolyCommand::LineTo(BlockCoord::Zero, BlockCoord::Frac(1, 2)),
+                ],
+                intensity: BlockAlpha::Full,
+                style: PolyStyle::Outline,
+            }]),
+            // [🯒] BOX DRAWINGS LIGHT DIAGONAL UPPER LEFT TO MIDDLE RIGHT
+            0x1fbd2 => Self::Poly(&[Poly {
+                path: &[
+                    PolyCommand::MoveTo(BlockCoord::Zero, BlockCoord::Zero),
+                    PolyCommand::LineTo(BlockCoord::One, BlockCoord::Frac(1, 2)),
+                ],
+                intensity: BlockAlpha::Full,
+                style: PolyStyle::Outline,
+            }]),
+            // [🯓] BOX DRAWINGS LIGHT DIAGONAL MIDDLE LEFT TO LOWER RIGHT
+            0x1fbd3 => Self::Poly(&[Poly {
+                path: &[
+                    PolyCommand::MoveTo(BlockCoord::Zero, BlockCoord::Frac(1, 2)),
+                    PolyCommand::LineTo(BlockCoord::One, BlockCoord::One),
+                ],
+                intensity: BlockAlpha::Full,
+                style: PolyStyle::Outline,
+            }]),
+            // [🯔] BOX DRAWINGS LIGHT DIAGONAL UPPER LEFT TO LOWER CENTRE
+            0x1fbd4 => Self::Poly(&[Poly {
+                path: &[
+                    PolyCommand::MoveTo(BlockCoord::Zero, BlockCoord::Zero),
+                    PolyCommand::LineTo(BlockCoord::Frac(1, 2), BlockCoord::One),
+                ],
+                intensity: BlockAlpha::Full,
+                style: PolyStyle::Outline,
+            }]),
+            // [🯕] BOX DRAWINGS LIGHT DIAGONAL UPPER CENTRE TO LOWER RIGHT
+            0x1fbd5 => Self::Poly(&[Poly {
+                path: &[
+                    PolyCommand::MoveTo(BlockCoord::Frac(1, 2), BlockCoord::Zero),
+                    PolyCommand::LineTo(BlockCoord::One, BlockCoord::One),
+                ],
+                intensity: BlockAlpha::Full,
+                style: PolyStyle::Outline,
+            }]),
+            // [🯖] BOX DRAWINGS LIGHT DIAGONAL UPPER RIGHT TO LOWER CENTRE
+            0x1fbd6 => Self::Poly(&[Poly {
+                path: &[
+                    PolyCommand::MoveTo(BlockCoord::One, BlockCoord::Zero),
+                    PolyCommand::LineTo(BlockCoord::Frac(1, 2), BlockCoord::One),
+                ],
+                intensity: BlockAlpha::Full,
+                style: PolyStyle::Outline,
+            }]),
+            // [🯗] BOX DRAWINGS LIGHT DIAGONAL UPPER CENTRE TO LOWER LEFT
+            0x1fbd7 => Self::Poly(&[Poly {
+                path: &[
+                    PolyCommand::MoveTo(BlockCoord::Frac(1, 2), BlockCoord::Zero),
+                    PolyCommand::LineTo(BlockCoord::Zero, BlockCoord::One),
+                ],
+                intensity: BlockAlpha::Full,
+                style: PolyStyle::Outline,
+            }]),
+            // [🯘] BOX DRAWINGS LIGHT DIAGONAL UPPER LEFT TO MIDDLE CENTRE TO UPPER RIGHT
+            0x1fbd8 => Self::Poly(&[Poly {
+                path: &[
+                    PolyCommand::MoveTo(BlockCoord::Zero, BlockCoord::Zero),
+                    PolyCommand::LineTo(BlockCoord::Frac(1, 2), BlockCoord::Frac(1, 2)),
+                    PolyCommand::LineTo(BlockCoord::One, BlockCoord::Zero),
+                ],
+                intensity: BlockAlpha::Full,
+                style: PolyStyle::Outline,
+            }]),
+            // [🯙] BOX DRAWINGS LIGHT DIAGONAL UPPER RIGHT TO MIDDLE CENTRE TO LOWER RIGHT
+            0x1fbd9 => Self::Poly(&[Poly {
+                path: &[
+                    PolyCommand::MoveTo(BlockCoord::One, BlockCoord::Zero),
+                    PolyCommand::LineTo(BlockCoord::Frac(1, 2), BlockCoord::Frac(1, 2)),
+                    PolyCommand::LineTo(BlockCoord::One, BlockCoord::One),
+                ],
+                intensity: BlockAlpha::Full,
+                style: PolyStyle::Outline,
+            }]),
+            // [🯚] BOX DRAWINGS LIGHT DIAGONAL LOWER LEFT TO MIDDLE CENTRE TO LOWER RIGHT
+            0x1fbda => Self::Poly(&[Poly {
+                path: &[
+                    PolyCommand::MoveTo(BlockCoord::Zero, BlockCoord::One),
+                    PolyCommand::LineTo(BlockCoord::Frac(1, 2), BlockCoord::Frac(1, 2)),
+                    PolyCommand::LineTo(BlockCoord::One, BlockCoord::One),
+                ],
+                intensity: BlockAlpha::Full,
+                style: PolyStyle::Outline,
+            }]),
+            // [🯛] BOX DRAWINGS LIGHT DIAGONAL UPPER LEFT TO MIDDLE CENTRE TO LOWER LEFT
+            0x1fbdb => Self::Poly(&[Poly {
+                path: &[
+                    PolyCommand::MoveTo(BlockCoord::Zero, BlockCoord::Zero),
+                    PolyCommand::LineTo(BlockCoord::Frac(1, 2), BlockCoord::Frac(1, 2)),
+                    PolyCommand::LineTo(BlockCoord::Zero, BlockCoord::One),
+                ],
+                intensity: BlockAlpha::Full,
+                style: PolyStyle::Outline,
+            }]),
+            // [🯜] BOX DRAWINGS LIGHT DIAGONAL UPPER LEFT TO LOWER CENTRE TO UPPER RIGHT
+            0x1fbdc => Self::Poly(&[Poly {
+                path: &[
+                    PolyCommand::MoveTo(BlockCoord::Zero, BlockCoord::Zero),
+                    PolyCommand::LineTo(BlockCoord::Frac(1, 2), BlockCoord::One),
+                    PolyCommand::LineTo(BlockCoord::One, BlockCoord::Zero),
+                ],
+                intensity: BlockAlpha::Full,
+                style: PolyStyle::Outline,
+            }]),
+            // [🯝] BOX DRAWINGS LIGHT DIAGONAL UPPER RIGHT TO MIDDLE LEFT TO LOWER RIGHT
+            0x1fbdd => Self::Poly(&[Poly {
+                path: &[
+                    PolyCommand::MoveTo(BlockCoord::One, BlockCoord::Zero),
+                    PolyCommand::LineTo(BlockCoord::Zero, BlockCoord::Frac(1, 2)),
+                    PolyCommand::LineTo(BlockCoord::One, BlockCoord::One),
+                ],
+                intensity: BlockAlpha::Full,
+                style: PolyStyle::Outline,
+            }]),
+            // [🯞] BOX DRAWINGS LIGHT DIAGONAL LOWER LEFT TO UPPER CENTRE TO LOWER RIGHT
+            0x1fbde => Self::Poly(&[Poly {
+                path: &[
+                    PolyCommand::MoveTo(BlockCoord::Zero, BlockCoord::One),
+                    PolyCommand::LineTo(BlockCoord::Frac(1, 2), BlockCoord::Zero),
+                    PolyCommand::LineTo(BlockCoord::One, BlockCoord::One),
+                ],
+                intensity: BlockAlpha::Full,
+                style: PolyStyle::Outline,
+            }]),
+            // [🯟] BOX DRAWINGS LIGHT DIAGONAL UPPER LEFT TO MIDDLE RIGHT TO LOWER LEFT
+            0x1fbdf => Self::Poly(&[Poly {
+                path: &[
+                    PolyCommand::MoveTo(BlockCoord::Zero, BlockCoord::Zero),
+                    PolyCommand::LineTo(BlockCoord::One, BlockCoord::Frac(1, 2)),
+                    PolyCommand::LineTo(BlockCoord::Zero, BlockCoord::One),
+                ],
+                intensity: BlockAlpha::Full,
+                style: PolyStyle::Outline,
+            }]),
+            // Half and quarter circles; the portion of the circle that
+            // falls outside of the cell is clipped away
+            // [🯠] TOP JUSTIFIED LOWER HALF WHITE CIRCLE
+            0x1fbe0 => Self::Poly(&[Poly {
+                path: &[PolyCommand::Circle {
+                    center: (BlockCoord::Frac(1, 2), BlockCoord::Zero),
+                    radius: BlockCoord::Frac(1, 2),
+                }],
+                intensity: BlockAlpha::Full,
+                style: PolyStyle::Outline,
+            }]),
+            // [🯡] RIGHT JUSTIFIED LEFT HALF WHITE CIRCLE
+            0x1fbe1 => Self::Poly(&[Poly {
+                path: &[PolyCommand::Circle {
+                    center: (BlockCoord::One, BlockCoord::Frac(1, 2)),
+                    radius: BlockCoord::Frac(1, 2),
+                }],
+                intensity: BlockAlpha::Full,
+                style: PolyStyle::Outline,
+            }]),
+            // [🯢] BOTTOM JUSTIFIED UPPER HALF WHITE CIRCLE
+            0x1fbe2 => Self::Poly(&[Poly {
+                path: &[PolyCommand::Circle {
+                    center: (BlockCoord::Frac(1, 2), BlockCoord::One),
+                    radius: BlockCoord::Frac(1, 2),
+                }],
+                intensity: BlockAlpha::Full,
+                style: PolyStyle::Outline,
+            }]),
+            // [🯣] LEFT JUSTIFIED RIGHT HALF WHITE CIRCLE
+            0x1fbe3 => Self::Poly(&[Poly {
+                path: &[PolyCommand::Circle {
+                    center: (BlockCoord::Zero, BlockCoord::Frac(1, 2)),
+                    radius: BlockCoord::Frac(1, 2),
+                }],
+                intensity: BlockAlpha::Full,
+                style: PolyStyle::Outline,
+            }]),
+            // [🯨] TOP JUSTIFIED LOWER HALF BLACK CIRCLE
+            0x1fbe8 => Self::Poly(&[Poly {
+                path: &[PolyCommand::Circle {
+                    center: (BlockCoord::Frac(1, 2), BlockCoord::Zero),
+                    radius: BlockCoord::Frac(1, 2),
+                }],
+                intensity: BlockAlpha::Full,
+                style: PolyStyle::Fill,
+            }]),
+            // [🯩] RIGHT JUSTIFIED LEFT HALF BLACK CIRCLE
+            0x1fbe9 => Self::Poly(&[Poly {
+                path: &[PolyCommand::Circle {
+                    center: (BlockCoord::One, BlockCoord::Frac(1, 2)),
+                    radius: BlockCoord::Frac(1, 2),
+                }],
+                intensity: BlockAlpha::Full,
+                style: PolyStyle::Fill,
+            }]),
+            // [🯪] BOTTOM JUSTIFIED UPPER HALF BLACK CIRCLE
+            0x1fbea => Self::Poly(&[Poly {
+                path: &[PolyCommand::Circle {
+                    center: (BlockCoord::Frac(1, 2), BlockCoord::One),
+                    radius: BlockCoord::Frac(1, 2),
+                }],
+                intensity: BlockAlpha::Full,
+                style: PolyStyle::Fill,
+            }]),
+            // [🯫] LEFT JUSTIFIED RIGHT HALF BLACK CIRCLE
+            0x1fbeb => Self::Poly(&[Poly {
+                path: &[PolyCommand::Circle {
+                    center: (BlockCoord::Zero, BlockCoord::Frac(1, 2)),
+                    radius: BlockCoord::Frac(1, 2),
+                }],
+                intensity: BlockAlpha::Full,
+                style: PolyStyle::Fill,
+            }]),
+            // [🯬] TOP RIGHT JUSTIFIED LOWER LEFT QUARTER BLACK CIRCLE
+            0x1fbec => Self::Poly(&[Poly {
+                path: &[PolyCommand::Circle {
+                    center: (BlockCoord::One, BlockCoord::Zero),
+                    radius: BlockCoord::One,
+                }],
+                intensity: BlockAlpha::Full,
+                style: PolyStyle::Fill,
+            }]),
+            // [🯭] BOTTOM LEFT JUSTIFIED UPPER RIGHT QUARTER BLACK CIRCLE
+            0x1fbed => Self::Poly(&[Poly {
+                path: &[PolyCommand::Circle {
+                    center: (BlockCoord::Zero, BlockCoord::One),
+                    radius: BlockCoord::One,
+                }],
+                intensity: BlockAlpha::Full,
+                style: PolyStyle::Fill,
+            }]),
+            // [🯮] BOTTOM RIGHT JUSTIFIED UPPER LEFT QUARTER BLACK CIRCLE
+            0x1fbee => Self::Poly(&[Poly {
+                path: &[PolyCommand::Circle {
+                    center: (BlockCoord::One, BlockCoord::One),
+                    radius: BlockCoord::One,
+                }],
+                intensity: BlockAlpha::Full,
+                style: PolyStyle::Fill,
+            }]),
+            // [🯯] TOP LEFT JUSTIFIED LOWER RIGHT QUARTER BLACK CIRCLE
+            0x1fbef => Self::Poly(&[Poly {
+                path: &[PolyCommand::Circle {
+                    center: (BlockCoord::Zero, BlockCoord::Zero),
+                    radius: BlockCoord::One,
+                }],
+                intensity: BlockAlpha::Full,
+                style: PolyStyle::Fill,
+            }]),
             // Braille dot patterns
             // ⠀ ⠁ ⠂ ⠃ ⠄ ⠅ ⠆ ⠇ ⠈ ⠉ ⠊ ⠋ ⠌ ⠍ ⠎ ⠏
             // ⠐ ⠑ ⠒ ⠓ ⠔ ⠕ ⠖ ⠗ ⠘ ⠙ ⠚ ⠛ ⠜ ⠝ ⠞ ⠟
@@ -5048,18 +5408,18 @@ impl GlyphCache {
         }
     }
 
+    /// Returns the sprite for the cursor `shape`, or for a ring when
+    /// `ring` is set.  `thickness` overrides cursor_thickness.
     pub fn cursor_sprite(
         &mut self,
         shape: Option<CursorShape>,
+        ring: bool,
+        thickness: Option<Dimension>,
         metrics: &RenderMetrics,
         width: u8,
     ) -> anyhow::Result<Sprite> {
-        if let Some(sprite) = self.cursor_glyphs.get(&(shape, width)) {
-            return Ok(sprite.clone());
-        }
-
         let mut metrics = metrics.scale_cell_width(width as f64);
-        if let Some(d) = &self.fonts.config().cursor_thickness {
+        if let Some(d) = thickness.or(self.fonts.config().cursor_thickness) {
             metrics.underline_height = d.evaluate_as_pixels(DimensionContext {
                 dpi: self.fonts.get_dpi() as f32,
                 pixel_max: metrics.underline_height as f32,
@@ -5067,6 +5427,16 @@ impl GlyphCache {
             }) as isize;
         }
 
+        let key = CursorSpriteKey {
+            shape,
+            ring,
+            width,
+            thickness: metrics.underline_height,
+        };
+        if let Some(sprite) = self.cursor_glyphs.get(&key) {
+            return Ok(sprite.clone());
+        }
+
         let mut buffer = Image::new(
             metrics.cell_size.width as usize,
             metrics.cell_size.height as usize,
@@ -5076,6 +5446,22 @@ impl GlyphCache {
         buffer.clear_rect(cell_rect, black);
 
         match shape {
+            _ if ring => {
+                self.draw_polys(
+                    &metrics,
+                    &[Poly {
+                        path: &[PolyCommand::Circle {
+                            center: (BlockCoord::Frac(1, 2), BlockCoord::Frac(1, 2)),
+                            radius: BlockCoord::FracWithOffset(1, 2, LineScale::Mul(-2)),
+                        }],
+                        intensity: BlockAlpha::Full,
+                        style: PolyStyle::OutlineHeavy,
+                    }],
+                    &mut buffer,
+                    PolyAA::AntiAlias,
+                    BlendMode::default(),
+                );
+            }
             None => {}
             Some(CursorShape::Default) => {
                 buffer.clear_rect(cell_rect, SrgbaPixel::rgba(0xff, 0xff, 0xff, 0xff));
@@ -5134,7 +5520,7 @@ impl GlyphCache {
         }
 
         let sprite = self.atlas.allocate(&buffer)?;
-        self.cursor_glyphs.insert((shape, width), sprite.clone());
+        self.cursor_glyphs.insert(key, sprite.clone());
         Ok(sprite)
     }
 
@@ -5153,6 +5539,8 @@ impl GlyphCache {
                 descender_row: 0,
                 descender_plus_two: 0,
                 underline_height: *underline_height,
+                double_underline_gap: *underline_height,
+                curly_underline_height: 2 * *underline_height,
                 strike_row: 0,
                 cell_size: cell_size.clone(),
             },
@@ -5398,6 +5786,12 @@ impl GlyphCache {
                     }
                 }
             }
+            BlockKey::SeparatedQuadrant(pattern) => {
+                fill_separated_mosaic(&mut buffer, &metrics, pattern, 2);
+            }
+            BlockKey::SeparatedSextant(pattern) => {
+                fill_separated_mosaic(&mut buffer, &metrics, pattern, 3);
+            }
             BlockKey::Braille(dots_pattern) => {
                 // `dots_pattern` is a byte whose bits corresponds to dots
                 // on a 2 by 4 dots-grid.
@@ -6007,6 +6401,32 @@ impl GlyphCache {
 }
 
 // Fill a rectangular region described by the x and y ranges
+/// Fills the elements of a 2 column mosaic pattern with `rows` rows,
+/// leaving a gap around each element in the style of teletext
+/// "separated" graphics.  Bit 0 is the upper left element, bit 1 the
+/// element to its right, and so on, row by row.
+fn fill_separated_mosaic(buffer: &mut Image, metrics: &RenderMetrics, pattern: u8, rows: u8) {
+    let width = metrics.cell_size.width as f32;
+    let height = metrics.cell_size.height as f32;
+    let (x_half, y_row) = (width / 2., height / rows as f32);
+    // Half of the gap goes on each side of an element, so that the gaps
+    // remain even when the glyphs are tiled across adjacent cells
+    let inset = (metrics.underline_height as f32).max(1.) / 2.;
+    for row in 0..rows {
+        for col in 0..2 {
+            let bit = 2 * row + col;
+            if pattern & (1u8 << bit) != 0 {
+                fill_rect(
+                    buffer,
+                    col as f32 * x_half + inset..(col + 1) as f32 * x_half - inset,
+                    row as f32 * y_row + inset..(row + 1) as f32 * y_row - inset,
+                    BlockAlpha::Full,
+                );
+            }
+        }
+    }
+}
+
 fn fill_rect(buffer: &mut Image, x: Range<f32>, y: Range<f32>, intensity: BlockAlpha) {
     let (width, height) = buffer.image_dimensions();
     let mut pixmap =
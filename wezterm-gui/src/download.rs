@@ -25,7 +25,7 @@ fn neuter_name(name: &str) -> Option<&str> {
 /// in the user's download folder that doesn't conflict with any other
 /// files in that folder.
 /// Returns the selected name and the opened File on success.
-fn resolve_file_name(name: Option<&str>) -> anyhow::Result<(PathBuf, File)> {
+pub fn resolve_file_name(name: Option<&str>) -> anyhow::Result<(PathBuf, File)> {
     let name = name
         .and_then(neuter_name)
         .unwrap_or("downloaded-via-wezterm");
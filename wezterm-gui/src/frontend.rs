@@ -11,7 +11,7 @@ use mux::window::WindowId as MuxWindowId;
 use mux::{Mux, MuxNotification};
 use promise::{Future, Promise};
 use std::cell::RefCell;
-use std::collections::{BTreeMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::rc::Rc;
 use std::sync::Arc;
 use wezterm_term::{Alert, ClipboardSelection};
@@ -22,6 +22,12 @@ pub struct GuiFrontEnd {
     switching_workspaces: RefCell<bool>,
     spawned_mux_window: RefCell<HashSet<MuxWindowId>>,
     known_windows: RefCell<BTreeMap<Window, MuxWindowId>>,
+    /// Mux windows that are to be shown as modal dialogs of a window,
+    /// see GuiFrontEnd::record_dialog_parent
+    dialog_parents: RefCell<HashMap<MuxWindowId, Window>>,
+    /// Activation tokens for mux windows whose gui window is yet to
+    /// be created, see GuiFrontEnd::activate_mux_window
+    pending_activations: RefCell<HashMap<MuxWindowId, String>>,
     client_id: Arc<ClientId>,
     config_subscription: RefCell<Option<ConfigSubscription>>,
 }
@@ -45,6 +51,8 @@ impl GuiFrontEnd {
             switching_workspaces: RefCell::new(false),
             spawned_mux_window: RefCell::new(HashSet::new()),
             known_windows: RefCell::new(BTreeMap::new()),
+            dialog_parents: RefCell::new(HashMap::new()),
+            pending_activations: RefCell::new(HashMap::new()),
             client_id: client_id.clone(),
             config_subscription: RefCell::new(None),
         });
@@ -160,6 +168,18 @@ impl GuiFrontEnd {
                         .detach();
                     }
                 }
+                MuxNotification::ActivateWindow {
+                    window_id,
+                    activation_token,
+                } => {
+                    promise::spawn::spawn_into_main_thread(async move {
+                        front_end().activate_mux_window(window_id, activation_token);
+                    })
+                    .detach();
+                }
+                MuxNotification::CaptureImage { .. } | MuxNotification::QueryClipboard { .. } => {
+                    // Handled by the TermWindow that contains the pane
+                }
                 MuxNotification::SaveToDownloads { name, data } => {
                     if !config::configuration().allow_download_protocols {
                         log::error!(
@@ -453,6 +473,9 @@ impl GuiFrontEnd {
     }
 
     pub fn record_known_window(&self, window: Window, mux_window_id: MuxWindowId) {
+        if let Some(token) = self.pending_activations.borrow_mut().remove(&mux_window_id) {
+            window.focus_with_activation_token(token);
+        }
         self.known_windows
             .borrow_mut()
             .insert(window, mux_window_id);
@@ -461,6 +484,32 @@ impl GuiFrontEnd {
         }
     }
 
+    /// Focuses the gui window for `mux_window_id` using an activation
+    /// token that was handed to another program.  If that window is
+    /// still being created, it is focused once it has been.
+    fn activate_mux_window(&self, mux_window_id: MuxWindowId, token: String) {
+        match self.gui_window_for_mux_window(mux_window_id) {
+            Some(gui) => gui.window.focus_with_activation_token(token),
+            None => {
+                self.pending_activations
+                    .borrow_mut()
+                    .insert(mux_window_id, token);
+            }
+        }
+    }
+
+    /// Arrange for the gui window that is created for `mux_window_id`
+    /// to be presented as a modal dialog of `parent`
+    pub fn record_dialog_parent(&self, mux_window_id: MuxWindowId, parent: Window) {
+        self.dialog_parents
+            .borrow_mut()
+            .insert(mux_window_id, parent);
+    }
+
+    pub fn take_dialog_parent(&self, mux_window_id: MuxWindowId) -> Option<Window> {
+        self.dialog_parents.borrow_mut().remove(&mux_window_id)
+    }
+
     pub fn forget_known_window(&self, window: &Window) {
         self.known_windows.borrow_mut().remove(window);
         if !self.is_switching_workspace() {
@@ -20,6 +20,8 @@ uniform vec3 foreground_text_hsb;
 uniform sampler2D atlas_nearest_sampler;
 uniform sampler2D atlas_linear_sampler;
 uniform bool subpixel_aa;
+uniform float text_gamma;
+uniform float text_contrast;
 uniform uint milliseconds;
 
 struct ColorEase {
@@ -101,6 +103,50 @@ vec4 to_srgb(vec4 v) {
 }
 */
 
+// Adjusts the antialiasing coverage of text drawn in the linear
+// color fg.  Dark text is weighted by 1/text_gamma and light text
+// by text_gamma, which evens out how heavy each appears when
+// blended over a background of the opposite lightness.
+float adjust_coverage(float coverage, vec3 fg) {
+  float luminance = dot(fg, vec3(0.2126, 0.7152, 0.0722));
+  float exponent = mix(1.0 / text_gamma, text_gamma, luminance);
+  return clamp(pow(coverage, exponent) * text_contrast, 0.0, 1.0);
+}
+
+// Samples the atlas using Catmull-Rom bicubic interpolation.
+// This uses the linear sampler to combine the 16 texels that
+// contribute to the result into 9 fetches.
+vec4 sample_cubic(vec2 uv) {
+  vec2 size = vec2(textureSize(atlas_linear_sampler, 0));
+  vec2 pos = uv * size;
+  vec2 pos1 = floor(pos - 0.5) + 0.5;
+  vec2 f = pos - pos1;
+
+  vec2 w0 = f * (-0.5 + f * (1.0 - 0.5 * f));
+  vec2 w1 = 1.0 + f * f * (-2.5 + 1.5 * f);
+  vec2 w2 = f * (0.5 + f * (2.0 - 1.5 * f));
+  vec2 w3 = f * f * (-0.5 + 0.5 * f);
+  vec2 w12 = w1 + w2;
+
+  vec2 p0 = (pos1 - 1.0) / size;
+  vec2 p12 = (pos1 + w2 / w12) / size;
+  vec2 p3 = (pos1 + 2.0) / size;
+
+  vec4 result = vec4(0.0);
+  result += textureLod(atlas_linear_sampler, vec2(p0.x, p0.y), 0.0) * w0.x * w0.y;
+  result += textureLod(atlas_linear_sampler, vec2(p12.x, p0.y), 0.0) * w12.x * w0.y;
+  result += textureLod(atlas_linear_sampler, vec2(p3.x, p0.y), 0.0) * w3.x * w0.y;
+  result += textureLod(atlas_linear_sampler, vec2(p0.x, p12.y), 0.0) * w0.x * w12.y;
+  result += textureLod(atlas_linear_sampler, vec2(p12.x, p12.y), 0.0) * w12.x * w12.y;
+  result += textureLod(atlas_linear_sampler, vec2(p3.x, p12.y), 0.0) * w3.x * w12.y;
+  result += textureLod(atlas_linear_sampler, vec2(p0.x, p3.y), 0.0) * w0.x * w3.y;
+  result += textureLod(atlas_linear_sampler, vec2(p12.x, p3.y), 0.0) * w12.x * w3.y;
+  result += textureLod(atlas_linear_sampler, vec2(p3.x, p3.y), 0.0) * w3.x * w3.y;
+
+  // The negative lobes of the filter can overshoot
+  return clamp(result, 0.0, 1.0);
+}
+
 vec4 to_srgb(vec4 linearRGB)
 {
   bvec3 cutoff = lessThan(linearRGB.rgb, vec3(0.0031308));
@@ -116,9 +162,16 @@ void main() {
     // Solid color block
     color = fg_color;
     colorMask = vec4(1.0);
-  } else if (o_has_color == 2.0) {
-    // The window background attachment
-    color = texture(atlas_linear_sampler, o_tex);
+  } else if (o_has_color == 2.0 || o_has_color == 5.0 || o_has_color == 6.0) {
+    // A full color image, such as the window background attachment,
+    // sampled using the linear, nearest or bicubic filter respectively
+    if (o_has_color == 5.0) {
+      color = texture(atlas_nearest_sampler, o_tex);
+    } else if (o_has_color == 6.0) {
+      color = sample_cubic(o_tex);
+    } else {
+      color = texture(atlas_linear_sampler, o_tex);
+    }
     // Apply window_background_image_opacity to the background image
     if (subpixel_aa) {
       colorMask = fg_color.aaaa;
@@ -147,8 +200,15 @@ void main() {
     colorMask = texture(atlas_nearest_sampler, o_tex);
     // and we need to tint with the fg_color
     color = fg_color;
-    if (!subpixel_aa) {
-      color.a = colorMask.a;
+    if (subpixel_aa) {
+      colorMask = vec4(
+        adjust_coverage(colorMask.r, color.rgb),
+        adjust_coverage(colorMask.g, color.rgb),
+        adjust_coverage(colorMask.b, color.rgb),
+        adjust_coverage(colorMask.a, color.rgb)
+      );
+    } else {
+      color.a = adjust_coverage(colorMask.a, color.rgb);
     }
     color = apply_hsv(color, foreground_text_hsb);
   }
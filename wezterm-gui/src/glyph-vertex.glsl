@@ -13,6 +13,8 @@ in vec3 hsv;
 in vec4 alt_color;
 
 uniform mat4 projection;
+// The page of the texture atlas that is bound for this draw
+uniform float atlas_page;
 
 out float o_has_color;
 out vec2 o_tex;
@@ -33,6 +35,16 @@ void pass_through_vertex() {
 void main() {
   pass_through_vertex();
 
+  // The integer part of the texture x coordinate is the atlas page.
+  // Quads on other pages are drawn by separate passes, so we place
+  // them outside the clip volume for this one.
+  float page = floor(tex.x);
+  o_tex = vec2(tex.x - page, tex.y);
+  if (page != atlas_page) {
+    gl_Position = vec4(2.0, 2.0, 2.0, 1.0);
+    return;
+  }
+
   // Use the adjusted cell position to render the quad
   gl_Position = projection * vec4(position, 0.0, 1.0);
 }
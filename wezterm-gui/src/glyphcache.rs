@@ -5,7 +5,7 @@ use crate::termwindow::render::paint::AllowImage;
 use ::window::bitmaps::atlas::{Atlas, OutOfTextureSpace, Sprite};
 use ::window::bitmaps::{BitmapImage, Image, ImageTexture, Texture2d};
 use ::window::color::SrgbaPixel;
-use ::window::{Point, Rect};
+use ::window::{Point, Rect, Size};
 use anyhow::Context;
 use config::{AllowSquareGlyphOverflow, TextStyle};
 use euclid::num::Zero;
@@ -16,14 +16,14 @@ use lfucache::LfuCache;
 use ordered_float::NotNan;
 use std::cell::RefCell;
 use std::collections::HashMap;
-use std::io::Seek;
+use std::io::{Read, Seek};
 use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{sync_channel, Receiver, RecvTimeoutError, SyncSender, TryRecvError};
 use std::sync::{Arc, LazyLock, MutexGuard};
 use std::time::{Duration, Instant};
 use termwiz::color::RgbColor;
-use termwiz::image::{ImageData, ImageDataType};
+use termwiz::image::{ImageAnimation, ImageAnimationState, ImageData, ImageDataType};
 use termwiz::surface::CursorShape;
 use wezterm_blob_leases::{BlobLease, BlobManager, BoxedReader};
 use wezterm_font::units::*;
@@ -31,6 +31,11 @@ use wezterm_font::{FontConfiguration, GlyphInfo, LoadedFont, LoadedFontId};
 use wezterm_term::Underline;
 
 static FRAME_ERROR_REPORTED: AtomicBool = AtomicBool::new(false);
+static MISSING_VIDEO_TOOL_REPORTED: AtomicBool = AtomicBool::new(false);
+
+/// The number of pages that the texture atlas may grow to before
+/// least recently used glyphs are evicted to make space
+const MAX_ATLAS_PAGES: usize = 4;
 
 /// We only want to report a frame error once at error level, because
 /// if it is triggering it is likely in a animated image and will continue
@@ -50,6 +55,42 @@ pub enum LoadState {
     Loaded,
 }
 
+/// Controls how the frames of an animated image advance
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImagePlayback {
+    /// Multiplies the rate at which the frames advance
+    pub speed: f32,
+    /// Stay on the current frame
+    pub paused: bool,
+}
+
+impl Default for ImagePlayback {
+    fn default() -> Self {
+        Self {
+            speed: 1.0,
+            paused: false,
+        }
+    }
+}
+
+impl ImagePlayback {
+    fn is_paused(&self) -> bool {
+        self.paused || self.speed <= 0.
+    }
+
+    /// Returns how long a frame with the specified `duration` should
+    /// be shown for, given that we cannot render faster than
+    /// `min_frame_duration`
+    fn frame_duration(&self, duration: Duration, min_frame_duration: Duration) -> Duration {
+        let duration = if self.speed > 0. && self.speed != 1. {
+            duration.mul_f32(1. / self.speed)
+        } else {
+            duration
+        };
+        duration.max(min_frame_duration)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct CellMetricKey {
     pub pixel_width: u16,
@@ -71,6 +112,16 @@ pub struct SizedBlockKey {
     pub size: CellMetricKey,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CursorSpriteKey {
+    pub shape: Option<CursorShape>,
+    /// A ring is drawn in place of `shape`
+    pub ring: bool,
+    pub width: u8,
+    /// The line thickness, in pixels
+    pub thickness: isize,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct GlyphKey {
     pub font_idx: usize,
@@ -189,6 +240,8 @@ impl std::fmt::Debug for CachedGlyph {
 struct LineKey {
     strike_through: bool,
     underline: Underline,
+    /// Draw a second wave beneath a curly underline
+    double_curly: bool,
     overline: bool,
     size: CellMetricKey,
 }
@@ -232,11 +285,46 @@ struct DecodedFrame {
 
 struct FrameDecoder {}
 
+fn log_decoder_error(err: anyhow::Error) {
+    if err
+        .downcast_ref::<std::sync::mpsc::SendError<DecodedFrame>>()
+        .is_some()
+    {
+        return;
+    }
+    // A missing ffmpeg is only reported once at error level, rather
+    // than each time that a video background is loaded
+    if err.downcast_ref::<crate::video::MissingTool>().is_some()
+        && MISSING_VIDEO_TOOL_REPORTED.swap(true, Ordering::Relaxed)
+    {
+        log::debug!("Error decoding video: {err:#}");
+        return;
+    }
+    log::error!("Error decoding image: {err:#}");
+}
+
 impl FrameDecoder {
-    pub fn start(lease: BlobLease) -> anyhow::Result<Receiver<DecodedFrame>> {
+    /// Starts decoding the frames of the image in a background thread.
+    /// Returns the receiver for the frames, and whether the frames
+    /// should be retained and cycled through once decoding completes.
+    /// Videos are not retained, as the decoder loops over them itself.
+    pub fn start(lease: BlobLease) -> anyhow::Result<(Receiver<DecodedFrame>, bool)> {
         let (tx, rx) = sync_channel(2);
 
-        let buf_reader = lease.get_reader().context("lease.get_reader()")?;
+        let mut buf_reader = lease.get_reader().context("lease.get_reader()")?;
+        let mut magic = [0u8; 12];
+        let is_video = buf_reader.read_exact(&mut magic).is_ok() && crate::video::is_video(&magic);
+        buf_reader.rewind().context("rewinding reader")?;
+
+        if is_video {
+            std::thread::spawn(move || {
+                if let Err(err) = Self::run_video_decoder_thread(buf_reader, tx) {
+                    log_decoder_error(err);
+                }
+            });
+            return Ok((rx, false));
+        }
+
         let reader = image::ImageReader::new(buf_reader)
             .with_guessed_format()
             .context("guess format from lease")?;
@@ -246,16 +334,34 @@ impl FrameDecoder {
 
         std::thread::spawn(move || {
             if let Err(err) = Self::run_decoder_thread(reader, format, tx) {
-                if err
-                    .downcast_ref::<std::sync::mpsc::SendError<DecodedFrame>>()
-                    .is_none()
-                {
-                    log::error!("Error decoding image: {err:#}");
-                }
+                log_decoder_error(err);
             }
         });
 
-        Ok(rx)
+        Ok((rx, true))
+    }
+
+    fn run_video_decoder_thread(
+        mut reader: BoxedReader,
+        tx: SyncSender<DecodedFrame>,
+    ) -> anyhow::Result<()> {
+        // ffmpeg needs to be able to seek within some containers,
+        // so we give it a file rather than piping the data to it
+        let mut file = tempfile::NamedTempFile::new().context("creating temporary file")?;
+        std::io::copy(&mut reader, &mut file).context("writing video to temporary file")?;
+        drop(reader);
+
+        crate::video::decode_frames(file.path(), |data, width, height, duration| {
+            let lease = BlobManager::store(&data).context("BlobManager::store")?;
+            tx.send(DecodedFrame {
+                lease,
+                duration,
+                width,
+                height,
+            })
+            .context("sending a frame")?;
+            Ok(())
+        })
     }
 
     fn run_decoder_thread(
@@ -378,11 +484,14 @@ struct FrameState {
     source: FrameSource,
     current_frame: DecodedFrame,
     frames: Vec<DecodedFrame>,
+    /// Whether to keep the decoded frames, to cycle through
+    /// them once decoding completes
+    retain_frames: bool,
     load_state: LoadState,
 }
 
 impl FrameState {
-    fn new(rx: Receiver<DecodedFrame>) -> Self {
+    fn new(rx: Receiver<DecodedFrame>, retain_frames: bool) -> Self {
         const BLACK_SIZE: usize = 8;
         static BLACK: LazyLock<BlobLease> = LazyLock::new(|| {
             let mut data = vec![];
@@ -395,6 +504,7 @@ impl FrameState {
         Self {
             source: FrameSource::Decoder(rx),
             frames: vec![],
+            retain_frames,
             current_frame: DecodedFrame {
                 lease: BLACK.clone(),
                 width: BLACK_SIZE,
@@ -406,7 +516,7 @@ impl FrameState {
     }
 
     fn wait_for_first_frame(&mut self, duration: Duration) {
-        if !self.frames.is_empty() {
+        if !self.frames.is_empty() || self.load_state == LoadState::Loaded {
             // Already decoded the first frame
             return;
         }
@@ -414,7 +524,9 @@ impl FrameState {
         match &mut self.source {
             FrameSource::Decoder(rx) => match rx.recv_timeout(duration) {
                 Ok(frame) => {
-                    self.frames.push(frame.clone());
+                    if self.retain_frames {
+                        self.frames.push(frame.clone());
+                    }
                     self.current_frame = frame;
                     self.load_state = LoadState::Loaded;
                 }
@@ -434,7 +546,9 @@ impl FrameState {
         match &mut self.source {
             FrameSource::Decoder(rx) => match rx.try_recv() {
                 Ok(frame) => {
-                    self.frames.push(frame.clone());
+                    if self.retain_frames {
+                        self.frames.push(frame.clone());
+                    }
                     self.current_frame = frame;
                     self.load_state = LoadState::Loaded;
                     true
@@ -490,6 +604,11 @@ pub struct DecodedImage {
     current_frame: RefCell<usize>,
     image: Arc<ImageData>,
     frames: RefCell<Option<FrameState>>,
+    /// The animation controls that were in effect the last time
+    /// we advanced the frames; used to notice when they change
+    animation: RefCell<ImageAnimation>,
+    /// How many times we have played through all of the frames
+    loops_done: RefCell<u32>,
 }
 
 impl DecodedImage {
@@ -500,16 +619,20 @@ impl DecodedImage {
             current_frame: RefCell::new(0),
             image: Arc::new(image),
             frames: RefCell::new(None),
+            animation: RefCell::new(ImageAnimation::default()),
+            loops_done: RefCell::new(0),
         }
     }
 
     fn start_frame_decoder(lease: BlobLease, image_data: &Arc<ImageData>) -> Self {
         match FrameDecoder::start(lease.clone()) {
-            Ok(rx) => Self {
+            Ok((rx, retain_frames)) => Self {
                 frame_start: RefCell::new(Instant::now()),
                 current_frame: RefCell::new(0),
                 image: Arc::clone(image_data),
-                frames: RefCell::new(Some(FrameState::new(rx))),
+                frames: RefCell::new(Some(FrameState::new(rx, retain_frames))),
+                animation: RefCell::new(ImageAnimation::default()),
+                loops_done: RefCell::new(0),
             },
             Err(err) => {
                 log::error!("failed to start FrameDecoder: {err:#}");
@@ -542,6 +665,8 @@ impl DecodedImage {
                     current_frame: RefCell::new(current_frame),
                     image: Arc::clone(image_data),
                     frames: RefCell::new(None),
+                    animation: RefCell::new(ImageAnimation::default()),
+                    loops_done: RefCell::new(0),
                 }
             }
 
@@ -550,6 +675,8 @@ impl DecodedImage {
                 current_frame: RefCell::new(0),
                 image: Arc::clone(image_data),
                 frames: RefCell::new(None),
+                animation: RefCell::new(ImageAnimation::default()),
+                loops_done: RefCell::new(0),
             },
         }
     }
@@ -563,11 +690,15 @@ pub struct GlyphCache {
     pub fonts: Rc<FontConfiguration>,
     pub image_cache: LfuCache<[u8; 32], DecodedImage>,
     frame_cache: HashMap<[u8; 32], Sprite>,
+    /// Still images that have been scaled down to the size at which
+    /// they are displayed, keyed by the image hash and that size
+    scaled_image_cache: HashMap<([u8; 32], usize, usize), Sprite>,
     line_glyphs: HashMap<LineKey, Sprite>,
     pub block_glyphs: HashMap<SizedBlockKey, Sprite>,
-    pub cursor_glyphs: HashMap<(Option<CursorShape>, u8), Sprite>,
+    pub cursor_glyphs: HashMap<CursorSpriteKey, Sprite>,
     pub color: HashMap<(RgbColor, NotNan<f32>), Sprite>,
     min_frame_duration: Duration,
+    refresh_interval: Option<Duration>,
 }
 
 impl GlyphCache {
@@ -585,12 +716,14 @@ impl GlyphCache {
                 &fonts.config(),
             ),
             frame_cache: HashMap::new(),
+            scaled_image_cache: HashMap::new(),
             atlas,
             line_glyphs: HashMap::new(),
             block_glyphs: HashMap::new(),
             cursor_glyphs: HashMap::new(),
             color: HashMap::new(),
             min_frame_duration: Duration::from_millis(1000 / fonts.config().max_fps as u64),
+            refresh_interval: None,
         })
     }
 }
@@ -602,7 +735,13 @@ impl GlyphCache {
         size: usize,
     ) -> anyhow::Result<Self> {
         let surface = backend.allocate_texture_atlas(size)?;
-        let atlas = Atlas::new(&surface).expect("failed to create new texture atlas");
+        let page_backend = backend.clone();
+        let atlas = Atlas::with_pages(
+            &surface,
+            MAX_ATLAS_PAGES,
+            Box::new(move |size| page_backend.allocate_texture_atlas(size)),
+        )
+        .expect("failed to create new texture atlas");
 
         Ok(Self {
             fonts: Rc::clone(fonts),
@@ -614,12 +753,14 @@ impl GlyphCache {
                 &fonts.config(),
             ),
             frame_cache: HashMap::new(),
+            scaled_image_cache: HashMap::new(),
             atlas,
             line_glyphs: HashMap::new(),
             block_glyphs: HashMap::new(),
             cursor_glyphs: HashMap::new(),
             color: HashMap::new(),
             min_frame_duration: Duration::from_millis(1000 / fonts.config().max_fps as u64),
+            refresh_interval: None,
         })
     }
 }
@@ -691,6 +832,46 @@ impl GlyphCache {
         Ok(glyph)
     }
 
+    /// Animate images no faster than the display refreshes, rather
+    /// than using the global max_fps
+    pub fn set_refresh_interval(&mut self, interval: Option<Duration>) {
+        self.refresh_interval = interval;
+        self.min_frame_duration = match interval {
+            Some(interval) => interval,
+            None => Duration::from_millis(1000 / self.fonts.config().max_fps as u64),
+        };
+    }
+
+    pub fn refresh_interval(&self) -> Option<Duration> {
+        self.refresh_interval
+    }
+
+    /// Makes space in the atlas by evicting the least recently used
+    /// glyphs and images, and forgets the cache entries that referred
+    /// to them.  Returns false if nothing could be evicted.
+    /// The caller must discard anything holding sprites or texture
+    /// coordinates obtained before the eviction.
+    pub fn evict_least_recently_used(&mut self) -> bool {
+        if !self.atlas.evict_least_recently_used() {
+            return false;
+        }
+
+        self.glyph_cache.retain(|_, glyph| {
+            glyph
+                .texture
+                .as_ref()
+                .map_or(true, |sprite| !sprite.is_evicted())
+        });
+        self.frame_cache.retain(|_, sprite| !sprite.is_evicted());
+        self.scaled_image_cache
+            .retain(|_, sprite| !sprite.is_evicted());
+        self.line_glyphs.retain(|_, sprite| !sprite.is_evicted());
+        self.block_glyphs.retain(|_, sprite| !sprite.is_evicted());
+        self.cursor_glyphs.retain(|_, sprite| !sprite.is_evicted());
+        self.color.retain(|_, sprite| !sprite.is_evicted());
+        true
+    }
+
     pub fn config_changed(&mut self) {
         let config = self.fonts.config();
         self.image_cache.update_config(&config);
@@ -903,6 +1084,7 @@ impl GlyphCache {
         padding: Option<usize>,
         min_frame_duration: Duration,
         allow_image: AllowImage,
+        playback: ImagePlayback,
     ) -> anyhow::Result<(Sprite, Option<Instant>, LoadState)> {
         let mut handle = DecodedImageHandle {
             h: decoded.image.data(),
@@ -935,7 +1117,39 @@ impl GlyphCache {
                 let mut next = None;
                 let mut decoded_frame_start = decoded.frame_start.borrow_mut();
                 let mut decoded_current_frame = decoded.current_frame.borrow_mut();
-                if frames.len() > 1 {
+                let mut loops_done = decoded.loops_done.borrow_mut();
+
+                // Apply any changes made by the kitty animation control
+                // commands since we were last here
+                let animation = decoded.image.animation();
+                {
+                    let mut prior = decoded.animation.borrow_mut();
+                    if *prior != animation {
+                        if animation.current_frame != prior.current_frame {
+                            if let Some((_, frame)) = animation.current_frame {
+                                *decoded_current_frame = frame;
+                            }
+                        }
+                        *loops_done = 0;
+                        *decoded_frame_start = Instant::now();
+                        *prior = animation;
+                    }
+                }
+                if *decoded_current_frame >= frames.len() {
+                    *decoded_current_frame = frames.len() - 1;
+                }
+                handle.current_frame = *decoded_current_frame;
+
+                let finished = animation
+                    .max_loops
+                    .map(|max| *loops_done >= max)
+                    .unwrap_or(false);
+
+                if frames.len() > 1
+                    && !playback.is_paused()
+                    && animation.state != ImageAnimationState::Stopped
+                    && !finished
+                {
                     let now = Instant::now();
 
                     // We round up the frame duration to at least the minimum
@@ -949,24 +1163,53 @@ impl GlyphCache {
                     // frame, so we want to avoid that.
                     // <https://github.com/wezterm/wezterm/issues/3260>
                     let mut next_due = *decoded_frame_start
-                        + durations[*decoded_current_frame].max(min_frame_duration);
-                    if now >= next_due {
-                        // Advance to next frame
-                        *decoded_current_frame = *decoded_current_frame + 1;
-                        if *decoded_current_frame >= frames.len() {
-                            *decoded_current_frame = 0;
-                            // Skip potential 0-duration root frame
-                            if durations[0].as_millis() == 0 && frames.len() > 1 {
-                                *decoded_current_frame = *decoded_current_frame + 1;
+                        + playback
+                            .frame_duration(durations[*decoded_current_frame], min_frame_duration);
+                    let is_last = *decoded_current_frame + 1 >= frames.len();
+                    if now >= next_due && is_last {
+                        *loops_done = loops_done.saturating_add(1);
+                    }
+                    let hold_last = is_last
+                        && (animation.state == ImageAnimationState::Loading
+                            || animation
+                                .max_loops
+                                .map(|max| *loops_done >= max)
+                                .unwrap_or(false));
+
+                    if now >= next_due && hold_last {
+                        if animation.state == ImageAnimationState::Loading {
+                            // Stay on the last frame, but keep checking
+                            // for more frames to be transmitted
+                            *decoded_frame_start = now;
+                            next_due = now
+                                + playback.frame_duration(
+                                    durations[*decoded_current_frame],
+                                    min_frame_duration,
+                                );
+                            next.replace(next_due);
+                        }
+                    } else {
+                        if now >= next_due {
+                            // Advance to next frame
+                            *decoded_current_frame = *decoded_current_frame + 1;
+                            if *decoded_current_frame >= frames.len() {
+                                *decoded_current_frame = 0;
+                                // Skip potential 0-duration root frame
+                                if durations[0].as_millis() == 0 && frames.len() > 1 {
+                                    *decoded_current_frame = *decoded_current_frame + 1;
+                                }
                             }
+                            *decoded_frame_start = now;
+                            next_due = *decoded_frame_start
+                                + playback.frame_duration(
+                                    durations[*decoded_current_frame],
+                                    min_frame_duration,
+                                );
+                            handle.current_frame = *decoded_current_frame;
                         }
-                        *decoded_frame_start = now;
-                        next_due = *decoded_frame_start
-                            + durations[*decoded_current_frame].max(min_frame_duration);
-                        handle.current_frame = *decoded_current_frame;
-                    }
 
-                    next.replace(next_due);
+                        next.replace(next_due);
+                    }
                 }
 
                 let hash = hashes[*decoded_current_frame];
@@ -981,14 +1224,7 @@ impl GlyphCache {
 
                 frame_cache.insert(hash, sprite.clone());
 
-                return Ok((
-                    sprite,
-                    Some(
-                        *decoded_frame_start
-                            + durations[*decoded_current_frame].max(min_frame_duration),
-                    ),
-                    LoadState::Loaded,
-                ));
+                return Ok((sprite, next, LoadState::Loaded));
             }
             ImageDataType::EncodedLease(_) | ImageDataType::EncodedFile(_) => {
                 let mut frames = decoded.frames.borrow_mut();
@@ -1006,31 +1242,36 @@ impl GlyphCache {
                     frames.wait_for_first_frame(remain);
                 }
 
-                let now = Instant::now();
-                // We round up the frame duration to at least the minimum
-                // frame duration that wezterm can use when rendering.
-                // There's no point trying to deal with smaller intervals
-                // because we simply cannot render them without dropping
-                // frames.
-                // In addition, with a 1ms frame delay, there's a good chance
-                // that any given cell may switch to a different frame from
-                // its neighbor while we are rendering the entire terminal
-                // frame, so we want to avoid that.
-                // <https://github.com/wezterm/wezterm/issues/3260>
-                let mut next_due =
-                    *decoded_frame_start + frames.frame_duration().max(min_frame_duration);
-                if now >= next_due {
-                    // Advance to next frame
-                    if frames.load_next_frame() {
-                        *decoded_current_frame = *decoded_current_frame + 1;
-                        *decoded_frame_start = now;
-                        next_due =
-                            *decoded_frame_start + frames.frame_duration().max(min_frame_duration);
-                        handle.current_frame = *decoded_current_frame;
+                // Until the first frame has been decoded, keep checking
+                // for it, even when paused
+                if !playback.is_paused() || frames.load_state == LoadState::Loading {
+                    let now = Instant::now();
+                    // We round up the frame duration to at least the minimum
+                    // frame duration that wezterm can use when rendering.
+                    // There's no point trying to deal with smaller intervals
+                    // because we simply cannot render them without dropping
+                    // frames.
+                    // In addition, with a 1ms frame delay, there's a good chance
+                    // that any given cell may switch to a different frame from
+                    // its neighbor while we are rendering the entire terminal
+                    // frame, so we want to avoid that.
+                    // <https://github.com/wezterm/wezterm/issues/3260>
+                    let mut next_due = *decoded_frame_start
+                        + playback.frame_duration(frames.frame_duration(), min_frame_duration);
+                    if now >= next_due {
+                        // Advance to next frame
+                        if frames.load_next_frame() {
+                            *decoded_current_frame = *decoded_current_frame + 1;
+                            *decoded_frame_start = now;
+                            next_due = *decoded_frame_start
+                                + playback
+                                    .frame_duration(frames.frame_duration(), min_frame_duration);
+                            handle.current_frame = *decoded_current_frame;
+                        }
                     }
-                }
 
-                next.replace(next_due);
+                    next.replace(next_due);
+                }
 
                 let hash = frames.frame_hash();
 
@@ -1072,11 +1313,7 @@ impl GlyphCache {
 
                 frame_cache.insert(hash, sprite.clone());
 
-                Ok((
-                    sprite,
-                    Some(*decoded_frame_start + frames.frame_duration().max(min_frame_duration)),
-                    frames.load_state,
-                ))
+                Ok((sprite, next, frames.load_state))
             }
         }
     }
@@ -1086,6 +1323,7 @@ impl GlyphCache {
         image_data: &Arc<ImageData>,
         padding: Option<usize>,
         allow_image: AllowImage,
+        playback: ImagePlayback,
     ) -> anyhow::Result<(Sprite, Option<Instant>, LoadState)> {
         let hash = image_data.hash();
 
@@ -1097,6 +1335,7 @@ impl GlyphCache {
                 padding,
                 self.min_frame_duration,
                 allow_image,
+                playback,
             )
         } else {
             let decoded = DecodedImage::load(image_data);
@@ -1107,12 +1346,79 @@ impl GlyphCache {
                 padding,
                 self.min_frame_duration,
                 allow_image,
+                playback,
             )?;
             self.image_cache.put(hash, decoded);
             Ok(res)
         }
     }
 
+    /// Like cached_image, but for an image whose placement displays
+    /// it at `display_size` pixels.  Still images that are larger than
+    /// that, such as a big sixel shown in a small area, are scaled down
+    /// to it once, and the result is shared by all of the cells of
+    /// the placement, so that painting only has to sample the pixels
+    /// that are actually shown.  The scaled copy is keyed by its size,
+    /// so changing the font size or dpi produces another one.  Any
+    /// scaling up is left to the sampler.
+    pub fn cached_image_at_size(
+        &mut self,
+        image_data: &Arc<ImageData>,
+        padding: Option<usize>,
+        allow_image: AllowImage,
+        display_size: Option<(usize, usize)>,
+    ) -> anyhow::Result<(Sprite, Option<Instant>, LoadState)> {
+        let (display_width, display_height) = display_size.unwrap_or((0, 0));
+        let scaled = {
+            let data = image_data.data();
+            match &*data {
+                ImageDataType::Rgba8 {
+                    data,
+                    width,
+                    height,
+                    hash,
+                } if display_width > 0
+                    && display_height > 0
+                    && (display_width < *width as usize || display_height < *height as usize) =>
+                {
+                    let width = *width as usize;
+                    let height = *height as usize;
+                    let key = (*hash, display_width.min(width), display_height.min(height));
+                    if let Some(sprite) = self.scaled_image_cache.get(&key) {
+                        return Ok((sprite.clone(), None, LoadState::Loaded));
+                    }
+                    let image =
+                        Image::with_rgba32(width, height, width * 4, data).resize(key.1, key.2);
+                    Some((key, image))
+                }
+                _ => None,
+            }
+        };
+
+        let (key, image) = match scaled {
+            Some(scaled) => scaled,
+            None => {
+                return self.cached_image(
+                    image_data,
+                    padding,
+                    allow_image,
+                    ImagePlayback::default(),
+                )
+            }
+        };
+
+        let scale_down = match allow_image {
+            AllowImage::Scale(n) => Some(n),
+            _ => None,
+        };
+        let sprite = self
+            .atlas
+            .allocate_with_padding(&image, padding, scale_down)
+            .context("atlas.allocate_with_padding")?;
+        self.scaled_image_cache.insert(key, sprite.clone());
+        Ok((sprite, None, LoadState::Loaded))
+    }
+
     pub fn cached_color(&mut self, color: RgbColor, alpha: f32) -> anyhow::Result<Sprite> {
         let key = (color, NotNan::new(alpha).unwrap());
 
@@ -1176,100 +1482,60 @@ impl GlyphCache {
         };
 
         let draw_dotted = |buffer: &mut Image| {
-            for row in 0..metrics.underline_height {
-                let y = (cell_rect.origin.y + metrics.descender_row + row) as usize;
-                if y >= metrics.cell_size.height as usize {
-                    break;
-                }
-
-                let mut color = white;
-                let segment_length = (metrics.cell_size.width / 4) as usize;
-                let mut count = segment_length;
-                let range =
-                    buffer.horizontal_pixel_range_mut(0, metrics.cell_size.width as usize, y);
-                for c in range.iter_mut() {
-                    *c = color.as_srgba32();
-                    count -= 1;
-                    if count == 0 {
-                        color = if color == white { black } else { white };
-                        count = segment_length;
-                    }
-                }
+            // Square dots the size of the underline thickness, spaced so
+            // that a whole number of them fit in the cell and the pattern
+            // continues evenly into the adjacent cells
+            let dot = metrics.underline_height.max(1);
+            let num_dots = (metrics.cell_size.width / (2 * dot)).max(1);
+            let spacing = metrics.cell_size.width as f32 / num_dots as f32;
+            for i in 0..num_dots {
+                let x = (i as f32 * spacing + (spacing - dot as f32) / 2.).round() as isize;
+                buffer.clear_rect(
+                    Rect::new(Point::new(x, metrics.descender_row), Size::new(dot, dot)),
+                    white,
+                );
             }
         };
 
         let draw_dashed = |buffer: &mut Image| {
-            for row in 0..metrics.underline_height {
-                let y = (cell_rect.origin.y + metrics.descender_row + row) as usize;
-                if y >= metrics.cell_size.height as usize {
-                    break;
-                }
-                let mut color = white;
-                let third = (metrics.cell_size.width / 3) as usize + 1;
-                let mut count = third;
-                let range =
-                    buffer.horizontal_pixel_range_mut(0, metrics.cell_size.width as usize, y);
-                for c in range.iter_mut() {
-                    *c = color.as_srgba32();
-                    count -= 1;
-                    if count == 0 {
-                        color = if color == white { black } else { white };
-                        count = third;
-                    }
-                }
-            }
+            // A dash spans the boundary between adjacent cells,
+            // with a gap in the middle third of each cell
+            let third = (metrics.cell_size.width + 2) / 3;
+            let size = Size::new(third, metrics.underline_height);
+            buffer.clear_rect(Rect::new(Point::new(0, metrics.descender_row), size), white);
+            buffer.clear_rect(
+                Rect::new(
+                    Point::new(metrics.cell_size.width - third, metrics.descender_row),
+                    size,
+                ),
+                white,
+            );
         };
 
-        let draw_curly = |buffer: &mut Image| {
-            let max_y = metrics.cell_size.height as usize - 1;
-            let x_factor = (2. * std::f32::consts::PI) / metrics.cell_size.width as f32;
-
-            // Have the wave go from the descender to the bottom of the cell
-            let wave_height =
-                metrics.cell_size.height - (cell_rect.origin.y + metrics.descender_row);
-
-            let half_height = (wave_height as f32 / 4.).max(1.);
-            let y = ((cell_rect.origin.y + metrics.descender_row) as usize)
-                .saturating_sub(half_height as usize);
-
-            fn add(x: usize, y: usize, val: u8, max_y: usize, buffer: &mut Image) {
-                let y = y.min(max_y);
-                let pixel = buffer.pixel_mut(x, y);
-                let (current, _, _, _) = SrgbaPixel::with_srgba_u32(*pixel).as_rgba();
-                let value = current.saturating_add(val);
-                *pixel = SrgbaPixel::rgba(value, value, value, value).as_srgba32();
-            }
-
-            for x in 0..metrics.cell_size.width as usize {
-                let vertical = -half_height * (x as f32 * x_factor).sin() + half_height;
-                let v1 = vertical.floor();
-                let v2 = vertical.ceil();
-
-                for row in 0..metrics.underline_height as usize {
-                    let value = (255. * (vertical - v1).abs()) as u8;
-                    add(
-                        x,
-                        row.saturating_add(y).saturating_add(v1 as usize),
-                        255u8.saturating_sub(value),
-                        max_y,
-                        buffer,
-                    );
-                    add(
-                        x,
-                        row.saturating_add(y).saturating_add(v2 as usize),
-                        value,
-                        max_y,
-                        buffer,
-                    );
-                }
+        let draw_curly = |buffer: &mut Image, double: bool| {
+            let thickness = metrics.underline_height as f32;
+            let gap = metrics.double_underline_gap as f32;
+            let amplitude = metrics.curly_underline_height as f32 / 2.;
+            let cell_height = metrics.cell_size.height as f32;
+
+            // Start the wave at the underline position, but keep the
+            // whole of the stroke inside the cell
+            let lowest = cell_height - amplitude - thickness / 2.;
+            let center = (metrics.descender_row as f32 + amplitude).min(lowest);
+            stroke_wave(buffer, metrics, center, amplitude);
+            if double {
+                let highest = amplitude + thickness / 2.;
+                let upper = (center - 2. * amplitude - thickness - gap).max(highest);
+                stroke_wave(buffer, metrics, upper, amplitude);
             }
         };
 
         let draw_double = |buffer: &mut Image| {
-            let first_line = metrics
-                .descender_row
-                .min(metrics.descender_plus_two - 2 * metrics.underline_height);
-
+            let first_line = metrics.descender_row.min(
+                metrics.descender_plus_two
+                    - metrics.underline_height
+                    - metrics.double_underline_gap,
+            );
             for row in 0..metrics.underline_height {
                 buffer.draw_line(
                     Point::new(cell_rect.origin.x, cell_rect.origin.y + first_line + row),
@@ -1329,7 +1595,7 @@ impl GlyphCache {
         match key.underline {
             Underline::None => {}
             Underline::Single => draw_single(&mut buffer),
-            Underline::Curly => draw_curly(&mut buffer),
+            Underline::Curly => draw_curly(&mut buffer, key.double_curly),
             Underline::Dashed => draw_dashed(&mut buffer),
             Underline::Dotted => draw_dotted(&mut buffer),
             Underline::Double => draw_double(&mut buffer),
@@ -1345,25 +1611,30 @@ impl GlyphCache {
     /// Figure out what we're going to draw for the underline.
     /// If the current cell is part of the current URL highlight
     /// then we want to show the underline.
+    /// `hover_underline` is set when the mouse is hovering over a
+    /// hyperlink, and holds the underline to use for it if the text
+    /// isn't already underlined.
     pub fn cached_line_sprite(
         &mut self,
-        is_highlited_hyperlink: bool,
+        hover_underline: Option<Underline>,
         is_strike_through: bool,
         underline: Underline,
         overline: bool,
         metrics: &RenderMetrics,
     ) -> anyhow::Result<Sprite> {
-        let effective_underline = match (is_highlited_hyperlink, underline) {
-            (true, Underline::None) => Underline::Single,
-            (true, Underline::Single) => Underline::Double,
-            (true, _) => Underline::Single,
-            (false, u) => u,
+        let effective_underline = match (hover_underline, underline) {
+            (Some(hover), Underline::None) => hover,
+            (Some(_), Underline::Single) => Underline::Double,
+            (Some(_), Underline::Curly) => Underline::Curly,
+            (Some(_), _) => Underline::Single,
+            (None, u) => u,
         };
 
         let key = LineKey {
             strike_through: is_strike_through,
             overline,
             underline: effective_underline,
+            double_curly: hover_underline.is_some() && underline == Underline::Curly,
             size: metrics.into(),
         };
 
@@ -1374,3 +1645,48 @@ impl GlyphCache {
         self.line_sprite(key, metrics)
     }
 }
+
+/// Strokes one period of a sine wave across the width of the cell,
+/// centered vertically on `center`.  The wave starts and ends at its
+/// center line so that it joins up with the wave in adjacent cells.
+fn stroke_wave(buffer: &mut Image, metrics: &RenderMetrics, center: f32, amplitude: f32) {
+    let (width, height) = buffer.image_dimensions();
+    let mut pixmap = tiny_skia::PixmapMut::from_bytes(
+        buffer.pixel_data_slice_mut(),
+        width as u32,
+        height as u32,
+    )
+    .expect("make pixmap from existing bitmap");
+
+    let cell_width = metrics.cell_size.width as f32;
+    let x_factor = (2. * std::f32::consts::PI) / cell_width;
+
+    // Sample a little beyond the cell so that the stroke has no
+    // visible end caps where it meets the adjacent cells
+    let mut pb = tiny_skia::PathBuilder::new();
+    pb.move_to(-1., center + amplitude * x_factor.sin());
+    for x in 0..=metrics.cell_size.width + 1 {
+        let x = x as f32;
+        pb.line_to(x, center - amplitude * (x * x_factor).sin());
+    }
+    let path = match pb.finish() {
+        Some(path) => path,
+        None => return,
+    };
+
+    let mut paint = tiny_skia::Paint::default();
+    paint.set_color(tiny_skia::Color::WHITE);
+    paint.anti_alias = true;
+    paint.force_hq_pipeline = true;
+
+    let mut stroke = tiny_skia::Stroke::default();
+    stroke.width = metrics.underline_height as f32;
+
+    pixmap.stroke_path(
+        &path,
+        &paint,
+        &stroke,
+        tiny_skia::Transform::identity(),
+        None,
+    );
+}
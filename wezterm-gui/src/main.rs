@@ -57,6 +57,7 @@ mod unicode_names;
 mod uniforms;
 mod update;
 mod utilsprites;
+mod video;
 
 #[cfg(feature = "dhat-heap")]
 #[global_allocator]
@@ -131,6 +132,13 @@ enum SubCommand {
 
     #[command(name = "show-keys", about = "Show key assignments")]
     ShowKeys(ShowKeysCommand),
+
+    #[command(
+        name = "show-gpu-info",
+        visible_alias = "show-gpus",
+        about = "Show information about GPUs and zero-copy scanout"
+    )]
+    ShowGpuInfo(ShowGpuInfoCommand),
 }
 
 async fn async_run_ssh(opts: SshCommand) -> anyhow::Result<()> {
@@ -612,6 +620,9 @@ impl Publish {
                                         .as_deref()
                                         .unwrap_or(mux::DEFAULT_WORKSPACE)
                                 ).to_string(),
+                                // Let the running instance focus the window
+                                // in our place
+                                activation_token: std::env::var(ACTIVATION_TOKEN_ENV).ok(),
                             })
                             .await
                     }));
@@ -831,7 +842,7 @@ fn main() {
     let _profiler = dhat::Profiler::new_heap();
 
     config::designate_this_as_the_main_thread();
-    config::assign_error_callback(mux::connui::show_configuration_error_message);
+    config::assign_error_callback(termwindow::toast::show_configuration_error);
     notify_on_panic();
     if let Err(e) = run() {
         terminate_with_error(e);
@@ -858,6 +869,56 @@ fn run_show_keys(config: config::ConfigHandle, cmd: &ShowKeysCommand) -> anyhow:
     Ok(())
 }
 
+fn run_show_gpu_info(config: ConfigHandle) -> anyhow::Result<()> {
+    let preferences = &config.webgpu_preferred_adapter;
+    for gpu in crate::termwindow::webgpu::enumerate_gpus() {
+        match preferences.position(&gpu) {
+            Some(rank) => println!(
+                "{} (webgpu_preferred_adapter entry {}: {})",
+                gpu.to_string(),
+                rank + 1,
+                preferences.0[rank]
+            ),
+            None => println!("{}", gpu.to_string()),
+        }
+    }
+
+    // Report on the adapters that the windows of the running
+    // gui processes are actually using
+    for sock in wezterm_client::discovery::discover_gui_socks() {
+        let pid = match sock
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix("gui-sock-"))
+            .and_then(|pid| pid.parse::<u32>().ok())
+        {
+            Some(pid) => pid,
+            None => continue,
+        };
+        let selections = match crate::termwindow::gpuselection::load_selections(pid) {
+            Ok(selections) => selections,
+            Err(_) => continue,
+        };
+        println!();
+        println!("wezterm-gui pid {pid}:");
+        for (window_id, selection) in selections {
+            println!("  window {window_id}: {}", selection.adapter);
+            println!("    reason: {}", selection.reason);
+            for rejected in &selection.rejected {
+                println!("    tried first: {rejected}");
+            }
+        }
+    }
+
+    let conn = Connection::init()?;
+    println!();
+    match conn.scanout_info() {
+        Ok(info) => println!("{info}"),
+        Err(err) => println!("Scanout information is not available: {err:#}"),
+    }
+    Ok(())
+}
+
 pub fn run_ls_fonts(config: config::ConfigHandle, cmd: &LsFontsCommand) -> anyhow::Result<()> {
     use wezterm_font::parser::ParsedFont;
 
@@ -1274,5 +1335,6 @@ fn run() -> anyhow::Result<()> {
         ),
         SubCommand::LsFonts(cmd) => run_ls_fonts(config, &cmd),
         SubCommand::ShowKeys(cmd) => run_show_keys(config, &cmd),
+        SubCommand::ShowGpuInfo(_) => run_show_gpu_info(config),
     }
 }
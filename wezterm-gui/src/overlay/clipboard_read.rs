@@ -0,0 +1,127 @@
+use mux::termwiztermtab::TermWizTerminal;
+use termwiz::cell::AttributeChange;
+use termwiz::color::ColorAttribute;
+use termwiz::input::{InputEvent, KeyCode, KeyEvent, MouseButtons, MouseEvent};
+use termwiz::surface::{Change, CursorVisibility, Position};
+use termwiz::terminal::Terminal;
+
+/// The answer to an application's request to read the clipboard
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ClipboardReadChoice {
+    AllowOnce,
+    AlwaysAllow,
+    Deny,
+}
+
+const BUTTONS: &[(&str, ClipboardReadChoice)] = &[
+    (" Allow [O]nce ", ClipboardReadChoice::AllowOnce),
+    (" [A]lways Allow ", ClipboardReadChoice::AlwaysAllow),
+    (" [D]eny ", ClipboardReadChoice::Deny),
+];
+
+/// Asks the user whether the application in the pane may read
+/// the clipboard via OSC 52.  Dismissing the prompt denies the request.
+pub fn confirm_clipboard_read(mut term: TermWizTerminal) -> anyhow::Result<ClipboardReadChoice> {
+    term.set_raw_mode()?;
+
+    let size = term.get_screen_size()?;
+
+    // Render 80% wide, centered
+    let text_width = size.cols * 80 / 100;
+    let x_pos = size.cols * 10 / 100;
+
+    let wrapped = textwrap::fill(
+        "🔒 The application running in this pane wants to read \
+         the contents of the clipboard.",
+        text_width,
+    );
+    let message_rows = wrapped.split("\n").count();
+    let top_row = size.rows.saturating_sub(message_rows + 2) / 2;
+    let button_row = top_row + message_rows + 1;
+
+    // The starting column of each button, with a spacer between them
+    let mut button_x = vec![];
+    let mut x = x_pos;
+    for (label, _) in BUTTONS {
+        button_x.push(x);
+        x += label.len() + 4;
+    }
+
+    let render = |term: &mut TermWizTerminal, active: Option<usize>| -> termwiz::Result<()> {
+        let mut changes = vec![
+            Change::ClearScreen(ColorAttribute::Default),
+            Change::CursorVisibility(CursorVisibility::Hidden),
+        ];
+
+        for (y, row) in wrapped.split("\n").enumerate() {
+            changes.push(Change::CursorPosition {
+                x: Position::Absolute(x_pos),
+                y: Position::Absolute(top_row + y),
+            });
+            changes.push(Change::Text(row.trim_end().to_string()));
+        }
+
+        for (idx, (label, _)) in BUTTONS.iter().enumerate() {
+            changes.push(Change::CursorPosition {
+                x: Position::Absolute(button_x[idx]),
+                y: Position::Absolute(button_row),
+            });
+            if active == Some(idx) {
+                changes.push(AttributeChange::Reverse(true).into());
+            }
+            changes.push(Change::Text(label.to_string()));
+            if active == Some(idx) {
+                changes.push(AttributeChange::Reverse(false).into());
+            }
+        }
+
+        term.render(&changes)?;
+        term.flush()
+    };
+
+    let mut active = None;
+    render(&mut term, active)?;
+
+    while let Ok(Some(event)) = term.poll_input(None) {
+        match event {
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Char('o' | 'O'),
+                ..
+            }) => return Ok(ClipboardReadChoice::AllowOnce),
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Char('a' | 'A'),
+                ..
+            }) => return Ok(ClipboardReadChoice::AlwaysAllow),
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Char('d' | 'D' | 'n' | 'N') | KeyCode::Escape,
+                ..
+            }) => return Ok(ClipboardReadChoice::Deny),
+            InputEvent::Mouse(MouseEvent {
+                x,
+                y,
+                mouse_buttons,
+                ..
+            }) => {
+                let x = x as usize;
+                let y = y as usize;
+                active = BUTTONS.iter().enumerate().position(|(idx, (label, _))| {
+                    y == button_row && x >= button_x[idx] && x < button_x[idx] + label.len()
+                });
+                if mouse_buttons == MouseButtons::LEFT {
+                    if let Some(idx) = active {
+                        return Ok(BUTTONS[idx].1);
+                    }
+                }
+                if mouse_buttons != MouseButtons::NONE {
+                    // Treat any other mouse button as deny
+                    return Ok(ClipboardReadChoice::Deny);
+                }
+            }
+            _ => {}
+        }
+
+        render(&mut term, active)?;
+    }
+
+    Ok(ClipboardReadChoice::Deny)
+}
@@ -1,10 +1,17 @@
 use super::confirm;
+use crate::frontend::front_end;
+use crate::termwindow::TermWindowNotif;
 use crate::TermWindow;
+use config::ConfigHandle;
 use mux::pane::PaneId;
-use mux::tab::TabId;
-use mux::termwiztermtab::TermWizTerminal;
+use mux::tab::{Tab, TabId};
+use mux::termwiztermtab::{allocate, TermWizTerminal};
 use mux::window::WindowId;
 use mux::Mux;
+use std::sync::Arc;
+use wezterm_term::{TerminalConfiguration, TerminalSize};
+
+const CLOSE_WINDOW_MESSAGE: &str = "🛑 Really kill this window and all contained tabs and panes?";
 
 pub fn confirm_close_pane(
     pane_id: PaneId,
@@ -55,10 +62,7 @@ pub fn confirm_close_window(
     window: ::window::Window,
     tab_id: TabId,
 ) -> anyhow::Result<()> {
-    if confirm::run_confirmation(
-        "🛑 Really kill this window and all contained tabs and panes?",
-        &mut term,
-    )? {
+    if confirm::run_confirmation(CLOSE_WINDOW_MESSAGE, &mut term)? {
         promise::spawn::spawn_into_main_thread(async move {
             let mux = Mux::get();
             mux.kill_window(mux_window_id);
@@ -70,6 +74,58 @@ pub fn confirm_close_window(
     Ok(())
 }
 
+/// Like confirm_close_window, but rather than using an overlay, asks
+/// in a small window of its own that the windowing system presents as
+/// a modal dialog of `window`.
+/// Returns the mux window id of the dialog.
+pub fn confirm_close_window_in_dialog(
+    mux_window_id: WindowId,
+    window: ::window::Window,
+    config: ConfigHandle,
+) -> anyhow::Result<WindowId> {
+    let mux = Mux::get();
+    let size = TerminalSize {
+        rows: 6,
+        cols: 64,
+        ..TerminalSize::default()
+    };
+    let term_config: Arc<dyn TerminalConfiguration + Send + Sync> =
+        Arc::new(config::TermConfig::with_config(config));
+    let (mut term, pane) = allocate(size, term_config);
+
+    let tab = Arc::new(Tab::new(&size));
+    tab.assign_pane(&pane);
+    mux.add_tab_and_active_pane(&tab)?;
+
+    // The gui window for the dialog is created once the builder
+    // is dropped, so the parent must be known before then
+    let dialog = mux.new_empty_window(None, None);
+    let dialog_window_id = *dialog;
+    front_end().record_dialog_parent(dialog_window_id, window.clone());
+    mux.add_tab_to_window(&tab, dialog_window_id)?;
+    drop(dialog);
+
+    let confirmation = promise::spawn::spawn_into_new_thread(move || {
+        confirm::run_confirmation(CLOSE_WINDOW_MESSAGE, &mut term)
+    });
+    promise::spawn::spawn(async move {
+        // If the dialog was closed without answering, the
+        // confirmation fails, which we treat as a "no"
+        let confirmed = confirmation.await.unwrap_or(false);
+        let mux = Mux::get();
+        mux.kill_window(dialog_window_id);
+        if confirmed {
+            mux.kill_window(mux_window_id);
+        }
+        window.notify(TermWindowNotif::Apply(Box::new(|term_window| {
+            term_window.modal_dialog_closed();
+        })));
+    })
+    .detach();
+
+    Ok(dialog_window_id)
+}
+
 pub fn confirm_quit_program(
     mut term: TermWizTerminal,
     window: ::window::Window,
@@ -27,13 +27,14 @@ use unicode_segmentation::*;
 use url::Url;
 use wezterm_term::color::ColorPalette;
 use wezterm_term::{
-    unicode_column_width, Clipboard, KeyCode, KeyModifiers, Line, MouseEvent, SemanticType,
-    StableRowIndex, TerminalSize,
+    unicode_column_width, Clipboard, CommandRecord, KeyCode, KeyModifiers, Line, MouseEvent,
+    SemanticType, SemanticZone, StableRowIndex, TerminalSize,
 };
 use window::{KeyCode as WKeyCode, Modifiers, WindowOps};
 
 lazy_static::lazy_static! {
     static ref SAVED_PATTERN: Mutex<HashMap<TabId, Pattern>> = Mutex::new(HashMap::new());
+    static ref SAVED_OPTIONS: Mutex<HashMap<TabId, SearchOptions>> = Mutex::new(HashMap::new());
 }
 
 const SEARCH_CHUNK_SIZE: StableRowIndex = 1000;
@@ -57,6 +58,17 @@ struct Jump {
     target: char,
 }
 
+/// Options that refine how the search pattern is matched
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+struct SearchOptions {
+    /// Ignore case unless the pattern contains an uppercase character
+    smart_case: bool,
+    /// Only match whole words
+    whole_word: bool,
+    /// Only search the output zone around the cursor
+    output_zone: bool,
+}
+
 struct CopyRenderable {
     cursor: StableCursorPosition,
     delegate: Arc<dyn Pane>,
@@ -69,6 +81,7 @@ struct CopyRenderable {
     /// The text that the user entered
     pattern_type: PatternType,
     search_line: LineEditBuffer,
+    options: SearchOptions,
     /// The most recently queried set of matches
     results: Vec<SearchResult>,
     by_line: HashMap<StableRowIndex, Vec<MatchResult>>,
@@ -85,10 +98,18 @@ struct CopyRenderable {
     searching: Option<Searching>,
     pending_jump: Option<PendingJump>,
     last_jump: Option<Jump>,
+    /// In Block mode, true if the block extends to the end of each
+    /// line, which is the case after moving to the end of the line
+    /// until the cursor is next moved horizontally
+    block_to_end_of_line: bool,
 }
 
 struct Searching {
     remain: StableRowIndex,
+    /// The top of the range being searched
+    top: StableRowIndex,
+    /// The search of the current chunk; dropping this cancels it
+    _task: promise::spawn::Task<anyhow::Result<()>>,
 }
 
 #[derive(Debug)]
@@ -155,6 +176,11 @@ impl CopyOverlay {
             tab_id,
             pattern_type: PatternType::from(&pattern),
             search_line,
+            options: SAVED_OPTIONS
+                .lock()
+                .get(&tab_id)
+                .copied()
+                .unwrap_or_default(),
             editing_search: params.editing_search,
             result_pos: None,
             selection_mode: SelectionMode::Cell,
@@ -162,6 +188,7 @@ impl CopyOverlay {
             searching: None,
             pending_jump: None,
             last_jump: None,
+            block_to_end_of_line: false,
         };
 
         let search_row = render.compute_search_row();
@@ -202,6 +229,12 @@ impl CopyOverlay {
         render.dirty_results.add(search_row);
     }
 
+    /// Returns the rows on which the most recent search found matches
+    pub fn search_result_rows(&self) -> Vec<StableRowIndex> {
+        let render = self.render.lock();
+        render.results.iter().map(|result| result.start_y).collect()
+    }
+
     pub fn viewport_changed(&self, viewport: Option<StableRowIndex>) {
         let mut render = self.render.lock();
         if render.viewport != viewport {
@@ -315,41 +348,12 @@ impl CopyRenderable {
         self.dirty_results.add(bar_pos);
         self.last_result_seqno = self.delegate.get_current_seqno();
 
-        let pattern = self.get_pattern();
-        if !pattern.is_empty() {
-            let pane: Arc<dyn Pane> = self.delegate.clone();
-            let window = self.window.clone();
-            let dims = pane.get_dimensions();
-
-            let end = dims.scrollback_top + dims.scrollback_rows as StableRowIndex;
-            let range = end
-                .saturating_sub(SEARCH_CHUNK_SIZE)
-                .max(dims.scrollback_top)..end;
-
-            self.searching.replace(Searching {
-                remain: range.start - dims.scrollback_top,
-            });
-
-            promise::spawn::spawn(async move {
-                let limit = None;
-                log::trace!("Searching for {pattern:?} in {range:?}");
-                let results = pane.search(pattern.clone(), range.clone(), limit).await?;
-
-                let pane_id = pane.pane_id();
-                let mut results = Some(results);
-                window.notify(TermWindowNotif::Apply(Box::new(move |term_window| {
-                    let state = term_window.pane_state(pane_id);
-                    if let Some(overlay) = state.overlay.as_ref() {
-                        if let Some(copy_overlay) = overlay.pane.downcast_ref::<CopyOverlay>() {
-                            let mut r = copy_overlay.render.lock();
-                            r.processed_search_chunk(pattern, results.take().unwrap(), range);
-                        }
-                    }
-                })));
-
-                anyhow::Result::<()>::Ok(())
-            })
-            .detach();
+        if !self.get_pattern().is_empty() {
+            let pattern = self.search_pattern();
+            let bounds = self.search_bounds();
+            let end = bounds.end;
+            let range = end.saturating_sub(SEARCH_CHUNK_SIZE).max(bounds.start)..end;
+            self.search_chunk(pattern, range, bounds.start);
         } else {
             self.searching.take();
             self.clear_selection();
@@ -364,7 +368,7 @@ impl CopyRenderable {
         range: Range<StableRowIndex>,
     ) {
         self.window.invalidate();
-        if pattern != self.get_pattern() {
+        if pattern != self.search_pattern() {
             return;
         }
         let is_first = self.results.is_empty();
@@ -379,25 +383,37 @@ impl CopyRenderable {
             }
         }
 
-        let dims = self.delegate.get_dimensions();
-        if range.start == dims.scrollback_top {
+        let top = match &self.searching {
+            Some(searching) => searching.top,
+            None => self.delegate.get_dimensions().scrollback_top,
+        };
+        if range.start <= top {
             self.searching.take();
             return;
         }
 
         // Search next chunk
-        let pane: Arc<dyn Pane> = self.delegate.clone();
-        let window = self.window.clone();
         let end = range.start;
-        let range = end
-            .saturating_sub(SEARCH_CHUNK_SIZE)
-            .max(dims.scrollback_top)..end;
+        let range = end.saturating_sub(SEARCH_CHUNK_SIZE).max(top)..end;
+        self.search_chunk(pattern, range, top);
+    }
 
-        self.searching.replace(Searching {
-            remain: range.start - dims.scrollback_top,
-        });
+    /// Starts searching `range`, which is the next chunk of the range
+    /// that ends at `top`.  The pane performs the search away from the
+    /// GUI thread, and the results are passed to processed_search_chunk
+    /// as they arrive.  Any search that is already in progress, such as
+    /// one for a prior pattern, is cancelled.
+    fn search_chunk(
+        &mut self,
+        pattern: Pattern,
+        range: Range<StableRowIndex>,
+        top: StableRowIndex,
+    ) {
+        let pane: Arc<dyn Pane> = self.delegate.clone();
+        let window = self.window.clone();
+        let remain = range.start - top;
 
-        promise::spawn::spawn(async move {
+        let task = promise::spawn::spawn(async move {
             let limit = None;
             log::trace!("Searching for {pattern:?} in {range:?}");
             let results = pane.search(pattern.clone(), range.clone(), limit).await?;
@@ -415,8 +431,13 @@ impl CopyRenderable {
             })));
 
             anyhow::Result::<()>::Ok(())
-        })
-        .detach();
+        });
+
+        self.searching.replace(Searching {
+            remain,
+            top,
+            _task: task,
+        });
     }
 
     fn clear_selection(&mut self) {
@@ -491,6 +512,10 @@ impl CopyRenderable {
 
                     (range.start, range.end)
                 }
+                SelectionMode::Block if self.block_to_end_of_line => {
+                    let end = SelectionCoordinate::x_y(usize::max_value(), self.cursor.y);
+                    (sel_start, end)
+                }
                 _ => {
                     let start = SelectionCoordinate {
                         x: sel_start.x,
@@ -649,6 +674,123 @@ impl CopyRenderable {
         }
     }
 
+    /// Returns the pattern to search for: the pattern that the user
+    /// entered, adjusted according to the search options
+    fn search_pattern(&self) -> Pattern {
+        let pattern = self.get_pattern();
+        let SearchOptions {
+            smart_case,
+            whole_word,
+            ..
+        } = self.options;
+        if !smart_case && !whole_word {
+            return pattern;
+        }
+
+        let ignore_case = if smart_case {
+            !pattern.chars().any(char::is_uppercase)
+        } else {
+            matches!(pattern, Pattern::CaseInSensitiveString(_))
+        };
+
+        match pattern {
+            Pattern::CaseSensitiveString(s) | Pattern::CaseInSensitiveString(s) if !whole_word => {
+                if ignore_case {
+                    Pattern::CaseInSensitiveString(s)
+                } else {
+                    Pattern::CaseSensitiveString(s)
+                }
+            }
+            pattern => {
+                let mut re = match pattern {
+                    Pattern::Regex(re) => re,
+                    Pattern::CaseSensitiveString(s) | Pattern::CaseInSensitiveString(s) => {
+                        regex::escape(&s)
+                    }
+                };
+                if whole_word {
+                    re = format!(r"\b(?:{re})\b");
+                }
+                if ignore_case {
+                    re = format!("(?i){re}");
+                }
+                Pattern::Regex(re)
+            }
+        }
+    }
+
+    /// Returns the range of rows to search: the whole scrollback, or
+    /// the output zone around the cursor if the search is restricted to it
+    fn search_bounds(&self) -> Range<StableRowIndex> {
+        let dims = self.delegate.get_dimensions();
+        let scrollback =
+            dims.scrollback_top..dims.scrollback_top + dims.scrollback_rows as StableRowIndex;
+        if self.options.output_zone {
+            if let Some(zone) = self.output_zone() {
+                return zone.start_y.max(scrollback.start)..(zone.end_y + 1).min(scrollback.end);
+            }
+        }
+        scrollback
+    }
+
+    /// Returns the output zone that contains the cursor, or else
+    /// the closest output zone above it
+    fn output_zone(&self) -> Option<SemanticZone> {
+        self.delegate
+            .get_semantic_zones()
+            .unwrap_or_else(|_| vec![])
+            .into_iter()
+            .rev()
+            .find(|zone| {
+                zone.semantic_type == SemanticType::Output && zone.start_y <= self.cursor.y
+            })
+    }
+
+    /// Returns the text of the search bar
+    fn search_status(&self, pattern: &Pattern) -> String {
+        let mut modes = vec![];
+        match pattern {
+            Pattern::CaseSensitiveString(_) | Pattern::CaseInSensitiveString(_)
+                if self.options.smart_case =>
+            {
+                modes.push("smart-case")
+            }
+            Pattern::CaseSensitiveString(_) => modes.push("case-sensitive"),
+            Pattern::CaseInSensitiveString(_) => modes.push("ignore-case"),
+            Pattern::Regex(_) => {
+                modes.push("regex");
+                if self.options.smart_case {
+                    modes.push("smart-case");
+                }
+            }
+        }
+        if self.options.whole_word {
+            modes.push("whole-word");
+        }
+        if self.options.output_zone {
+            modes.push("in output zone");
+        }
+
+        let remain = match &self.searching {
+            Some(Searching { remain, .. }) => format!(" searching {remain} lines"),
+            None => String::new(),
+        };
+
+        format!(
+            "Search: {} ({} of {} matches. {}{remain})",
+            **pattern,
+            self.result_pos.map(|x| x + 1).unwrap_or(0),
+            self.results.len(),
+            modes.join(", ")
+        )
+    }
+
+    fn toggle_search_option(&mut self, toggle: impl FnOnce(&mut SearchOptions)) {
+        toggle(&mut self.options);
+        SAVED_OPTIONS.lock().insert(self.tab_id, self.options);
+        self.schedule_update_search();
+    }
+
     fn clear_pattern(&mut self) {
         self.search_line.clear();
         self.update_search();
@@ -1248,6 +1390,23 @@ impl Pane for CopyOverlay {
         }
         match assignment {
             KeyAssignment::CopyMode(assignment) => {
+                // Moving to the end of the line extends a block to the end
+                // of every line that it spans, until the cursor is moved
+                // horizontally or the selection mode changes
+                render.block_to_end_of_line = match assignment {
+                    MoveToEndOfLineContent => render.selection_mode == SelectionMode::Block,
+                    MoveUp
+                    | MoveDown
+                    | MoveByPage(_)
+                    | PageUp
+                    | PageDown
+                    | MoveToViewportBottom
+                    | MoveToViewportTop
+                    | MoveToViewportMiddle
+                    | MoveToScrollbackTop
+                    | MoveToScrollbackBottom => render.block_to_end_of_line,
+                    _ => false,
+                };
                 match assignment {
                     MoveToViewportBottom => render.move_to_viewport_bottom(),
                     MoveToViewportTop => render.move_to_viewport_top(),
@@ -1276,6 +1435,15 @@ impl Pane for CopyOverlay {
                     PriorMatchPage => render.prior_match_page(),
                     NextMatchPage => render.next_match_page(),
                     CycleMatchType => render.cycle_match_type(),
+                    ToggleSmartCase => render.toggle_search_option(|options| {
+                        options.smart_case = !options.smart_case;
+                    }),
+                    ToggleWholeWord => render.toggle_search_option(|options| {
+                        options.whole_word = !options.whole_word;
+                    }),
+                    ToggleSearchOutputZone => render.toggle_search_option(|options| {
+                        options.output_zone = !options.output_zone;
+                    }),
                     ClearPattern => render.clear_pattern(),
                     EditPattern => render.edit_pattern(),
                     AcceptPattern => render.accept_pattern(),
@@ -1430,28 +1598,9 @@ impl Pane for CopyOverlay {
                         // Replace with search UI
                         let rev = CellAttributes::default().set_reverse(true).clone();
                         line.fill_range(0..self.dims.cols, &Cell::new(' ', rev.clone()), SEQ_ZERO);
-                        let mode = &match pattern {
-                            Pattern::CaseSensitiveString(_) => "case-sensitive",
-                            Pattern::CaseInSensitiveString(_) => "ignore-case",
-                            Pattern::Regex(_) => "regex",
-                        };
-
-                        let remain = match &self.renderer.searching {
-                            Some(Searching { remain, .. }) => {
-                                format!(" searching {remain} lines")
-                            }
-                            None => String::new(),
-                        };
-
                         line.overlay_text_with_attribute(
                             0,
-                            &format!(
-                                "Search: {} ({}/{} matches. {}{remain})",
-                                *pattern,
-                                self.renderer.result_pos.map(|x| x + 1).unwrap_or(0),
-                                self.renderer.results.len(),
-                                mode
-                            ),
+                            &self.renderer.search_status(&pattern),
                             rev,
                             SEQ_ZERO,
                         );
@@ -1531,20 +1680,9 @@ impl Pane for CopyOverlay {
                 // Replace with search UI
                 let rev = CellAttributes::default().set_reverse(true).clone();
                 line.fill_range(0..dims.cols, &Cell::new(' ', rev.clone()), SEQ_ZERO);
-                let mode = &match pattern {
-                    Pattern::CaseSensitiveString(_) => "case-sensitive",
-                    Pattern::CaseInSensitiveString(_) => "ignore-case",
-                    Pattern::Regex(_) => "regex",
-                };
                 line.overlay_text_with_attribute(
                     0,
-                    &format!(
-                        "Search: {} ({}/{} matches. {})",
-                        *pattern,
-                        renderer.result_pos.map(|x| x + 1).unwrap_or(0),
-                        renderer.results.len(),
-                        mode
-                    ),
+                    &renderer.search_status(&pattern),
                     rev,
                     SEQ_ZERO,
                 );
@@ -1594,6 +1732,18 @@ impl Pane for CopyOverlay {
     fn get_dimensions(&self) -> RenderableDimensions {
         self.delegate.get_dimensions()
     }
+
+    fn get_semantic_zones(&self) -> anyhow::Result<Vec<SemanticZone>> {
+        self.delegate.get_semantic_zones()
+    }
+
+    fn get_failed_command_rows(&self) -> Vec<StableRowIndex> {
+        self.delegate.get_failed_command_rows()
+    }
+
+    fn get_command_history(&self) -> Vec<CommandRecord> {
+        self.delegate.get_command_history()
+    }
 }
 
 pub struct SearchOverlayPatternWriter {
@@ -1677,6 +1827,21 @@ pub fn search_key_table() -> KeyTable {
             Modifiers::CTRL,
             KeyAssignment::CopyMode(CopyModeAssignment::ClearPattern),
         ),
+        (
+            WKeyCode::Char('c'),
+            Modifiers::ALT,
+            KeyAssignment::CopyMode(CopyModeAssignment::ToggleSmartCase),
+        ),
+        (
+            WKeyCode::Char('w'),
+            Modifiers::ALT,
+            KeyAssignment::CopyMode(CopyModeAssignment::ToggleWholeWord),
+        ),
+        (
+            WKeyCode::Char('o'),
+            Modifiers::ALT,
+            KeyAssignment::CopyMode(CopyModeAssignment::ToggleSearchOutputZone),
+        ),
     ] {
         table.insert((key, mods), KeyTableEntry { action });
     }
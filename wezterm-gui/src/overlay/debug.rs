@@ -133,6 +133,7 @@ pub fn show_debug_overlay(
     gui_win: GuiWin,
     opengl_info: String,
     connection_info: String,
+    scanout_info: Option<String>,
 ) -> anyhow::Result<()> {
     term.no_grab_mouse_in_raw_mode();
 
@@ -195,13 +196,23 @@ pub fn show_debug_overlay(
 
     let version = config::wezterm_version();
     let triple = config::wezterm_target_triple();
+    let input_latency = match crate::stats::latency_p50_p95("window.input_latency") {
+        Some((p50, p95)) => format!("p50 {p50:.2?}, p95 {p95:.2?}"),
+        None => "Not measured".to_string(),
+    };
+    let scanout_info = match scanout_info {
+        Some(info) => format!("{}\r\n", info.replace("\n", "\r\n")),
+        None => String::new(),
+    };
 
     term.render(&[Change::Text(format!(
         "Debug Overlay\r\n\
          wezterm version: {version} {triple}\r\n\
          Window Environment: {connection_info}\r\n\
          Lua Version: {lua_version}\r\n\
+         Input Latency: {input_latency}\r\n\
          {opengl_info}\r\n\
+         {scanout_info}\
          Enter lua statements or expressions and hit Enter.\r\n\
          Press ESC or CTRL-D to exit\r\n",
     ))])?;
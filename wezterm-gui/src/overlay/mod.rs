@@ -6,17 +6,21 @@ use std::pin::Pin;
 use std::sync::Arc;
 use wezterm_term::{TerminalConfiguration, TerminalSize};
 
+pub mod clipboard_read;
 pub mod confirm;
 pub mod confirm_close_pane;
 pub mod copy;
 pub mod debug;
 pub mod launcher;
+pub mod pager;
+pub mod paste;
 pub mod prompt;
 pub mod quickselect;
 pub mod selector;
 
 pub use confirm_close_pane::{
-    confirm_close_pane, confirm_close_tab, confirm_close_window, confirm_quit_program,
+    confirm_close_pane, confirm_close_tab, confirm_close_window, confirm_close_window_in_dialog,
+    confirm_quit_program,
 };
 pub use copy::{CopyModeParams, CopyOverlay};
 pub use debug::show_debug_overlay;
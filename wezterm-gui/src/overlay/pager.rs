@@ -0,0 +1,124 @@
+use mux::termwiztermtab::TermWizTerminal;
+use termwiz::cell::{AttributeChange, CellAttributes};
+use termwiz::color::ColorAttribute;
+use termwiz::input::{InputEvent, KeyCode, KeyEvent, MouseButtons, MouseEvent};
+use termwiz::surface::{Change, CursorVisibility, Line, Position};
+use termwiz::terminal::Terminal;
+
+/// Displays `lines` in a scrollable view, with a status line at the
+/// bottom showing `title`.  Returns when the user presses `q` or Escape.
+pub fn show_pager_overlay(
+    mut term: TermWizTerminal,
+    title: String,
+    lines: Vec<Line>,
+) -> anyhow::Result<()> {
+    term.set_raw_mode()?;
+
+    let mut top = 0;
+
+    loop {
+        let size = term.get_screen_size()?;
+        // Reserve the last row for the status line
+        let page = size.rows.saturating_sub(1).max(1);
+        let max_top = lines.len().saturating_sub(page);
+        top = top.min(max_top);
+
+        let mut changes = vec![
+            Change::ClearScreen(ColorAttribute::Default),
+            Change::CursorVisibility(CursorVisibility::Hidden),
+        ];
+
+        for (y, line) in lines.iter().skip(top).take(page).enumerate() {
+            changes.push(Change::CursorPosition {
+                x: Position::Absolute(0),
+                y: Position::Absolute(y),
+            });
+            changes.append(&mut line.changes(&CellAttributes::default()));
+            changes.push(Change::AllAttributes(CellAttributes::default()));
+        }
+
+        let status = if lines.is_empty() {
+            format!("{} (empty) q: close", title)
+        } else {
+            format!(
+                "{} lines {}-{} of {} q: close",
+                title,
+                top + 1,
+                (top + page).min(lines.len()),
+                lines.len()
+            )
+        };
+        changes.push(Change::CursorPosition {
+            x: Position::Absolute(0),
+            y: Position::Absolute(page),
+        });
+        changes.push(AttributeChange::Reverse(true).into());
+        changes.push(Change::Text(format!(
+            "{:width$}",
+            status,
+            width = size.cols
+        )));
+        changes.push(AttributeChange::Reverse(false).into());
+
+        term.render(&changes)?;
+        term.flush()?;
+
+        let event = match term.poll_input(None) {
+            Ok(Some(event)) => event,
+            _ => return Ok(()),
+        };
+
+        match event {
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Char('q') | KeyCode::Escape,
+                ..
+            }) => return Ok(()),
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Char('j') | KeyCode::DownArrow | KeyCode::Enter,
+                ..
+            }) => {
+                top = (top + 1).min(max_top);
+            }
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Char('k') | KeyCode::UpArrow,
+                ..
+            }) => {
+                top = top.saturating_sub(1);
+            }
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Char(' ' | 'f') | KeyCode::PageDown,
+                ..
+            }) => {
+                top = (top + page).min(max_top);
+            }
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Char('b') | KeyCode::PageUp,
+                ..
+            }) => {
+                top = top.saturating_sub(page);
+            }
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Char('g') | KeyCode::Home,
+                ..
+            }) => {
+                top = 0;
+            }
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Char('G') | KeyCode::End,
+                ..
+            }) => {
+                top = max_top;
+            }
+            InputEvent::Mouse(MouseEvent { mouse_buttons, .. }) => {
+                if mouse_buttons.contains(MouseButtons::VERT_WHEEL) {
+                    if mouse_buttons.contains(MouseButtons::WHEEL_POSITIVE) {
+                        top = top.saturating_sub(3);
+                    } else {
+                        top = (top + 3).min(max_top);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
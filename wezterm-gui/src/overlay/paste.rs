@@ -0,0 +1,229 @@
+use mux::termwiztermtab::TermWizTerminal;
+use termwiz::cell::AttributeChange;
+use termwiz::color::ColorAttribute;
+use termwiz::input::{InputEvent, KeyCode, KeyEvent, MouseButtons, MouseEvent};
+use termwiz::lineedit::*;
+use termwiz::surface::{Change, CursorVisibility, Position};
+use termwiz::terminal::Terminal;
+
+/// Represents a line break while the text is being edited,
+/// as the line editor operates on a single line
+const LINE_BREAK: char = '\u{2424}';
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum PasteChoice {
+    Paste,
+    Edit,
+    Cancel,
+}
+
+const BUTTONS: &[(&str, PasteChoice)] = &[
+    (" [P]aste ", PasteChoice::Paste),
+    (" [E]dit ", PasteChoice::Edit),
+    (" [C]ancel ", PasteChoice::Cancel),
+];
+
+/// Shows a preview of `text` and asks the user whether it should be
+/// pasted, giving them the opportunity to edit it first.
+/// Returns the text to paste, or None if the paste was cancelled.
+pub fn confirm_paste(mut term: TermWizTerminal, text: String) -> anyhow::Result<Option<String>> {
+    let mut text = text;
+    loop {
+        match choose(&mut term, &text)? {
+            PasteChoice::Paste => return Ok(Some(text)),
+            PasteChoice::Cancel => return Ok(None),
+            PasteChoice::Edit => {
+                if let Some(edited) = edit(&mut term, &text)? {
+                    text = edited;
+                }
+            }
+        }
+    }
+}
+
+/// Replaces control characters with their visible representation
+/// from the Control Pictures block, so that the preview shows them
+fn visible_line(line: &str, width: usize) -> String {
+    line.chars()
+        .map(|c| {
+            if c < ' ' {
+                char::from_u32(0x2400 + c as u32).unwrap_or(c)
+            } else {
+                c
+            }
+        })
+        .take(width)
+        .collect()
+}
+
+fn choose(term: &mut TermWizTerminal, text: &str) -> anyhow::Result<PasteChoice> {
+    term.set_raw_mode()?;
+
+    let size = term.get_screen_size()?;
+    let lines: Vec<&str> = text.lines().collect();
+
+    // Leave room for the heading and the buttons, each
+    // separated from the preview by a blank row
+    let preview_rows = size.rows.saturating_sub(4).max(1);
+    let button_row = (preview_rows + 3).min(size.rows.saturating_sub(1));
+
+    let mut button_x = vec![];
+    let mut x = 0;
+    for (label, _) in BUTTONS {
+        button_x.push(x);
+        x += label.len() + 4;
+    }
+
+    let render = |term: &mut TermWizTerminal, active: Option<usize>| -> termwiz::Result<()> {
+        let mut changes = vec![
+            Change::ClearScreen(ColorAttribute::Default),
+            Change::CursorVisibility(CursorVisibility::Hidden),
+            Change::Text(format!(
+                "Paste {} line{} ({} bytes) into the shell prompt?",
+                lines.len(),
+                if lines.len() == 1 { "" } else { "s" },
+                text.len()
+            )),
+        ];
+
+        let overflow = lines.len() > preview_rows;
+        let shown = if overflow {
+            preview_rows - 1
+        } else {
+            lines.len()
+        };
+        for (y, line) in lines.iter().take(shown).enumerate() {
+            changes.push(Change::CursorPosition {
+                x: Position::Absolute(0),
+                y: Position::Absolute(2 + y),
+            });
+            changes.push(AttributeChange::Reverse(true).into());
+            changes.push(Change::Text(visible_line(line, size.cols)));
+            changes.push(AttributeChange::Reverse(false).into());
+        }
+        if overflow {
+            changes.push(Change::CursorPosition {
+                x: Position::Absolute(0),
+                y: Position::Absolute(2 + shown),
+            });
+            changes.push(Change::Text(format!(
+                "... and {} more lines",
+                lines.len() - shown
+            )));
+        }
+
+        for (idx, (label, _)) in BUTTONS.iter().enumerate() {
+            changes.push(Change::CursorPosition {
+                x: Position::Absolute(button_x[idx]),
+                y: Position::Absolute(button_row),
+            });
+            if active == Some(idx) {
+                changes.push(AttributeChange::Reverse(true).into());
+            }
+            changes.push(Change::Text(label.to_string()));
+            if active == Some(idx) {
+                changes.push(AttributeChange::Reverse(false).into());
+            }
+        }
+
+        term.render(&changes)?;
+        term.flush()
+    };
+
+    let mut active = None;
+    render(term, active)?;
+
+    while let Ok(Some(event)) = term.poll_input(None) {
+        match event {
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Char('p' | 'P' | 'y' | 'Y') | KeyCode::Enter,
+                ..
+            }) => return Ok(PasteChoice::Paste),
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Char('e' | 'E'),
+                ..
+            }) => return Ok(PasteChoice::Edit),
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Char('c' | 'C' | 'n' | 'N') | KeyCode::Escape,
+                ..
+            }) => return Ok(PasteChoice::Cancel),
+            InputEvent::Mouse(MouseEvent {
+                x,
+                y,
+                mouse_buttons,
+                ..
+            }) => {
+                let x = x as usize;
+                let y = y as usize;
+                active = BUTTONS.iter().enumerate().position(|(idx, (label, _))| {
+                    y == button_row && x >= button_x[idx] && x < button_x[idx] + label.len()
+                });
+                if mouse_buttons == MouseButtons::LEFT {
+                    if let Some(idx) = active {
+                        return Ok(BUTTONS[idx].1);
+                    }
+                }
+                if mouse_buttons != MouseButtons::NONE {
+                    // Treat any other mouse button as cancel
+                    return Ok(PasteChoice::Cancel);
+                }
+            }
+            _ => {}
+        }
+
+        render(term, active)?;
+    }
+
+    Ok(PasteChoice::Cancel)
+}
+
+struct EditHost {
+    history: BasicHistory,
+}
+
+impl LineEditorHost for EditHost {
+    fn history(&mut self) -> &mut dyn History {
+        &mut self.history
+    }
+
+    fn resolve_action(
+        &mut self,
+        event: &InputEvent,
+        _editor: &mut LineEditor<'_>,
+    ) -> Option<Action> {
+        match event {
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Escape,
+                ..
+            }) => Some(Action::Cancel),
+            _ => None,
+        }
+    }
+}
+
+/// Allows the user to edit `text`, returning the edited text,
+/// or None if the edit was cancelled
+fn edit(term: &mut TermWizTerminal, text: &str) -> anyhow::Result<Option<String>> {
+    term.render(&[
+        Change::ClearScreen(ColorAttribute::Default),
+        Change::CursorVisibility(CursorVisibility::Visible),
+        Change::Text(format!(
+            "Edit the text to paste; {} marks a line break.\r\n\
+             Enter: accept the changes, Escape: discard them\r\n\r\n",
+            LINE_BREAK
+        )),
+    ])?;
+
+    let initial = text
+        .replace("\r\n", "\n")
+        .replace(|c| c == '\r' || c == '\n', &LINE_BREAK.to_string());
+
+    let mut host = EditHost {
+        history: BasicHistory::default(),
+    };
+    let mut editor = LineEditor::new(term);
+    editor.set_prompt("> ");
+    let line = editor.read_line_with_optional_initial_value(&mut host, Some(&initial))?;
+
+    Ok(line.map(|line| line.replace(LINE_BREAK, "\n")))
+}
@@ -1,6 +1,9 @@
 use crate::selection::{SelectionCoordinate, SelectionRange};
 use crate::termwindow::{TermWindow, TermWindowNotif};
-use config::keyassignment::{ClipboardCopyDestination, QuickSelectArguments, ScrollbackEraseMode};
+use config::keyassignment::{
+    ClipboardCopyDestination, QuickSelectAction, QuickSelectArguments, QuickSelectPattern,
+    ScrollbackEraseMode,
+};
 use config::ConfigHandle;
 use mux::domain::DomainId;
 use mux::pane::{
@@ -202,17 +205,71 @@ pub struct QuickSelectOverlay {
 struct MatchResult {
     range: Range<usize>,
     label: String,
+    /// The index of the PatternGroup that produced this match
+    group: usize,
+}
+
+/// A regex that is searched for separately from the others.
+/// Patterns that don't specify their own action or label colors
+/// are combined into a single group; each pattern that does is
+/// placed into a group of its own so that its matches can be
+/// told apart from the rest.
+#[derive(Debug, Clone)]
+struct PatternGroup {
+    pattern: Pattern,
+    overrides: Option<QuickSelectPattern>,
+}
+
+fn compute_pattern_groups(patterns: &[QuickSelectPattern]) -> Vec<PatternGroup> {
+    let mut groups = vec![];
+    let mut combined: Vec<&str> = vec![];
+
+    fn flush(combined: &mut Vec<&str>, groups: &mut Vec<PatternGroup>) {
+        if !combined.is_empty() {
+            groups.push(PatternGroup {
+                pattern: Pattern::Regex(format!("(?m)({})", combined.join("|"))),
+                overrides: None,
+            });
+            combined.clear();
+        }
+    }
+
+    for p in patterns {
+        if p.has_overrides() {
+            flush(&mut combined, &mut groups);
+            groups.push(PatternGroup {
+                pattern: Pattern::Regex(format!("(?m)({})", p.pattern)),
+                overrides: Some(p.clone()),
+            });
+        } else {
+            combined.push(&p.pattern);
+        }
+    }
+    flush(&mut combined, &mut groups);
+
+    groups
+}
+
+fn results_overlap(a: &SearchResult, b: &SearchResult) -> bool {
+    (a.start_y, a.start_x) < (b.end_y, b.end_x) && (b.start_y, b.start_x) < (a.end_y, a.end_x)
 }
 
 struct QuickSelectRenderable {
     delegate: Arc<dyn Pane>,
-    /// The text that the user entered
-    pattern: Pattern,
+    /// The patterns to search for, in order of precedence
+    patterns: Vec<PatternGroup>,
     /// The most recently queried set of matches
     results: Vec<SearchResult>,
+    /// The index of the PatternGroup that produced each of the results
+    result_groups: Vec<usize>,
     by_line: HashMap<StableRowIndex, Vec<MatchResult>>,
     by_label: HashMap<String, usize>,
     selection: String,
+    /// When true, typing a label tags or untags its match rather
+    /// than acting on it
+    tagging: bool,
+    /// The labels of the tagged matches, in the order that they were tagged
+    tagged: Vec<String>,
 
     viewport: Option<StableRowIndex>,
     last_bar_pos: Option<StableRowIndex>,
@@ -240,45 +297,27 @@ impl QuickSelectOverlay {
 
         let config = term_window.config.clone();
 
-        let mut pattern = "(?m)(".to_string();
-        let mut have_patterns = false;
-        if !args.patterns.is_empty() {
-            for p in &args.patterns {
-                if have_patterns {
-                    pattern.push('|');
-                }
-                pattern.push_str(p);
-                have_patterns = true;
-            }
+        let patterns: Vec<QuickSelectPattern> = if !args.patterns.is_empty() {
+            args.patterns.clone()
         } else {
             // User-provided patterns take precedence over built-ins
-            for p in &config.quick_select_patterns {
-                if have_patterns {
-                    pattern.push('|');
-                }
-                pattern.push_str(p);
-                have_patterns = true;
-            }
+            let mut patterns = config.quick_select_patterns.clone();
             if !config.disable_default_quick_select_patterns {
-                for p in &PATTERNS {
-                    if have_patterns {
-                        pattern.push('|');
-                    }
-                    pattern.push_str(p);
-                    have_patterns = true;
-                }
+                patterns.extend(PATTERNS.iter().map(|p| QuickSelectPattern::from(*p)));
             }
-        }
-        pattern.push(')');
-
-        let pattern = Pattern::Regex(pattern);
+            patterns
+        };
+        let patterns = compute_pattern_groups(&patterns);
 
         let window = term_window.window.clone().unwrap();
         let mut renderer = QuickSelectRenderable {
             delegate: Arc::clone(pane),
-            pattern,
+            patterns,
             selection: "".to_string(),
+            tagging: false,
+            tagged: vec![],
             results: vec![],
+            result_groups: vec![],
             by_line: HashMap::new(),
             by_label: HashMap::new(),
             dirty_results: RangeSet::default(),
@@ -350,11 +389,25 @@ impl Pane for QuickSelectOverlay {
         let mods = mods.remove_positional_mods();
         match (key, mods) {
             (KeyCode::Escape, KeyModifiers::NONE) => self.renderer.lock().close(),
+            (KeyCode::Char(' '), KeyModifiers::NONE) => {
+                // Space toggles between acting on a match and tagging matches
+                let mut r = self.renderer.lock();
+                r.tagging = !r.tagging;
+                r.selection.clear();
+                let bar_pos = r.compute_search_row();
+                r.dirty_results.add(bar_pos);
+            }
             (KeyCode::UpArrow, KeyModifiers::NONE)
             | (KeyCode::Enter, KeyModifiers::NONE)
             | (KeyCode::Char('p'), KeyModifiers::CTRL) => {
-                // Move to prior match
                 let mut r = self.renderer.lock();
+                if key == KeyCode::Enter && !r.tagged.is_empty() {
+                    // Copy the tagged matches
+                    r.copy_tagged_matches();
+                    r.close();
+                    return Ok(());
+                }
+                // Move to prior match
                 if let Some(cur) = r.result_pos.as_ref() {
                     let prior = if *cur > 0 {
                         cur - 1
@@ -418,8 +471,12 @@ impl Pane for QuickSelectOverlay {
                 let lowered = r.selection.to_lowercase();
                 let paste = lowered != r.selection;
                 if let Some(result_index) = r.by_label.get(&lowered).cloned() {
-                    r.select_and_copy_match_number(result_index, paste);
-                    r.close();
+                    if r.tagging {
+                        r.toggle_tag(lowered, result_index);
+                    } else {
+                        r.select_and_copy_match_number(result_index, paste);
+                        r.close();
+                    }
                 }
             }
             (KeyCode::Backspace, KeyModifiers::NONE) => {
@@ -481,7 +538,8 @@ impl Pane for QuickSelectOverlay {
         // move to the search box
         let renderer = self.renderer.lock();
         StableCursorPosition {
-            x: 8 + wezterm_term::unicode_column_width(&renderer.selection, None),
+            x: renderer.bar_prefix().len()
+                + wezterm_term::unicode_column_width(&renderer.selection, None),
             y: renderer.compute_search_row(),
             shape: termwiz::surface::CursorShape::SteadyBlock,
             visibility: termwiz::surface::CursorVisibility::Visible,
@@ -545,9 +603,7 @@ impl Pane for QuickSelectOverlay {
             fn with_lines_mut(&mut self, first_row: StableRowIndex, lines: &mut [&mut Line]) {
                 let mut overlay_lines = vec![];
 
-                let config = &self.renderer.config;
-                let colors = config.resolved_palette.clone();
-                let disable_attr = config.quick_select_remove_styling;
+                let disable_attr = self.renderer.config.quick_select_remove_styling;
 
                 // Process the lines; for the search row we want to render instead
                 // the search UI.
@@ -569,62 +625,13 @@ impl Pane for QuickSelectOverlay {
                         line.fill_range(0..self.dims.cols, &Cell::new(' ', rev.clone()), SEQ_ZERO);
                         line.overlay_text_with_attribute(
                             0,
-                            &format!(
-                                "Select: {}  (type highlighted prefix to {}, uppercase pastes, ESC to cancel)",
-                                self.renderer.selection,
-                                if self.renderer.args.label.is_empty() {
-                                    "copy"
-                                } else {
-                                    &self.renderer.args.label
-                                },
-                            ),
+                            &self.renderer.bar_text(),
                             rev,
                             SEQ_ZERO,
                         );
                         self.renderer.last_bar_pos = Some(self.search_row);
                         line.clear_appdata();
-                    } else if let Some(matches) = self.renderer.by_line.get(&stable_idx) {
-                        for m in matches {
-                            // highlight
-                            for cell_idx in m.range.clone() {
-                                if let Some(cell) =
-                                    line.cells_mut_for_attr_changes_only().get_mut(cell_idx)
-                                {
-                                    cell.attrs_mut()
-                                        .set_background(
-                                            colors
-                                                .quick_select_match_bg
-                                                .unwrap_or(AnsiColor::Black.into()),
-                                        )
-                                        .set_foreground(
-                                            colors
-                                                .quick_select_match_fg
-                                                .unwrap_or(AnsiColor::Green.into()),
-                                        )
-                                        .set_reverse(false)
-                                        .set_intensity(Intensity::Bold);
-                                }
-                            }
-                            for (idx, c) in m.label.chars().enumerate() {
-                                let mut attr = line
-                                    .get_cell(idx)
-                                    .map(|cell| cell.attrs().clone())
-                                    .unwrap_or_else(|| CellAttributes::default());
-                                attr.set_background(
-                                    colors
-                                        .quick_select_label_bg
-                                        .unwrap_or(AnsiColor::Black.into()),
-                                )
-                                .set_foreground(
-                                    colors
-                                        .quick_select_label_fg
-                                        .unwrap_or(AnsiColor::Olive.into()),
-                                )
-                                .set_reverse(false)
-                                .set_intensity(Intensity::Bold);
-                                line.set_cell(m.range.start + idx, Cell::new(c, attr), SEQ_ZERO);
-                            }
-                        }
+                    } else if self.renderer.highlight_matches(&mut line, stable_idx) {
                         line.clear_appdata();
                     }
                     overlay_lines.push(line);
@@ -642,7 +649,6 @@ impl Pane for QuickSelectOverlay {
         let dims = self.get_dimensions();
 
         let (top, mut lines) = self.delegate.get_lines(lines);
-        let colors = renderer.config.resolved_palette.clone();
         let disable_attr = renderer.config.quick_select_remove_styling;
 
         // Process the lines; for the search row we want to render instead
@@ -661,62 +667,10 @@ impl Pane for QuickSelectOverlay {
                 // Replace with search UI
                 let rev = CellAttributes::default().set_reverse(true).clone();
                 line.fill_range(0..dims.cols, &Cell::new(' ', rev.clone()), SEQ_ZERO);
-                line.overlay_text_with_attribute(
-                    0,
-                    &format!(
-                        "Select: {}  (type highlighted prefix to {}, uppercase pastes, ESC to cancel)",
-                        renderer.selection,
-                        if renderer.args.label.is_empty() {
-                            "copy"
-                        } else {
-                            &renderer.args.label
-                        },
-                    ),
-                    rev,
-                    SEQ_ZERO,
-                );
+                line.overlay_text_with_attribute(0, &renderer.bar_text(), rev, SEQ_ZERO);
                 renderer.last_bar_pos = Some(search_row);
-            } else if let Some(matches) = renderer.by_line.get(&stable_idx) {
-                for m in matches {
-                    // highlight
-                    for cell_idx in m.range.clone() {
-                        if let Some(cell) = line.cells_mut_for_attr_changes_only().get_mut(cell_idx)
-                        {
-                            cell.attrs_mut()
-                                .set_background(
-                                    colors
-                                        .quick_select_match_bg
-                                        .unwrap_or(AnsiColor::Black.into()),
-                                )
-                                .set_foreground(
-                                    colors
-                                        .quick_select_match_fg
-                                        .unwrap_or(AnsiColor::Green.into()),
-                                )
-                                .set_reverse(false)
-                                .set_intensity(Intensity::Bold);
-                        }
-                    }
-                    for (idx, c) in m.label.chars().enumerate() {
-                        let mut attr = line
-                            .get_cell(idx)
-                            .map(|cell| cell.attrs().clone())
-                            .unwrap_or_else(|| CellAttributes::default());
-                        attr.set_background(
-                            colors
-                                .quick_select_label_bg
-                                .unwrap_or(AnsiColor::Black.into()),
-                        )
-                        .set_foreground(
-                            colors
-                                .quick_select_label_fg
-                                .unwrap_or(AnsiColor::Olive.into()),
-                        )
-                        .set_reverse(false)
-                        .set_intensity(Intensity::Bold);
-                        line.set_cell(m.range.start + idx, Cell::new(c, attr), SEQ_ZERO);
-                    }
-                }
+            } else {
+                renderer.highlight_matches(line, stable_idx);
             }
         }
 
@@ -729,6 +683,100 @@ impl Pane for QuickSelectOverlay {
 }
 
 impl QuickSelectRenderable {
+    fn bar_prefix(&self) -> &'static str {
+        if self.tagging {
+            "Tag: "
+        } else {
+            "Select: "
+        }
+    }
+
+    fn bar_text(&self) -> String {
+        if self.tagging {
+            format!(
+                "{}{}  (type highlighted prefix to tag, {} tagged, ENTER copies tagged, SPACE stops tagging, ESC to cancel)",
+                self.bar_prefix(),
+                self.selection,
+                self.tagged.len(),
+            )
+        } else {
+            format!(
+                "{}{}  (type highlighted prefix to {}, uppercase pastes, SPACE to tag, ESC to cancel)",
+                self.bar_prefix(),
+                self.selection,
+                if self.args.label.is_empty() {
+                    "copy"
+                } else {
+                    &self.args.label
+                },
+            )
+        }
+    }
+
+    /// Highlights the matches and their labels in `line`, which is
+    /// the row `stable_idx`.  Returns false if there are no matches
+    /// in the row.
+    fn highlight_matches(&self, line: &mut Line, stable_idx: StableRowIndex) -> bool {
+        let matches = match self.by_line.get(&stable_idx) {
+            Some(matches) => matches,
+            None => return false,
+        };
+        let colors = &self.config.resolved_palette;
+        for m in matches {
+            let (match_bg, match_fg) = if self.tagged.contains(&m.label) {
+                (
+                    colors
+                        .quick_select_tagged_bg
+                        .unwrap_or(AnsiColor::Green.into()),
+                    colors
+                        .quick_select_tagged_fg
+                        .unwrap_or(AnsiColor::Black.into()),
+                )
+            } else {
+                (
+                    colors
+                        .quick_select_match_bg
+                        .unwrap_or(AnsiColor::Black.into()),
+                    colors
+                        .quick_select_match_fg
+                        .unwrap_or(AnsiColor::Green.into()),
+                )
+            };
+            let overrides = self.patterns[m.group].overrides.as_ref();
+            let label_bg = overrides
+                .and_then(|p| p.label_bg)
+                .or(colors.quick_select_label_bg)
+                .unwrap_or(AnsiColor::Black.into());
+            let label_fg = overrides
+                .and_then(|p| p.label_fg)
+                .or(colors.quick_select_label_fg)
+                .unwrap_or(AnsiColor::Olive.into());
+
+            // highlight
+            for cell_idx in m.range.clone() {
+                if let Some(cell) = line.cells_mut_for_attr_changes_only().get_mut(cell_idx) {
+                    cell.attrs_mut()
+                        .set_background(match_bg)
+                        .set_foreground(match_fg)
+                        .set_reverse(false)
+                        .set_intensity(Intensity::Bold);
+                }
+            }
+            for (idx, c) in m.label.chars().enumerate() {
+                let mut attr = line
+                    .get_cell(idx)
+                    .map(|cell| cell.attrs().clone())
+                    .unwrap_or_else(|| CellAttributes::default());
+                attr.set_background(label_bg)
+                    .set_foreground(label_fg)
+                    .set_reverse(false)
+                    .set_intensity(Intensity::Bold);
+                line.set_cell(m.range.start + idx, Cell::new(c, attr), SEQ_ZERO);
+            }
+        }
+        true
+    }
+
     fn compute_search_row(&self) -> StableRowIndex {
         let dims = self.delegate.get_dimensions();
         let top = self.viewport.unwrap_or_else(|| dims.physical_top);
@@ -831,6 +879,7 @@ impl QuickSelectRenderable {
                 let result = MatchResult {
                     range,
                     label: label.clone(),
+                    group: self.result_groups[result_index],
                 };
 
                 let matches = self.by_line.entry(idx).or_insert_with(|| vec![]);
@@ -850,16 +899,17 @@ impl QuickSelectRenderable {
         }
 
         self.results.clear();
+        self.result_groups.clear();
         self.by_line.clear();
         self.result_pos.take();
 
         let bar_pos = self.compute_search_row();
         self.dirty_results.add(bar_pos);
 
-        if !self.pattern.is_empty() {
+        if !self.patterns.is_empty() {
             let pane: Arc<dyn Pane> = self.delegate.clone();
             let window = self.window.clone();
-            let pattern = self.pattern.clone();
+            let patterns = self.patterns.clone();
             let scope = self.args.scope_lines;
             let viewport = self.viewport;
             promise::spawn::spawn(async move {
@@ -869,11 +919,35 @@ impl QuickSelectRenderable {
                 let range = top.saturating_sub(scope as StableRowIndex)
                     ..top + (dims.viewport_rows + scope) as StableRowIndex;
                 let limit = None;
-                let mut results = pane.search(pattern, range, limit).await?;
+
+                // Matches from earlier groups take precedence over any
+                // that overlap them from later groups.  The match_ids are
+                // offset so that they remain distinct across the groups.
+                let mut results: Vec<(SearchResult, usize)> = vec![];
+                let mut next_match_id = 0;
+                for (group_idx, group) in patterns.into_iter().enumerate() {
+                    let base_match_id = next_match_id;
+                    let group_results = pane.search(group.pattern, range.clone(), limit).await?;
+                    for mut res in group_results {
+                        if group_idx > 0
+                            && results
+                                .iter()
+                                .any(|(other, _)| results_overlap(other, &res))
+                        {
+                            continue;
+                        }
+                        res.match_id += base_match_id;
+                        next_match_id = next_match_id.max(res.match_id + 1);
+                        results.push((res, group_idx));
+                    }
+                }
                 results.sort();
+                let (results, result_groups): (Vec<SearchResult>, Vec<usize>) =
+                    results.into_iter().unzip();
 
                 let pane_id = pane.pane_id();
                 let mut results = Some(results);
+                let mut result_groups = Some(result_groups);
                 window.notify(TermWindowNotif::Apply(Box::new(move |term_window| {
                     let state = term_window.pane_state(pane_id);
                     if let Some(overlay) = state.overlay.as_ref() {
@@ -882,6 +956,7 @@ impl QuickSelectRenderable {
                         {
                             let mut r = search_overlay.renderer.lock();
                             r.results = results.take().unwrap();
+                            r.result_groups = result_groups.take().unwrap();
                             r.recompute_results();
                             let num_results = r.results.len();
 
@@ -931,7 +1006,13 @@ impl QuickSelectRenderable {
         let result = self.results[n].clone();
 
         let pane_id = self.delegate.pane_id();
-        let action = self.args.action.clone();
+        // An action given for the pattern overrides the action
+        // given for the QuickSelect as a whole
+        let action = self.patterns[self.result_groups[n]]
+            .overrides
+            .as_ref()
+            .and_then(|p| p.action.clone())
+            .or_else(|| self.args.action.clone().map(QuickSelectAction::Action));
         let skip_action_on_paste = self.args.skip_action_on_paste;
         self.window
             .notify(TermWindowNotif::Apply(Box::new(move |term_window| {
@@ -960,21 +1041,90 @@ impl QuickSelectRenderable {
                         if paste {
                             let _ = pane.send_paste(&text);
                         }
-                        if let Some(action) = action {
-                            if !paste || !skip_action_on_paste {
-                                let _ = term_window.perform_key_assignment(&pane, &action);
+                        let perform_action = !paste || !skip_action_on_paste;
+                        match action {
+                            None | Some(QuickSelectAction::Copy) => {
+                                term_window.copy_to_clipboard(
+                                    ClipboardCopyDestination::ClipboardAndPrimarySelection,
+                                    text,
+                                );
+                            }
+                            Some(QuickSelectAction::Paste) => {
+                                if !paste {
+                                    let _ = pane.send_paste(&text);
+                                }
+                            }
+                            Some(QuickSelectAction::Open) => {
+                                if perform_action {
+                                    wezterm_open_url::open_url(&text);
+                                }
+                            }
+                            Some(QuickSelectAction::Action(action)) => {
+                                if perform_action {
+                                    let _ = term_window.perform_key_assignment(&pane, &action);
+                                }
                             }
-                        } else {
-                            term_window.copy_to_clipboard(
-                                ClipboardCopyDestination::ClipboardAndPrimarySelection,
-                                text,
-                            );
                         }
                     }
                 }
             })));
     }
 
+    fn toggle_tag(&mut self, label: String, n: usize) {
+        match self.tagged.iter().position(|l| *l == label) {
+            Some(idx) => {
+                self.tagged.remove(idx);
+            }
+            None => self.tagged.push(label),
+        }
+        self.selection.clear();
+
+        let result = self.results[n];
+        for idx in result.start_y..=result.end_y {
+            self.dirty_results.add(idx);
+        }
+        let bar_pos = self.compute_search_row();
+        self.dirty_results.add(bar_pos);
+    }
+
+    /// Copies the text of the tagged matches to the clipboard,
+    /// one per line, in the order that they were tagged
+    fn copy_tagged_matches(&self) {
+        let ranges: Vec<SelectionRange> = self
+            .tagged
+            .iter()
+            .filter_map(|label| self.by_label.get(label))
+            .map(|&n| {
+                let result = self.results[n];
+                SelectionRange {
+                    start: SelectionCoordinate::x_y(result.start_x, result.start_y),
+                    // inclusive range for selection, but the result
+                    // range is exclusive
+                    end: SelectionCoordinate::x_y(result.end_x.saturating_sub(1), result.end_y),
+                }
+            })
+            .collect();
+
+        let pane_id = self.delegate.pane_id();
+        self.window
+            .notify(TermWindowNotif::Apply(Box::new(move |term_window| {
+                let mux = mux::Mux::get();
+                if let Some(pane) = mux.get_pane(pane_id) {
+                    let text = ranges
+                        .iter()
+                        .map(|range| TermWindow::text_for_selection_range(&pane, range, false))
+                        .collect::<Vec<String>>()
+                        .join("\n");
+                    if !text.is_empty() {
+                        term_window.copy_to_clipboard(
+                            ClipboardCopyDestination::ClipboardAndPrimarySelection,
+                            text,
+                        );
+                    }
+                }
+            })));
+    }
+
     fn activate_match_number(&mut self, n: usize) {
         self.result_pos.replace(n);
         let result = self.results[n].clone();
@@ -0,0 +1,54 @@
+// This is the built-in post processing shader that applies effects,
+// such as dimming and blurring, to the regions of individual panes.
+// It is appended to post-process-prelude.glsl
+
+vec3 rgb2hsv(vec3 c)
+{
+    vec4 K = vec4(0.0, -1.0 / 3.0, 2.0 / 3.0, -1.0);
+    vec4 p = mix(vec4(c.bg, K.wz), vec4(c.gb, K.xy), step(c.b, c.g));
+    vec4 q = mix(vec4(p.xyw, c.r), vec4(c.r, p.yzx), step(p.x, c.r));
+
+    float d = q.x - min(q.w, q.y);
+    float e = 1.0e-10;
+    return vec3(abs(q.z + (q.w - q.y) / (6.0 * d + e)), d / (q.x + e), q.x);
+}
+
+vec3 hsv2rgb(vec3 c)
+{
+    vec4 K = vec4(1.0, 2.0 / 3.0, 1.0 / 3.0, 3.0);
+    vec3 p = abs(fract(c.xxx + K.xyz) * 6.0 - K.www);
+    return c.z * mix(K.xxx, clamp(p - K.xxx, 0.0, 1.0), c.y);
+}
+
+// Averages a 5x5 grid of samples spread over `radius` pixels,
+// without sampling from outside of the pane
+vec4 blur(vec2 pixel, vec4 rect, float radius)
+{
+  vec4 sum = vec4(0.0);
+  for (int y = -2; y <= 2; y++) {
+    for (int x = -2; x <= 2; x++) {
+      vec2 pos = pixel + vec2(float(x), float(y)) * (radius / 2.0);
+      pos = clamp(pos, rect.xy, rect.xy + rect.zw - 1.0);
+      sum += texture(source, pos / resolution);
+    }
+  }
+  return sum / 25.0;
+}
+
+void main() {
+  vec2 pixel = tex_coord * resolution;
+  color = texture(source, tex_coord);
+
+  for (int i = 0; i < pane_count; i++) {
+    vec4 rect = pane_rects[i];
+    if (all(greaterThanEqual(pixel, rect.xy)) && all(lessThan(pixel, rect.xy + rect.zw))) {
+      vec4 effect = pane_effects[i];
+      if (effect.w > 0.0) {
+        color = blur(pixel, rect, effect.w);
+      }
+      vec3 hsv = rgb2hsv(color.rgb) * effect.xyz;
+      color = vec4(hsv2rgb(hsv), color.a);
+      break;
+    }
+  }
+}
@@ -0,0 +1,56 @@
+// This is the built-in post processing shader that applies effects,
+// such as dimming and blurring, to the regions of individual panes.
+// It is appended to post-process.wgsl
+
+fn rgb2hsv(c: vec3<f32>) -> vec3<f32>
+{
+    let K = vec4<f32>(0.0, -1.0 / 3.0, 2.0 / 3.0, -1.0);
+    let p = mix(vec4<f32>(c.bg, K.wz), vec4<f32>(c.gb, K.xy), step(c.b, c.g));
+    let q = mix(vec4<f32>(p.xyw, c.r), vec4<f32>(c.r, p.yzx), step(p.x, c.r));
+
+    let d = q.x - min(q.w, q.y);
+    let e = 1.0e-10;
+    return vec3<f32>(abs(q.z + (q.w - q.y) / (6.0 * d + e)), d / (q.x + e), q.x);
+}
+
+fn hsv2rgb(c: vec3<f32>) -> vec3<f32>
+{
+    let K = vec4<f32>(1.0, 2.0 / 3.0, 1.0 / 3.0, 3.0);
+    let p = abs(fract(c.xxx + K.xyz) * 6.0 - K.www);
+    return c.z * mix(K.xxx, clamp(p - K.xxx, vec3(0.0), vec3(1.0)), c.y);
+}
+
+// Averages a 5x5 grid of samples spread over `radius` pixels,
+// without sampling from outside of the pane
+fn blur(pixel: vec2<f32>, rect: vec4<f32>, radius: f32) -> vec4<f32>
+{
+  var sum = vec4<f32>(0.0);
+  for (var y = -2; y <= 2; y++) {
+    for (var x = -2; x <= 2; x++) {
+      var pos = pixel + vec2<f32>(f32(x), f32(y)) * (radius / 2.0);
+      pos = clamp(pos, rect.xy, rect.xy + rect.zw - 1.0);
+      sum += textureSampleLevel(source_texture, source_sampler, pos / uniforms.resolution, 0.0);
+    }
+  }
+  return sum / 25.0;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+  let pixel = in.tex_coord * uniforms.resolution;
+  var color = textureSample(source_texture, source_sampler, in.tex_coord);
+
+  for (var i = 0u; i < uniforms.pane_count; i++) {
+    let rect = uniforms.pane_rects[i];
+    if all(pixel >= rect.xy) && all(pixel < rect.xy + rect.zw) {
+      let effect = uniforms.pane_effects[i];
+      if effect.w > 0.0 {
+        color = blur(pixel, rect, effect.w);
+      }
+      let hsv = rgb2hsv(color.rgb) * effect.xyz;
+      color = vec4<f32>(hsv2rgb(hsv), color.a);
+      break;
+    }
+  }
+  return color;
+}
@@ -0,0 +1,31 @@
+// These declarations are prepended to the post_process_shader
+// fragment shader.
+
+precision highp float;
+
+// The position of this fragment within `source`
+in vec2 tex_coord;
+
+// The color + alpha to output
+out vec4 color;
+
+// The rendered window
+uniform sampler2D source;
+// Seconds since the window was created
+uniform float time;
+// The size of the window in pixels
+uniform vec2 resolution;
+// The position and size of the cursor in pixels, in the same
+// coordinate system as gl_FragCoord
+uniform vec4 cursor;
+
+// The number of valid entries in pane_rects and pane_effects
+uniform int pane_count;
+// The position and size in pixels of each of the panes that have
+// an effect, such as inactive_pane_hsb, in the same coordinate
+// system as gl_FragCoord
+uniform vec4 pane_rects[16];
+// The hue, saturation and brightness multipliers and the blur
+// radius of the effect that applies to the corresponding pane_rects
+uniform vec4 pane_effects[16];
+
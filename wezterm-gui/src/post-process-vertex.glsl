@@ -0,0 +1,16 @@
+// This is the vertex shader for the post_process_shader.
+// It covers the window with a single quad, rotated to match
+// the orientation of the output.
+
+precision highp float;
+
+in vec2 position;
+
+uniform mat4 transform;
+
+out vec2 tex_coord;
+
+void main() {
+  tex_coord = position * 0.5 + 0.5;
+  gl_Position = transform * vec4(position, 0.0, 1.0);
+}
@@ -0,0 +1,42 @@
+// These declarations are prepended to the post_process_shader
+
+struct PostProcessUniform {
+  // The position and size of the cursor in pixels, in the same
+  // coordinate system as @builtin(position)
+  cursor: vec4<f32>,
+  // The size of the window in pixels
+  resolution: vec2<f32>,
+  // Seconds since the window was created
+  time: f32,
+  // The number of valid entries in pane_rects and pane_effects
+  pane_count: u32,
+  // The position and size in pixels of each of the panes that have
+  // an effect, such as inactive_pane_hsb, in the same coordinate
+  // system as @builtin(position)
+  pane_rects: array<vec4<f32>, 16>,
+  // The hue, saturation and brightness multipliers and the blur
+  // radius of the effect that applies to the corresponding pane_rects
+  pane_effects: array<vec4<f32>, 16>,
+};
+@group(0) @binding(0) var<uniform> uniforms: PostProcessUniform;
+
+// The rendered window
+@group(1) @binding(0) var source_texture: texture_2d<f32>;
+@group(1) @binding(1) var source_sampler: sampler;
+
+struct VertexOutput {
+  @builtin(position) position: vec4<f32>,
+  // The position of this fragment within source_texture
+  @location(0) tex_coord: vec2<f32>,
+};
+
+// Covers the window with a single triangle
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+  let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+  var out: VertexOutput;
+  out.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+  out.tex_coord = uv;
+  return out;
+}
+
@@ -5,7 +5,8 @@
 use crate::renderstate::BorrowedLayers;
 use ::window::bitmaps::TextureRect;
 use ::window::color::LinearRgba;
-use config::HsbTransform;
+use config::{HsbTransform, ImageFilter};
+use std::ops::Range;
 
 /// Each cell is composed of two triangles built from 4 vertices.
 /// The buffer is organized row by row.
@@ -27,6 +28,12 @@ const IS_BG_IMAGE: f32 = 2.0;
 const IS_SOLID_COLOR: f32 = 3.0;
 /// Grayscale poly quad for non-aa text render layers
 const IS_GRAY_SCALE: f32 = 4.0;
+/// like 2.0, except that the image is sampled using
+/// the nearest texel
+const IS_IMAGE_NEAREST: f32 = 5.0;
+/// like 2.0, except that the image is sampled using
+/// bicubic interpolation
+const IS_IMAGE_CUBIC: f32 = 6.0;
 
 #[repr(C)]
 #[derive(Copy, Clone, Default, Debug, bytemuck::Pod, bytemuck::Zeroable)]
@@ -88,10 +95,16 @@ pub trait QuadTrait {
         self.set_has_color_impl(IS_GRAY_SCALE);
     }
 
-    /// Mark this quad as a background image.
+    /// Mark this quad as a full color image, such as the background
+    /// image, that is resampled using the specified filter.
+    /// The alpha of the image is multiplied by the alpha of the fg color.
     /// Mutually exclusive with set_has_color.
-    fn set_is_background_image(&mut self) {
-        self.set_has_color_impl(IS_BG_IMAGE);
+    fn set_is_image(&mut self, filter: ImageFilter) {
+        self.set_has_color_impl(match filter {
+            ImageFilter::Nearest => IS_IMAGE_NEAREST,
+            ImageFilter::Linear => IS_BG_IMAGE,
+            ImageFilter::Cubic => IS_IMAGE_CUBIC,
+        });
     }
 
     fn set_is_background(&mut self) {
@@ -222,7 +235,7 @@ pub trait TripleLayerQuadAllocatorTrait {
 /// and the total amount of contiguous memory is in the MB range,
 /// which is a bit gnarly to reallocate, and can waste several MB
 /// in unused capacity
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct BoxedQuad {
     position: (f32, f32, f32, f32),
     fg_color: [f32; 4],
@@ -306,6 +319,41 @@ impl BoxedQuad {
 
         vert
     }
+
+    /// Returns a copy of this quad moved down by `y_offset` and cropped
+    /// to the vertical range `clip`, or None if nothing remains visible
+    fn shifted_and_clipped(&self, y_offset: f32, clip: &Range<f32>) -> Option<Self> {
+        let (left, top, right, bottom) = self.position;
+        let (top, bottom) = (top + y_offset, bottom + y_offset);
+        if bottom <= clip.start || top >= clip.end {
+            return None;
+        }
+
+        let clipped_top = top.max(clip.start);
+        let clipped_bottom = bottom.min(clip.end);
+
+        // Crop the texture by the same proportion as the position, so
+        // that the visible portion is not stretched
+        let (x1, x2, y1, y2) = self.tex;
+        let height = bottom - top;
+        let tex = if height > 0. {
+            let tex_height = y2 - y1;
+            (
+                x1,
+                x2,
+                y1 + tex_height * (clipped_top - top) / height,
+                y1 + tex_height * (clipped_bottom - top) / height,
+            )
+        } else {
+            self.tex
+        };
+
+        Some(Self {
+            position: (left, clipped_top, right, clipped_bottom),
+            tex,
+            ..self.clone()
+        })
+    }
 }
 
 #[derive(Default)]
@@ -332,6 +380,24 @@ impl HeapQuadAllocator {
         metrics::histogram!("quad_buffer_apply").record(start.elapsed());
         Ok(())
     }
+
+    /// Like apply_to, but moves each quad down by `y_offset` and
+    /// discards the parts of it that fall outside of `clip`
+    pub fn apply_to_clipped(
+        &self,
+        other: &mut TripleLayerQuadAllocator,
+        y_offset: f32,
+        clip: Range<f32>,
+    ) -> anyhow::Result<()> {
+        for (layer_num, quads) in [(0, &self.layer0), (1, &self.layer1), (2, &self.layer2)] {
+            for quad in quads {
+                if let Some(quad) = quad.shifted_and_clipped(y_offset, &clip) {
+                    other.extend_with(layer_num, &quad.to_vertices());
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
 impl TripleLayerQuadAllocatorTrait for HeapQuadAllocator {
@@ -1,9 +1,10 @@
 use super::glyphcache::GlyphCache;
 use super::quad::*;
 use super::utilsprites::{RenderMetrics, UtilSprites};
+use crate::termwindow::software::SoftwareFrame;
 use crate::termwindow::webgpu::{adapter_info_to_gpu_info, WebGpuState, WebGpuTexture};
 use ::window::bitmaps::atlas::OutOfTextureSpace;
-use ::window::bitmaps::Texture2d;
+use ::window::bitmaps::{ImageTexture, Texture2d};
 use ::window::glium::backend::Context as GliumContext;
 use ::window::glium::buffer::{BufferMutSlice, Mapping};
 use ::window::glium::{
@@ -23,11 +24,14 @@ const INDICES_PER_CELL: usize = 6;
 pub enum RenderContext {
     Glium(Rc<GliumContext>),
     WebGpu(Rc<WebGpuState>),
+    /// Drawing on the CPU; see termwindow/software.rs
+    Software,
 }
 
 pub enum RenderFrame<'a> {
     Glium(&'a mut glium::Frame),
     WebGpu,
+    Software(&'a mut SoftwareFrame),
 }
 
 impl RenderContext {
@@ -39,12 +43,15 @@ impl RenderContext {
                 indices,
             )?)),
             Self::WebGpu(state) => Ok(IndexBuffer::WebGpu(WebGpuIndexBuffer::new(indices, state))),
+            // We draw each group of 4 vertices as a quad, so the
+            // indices are implied
+            Self::Software => Ok(IndexBuffer::Software),
         }
     }
 
     pub fn allocate_vertex_buffer_initializer(&self, num_quads: usize) -> Vec<Vertex> {
         match self {
-            Self::Glium(_) => {
+            Self::Glium(_) | Self::Software => {
                 vec![Vertex::default(); num_quads * VERTICES_PER_CELL]
             }
             Self::WebGpu(_) => vec![],
@@ -65,6 +72,7 @@ impl RenderContext {
                 num_quads * VERTICES_PER_CELL,
                 state,
             ))),
+            Self::Software => Ok(VertexBuffer::Software(initializer.to_vec())),
         }
     }
 
@@ -103,6 +111,10 @@ impl RenderContext {
                     Rc::new(WebGpuTexture::new(size as u32, size as u32, state)?);
                 Ok(texture)
             }
+            Self::Software => {
+                let texture: Rc<dyn Texture2d> = Rc::new(ImageTexture::new(size, size));
+                Ok(texture)
+            }
         }
     }
 
@@ -117,6 +129,7 @@ impl RenderContext {
                 let info = adapter_info_to_gpu_info(state.adapter_info.clone());
                 format!("WebGPU: {}", info.to_string())
             }
+            Self::Software => "Software: CPU rasterizer".to_string(),
         }
     }
 }
@@ -124,6 +137,7 @@ impl RenderContext {
 pub enum IndexBuffer {
     Glium(GliumIndexBuffer<u32>),
     WebGpu(WebGpuIndexBuffer),
+    Software,
 }
 
 impl IndexBuffer {
@@ -144,6 +158,7 @@ impl IndexBuffer {
 pub enum VertexBuffer {
     Glium(GliumVertexBuffer<Vertex>),
     WebGpu(WebGpuVertexBuffer),
+    Software(Vec<Vertex>),
 }
 
 impl VertexBuffer {
@@ -165,11 +180,20 @@ impl VertexBuffer {
             _ => unreachable!(),
         }
     }
+    pub fn software(&self) -> &[Vertex] {
+        match self {
+            Self::Software(v) => v,
+            _ => unreachable!(),
+        }
+    }
 }
 
 enum MappedVertexBuffer {
     Glium(GliumMappedVertexBuffer),
     WebGpu(WebGpuMappedVertexBuffer),
+    /// The vertices are already in memory, so we just hold on
+    /// to the buffer
+    Software(RefMut<'static, VertexBuffer>),
 }
 
 impl MappedVertexBuffer {
@@ -180,6 +204,10 @@ impl MappedVertexBuffer {
                 let mapping: &mut [Vertex] = bytemuck::cast_slice_mut(&mut g.mapping);
                 &mut mapping[range]
             }
+            Self::Software(g) => match &mut **g {
+                VertexBuffer::Software(v) => &mut v[range],
+                _ => unreachable!(),
+            },
         }
     }
 }
@@ -427,6 +455,7 @@ impl TripleVertexBuffer {
                 })
             }
             VertexBuffer::WebGpu(vb) => MappedVertexBuffer::WebGpu(vb.map()),
+            VertexBuffer::Software(_) => MappedVertexBuffer::Software(bufs),
         };
 
         MappedQuads {
@@ -594,7 +623,7 @@ impl RenderState {
                         RenderContext::Glium(context) => {
                             Some(Self::compile_prog(&context, Self::glyph_shader)?)
                         }
-                        RenderContext::WebGpu(_) => None,
+                        RenderContext::WebGpu(_) | RenderContext::Software => None,
                     };
 
                     let main_layer = Rc::new(RenderLayer::new(&context, 1024, 0)?);
@@ -669,9 +698,9 @@ impl RenderState {
         Ok(allocated)
     }
 
-    fn compile_prog(
+    pub fn compile_prog(
         context: &Rc<GliumContext>,
-        fragment_shader: fn(&str) -> (String, String),
+        fragment_shader: impl Fn(&str) -> (String, String),
     ) -> anyhow::Result<glium::Program> {
         let mut errors = vec![];
 
@@ -716,6 +745,19 @@ impl RenderState {
         self.glyph_cache.borrow_mut().config_changed();
     }
 
+    /// Evicts the least recently used glyphs and images from the
+    /// texture atlas; see GlyphCache::evict_least_recently_used.
+    /// Returns false if nothing could be evicted.
+    pub fn evict_texture_atlas(&mut self, metrics: &RenderMetrics) -> anyhow::Result<bool> {
+        let mut glyph_cache = self.glyph_cache.borrow_mut();
+        if !glyph_cache.evict_least_recently_used() {
+            return Ok(false);
+        }
+        // The utility sprites may have been evicted along with everything else
+        self.util_sprites = UtilSprites::new(&mut glyph_cache, metrics)?;
+        Ok(true)
+    }
+
     pub fn recreate_texture_atlas(
         &mut self,
         fonts: &Rc<FontConfiguration>,
@@ -765,6 +807,7 @@ impl RenderState {
         self.util_sprites = UtilSprites::new(&mut new_glyph_cache, metrics)?;
 
         let mut glyph_cache = self.glyph_cache.borrow_mut();
+        new_glyph_cache.set_refresh_interval(glyph_cache.refresh_interval());
 
         // Steal the decoded image cache; without this, any animating gifs
         // would reset back to frame 0 each time we filled the texture
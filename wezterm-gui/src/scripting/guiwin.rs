@@ -1,8 +1,11 @@
 //! GuiWin represents a Gui TermWindow (as opposed to a Mux window) in lua code
 use super::luaerr;
+use crate::termwindow::capture::{CaptureDestination, CaptureTarget};
+use crate::termwindow::toast::ToastOptions;
 use crate::termwindow::TermWindowNotif;
 use crate::TermWindow;
-use config::keyassignment::{ClipboardCopyDestination, KeyAssignment};
+use config::keyassignment::{ClipboardCopyDestination, KeyAssignment, ScreenSelector};
+use config::PaneEffect;
 use luahelper::*;
 use mlua::{UserData, UserDataMethods, UserDataRef};
 use mux::pane::PaneId;
@@ -12,7 +15,7 @@ use mux_lua::MuxPane;
 use termwiz_funcs::lines_to_escapes;
 use wezterm_dynamic::{FromDynamic, ToDynamic};
 use wezterm_toast_notification::ToastNotification;
-use window::{Connection, ConnectionOps, DeadKeyStatus, WindowOps, WindowState};
+use window::{Connection, ConnectionOps, WindowOps, WindowState};
 
 #[derive(Clone)]
 pub struct GuiWin {
@@ -73,8 +76,46 @@ impl UserData for GuiWin {
             this.window.restore();
             Ok(())
         });
-        methods.add_method("toggle_fullscreen", |_, this, _: ()| {
-            this.window.toggle_fullscreen();
+        methods.add_method(
+            "toggle_fullscreen",
+            |_, this, screen: Option<mlua::Value>| {
+                let selector = match screen {
+                    None | Some(mlua::Value::Nil) => {
+                        this.window.toggle_fullscreen();
+                        return Ok(());
+                    }
+                    Some(mlua::Value::String(name)) => {
+                        ScreenSelector::Name(name.to_str()?.to_string())
+                    }
+                    Some(mlua::Value::Integer(idx)) => ScreenSelector::Index(
+                        usize::try_from(idx)
+                            .map_err(|_| luaerr(anyhow::anyhow!("invalid index")))?,
+                    ),
+                    Some(other) => {
+                        return Err(luaerr(anyhow::anyhow!(
+                            "toggle_fullscreen: expected a screen name or index, got {}",
+                            other.type_name()
+                        )))
+                    }
+                };
+                this.window
+                    .notify(TermWindowNotif::Apply(Box::new(move |term_window| {
+                        if let Err(err) = term_window.toggle_fullscreen_on_screen(&selector) {
+                            log::error!("toggle_fullscreen: {err:#}");
+                        }
+                    })));
+                Ok(())
+            },
+        );
+        methods.add_method("set_icon", |_, this, path: Option<String>| {
+            let image = match path {
+                Some(path) => std::fs::read(&path)
+                    .map_err(|err| anyhow::anyhow!("reading {path}: {err:#}"))
+                    .and_then(|data| TermWindow::decode_icon(&data)),
+                None => TermWindow::decode_icon(crate::ICON_DATA),
+            }
+            .map_err(luaerr)?;
+            this.window.set_icon(image);
             Ok(())
         });
         methods.add_method("focus", |_, this, _: ()| {
@@ -93,6 +134,20 @@ impl UserData for GuiWin {
                 Ok(())
             },
         );
+        methods.add_method(
+            "toast_notification_inline",
+            |_, this, (title, message, options): (String, String, Option<mlua::Value>)| {
+                let options: ToastOptions = match options {
+                    Some(options) => from_lua_value_dynamic(options)?,
+                    None => ToastOptions::default(),
+                };
+                this.window
+                    .notify(TermWindowNotif::Apply(Box::new(move |term_window| {
+                        term_window.show_toast(&title, &message, options);
+                    })));
+                Ok(())
+            },
+        );
         methods.add_method("get_appearance", |_, _, _: ()| {
             Ok(Connection::get().unwrap().get_appearance().to_string())
         });
@@ -148,6 +203,20 @@ impl UserData for GuiWin {
                 Ok(text)
             },
         );
+        methods.add_async_method("capture", |lua, this, _: ()| async move {
+            let (tx, rx) = smol::channel::bounded(1);
+            this.window
+                .notify(TermWindowNotif::Apply(Box::new(move |term_window| {
+                    term_window
+                        .request_capture(CaptureTarget::Window, CaptureDestination::Reply(tx));
+                })));
+            let png = rx
+                .recv()
+                .await
+                .map_err(mlua::Error::external)?
+                .map_err(mlua::Error::external)?;
+            lua.create_string(&png)
+        });
         methods.add_async_method("current_event", |lua, this, _: ()| async move {
             let (tx, rx) = smol::channel::bounded(1);
             this.window
@@ -198,6 +267,42 @@ impl UserData for GuiWin {
                 .notify(TermWindowNotif::SetConfigOverrides(value));
             Ok(())
         });
+        methods.add_async_method("get_tiling", |_, this, _: ()| async move {
+            let (tx, rx) = smol::channel::bounded(1);
+            this.window
+                .notify(TermWindowNotif::Apply(Box::new(move |term_window| {
+                    tx.try_send(term_window.window_state).ok();
+                })));
+            let window_state = rx
+                .recv()
+                .await
+                .map_err(|e| anyhow::anyhow!("{:#}", e))
+                .map_err(luaerr)?;
+
+            #[derive(FromDynamic, ToDynamic)]
+            struct Tiling {
+                tiled: bool,
+                tiled_left: bool,
+                tiled_right: bool,
+                tiled_top: bool,
+                tiled_bottom: bool,
+                maximized: bool,
+                full_screen: bool,
+                suspended: bool,
+            }
+            impl_lua_conversion_dynamic!(Tiling);
+
+            Ok(Tiling {
+                tiled: window_state.intersects(WindowState::TILED),
+                tiled_left: window_state.contains(WindowState::TILED_LEFT),
+                tiled_right: window_state.contains(WindowState::TILED_RIGHT),
+                tiled_top: window_state.contains(WindowState::TILED_TOP),
+                tiled_bottom: window_state.contains(WindowState::TILED_BOTTOM),
+                maximized: window_state.contains(WindowState::MAXIMIZED),
+                full_screen: window_state.contains(WindowState::FULL_SCREEN),
+                suspended: window_state.contains(WindowState::SUSPENDED),
+            })
+        });
         methods.add_async_method("is_focused", |_, this, _: ()| async move {
             let (tx, rx) = smol::channel::bounded(1);
             this.window
@@ -230,10 +335,12 @@ impl UserData for GuiWin {
             let (tx, rx) = smol::channel::bounded(1);
             this.window
                 .notify(TermWindowNotif::Apply(Box::new(move |term_window| {
-                    tx.try_send(match term_window.composition_status() {
-                        DeadKeyStatus::None => None,
-                        DeadKeyStatus::Composing(s) => Some(s.clone()),
-                    })
+                    tx.try_send(
+                        term_window
+                            .composition_status()
+                            .composing_text()
+                            .map(|s| s.to_string()),
+                    )
                     .ok();
                 })));
             let result = rx
@@ -273,6 +380,45 @@ impl UserData for GuiWin {
 
             Ok((mods.to_string(), leds.to_string()))
         });
+        methods.add_async_method("frame_stats", |_, this, _: ()| async move {
+            let (tx, rx) = smol::channel::bounded(1);
+            this.window
+                .notify(TermWindowNotif::Apply(Box::new(move |term_window| {
+                    tx.try_send(term_window.frame_stats.clone()).ok();
+                })));
+            let stats = rx
+                .recv()
+                .await
+                .map_err(|e| anyhow::anyhow!("{:#}", e))
+                .map_err(luaerr)?;
+
+            #[derive(FromDynamic, ToDynamic)]
+            struct FrameStats {
+                interval_ms: f64,
+                presented: u64,
+                discarded: u64,
+                vsync: u64,
+                zero_copy: u64,
+                missed_deadlines: u64,
+                frame_time_p50_ms: Option<f64>,
+                frame_time_p95_ms: Option<f64>,
+                frame_time_max_ms: Option<f64>,
+            }
+            impl_lua_conversion_dynamic!(FrameStats);
+
+            let ms = |d: std::time::Duration| d.as_secs_f64() * 1000.;
+            Ok(stats.map(|stats| FrameStats {
+                interval_ms: ms(stats.interval),
+                presented: stats.presented,
+                discarded: stats.discarded,
+                vsync: stats.vsync,
+                zero_copy: stats.zero_copy,
+                missed_deadlines: stats.missed_deadlines,
+                frame_time_p50_ms: stats.frame_time_p50.map(ms),
+                frame_time_p95_ms: stats.frame_time_p95.map(ms),
+                frame_time_max_ms: stats.frame_time_max.map(ms),
+            }))
+        });
         methods.add_async_method("active_pane", |_, this, _: ()| async move {
             let (tx, rx) = smol::channel::bounded(1);
             this.window
@@ -309,6 +455,28 @@ impl UserData for GuiWin {
                 Ok(())
             },
         );
+        methods.add_method(
+            "set_inactive_pane_effect",
+            |_, this, (pane, effect): (UserDataRef<MuxPane>, Option<PaneEffect>)| {
+                let pane_id = pane.0;
+                this.window
+                    .notify(TermWindowNotif::Apply(Box::new(move |term_window| {
+                        term_window.set_inactive_pane_effect(pane_id, effect);
+                    })));
+                Ok(())
+            },
+        );
+        methods.add_method(
+            "set_pane_ligatures",
+            |_, this, (pane, enabled): (UserDataRef<MuxPane>, Option<bool>)| {
+                let pane_id = pane.0;
+                this.window
+                    .notify(TermWindowNotif::Apply(Box::new(move |term_window| {
+                        term_window.set_pane_ligatures(pane_id, enabled);
+                    })));
+                Ok(())
+            },
+        );
         methods.add_async_method(
             "get_selection_escapes_for_pane",
             |_, this, pane: UserDataRef<MuxPane>| async move {
@@ -1,10 +1,11 @@
 use crate::frontend::try_front_end;
 use crate::inputmap::InputMap;
-use config::keyassignment::KeyTable;
-use config::lua::get_or_create_sub_module;
+use crate::termwindow::palette::{UserPaletteEntry, LUA_REGISTRY_PALETTE_ENTRIES};
+use config::keyassignment::{KeyAssignment, KeyTable};
 use config::lua::mlua::{self, Lua};
+use config::lua::{get_or_create_sub_module, wrap_callback};
 use config::{DeferredKeyCode, GpuInfo, Key, KeyNoAction};
-use luahelper::dynamic_to_lua_value;
+use luahelper::{dynamic_to_lua_value, from_lua_value_dynamic};
 use mux::window::WindowId as MuxWindowId;
 use std::collections::HashMap;
 use wezterm_dynamic::ToDynamic;
@@ -78,22 +79,37 @@ pub fn register(lua: &Lua) -> anyhow::Result<()> {
         })?,
     )?;
 
+    window_mod.set(
+        "register_palette_entry",
+        lua.create_function(|lua, entry: mlua::Table| {
+            // Allow passing a function as the action, as a shorthand
+            // for wrapping it in wezterm.action_callback
+            if let mlua::Value::Function(callback) = entry.get("action")? {
+                let event_name = wrap_callback(lua, callback)?;
+                entry.set("action", KeyAssignment::EmitEvent(event_name))?;
+            }
+            let entry: UserPaletteEntry = from_lua_value_dynamic(mlua::Value::Table(entry))?;
+
+            let mut entries: Vec<UserPaletteEntry> =
+                match lua.named_registry_value(LUA_REGISTRY_PALETTE_ENTRIES)? {
+                    mlua::Value::Nil => vec![],
+                    value => from_lua_value_dynamic(value)?,
+                };
+            // Registering an entry with the same brief replaces it
+            entries.retain(|existing| existing.brief != entry.brief);
+            entries.push(entry);
+            lua.set_named_registry_value(
+                LUA_REGISTRY_PALETTE_ENTRIES,
+                dynamic_to_lua_value(lua, entries.to_dynamic())?,
+            )?;
+            Ok(())
+        })?,
+    )?;
+
     window_mod.set(
         "enumerate_gpus",
         lua.create_function(|_, _: ()| {
-            let backends = wgpu::Backends::all();
-            let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
-                backends,
-                ..Default::default()
-            });
-            let gpus: Vec<GpuInfo> = instance
-                .enumerate_adapters(backends)
-                .into_iter()
-                .map(|adapter| {
-                    let info = adapter.get_info();
-                    crate::termwindow::webgpu::adapter_info_to_gpu_info(info)
-                })
-                .collect();
+            let gpus: Vec<GpuInfo> = crate::termwindow::webgpu::enumerate_gpus();
             Ok(gpus)
         })?,
     )?;
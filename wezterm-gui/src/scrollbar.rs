@@ -1,6 +1,18 @@
 use mux::pane::Pane;
 use wezterm_term::StableRowIndex;
 
+/// The kinds of rows that are marked on the scrollbar, in
+/// increasing order of priority when they share a position
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ScrollMarker {
+    /// A prompt, as marked by OSC 133
+    Prompt,
+    /// A match for the current search
+    SearchMatch,
+    /// The end of a command that reported a non-zero exit status
+    Error,
+}
+
 pub struct ScrollHit {
     /// Offset from the top of the window in pixels
     pub top: usize,
@@ -67,4 +79,13 @@ impl ScrollHit {
                 as StableRowIndex,
         )
     }
+
+    /// Compute the y-coordinate within the scrollbar of the marker for
+    /// `row`, which is proportional to its position in the scrollback
+    pub fn marker_top(pane: &dyn Pane, row: StableRowIndex, max_thumb_height: usize) -> usize {
+        let render_dims = pane.get_dimensions();
+        let offset = row.saturating_sub(render_dims.scrollback_top).max(0) as f32;
+        let scroll_size = render_dims.scrollback_rows.max(1) as f32;
+        ((offset / scroll_size) * max_thumb_height as f32) as usize
+    }
 }
@@ -1,11 +1,13 @@
 // The range_plus_one lint can't see when the LHS is not compatible with
 // and inclusive range
 #![allow(clippy::range_plus_one)]
+use config::SelectionWordRule;
 use mux::pane::Pane;
+use regex::Regex;
 use std::cmp::Ordering;
 use std::ops::Range;
 use termwiz::surface::line::DoubleClickRange;
-use termwiz::surface::SequenceNo;
+use termwiz::surface::{Line, SequenceNo};
 use wezterm_term::{SemanticZone, StableRowIndex};
 
 #[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
@@ -164,14 +166,61 @@ pub struct SelectionRange {
     pub end: SelectionCoordinate,
 }
 
-fn is_double_click_word(s: &str) -> bool {
+fn is_double_click_word(s: &str, word_boundary: &str) -> bool {
     match s.chars().count() {
-        1 => !config::configuration().selection_word_boundary.contains(s),
+        1 => !word_boundary.contains(s),
         0 => false,
         _ => true,
     }
 }
 
+/// Returns the range of cells in `line` that is matched by the first
+/// of `rules` to have a match that contains the cell at `click_idx`
+fn word_rule_range(
+    line: &Line,
+    click_idx: usize,
+    rules: &[SelectionWordRule],
+) -> Option<Range<usize>> {
+    if rules.is_empty() {
+        return None;
+    }
+
+    // The byte offset in `text` at which each cell starts,
+    // along with its cell index
+    let mut text = String::new();
+    let mut cells = vec![];
+    for cell in line.visible_cells() {
+        cells.push((text.len(), cell.cell_index()));
+        text.push_str(cell.str());
+    }
+    let click_byte = cells.iter().rev().find(|(_, idx)| *idx <= click_idx)?.0;
+    let byte_to_cell = |byte: usize| {
+        cells
+            .get(cells.partition_point(|(b, _)| *b < byte))
+            .map(|(_, idx)| *idx)
+            .unwrap_or_else(|| line.len())
+    };
+
+    for rule in rules {
+        let re = match Regex::new(&rule.regex) {
+            Ok(re) => re,
+            Err(err) => {
+                log::error!("selection_word_rules: {}: {:#}", rule.regex, err);
+                continue;
+            }
+        };
+        for m in re.find_iter(&text) {
+            if m.start() > click_byte {
+                break;
+            }
+            if click_byte < m.end() {
+                return Some(byte_to_cell(m.start())..byte_to_cell(m.end()));
+            }
+        }
+    }
+    None
+}
+
 impl SelectionRange {
     /// Create a new range that starts at the specified location
     pub fn start(start: SelectionCoordinate) -> Self {
@@ -236,8 +285,21 @@ impl SelectionRange {
         }
     }
 
-    /// Computes the selection range for the word around the specified coords
+    /// Computes the selection range for the word around the specified coords.
+    /// Words are delimited by the selection_word_boundary characters, except
+    /// where one of the selection_word_rules matches around the coords.
     pub fn word_around(start: SelectionCoordinate, pane: &dyn Pane) -> Self {
+        let config = config::configuration();
+        let overrides = pane.get_selection_word_config().unwrap_or_default();
+        let word_boundary = overrides
+            .word_boundary
+            .as_deref()
+            .unwrap_or(&config.selection_word_boundary);
+        let rules = overrides
+            .rules
+            .as_deref()
+            .unwrap_or(&config.selection_word_rules);
+
         for logical in pane.get_logical_lines(start.y..start.y + 1) {
             if !logical.contains_y(start.y) {
                 continue;
@@ -245,10 +307,13 @@ impl SelectionRange {
 
             if let SelectionX::Cell(start_x) = start.x {
                 let start_idx = logical.xy_to_logical_x(start_x, start.y);
-                return match logical
-                    .logical
-                    .compute_double_click_range(start_idx, is_double_click_word)
-                {
+                let click_range = match word_rule_range(&logical.logical, start_idx, rules) {
+                    Some(range) => DoubleClickRange::Range(range),
+                    None => logical.logical.compute_double_click_range(start_idx, |s| {
+                        is_double_click_word(s, word_boundary)
+                    }),
+                };
+                return match click_range {
                     DoubleClickRange::RangeWithWrap(click_range)
                     | DoubleClickRange::Range(click_range) => {
                         let (start_y, start_x) =
@@ -0,0 +1,30 @@
+// Subpixel antialiased glyph fragment shader.
+// This is appended to shader.wgsl, and is only compiled when the
+// adapter supports dual source blending, which is enabled by a
+// directive that webgpu.rs places ahead of both.
+
+struct SubpixelOutput {
+  // The color + alpha
+  @location(0) @blend_src(0) color: vec4<f32>,
+  // Individual alpha channels for RGBA in color, used for subpixel
+  // antialiasing blending
+  @location(0) @blend_src(1) mask: vec4<f32>,
+};
+
+@fragment
+fn fs_subpixel(in: VertexOutput) -> SubpixelOutput {
+  let linear_tex = textureSample(atlas_linear_tex, atlas_linear_sampler, in.tex);
+  let nearest_tex = textureSample(atlas_nearest_tex, atlas_nearest_sampler, in.tex);
+
+  var out: SubpixelOutput;
+  out.color = shade(in, linear_tex, nearest_tex);
+  if in.has_color == IS_GLYPH {
+    // the texture holds the coverage of each subpixel, which
+    // is used to blend each channel independently
+    out.color.a = in.fg_color.a;
+    out.mask = adjust_coverage(nearest_tex, in.fg_color.rgb);
+  } else {
+    out.mask = vec4<f32>(out.color.a);
+  }
+  return out;
+}
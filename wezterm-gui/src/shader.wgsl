@@ -35,10 +35,24 @@ const IS_SOLID_COLOR: f32 = 3.0;
 // Grayscale poly quad for non-aa text render layers
 const IS_GRAY_SCALE: f32 = 4.0;
 
+// like 2.0, except that the image is sampled using
+// the nearest texel
+const IS_IMAGE_NEAREST: f32 = 5.0;
+
+// like 2.0, except that the image is sampled using
+// bicubic interpolation
+const IS_IMAGE_CUBIC: f32 = 6.0;
+
 struct ShaderUniform {
   foreground_text_hsb: vec3<f32>,
   milliseconds: u32,
   projection: mat4x4<f32>,
+  sdr_scale: f32,
+  color_lut: u32,
+  // The page of the texture atlas that is bound for this draw
+  atlas_page: f32,
+  text_gamma: f32,
+  text_contrast: f32,
 };
 @group(0) @binding(0) var<uniform> uniforms: ShaderUniform;
 
@@ -48,6 +62,9 @@ struct ShaderUniform {
 @group(2) @binding(0) var atlas_nearest_tex: texture_2d<f32>;
 @group(2) @binding(1) var atlas_nearest_sampler: sampler;
 
+@group(3) @binding(0) var color_lut_tex: texture_3d<f32>;
+@group(3) @binding(1) var color_lut_sampler: sampler;
+
 fn rgb2hsv(c: vec3<f32>) -> vec3<f32>
 {
     let K = vec4<f32>(0.0, -1.0 / 3.0, 2.0 / 3.0, -1.0);
@@ -72,27 +89,111 @@ fn apply_hsv(c: vec4<f32>, transform: vec3<f32>) -> vec4<f32>
   return vec4<f32>(hsv2rgb(hsv).rgb, c.a);
 }
 
+fn linear_to_srgb(c: vec3<f32>) -> vec3<f32>
+{
+  let lo = c * 12.92;
+  let hi = 1.055 * pow(c, vec3<f32>(1.0 / 2.4)) - 0.055;
+  return select(hi, lo, c <= vec3<f32>(0.0031308));
+}
+
+fn srgb_to_linear(c: vec3<f32>) -> vec3<f32>
+{
+  let lo = c / 12.92;
+  let hi = pow((c + 0.055) / 1.055, vec3<f32>(2.4));
+  return select(hi, lo, c <= vec3<f32>(0.04045));
+}
+
+// Adjusts the antialiasing coverage of text drawn in the linear
+// color fg.  Dark text is weighted by 1/text_gamma and light text
+// by text_gamma, which evens out how heavy each appears when
+// blended over a background of the opposite lightness.
+fn adjust_coverage(coverage: vec4<f32>, fg: vec3<f32>) -> vec4<f32>
+{
+  let luminance = dot(fg, vec3<f32>(0.2126, 0.7152, 0.0722));
+  let exponent = mix(1.0 / uniforms.text_gamma, uniforms.text_gamma, luminance);
+  return clamp(
+    pow(coverage, vec4<f32>(exponent)) * uniforms.text_contrast,
+    vec4<f32>(0.0),
+    vec4<f32>(1.0)
+  );
+}
+
+// Samples the atlas using Catmull-Rom bicubic interpolation.
+// This uses the linear sampler to combine the 16 texels that
+// contribute to the result into 9 fetches.
+fn sample_cubic(uv: vec2<f32>) -> vec4<f32>
+{
+  let size = vec2<f32>(textureDimensions(atlas_linear_tex));
+  let pos = uv * size;
+  let pos1 = floor(pos - 0.5) + 0.5;
+  let f = pos - pos1;
+
+  let w0 = f * (-0.5 + f * (1.0 - 0.5 * f));
+  let w1 = 1.0 + f * f * (-2.5 + 1.5 * f);
+  let w2 = f * (0.5 + f * (2.0 - 1.5 * f));
+  let w3 = f * f * (-0.5 + 0.5 * f);
+  let w12 = w1 + w2;
+
+  let p0 = (pos1 - 1.0) / size;
+  let p12 = (pos1 + w2 / w12) / size;
+  let p3 = (pos1 + 2.0) / size;
+
+  var result = vec4<f32>(0.0);
+  result += textureSampleLevel(atlas_linear_tex, atlas_linear_sampler, vec2<f32>(p0.x, p0.y), 0.0) * w0.x * w0.y;
+  result += textureSampleLevel(atlas_linear_tex, atlas_linear_sampler, vec2<f32>(p12.x, p0.y), 0.0) * w12.x * w0.y;
+  result += textureSampleLevel(atlas_linear_tex, atlas_linear_sampler, vec2<f32>(p3.x, p0.y), 0.0) * w3.x * w0.y;
+  result += textureSampleLevel(atlas_linear_tex, atlas_linear_sampler, vec2<f32>(p0.x, p12.y), 0.0) * w0.x * w12.y;
+  result += textureSampleLevel(atlas_linear_tex, atlas_linear_sampler, vec2<f32>(p12.x, p12.y), 0.0) * w12.x * w12.y;
+  result += textureSampleLevel(atlas_linear_tex, atlas_linear_sampler, vec2<f32>(p3.x, p12.y), 0.0) * w3.x * w12.y;
+  result += textureSampleLevel(atlas_linear_tex, atlas_linear_sampler, vec2<f32>(p0.x, p3.y), 0.0) * w0.x * w3.y;
+  result += textureSampleLevel(atlas_linear_tex, atlas_linear_sampler, vec2<f32>(p12.x, p3.y), 0.0) * w12.x * w3.y;
+  result += textureSampleLevel(atlas_linear_tex, atlas_linear_sampler, vec2<f32>(p3.x, p3.y), 0.0) * w3.x * w3.y;
+
+  // The negative lobes of the filter can overshoot
+  return clamp(result, vec4<f32>(0.0), vec4<f32>(1.0));
+}
+
+// Converts a linear sRGB color to the color space of the display.
+// The lut is indexed and filled with encoded values, and we return
+// linear values for the sRGB surface to encode once more.
+fn apply_color_lut(c: vec3<f32>) -> vec3<f32>
+{
+  let size = f32(textureDimensions(color_lut_tex).x);
+  let encoded = linear_to_srgb(clamp(c, vec3<f32>(0.0), vec3<f32>(1.0)));
+  // Sample at the centers of the texels at either end
+  let coord = (encoded * (size - 1.0) + 0.5) / size;
+  let display = textureSampleLevel(color_lut_tex, color_lut_sampler, coord, 0.0).rgb;
+  return srgb_to_linear(display);
+}
+
 @vertex
 fn vs_main(
     model: VertexInput,
 ) -> VertexOutput {
     var out: VertexOutput;
-    out.tex = model.tex;
+    // The integer part of the texture x coordinate is the atlas page
+    let page = floor(model.tex.x);
+    out.tex = vec2<f32>(model.tex.x - page, model.tex.y);
     out.hsv = model.hsv;
     out.has_color = model.has_color;
     out.fg_color = mix(model.fg_color, model.alt_color, model.mix_value);
+    if page != uniforms.atlas_page {
+      // Quads on other pages are drawn by separate passes,
+      // so place this one outside the clip volume
+      out.clip_position = vec4<f32>(2.0, 2.0, 2.0, 1.0);
+      return out;
+    }
     out.clip_position = uniforms.projection * vec4<f32>(model.position, 0.0, 1.0);
     return out;
 }
 
 // Fragment shader
 
-@fragment
-fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+// Computes the color of a fragment, given the texels sampled for it.
+// The sampling is left to the entry points, as it must happen in
+// uniform control flow.
+fn shade(in: VertexOutput, linear_tex: vec4<f32>, nearest_tex: vec4<f32>) -> vec4<f32> {
   var color: vec4<f32>;
-  var linear_tex: vec4<f32> = textureSample(atlas_linear_tex, atlas_linear_sampler, in.tex);
-  var nearest_tex: vec4<f32> = textureSample(atlas_nearest_tex, atlas_nearest_sampler, in.tex);
-
   var hsv = in.hsv;
 
   if in.has_color == IS_SOLID_COLOR {
@@ -103,6 +204,12 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     // Apply window_background_image_opacity to the background image
     color = linear_tex;
     color.a *= in.fg_color.a;
+  } else if in.has_color == IS_IMAGE_NEAREST {
+    color = nearest_tex;
+    color.a *= in.fg_color.a;
+  } else if in.has_color == IS_IMAGE_CUBIC {
+    color = sample_cubic(in.tex);
+    color.a *= in.fg_color.a;
   } else if in.has_color == IS_COLOR_EMOJI {
     // the texture is full color info (eg: color emoji glyph)
     color = nearest_tex;
@@ -114,11 +221,26 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     // the texture is the alpha channel/color mask
     // and we need to tint with the fg_color
     color = in.fg_color;
-    color.a = nearest_tex.a;
+    color.a = adjust_coverage(nearest_tex, color.rgb).a;
     hsv *= uniforms.foreground_text_hsb;
   }
 
   color = apply_hsv(color, hsv);
 
+  if uniforms.color_lut != 0u {
+    color = vec4<f32>(apply_color_lut(color.rgb), color.a);
+  }
+
+  // In hdr mode we output scRGB, so bring our sRGB content up to
+  // the SDR reference white
+  color = vec4<f32>(color.rgb * uniforms.sdr_scale, color.a);
+
   return color;
 }
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+  let linear_tex = textureSample(atlas_linear_tex, atlas_linear_sampler, in.tex);
+  let nearest_tex = textureSample(atlas_nearest_tex, atlas_nearest_sampler, in.tex);
+  return shade(in, linear_tex, nearest_tex);
+}
@@ -2,13 +2,14 @@ use anyhow::{anyhow, bail, Context};
 use config::keyassignment::SpawnCommand;
 use config::TermConfig;
 use mux::activity::Activity;
-use mux::domain::SplitSource;
+use mux::domain::{LocalDomain, SplitSource};
 use mux::tab::SplitRequest;
 use mux::window::WindowId as MuxWindowId;
 use mux::Mux;
 use portable_pty::CommandBuilder;
 use std::sync::Arc;
 use wezterm_term::TerminalSize;
+use window::{Connection, ConnectionOps, ACTIVATION_TOKEN_ENV};
 
 #[derive(Copy, Debug, Clone, Eq, PartialEq)]
 pub enum SpawnWhere {
@@ -68,12 +69,23 @@ pub async fn spawn_command_internal(
         None
     };
 
+    // Give the new program a token that allows it to focus any
+    // windows that it opens; that only makes sense for processes
+    // that run on this machine
+    let activation_token = match mux.resolve_spawn_tab_domain(current_pane_id, &spawn.domain) {
+        Ok(domain) if domain.downcast_ref::<LocalDomain>().is_some() => {
+            Connection::get().and_then(|conn| conn.take_activation_token())
+        }
+        _ => None,
+    };
+
     let cmd_builder = match (
         spawn.args.as_ref(),
         spawn.cwd.as_ref(),
         spawn.set_environment_variables.is_empty(),
+        activation_token.is_none(),
     ) {
-        (None, None, true) => None,
+        (None, None, true, true) => None,
         _ => {
             let mut builder = spawn
                 .args
@@ -83,6 +95,9 @@ pub async fn spawn_command_internal(
             for (k, v) in spawn.set_environment_variables.iter() {
                 builder.env(k, v);
             }
+            if let Some(token) = &activation_token {
+                builder.env(ACTIVATION_TOKEN_ENV, token);
+            }
             if let Some(cwd) = &spawn.cwd {
                 builder.cwd(cwd);
             }
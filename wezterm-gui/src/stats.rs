@@ -354,6 +354,21 @@ impl Recorder for Stats {
     }
 }
 
+/// Returns the p50 and p95 of the latency histogram named `name`,
+/// if anything has been recorded to it
+pub fn latency_p50_p95(name: &str) -> Option<(Duration, Duration)> {
+    let inner = INNER.lock();
+    let hist = inner
+        .histograms
+        .iter()
+        .find_map(|(key, hist)| (key.name() == name).then_some(hist))?;
+    if hist.hist.lock().is_empty() {
+        return None;
+    }
+    let (p50, _p75, p95) = hist.latency_percentiles();
+    Some((p50, p95))
+}
+
 pub fn register(lua: &Lua) -> anyhow::Result<()> {
     let metrics_mod = get_or_create_sub_module(lua, "metrics")?;
     metrics_mod.set(
@@ -1,5 +1,5 @@
 use crate::termwindow::{PaneInformation, TabInformation, UIItem, UIItemType};
-use config::{ConfigHandle, TabBarColors};
+use config::{ConfigHandle, TabBarColors, TabBarProgressStyle};
 use finl_unicode::grapheme_clusters::Graphemes;
 use mlua::FromLua;
 use termwiz::cell::{unicode_column_width, Cell, CellAttributes};
@@ -10,7 +10,9 @@ use termwiz::escape::{Action, ControlCode, CSI};
 use termwiz::surface::SEQ_ZERO;
 use termwiz_funcs::{format_as_escapes, FormatColor, FormatItem};
 use wezterm_term::{Line, Progress};
-use window::{IntegratedTitleButton, IntegratedTitleButtonAlignment, IntegratedTitleButtonStyle};
+use window::{
+    IntegratedTitleButton, IntegratedTitleButtonAlignment, IntegratedTitleButtonStyle, WindowState,
+};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct TabBarState {
@@ -130,6 +132,64 @@ fn pct_to_glyph(pct: u8) -> char {
     }
 }
 
+/// Renders pct (in the range 0-100) as a small progress bar made from
+/// the nerdfonts progress chunk glyphs (0xee00 ..= 0xee05), which are
+/// drawn by our custom glyph renderer so that they join up seamlessly.
+fn pct_to_bar(pct: u8) -> String {
+    const CELLS: usize = 4;
+    let filled = (pct.min(100) as usize * CELLS + 50) / 100;
+    (0..CELLS)
+        .map(|idx| {
+            let full = idx < filled;
+            match (idx, full) {
+                (0, false) => '\u{ee00}',
+                (0, true) => '\u{ee03}',
+                (i, false) if i == CELLS - 1 => '\u{ee02}',
+                (i, true) if i == CELLS - 1 => '\u{ee05}',
+                (_, false) => '\u{ee01}',
+                (_, true) => '\u{ee04}',
+            }
+        })
+        .collect()
+}
+
+/// Returns the text and color used to show the progress of a pane,
+/// or None if there is nothing to show.
+fn progress_graphic(
+    progress: &Progress,
+    style: TabBarProgressStyle,
+) -> Option<(String, AnsiColor)> {
+    let (pct, color) = match progress {
+        Progress::None => return None,
+        Progress::Percentage(pct) => (*pct, AnsiColor::Green),
+        Progress::Error(pct) => (*pct, AnsiColor::Red),
+        Progress::Indeterminate => {
+            return match style {
+                TabBarProgressStyle::None => None,
+                // The first frame of the nerdfonts spinner
+                TabBarProgressStyle::Glyph | TabBarProgressStyle::Bar => {
+                    Some(("\u{ee06} ".to_string(), AnsiColor::Green))
+                }
+            };
+        }
+    };
+    match style {
+        TabBarProgressStyle::None => None,
+        TabBarProgressStyle::Glyph => Some((format!("{} ", pct_to_glyph(pct)), color)),
+        TabBarProgressStyle::Bar => Some((format!("{} ", pct_to_bar(pct)), color)),
+    }
+}
+
+/// Returns the badge text for a tab whose active pane has output that
+/// has not been seen yet
+fn unseen_output_badge(pane: &PaneInformation) -> String {
+    match pane.unseen_output_lines {
+        0 => "\u{25cf} ".to_string(),
+        n @ 1..=99 => format!("\u{25cf}{n} "),
+        _ => "\u{25cf}99+ ".to_string(),
+    }
+}
+
 fn compute_tab_title(
     tab: &TabInformation,
     tab_info: &[TabInformation],
@@ -170,23 +230,23 @@ fn compute_tab_title(
                     title = format!("{}{classic_spacing}", title);
                 }
 
-                match pane.progress {
-                    Progress::None => {}
-                    Progress::Percentage(pct) | Progress::Error(pct) => {
-                        let graphic = format!("{} ", pct_to_glyph(pct));
-                        len += unicode_column_width(&graphic, None);
-                        let color = if matches!(pane.progress, Progress::Percentage(_)) {
-                            FormatItem::Foreground(FormatColor::AnsiColor(AnsiColor::Green))
-                        } else {
-                            FormatItem::Foreground(FormatColor::AnsiColor(AnsiColor::Red))
-                        };
-                        items.push(color);
-                        items.push(FormatItem::Text(graphic));
-                        items.push(FormatItem::Foreground(FormatColor::Default));
-                    }
-                    Progress::Indeterminate => {
-                        // TODO: Decide what to do here to indicate this
-                    }
+                if let Some((graphic, color)) =
+                    progress_graphic(&pane.progress, config.tab_bar_progress_style)
+                {
+                    len += unicode_column_width(&graphic, None);
+                    items.push(FormatItem::Foreground(FormatColor::AnsiColor(color)));
+                    items.push(FormatItem::Text(graphic));
+                    items.push(FormatItem::Foreground(FormatColor::Default));
+                }
+
+                if config.show_tab_unseen_output_badge && !tab.is_active && pane.has_unseen_output {
+                    let badge = unseen_output_badge(pane);
+                    len += unicode_column_width(&badge, None);
+                    items.push(FormatItem::Foreground(FormatColor::AnsiColor(
+                        AnsiColor::Yellow,
+                    )));
+                    items.push(FormatItem::Text(badge));
+                    items.push(FormatItem::Foreground(FormatColor::Default));
                 }
 
                 // We have a preferred soft minimum on tab width to make it
@@ -339,6 +399,7 @@ impl TabBarState {
         config: &ConfigHandle,
         left_status: &str,
         right_status: &str,
+        window_state: WindowState,
     ) -> Self {
         let colors = colors.cloned().unwrap_or_else(TabBarColors::default);
 
@@ -365,9 +426,12 @@ impl TabBarState {
             },
         );
 
+        // When the window manager has decided to draw its own title
+        // bar there is no need for us to draw the buttons as well
         let use_integrated_title_buttons = config
             .window_decorations
-            .contains(window::WindowDecorations::INTEGRATED_BUTTONS);
+            .contains(window::WindowDecorations::INTEGRATED_BUTTONS)
+            && !window_state.contains(WindowState::SERVER_DECORATIONS);
 
         // We ultimately want to produce a line looking like this:
         // ` | tab1-title x | tab2-title x |  +      . - X `
@@ -1,6 +1,6 @@
 use crate::color::LinearRgba;
-use crate::glyphcache::LoadState;
-use crate::quad::{QuadAllocator, QuadTrait};
+use crate::glyphcache::{ImagePlayback, LoadState};
+use crate::quad::{QuadAllocator, QuadImpl, QuadTrait, TripleLayerQuadAllocator};
 use crate::termwindow::RenderState;
 use crate::utilsprites::RenderMetrics;
 use crate::Dimensions;
@@ -8,13 +8,15 @@ use anyhow::Context;
 use config::{
     BackgroundHorizontalAlignment, BackgroundLayer, BackgroundRepeat, BackgroundSize,
     BackgroundSource, BackgroundVerticalAlignment, ConfigHandle, DimensionContext, Gradient,
-    GradientOrientation,
+    GradientOrientation, ImageFilter,
 };
+use mux::pane::PaneId;
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use std::time::SystemTime;
 use termwiz::image::{ImageData, ImageDataType};
 use wezterm_term::StableRowIndex;
+use window::RectF;
 
 lazy_static::lazy_static! {
     static ref IMAGE_CACHE: Mutex<HashMap<String, CachedImage>> = Mutex::new(HashMap::new());
@@ -188,17 +190,16 @@ struct CachedImage {
     modified: SystemTime,
     image: Arc<ImageData>,
     marked: bool,
-    speed: f32,
 }
 
 impl CachedImage {
-    fn load(path: &str, speed: f32) -> anyhow::Result<Arc<ImageData>> {
+    fn load(path: &str) -> anyhow::Result<Arc<ImageData>> {
         let modified = std::fs::metadata(path)
             .and_then(|m| m.modified())
             .with_context(|| format!("getting metadata for {}", path))?;
         let mut cache = IMAGE_CACHE.lock().unwrap();
         if let Some(cached) = cache.get_mut(path) {
-            if cached.modified == modified && cached.speed == speed {
+            if cached.modified == modified {
                 cached.marked = false;
                 return Ok(Arc::clone(&cached.image));
             }
@@ -207,9 +208,7 @@ impl CachedImage {
         let data = std::fs::read(path)
             .with_context(|| format!("Failed to load window_background_image {}", path))?;
         log::trace!("loaded {}", path);
-        let mut data = ImageDataType::EncodedFile(data);
-        data.adjust_speed(speed);
-        let image = Arc::new(ImageData::with_data(data));
+        let image = Arc::new(ImageData::with_data(ImageDataType::EncodedFile(data)));
 
         cache.insert(
             path.to_string(),
@@ -217,7 +216,6 @@ impl CachedImage {
                 modified,
                 image: Arc::clone(&image),
                 marked: false,
-                speed,
             },
         );
 
@@ -327,7 +325,7 @@ fn load_background_layer(
                 size, size, data,
             )))
         }
-        BackgroundSource::File(source) => CachedImage::load(&source.path, source.speed)?,
+        BackgroundSource::File(source) => CachedImage::load(&source.path)?,
     };
 
     Ok(LoadedBackgroundLayer {
@@ -425,12 +423,24 @@ impl crate::TermWindow {
 
         let color = bg_color.mul_alpha(layer.def.opacity);
 
+        let playback = match &layer.def.source {
+            BackgroundSource::File(source) => ImagePlayback {
+                speed: source.speed,
+                paused: source.pause_when_unfocused && self.focused.is_none(),
+            },
+            _ => ImagePlayback::default(),
+        };
+
         let (sprite, next_due, load_state) = gl_state.glyph_cache.borrow_mut().cached_image(
             &layer.source,
             None,
             self.allow_images,
+            playback,
         )?;
         self.update_next_frame_time(next_due);
+        if next_due.is_some() && self.focused.is_none() {
+            self.animate_unfocused.set(true);
+        }
 
         if load_state == LoadState::Loading {
             return Ok(false);
@@ -577,7 +587,7 @@ impl crate::TermWindow {
                 }
 
                 quad.set_texture_discrete(x1, x2, y1, y2);
-                quad.set_is_background_image();
+                quad.set_is_image(layer.def.filter);
                 quad.set_hsv(Some(layer.def.hsb));
                 quad.set_fg_color(color);
             }
@@ -585,4 +595,89 @@ impl crate::TermWindow {
 
         Ok(emitted)
     }
+    /// Returns the image that overrides the background of `pane_id`,
+    /// loading it if the path has changed since the previous frame.
+    /// Returns None if the image could not be loaded.
+    fn pane_background_image(&self, pane_id: PaneId, path: &str) -> Option<Arc<ImageData>> {
+        let mut state = self.pane_state(pane_id);
+        match &state.background_image {
+            Some((cached_path, image)) if cached_path == path => image.clone(),
+            _ => {
+                // Remember failures too, so that we only log them once
+                let image = match CachedImage::load(path) {
+                    Ok(image) => Some(image),
+                    Err(err) => {
+                        log::error!("Failed to load pane background: {:#}", err);
+                        None
+                    }
+                };
+                state
+                    .background_image
+                    .replace((path.to_string(), image.clone()));
+                image
+            }
+        }
+    }
+
+    /// Draws the image at `path` over `rect`, scaled to cover it while
+    /// preserving its aspect ratio and cropped to fit it.  The opacity
+    /// of the image is taken from the alpha channel of `color`.
+    /// Returns None if the image is not yet ready to be drawn.
+    pub fn paint_pane_background_image<'a>(
+        &self,
+        layers: &'a mut TripleLayerQuadAllocator,
+        pane_id: PaneId,
+        path: &str,
+        rect: RectF,
+        color: LinearRgba,
+    ) -> anyhow::Result<Option<QuadImpl<'a>>> {
+        let image = match self.pane_background_image(pane_id, path) {
+            Some(image) => image,
+            None => return Ok(None),
+        };
+
+        let gl_state = self.render_state.as_ref().unwrap();
+        let (sprite, next_due, load_state) = gl_state.glyph_cache.borrow_mut().cached_image(
+            &image,
+            None,
+            self.allow_images,
+            ImagePlayback::default(),
+        )?;
+        self.update_next_frame_time(next_due);
+        if load_state == LoadState::Loading {
+            return Ok(None);
+        }
+
+        let tex_width = sprite.coords.width() as f32;
+        let tex_height = sprite.coords.height() as f32;
+        if tex_width <= 0. || tex_height <= 0. || rect.is_empty() {
+            return Ok(None);
+        }
+
+        // Crop the texture to the fraction of it that is visible when
+        // it is scaled to cover the rect, keeping it centered
+        let scale = (rect.width() / tex_width).max(rect.height() / tex_height);
+        let visible_x = (rect.width() / scale) / tex_width;
+        let visible_y = (rect.height() / scale) / tex_height;
+        let coords = sprite.texture_coords();
+        let x1 = coords.min_x() + coords.width() * (1. - visible_x) / 2.;
+        let y1 = coords.min_y() + coords.height() * (1. - visible_y) / 2.;
+        let x2 = x1 + coords.width() * visible_x;
+        let y2 = y1 + coords.height() * visible_y;
+
+        let left_offset = self.dimensions.pixel_width as f32 / 2.;
+        let top_offset = self.dimensions.pixel_height as f32 / 2.;
+        let mut quad = layers.allocate(0)?;
+        quad.set_position(
+            rect.min_x() - left_offset,
+            rect.min_y() - top_offset,
+            rect.max_x() - left_offset,
+            rect.max_y() - top_offset,
+        );
+        quad.set_texture_discrete(x1, x2, y1, y2);
+        quad.set_is_image(ImageFilter::Linear);
+        quad.set_hsv(None);
+        quad.set_fg_color(color);
+        Ok(Some(quad))
+    }
 }
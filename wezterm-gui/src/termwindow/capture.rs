@@ -0,0 +1,258 @@
+//! Capturing the rendered contents of a window or pane as an image.
+//!
+//! We can't use wlr-screencopy (or any other compositor capture protocol)
+//! to do this because wayland clients are not told where their surfaces
+//! are placed on an output, so there is no way to crop the captured output
+//! to our window.  Instead we render the next frame a second time into an
+//! offscreen texture and read that back from the GPU.
+use crate::termwindow::taboverview::{store_thumbnail, TabOverview};
+use crate::termwindow::TermWindow;
+use ::window::{Clipboard, ClipboardContents, Window, WindowOps};
+use anyhow::Context;
+use config::keyassignment::TabOverviewArguments;
+use mux::pane::PaneId;
+use mux::tab::TabId;
+use std::io::Write;
+use std::rc::Rc;
+
+pub enum CaptureTarget {
+    Window,
+    Pane(PaneId),
+    /// The area of the window in which the panes of the
+    /// active tab are shown, excluding the tab bar
+    Tab,
+}
+
+pub enum CaptureDestination {
+    /// Save to the downloads directory and show a notification
+    Downloads,
+    /// Place the PNG data on the clipboard
+    Clipboard,
+    /// Send the PNG data to a mux client that requested it
+    Reply(smol::channel::Sender<Result<Vec<u8>, String>>),
+    /// Keep a thumbnail of the image for the tab overview,
+    /// and then show the overview if `overview` is set
+    Thumbnail {
+        tab_id: TabId,
+        overview: Option<TabOverviewArguments>,
+    },
+}
+
+pub struct PendingCapture {
+    target: CaptureTarget,
+    destination: CaptureDestination,
+}
+
+impl CaptureDestination {
+    fn complete(self, window: Option<&Window>, result: anyhow::Result<Vec<u8>>) {
+        match self {
+            Self::Downloads => {
+                if let Err(err) = result.and_then(|png| save_capture(&png)) {
+                    log::error!("capture failed: {err:#}");
+                    wezterm_toast_notification::persistent_toast_notification(
+                        "Capture failed",
+                        &format!("{err:#}"),
+                    );
+                }
+            }
+            Self::Clipboard => match (result, window) {
+                (Ok(png), Some(window)) => {
+                    window.set_clipboard_contents(
+                        Clipboard::Clipboard,
+                        ClipboardContents {
+                            png: Some(png),
+                            ..Default::default()
+                        },
+                    );
+                }
+                (Ok(_), None) => {}
+                (Err(err), _) => {
+                    log::error!("capture failed: {err:#}");
+                }
+            },
+            Self::Reply(tx) => {
+                tx.try_send(result.map_err(|err| format!("{err:#}"))).ok();
+            }
+            // Thumbnails are kept by the TermWindow rather than encoded
+            Self::Thumbnail { .. } => {}
+        }
+    }
+}
+
+fn save_capture(png: &[u8]) -> anyhow::Result<()> {
+    let name = format!(
+        "wezterm-capture-{}.png",
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    );
+    let (name, mut file) = crate::download::resolve_file_name(Some(&name))?;
+    file.write_all(png)
+        .with_context(|| format!("writing capture to {}", name.display()))?;
+
+    let url = format!("file://{}", name.display());
+    wezterm_toast_notification::persistent_toast_notification_with_click_to_open_url(
+        "Capture saved",
+        &format!("Saved {}", name.display()),
+        &url,
+    );
+    log::info!("Saved capture to {}", name.display());
+    Ok(())
+}
+
+impl TermWindow {
+    /// Arrange for the next frame to be captured and delivered to
+    /// `destination`.
+    pub fn request_capture(&mut self, target: CaptureTarget, destination: CaptureDestination) {
+        self.pending_captures.push(PendingCapture {
+            target,
+            destination,
+        });
+        if let Some(window) = self.window.as_ref() {
+            window.invalidate();
+        }
+    }
+
+    pub fn has_pending_captures(&self) -> bool {
+        !self.pending_captures.is_empty()
+    }
+
+    /// Called by the renderer when it is unable to capture the frame
+    pub fn fail_captures(&mut self, reason: &str) {
+        let captures: Vec<PendingCapture> = self.pending_captures.drain(..).collect();
+        for capture in captures {
+            self.finish_capture(capture, Err(anyhow::anyhow!("{reason}")));
+        }
+    }
+
+    fn finish_capture(
+        &mut self,
+        capture: PendingCapture,
+        image: anyhow::Result<&image::RgbaImage>,
+    ) {
+        match capture.destination {
+            CaptureDestination::Thumbnail { tab_id, overview } => {
+                match image.and_then(|image| self.crop_capture(image, &capture.target)) {
+                    Ok(image) => store_thumbnail(tab_id, &image),
+                    Err(err) => log::debug!("thumbnail of tab {tab_id} failed: {err:#}"),
+                }
+                if let Some(args) = overview {
+                    let modal = TabOverview::new(self, &args);
+                    self.set_modal(Rc::new(modal));
+                }
+            }
+            destination => {
+                let result = image.and_then(|image| self.encode_capture(image, &capture.target));
+                destination.complete(self.window.as_ref(), result);
+            }
+        }
+    }
+
+    /// Called by the renderer with the RGBA pixels of the frame that it
+    /// just drew.  The rows are ordered bottom-up, as OpenGL returns them.
+    pub fn complete_captures(&mut self, width: u32, height: u32, data: Vec<u8>) {
+        let captures: Vec<PendingCapture> = self.pending_captures.drain(..).collect();
+        if captures.is_empty() {
+            return;
+        }
+
+        let image = match image::RgbaImage::from_raw(width, height, data) {
+            Some(image) => image::imageops::flip_vertical(&image),
+            None => {
+                for capture in captures {
+                    self.finish_capture(
+                        capture,
+                        Err(anyhow::anyhow!("frame data has unexpected size")),
+                    );
+                }
+                return;
+            }
+        };
+
+        for capture in captures {
+            self.finish_capture(capture, Ok(&image));
+        }
+    }
+
+    /// Crops the image of the whole window to `target`
+    fn crop_capture(
+        &self,
+        image: &image::RgbaImage,
+        target: &CaptureTarget,
+    ) -> anyhow::Result<image::RgbaImage> {
+        let (x, y, width, height) = match target {
+            CaptureTarget::Window => return Ok(image.clone()),
+            CaptureTarget::Pane(pane_id) => self.pane_pixel_rect(*pane_id)?,
+            CaptureTarget::Tab => self.tab_pixel_rect()?,
+        };
+        let x = x.min(image.width());
+        let y = y.min(image.height());
+        let width = width.min(image.width() - x);
+        let height = height.min(image.height() - y);
+        Ok(image::imageops::crop_imm(image, x, y, width, height).to_image())
+    }
+
+    fn encode_capture(
+        &self,
+        image: &image::RgbaImage,
+        target: &CaptureTarget,
+    ) -> anyhow::Result<Vec<u8>> {
+        let image = self.crop_capture(image, target)?;
+
+        let mut png = vec![];
+        image
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .context("encoding capture as PNG")?;
+        Ok(png)
+    }
+
+    /// Returns the x, y, width and height of the area below (or above)
+    /// the tab bar, in pixels relative to the top left of the window
+    fn tab_pixel_rect(&self) -> anyhow::Result<(u32, u32, u32, u32)> {
+        let tab_bar_height = if self.show_tab_bar {
+            self.tab_bar_pixel_height()?
+        } else {
+            0.
+        };
+        let y = if self.config.tab_bar_at_bottom {
+            0.
+        } else {
+            tab_bar_height
+        };
+        Ok((
+            0,
+            y as u32,
+            self.dimensions.pixel_width as u32,
+            (self.dimensions.pixel_height as f32 - tab_bar_height).max(0.) as u32,
+        ))
+    }
+
+    /// Returns the x, y, width and height of the pane, in pixels
+    /// relative to the top left of the window
+    fn pane_pixel_rect(&self, pane_id: PaneId) -> anyhow::Result<(u32, u32, u32, u32)> {
+        let pos = self
+            .get_panes_to_render()
+            .into_iter()
+            .find(|pos| pos.pane.pane_id() == pane_id)
+            .ok_or_else(|| anyhow::anyhow!("pane {pane_id} is not visible"))?;
+
+        let (padding_left, padding_top) = self.padding_left_top();
+        let top_bar_height = if self.show_tab_bar && !self.config.tab_bar_at_bottom {
+            self.tab_bar_pixel_height()?
+        } else {
+            0.
+        };
+        let border = self.get_os_border();
+        let cell_width = self.render_metrics.cell_size.width as f32;
+        let cell_height = self.render_metrics.cell_size.height as f32;
+
+        let x = padding_left + border.left.get() as f32 + (pos.left as f32 * cell_width);
+        let y =
+            top_bar_height + padding_top + border.top.get() as f32 + (pos.top as f32 * cell_height);
+
+        Ok((
+            x as u32,
+            y as u32,
+            (pos.width as f32 * cell_width) as u32,
+            (pos.height as f32 * cell_height) as u32,
+        ))
+    }
+}
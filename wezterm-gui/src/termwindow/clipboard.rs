@@ -1,13 +1,39 @@
+use crate::overlay::clipboard_read::{confirm_clipboard_read, ClipboardReadChoice};
+use crate::overlay::paste::confirm_paste;
+use crate::overlay::start_overlay_pane;
+use crate::scripting::guiwin::GuiWin;
 use crate::termwindow::TermWindowNotif;
 use crate::TermWindow;
+use anyhow::Context;
 use config::keyassignment::{ClipboardCopyDestination, ClipboardPasteSource};
-use mux::pane::Pane;
+use config::{DroppedFileQuoting, Osc52ReadAccess, PasteImageBehavior};
+use mux::pane::{Pane, PaneId};
 use mux::Mux;
+use mux_lua::MuxPane;
+use std::io::Write;
 use std::sync::Arc;
-use window::{Clipboard, WindowOps};
+use termwiz::cell::{Intensity, SemanticType, Underline};
+use termwiz::escape::osc::{ITermDimension, ITermFileData, ITermProprietary};
+use termwiz::escape::{Action, OperatingSystemCommand};
+use wezterm_term::ClipboardSelection;
+use window::{Clipboard, ClipboardContents, WindowOps};
 
 impl TermWindow {
     pub fn copy_to_clipboard(&self, clipboard: ClipboardCopyDestination, text: String) {
+        self.copy_contents_to_clipboard(
+            clipboard,
+            ClipboardContents {
+                text,
+                ..Default::default()
+            },
+        );
+    }
+
+    pub fn copy_contents_to_clipboard(
+        &self,
+        clipboard: ClipboardCopyDestination,
+        contents: ClipboardContents,
+    ) {
         let clipboard = match clipboard {
             ClipboardCopyDestination::Clipboard => [Some(Clipboard::Clipboard), None],
             ClipboardCopyDestination::PrimarySelection => [Some(Clipboard::PrimarySelection), None],
@@ -18,9 +44,65 @@ impl TermWindow {
         };
         for &c in &clipboard {
             if let Some(c) = c {
-                self.window.as_ref().unwrap().set_clipboard(c, text.clone());
+                self.window
+                    .as_ref()
+                    .unwrap()
+                    .set_clipboard_contents(c, contents.clone());
+            }
+        }
+    }
+
+    /// Renders the selection as an HTML fragment that preserves the
+    /// colors and text attributes of the selected cells
+    pub fn selection_html(&self, pane: &Arc<dyn Pane>) -> String {
+        let palette = pane.palette();
+        let mut html = format!(
+            "<pre style=\"color:{};background-color:{}\">",
+            palette.foreground.to_rgb_string(),
+            palette.background.to_rgb_string()
+        );
+        for (idx, line) in self.selection_lines(pane).iter().enumerate() {
+            if idx > 0 {
+                html.push('\n');
+            }
+            for cluster in line.cluster(None) {
+                let attrs = &cluster.attrs;
+                let mut fg = palette.resolve_fg(attrs.foreground());
+                let mut bg = palette.resolve_bg(attrs.background());
+                if attrs.reverse() {
+                    std::mem::swap(&mut fg, &mut bg);
+                }
+
+                let mut style = format!("color:{};", fg.to_rgb_string());
+                if bg != palette.background || attrs.reverse() {
+                    style.push_str(&format!("background-color:{};", bg.to_rgb_string()));
+                }
+                match attrs.intensity() {
+                    Intensity::Bold => style.push_str("font-weight:bold;"),
+                    Intensity::Half => style.push_str("opacity:0.5;"),
+                    Intensity::Normal => {}
+                }
+                if attrs.italic() {
+                    style.push_str("font-style:italic;");
+                }
+                let mut decorations = vec![];
+                if attrs.underline() != Underline::None {
+                    decorations.push("underline");
+                }
+                if attrs.strikethrough() {
+                    decorations.push("line-through");
+                }
+                if !decorations.is_empty() {
+                    style.push_str(&format!("text-decoration:{};", decorations.join(" ")));
+                }
+
+                html.push_str(&format!("<span style=\"{style}\">"));
+                html_escape(&cluster.text, &mut html);
+                html.push_str("</span>");
             }
         }
+        html.push_str("</pre>");
+        html
     }
 
     pub fn paste_from_clipboard(&mut self, pane: &Arc<dyn Pane>, clipboard: ClipboardPasteSource) {
@@ -36,25 +118,263 @@ impl TermWindow {
             ClipboardPasteSource::PrimarySelection => Clipboard::PrimarySelection,
         };
         let future = window.get_clipboard(clipboard);
+        let image_behavior = self.config.paste_image_behavior;
+        let quoting = self.config.quote_dropped_files;
         promise::spawn::spawn(async move {
-            if let Ok(clip) = future.await {
-                window.notify(TermWindowNotif::Apply(Box::new(move |myself| {
-                    if let Some(pane) = myself
-                        .pane_state(pane_id)
-                        .overlay
-                        .as_ref()
-                        .map(|overlay| overlay.pane.clone())
-                        .or_else(|| {
-                            let mux = Mux::get();
-                            mux.get_pane(pane_id)
-                        })
-                    {
-                        pane.send_paste(&clip).ok();
+            let clip = match future.await {
+                Ok(clip) if !clip.is_empty() => ClipboardPaste::Text(clip),
+                _ if image_behavior == PasteImageBehavior::None => return,
+                _ => match window.get_clipboard_image(clipboard).await {
+                    Ok(Some(png)) => ClipboardPaste::Image(png),
+                    Ok(None) => return,
+                    Err(err) => {
+                        log::error!("while reading image from clipboard: {err:#}");
+                        return;
                     }
-                })));
-            }
+                },
+            };
+            window.notify(TermWindowNotif::Apply(Box::new(move |myself| {
+                let overlay = myself
+                    .pane_state(pane_id)
+                    .overlay
+                    .as_ref()
+                    .map(|overlay| overlay.pane.clone());
+                // Pastes into an overlay, such as the search bar,
+                // don't require confirmation
+                let may_confirm = overlay.is_none();
+                if let Some(pane) = overlay.or_else(|| {
+                    let mux = Mux::get();
+                    mux.get_pane(pane_id)
+                }) {
+                    let result = match clip {
+                        ClipboardPaste::Text(text) => myself.paste_text(&pane, text, may_confirm),
+                        ClipboardPaste::Image(png) => {
+                            paste_image(&pane, png, image_behavior, quoting)
+                        }
+                    };
+                    if let Err(err) = result {
+                        log::error!("paste failed: {err:#}");
+                    }
+                }
+            })));
         })
         .detach();
         self.maybe_scroll_to_bottom_for_input(&pane);
     }
+
+    /// Sends text from the clipboard to the pane, after applying the
+    /// `paste_transforms` and the `transform-paste` event.  If `may_confirm`
+    /// is true, the user is first asked to confirm pastes into a shell
+    /// prompt that match the `paste_confirmation` configuration.
+    fn paste_text(
+        &mut self,
+        pane: &Arc<dyn Pane>,
+        text: String,
+        may_confirm: bool,
+    ) -> anyhow::Result<()> {
+        let text = self.config.paste_transforms.apply(&text);
+        let text = match self.transform_paste(pane, text) {
+            Some(text) if !text.is_empty() => text,
+            _ => return Ok(()),
+        };
+
+        if !may_confirm
+            || !self.config.paste_confirmation.is_required_for(&text)
+            || !is_at_shell_prompt(pane)
+        {
+            return pane.send_paste(&text);
+        }
+
+        let pane_id = pane.pane_id();
+        let (overlay, future) =
+            start_overlay_pane(self, pane, move |_pane_id, term| confirm_paste(term, text));
+        self.assign_overlay_for_pane(pane_id, overlay);
+        promise::spawn::spawn(async move {
+            if let Ok(Some(text)) = future.await {
+                if let Some(pane) = Mux::get().get_pane(pane_id) {
+                    if let Err(err) = pane.send_paste(&text) {
+                        log::error!("paste failed: {err:#}");
+                    }
+                }
+            }
+        })
+        .detach();
+        Ok(())
+    }
+
+    /// Gives the `transform-paste` event the opportunity to replace
+    /// the text that is about to be pasted.  Returns None if the
+    /// handler cancelled the paste by returning false.
+    fn transform_paste(&mut self, pane: &Arc<dyn Pane>, text: String) -> Option<String> {
+        let window = GuiWin::new(self);
+
+        match config::run_immediate_with_lua_config(|lua| {
+            if let Some(lua) = lua {
+                let v = config::lua::emit_sync_callback(
+                    &*lua,
+                    (
+                        "transform-paste".to_string(),
+                        (window, MuxPane(pane.pane_id()), text.clone()),
+                    ),
+                )?;
+                match v {
+                    mlua::Value::Nil => Ok(Some(text.clone())),
+                    mlua::Value::Boolean(false) => Ok(None),
+                    v => Ok(Some(luahelper::from_lua::<String>(v)?)),
+                }
+            } else {
+                Ok(Some(text.clone()))
+            }
+        }) {
+            Ok(text) => text,
+            Err(err) => {
+                log::warn!("transform-paste: {err:#}");
+                Some(text)
+            }
+        }
+    }
+
+    /// Answers an OSC 52 clipboard query from the pane, subject to
+    /// the `osc52_read_access` configuration
+    pub fn query_clipboard_for_pane(&mut self, pane_id: PaneId, selection: ClipboardSelection) {
+        let mux = Mux::get();
+        let pane = match mux.get_pane(pane_id) {
+            Some(pane) => pane,
+            None => return,
+        };
+
+        match self.config.osc52_read_access {
+            Osc52ReadAccess::Deny => pane.send_clipboard_contents(selection, ""),
+            Osc52ReadAccess::Allow => self.send_clipboard_to_pane(pane_id, selection),
+            Osc52ReadAccess::Prompt if self.pane_state(pane_id).osc52_read_allowed => {
+                self.send_clipboard_to_pane(pane_id, selection)
+            }
+            Osc52ReadAccess::Prompt => {
+                let (overlay, future) = start_overlay_pane(self, &pane, move |_pane_id, term| {
+                    confirm_clipboard_read(term)
+                });
+                self.assign_overlay_for_pane(pane_id, overlay);
+                let window = self.window.as_ref().unwrap().clone();
+                promise::spawn::spawn(async move {
+                    let choice = future.await.unwrap_or(ClipboardReadChoice::Deny);
+                    window.notify(TermWindowNotif::Apply(Box::new(
+                        move |myself| match choice {
+                            ClipboardReadChoice::AlwaysAllow => {
+                                myself.pane_state(pane_id).osc52_read_allowed = true;
+                                myself.send_clipboard_to_pane(pane_id, selection);
+                            }
+                            ClipboardReadChoice::AllowOnce => {
+                                myself.send_clipboard_to_pane(pane_id, selection);
+                            }
+                            ClipboardReadChoice::Deny => {
+                                if let Some(pane) = Mux::get().get_pane(pane_id) {
+                                    pane.send_clipboard_contents(selection, "");
+                                }
+                            }
+                        },
+                    )));
+                })
+                .detach();
+            }
+        }
+    }
+
+    fn send_clipboard_to_pane(&self, pane_id: PaneId, selection: ClipboardSelection) {
+        let window = self.window.as_ref().unwrap().clone();
+        let future = window.get_clipboard(match selection {
+            ClipboardSelection::Clipboard => Clipboard::Clipboard,
+            ClipboardSelection::PrimarySelection => Clipboard::PrimarySelection,
+        });
+        promise::spawn::spawn(async move {
+            let text = match future.await {
+                Ok(text) => text,
+                Err(err) => {
+                    log::error!("while reading clipboard for OSC 52 query: {err:#}");
+                    String::new()
+                }
+            };
+            if let Some(pane) = Mux::get().get_pane(pane_id) {
+                pane.send_clipboard_contents(selection, &text);
+            }
+        })
+        .detach();
+    }
+}
+
+fn html_escape(text: &str, html: &mut String) {
+    for c in text.chars() {
+        match c {
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '&' => html.push_str("&amp;"),
+            '"' => html.push_str("&quot;"),
+            c => html.push(c),
+        }
+    }
+}
+
+/// Returns true if the pane appears to be waiting for input at a shell
+/// prompt.  When shell integration has marked up the prompt, the most
+/// recent semantic zone must be the prompt or its input, containing the
+/// cursor.  Otherwise, any pane that isn't using the alternate screen
+/// is assumed to be at a prompt.
+fn is_at_shell_prompt(pane: &Arc<dyn Pane>) -> bool {
+    if pane.is_alt_screen_active() {
+        return false;
+    }
+    let zones = pane.get_semantic_zones().unwrap_or_default();
+    if zones
+        .iter()
+        .all(|zone| zone.semantic_type == SemanticType::Output)
+    {
+        return true;
+    }
+    let cursor_row = pane.get_cursor_position().y;
+    match zones.last() {
+        Some(zone) => zone.semantic_type != SemanticType::Output && cursor_row >= zone.start_y,
+        None => true,
+    }
+}
+
+enum ClipboardPaste {
+    Text(String),
+    Image(Vec<u8>),
+}
+
+fn paste_image(
+    pane: &Arc<dyn Pane>,
+    png: Vec<u8>,
+    behavior: PasteImageBehavior,
+    quoting: DroppedFileQuoting,
+) -> anyhow::Result<()> {
+    match behavior {
+        PasteImageBehavior::None => Ok(()),
+        PasteImageBehavior::SaveToFile => {
+            let mut file = tempfile::Builder::new()
+                .prefix("wezterm-paste-")
+                .suffix(".png")
+                .tempfile()
+                .context("creating file for pasted image")?;
+            file.write_all(&png).context("writing pasted image")?;
+            let (_, path) = file.keep().context("keeping pasted image")?;
+            pane.send_paste(&(quoting.escape(&path.to_string_lossy()) + " "))
+        }
+        PasteImageBehavior::Display => {
+            pane.perform_actions(vec![Action::OperatingSystemCommand(Box::new(
+                OperatingSystemCommand::ITermProprietary(ITermProprietary::File(Box::new(
+                    ITermFileData {
+                        name: None,
+                        size: Some(png.len()),
+                        width: ITermDimension::Automatic,
+                        height: ITermDimension::Automatic,
+                        preserve_aspect_ratio: true,
+                        inline: true,
+                        do_not_move_cursor: false,
+                        data: png,
+                    },
+                ))),
+            ))]);
+            Ok(())
+        }
+    }
 }
@@ -0,0 +1,160 @@
+use config::EasingFunction;
+use mux::pane::PaneId;
+use std::time::{Duration, Instant};
+use window::RectF;
+
+/// Tracks the on-screen position of the text cursor so that it can
+/// glide from one cell to the next rather than jumping there.
+pub struct CursorAnimation {
+    pane_id: Option<PaneId>,
+    from: RectF,
+    to: RectF,
+    start: Instant,
+    /// Set while the region covered by the animation still needs
+    /// to be reported as damaged
+    damage_pending: bool,
+}
+
+pub struct AnimatedCursor {
+    /// Where to draw the cursor right now
+    pub rect: RectF,
+    /// Set while the cursor is away from its target cell
+    pub moving: bool,
+    /// When the next frame of the animation is due
+    pub next_due: Option<Instant>,
+    /// The area that needs to be redrawn to reflect the cursor
+    /// having moved
+    pub damage: Option<RectF>,
+}
+
+impl CursorAnimation {
+    pub fn new() -> Self {
+        Self {
+            pane_id: None,
+            from: RectF::zero(),
+            to: RectF::zero(),
+            start: Instant::now(),
+            damage_pending: false,
+        }
+    }
+
+    fn progress(&self, duration: Duration, ease: EasingFunction) -> f32 {
+        let elapsed = self.start.elapsed().as_secs_f32() / duration.as_secs_f32();
+        if elapsed >= 1. {
+            1.
+        } else {
+            ease.evaluate_at_position(elapsed).clamp(0., 1.)
+        }
+    }
+
+    fn current(&self, duration: Duration, ease: EasingFunction) -> RectF {
+        let t = self.progress(duration, ease);
+        self.from.lerp(self.to, t)
+    }
+
+    /// Compute where to draw the cursor, given that it belongs at
+    /// `target` in `pane_id`.  When `animate` is false the cursor
+    /// snaps straight to its target.
+    pub fn update(
+        &mut self,
+        pane_id: PaneId,
+        target: RectF,
+        duration: Duration,
+        ease: EasingFunction,
+        frame_interval: Duration,
+        animate: bool,
+    ) -> AnimatedCursor {
+        let now = Instant::now();
+
+        if !animate || self.pane_id != Some(pane_id) || duration.is_zero() {
+            // Make sure that wherever the cursor was last drawn
+            // gets repainted
+            let damage = if self.damage_pending {
+                Some(self.from.union(&self.to).union(&target))
+            } else {
+                None
+            };
+            self.pane_id = Some(pane_id);
+            self.from = target;
+            self.to = target;
+            self.damage_pending = false;
+            return AnimatedCursor {
+                rect: target,
+                moving: false,
+                next_due: None,
+                damage,
+            };
+        }
+
+        if target != self.to {
+            // Start moving towards the new target from wherever we
+            // are right now, so that a change of direction mid-flight
+            // remains smooth
+            self.from = self.current(duration, ease);
+            self.to = target;
+            self.start = now;
+            self.damage_pending = true;
+        }
+
+        let moving = now < self.start + duration;
+        let damage = if self.damage_pending {
+            let damage = self.from.union(&self.to);
+            self.damage_pending = moving;
+            Some(damage)
+        } else {
+            None
+        };
+
+        AnimatedCursor {
+            rect: self.current(duration, ease),
+            moving,
+            next_due: if moving {
+                Some(now + frame_interval)
+            } else {
+                None
+            },
+            damage,
+        }
+    }
+}
+
+impl crate::TermWindow {
+    /// Returns where to draw the text cursor of `pane_id`, which
+    /// belongs at `target`, and arranges for the next frame of the
+    /// animation to be painted
+    pub fn animate_text_cursor(&self, pane_id: PaneId, target: RectF) -> AnimatedCursor {
+        let duration = Duration::from_millis(self.config.cursor_animation_duration_ms);
+        let frame_interval = self.animation_frame_interval();
+
+        // Frames are only scheduled while the window is focused and the
+        // user is active, so don't start something we cannot finish
+        let animate = self.focused.is_some() && !self.user_idle;
+        // When the viewport is smooth scrolling, the cursor is already
+        // carried along with the rest of the content
+        let animate = animate && self.pane_state(pane_id).smooth_scroll.is_none();
+
+        let animated = self.cursor_animation.borrow_mut().update(
+            pane_id,
+            target,
+            duration,
+            self.config.cursor_animation_ease,
+            frame_interval,
+            animate,
+        );
+        self.update_next_frame_time(animated.next_due);
+
+        if let Some(damage) = animated.damage {
+            // Convert from the centered coordinate space used by the quads
+            let x = damage.min_x() + self.dimensions.pixel_width as f32 / 2.;
+            let y = damage.min_y() + self.dimensions.pixel_height as f32 / 2.;
+            self.dirty_rects.borrow_mut().push((
+                x.floor() as i32,
+                y.floor() as i32,
+                damage.width().ceil() as i32 + 1,
+                damage.height().ceil() as i32 + 1,
+            ));
+        }
+
+        animated
+    }
+}
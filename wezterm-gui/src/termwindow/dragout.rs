@@ -0,0 +1,176 @@
+//! Dragging selections and hyperlinks out of a pane into other applications
+use crate::termwindow::TermWindow;
+use ::window::{DragData, MouseButtons as WMB, MouseEvent, MouseEventKind as WMEK, MousePress};
+use mux::pane::{CachePolicy, Pane};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use termwiz::hyperlink::Hyperlink;
+use url::Url;
+use wezterm_term::StableRowIndex;
+
+/// How long the button must be held over a hyperlink before dragging
+/// drags the link rather than starting a selection
+const LONG_PRESS: Duration = Duration::from_millis(500);
+
+pub struct DragOutCandidate {
+    origin: ::window::Point,
+    pressed_at: Instant,
+    data: DragData,
+    /// true if the press was inside the selection, in which case
+    /// we didn't pass it on to the mouse bindings
+    swallowed_press: bool,
+}
+
+impl TermWindow {
+    /// Tracks the left button being pressed on the selection or on a
+    /// hyperlink, and starts a drag when the mouse is subsequently
+    /// moved far enough.
+    /// Returns true if the event was consumed and should not be
+    /// processed any further.
+    pub fn drag_out_mouse_event(
+        &mut self,
+        pane: &Arc<dyn Pane>,
+        event: &MouseEvent,
+        column: usize,
+        stable_row: StableRowIndex,
+    ) -> bool {
+        if !self.config.enable_drag_out || pane.is_mouse_grabbed() {
+            self.drag_out.take();
+            return false;
+        }
+
+        match &event.kind {
+            WMEK::Press(MousePress::Left) if event.modifiers.is_empty() => {
+                if self.is_in_selection(pane, column, stable_row) {
+                    let text = self.selection_text(pane);
+                    let uris = selection_to_uris(&text, pane);
+                    self.drag_out.replace(DragOutCandidate {
+                        origin: event.coords,
+                        pressed_at: Instant::now(),
+                        data: DragData { text, uris },
+                        swallowed_press: true,
+                    });
+                    return true;
+                }
+                self.drag_out = self
+                    .current_highlight
+                    .as_ref()
+                    .map(|link| DragOutCandidate {
+                        origin: event.coords,
+                        pressed_at: Instant::now(),
+                        data: hyperlink_to_drag_data(link),
+                        swallowed_press: false,
+                    });
+                false
+            }
+            WMEK::Move if event.mouse_buttons == WMB::LEFT => {
+                let candidate = match self.drag_out.as_ref() {
+                    Some(candidate) => candidate,
+                    None => return false,
+                };
+                let distance = (event.coords.x - candidate.origin.x)
+                    .abs()
+                    .max((event.coords.y - candidate.origin.y).abs());
+                if distance < self.render_metrics.cell_size.width {
+                    return candidate.swallowed_press;
+                }
+
+                let candidate = self.drag_out.take().unwrap();
+                if !candidate.swallowed_press && candidate.pressed_at.elapsed() < LONG_PRESS {
+                    // A quick drag over a link selects text as usual
+                    return false;
+                }
+                if !candidate.swallowed_press {
+                    // The press started a new selection; discard it
+                    self.selection(pane.pane_id()).clear();
+                }
+
+                if let Some(window) = self.window.as_ref() {
+                    window.start_drag(candidate.data);
+                }
+
+                // The windowing system now owns the pointer and we won't
+                // see the button being released
+                self.current_mouse_buttons
+                    .retain(|p| *p != MousePress::Left);
+                self.current_mouse_capture = None;
+                true
+            }
+            WMEK::Release(MousePress::Left) => match self.drag_out.take() {
+                Some(candidate) if candidate.swallowed_press => {
+                    // A click inside the selection without dragging
+                    // clears it, just as a regular click would
+                    self.selection(pane.pane_id()).clear();
+                    if let Some(window) = self.window.as_ref() {
+                        window.invalidate();
+                    }
+                    true
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    fn is_in_selection(&self, pane: &Arc<dyn Pane>, column: usize, row: StableRowIndex) -> bool {
+        let selection = self.selection(pane.pane_id());
+        match selection.range.as_ref().map(|r| r.normalize()) {
+            Some(range) => {
+                range.rows().contains(&row)
+                    && range
+                        .cols_for_row(row, selection.rectangular)
+                        .contains(&column)
+            }
+            None => false,
+        }
+    }
+}
+
+fn hyperlink_to_drag_data(link: &Hyperlink) -> DragData {
+    let uri = link.uri().to_string();
+    let text = Url::parse(&uri)
+        .ok()
+        .filter(|url| url.scheme() == "file")
+        .and_then(|url| url.to_file_path().ok())
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|| uri.clone());
+    DragData {
+        text,
+        uris: vec![uri],
+    }
+}
+
+/// If every line of the selection names a file that exists, relative
+/// to the cwd of the pane, returns their file:// urls
+fn selection_to_uris(text: &str, pane: &Arc<dyn Pane>) -> Vec<String> {
+    // The cwd reported via OSC 7 usually includes the hostname,
+    // which Url::to_file_path refuses to handle
+    let cwd = pane
+        .get_current_working_dir(CachePolicy::AllowStale)
+        .filter(|url| url.scheme() == "file")
+        .and_then(|mut url| {
+            url.set_host(None).ok()?;
+            url.to_file_path().ok()
+        });
+
+    let mut uris = vec![];
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let path = match &cwd {
+            Some(cwd) => cwd.join(line),
+            None => PathBuf::from(line),
+        };
+        if !path.is_absolute() || !path.exists() {
+            return vec![];
+        }
+        match Url::from_file_path(&path) {
+            Ok(url) => uris.push(url.to_string()),
+            Err(()) => return vec![],
+        }
+    }
+    uris
+}
@@ -0,0 +1,119 @@
+//! Handling files and urls that are dropped into a window
+use crate::scripting::guiwin::GuiWin;
+use crate::termwindow::TermWindow;
+use ::window::{Modifiers, Point};
+use config::{DropBehavior, DroppedFileQuoting};
+use mux::pane::{Pane, PaneId};
+use mux::Mux;
+use mux_lua::MuxPane;
+use std::rc::Rc;
+use std::sync::Arc;
+use url::Url;
+
+impl TermWindow {
+    /// Emits the `file-dropped` event for urls dropped at coords (or
+    /// on the active pane, if the position isn't known) and then, unless
+    /// a handler returned false, applies the configured drop_behavior.
+    pub fn handle_dropped_urls(&mut self, urls: Vec<Url>, coords: Option<Point>) {
+        let pane = match coords
+            .and_then(|coords| self.pane_at_pixel(coords))
+            .or_else(|| self.get_active_pane_or_overlay())
+        {
+            Some(pane) => pane,
+            None => return,
+        };
+
+        let window = GuiWin::new(self);
+        let mods = self.current_modifier_and_leds.0;
+        let behavior = self.config.drop_behavior;
+        let quoting = self.config.quote_dropped_files;
+        let pane_id = pane.pane_id();
+
+        async fn file_dropped(
+            lua: Option<Rc<mlua::Lua>>,
+            window: GuiWin,
+            pane_id: PaneId,
+            urls: Vec<Url>,
+            mods: Modifiers,
+            behavior: DropBehavior,
+            quoting: DroppedFileQuoting,
+        ) -> anyhow::Result<()> {
+            let default_action = match lua {
+                Some(lua) => {
+                    let uris: Vec<String> = urls.iter().map(|url| url.to_string()).collect();
+                    let args =
+                        lua.pack_multi((window, MuxPane(pane_id), uris, mods.to_string()))?;
+                    config::lua::emit_event(&lua, ("file-dropped".to_string(), args))
+                        .await
+                        .map_err(|e| {
+                            log::error!("while processing file-dropped event: {:#}", e);
+                            e
+                        })?
+                }
+                None => true,
+            };
+            if default_action {
+                if let Some(text) = dropped_urls_to_paste(&urls, behavior, quoting) {
+                    if let Some(pane) = Mux::get().get_pane(pane_id) {
+                        pane.send_paste(&text)?;
+                    }
+                }
+            }
+            Ok(())
+        }
+
+        promise::spawn::spawn(config::with_lua_config_on_main_thread(move |lua| {
+            file_dropped(lua, window, pane_id, urls, mods, behavior, quoting)
+        }))
+        .detach();
+    }
+
+    /// Returns the pane that is displayed at the specified pixel
+    /// coordinates within the window
+    fn pane_at_pixel(&self, coords: Point) -> Option<Arc<dyn Pane>> {
+        let border = self.get_os_border();
+        let first_line_offset = if self.show_tab_bar && !self.config.tab_bar_at_bottom {
+            self.tab_bar_pixel_height().unwrap_or(0.) as isize
+        } else {
+            0
+        } + border.top.get() as isize;
+        let (padding_left, padding_top) = self.padding_left_top();
+
+        let row = ((coords.y - padding_top as isize - first_line_offset).max(0)
+            / self.render_metrics.cell_size.height) as usize;
+        let column = ((coords.x - (padding_left + border.left.get() as f32) as isize).max(0)
+            / self.render_metrics.cell_size.width) as usize;
+
+        self.get_panes_to_render()
+            .into_iter()
+            .find(|pos| {
+                row >= pos.top
+                    && row < pos.top + pos.height
+                    && column >= pos.left
+                    && column < pos.left + pos.width
+            })
+            .map(|pos| pos.pane)
+    }
+}
+
+fn dropped_urls_to_paste(
+    urls: &[Url],
+    behavior: DropBehavior,
+    quoting: DroppedFileQuoting,
+) -> Option<String> {
+    let items: Vec<String> = match behavior {
+        DropBehavior::None => return None,
+        DropBehavior::PasteUrls => urls.iter().map(|url| url.to_string()).collect(),
+        DropBehavior::PasteQuoted => urls
+            .iter()
+            .map(|url| match url.to_file_path() {
+                Ok(path) if url.scheme() == "file" => quoting.escape(&path.to_string_lossy()),
+                _ => quoting.escape(url.as_str()),
+            })
+            .collect(),
+    };
+    if items.is_empty() {
+        return None;
+    }
+    Some(items.join(" ") + " ")
+}
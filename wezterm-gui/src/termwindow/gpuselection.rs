@@ -0,0 +1,76 @@
+//! Keeps track of the adapter that each window renders with, and why
+//! it was chosen, in a file in the runtime directory so that
+//! `wezterm show-gpus` can report on the running gui processes.
+use mux::window::WindowId as MuxWindowId;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GpuSelection {
+    /// Describes the adapter, or the renderer when not using WebGpu
+    pub adapter: String,
+    /// Why it was chosen
+    pub reason: String,
+    /// The adapters that were tried first, and why they couldn't be used
+    pub rejected: Vec<String>,
+}
+
+lazy_static::lazy_static! {
+    static ref SELECTIONS: Mutex<BTreeMap<MuxWindowId, GpuSelection>> = Mutex::new(BTreeMap::new());
+}
+
+fn selections_path(pid: u32) -> PathBuf {
+    config::RUNTIME_DIR.join(format!("gpu-selections-{pid}.json"))
+}
+
+fn save(selections: &BTreeMap<MuxWindowId, GpuSelection>) {
+    let path = selections_path(std::process::id());
+    let result = if selections.is_empty() {
+        std::fs::remove_file(&path).or_else(|err| match err.kind() {
+            std::io::ErrorKind::NotFound => Ok(()),
+            _ => Err(err.into()),
+        })
+    } else {
+        serde_json::to_vec_pretty(selections)
+            .map_err(anyhow::Error::from)
+            .and_then(|data| Ok(std::fs::write(&path, data)?))
+    };
+    if let Err(err) = result {
+        log::warn!("Unable to update {}: {err:#}", path.display());
+    }
+}
+
+pub fn record_selection(window_id: MuxWindowId, selection: GpuSelection) {
+    log::info!(
+        "window {window_id} is rendering with {}: {}",
+        selection.adapter,
+        selection.reason
+    );
+    let mut selections = SELECTIONS.lock().unwrap();
+    selections.insert(window_id, selection);
+    save(&selections);
+}
+
+pub fn forget_selection(window_id: MuxWindowId) {
+    let mut selections = SELECTIONS.lock().unwrap();
+    if selections.remove(&window_id).is_some() {
+        save(&selections);
+    }
+}
+
+/// Called when the gui window switches to a different mux window
+pub fn move_selection(from: MuxWindowId, to: MuxWindowId) {
+    let mut selections = SELECTIONS.lock().unwrap();
+    if let Some(selection) = selections.remove(&from) {
+        selections.insert(to, selection);
+        save(&selections);
+    }
+}
+
+/// Returns the selections recorded by the gui process `pid`
+pub fn load_selections(pid: u32) -> anyhow::Result<BTreeMap<MuxWindowId, GpuSelection>> {
+    let data = std::fs::read(selections_path(pid))?;
+    Ok(serde_json::from_slice(&data)?)
+}
@@ -0,0 +1,93 @@
+//! Converting our sRGB output into the color space of the display,
+//! as described by an ICC profile.
+//!
+//! The conversion is baked into a 3D lookup table that the webgpu
+//! fragment shader samples as the last step of rendering each quad.
+use anyhow::Context;
+use config::ConfigHandle;
+use moxcms::{ColorProfile, DataColorSpace, Layout, TransformOptions};
+use window::{Window, WindowOps};
+
+/// The number of entries along each axis of the lookup table
+pub const LUT_SIZE: u32 = 33;
+
+/// A lookup table mapping sRGB encoded colors to the encoding of
+/// the display, stored as RGBA8 with red varying fastest
+pub struct ColorLut {
+    pub data: Vec<u8>,
+}
+
+impl ColorLut {
+    pub fn from_icc(profile: &[u8]) -> anyhow::Result<Self> {
+        let display = ColorProfile::new_from_slice(profile).context("parsing ICC profile")?;
+        anyhow::ensure!(
+            display.color_space == DataColorSpace::Rgb,
+            "ICC profile describes a {:?} device rather than an RGB display",
+            display.color_space
+        );
+        let transform = ColorProfile::new_srgb()
+            .create_transform_f32(
+                Layout::Rgb,
+                &display,
+                Layout::Rgb,
+                TransformOptions::default(),
+            )
+            .context("creating transform from sRGB")?;
+
+        let n = LUT_SIZE as usize;
+        let scale = (n - 1) as f32;
+        let mut src = Vec::with_capacity(n * n * n * 3);
+        for b in 0..n {
+            for g in 0..n {
+                for r in 0..n {
+                    src.push(r as f32 / scale);
+                    src.push(g as f32 / scale);
+                    src.push(b as f32 / scale);
+                }
+            }
+        }
+        let mut dest = vec![0f32; src.len()];
+        transform
+            .transform(&src, &mut dest)
+            .context("transforming lookup table")?;
+
+        let mut data = Vec::with_capacity(n * n * n * 4);
+        for rgb in dest.chunks_exact(3) {
+            for &c in rgb {
+                data.push((c.clamp(0., 1.) * 255.).round() as u8);
+            }
+            data.push(0xff);
+        }
+        Ok(Self { data })
+    }
+
+    /// Loads the profile selected by the `icc_profile` and
+    /// `use_display_icc_profile` config, returning None if no
+    /// conversion should be applied
+    pub fn from_config(config: &ConfigHandle, window: &Window) -> Option<Self> {
+        let (profile, source) = if let Some(path) = &config.icc_profile {
+            match std::fs::read(path) {
+                Ok(profile) => (profile, path.display().to_string()),
+                Err(err) => {
+                    log::error!("Failed to read icc_profile {}: {err:#}", path.display());
+                    return None;
+                }
+            }
+        } else if config.use_display_icc_profile {
+            (window.get_icc_profile()?, "the display".to_string())
+        } else {
+            return None;
+        };
+
+        match Self::from_icc(&profile) {
+            Ok(lut) => {
+                log::debug!("Using ICC profile from {source}");
+                Some(lut)
+            }
+            Err(err) => {
+                log::error!("Failed to load ICC profile from {source}: {err:#}");
+                None
+            }
+        }
+    }
+}
@@ -197,12 +197,28 @@ impl super::TermWindow {
         key.encode_win32_input_mode()
     }
 
-    fn encode_kitty_input(&self, pane: &Arc<dyn Pane>, key: &KeyEvent) -> Option<String> {
+    fn encode_kitty_input(&mut self, pane: &Arc<dyn Pane>, key: &KeyEvent) -> Option<String> {
         if !self.config.enable_kitty_keyboard {
             return None;
         }
+
+        // Prefer the physical key, so that releasing it after
+        // releasing shift still matches the shifted key down
+        let held_key = key
+            .raw
+            .as_ref()
+            .and_then(|raw| raw.phys_code)
+            .map(::window::KeyCode::Physical)
+            .unwrap_or_else(|| key.key.clone());
+        let is_repeat = if key.key_is_down {
+            !self.keys_held_down.insert(held_key)
+        } else {
+            self.keys_held_down.remove(&held_key);
+            false
+        };
+
         if let KeyboardEncoding::Kitty(flags) = pane.get_keyboard_encoding() {
-            Some(key.encode_kitty(flags))
+            Some(key.encode_kitty_event(flags, is_repeat))
         } else {
             None
         }
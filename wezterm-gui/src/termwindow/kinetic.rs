@@ -0,0 +1,128 @@
+use crate::termwindow::TermWindowNotif;
+use ::window::{MouseEvent, MouseEventKind as WMEK, WindowOps};
+use smol::Timer;
+use std::time::Instant;
+
+/// How quickly a kinetic scroll slows down; the velocity decays by
+/// a factor of e every 1/FRICTION seconds
+const FRICTION: f64 = 4.0;
+/// Below this speed, in lines per second, the scroll comes to a stop
+const MIN_VELOCITY: f64 = 2.0;
+
+/// Continues scrolling after the fingers have been lifted from a
+/// touchpad, by synthesizing wheel events that slow down over time
+pub struct KineticScroll {
+    /// Distinguishes this scroll from any that it replaced, so that
+    /// their pending ticks can be ignored
+    generation: usize,
+    /// The wheel event that the synthesized events are based upon
+    template: MouseEvent,
+    /// In wheel units per second
+    velocity: (f64, f64),
+    /// The fractional wheel units that have yet to be delivered
+    remainder: (f64, f64),
+    last_tick: Instant,
+}
+
+impl crate::TermWindow {
+    pub fn start_kinetic_scroll(&mut self, horizontal: f64, vertical: f64) {
+        self.stop_kinetic_scroll();
+        if !self.config.kinetic_scrolling
+            || (horizontal.abs() < MIN_VELOCITY && vertical.abs() < MIN_VELOCITY)
+        {
+            return;
+        }
+
+        // Base the scroll on the wheel event that the fingers produced,
+        // so that it goes to the same place and respects the modifiers
+        let template = match &self.current_mouse_event {
+            Some(
+                event @ MouseEvent {
+                    kind: WMEK::VertWheel(_) | WMEK::HorzWheel(_),
+                    ..
+                },
+            ) => event.clone(),
+            _ => return,
+        };
+
+        self.kinetic_generation += 1;
+        self.kinetic_scroll.replace(KineticScroll {
+            generation: self.kinetic_generation,
+            template,
+            velocity: (horizontal, vertical),
+            remainder: (0., 0.),
+            last_tick: Instant::now(),
+        });
+        self.schedule_kinetic_scroll_tick(self.kinetic_generation);
+    }
+
+    pub fn stop_kinetic_scroll(&mut self) {
+        self.kinetic_scroll.take();
+    }
+
+    fn schedule_kinetic_scroll_tick(&self, generation: usize) {
+        let window = match self.window.clone() {
+            Some(window) => window,
+            None => return,
+        };
+        let interval = self.animation_frame_interval();
+        promise::spawn::spawn(async move {
+            Timer::after(interval).await;
+            window.notify(TermWindowNotif::Apply(Box::new(move |tw| {
+                tw.kinetic_scroll_tick(generation);
+            })));
+        })
+        .detach();
+    }
+
+    fn kinetic_scroll_tick(&mut self, generation: usize) {
+        let (events, keep_going) = {
+            let scroll = match self.kinetic_scroll.as_mut() {
+                Some(scroll) if scroll.generation == generation => scroll,
+                _ => return,
+            };
+
+            let now = Instant::now();
+            let elapsed = (now - scroll.last_tick).as_secs_f64();
+            scroll.last_tick = now;
+
+            let decay = (-FRICTION * elapsed).exp();
+            scroll.velocity.0 *= decay;
+            scroll.velocity.1 *= decay;
+
+            let x = scroll.remainder.0 + scroll.velocity.0 * elapsed;
+            let y = scroll.remainder.1 + scroll.velocity.1 * elapsed;
+            scroll.remainder = (x.fract(), y.fract());
+
+            let mut events = vec![];
+            if x.trunc() != 0. {
+                events.push(MouseEvent {
+                    kind: WMEK::HorzWheel(x.trunc() as i16),
+                    ..scroll.template.clone()
+                });
+            }
+            if y.trunc() != 0. {
+                events.push(MouseEvent {
+                    kind: WMEK::VertWheel(y.trunc() as i16),
+                    ..scroll.template.clone()
+                });
+            }
+
+            let keep_going =
+                scroll.velocity.0.abs() >= MIN_VELOCITY || scroll.velocity.1.abs() >= MIN_VELOCITY;
+            (events, keep_going)
+        };
+
+        if let Some(window) = self.window.clone() {
+            for event in events {
+                self.mouse_event_impl(event, &window);
+            }
+        }
+
+        if keep_going {
+            self.schedule_kinetic_scroll_tick(generation);
+        } else {
+            self.stop_kinetic_scroll();
+        }
+    }
+}
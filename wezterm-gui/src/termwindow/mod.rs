@@ -5,38 +5,44 @@ use crate::colorease::ColorEase;
 use crate::frontend::{front_end, try_front_end};
 use crate::inputmap::InputMap;
 use crate::overlay::{
-    confirm_close_pane, confirm_close_tab, confirm_close_window, confirm_quit_program, launcher,
-    start_overlay, start_overlay_pane, CopyModeParams, CopyOverlay, LauncherArgs, LauncherFlags,
-    QuickSelectOverlay,
+    confirm_close_pane, confirm_close_tab, confirm_close_window, confirm_close_window_in_dialog,
+    confirm_quit_program, launcher, start_overlay, start_overlay_pane, CopyModeParams, CopyOverlay,
+    LauncherArgs, LauncherFlags, QuickSelectOverlay,
 };
 use crate::resize_increment_calculator::ResizeIncrementCalculator;
 use crate::scripting::guiwin::GuiWin;
 use crate::scrollbar::*;
-use crate::selection::Selection;
+use crate::selection::{Selection, SelectionCoordinate, SelectionRange};
 use crate::shapecache::*;
 use crate::tabbar::{TabBarItem, TabBarState};
 use crate::termwindow::background::{
     load_background_image, reload_background_image, LoadedBackgroundLayer,
 };
+use crate::termwindow::capture::{CaptureDestination, CaptureTarget};
+use crate::termwindow::gpuselection::GpuSelection;
 use crate::termwindow::keyevent::{KeyTableArgs, KeyTableState};
 use crate::termwindow::modal::Modal;
+use crate::termwindow::render::minimap::{MinimapCache, MinimapHit};
 use crate::termwindow::render::paint::AllowImage;
+use crate::termwindow::render::postprocess::{PaneEffectRegion, PostProcess};
 use crate::termwindow::render::{
-    CachedLineState, LineQuadCacheKey, LineQuadCacheValue, LineToEleShapeCacheKey,
-    LineToElementShapeItem,
+    BackgroundShapeState, CachedLineState, LineQuadCacheKey, LineQuadCacheValue,
+    LineToEleShapeCacheKey, LineToElementShapeItem,
 };
+use crate::termwindow::software::SoftwareFrame;
 use crate::termwindow::webgpu::WebGpuState;
 use ::wezterm_term::input::{ClickPosition, MouseButton as TMB};
 use ::window::*;
 use anyhow::{anyhow, ensure, Context};
 use config::keyassignment::{
     Confirmation, KeyAssignment, LauncherActionArgs, PaneDirection, Pattern, PromptInputLine,
-    QuickSelectArguments, RotationDirection, SpawnCommand, SplitSize,
+    QuickSelectArguments, RotationDirection, ScreenSelector, SpawnCommand, SplitSize,
 };
 use config::window::WindowLevel;
 use config::{
     configuration, AudibleBell, ConfigHandle, Dimension, DimensionContext, FrontEndSelection,
-    GeometryOrigin, GuiPosition, TermConfig, WindowCloseConfirmation,
+    GeometryOrigin, GuiPosition, HdrMode, InlineToastEvent, PaneEffect, TermConfig,
+    WindowCloseConfirmation,
 };
 use lfucache::*;
 use mlua::{FromLua, LuaSerdeExt, UserData, UserDataFields};
@@ -53,14 +59,15 @@ use mux::{Mux, MuxNotification};
 use mux_lua::MuxPane;
 use smol::channel::Sender;
 use smol::Timer;
-use std::cell::{RefCell, RefMut};
-use std::collections::{HashMap, LinkedList};
+use std::cell::{Cell, RefCell, RefMut};
+use std::collections::{HashMap, HashSet, LinkedList};
 use std::ops::Add;
 use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
-use termwiz::hyperlink::Hyperlink;
+use termwiz::hyperlink::{rule_captures, Hyperlink, RuleAction};
+use termwiz::image::ImageData;
 use termwiz::surface::SequenceNo;
 use wezterm_dynamic::Value;
 use wezterm_font::FontConfiguration;
@@ -70,9 +77,16 @@ use wezterm_term::{Alert, Progress, StableRowIndex, TerminalConfiguration, Termi
 
 pub mod background;
 pub mod box_model;
+pub mod capture;
 pub mod charselect;
 pub mod clipboard;
+mod cursoranim;
+mod dragout;
+mod filedrop;
+pub mod gpuselection;
+pub mod icc;
 pub mod keyevent;
+mod kinetic;
 pub mod modal;
 mod mouseevent;
 pub mod palette;
@@ -81,9 +95,14 @@ mod prevcursor;
 pub mod render;
 pub mod resize;
 mod selection;
+pub mod software;
 pub mod spawn;
+pub mod taboverview;
+pub mod toast;
 pub mod webgpu;
 use crate::spawn::SpawnWhere;
+use cursoranim::CursorAnimation;
+use kinetic::KineticScroll;
 use prevcursor::PrevCursorPos;
 
 const ATLAS_SIZE: usize = 128;
@@ -107,6 +126,26 @@ pub fn get_window_class() -> String {
     WINDOW_CLASS.lock().unwrap().clone()
 }
 
+/// Splits a `path[:line[:column]]` link into its path and line number.
+/// A leading `file://` is stripped; the column is ignored as there
+/// is no portable way to pass it to an editor.
+fn parse_editor_link(uri: &str) -> (String, Option<u64>) {
+    let uri = uri.strip_prefix("file://").unwrap_or(uri);
+    let mut numbers = vec![];
+    let mut path = uri;
+    while let Some((head, tail)) = path.rsplit_once(':') {
+        match tail.parse::<u64>() {
+            Ok(n) if numbers.len() < 2 => {
+                numbers.push(n);
+                path = head;
+            }
+            _ => break,
+        }
+    }
+    // numbers were collected from the end, so the line number is last
+    (path.to_string(), numbers.last().copied())
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum MouseCapture {
     UI,
@@ -158,7 +197,15 @@ pub enum UIItemType {
     AboveScrollThumb,
     ScrollThumb,
     BelowScrollThumb,
+    /// A marker on the scrollbar; clicking it scrolls to the row
+    ScrollMarker(StableRowIndex),
+    /// The minimap; clicking or dragging scrolls to the row under the mouse
+    Minimap(MinimapHit),
     Split(PositionedSplit),
+    /// An entry in the tab overview, or its backdrop when None
+    TabOverview(Option<usize>),
+    /// A toast; clicking it dismisses it
+    Toast(usize),
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -183,6 +230,9 @@ impl UIItem {
 pub struct SemanticZoneCache {
     seqno: SequenceNo,
     zones: Vec<StableRowIndex>,
+    /// The prompt rows of the commands that reported their
+    /// exit status, along with that status
+    command_status: Vec<(StableRowIndex, i32)>,
 }
 
 pub struct OverlayState {
@@ -204,6 +254,32 @@ pub struct PaneState {
 
     bell_start: Option<Instant>,
     pub mouse_terminal_coords: Option<(ClickPosition, StableRowIndex)>,
+
+    /// While smooth scrolling, the number of rows by which the content
+    /// was offset from the viewport when the animation started, and
+    /// when that was
+    smooth_scroll: Option<(f32, Instant)>,
+
+    /// Overrides the effect applied to the pane while it is
+    /// inactive; set via `window:set_inactive_pane_effect`
+    pub inactive_effect: Option<PaneEffect>,
+
+    /// The path of the image that overrides the background of the
+    /// pane, and the image loaded from it, if it could be loaded
+    background_image: Option<(String, Option<Arc<ImageData>>)>,
+
+    /// Overrides whether ligatures are used for the pane;
+    /// set via `window:set_pane_ligatures`
+    pub ligatures: Option<bool>,
+
+    /// Whether the user chose to always allow OSC 52 clipboard
+    /// reads from this pane
+    pub osc52_read_allowed: bool,
+
+    /// The rows that were double-width lines when the pane was last
+    /// rendered, so that mouse events can be mapped to their cells
+    /// without fetching the lines again
+    double_width_rows: Vec<StableRowIndex>,
 }
 
 /// Data used when synchronously formatting pane and window titles
@@ -263,6 +339,7 @@ pub struct PaneInformation {
     pub is_active: bool,
     pub is_zoomed: bool,
     pub has_unseen_output: bool,
+    pub unseen_output_lines: usize,
     pub left: usize,
     pub top: usize,
     pub width: usize,
@@ -281,6 +358,10 @@ impl UserData for PaneInformation {
         fields.add_field_method_get("is_active", |_, this| Ok(this.is_active));
         fields.add_field_method_get("is_zoomed", |_, this| Ok(this.is_zoomed));
         fields.add_field_method_get("has_unseen_output", |_, this| Ok(this.has_unseen_output));
+        fields.add_field_method_get(
+            "unseen_output_lines",
+            |_, this| Ok(this.unseen_output_lines),
+        );
         fields.add_field_method_get("left", |_, this| Ok(this.left));
         fields.add_field_method_get("top", |_, this| Ok(this.top));
         fields.add_field_method_get("width", |_, this| Ok(this.width));
@@ -386,9 +467,15 @@ pub struct TermWindow {
     /// If is_some, the LEADER modifier is active until the specified instant.
     leader_is_down: Option<std::time::Instant>,
     dead_key_status: DeadKeyStatus,
+    /// The keys that are currently held down, so that a kitty
+    /// keyboard protocol key down can be reported as a repeat
+    keys_held_down: HashSet<::window::KeyCode>,
     key_table_state: KeyTableState,
     show_tab_bar: bool,
     show_scroll_bar: bool,
+    show_minimap: bool,
+    /// The rows of the minimap of the active pane
+    minimap_cache: Option<MinimapCache>,
     tab_bar: TabBarState,
     fancy_tab_bar: Option<box_model::ComputedElement>,
     pub right_status: String,
@@ -400,7 +487,11 @@ pub struct TermWindow {
     last_mouse_coords: (usize, i64),
     window_drag_position: Option<MouseEvent>,
     current_mouse_event: Option<MouseEvent>,
+    /// Momentum from a touchpad scroll whose fingers have been lifted
+    kinetic_scroll: Option<KineticScroll>,
+    kinetic_generation: usize,
     prev_cursor: PrevCursorPos,
+    cursor_animation: RefCell<CursorAnimation>,
     last_scroll_info: RenderableDimensions,
 
     tab_state: RefCell<HashMap<TabId, TabState>>,
@@ -408,9 +499,20 @@ pub struct TermWindow {
     semantic_zones: HashMap<PaneId, SemanticZoneCache>,
 
     window_background: Vec<LoadedBackgroundLayer>,
+    /// The color that the window system is currently drawing
+    /// beneath us, see TermWindow::solid_window_background
+    solid_background: Option<::window::color::SrgbaTuple>,
+    /// The area that we last asked the window system to blur,
+    /// see TermWindow::blur_region
+    blur_region: Option<Rect>,
+    /// The mux window of a modal dialog that we are showing;
+    /// we ignore input while it is open
+    modal_dialog: Option<MuxWindowId>,
 
     current_modifier_and_leds: (Modifiers, KeyboardLedStatus),
     current_mouse_buttons: Vec<MousePress>,
+    /// A press that may turn into dragging data out of the window
+    drag_out: Option<dragout::DragOutCandidate>,
     current_mouse_capture: Option<MouseCapture>,
 
     opengl_info: Option<String>,
@@ -425,6 +527,9 @@ pub struct TermWindow {
     shape_generation: usize,
     shape_cache: RefCell<LfuCache<ShapeCacheKey, anyhow::Result<Rc<Vec<ShapedInfo>>>>>,
     line_to_ele_shape_cache: RefCell<LfuCache<LineToEleShapeCacheKey, LineToElementShapeItem>>,
+    /// Long clusters that are being shaped on the background pool,
+    /// or that must be shaped on this thread instead
+    background_shapes: RefCell<HashMap<ShapeCacheKey, BackgroundShapeState>>,
 
     line_state_cache: RefCell<LfuCacheU64<Arc<CachedLineState>>>,
     next_line_state_id: u64,
@@ -446,11 +551,55 @@ pub struct TermWindow {
     event_states: HashMap<String, EventState>,
     pub current_event: Option<Value>,
     has_animation: RefCell<Option<Instant>>,
+    /// Set by the last render when something that asked to be
+    /// animated while the window is unfocused is on screen
+    animate_unfocused: Cell<bool>,
+    /// Set while the windowing system tells us that the user is idle;
+    /// we don't schedule animation frames during that time
+    user_idle: bool,
+    /// How often the display that we are on refreshes, if the
+    /// window backend is able to tell us
+    refresh_interval: Option<Duration>,
+    /// Captures to be taken of the next frame that we render
+    pending_captures: Vec<capture::PendingCapture>,
+    /// The tab whose thumbnail was most recently captured
+    /// for the tab overview
+    thumbnail_tab_id: Option<TabId>,
+    /// The toasts that are currently shown, oldest first
+    toasts: Vec<toast::Toast>,
+    next_toast_id: usize,
+    /// When we last emitted the input-latency-updated event
+    last_input_latency_event: Option<Instant>,
+    /// The most recent frame statistics reported by the window backend
+    pub frame_stats: Option<FrameStats>,
+    /// Whether to draw the render statistics overlay
+    show_debug_hud: bool,
+    /// Counts frames painted with debug_visualize_damage, to pick
+    /// the tint for each frame
+    damage_visualization_frame: usize,
     /// We use this to attempt to do something reasonable
     /// if we run out of texture space
     allow_images: AllowImage,
     /// Dirty rectangles for Wayland damage tracking (x, y, width, height in pixels)
     dirty_rects: RefCell<Vec<(i32, i32, i32, i32)>>,
+    /// Set when the window becomes visible after we stopped painting
+    /// it, so that the next frame damages all of the window
+    full_damage_pending: bool,
+    /// The configuration used to shape the lines of panes
+    /// in which ligatures are turned off
+    ligature_free_config: Option<ConfigHandle>,
+    /// The compiled post_process_shader, or None if it failed to load.
+    /// This is None until the first frame after the config changes.
+    post_process: Option<Option<Rc<PostProcess>>>,
+    /// The compiled built-in shader that applies the effects of
+    /// inactive panes, or None if it isn't supported by the front end.
+    /// This is None until the first frame after the window is created.
+    pane_effects: Option<Option<Rc<PostProcess>>>,
+    /// The regions of the panes painted in this frame that have
+    /// an effect applied while compositing
+    pane_effect_regions: RefCell<Vec<PaneEffectRegion>>,
+    /// Where update_text_cursor last placed the cursor, in pixels
+    text_cursor_rect: Option<Rect>,
     scheduled_animation: RefCell<Option<Instant>>,
 
     created: Instant,
@@ -464,6 +613,15 @@ pub struct TermWindow {
 
     gl: Option<Rc<glium::backend::Context>>,
     webgpu: Option<Rc<WebGpuState>>,
+    /// Set when we couldn't use the gpu and draw on the cpu instead
+    software: bool,
+    /// How our buffers are rotated to match the output that the
+    /// window is on
+    buffer_transform: BufferTransform,
+    /// Whether the window is on an HDR output
+    hdr_output: bool,
+    /// Whether we are rendering scRGB for an HDR output
+    hdr: bool,
     config_subscription: Option<config::ConfigSubscription>,
 }
 
@@ -480,6 +638,49 @@ impl TermWindow {
         }
     }
 
+    /// Switches between sRGB and scRGB rendering according to the
+    /// `hdr` config and the output that the window is on.
+    /// Only the webgpu front end can render scRGB.
+    fn update_hdr(&mut self) {
+        let (window, webgpu) = match (self.window.as_ref(), self.webgpu.as_ref()) {
+            (Some(window), Some(webgpu)) => (window, webgpu),
+            _ => return,
+        };
+        let hdr = window.supports_hdr_content()
+            && match self.config.hdr {
+                HdrMode::Off => false,
+                HdrMode::On => true,
+                HdrMode::Auto => self.hdr_output,
+            };
+        if hdr == self.hdr {
+            return;
+        }
+        if !webgpu.set_hdr(hdr) {
+            log::warn!("hdr: the surface doesn't support a half-float format");
+            return;
+        }
+        log::debug!("hdr rendering is now {hdr}");
+        self.hdr = hdr;
+        window.set_hdr_content(hdr);
+        window.invalidate();
+    }
+
+    /// Loads the ICC profile selected by the config, so that the
+    /// webgpu front end can convert its output to the display
+    /// color space
+    fn update_color_profile(&mut self) {
+        let (window, webgpu) = match (self.window.as_ref(), self.webgpu.as_ref()) {
+            (Some(window), Some(webgpu)) => (window, webgpu),
+            _ => return,
+        };
+        let lut = icc::ColorLut::from_config(&self.config, window);
+        if lut.is_none() && !webgpu.has_color_lut.get() {
+            return;
+        }
+        webgpu.set_color_lut(lut.as_ref());
+        window.invalidate();
+    }
+
     fn close_requested(&mut self, window: &Window) {
         let mux = Mux::get();
         match self.config.window_close_confirmation {
@@ -512,6 +713,23 @@ impl TermWindow {
                     return;
                 }
                 let window = self.window.clone().unwrap();
+
+                if Connection::get().map_or(false, |conn| conn.supports_modal_dialogs()) {
+                    match confirm_close_window_in_dialog(
+                        mux_window_id,
+                        window.clone(),
+                        self.config.clone(),
+                    ) {
+                        Ok(dialog_window_id) => {
+                            self.modal_dialog.replace(dialog_window_id);
+                            return;
+                        }
+                        Err(err) => {
+                            log::error!("Failed to show close confirmation dialog: {err:#}");
+                        }
+                    }
+                }
+
                 let (overlay, future) = start_overlay(self, &tab, move |tab_id, term| {
                     confirm_close_window(term, mux_window_id, window, tab_id)
                 });
@@ -524,6 +742,11 @@ impl TermWindow {
         }
     }
 
+    /// Called once the modal dialog that we opened has been dismissed
+    pub fn modal_dialog_closed(&mut self) {
+        self.modal_dialog.take();
+    }
+
     fn focus_changed(&mut self, focused: bool, window: &Window) {
         log::trace!("Setting focus to {:?}", focused);
         self.focused = if focused { Some(Instant::now()) } else { None };
@@ -535,6 +758,8 @@ impl TermWindow {
             self.current_mouse_buttons.clear();
             self.current_mouse_capture = None;
             self.is_click_to_focus_window = false;
+            // We won't see the releases of keys that are held now
+            self.keys_held_down.clear();
 
             for state in self.pane_state.borrow_mut().values_mut() {
                 state.mouse_terminal_coords.take();
@@ -557,6 +782,8 @@ impl TermWindow {
 
     fn created(&mut self, ctx: RenderContext) -> anyhow::Result<()> {
         self.render_state = None;
+        self.post_process = None;
+        self.pane_effects = None;
 
         let render_info = ctx.renderer_info();
         self.opengl_info.replace(render_info.clone());
@@ -568,6 +795,10 @@ impl TermWindow {
                     render_info,
                     config::wezterm_version(),
                 );
+                render_state
+                    .glyph_cache
+                    .borrow_mut()
+                    .set_refresh_interval(self.refresh_interval);
                 self.render_state.replace(render_state);
             }
             Err(err) => {
@@ -690,8 +921,15 @@ impl TermWindow {
             os_parameters: None,
             gl: None,
             webgpu: None,
+            software: false,
+            buffer_transform: BufferTransform::Normal,
+            hdr_output: false,
+            hdr: false,
             window: None,
             window_background,
+            solid_background: None,
+            blur_region: None,
+            modal_dialog: None,
             config: config.clone(),
             config_overrides: wezterm_dynamic::Value::default(),
             palette: None,
@@ -710,8 +948,11 @@ impl TermWindow {
             input_map: InputMap::new(&config),
             leader_is_down: None,
             dead_key_status: DeadKeyStatus::None,
+            keys_held_down: HashSet::new(),
             show_tab_bar,
             show_scroll_bar: config.enable_scroll_bar,
+            show_minimap: config.enable_minimap,
+            minimap_cache: None,
             tab_bar: TabBarState::default(),
             fancy_tab_bar: None,
             right_status: String::new(),
@@ -719,12 +960,16 @@ impl TermWindow {
             last_mouse_coords: (0, -1),
             window_drag_position: None,
             current_mouse_event: None,
+            kinetic_scroll: None,
+            kinetic_generation: 0,
             current_modifier_and_leds: Default::default(),
             prev_cursor: PrevCursorPos::new(),
+            cursor_animation: RefCell::new(CursorAnimation::new()),
             last_scroll_info: RenderableDimensions::default(),
             tab_state: RefCell::new(HashMap::new()),
             pane_state: RefCell::new(HashMap::new()),
             current_mouse_buttons: vec![],
+            drag_out: None,
             current_mouse_capture: None,
             last_mouse_click: None,
             current_highlight: None,
@@ -755,6 +1000,7 @@ impl TermWindow {
                 |config| config.line_to_ele_shape_cache_size,
                 &config,
             )),
+            background_shapes: RefCell::new(HashMap::new()),
             last_status_call: Instant::now(),
             cursor_blink_state: RefCell::new(ColorEase::new(
                 config.cursor_blink_rate,
@@ -780,9 +1026,26 @@ impl TermWindow {
             event_states: HashMap::new(),
             current_event: None,
             has_animation: RefCell::new(None),
+            animate_unfocused: Cell::new(false),
+            user_idle: false,
+            refresh_interval: None,
+            pending_captures: vec![],
+            thumbnail_tab_id: None,
+            toasts: vec![],
+            next_toast_id: 0,
+            last_input_latency_event: None,
+            frame_stats: None,
+            show_debug_hud: false,
+            damage_visualization_frame: 0,
             scheduled_animation: RefCell::new(None),
             allow_images: AllowImage::Yes,
             dirty_rects: RefCell::new(Vec::new()),
+            full_damage_pending: false,
+            ligature_free_config: None,
+            post_process: None,
+            pane_effects: None,
+            pane_effect_regions: RefCell::new(vec![]),
+            text_cursor_rect: None,
             semantic_zones: HashMap::new(),
             ui_items: vec![],
             dragging: None,
@@ -835,6 +1098,10 @@ impl TermWindow {
         .await?;
         tw.borrow_mut().window.replace(window.clone());
 
+        if let Some(parent) = front_end().take_dialog_parent(mux_window_id) {
+            window.set_modal_parent(&parent);
+        }
+
         Self::apply_icon(&window)?;
 
         let config_subscription = config::subscribe_to_config_reload({
@@ -847,19 +1114,32 @@ impl TermWindow {
             }
         });
 
+        let mut software = None;
         let gl = match config.front_end {
             FrontEndSelection::WebGpu => None,
-            _ => Some(window.enable_opengl().await?),
+            _ => match window.enable_opengl().await {
+                Ok(gl) => Some(gl),
+                Err(err) => {
+                    software.replace(Self::enable_software_fallback(&window, err).await?);
+                    None
+                }
+            },
+        };
+        let webgpu = match config.front_end {
+            FrontEndSelection::WebGpu => {
+                match WebGpuState::new(&window, dimensions, &config).await {
+                    Ok(state) => Some(Rc::new(state)),
+                    Err(err) => {
+                        software.replace(Self::enable_software_fallback(&window, err).await?);
+                        None
+                    }
+                }
+            }
+            _ => None,
         };
 
         {
             let mut myself = tw.borrow_mut();
-            let webgpu = match config.front_end {
-                FrontEndSelection::WebGpu => Some(Rc::new(
-                    WebGpuState::new(&window, dimensions, &config).await?,
-                )),
-                _ => None,
-            };
             myself.config_subscription.replace(config_subscription);
             if config.use_resize_increments {
                 window.set_resize_increments(
@@ -877,15 +1157,30 @@ impl TermWindow {
                 );
             }
 
+            let mut selection = GpuSelection::default();
             if let Some(gl) = gl {
                 myself.gl.replace(Rc::clone(&gl));
                 myself.created(RenderContext::Glium(Rc::clone(&gl)))?;
+                selection.reason = format!("front_end={:?}", config.front_end);
             }
             if let Some(webgpu) = webgpu {
                 myself.webgpu.replace(Rc::clone(&webgpu));
                 myself.created(RenderContext::WebGpu(Rc::clone(&webgpu)))?;
-            }
+                selection = webgpu.selection.clone();
+            }
+            if let Some(reason) = software {
+                myself.software = true;
+                myself.created(RenderContext::Software)?;
+                selection.reason = format!("unable to use the GPU: {reason}");
+            }
+            selection.adapter = myself
+                .opengl_info
+                .clone()
+                .unwrap_or_else(|| "Unknown".to_string());
+            gpuselection::record_selection(mux_window_id, selection);
             myself.load_os_parameters();
+            myself.update_hdr();
+            myself.update_color_profile();
             window.show();
             myself.subscribe_to_pane_updates();
             myself.emit_window_event("window-config-reloaded", None);
@@ -898,12 +1193,53 @@ impl TermWindow {
         Ok(())
     }
 
+    /// Called when we were unable to initialize the gpu; rather than
+    /// giving up, switch to drawing on the cpu if the window supports it,
+    /// returning the reason for doing so.
+    /// If it doesn't, returns the original error.
+    async fn enable_software_fallback(
+        window: &Window,
+        err: anyhow::Error,
+    ) -> anyhow::Result<String> {
+        match window.enable_software_rendering().await {
+            Ok(()) => {
+                log::error!(
+                    "Unable to use the GPU ({err:#}); falling back to \
+                     software rendering, which will be slower"
+                );
+                Ok(format!("{err:#}"))
+            }
+            Err(software_err) => {
+                log::debug!("software rendering is unavailable: {software_err:#}");
+                Err(err)
+            }
+        }
+    }
+
     fn dispatch_window_event(
         &mut self,
         event: WindowEvent,
         window: &Window,
     ) -> anyhow::Result<bool> {
         log::debug!("{event:?}");
+
+        // The modal dialog has the user's attention until it is
+        // dismissed, so we ignore their input until then
+        if self.modal_dialog.is_some() {
+            match &event {
+                WindowEvent::CloseRequested
+                | WindowEvent::PerformKeyAssignment(_)
+                | WindowEvent::MouseEvent(_)
+                | WindowEvent::RawKeyEvent(_)
+                | WindowEvent::KeyEvent(_)
+                | WindowEvent::DroppedString(_)
+                | WindowEvent::DroppedUrl(_)
+                | WindowEvent::DroppedUrlsAt { .. }
+                | WindowEvent::DroppedFile(_) => return Ok(true),
+                _ => {}
+            }
+        }
+
         match event {
             WindowEvent::Destroyed => {
                 // Ensure that we cancel any overlays we had running, so
@@ -946,9 +1282,28 @@ impl TermWindow {
                 Ok(true)
             }
             WindowEvent::MouseEvent(event) => {
+                if matches!(
+                    event.kind,
+                    MouseEventKind::VertWheel(_)
+                        | MouseEventKind::HorzWheel(_)
+                        | MouseEventKind::Press(_)
+                ) {
+                    self.stop_kinetic_scroll();
+                }
                 self.mouse_event_impl(event, window);
                 Ok(true)
             }
+            WindowEvent::KineticScroll {
+                horizontal,
+                vertical,
+            } => {
+                self.start_kinetic_scroll(horizontal, vertical);
+                Ok(true)
+            }
+            WindowEvent::KineticScrollStop => {
+                self.stop_kinetic_scroll();
+                Ok(true)
+            }
             WindowEvent::MouseLeave => {
                 self.mouse_leave_impl(window);
                 Ok(true)
@@ -974,6 +1329,69 @@ impl TermWindow {
                 self.apply_pending_scale_changes();
                 Ok(true)
             }
+            WindowEvent::UserIdleChanged(idle) => {
+                self.user_idle = idle;
+                if !idle {
+                    // Resume anything that we stopped animating
+                    window.invalidate();
+                }
+                Ok(true)
+            }
+            WindowEvent::InputLatencyMeasured(_latency) => {
+                // The measurement itself has already been recorded
+                // to the metrics; just let the config know that the
+                // numbers changed, at most once per second
+                let due = self
+                    .last_input_latency_event
+                    .map(|last| last.elapsed() >= Duration::from_secs(1))
+                    .unwrap_or(true);
+                if due {
+                    self.last_input_latency_event.replace(Instant::now());
+                    self.emit_window_event("input-latency-updated", None);
+                }
+                Ok(true)
+            }
+            WindowEvent::FrameStatsUpdated(stats) => {
+                self.frame_stats.replace(stats);
+                self.emit_window_event("frame-stats", None);
+                if self.show_debug_hud {
+                    window.invalidate();
+                }
+                Ok(true)
+            }
+            WindowEvent::RefreshIntervalChanged(interval) => {
+                self.refresh_interval.replace(interval);
+                if let Some(render_state) = self.render_state.as_ref() {
+                    render_state
+                        .glyph_cache
+                        .borrow_mut()
+                        .set_refresh_interval(Some(interval));
+                }
+                Ok(true)
+            }
+            WindowEvent::FocusedToplevelChanged => {
+                self.emit_window_event("focused-toplevel-changed", None);
+                Ok(true)
+            }
+            WindowEvent::OutputConfigurationChanged => {
+                self.emit_window_event("output-configuration-changed", None);
+                Ok(true)
+            }
+            WindowEvent::PreferredBufferTransformChanged(transform) => {
+                // The webgpu surface is always presented untransformed;
+                // leave it to the compositor in that case
+                if self.webgpu.is_none() && transform != self.buffer_transform {
+                    self.buffer_transform = transform;
+                    window.set_buffer_transform(transform);
+                    window.invalidate();
+                }
+                Ok(true)
+            }
+            WindowEvent::HdrOutputChanged(hdr_output) => {
+                self.hdr_output = hdr_output;
+                self.update_hdr();
+                Ok(true)
+            }
             WindowEvent::AdviseModifiersLedStatus(modifiers, leds) => {
                 self.current_modifier_and_leds = (modifiers, leds);
                 self.update_title();
@@ -1002,7 +1420,11 @@ impl TermWindow {
                 Ok(true)
             }
             WindowEvent::NeedRepaint => {
-                if self.resizes_pending > 0 {
+                if !self.window_state.is_visible() {
+                    // Nobody would see it; we repaint everything
+                    // once the window becomes visible again
+                    Ok(true)
+                } else if self.resizes_pending > 0 {
                     self.is_repaint_pending = true;
                     Ok(true)
                 } else if self.webgpu.is_some() {
@@ -1027,35 +1449,19 @@ impl TermWindow {
                 Ok(true)
             }
             WindowEvent::DroppedUrl(urls) => {
-                let pane = match self.get_active_pane_or_overlay() {
-                    Some(pane) => pane,
-                    None => return Ok(true),
-                };
-                let urls = urls
-                    .iter()
-                    .map(|url| self.config.quote_dropped_files.escape(&url.to_string()))
-                    .collect::<Vec<_>>()
-                    .join(" ")
-                    + " ";
-                pane.send_paste(urls.as_str())?;
+                self.handle_dropped_urls(urls, None);
+                Ok(true)
+            }
+            WindowEvent::DroppedUrlsAt { urls, coords } => {
+                self.handle_dropped_urls(urls, Some(coords));
                 Ok(true)
             }
             WindowEvent::DroppedFile(paths) => {
-                let pane = match self.get_active_pane_or_overlay() {
-                    Some(pane) => pane,
-                    None => return Ok(true),
-                };
-                let paths = paths
+                let urls = paths
                     .iter()
-                    .map(|path| {
-                        self.config
-                            .quote_dropped_files
-                            .escape(&path.to_string_lossy())
-                    })
-                    .collect::<Vec<_>>()
-                    .join(" ")
-                    + " ";
-                pane.send_paste(&paths)?;
+                    .filter_map(|path| url::Url::from_file_path(path).ok())
+                    .collect();
+                self.handle_dropped_urls(urls, None);
                 Ok(true)
             }
             WindowEvent::DraggedFile(_) => Ok(true),
@@ -1063,6 +1469,10 @@ impl TermWindow {
     }
 
     fn do_paint(&mut self, window: &Window) -> bool {
+        if self.software {
+            return self.do_paint_software(window);
+        }
+
         let gl = match self.gl.as_ref() {
             Some(gl) => gl,
             None => return false,
@@ -1075,19 +1485,28 @@ impl TermWindow {
             return false;
         }
 
-        let mut frame = glium::Frame::new(
-            Rc::clone(&gl),
-            (
-                self.dimensions.pixel_width as u32,
-                self.dimensions.pixel_height as u32,
-            ),
-        );
+        let (width, height) = self
+            .buffer_transform
+            .buffer_size(self.dimensions.pixel_width, self.dimensions.pixel_height);
+        let mut frame = glium::Frame::new(Rc::clone(&gl), (width as u32, height as u32));
         self.paint_impl(&mut RenderFrame::Glium(&mut frame));
         // Get dirty rectangles for Wayland damage tracking
         let dirty_rects = self.dirty_rects.borrow();
         window.finish_frame(frame, &dirty_rects).is_ok()
     }
 
+    fn do_paint_software(&mut self, window: &Window) -> bool {
+        let mut frame =
+            SoftwareFrame::new(self.dimensions.pixel_width, self.dimensions.pixel_height);
+        self.paint_impl(&mut RenderFrame::Software(&mut frame));
+        let (width, height) = self
+            .buffer_transform
+            .buffer_size(frame.width(), frame.height());
+        window
+            .present_software_frame(width, height, frame.into_argb(self.buffer_transform))
+            .is_ok()
+    }
+
     fn do_paint_webgpu(&mut self) -> anyhow::Result<bool> {
         self.webgpu.as_mut().unwrap().resize(self.dimensions);
         match self.do_paint_webgpu_impl() {
@@ -1246,6 +1665,7 @@ impl TermWindow {
 
                     log::trace!("Ding! (this is the bell) in pane {}", pane_id);
                     self.emit_window_event("bell", Some(pane_id));
+                    self.show_background_bell_toast(pane_id);
 
                     let mut per_pane = self.pane_state(pane_id);
                     per_pane.bell_start.replace(Instant::now());
@@ -1302,6 +1722,27 @@ impl TermWindow {
                 MuxNotification::SaveToDownloads { .. } => {
                     // Handled by frontend
                 }
+                MuxNotification::CaptureImage {
+                    pane_id,
+                    whole_window,
+                    result,
+                } => {
+                    if !self.window_contains_pane(pane_id) {
+                        return Ok(());
+                    }
+                    let target = if whole_window {
+                        CaptureTarget::Window
+                    } else {
+                        CaptureTarget::Pane(pane_id)
+                    };
+                    self.request_capture(target, CaptureDestination::Reply(result));
+                }
+                MuxNotification::QueryClipboard { pane_id, selection } => {
+                    if !self.window_contains_pane(pane_id) {
+                        return Ok(());
+                    }
+                    self.query_clipboard_for_pane(pane_id, selection);
+                }
                 MuxNotification::PaneFocused(_) => {
                     // Also handled by clientpane
                     self.update_title_post_status();
@@ -1319,6 +1760,7 @@ impl TermWindow {
                 | MuxNotification::WindowWorkspaceChanged(_)
                 | MuxNotification::ActiveWorkspaceChanged(_)
                 | MuxNotification::Empty
+                | MuxNotification::ActivateWindow { .. }
                 | MuxNotification::WindowCreated(_) => {}
             },
             TermWindowNotif::EmitStatusUpdate => {
@@ -1338,6 +1780,7 @@ impl TermWindow {
                 func(self);
             }
             TermWindowNotif::SwitchToMuxWindow(mux_window_id) => {
+                gpuselection::move_selection(self.mux_window_id, mux_window_id);
                 self.mux_window_id = mux_window_id;
                 *self.mux_window_id_for_subscriptions.lock().unwrap() = mux_window_id;
 
@@ -1399,15 +1842,21 @@ impl TermWindow {
     }
 
     fn apply_icon(window: &Window) -> anyhow::Result<()> {
-        let image = image::load_from_memory(ICON_DATA)?.into_rgba8();
+        window.set_icon(Self::decode_icon(ICON_DATA)?);
+        Ok(())
+    }
+
+    /// Decode an encoded image, such as a PNG, into a form
+    /// that is suitable for use as a window icon
+    pub fn decode_icon(data: &[u8]) -> anyhow::Result<Image> {
+        let image = image::load_from_memory(data)?.into_rgba8();
         let (width, height) = image.dimensions();
-        window.set_icon(Image::with_rgba32(
+        Ok(Image::with_rgba32(
             width as usize,
             height as usize,
             width as usize * 4,
             image.as_raw(),
-        ));
-        Ok(())
+        ))
     }
 
     fn schedule_status_update(&self) {
@@ -1442,6 +1891,9 @@ impl TermWindow {
             if let Some(ref win) = self.window {
                 win.invalidate();
             }
+        } else if self.config.show_tab_unseen_output_badge && self.window_contains_pane(pane_id) {
+            // Keep the count of unseen lines in the tab bar up to date
+            self.update_title_impl();
         }
     }
 
@@ -1520,12 +1972,23 @@ impl TermWindow {
                     return true;
                 }
             }
+            MuxNotification::CaptureImage { pane_id, .. }
+            | MuxNotification::QueryClipboard { pane_id, .. } => {
+                let mux = Mux::get();
+                match mux.resolve_pane_id(pane_id) {
+                    Some((_domain_id, window_id, _tab_id)) if window_id == mux_window_id => {
+                        // fall through
+                    }
+                    _ => return true,
+                }
+            }
             MuxNotification::Alert {
                 alert: Alert::ToastNotification { .. },
                 ..
             }
             | MuxNotification::AssignClipboard { .. }
             | MuxNotification::SaveToDownloads { .. }
+            | MuxNotification::ActivateWindow { .. }
             | MuxNotification::WindowCreated(_)
             | MuxNotification::ActiveWorkspaceChanged(_)
             | MuxNotification::WorkspaceRenamed { .. }
@@ -1795,6 +2258,8 @@ impl TermWindow {
         );
 
         self.show_scroll_bar = config.enable_scroll_bar;
+        self.show_minimap = config.enable_minimap;
+        self.minimap_cache.take();
         self.shape_generation += 1;
         {
             let mut shape_cache = self.shape_cache.borrow_mut();
@@ -1812,6 +2277,7 @@ impl TermWindow {
         self.input_map = InputMap::new(&config);
         self.leader_is_down = None;
         self.render_state.as_mut().map(|rs| rs.config_changed());
+        self.post_process = None;
         let dimensions = self.dimensions;
 
         if let Err(err) = self.fonts.config_changed(&config) {
@@ -1819,21 +2285,24 @@ impl TermWindow {
         }
 
         if let Some(window) = mux.get_window(self.mux_window_id) {
-            let term_config: Arc<dyn TerminalConfiguration> =
-                Arc::new(TermConfig::with_config(config.clone()));
+            // Each pane gets its own TermConfig, as the mux server
+            // may assign a palette to an individual pane via its config
+            let term_config = || -> Arc<dyn TerminalConfiguration> {
+                Arc::new(TermConfig::with_config(config.clone()))
+            };
             for tab in window.iter() {
                 for pane in tab.iter_panes_ignoring_zoom() {
-                    pane.pane.set_config(Arc::clone(&term_config));
+                    pane.pane.set_config(term_config());
                 }
             }
             for state in self.pane_state.borrow().values() {
                 if let Some(overlay) = &state.overlay {
-                    overlay.pane.set_config(Arc::clone(&term_config));
+                    overlay.pane.set_config(term_config());
                 }
             }
             for state in self.tab_state.borrow().values() {
                 if let Some(overlay) = &state.overlay {
-                    overlay.pane.set_config(Arc::clone(&term_config));
+                    overlay.pane.set_config(term_config());
                 }
             }
         }
@@ -1845,6 +2314,8 @@ impl TermWindow {
             window.config_did_change(&config);
             window.invalidate();
         }
+        self.update_hdr();
+        self.update_color_profile();
 
         // Do this after we've potentially adjusted scaling based on config/padding
         // and window size
@@ -2019,6 +2490,7 @@ impl TermWindow {
             &self.config,
             &self.left_status,
             &self.right_status,
+            self.window_state,
         );
         if new_tab_bar != self.tab_bar {
             self.tab_bar = new_tab_bar;
@@ -2130,26 +2602,34 @@ impl TermWindow {
     fn update_text_cursor(&mut self, pos: &PositionedPane) {
         if let Some(win) = self.window.as_ref() {
             let cursor = pos.pane.get_cursor_position();
-            let top = pos.pane.get_dimensions().physical_top;
+            // Take scrollback into account, so that the input method
+            // follows the cursor when the viewport is scrolled
+            let top = self
+                .get_viewport(pos.pane.pane_id())
+                .unwrap_or_else(|| pos.pane.get_dimensions().physical_top);
             let tab_bar_height = if self.show_tab_bar && !self.config.tab_bar_at_bottom {
                 self.tab_bar_pixel_height().unwrap()
             } else {
                 0.0
             };
             let (padding_left, padding_top) = self.padding_left_top();
+            let border = self.get_os_border();
 
             let r = Rect::new(
                 Point::new(
                     (((cursor.x + pos.left) as isize).max(0) * self.render_metrics.cell_size.width)
-                        .add(padding_left as isize),
+                        .add(padding_left as isize)
+                        .add(border.left.get() as isize),
                     ((cursor.y + pos.top as isize - top).max(0)
                         * self.render_metrics.cell_size.height)
                         .add(tab_bar_height as isize)
-                        .add(padding_top as isize),
+                        .add(padding_top as isize)
+                        .add(border.top.get() as isize),
                 ),
                 self.render_metrics.cell_size,
             );
             win.set_text_cursor_position(r);
+            self.text_cursor_rect = Some(r);
         }
     }
 
@@ -2377,9 +2857,18 @@ impl TermWindow {
 
         let opengl_info = self.opengl_info.as_deref().unwrap_or("Unknown").to_string();
         let connection_info = self.connection_name.clone();
+        let scanout_info = Connection::get()
+            .and_then(|conn| conn.scanout_info().ok())
+            .map(|info| info.to_string());
 
         let (overlay, future) = start_overlay(self, &tab, move |_tab_id, term| {
-            crate::overlay::show_debug_overlay(term, gui_win, opengl_info, connection_info)
+            crate::overlay::show_debug_overlay(
+                term,
+                gui_win,
+                opengl_info,
+                connection_info,
+                scanout_info,
+            )
         });
         self.assign_overlay(tab.tab_id(), overlay);
         promise::spawn::spawn(future).detach();
@@ -2486,6 +2975,16 @@ impl TermWindow {
 
     /// Returns the Prompt semantic zones
     fn get_semantic_prompt_zones(&mut self, pane: &Arc<dyn Pane>) -> &[StableRowIndex] {
+        &self.semantic_zone_cache(pane).zones
+    }
+
+    /// Returns the prompt rows and exit status of the commands
+    /// that have completed in the pane
+    fn get_command_status_rows(&mut self, pane: &Arc<dyn Pane>) -> &[(StableRowIndex, i32)] {
+        &self.semantic_zone_cache(pane).command_status
+    }
+
+    fn semantic_zone_cache(&mut self, pane: &Arc<dyn Pane>) -> &SemanticZoneCache {
         let cache = self
             .semantic_zones
             .entry(pane.pane_id())
@@ -2510,9 +3009,14 @@ impl TermWindow {
             // <https://github.com/wezterm/wezterm/issues/1121>
             zones.dedup();
             cache.zones = zones;
+            cache.command_status = pane
+                .get_command_history()
+                .into_iter()
+                .filter_map(|cmd| Some((cmd.prompt_row, cmd.status?)))
+                .collect();
             cache.seqno = seqno;
         }
-        &cache.zones
+        cache
     }
 
     fn scroll_to_prompt(&mut self, amount: isize, pane: &Arc<dyn Pane>) -> anyhow::Result<()> {
@@ -2538,13 +3042,81 @@ impl TermWindow {
         Ok(())
     }
 
+    /// Returns the range spanned by the most recent semantic output
+    /// zone of the pane, which holds the output of the last command
+    /// that was run in it
+    fn last_command_output_range(&self, pane: &Arc<dyn Pane>) -> Option<SelectionRange> {
+        let zones = pane.get_semantic_zones().ok()?;
+        let zone = zones
+            .iter()
+            .rev()
+            .find(|zone| zone.semantic_type == wezterm_term::SemanticType::Output)?;
+        Some(SelectionRange {
+            start: SelectionCoordinate::x_y(zone.start_x, zone.start_y),
+            end: SelectionCoordinate::x_y(zone.end_x, zone.end_y),
+        })
+    }
+
+    fn select_last_command_output(&mut self, pane: &Arc<dyn Pane>) {
+        let range = match self.last_command_output_range(pane) {
+            Some(range) => range,
+            None => return,
+        };
+
+        {
+            let mut selection = self.selection(pane.pane_id());
+            selection.origin = Some(range.start);
+            selection.range = Some(range);
+            selection.rectangular = false;
+            selection.seqno = pane.get_current_seqno();
+        }
+
+        // Bring the start of the output into view
+        let dims = pane.get_dimensions();
+        let top = self
+            .get_viewport(pane.pane_id())
+            .unwrap_or(dims.physical_top);
+        if range.start.y < top || range.start.y >= top + dims.viewport_rows as StableRowIndex {
+            self.set_viewport(pane.pane_id(), Some(range.start.y), dims);
+        }
+
+        if let Some(win) = self.window.as_ref() {
+            win.invalidate();
+        }
+    }
+
+    fn show_last_command_output(&mut self, pane: &Arc<dyn Pane>) {
+        let range = match self.last_command_output_range(pane) {
+            Some(range) => range.normalize(),
+            None => return,
+        };
+
+        let mut lines = vec![];
+        let (first_row, phys) = pane.get_lines(range.rows());
+        for (idx, line) in phys.into_iter().enumerate() {
+            let row = first_row + idx as StableRowIndex;
+            lines.push(line.columns_as_line(range.cols_for_row(row, false)));
+        }
+
+        let (overlay, future) = start_overlay_pane(self, pane, move |_pane_id, term| {
+            crate::overlay::pager::show_pager_overlay(
+                term,
+                "Output of the last command".to_string(),
+                lines,
+            )
+        });
+        self.assign_overlay_for_pane(pane.pane_id(), overlay);
+        promise::spawn::spawn(future).detach();
+    }
+
     fn scroll_by_page(&mut self, amount: f64, pane: &Arc<dyn Pane>) -> anyhow::Result<()> {
         let dims = pane.get_dimensions();
-        let position = self
+        let prior = self
             .get_viewport(pane.pane_id())
-            .unwrap_or(dims.physical_top) as f64
-            + (amount * dims.viewport_rows as f64);
+            .unwrap_or(dims.physical_top);
+        let position = prior as f64 + (amount * dims.viewport_rows as f64);
         self.set_viewport(pane.pane_id(), Some(position as isize), dims);
+        self.start_smooth_scroll(pane.pane_id(), prior, dims);
         if let Some(win) = self.window.as_ref() {
             win.invalidate();
         }
@@ -2564,17 +3136,67 @@ impl TermWindow {
 
     fn scroll_by_line(&mut self, amount: isize, pane: &Arc<dyn Pane>) -> anyhow::Result<()> {
         let dims = pane.get_dimensions();
-        let position = self
+        let prior = self
             .get_viewport(pane.pane_id())
-            .unwrap_or(dims.physical_top)
-            .saturating_add(amount);
-        self.set_viewport(pane.pane_id(), Some(position), dims);
+            .unwrap_or(dims.physical_top);
+        self.set_viewport(pane.pane_id(), Some(prior.saturating_add(amount)), dims);
+        self.start_smooth_scroll(pane.pane_id(), prior, dims);
         if let Some(win) = self.window.as_ref() {
             win.invalidate();
         }
         Ok(())
     }
 
+    /// Having just scrolled the viewport away from `prior`, arrange
+    /// for the content to glide to its new position rather than jump
+    fn start_smooth_scroll(
+        &mut self,
+        pane_id: PaneId,
+        prior: StableRowIndex,
+        dims: RenderableDimensions,
+    ) {
+        if self.config.smooth_scroll_duration_ms == 0 {
+            return;
+        }
+        let delta = self.get_viewport(pane_id).unwrap_or(dims.physical_top) - prior;
+        if delta == 0 {
+            return;
+        }
+
+        // Continue from wherever a scroll that is already in progress
+        // has got to, so that rapid wheel events accumulate smoothly
+        let remaining = self.smooth_scroll_offset(pane_id).unwrap_or(0.);
+        let rows = dims.viewport_rows as f32;
+        let offset = (remaining + delta as f32).clamp(-rows, rows);
+        self.pane_state(pane_id)
+            .smooth_scroll
+            .replace((offset, Instant::now()));
+    }
+
+    /// Returns the number of rows, possibly fractional, by which the
+    /// content of the pane should currently be drawn lower than its
+    /// true position while smooth scrolling.  Returns `Some(0.)` for
+    /// the frame that completes the animation, so that the caller can
+    /// repaint the area one last time, and None when not scrolling.
+    pub fn smooth_scroll_offset(&self, pane_id: PaneId) -> Option<f32> {
+        let mut state = self.pane_state(pane_id);
+        let (offset, start) = state.smooth_scroll?;
+
+        let duration = Duration::from_millis(self.config.smooth_scroll_duration_ms);
+        let elapsed = start.elapsed();
+        // Animation frames are only scheduled while focused
+        if elapsed >= duration || self.focused.is_none() {
+            state.smooth_scroll.take();
+            return Some(0.);
+        }
+
+        let progress = self
+            .config
+            .smooth_scroll_ease
+            .evaluate_at_position(elapsed.as_secs_f32() / duration.as_secs_f32());
+        Some(offset * (1. - progress.clamp(0., 1.)))
+    }
+
     fn move_tab_relative(&mut self, delta: isize) -> anyhow::Result<()> {
         let mux = Mux::get();
         let window = mux
@@ -2598,6 +3220,30 @@ impl TermWindow {
         self.move_tab(tab)
     }
 
+    /// Toggles full screen mode on the screen identified by `selector`.
+    /// Screens are indexed from left to right, then top to bottom.
+    pub fn toggle_fullscreen_on_screen(&self, selector: &ScreenSelector) -> anyhow::Result<()> {
+        let name = match selector {
+            ScreenSelector::Name(name) => name.clone(),
+            ScreenSelector::Index(idx) => {
+                let screens = Connection::get()
+                    .ok_or_else(|| anyhow!("no connection"))?
+                    .screens()?;
+                let mut screens: Vec<_> = screens.by_name.into_values().collect();
+                screens.sort_by_key(|screen| (screen.rect.min_x(), screen.rect.min_y()));
+                screens
+                    .into_iter()
+                    .nth(*idx)
+                    .ok_or_else(|| anyhow!("there is no screen with index {idx}"))?
+                    .name
+            }
+        };
+        if let Some(window) = self.window.as_ref() {
+            window.toggle_fullscreen_on_screen(name);
+        }
+        Ok(())
+    }
+
     pub fn perform_key_assignment(
         &mut self,
         pane: &Arc<dyn Pane>,
@@ -2694,6 +3340,24 @@ impl TermWindow {
             ToggleFullScreen => {
                 self.window.as_ref().unwrap().toggle_fullscreen();
             }
+            ToggleFullScreenOnScreen(selector) => {
+                self.toggle_fullscreen_on_screen(selector)?;
+            }
+            ToggleDebugOverlay => {
+                self.show_debug_hud = !self.show_debug_hud;
+                if let Some(window) = self.window.as_ref() {
+                    window.invalidate();
+                }
+            }
+            ToggleMinimap => {
+                self.show_minimap = !self.show_minimap;
+                if !self.show_minimap {
+                    self.minimap_cache.take();
+                }
+                if let Some(window) = self.window.as_ref() {
+                    window.invalidate();
+                }
+            }
             ToggleAlwaysOnTop => {
                 let window = self.window.clone().unwrap();
                 let current_level = self.window_state.as_window_level();
@@ -2724,9 +3388,50 @@ impl TermWindow {
                 let window = self.window.clone().unwrap();
                 window.set_window_level(level.clone());
             }
+            ToggleShortcutInhibit => {
+                self.window.as_ref().unwrap().toggle_shortcut_inhibit();
+            }
+            TogglePresentationMode => {
+                self.window.as_ref().unwrap().toggle_presentation_mode();
+            }
+            CaptureWindow => {
+                self.request_capture(CaptureTarget::Window, CaptureDestination::Downloads);
+            }
+            CapturePane => {
+                self.request_capture(
+                    CaptureTarget::Pane(pane.pane_id()),
+                    CaptureDestination::Downloads,
+                );
+            }
+            CapturePaneToClipboard => {
+                self.request_capture(
+                    CaptureTarget::Pane(pane.pane_id()),
+                    CaptureDestination::Clipboard,
+                );
+            }
             CopyTo(dest) => {
                 let text = self.selection_text(pane);
-                self.copy_to_clipboard(*dest, text);
+                let num_chars = text.chars().count();
+                if self.config.copy_as_html {
+                    let html = self.selection_html(pane);
+                    self.copy_contents_to_clipboard(
+                        *dest,
+                        ClipboardContents {
+                            text,
+                            html: Some(html),
+                            png: None,
+                        },
+                    );
+                } else {
+                    self.copy_to_clipboard(*dest, text);
+                }
+                if num_chars > 0 {
+                    self.show_toast_for_event(
+                        InlineToastEvent::Copy,
+                        "",
+                        &format!("Copied {num_chars} characters"),
+                    );
+                }
             }
             CopyTextTo { text, destination } => {
                 self.copy_to_clipboard(*destination, text.clone());
@@ -2734,6 +3439,7 @@ impl TermWindow {
             PasteFrom(source) => {
                 self.paste_from_clipboard(pane, *source);
             }
+            CancelPaste => pane.cancel_paste(),
             ActivateTabRelative(n) => {
                 self.activate_tab_relative(*n, true)?;
             }
@@ -2791,6 +3497,22 @@ impl TermWindow {
             ScrollByLine(n) => self.scroll_by_line(*n, pane)?,
             ScrollByCurrentEventWheelDelta => self.scroll_by_current_event_wheel_delta(pane)?,
             ScrollToPrompt(n) => self.scroll_to_prompt(*n, pane)?,
+            SelectLastCommandOutput => self.select_last_command_output(pane),
+            CopyLastCommandOutput(dest) => {
+                if let Some(range) = self.last_command_output_range(pane) {
+                    let text = Self::text_for_selection_range(pane, &range, false);
+                    let num_chars = text.chars().count();
+                    self.copy_to_clipboard(*dest, text);
+                    if num_chars > 0 {
+                        self.show_toast_for_event(
+                            InlineToastEvent::Copy,
+                            "",
+                            &format!("Copied {num_chars} characters"),
+                        );
+                    }
+                }
+            }
+            ShowLastCommandOutput => self.show_last_command_output(pane),
             ScrollToTop => self.scroll_to_top(pane),
             ScrollToBottom => self.scroll_to_bottom(pane),
             ShowTabNavigator => self.show_tab_navigator(),
@@ -3165,6 +3887,7 @@ impl TermWindow {
                 let modal = crate::termwindow::charselect::CharSelector::new(self, args);
                 self.set_modal(Rc::new(modal));
             }
+            ShowTabOverview(args) => self.show_tab_overview(args),
             ResetTerminal => {
                 pane.perform_actions(vec![termwiz::escape::Action::Esc(
                     termwiz::escape::Esc::Code(termwiz::escape::EscCode::FullReset),
@@ -3193,9 +3916,38 @@ impl TermWindow {
         // perform below; here we allow the user to define an `open-uri` event
         // handler that can bypass the normal `open_url` functionality.
         if let Some(link) = self.current_highlight.as_ref().cloned() {
+            let action = RuleAction::for_link(&link);
+            if action == RuleAction::OpenInEditor {
+                self.open_link_in_editor(pane, link.uri());
+                return;
+            }
+
             let window = GuiWin::new(self);
             let pane = MuxPane(pane.pane_id());
 
+            if let RuleAction::EmitEvent(name) = action {
+                // The rule wants a lua callback; pass along the
+                // capture groups so that it doesn't need to re-parse
+                // the uri.
+                let uri = link.uri().to_string();
+                let captures = rule_captures(&link);
+                promise::spawn::spawn(config::with_lua_config_on_main_thread(
+                    move |lua| async move {
+                        if let Some(lua) = lua {
+                            let args = lua.pack_multi((window, pane, uri, captures))?;
+                            if let Err(err) =
+                                config::lua::emit_event(&lua, (name.clone(), args)).await
+                            {
+                                log::error!("while processing {} event: {:#}", name, err);
+                            }
+                        }
+                        anyhow::Result::<()>::Ok(())
+                    },
+                ))
+                .detach();
+                return;
+            }
+
             async fn open_uri(
                 lua: Option<Rc<mlua::Lua>>,
                 window: GuiWin,
@@ -3227,6 +3979,41 @@ impl TermWindow {
             .detach();
         }
     }
+
+    /// Opens a `path[:line[:column]]` link produced by a hyperlink rule
+    /// using `$VISUAL` or `$EDITOR` in a new tab.
+    fn open_link_in_editor(&self, pane: &Arc<dyn Pane>, uri: &str) {
+        let (path, line) = parse_editor_link(uri);
+
+        let editor = std::env::var("VISUAL")
+            .or_else(|_| std::env::var("EDITOR"))
+            .unwrap_or_else(|_| "vi".to_string());
+        let mut args = match shlex::split(&editor) {
+            Some(args) if !args.is_empty() => args,
+            _ => {
+                log::error!("unable to parse editor command {:?}", editor);
+                return;
+            }
+        };
+        if let Some(line) = line {
+            args.push(format!("+{}", line));
+        }
+        args.push(path);
+
+        let cwd = pane
+            .get_current_working_dir(CachePolicy::AllowStale)
+            .and_then(|url| url.to_file_path().ok());
+
+        log::info!("opening {} in editor: {:?}", uri, args);
+        self.spawn_command(
+            &SpawnCommand {
+                args: Some(args),
+                cwd,
+                ..Default::default()
+            },
+            SpawnWhere::NewTab,
+        );
+    }
     fn close_current_pane(&mut self, confirm: bool) {
         let mux_window_id = self.mux_window_id;
         let mux = Mux::get();
@@ -3395,7 +4182,9 @@ impl TermWindow {
     }
 
     fn scroll_to_bottom(&mut self, pane: &Arc<dyn Pane>) {
-        self.pane_state(pane.pane_id()).viewport = None;
+        let mut state = self.pane_state(pane.pane_id());
+        state.viewport = None;
+        state.smooth_scroll.take();
     }
 
     fn get_active_pane_no_overlay(&self) -> Option<Arc<dyn Pane>> {
@@ -3460,6 +4249,7 @@ impl TermWindow {
             is_active: pos.is_active,
             is_zoomed: pos.is_zoomed,
             has_unseen_output: pos.pane.has_unseen_output(),
+            unseen_output_lines: pos.pane.unseen_output_lines(),
             left: pos.left,
             top: pos.top,
             width: pos.width,
@@ -3642,6 +4432,7 @@ impl Drop for TermWindow {
             if let Some(fe) = try_front_end() {
                 fe.forget_known_window(&window);
             }
+            gpuselection::forget_selection(self.mux_window_id);
         }
     }
 }
@@ -25,5 +25,10 @@ pub trait Modal: Downcast {
         term_window: &mut TermWindow,
     ) -> anyhow::Result<Ref<'_, [ComputedElement]>>;
     fn reconfigure(&self, term_window: &mut TermWindow);
+    /// Called after the computed elements have been rendered,
+    /// to draw anything that they are unable to express
+    fn paint(&self, _term_window: &mut TermWindow) -> anyhow::Result<()> {
+        Ok(())
+    }
 }
 impl_downcast!(Modal);
@@ -1,4 +1,5 @@
 use crate::tabbar::TabBarItem;
+use crate::termwindow::render::minimap::MinimapHit;
 use crate::termwindow::{
     GuiWin, MouseCapture, PositionedSplit, ScrollHit, TermWindowNotif, UIItem, UIItemType, TMB,
 };
@@ -13,7 +14,6 @@ use mux::tab::SplitDirection;
 use mux::Mux;
 use mux_lua::MuxPane;
 use std::convert::TryInto;
-use std::ops::Sub;
 use std::rc::Rc;
 use std::sync::Arc;
 use std::time::Duration;
@@ -43,7 +43,11 @@ impl super::TermWindow {
             | UIItemType::AboveScrollThumb
             | UIItemType::BelowScrollThumb
             | UIItemType::ScrollThumb
-            | UIItemType::Split(_) => {}
+            | UIItemType::ScrollMarker(_)
+            | UIItemType::Minimap(_)
+            | UIItemType::Split(_)
+            | UIItemType::TabOverview(_)
+            | UIItemType::Toast(_) => {}
         }
     }
 
@@ -54,7 +58,11 @@ impl super::TermWindow {
             | UIItemType::AboveScrollThumb
             | UIItemType::BelowScrollThumb
             | UIItemType::ScrollThumb
-            | UIItemType::Split(_) => {}
+            | UIItemType::ScrollMarker(_)
+            | UIItemType::Minimap(_)
+            | UIItemType::Split(_)
+            | UIItemType::TabOverview(_)
+            | UIItemType::Toast(_) => {}
         }
     }
 
@@ -70,27 +78,23 @@ impl super::TermWindow {
         let border = self.get_os_border();
 
         let first_line_offset = if self.show_tab_bar && !self.config.tab_bar_at_bottom {
-            self.tab_bar_pixel_height().unwrap_or(0.) as isize
+            self.tab_bar_pixel_height().unwrap_or(0.)
         } else {
-            0
-        } + border.top.get() as isize;
+            0.
+        } + border.top.get() as f32;
 
         let (padding_left, padding_top) = self.padding_left_top();
 
-        let y = (event
-            .coords
-            .y
-            .sub(padding_top as isize)
-            .sub(first_line_offset)
-            .max(0)
-            / self.render_metrics.cell_size.height) as i64;
+        // The position relative to the top left corner of the first cell.
+        // The padding and tab bar height may be fractional, so this is
+        // computed in the same way that the panes are positioned when
+        // they are rendered, rather than truncating them first
+        let x_pixels = event.coords.x as f32 - (padding_left + border.left.get() as f32);
+        let y_pixels = event.coords.y as f32 - (padding_top + first_line_offset);
 
-        let x = (event
-            .coords
-            .x
-            .sub((padding_left + border.left.get() as f32) as isize)
-            .max(0) as f32)
-            / self.render_metrics.cell_size.width as f32;
+        let y = (y_pixels.max(0.) / self.render_metrics.cell_size.height as f32) as i64;
+
+        let x = x_pixels.max(0.) / self.render_metrics.cell_size.width as f32;
         let x = if !pane.is_mouse_grabbed() {
             // Round the x coordinate so that we're a bit more forgiving of
             // the horizontal position when selecting cells
@@ -100,19 +104,12 @@ impl super::TermWindow {
         }
         .trunc() as usize;
 
-        let mut y_pixel_offset = event
-            .coords
-            .y
-            .sub(padding_top as isize)
-            .sub(first_line_offset);
+        let mut y_pixel_offset = y_pixels.floor() as isize;
         if y > 0 {
             y_pixel_offset = y_pixel_offset.max(0) % self.render_metrics.cell_size.height;
         }
 
-        let mut x_pixel_offset = event
-            .coords
-            .x
-            .sub((padding_left + border.left.get() as f32) as isize);
+        let mut x_pixel_offset = x_pixels.floor() as isize;
         if x > 0 {
             x_pixel_offset = x_pixel_offset.max(0) % self.render_metrics.cell_size.width;
         }
@@ -348,6 +345,9 @@ impl super::TermWindow {
             UIItemType::ScrollThumb => {
                 self.drag_scroll_thumb(item, start_event, event, context);
             }
+            UIItemType::Minimap(hit) => {
+                self.drag_minimap(item, hit, start_event, event, context);
+            }
             _ => {
                 log::error!("drag not implemented for {:?}", item);
             }
@@ -376,12 +376,24 @@ impl super::TermWindow {
             UIItemType::BelowScrollThumb => {
                 self.mouse_event_below_scroll_thumb(item, pane, event, context);
             }
+            UIItemType::ScrollMarker(row) => {
+                self.mouse_event_scroll_marker(row, pane, event, context);
+            }
+            UIItemType::Minimap(hit) => {
+                self.mouse_event_minimap(item, hit, pane, event, context);
+            }
             UIItemType::Split(split) => {
                 self.mouse_event_split(item, split, event, context);
             }
             UIItemType::CloseTab(idx) => {
                 self.mouse_event_close_tab(idx, event, context);
             }
+            UIItemType::TabOverview(idx) => {
+                self.mouse_event_tab_overview(idx, &event);
+            }
+            UIItemType::Toast(id) => {
+                self.mouse_event_toast(id, &event);
+            }
         }
     }
 
@@ -613,6 +625,75 @@ impl super::TermWindow {
         context.set_cursor(Some(MouseCursor::Arrow));
     }
 
+    pub fn mouse_event_scroll_marker(
+        &mut self,
+        row: StableRowIndex,
+        pane: Arc<dyn Pane>,
+        event: MouseEvent,
+        context: &dyn WindowOps,
+    ) {
+        if let WMEK::Press(MousePress::Left) = event.kind {
+            // Scroll so that the marked row is in the middle of the viewport
+            let dims = pane.get_dimensions();
+            let top = row.saturating_sub(dims.viewport_rows as StableRowIndex / 2);
+            self.set_viewport(pane.pane_id(), Some(top), dims);
+            context.invalidate();
+        }
+        context.set_cursor(Some(MouseCursor::Arrow));
+    }
+
+    /// Scrolls so that the row under the mouse in the minimap
+    /// is in the middle of the viewport
+    fn scroll_to_minimap_row(
+        &mut self,
+        item: &UIItem,
+        hit: MinimapHit,
+        pane: &Arc<dyn Pane>,
+        event: &MouseEvent,
+    ) {
+        let row = hit.row_at(event.coords.y - item.y as isize);
+        let dims = pane.get_dimensions();
+        let top = row.saturating_sub(dims.viewport_rows as StableRowIndex / 2);
+        self.set_viewport(pane.pane_id(), Some(top), dims);
+    }
+
+    pub fn mouse_event_minimap(
+        &mut self,
+        item: UIItem,
+        hit: MinimapHit,
+        pane: Arc<dyn Pane>,
+        event: MouseEvent,
+        context: &dyn WindowOps,
+    ) {
+        if let WMEK::Press(MousePress::Left) = event.kind {
+            self.scroll_to_minimap_row(&item, hit, &pane, &event);
+            context.invalidate();
+            // and keep scrolling while the mouse is dragged
+            self.dragging = Some((item, event));
+        }
+        context.set_cursor(Some(MouseCursor::Arrow));
+    }
+
+    fn drag_minimap(
+        &mut self,
+        item: UIItem,
+        hit: MinimapHit,
+        start_event: MouseEvent,
+        event: MouseEvent,
+        context: &dyn WindowOps,
+    ) {
+        let pane = match self.get_active_pane_or_overlay() {
+            Some(pane) => pane,
+            None => return,
+        };
+        // The item retains the rows that were shown when the drag
+        // started, so that the mapping doesn't shift as the minimap
+        // scrolls along with the viewport
+        self.scroll_to_minimap_row(&item, hit, &pane, &event);
+        context.invalidate();
+        self.dragging.replace((item, start_event));
+    }
+
     pub fn mouse_event_scroll_thumb(
         &mut self,
         item: UIItem,
@@ -773,6 +854,18 @@ impl super::TermWindow {
             .unwrap_or(dims.physical_top)
             + row as StableRowIndex;
 
+        // Each cell of a double-width line occupies two cells
+        // worth of space on screen; the pixel offset is relative
+        // to the start of that wider cell
+        if self
+            .pane_state(pane.pane_id())
+            .double_width_rows
+            .contains(&stable_row)
+        {
+            x_pixel_offset += (column % 2) as isize * self.render_metrics.cell_size.width;
+            column /= 2;
+        }
+
         self.pane_state(pane.pane_id())
             .mouse_terminal_coords
             .replace((
@@ -785,7 +878,13 @@ impl super::TermWindow {
                 stable_row,
             ));
 
-        pane.apply_hyperlinks(stable_row..stable_row + 1, &self.config.hyperlink_rules);
+        let pane_rules = pane.get_hyperlink_rules();
+        pane.apply_hyperlinks(
+            stable_row..stable_row + 1,
+            pane_rules
+                .as_deref()
+                .unwrap_or(&self.config.hyperlink_rules),
+        );
 
         struct FindCurrentLink {
             current: Option<Arc<Hyperlink>>,
@@ -843,6 +942,10 @@ impl super::TermWindow {
             MouseCursor::Text
         }));
 
+        if allow_action && self.drag_out_mouse_event(&pane, &event, column, stable_row) {
+            return;
+        }
+
         let event_trigger_type = match &event.kind {
             WMEK::Press(press) => {
                 let press = mouse_press_to_tmb(press);
@@ -13,6 +13,7 @@ use config::Dimension;
 use frecency::Frecency;
 use luahelper::{from_lua_value_dynamic, impl_lua_conversion_dynamic};
 use mux_lua::MuxPane;
+use nucleo_matcher::pattern::Pattern;
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
@@ -39,6 +40,8 @@ pub struct CommandPalette {
     top_row: RefCell<usize>,
     max_rows_on_screen: RefCell<usize>,
     commands: Vec<ExpandedCommand>,
+    /// Frecency scores of the recently used commands
+    scores: HashMap<String, f64>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -79,6 +82,23 @@ fn save_recent(command: &ExpandedCommand) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Returns the frecency score of each recently used command,
+/// keyed by its brief description
+fn recent_scores() -> HashMap<String, f64> {
+    load_recents()
+        .map(|recents| {
+            recents
+                .into_iter()
+                .map(|r| (r.brief, r.frecency.score()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The name of the lua registry value that holds the entries
+/// added by `wezterm.gui.register_palette_entry`
+pub const LUA_REGISTRY_PALETTE_ENTRIES: &str = "wezterm-palette-entries";
+
 #[derive(Debug, Clone, FromDynamic, ToDynamic)]
 pub struct UserPaletteEntry {
     pub brief: String,
@@ -92,6 +112,7 @@ fn build_commands(
     gui_window: GuiWin,
     pane: Option<MuxPane>,
     filter_copy_mode: bool,
+    scores: &HashMap<String, f64>,
 ) -> Vec<ExpandedCommand> {
     let mut commands = CommandDef::actions_for_palette_and_menubar(&config::configuration());
 
@@ -99,13 +120,19 @@ fn build_commands(
         let mut entries: Vec<UserPaletteEntry> = vec![];
 
         if let Some(lua) = lua {
+            let registered: mlua::Value = lua.named_registry_value(LUA_REGISTRY_PALETTE_ENTRIES)?;
+            if !matches!(&registered, mlua::Value::Nil) {
+                entries = from_lua_value_dynamic(registered)?;
+            }
+
             let result = config::lua::emit_sync_callback(
                 &*lua,
                 ("augment-command-palette".to_string(), (gui_window, pane)),
             )?;
 
             if !matches!(&result, mlua::Value::Nil) {
-                entries = from_lua_value_dynamic(result)?;
+                let augmented: Vec<UserPaletteEntry> = from_lua_value_dynamic(result)?;
+                entries.extend(augmented);
             }
         }
 
@@ -139,14 +166,6 @@ fn build_commands(
         }
     });
 
-    let mut scores: HashMap<&str, f64> = HashMap::new();
-    let recents = load_recents();
-    if let Ok(recents) = &recents {
-        for r in recents {
-            scores.insert(&r.brief, r.frecency.score());
-        }
-    }
-
     commands.sort_by(|a, b| {
         match (scores.get(&*a.brief), scores.get(&*b.brief)) {
             // Want descending frecency score, so swap a<->b
@@ -169,49 +188,101 @@ fn build_commands(
     commands
 }
 
-#[derive(Debug)]
-struct MatchResult {
-    row_idx: usize,
-    score: u32,
+/// Added to the score of a command for each word of the selection
+/// that matches the start of a word (or the initials) of its brief
+/// description, so that "new tab" ranks "New Tab" above commands
+/// that merely contain those letters somewhere in their text
+const WORD_START_BONUS: u32 = 64;
+/// Scales the frecency score of a recently used command into a bonus,
+/// which is enough to rank it above similar matches without letting
+/// it win over a much better match
+const FRECENCY_WEIGHT: f64 = 48.;
+
+fn word_start_bonus(selection: &str, brief: &str) -> u32 {
+    let words: Vec<String> = brief
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect();
+    let initials: String = words
+        .iter()
+        .filter_map(|word| word.chars().next())
+        .collect();
+
+    let mut bonus = 0;
+    for term in selection.split_whitespace() {
+        let term = term.to_lowercase();
+        if words.iter().any(|word| word.starts_with(&term)) {
+            bonus += WORD_START_BONUS;
+        } else if term.chars().count() > 1 && initials.starts_with(&term) {
+            bonus += WORD_START_BONUS / 2;
+        }
+    }
+    bonus
 }
 
-impl MatchResult {
-    fn new(row_idx: usize, score: u32, selection: &str, commands: &[ExpandedCommand]) -> Self {
-        Self {
-            row_idx,
-            score: if commands[row_idx].brief == selection {
-                // Pump up the score for an exact match, otherwise
-                // the order may be undesirable if there are a lot
-                // of candidates with the same score
-                u32::max_value()
-            } else {
-                score
-            },
-        }
+fn score_command(
+    pattern: &Pattern,
+    selection: &str,
+    command: &ExpandedCommand,
+    frecency: Option<f64>,
+) -> Option<u32> {
+    if command.brief == selection {
+        // Pump up the score for an exact match, otherwise
+        // the order may be undesirable if there are a lot
+        // of candidates with the same score
+        return Some(u32::max_value());
     }
+
+    let group = command.menubar.join(" ");
+    let text = format!(
+        "{group}: {}. {} {:?}",
+        command.brief, command.doc, command.action
+    );
+    let text_score = matcher_score(pattern, &text)?;
+    // A match within the brief description is worth more than one
+    // that is spread across the group, doc and action
+    let brief_score = matcher_score(pattern, &command.brief)
+        .map(|score| score.saturating_mul(2))
+        .unwrap_or(0);
+
+    let frecency_bonus = frecency
+        .map(|score| (score.max(0.).ln_1p() * FRECENCY_WEIGHT) as u32)
+        .unwrap_or(0);
+
+    Some(
+        text_score
+            .max(brief_score)
+            .saturating_add(word_start_bonus(selection, &command.brief))
+            .saturating_add(frecency_bonus),
+    )
 }
 
-fn compute_matches(selection: &str, commands: &[ExpandedCommand]) -> Vec<usize> {
+fn compute_matches(
+    selection: &str,
+    commands: &[ExpandedCommand],
+    scores: &HashMap<String, f64>,
+) -> Vec<usize> {
     if selection.is_empty() {
         commands.iter().enumerate().map(|(idx, _)| idx).collect()
     } else {
         let pattern = matcher_pattern(selection);
 
         let start = std::time::Instant::now();
-        let mut scores: Vec<MatchResult> = commands
+        let mut results: Vec<(usize, u32)> = commands
             .par_iter()
             .enumerate()
-            .filter_map(|(row_idx, entry)| {
-                let group = entry.menubar.join(" ");
-                let text = format!("{group}: {}. {} {:?}", entry.brief, entry.doc, entry.action);
-                matcher_score(&pattern, &text)
-                    .map(|score| MatchResult::new(row_idx, score, selection, commands))
+            .filter_map(|(row_idx, command)| {
+                let frecency = scores.get(&*command.brief).copied();
+                score_command(&pattern, selection, command, frecency).map(|score| (row_idx, score))
             })
             .collect();
-        scores.sort_by(|a, b| a.score.cmp(&b.score).reverse());
+        // The sort is stable, so equally scored commands
+        // remain in their frecency order
+        results.sort_by(|a, b| a.1.cmp(&b.1).reverse());
         log::trace!("matching took {:?}", start.elapsed());
 
-        scores.iter().map(|result| result.row_idx).collect()
+        results.iter().map(|(row_idx, _)| *row_idx).collect()
     }
 }
 
@@ -232,12 +303,19 @@ impl CommandPalette {
             .get_active_pane_or_overlay()
             .map(|pane| MuxPane(pane.pane_id()));
 
-        let commands = build_commands(GuiWin::new(term_window), mux_pane, filter_copy_mode);
+        let scores = recent_scores();
+        let commands = build_commands(
+            GuiWin::new(term_window),
+            mux_pane,
+            filter_copy_mode,
+            &scores,
+        );
 
         Self {
             element: RefCell::new(None),
             selection: RefCell::new(String::new()),
             commands,
+            scores,
             matches: RefCell::new(None),
             selected_row: RefCell::new(0),
             top_row: RefCell::new(0),
@@ -678,7 +756,7 @@ impl Modal for CommandPalette {
         if rebuild_matches {
             results.replace(MatchResults {
                 selection: selection.to_string(),
-                matches: compute_matches(selection, &self.commands),
+                matches: compute_matches(selection, &self.commands, &self.scores),
             });
         };
         let matches = results.as_ref().unwrap();
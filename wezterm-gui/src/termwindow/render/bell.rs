@@ -0,0 +1,206 @@
+use crate::quad::TripleLayerQuadAllocator;
+use crate::tabbar::TabBarItem;
+use crate::termwindow::UIItemType;
+use anyhow::Context;
+use config::VisualBellTarget;
+use mux::renderable::StableCursorPosition;
+use mux::tab::PositionedPane;
+use std::time::Instant;
+use wezterm_term::color::ColorPalette;
+use wezterm_term::StableRowIndex;
+use window::color::LinearRgba;
+use window::RectF;
+
+impl crate::TermWindow {
+    /// The color that the visual bell effects flash towards
+    fn visual_bell_color(&self, palette: &ColorPalette) -> LinearRgba {
+        self.config
+            .resolved_palette
+            .visual_bell
+            .as_deref()
+            .unwrap_or(&palette.foreground)
+            .to_linear()
+    }
+
+    pub fn push_full_window_dirty_rect(&self) {
+        self.dirty_rects.borrow_mut().push((
+            0,
+            0,
+            self.dimensions.pixel_width as i32,
+            self.dimensions.pixel_height as i32,
+        ));
+    }
+
+    fn push_dirty_rect(&self, rect: RectF) {
+        self.dirty_rects.borrow_mut().push((
+            rect.min_x() as i32,
+            rect.min_y() as i32,
+            rect.width().ceil() as i32,
+            rect.height().ceil() as i32,
+        ));
+    }
+
+    /// Draws the Border and CursorCell bell effects over `pos`, whose
+    /// background covers `background_rect`
+    pub fn paint_bell_effects(
+        &mut self,
+        pos: &PositionedPane,
+        layers: &mut TripleLayerQuadAllocator,
+        background_rect: RectF,
+        top_pixel_y: f32,
+        cursor: &StableCursorPosition,
+        viewport_top: StableRowIndex,
+    ) -> anyhow::Result<()> {
+        let config = self.config.clone();
+        let palette = pos.pane.palette();
+        let cell_width = self.render_metrics.cell_size.width as f32;
+        let cell_height = self.render_metrics.cell_size.height as f32;
+
+        if let Some(intensity) =
+            self.get_intensity_if_bell_target_ringing(&pos.pane, &config, VisualBellTarget::Border)
+        {
+            let color = self.visual_bell_color(&palette).mul_alpha(intensity);
+            let thickness = (cell_width / 4.).round().max(2.);
+            let rect = background_rect;
+            for edge in [
+                euclid::rect(rect.min_x(), rect.min_y(), rect.width(), thickness),
+                euclid::rect(
+                    rect.min_x(),
+                    rect.max_y() - thickness,
+                    rect.width(),
+                    thickness,
+                ),
+                euclid::rect(rect.min_x(), rect.min_y(), thickness, rect.height()),
+                euclid::rect(
+                    rect.max_x() - thickness,
+                    rect.min_y(),
+                    thickness,
+                    rect.height(),
+                ),
+            ] {
+                self.filled_rectangle(layers, 2, edge, color)
+                    .context("filled_rectangle")?;
+                self.push_dirty_rect(edge);
+            }
+        }
+
+        if let Some(intensity) = self.get_intensity_if_bell_target_ringing(
+            &pos.pane,
+            &config,
+            VisualBellTarget::CursorCell,
+        ) {
+            let dims = pos.pane.get_dimensions();
+            let row = cursor.y - viewport_top;
+            if row >= 0 && (row as usize) < dims.viewport_rows && cursor.x < dims.cols {
+                let (padding_left, _) = self.padding_left_top();
+                let border = self.get_os_border();
+                let cell = euclid::rect(
+                    padding_left
+                        + border.left.get() as f32
+                        + (pos.left + cursor.x) as f32 * cell_width,
+                    top_pixel_y + (pos.top as f32 + row as f32) * cell_height,
+                    cell_width,
+                    cell_height,
+                );
+                self.filled_rectangle(
+                    layers,
+                    2,
+                    cell,
+                    self.visual_bell_color(&palette).mul_alpha(intensity),
+                )
+                .context("filled_rectangle")?;
+                self.push_dirty_rect(cell);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Flashes the titles of the inactive tabs that have a pane
+    /// in which the bell is ringing with the TabTitle target
+    pub fn paint_bell_tab_titles(&mut self) -> anyhow::Result<()> {
+        let mux = mux::Mux::get();
+        let mut flashing = vec![];
+        if let Some(window) = mux.get_window(self.mux_window_id) {
+            for item in &self.ui_items {
+                if let UIItemType::TabBar(TabBarItem::Tab {
+                    tab_idx,
+                    active: false,
+                }) = item.item_type
+                {
+                    let tab = match window.get_by_idx(tab_idx) {
+                        Some(tab) => tab,
+                        None => continue,
+                    };
+                    for pos in tab.iter_panes_ignoring_zoom() {
+                        if let Some(intensity) = self.get_intensity_if_bell_target_ringing(
+                            &pos.pane,
+                            &self.config,
+                            VisualBellTarget::TabTitle,
+                        ) {
+                            let rect = euclid::rect(
+                                item.x as f32,
+                                item.y as f32,
+                                item.width as f32,
+                                item.height as f32,
+                            );
+                            flashing.push((rect, intensity, pos.pane.palette()));
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        if flashing.is_empty() {
+            return Ok(());
+        }
+
+        // The fancy tab bar is rendered on a layer of its own,
+        // so the flash is drawn on a layer above that
+        let gl_state = self.render_state.as_ref().unwrap();
+        let layer = gl_state
+            .layer_for_zindex(11)
+            .context("layer_for_zindex(11)")?;
+        let mut layers = layer.quad_allocator();
+        for (rect, intensity, palette) in flashing {
+            self.filled_rectangle(
+                &mut layers,
+                0,
+                rect,
+                self.visual_bell_color(&palette).mul_alpha(intensity),
+            )
+            .context("filled_rectangle")?;
+            self.push_dirty_rect(rect);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the horizontal offset, in pixels, by which the contents
+    /// of the window are displaced while the bell is ringing with the
+    /// ScreenShake target in one of the visible panes
+    pub fn bell_shake_offset(&self) -> f32 {
+        let intensity = self
+            .get_panes_to_render()
+            .iter()
+            .filter_map(|pos| {
+                self.get_intensity_if_bell_target_ringing(
+                    &pos.pane,
+                    &self.config,
+                    VisualBellTarget::ScreenShake,
+                )
+            })
+            .fold(0., f32::max);
+        if intensity <= 0. {
+            return 0.;
+        }
+
+        // The shake oscillates much faster than the easing is normally
+        // sampled, so keep painting for as long as it lasts
+        self.update_next_frame_time(Some(Instant::now() + self.animation_frame_interval()));
+
+        let amplitude = self.render_metrics.cell_size.width as f32 / 2.;
+        let phase = self.created.elapsed().as_secs_f32() * 40.;
+        (amplitude * intensity * phase.sin()).round()
+    }
+}
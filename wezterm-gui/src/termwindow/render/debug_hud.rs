@@ -0,0 +1,136 @@
+use crate::termwindow::box_model::*;
+use crate::utilsprites::RenderMetrics;
+use config::{Dimension, DimensionContext};
+use std::time::Duration;
+use window::color::LinearRgba;
+
+fn format_ms(duration: Option<Duration>) -> String {
+    match duration {
+        Some(duration) => format!("{:.2}ms", duration.as_secs_f64() * 1000.),
+        None => "-".to_string(),
+    }
+}
+
+impl crate::TermWindow {
+    /// Collects the statistics shown by the debug hud, one line each
+    fn debug_hud_lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!("fps: {:.1}", self.fps),
+            format!("frame: {}", format_ms(Some(self.last_frame_duration))),
+        ];
+
+        if let Some(render_state) = self.render_state.as_ref() {
+            let layers = render_state.layers.borrow();
+            let quads: Vec<usize> = layers
+                .iter()
+                .map(|layer| {
+                    layer
+                        .vb
+                        .borrow()
+                        .iter()
+                        .map(|vb| *vb.next_quad.borrow())
+                        .sum()
+                })
+                .collect();
+            lines.push(format!(
+                "quads: {} ({} layers: {:?})",
+                quads.iter().sum::<usize>(),
+                quads.len(),
+                quads
+            ));
+
+            let glyph_cache = render_state.glyph_cache.borrow();
+            let size = glyph_cache.atlas.size();
+            lines.push(format!(
+                "atlas: {size}x{size} x{} pages {:.1}% used",
+                glyph_cache.atlas.num_pages(),
+                glyph_cache.atlas.utilization() * 100.
+            ));
+        }
+
+        lines.push(format!("damage rects: {}", self.dirty_rects.borrow().len()));
+
+        match &self.frame_stats {
+            Some(stats) => {
+                lines.push(format!(
+                    "presented: {} vsync: {} zero-copy: {}",
+                    stats.presented, stats.vsync, stats.zero_copy
+                ));
+                lines.push(format!(
+                    "discarded: {} missed: {}",
+                    stats.discarded, stats.missed_deadlines
+                ));
+                lines.push(format!(
+                    "latency p50: {} p95: {} max: {}",
+                    format_ms(stats.frame_time_p50),
+                    format_ms(stats.frame_time_p95),
+                    format_ms(stats.frame_time_max)
+                ));
+            }
+            None => lines.push("presentation: -".to_string()),
+        }
+
+        lines
+    }
+
+    /// Draws the render statistics in the top right corner of the
+    /// window, above everything else
+    pub fn paint_debug_hud(&mut self) -> anyhow::Result<()> {
+        let font = self.fonts.title_font()?;
+        let metrics = RenderMetrics::with_font_metrics(&font.metrics());
+
+        let text = LinearRgba::with_components(1., 1., 1., 1.);
+        let bg = LinearRgba::with_components(0., 0., 0., 0.75);
+
+        let lines = self
+            .debug_hud_lines()
+            .into_iter()
+            .map(|line| Element::new(&font, ElementContent::Text(line)).display(DisplayType::Block))
+            .collect();
+
+        let element = Element::new(&font, ElementContent::Children(lines))
+            .colors(ElementColors {
+                border: BorderColor::new(bg),
+                bg: bg.into(),
+                text: text.into(),
+            })
+            .padding(BoxDimension::new(Dimension::Cells(0.5)));
+
+        let dimensions = self.dimensions;
+        let border = self.get_os_border();
+        let mut computed = self.compute_element(
+            &LayoutContext {
+                height: DimensionContext {
+                    dpi: dimensions.dpi as f32,
+                    pixel_max: dimensions.pixel_height as f32,
+                    pixel_cell: metrics.cell_size.height as f32,
+                },
+                width: DimensionContext {
+                    dpi: dimensions.dpi as f32,
+                    pixel_max: dimensions.pixel_width as f32,
+                    pixel_cell: metrics.cell_size.width as f32,
+                },
+                bounds: euclid::rect(
+                    0.,
+                    0.,
+                    dimensions.pixel_width as f32,
+                    dimensions.pixel_height as f32,
+                ),
+                metrics: &metrics,
+                gl_state: self.render_state.as_ref().unwrap(),
+                zindex: 120,
+            },
+            &element,
+        )?;
+
+        computed.translate(euclid::vec2(
+            dimensions.pixel_width as f32 - border.right.get() as f32 - computed.bounds.width(),
+            border.top.get() as f32,
+        ));
+
+        let gl_state = self.render_state.as_ref().unwrap();
+        self.render_element(&computed, gl_state, None)?;
+
+        Ok(())
+    }
+}
@@ -1,12 +1,16 @@
 use crate::colorease::ColorEaseUniform;
+use crate::renderstate::RenderContext;
+use crate::termwindow::software::SoftwareFrame;
 use crate::termwindow::webgpu::ShaderUniform;
 use crate::termwindow::RenderFrame;
 use crate::uniforms::UniformBuilder;
 use ::window::glium;
+use ::window::glium::framebuffer::SimpleFrameBuffer;
 use ::window::glium::uniforms::{
     MagnifySamplerFilter, MinifySamplerFilter, Sampler, SamplerWrapFunction,
 };
 use ::window::glium::{BlendingFunction, LinearBlendingFactor, Surface};
+use ::window::BufferTransform;
 use config::FreeTypeLoadTarget;
 
 impl crate::TermWindow {
@@ -14,12 +18,98 @@ impl crate::TermWindow {
         match frame {
             RenderFrame::Glium(ref mut frame) => self.call_draw_glium(frame),
             RenderFrame::WebGpu => self.call_draw_webgpu(),
+            RenderFrame::Software(ref mut frame) => self.call_draw_software(frame),
         }
     }
 
+    /// Returns true if the glyphs should be blended using their
+    /// individual subpixel coverage, rather than as grayscale.
+    /// That relies on knowing the color that is beneath each glyph on
+    /// the display, so it is not possible when the window is
+    /// translucent, nor when the compositor will resample the window
+    /// to a fractional scale.  In those cases the glyphs are blended
+    /// using the coverage that was recorded in their alpha channel.
+    fn use_subpixel_aa(&self) -> bool {
+        let lcd = match self
+            .config
+            .freetype_render_target
+            .unwrap_or(self.config.freetype_load_target)
+        {
+            FreeTypeLoadTarget::HorizontalLcd | FreeTypeLoadTarget::VerticalLcd => true,
+            _ => false,
+        };
+        let translucent = self.config.window_background_opacity < 1.0;
+        let scale = self.dimensions.dpi as f64 / ::window::default_dpi();
+        let fractional = scale.fract() != 0.;
+        lcd && !translucent && !fractional
+    }
+
+    fn call_draw_software(&mut self, frame: &mut SoftwareFrame) -> anyhow::Result<()> {
+        use window::bitmaps::{Image, ImageTexture};
+
+        // There is no post processing on the cpu, but this
+        // reports that to the user if they have configured it
+        self.post_process();
+
+        let render_state = self.render_state.as_ref().unwrap();
+        let pages = render_state.glyph_cache.borrow().atlas.page_textures();
+        let images: Vec<_> = pages
+            .iter()
+            .map(|tex| tex.downcast_ref::<ImageTexture>().unwrap().image.borrow())
+            .collect();
+        let atlas_pages: Vec<&Image> = images.iter().map(|image| &**image).collect();
+
+        let foreground_text_hsb = self.config.foreground_text_hsb;
+        let foreground_text_hsb = [
+            foreground_text_hsb.hue,
+            foreground_text_hsb.saturation,
+            foreground_text_hsb.brightness,
+        ];
+        let text_gamma = self.config.text_gamma;
+        let text_contrast = self.config.text_contrast;
+
+        for layer in render_state.layers.borrow().iter() {
+            for idx in 0..3 {
+                let vb = &layer.vb.borrow()[idx];
+                let (vertex_count, _index_count) = vb.vertex_index_count();
+                if vertex_count > 0 {
+                    let vertices = vb.current_vb_mut();
+                    frame.draw_quads(
+                        &vertices.software()[0..vertex_count],
+                        &atlas_pages,
+                        foreground_text_hsb,
+                        text_gamma,
+                        text_contrast,
+                    );
+                }
+
+                vb.next_index();
+            }
+        }
+
+        drop(atlas_pages);
+        drop(images);
+        if self.has_pending_captures() {
+            self.complete_captures(
+                frame.width() as u32,
+                frame.height() as u32,
+                frame.to_rgba_bottom_up(),
+            );
+        }
+
+        Ok(())
+    }
+
     fn call_draw_webgpu(&mut self) -> anyhow::Result<()> {
         use crate::termwindow::webgpu::WebGpuTexture;
 
+        if self.has_pending_captures() {
+            self.fail_captures("capturing is not supported with front_end=\"WebGpu\"");
+        }
+
+        let post_process = self.post_process_passes();
+        let post_process_inputs = self.post_process_inputs();
+        let use_subpixel = self.use_subpixel_aa();
         let webgpu = self.webgpu.as_mut().unwrap();
         let render_state = self.render_state.as_ref().unwrap();
 
@@ -27,46 +117,80 @@ impl crate::TermWindow {
         let view = output
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
+
+        // When post processing, we render the window into a texture
+        // that the first shader then samples while drawing into the
+        // texture of the next, and the last draws into the surface
+        let (width, height) = {
+            let config = webgpu.config.borrow();
+            (config.width, config.height)
+        };
+        let post_process: Vec<_> = post_process
+            .into_iter()
+            .filter_map(
+                |post_process| match post_process.webgpu_view(width, height) {
+                    Ok(source_view) => Some((post_process, source_view)),
+                    Err(err) => {
+                        log::error!("post_process_shader: {:#}", err);
+                        None
+                    }
+                },
+            )
+            .collect();
+        let target_view = post_process
+            .first()
+            .map_or(&view, |(_, source_view)| source_view);
         let mut encoder = webgpu
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                 label: Some("Render Encoder"),
             });
-        let tex = render_state.glyph_cache.borrow().atlas.texture();
-        let tex = tex.downcast_ref::<WebGpuTexture>().unwrap();
-        let texture_view = tex.create_view(&wgpu::TextureViewDescriptor::default());
-
-        let texture_linear_bind_group =
-            webgpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
-                layout: &webgpu.texture_bind_group_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::TextureView(&texture_view),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: wgpu::BindingResource::Sampler(&webgpu.texture_linear_sampler),
-                    },
-                ],
-                label: Some("linear bind group"),
-            });
+        let pages = render_state.glyph_cache.borrow().atlas.page_textures();
+        let page_bind_groups: Vec<_> = pages
+            .iter()
+            .map(|tex| {
+                let tex = tex.downcast_ref::<WebGpuTexture>().unwrap();
+                let texture_view = tex.create_view(&wgpu::TextureViewDescriptor::default());
+
+                let texture_linear_bind_group =
+                    webgpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                        layout: &webgpu.texture_bind_group_layout,
+                        entries: &[
+                            wgpu::BindGroupEntry {
+                                binding: 0,
+                                resource: wgpu::BindingResource::TextureView(&texture_view),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 1,
+                                resource: wgpu::BindingResource::Sampler(
+                                    &webgpu.texture_linear_sampler,
+                                ),
+                            },
+                        ],
+                        label: Some("linear bind group"),
+                    });
 
-        let texture_nearest_bind_group =
-            webgpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
-                layout: &webgpu.texture_bind_group_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::TextureView(&texture_view),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: wgpu::BindingResource::Sampler(&webgpu.texture_nearest_sampler),
-                    },
-                ],
-                label: Some("nearest bind group"),
-            });
+                let texture_nearest_bind_group =
+                    webgpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                        layout: &webgpu.texture_bind_group_layout,
+                        entries: &[
+                            wgpu::BindGroupEntry {
+                                binding: 0,
+                                resource: wgpu::BindingResource::TextureView(&texture_view),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 1,
+                                resource: wgpu::BindingResource::Sampler(
+                                    &webgpu.texture_nearest_sampler,
+                                ),
+                            },
+                        ],
+                        label: Some("nearest bind group"),
+                    });
+
+                (texture_linear_bind_group, texture_nearest_bind_group)
+            })
+            .collect();
 
         let mut cleared = false;
         let foreground_text_hsb = self.config.foreground_text_hsb;
@@ -76,29 +200,39 @@ impl crate::TermWindow {
             foreground_text_hsb.brightness,
         ];
 
+        let text_gamma = self.config.text_gamma;
+        let text_contrast = self.config.text_contrast;
         let milliseconds = self.created.elapsed().as_millis() as u32;
-        let projection = euclid::Transform3D::<f32, f32, f32>::ortho(
-            -(self.dimensions.pixel_width as f32) / 2.0,
-            self.dimensions.pixel_width as f32 / 2.0,
-            self.dimensions.pixel_height as f32 / 2.0,
-            -(self.dimensions.pixel_height as f32) / 2.0,
-            -1.0,
-            1.0,
-        )
-        .to_arrays_transposed();
+        let shake = self.bell_shake_offset();
+        let projection = euclid::Transform3D::<f32, f32, f32>::translation(shake, 0., 0.)
+            .then(&euclid::Transform3D::<f32, f32, f32>::ortho(
+                -(self.dimensions.pixel_width as f32) / 2.0,
+                self.dimensions.pixel_width as f32 / 2.0,
+                self.dimensions.pixel_height as f32 / 2.0,
+                -(self.dimensions.pixel_height as f32) / 2.0,
+                -1.0,
+                1.0,
+            ))
+            .to_arrays_transposed();
+        if shake != 0. {
+            self.push_full_window_dirty_rect();
+        }
+        let sdr_scale = webgpu.sdr_scale();
+        // The compositor takes care of color conversion for hdr output
+        let color_lut = (webgpu.has_color_lut.get() && !webgpu.hdr.get()) as u32;
+        let color_lut_bind_group = webgpu.color_lut_bind_group.borrow();
 
         for layer in render_state.layers.borrow().iter() {
             for idx in 0..3 {
                 let vb = &layer.vb.borrow()[idx];
                 let (vertex_count, index_count) = vb.vertex_index_count();
                 let vertex_buffer;
-                let uniforms;
                 if vertex_count > 0 {
                     let mut vertices = vb.current_vb_mut();
                     let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                         label: Some("Render Pass"),
                         color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                            view: &view,
+                            view: target_view,
                             resolve_target: None,
                             ops: wgpu::Operations {
                                 load: if cleared {
@@ -120,28 +254,57 @@ impl crate::TermWindow {
                     });
                     cleared = true;
 
-                    uniforms = webgpu.create_uniform(ShaderUniform {
-                        foreground_text_hsb,
-                        milliseconds,
-                        projection,
-                    });
-
-                    render_pass.set_pipeline(&webgpu.render_pipeline);
-                    render_pass.set_bind_group(0, &uniforms, &[]);
-                    render_pass.set_bind_group(1, &texture_linear_bind_group, &[]);
-                    render_pass.set_bind_group(2, &texture_nearest_bind_group, &[]);
+                    let subpixel_pipeline = webgpu.subpixel_pipeline.borrow();
+                    match subpixel_pipeline.as_ref() {
+                        Some(pipeline) if use_subpixel && idx == 1 => {
+                            render_pass.set_pipeline(pipeline);
+                        }
+                        _ => render_pass.set_pipeline(&webgpu.render_pipeline.borrow()),
+                    }
+                    render_pass.set_bind_group(3, &*color_lut_bind_group, &[]);
                     vertex_buffer = vertices.webgpu_mut().recreate();
                     vertex_buffer.unmap();
                     render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
                     render_pass
                         .set_index_buffer(vb.indices.webgpu().slice(..), wgpu::IndexFormat::Uint32);
-                    render_pass.draw_indexed(0..index_count as _, 0, 0..1);
+
+                    // Each draw renders the quads whose textures are on
+                    // one page of the atlas
+                    for (page, (texture_linear_bind_group, texture_nearest_bind_group)) in
+                        page_bind_groups.iter().enumerate()
+                    {
+                        let uniforms = webgpu.create_uniform(ShaderUniform {
+                            foreground_text_hsb,
+                            milliseconds,
+                            projection,
+                            sdr_scale,
+                            color_lut,
+                            atlas_page: page as f32,
+                            text_gamma,
+                            text_contrast,
+                            _padding: [0.; 3],
+                        });
+
+                        render_pass.set_bind_group(0, &uniforms, &[]);
+                        render_pass.set_bind_group(1, texture_linear_bind_group, &[]);
+                        render_pass.set_bind_group(2, texture_nearest_bind_group, &[]);
+                        render_pass.draw_indexed(0..index_count as _, 0, 0..1);
+                    }
                 }
 
                 vb.next_index();
             }
         }
 
+        for (idx, (pass, _)) in post_process.iter().enumerate() {
+            let target_view = post_process
+                .get(idx + 1)
+                .map_or(&view, |(_, next_view)| next_view);
+            if let Err(err) = pass.draw_webgpu(&mut encoder, target_view, &post_process_inputs) {
+                log::error!("post_process_shader: {:#}", err);
+            }
+        }
+
         // submit will accept anything that implements IntoIter
         webgpu.queue.submit(std::iter::once(encoder.finish()));
         output.present();
@@ -152,30 +315,95 @@ impl crate::TermWindow {
     fn call_draw_glium(&mut self, frame: &mut glium::Frame) -> anyhow::Result<()> {
         use window::glium::texture::SrgbTexture2d;
 
+        // When post processing, we render the window into a texture
+        // that the first shader then samples while drawing into the
+        // texture of the next, and the last draws into the frame
+        let post_process: Vec<_> = self
+            .post_process_passes()
+            .into_iter()
+            .filter_map(|post_process| {
+                match post_process.glium_texture(
+                    self.dimensions.pixel_width as u32,
+                    self.dimensions.pixel_height as u32,
+                ) {
+                    Ok(texture) => Some((post_process, texture)),
+                    Err(err) => {
+                        log::error!("post_process_shader: {:#}", err);
+                        None
+                    }
+                }
+            })
+            .collect();
+        let post_process_inputs = self.post_process_inputs();
+
         let gl_state = self.render_state.as_ref().unwrap();
-        let tex = gl_state.glyph_cache.borrow().atlas.texture();
-        let tex = tex.downcast_ref::<SrgbTexture2d>().unwrap();
+        let pages = gl_state.glyph_cache.borrow().atlas.page_textures();
 
         frame.clear_color(0., 0., 0., 0.);
 
-        let projection = euclid::Transform3D::<f32, f32, f32>::ortho(
+        // When a capture has been requested, we draw everything a second
+        // time into an offscreen texture that we can read back afterwards
+        let capture_texture = if self.has_pending_captures() {
+            match &gl_state.context {
+                RenderContext::Glium(context) => Some(SrgbTexture2d::empty(
+                    context,
+                    self.dimensions.pixel_width as u32,
+                    self.dimensions.pixel_height as u32,
+                )?),
+                RenderContext::WebGpu(_) | RenderContext::Software => None,
+            }
+        } else {
+            None
+        };
+        let mut capture_target = match (&gl_state.context, &capture_texture) {
+            (RenderContext::Glium(context), Some(texture)) => {
+                let mut target = SimpleFrameBuffer::new(context, texture)?;
+                target.clear_color(0., 0., 0., 0.);
+                Some(target)
+            }
+            _ => None,
+        };
+        let mut post_process_target = match (&gl_state.context, post_process.first()) {
+            (RenderContext::Glium(context), Some((_, texture))) => {
+                let mut target = SimpleFrameBuffer::new(context, &**texture)?;
+                target.clear_color(0., 0., 0., 0.);
+                Some(target)
+            }
+            _ => None,
+        };
+
+        // Captures are always taken in the orientation of the window
+        let capture_projection = euclid::Transform3D::<f32, f32, f32>::ortho(
             -(self.dimensions.pixel_width as f32) / 2.0,
             self.dimensions.pixel_width as f32 / 2.0,
             self.dimensions.pixel_height as f32 / 2.0,
             -(self.dimensions.pixel_height as f32) / 2.0,
             -1.0,
             1.0,
-        )
-        .to_arrays_transposed();
-
-        let use_subpixel = match self
-            .config
-            .freetype_render_target
-            .unwrap_or(self.config.freetype_load_target)
-        {
-            FreeTypeLoadTarget::HorizontalLcd | FreeTypeLoadTarget::VerticalLcd => true,
-            _ => false,
-        };
+        );
+        // whereas the frame may be rotated to match the output
+        let (buffer_width, buffer_height) = self
+            .buffer_transform
+            .buffer_size(self.dimensions.pixel_width, self.dimensions.pixel_height);
+        // The bell may shake the frame, but not the captures
+        let shake = self.bell_shake_offset();
+        if shake != 0. {
+            self.push_full_window_dirty_rect();
+        }
+        let projection = euclid::Transform3D::<f32, f32, f32>::translation(shake, 0., 0.)
+            .then(&buffer_transform_matrix(self.buffer_transform))
+            .then(&euclid::Transform3D::<f32, f32, f32>::ortho(
+                -(buffer_width as f32) / 2.0,
+                buffer_width as f32 / 2.0,
+                buffer_height as f32 / 2.0,
+                -(buffer_height as f32) / 2.0,
+                -1.0,
+                1.0,
+            ))
+            .to_arrays_transposed();
+        let capture_projection = capture_projection.to_arrays_transposed();
+
+        let use_subpixel = self.use_subpixel_aa();
 
         let dual_source_blending = glium::DrawParameters {
             blend: glium::Blend {
@@ -208,18 +436,26 @@ impl crate::TermWindow {
             ..Default::default()
         };
 
-        // Clamp and use the nearest texel rather than interpolate.
-        // This prevents things like the box cursor outlines from
-        // being randomly doubled in width or height
-        let atlas_nearest_sampler = Sampler::new(&*tex)
-            .wrap_function(SamplerWrapFunction::Clamp)
-            .magnify_filter(MagnifySamplerFilter::Nearest)
-            .minify_filter(MinifySamplerFilter::Nearest);
-
-        let atlas_linear_sampler = Sampler::new(&*tex)
-            .wrap_function(SamplerWrapFunction::Clamp)
-            .magnify_filter(MagnifySamplerFilter::Linear)
-            .minify_filter(MinifySamplerFilter::Linear);
+        let samplers: Vec<_> = pages
+            .iter()
+            .map(|tex| {
+                let tex = tex.downcast_ref::<SrgbTexture2d>().unwrap();
+                // Clamp and use the nearest texel rather than interpolate.
+                // This prevents things like the box cursor outlines from
+                // being randomly doubled in width or height
+                let atlas_nearest_sampler = Sampler::new(tex)
+                    .wrap_function(SamplerWrapFunction::Clamp)
+                    .magnify_filter(MagnifySamplerFilter::Nearest)
+                    .minify_filter(MinifySamplerFilter::Nearest);
+
+                let atlas_linear_sampler = Sampler::new(tex)
+                    .wrap_function(SamplerWrapFunction::Clamp)
+                    .magnify_filter(MagnifySamplerFilter::Linear)
+                    .minify_filter(MinifySamplerFilter::Linear);
+
+                (atlas_nearest_sampler, atlas_linear_sampler)
+            })
+            .collect();
 
         let foreground_text_hsb = self.config.foreground_text_hsb;
         let foreground_text_hsb = (
@@ -228,6 +464,8 @@ impl crate::TermWindow {
             foreground_text_hsb.brightness,
         );
 
+        let text_gamma = self.config.text_gamma;
+        let text_contrast = self.config.text_contrast;
         let milliseconds = self.created.elapsed().as_millis() as u32;
 
         let cursor_blink: ColorEaseUniform = (*self.cursor_blink_state.borrow()).into();
@@ -242,35 +480,126 @@ impl crate::TermWindow {
                     let vertices = vb.current_vb_mut();
                     let subpixel_aa = use_subpixel && idx == 1;
 
-                    let mut uniforms = UniformBuilder::default();
-
-                    uniforms.add("projection", &projection);
-                    uniforms.add("atlas_nearest_sampler", &atlas_nearest_sampler);
-                    uniforms.add("atlas_linear_sampler", &atlas_linear_sampler);
-                    uniforms.add("foreground_text_hsb", &foreground_text_hsb);
-                    uniforms.add("subpixel_aa", &subpixel_aa);
-                    uniforms.add("milliseconds", &milliseconds);
-                    uniforms.add_struct("cursor_blink", &cursor_blink);
-                    uniforms.add_struct("blink", &blink);
-                    uniforms.add_struct("rapid_blink", &rapid_blink);
-
-                    frame.draw(
-                        vertices.glium().slice(0..vertex_count).unwrap(),
-                        vb.indices.glium().slice(0..index_count).unwrap(),
-                        gl_state.glyph_prog.as_ref().unwrap(),
-                        &uniforms,
-                        if subpixel_aa {
-                            &dual_source_blending
-                        } else {
-                            &alpha_blending
-                        },
-                    )?;
+                    let draw_params = if subpixel_aa {
+                        &dual_source_blending
+                    } else {
+                        &alpha_blending
+                    };
+
+                    // Each pass draws the quads whose textures are on
+                    // one page of the atlas
+                    for (page, (atlas_nearest_sampler, atlas_linear_sampler)) in
+                        samplers.iter().enumerate()
+                    {
+                        let atlas_page = page as f32;
+                        let mut common_uniforms = UniformBuilder::default();
+
+                        common_uniforms.add("atlas_nearest_sampler", atlas_nearest_sampler);
+                        common_uniforms.add("atlas_linear_sampler", atlas_linear_sampler);
+                        common_uniforms.add("atlas_page", &atlas_page);
+                        common_uniforms.add("foreground_text_hsb", &foreground_text_hsb);
+                        common_uniforms.add("subpixel_aa", &subpixel_aa);
+                        common_uniforms.add("text_gamma", &text_gamma);
+                        common_uniforms.add("text_contrast", &text_contrast);
+                        common_uniforms.add("milliseconds", &milliseconds);
+                        common_uniforms.add_struct("cursor_blink", &cursor_blink);
+                        common_uniforms.add_struct("blink", &blink);
+                        common_uniforms.add_struct("rapid_blink", &rapid_blink);
+
+                        let mut uniforms = common_uniforms.clone();
+                        match post_process_target.as_mut() {
+                            Some(target) => {
+                                // The post processing pass takes care of
+                                // rotating the window to match the frame
+                                uniforms.add("projection", &capture_projection);
+                                target.draw(
+                                    vertices.glium().slice(0..vertex_count).unwrap(),
+                                    vb.indices.glium().slice(0..index_count).unwrap(),
+                                    gl_state.glyph_prog.as_ref().unwrap(),
+                                    &uniforms,
+                                    draw_params,
+                                )?;
+                            }
+                            None => {
+                                uniforms.add("projection", &projection);
+                                frame.draw(
+                                    vertices.glium().slice(0..vertex_count).unwrap(),
+                                    vb.indices.glium().slice(0..index_count).unwrap(),
+                                    gl_state.glyph_prog.as_ref().unwrap(),
+                                    &uniforms,
+                                    draw_params,
+                                )?;
+                            }
+                        }
+
+                        if let Some(target) = capture_target.as_mut() {
+                            let mut uniforms = common_uniforms;
+                            uniforms.add("projection", &capture_projection);
+                            target.draw(
+                                vertices.glium().slice(0..vertex_count).unwrap(),
+                                vb.indices.glium().slice(0..index_count).unwrap(),
+                                gl_state.glyph_prog.as_ref().unwrap(),
+                                &uniforms,
+                                draw_params,
+                            )?;
+                        }
+                    }
                 }
 
                 vb.next_index();
             }
         }
 
+        drop(post_process_target);
+        for (idx, (pass, _)) in post_process.iter().enumerate() {
+            match (&gl_state.context, post_process.get(idx + 1)) {
+                (RenderContext::Glium(context), Some((_, next_texture))) => {
+                    // Only the last pass rotates the window to match the frame
+                    let mut target = SimpleFrameBuffer::new(context, &**next_texture)?;
+                    pass.draw_glium(
+                        &mut target,
+                        &post_process_inputs,
+                        euclid::Transform3D::<f32, f32, f32>::identity().to_arrays_transposed(),
+                    )?;
+                }
+                _ => {
+                    pass.draw_glium(
+                        frame,
+                        &post_process_inputs,
+                        buffer_transform_matrix(self.buffer_transform).to_arrays_transposed(),
+                    )?;
+                }
+            }
+        }
+        if !post_process.is_empty() {
+            // The shaders may change any pixel, not only those
+            // that were damaged
+            self.push_full_window_dirty_rect();
+        }
+
+        drop(capture_target);
+        if let Some(texture) = capture_texture {
+            let image: glium::texture::RawImage2d<u8> = texture.read();
+            self.complete_captures(image.width, image.height, image.data.into_owned());
+        }
+
         Ok(())
     }
 }
+
+/// Returns the matrix that maps a position relative to the center
+/// of the window to the corresponding position in a buffer that has
+/// `transform` applied; see BufferTransform::transform_point
+fn buffer_transform_matrix(transform: BufferTransform) -> euclid::Transform3D<f32, f32, f32> {
+    let (m11, m12, m21, m22) = match transform {
+        BufferTransform::Normal => (1., 0., 0., 1.),
+        BufferTransform::Rotate90 => (0., -1., 1., 0.),
+        BufferTransform::Rotate180 => (-1., 0., 0., -1.),
+        BufferTransform::Rotate270 => (0., 1., -1., 0.),
+        BufferTransform::Flipped => (-1., 0., 0., 1.),
+        BufferTransform::Flipped90 => (0., 1., 1., 0.),
+        BufferTransform::Flipped180 => (1., 0., 0., -1.),
+        BufferTransform::Flipped270 => (0., -1., -1., 0.),
+    };
+    euclid::Transform2D::new(m11, m12, m21, m22, 0., 0.).to_3d()
+}
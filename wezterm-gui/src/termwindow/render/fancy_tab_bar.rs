@@ -370,6 +370,9 @@ impl crate::TermWindow {
             .config
             .window_decorations
             .contains(window::WindowDecorations::INTEGRATED_BUTTONS)
+            && !self
+                .window_state
+                .contains(window::WindowState::SERVER_DECORATIONS)
             && (self.config.integrated_title_button_alignment
                 == IntegratedTitleButtonAlignment::Left
                 || self.config.integrated_title_button_style
@@ -0,0 +1,335 @@
+use crate::quad::TripleLayerQuadAllocator;
+use crate::termwindow::{UIItem, UIItemType};
+use anyhow::Context;
+use config::DimensionContext;
+use mux::pane::{Pane, PaneId};
+use mux::tab::PositionedPane;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Arc;
+use termwiz::surface::SequenceNo;
+use wezterm_term::color::{ColorAttribute, ColorPalette};
+use wezterm_term::{Line, StableRowIndex};
+use window::color::LinearRgba;
+
+/// A run of adjacent cells in a row of the minimap that are
+/// drawn in the same color
+#[derive(Debug, Clone)]
+struct MinimapRun {
+    cols: Range<usize>,
+    color: LinearRgba,
+}
+
+/// Reduces a line to the runs of color that represent it in the
+/// minimap: the foreground color of cells with visible text, or the
+/// background color of blank cells whose background isn't the default
+fn minimap_runs(line: &Line, palette: &ColorPalette) -> Vec<MinimapRun> {
+    let mut runs: Vec<MinimapRun> = vec![];
+    for cell in line.visible_cells() {
+        let attrs = cell.attrs();
+        let mut fg = palette.resolve_fg(attrs.foreground());
+        let mut bg = palette.resolve_bg(attrs.background());
+        let mut has_bg = attrs.background() != ColorAttribute::Default;
+        if attrs.reverse() {
+            std::mem::swap(&mut fg, &mut bg);
+            has_bg = true;
+        }
+
+        let color = if !attrs.invisible() && !cell.str().trim().is_empty() {
+            fg.to_linear()
+        } else if has_bg {
+            bg.to_linear()
+        } else {
+            continue;
+        };
+
+        let start = cell.cell_index();
+        let end = start + cell.width().max(1);
+        match runs.last_mut() {
+            Some(run) if run.cols.end == start && run.color == color => {
+                run.cols.end = end;
+            }
+            _ => runs.push(MinimapRun {
+                cols: start..end,
+                color,
+            }),
+        }
+    }
+    runs
+}
+
+/// The rows of the minimap of the active pane.  They are retained
+/// between frames so that only the rows that have changed since the
+/// previous frame need to be fetched from the pane.
+pub struct MinimapCache {
+    pane_id: PaneId,
+    palette: ColorPalette,
+    seqno: SequenceNo,
+    rows: HashMap<StableRowIndex, Vec<MinimapRun>>,
+    /// The rows that were shown, and the top of the viewport,
+    /// in the previous frame
+    shown: Range<StableRowIndex>,
+    viewport_top: StableRowIndex,
+}
+
+impl MinimapCache {
+    fn new(pane_id: PaneId, palette: ColorPalette) -> Self {
+        Self {
+            pane_id,
+            palette,
+            seqno: 0,
+            rows: HashMap::new(),
+            shown: 0..0,
+            viewport_top: 0,
+        }
+    }
+
+    /// Brings the rows in `shown` up to date, fetching the lines that
+    /// have changed since the previous call, or that were not previously
+    /// shown.  Returns the rows that were refreshed, or None if the
+    /// minimap has scrolled and needs to be redrawn in its entirety.
+    fn update(
+        &mut self,
+        pane: &Arc<dyn Pane>,
+        shown: Range<StableRowIndex>,
+        viewport_top: StableRowIndex,
+    ) -> Option<Vec<StableRowIndex>> {
+        let redraw_all =
+            self.rows.is_empty() || self.shown != shown || self.viewport_top != viewport_top;
+        self.shown = shown.clone();
+        self.viewport_top = viewport_top;
+
+        let seqno = pane.get_current_seqno();
+        let mut stale = pane.get_changed_since(shown.clone(), self.seqno);
+        self.seqno = seqno;
+
+        self.rows.retain(|row, _| shown.contains(row));
+        for row in shown {
+            if !self.rows.contains_key(&row) {
+                stale.add(row);
+            }
+        }
+
+        let mut refreshed = vec![];
+        for range in stale.iter() {
+            let (first_row, lines) = pane.get_lines(range.clone());
+            for (idx, line) in lines.iter().enumerate() {
+                let row = first_row + idx as StableRowIndex;
+                self.rows.insert(row, minimap_runs(line, &self.palette));
+                refreshed.push(row);
+            }
+        }
+
+        if redraw_all {
+            None
+        } else {
+            Some(refreshed)
+        }
+    }
+}
+
+/// The rows shown by the minimap, used to map a mouse position
+/// over it back to the corresponding row
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MinimapHit {
+    pub first_row: StableRowIndex,
+    pub num_rows: usize,
+    /// The height in pixels of the rows
+    pub rows_height: usize,
+}
+
+impl MinimapHit {
+    /// Returns the row at `y` pixels from the top of the minimap
+    pub fn row_at(&self, y: isize) -> StableRowIndex {
+        let y = y.max(0) as usize;
+        let idx = (y * self.num_rows)
+            .checked_div(self.rows_height)
+            .unwrap_or(0)
+            .min(self.num_rows.saturating_sub(1));
+        self.first_row + idx as StableRowIndex
+    }
+}
+
+impl crate::TermWindow {
+    /// Draws the minimap over the right edge of `pos`
+    pub fn paint_minimap(
+        &mut self,
+        pos: &PositionedPane,
+        layers: &mut TripleLayerQuadAllocator,
+    ) -> anyhow::Result<()> {
+        let pane = &pos.pane;
+        let dims = pane.get_dimensions();
+        let palette = pane.palette();
+        let cell_width = self.render_metrics.cell_size.width as f32;
+        let cell_height = self.render_metrics.cell_size.height as f32;
+
+        let border = self.get_os_border();
+        let (padding_left, padding_top) = self.padding_left_top();
+        let top_bar_height = if self.show_tab_bar && !self.config.tab_bar_at_bottom {
+            self.tab_bar_pixel_height()
+                .context("tab_bar_pixel_height")?
+        } else {
+            0.
+        };
+
+        let pane_left = padding_left + border.left.get() as f32 + pos.left as f32 * cell_width;
+        let pane_top =
+            top_bar_height + padding_top + border.top.get() as f32 + pos.top as f32 * cell_height;
+        let pane_width = pos.width as f32 * cell_width;
+        let pane_height = pos.height as f32 * cell_height;
+
+        // Don't let the minimap hide more than half of the pane
+        let width = self
+            .config
+            .minimap_width
+            .evaluate_as_pixels(DimensionContext {
+                dpi: self.dimensions.dpi as f32,
+                pixel_max: pane_width,
+                pixel_cell: cell_width,
+            })
+            .round()
+            .min((pane_width / 2.).floor());
+        if width < 1. || dims.cols == 0 || dims.scrollback_rows == 0 {
+            return Ok(());
+        }
+        let left = pane_left + pane_width - width;
+
+        // The cells are scaled down so that a full line fits the
+        // width of the minimap, preserving their aspect ratio
+        let scale = width / (dims.cols as f32 * cell_width);
+        let mini_cell_width = cell_width * scale;
+        let row_height = (cell_height * scale).max(1.);
+
+        let max_rows = ((pane_height / row_height) as usize).max(1);
+        let num_rows = max_rows.min(dims.scrollback_rows);
+        let viewport_top = self
+            .get_viewport(pane.pane_id())
+            .unwrap_or(dims.physical_top);
+        let first_row = if dims.scrollback_rows <= max_rows {
+            dims.scrollback_top
+        } else {
+            // When the scrollback doesn't fit, the minimap scrolls in
+            // proportion to the viewport so that they both reach the
+            // top and the bottom of the scrollback together
+            let scrollable = dims.scrollback_rows.saturating_sub(dims.viewport_rows);
+            let progress = if scrollable == 0 {
+                1.
+            } else {
+                ((viewport_top - dims.scrollback_top).max(0) as f32 / scrollable as f32).min(1.)
+            };
+            dims.scrollback_top
+                + ((dims.scrollback_rows - max_rows) as f32 * progress).round() as StableRowIndex
+        };
+        let shown = first_row..first_row + num_rows as StableRowIndex;
+
+        let mut cache = match self.minimap_cache.take() {
+            Some(cache) if cache.pane_id == pane.pane_id() && cache.palette == palette => cache,
+            _ => MinimapCache::new(pane.pane_id(), palette.clone()),
+        };
+        let refreshed = cache.update(pane, shown.clone(), viewport_top);
+
+        let resolved_palette = &self.config.resolved_palette;
+        let background = resolved_palette
+            .minimap_background
+            .map(|c| c.to_linear())
+            .unwrap_or_else(|| palette.background.to_linear().mul_alpha(0.85));
+        let viewport_color = resolved_palette
+            .minimap_viewport
+            .map(|c| c.to_linear())
+            .unwrap_or_else(|| palette.foreground.to_linear().mul_alpha(0.15));
+
+        self.filled_rectangle(
+            layers,
+            2,
+            euclid::rect(left, pane_top, width, pane_height),
+            background,
+        )
+        .context("filled_rectangle")?;
+
+        for (idx, row) in shown.clone().enumerate() {
+            let runs = match cache.rows.get(&row) {
+                Some(runs) => runs,
+                None => continue,
+            };
+            let y = pane_top + idx as f32 * row_height;
+            for run in runs {
+                let cols = run.cols.start..run.cols.end.min(dims.cols);
+                if cols.is_empty() {
+                    continue;
+                }
+                self.filled_rectangle(
+                    layers,
+                    2,
+                    euclid::rect(
+                        left + cols.start as f32 * mini_cell_width,
+                        y,
+                        cols.len() as f32 * mini_cell_width,
+                        row_height,
+                    ),
+                    run.color,
+                )
+                .context("filled_rectangle")?;
+            }
+        }
+
+        // Highlight the rows that are in the viewport
+        let viewport_start = (viewport_top - first_row).max(0) as usize;
+        let viewport_end =
+            ((viewport_top + dims.viewport_rows as StableRowIndex - first_row).max(0) as usize)
+                .min(num_rows);
+        if viewport_start < viewport_end {
+            self.filled_rectangle(
+                layers,
+                2,
+                euclid::rect(
+                    left,
+                    pane_top + viewport_start as f32 * row_height,
+                    width,
+                    (viewport_end - viewport_start) as f32 * row_height,
+                ),
+                viewport_color,
+            )
+            .context("filled_rectangle")?;
+        }
+
+        // Only the rows that changed need to be presented again,
+        // unless the minimap or the viewport moved
+        {
+            let mut dirty_rects = self.dirty_rects.borrow_mut();
+            match refreshed {
+                None => dirty_rects.push((
+                    left as i32,
+                    pane_top as i32,
+                    width.ceil() as i32,
+                    pane_height.ceil() as i32,
+                )),
+                Some(rows) => {
+                    for row in rows {
+                        let y = pane_top + (row - first_row) as f32 * row_height;
+                        dirty_rects.push((
+                            left as i32,
+                            y as i32,
+                            width.ceil() as i32,
+                            row_height.ceil() as i32,
+                        ));
+                    }
+                }
+            }
+        }
+        self.minimap_cache.replace(cache);
+
+        self.ui_items.push(UIItem {
+            x: left as usize,
+            y: pane_top as usize,
+            width: width as usize,
+            height: pane_height as usize,
+            item_type: UIItemType::Minimap(MinimapHit {
+                first_row,
+                num_rows,
+                rows_height: (num_rows as f32 * row_height) as usize,
+            }),
+        });
+
+        Ok(())
+    }
+}
@@ -1,6 +1,7 @@
 use crate::colorease::ColorEase;
 use crate::customglyph::{BlockKey, *};
 use crate::glyphcache::{CachedGlyph, GlyphCache};
+use crate::overlay::CopyOverlay;
 use crate::quad::{
     HeapQuadAllocator, QuadAllocator, QuadImpl, QuadTrait, TripleLayerQuadAllocator,
     TripleLayerQuadAllocatorTrait,
@@ -13,8 +14,9 @@ use ::window::bitmaps::{TextureCoord, TextureRect, TextureSize};
 use ::window::{DeadKeyStatus, PointF, RectF, SizeF, WindowOps};
 use anyhow::{anyhow, Context};
 use config::{
-    BoldBrightening, ConfigHandle, DimensionContext, HorizontalWindowContentAlignment, TextStyle,
-    VerticalWindowContentAlignment, VisualBellTarget,
+    BoldBrightening, ConfigHandle, CursorStyle, CursorStyleShape, Dimension, DimensionContext,
+    HorizontalWindowContentAlignment, ImageFilter, TextStyle, VerticalWindowContentAlignment,
+    VisualBellTarget,
 };
 use euclid::num::Zero;
 use mux::pane::{Pane, PaneId};
@@ -23,28 +25,47 @@ use ordered_float::NotNan;
 use std::ops::Range;
 use std::rc::Rc;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use termwiz::cellcluster::CellCluster;
 use termwiz::hyperlink::Hyperlink;
+use termwiz::image::ImageProtocol;
 use termwiz::surface::{CursorShape, CursorVisibility, SequenceNo};
 use wezterm_font::shaper::PresentationWidth;
 use wezterm_font::units::{IntPixelLength, PixelLength};
-use wezterm_font::{ClearShapeCache, GlyphInfo, LoadedFont};
+use wezterm_font::{BackgroundShapeResult, ClearShapeCache, GlyphInfo, LoadedFont};
 use wezterm_term::color::{ColorAttribute, ColorPalette};
 use wezterm_term::{CellAttributes, Line, StableRowIndex};
 use window::color::LinearRgba;
 
+pub mod bell;
 pub mod borders;
 pub mod corners;
+pub mod debug_hud;
 pub mod draw;
 pub mod fancy_tab_bar;
+pub mod minimap;
 pub mod paint;
 pub mod pane;
+pub mod postprocess;
 pub mod screen_line;
 pub mod split;
 pub mod tab_bar;
 pub mod window_buttons;
 
+/// Clusters with at least this many bytes of text are shaped on the
+/// background shaping pool rather than during paint
+const BACKGROUND_SHAPING_THRESHOLD: usize = 256;
+
+/// Tracks a cluster that was too long to shape during paint.
+/// The value is the shape_generation at the time it was recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackgroundShapeState {
+    /// Being shaped on the background pool
+    Pending(usize),
+    /// Needs font fallback resolution, so must be shaped during paint
+    Foreground(usize),
+}
+
 /// The data that we associate with a line; we use this to cache it shape hash
 #[derive(Debug)]
 pub struct CachedLineState {
@@ -59,7 +80,7 @@ pub struct LineQuadCacheKey {
     pub shape_generation: usize,
     pub quad_generation: usize,
     /// Only set if cursor.y == stable_row
-    pub composing: Option<String>,
+    pub composing: Option<DeadKeyStatus>,
     pub selection: Range<usize>,
     pub shape_hash: [u8; 16],
     pub top_pixel_y: NotNan<f32>,
@@ -72,6 +93,7 @@ pub struct LineQuadCacheKey {
     pub cursor: Option<CursorProperties>,
     pub reverse_video: bool,
     pub password_input: bool,
+    pub ligatures: bool,
 }
 
 pub struct LineQuadCacheValue {
@@ -95,7 +117,8 @@ pub struct LineToElementParams<'a> {
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub struct LineToEleShapeCacheKey {
     pub shape_hash: [u8; 16],
-    pub composing: Option<(usize, String)>,
+    pub ligatures: bool,
+    pub composing: Option<(usize, DeadKeyStatus)>,
     pub shape_generation: usize,
 }
 
@@ -121,6 +144,9 @@ pub struct LineToElementShape {
 
 pub struct RenderScreenLineResult {
     pub invalidate_on_hover_change: bool,
+    /// Some of the text is still being shaped in the background
+    /// and was rendered as blank space; the line must not be cached
+    pub shaping_pending: bool,
 }
 
 pub struct RenderScreenLineParams<'a> {
@@ -146,6 +172,9 @@ pub struct RenderScreenLineParams<'a> {
     pub cursor_border_color: LinearRgba,
     pub foreground: LinearRgba,
     pub is_active: bool,
+    /// The transform to apply to the colors of the line; only set for
+    /// inactive panes whose effect can't be applied while compositing
+    pub hsv: Option<config::HsbTransform>,
 
     pub selection_fg: LinearRgba,
     pub selection_bg: LinearRgba,
@@ -208,6 +237,11 @@ pub struct ComputeCellFgBgResult {
     pub cursor_border_color_alt: LinearRgba,
     pub cursor_border_mix: f32,
     pub cursor_shape: Option<CursorShape>,
+    /// Set when cursor_styles draws the cursor as a hollow block,
+    /// ring or glyph; cursor_shape is then a bar, which leaves the
+    /// colors of the cell alone
+    pub cursor_style: Option<CursorStyleShape>,
+    pub cursor_thickness: Option<Dimension>,
 }
 
 /// Basic cache of computed data from prior cluster to avoid doing the same
@@ -229,6 +263,13 @@ impl crate::TermWindow {
         }
     }
 
+    /// How often to paint while something is moving smoothly;
+    /// ideally once per refresh of the display
+    pub fn animation_frame_interval(&self) -> Duration {
+        self.refresh_interval
+            .unwrap_or_else(|| Duration::from_millis(1000 / self.config.max_fps.max(1) as u64))
+    }
+
     fn get_intensity_if_bell_target_ringing(
         &self,
         pane: &Arc<dyn Pane>,
@@ -236,30 +277,34 @@ impl crate::TermWindow {
         target: VisualBellTarget,
     ) -> Option<f32> {
         let mut per_pane = self.pane_state(pane.pane_id());
-        if let Some(ringing) = per_pane.bell_start {
-            if config.visual_bell.target == target {
-                let mut color_ease = ColorEase::new(
-                    config.visual_bell.fade_in_duration_ms,
-                    config.visual_bell.fade_in_function,
-                    config.visual_bell.fade_out_duration_ms,
-                    config.visual_bell.fade_out_function,
-                    Some(ringing),
-                );
-
-                let intensity = color_ease.intensity_one_shot();
-
-                match intensity {
-                    None => {
-                        per_pane.bell_start.take();
-                    }
-                    Some((intensity, next)) => {
-                        self.update_next_frame_time(Some(next));
-                        return Some(intensity);
-                    }
+        let ringing = per_pane.bell_start?;
+
+        // The bell stops ringing once all of its effects have finished,
+        // not only those for this target
+        let mut result: Option<f32> = None;
+        let mut finished = true;
+        for effect in config.visual_bell.all_effects() {
+            let mut color_ease = ColorEase::new(
+                effect.fade_in_duration_ms,
+                effect.fade_in_function,
+                effect.fade_out_duration_ms,
+                effect.fade_out_function,
+                Some(ringing),
+            );
+
+            if let Some((intensity, next)) = color_ease.intensity_one_shot() {
+                finished = false;
+                if effect.target == target {
+                    self.update_next_frame_time(Some(next));
+                    result = Some(result.map_or(intensity, |prior| prior.max(intensity)));
                 }
             }
         }
-        None
+
+        if finished {
+            per_pane.bell_start.take();
+        }
+        result
     }
 
     pub fn filled_rectangle<'a>(
@@ -394,16 +439,17 @@ impl crate::TermWindow {
         (padding_left + left_gap, padding_top + top_gap)
     }
 
-    fn resolve_lock_glyph(
+    /// Shapes `text` to be drawn as the cursor
+    fn resolve_cursor_glyph(
         &self,
+        text: &str,
         style: &TextStyle,
         attrs: &CellAttributes,
         font: Option<&Rc<LoadedFont>>,
         gl_state: &RenderState,
         metrics: &RenderMetrics,
     ) -> anyhow::Result<Rc<CachedGlyph>> {
-        let fa_lock = "\u{f023}";
-        let line = Line::from_text(fa_lock, attrs, 0, None);
+        let line = Line::from_text(text, attrs, 0, None);
         let cluster = line.cluster(None);
         let shape_info = self.cached_cluster_shape(style, &cluster[0], gl_state, font, metrics)?;
         Ok(Rc::clone(&shape_info[0].glyph))
@@ -464,27 +510,44 @@ impl crate::TermWindow {
             padding.next_power_of_two()
         };
 
+        let top_left = image.top_left();
+        let bottom_right = image.bottom_right();
+        let (padding_left, padding_top, padding_right, padding_bottom) = image.padding();
+
+        let cell_width = params.render_metrics.cell_size.width as f32;
+        let cell_height = params.render_metrics.cell_size.height as f32;
+
+        // This cell shows a slice of the image.  Its placement records
+        // the size of the whole image in cells, so every slice uses the
+        // same copy of a large image, scaled to fit the current cells.
         let (sprite, next_due, _load_state) = gl_state
             .glyph_cache
             .borrow_mut()
-            .cached_image(image.image_data(), Some(padding), self.allow_images)
+            .cached_image_at_size(
+                image.image_data(),
+                Some(padding),
+                self.allow_images,
+                image
+                    .display_cells()
+                    .map(|cells| cells.pixel_size(cell_width, cell_height)),
+            )
             .context("cached_image")?;
         self.update_next_frame_time(next_due);
         let width = sprite.coords.size.width;
         let height = sprite.coords.size.height;
 
-        let top_left = image.top_left();
-        let bottom_right = image.bottom_right();
-
         // We *could* call sprite.texture.to_texture_coords() here,
         // but since that takes integer pixel coordinates, we'd
         // lose precision and end up with visual artifacts.
         // Instead, we compute the texture coords here in floating point.
+        // The integer part of the x coordinate selects the atlas page.
 
+        sprite.mark_used();
         let texture_width = sprite.texture.width() as f32;
         let texture_height = sprite.texture.height() as f32;
         let origin = TextureCoord::new(
-            (sprite.coords.origin.x as f32 + (*top_left.x * width as f32)) / texture_width,
+            sprite.page as f32
+                + (sprite.coords.origin.x as f32 + (*top_left.x * width as f32)) / texture_width,
             (sprite.coords.origin.y as f32 + (*top_left.y * height as f32)) / texture_height,
         );
 
@@ -496,16 +559,12 @@ impl crate::TermWindow {
         let texture_rect = TextureRect::new(origin, size);
 
         let mut quad = layers.allocate(layer_num)?;
-        let cell_width = params.render_metrics.cell_size.width as f32;
-        let cell_height = params.render_metrics.cell_size.height as f32;
         let pos_y = (self.dimensions.pixel_height as f32 / -2.) + params.top_pixel_y;
 
         let pos_x = (self.dimensions.pixel_width as f32 / -2.)
             + params.left_pixel_x
             + (cell_idx as f32 * cell_width);
 
-        let (padding_left, padding_top, padding_right, padding_bottom) = image.padding();
-
         quad.set_position(
             pos_x + padding_left as f32,
             pos_y + padding_top as f32,
@@ -513,9 +572,15 @@ impl crate::TermWindow {
             pos_y + cell_height + padding_top as f32 - padding_bottom as f32,
         );
         quad.set_hsv(hsv);
-        quad.set_fg_color(glyph_color);
+        // Images are drawn fully opaque, whatever the alpha of the text
+        quad.set_fg_color(LinearRgba(glyph_color.0, glyph_color.1, glyph_color.2, 1.0));
         quad.set_texture(texture_rect);
-        quad.set_has_color(true);
+        quad.set_is_image(match image.protocol() {
+            ImageProtocol::Kitty => self.config.kitty_image_filter,
+            ImageProtocol::Sixel => self.config.sixel_image_filter,
+            ImageProtocol::Iterm => self.config.iterm_image_filter,
+            ImageProtocol::Other => ImageFilter::Nearest,
+        });
 
         Ok(())
     }
@@ -561,6 +626,8 @@ impl crate::TermWindow {
                     bg_color_alt,
                     bg_color_mix,
                     cursor_shape: Some(CursorShape::Default),
+                    cursor_style: None,
+                    cursor_thickness: None,
                     cursor_border_color: bg_color,
                     cursor_border_color_alt: bg_color_alt,
                     cursor_border_mix: bg_color_mix,
@@ -571,7 +638,19 @@ impl crate::TermWindow {
                 self.dead_key_status != DeadKeyStatus::None || self.leader_is_active();
 
             if dead_key_or_leader && params.is_active_pane {
-                let (fg_color, bg_color) = if self.use_reverse_video_cursor(&params) {
+                let style = self.cursor_style(&params);
+                let (shape, cursor_style) = if self.dead_key_status == DeadKeyStatus::None {
+                    // Only the leader is active; that is always shown as a block
+                    (CursorShape::SteadyBlock, None)
+                } else {
+                    apply_cursor_style(style, CursorShape::SteadyBlock)
+                };
+                let is_block = cursor_style.is_none()
+                    && matches!(shape, CursorShape::BlinkingBlock | CursorShape::SteadyBlock);
+
+                let (fg_color, bg_color) = if !is_block {
+                    (params.fg_color, params.bg_color)
+                } else if self.use_reverse_video_cursor(&params) {
                     (params.bg_color, params.fg_color)
                 } else {
                     (params.cursor_fg, params.cursor_bg)
@@ -584,7 +663,7 @@ impl crate::TermWindow {
                     .resolved_palette
                     .compose_cursor
                     .map(|c| c.to_linear())
-                    .unwrap_or(bg_color);
+                    .unwrap_or(if is_block { bg_color } else { params.cursor_bg });
 
                 return ComputeCellFgBgResult {
                     fg_color,
@@ -593,7 +672,13 @@ impl crate::TermWindow {
                     bg_color,
                     bg_color_alt: bg_color,
                     bg_color_mix: 0.,
-                    cursor_shape: Some(CursorShape::Default),
+                    cursor_shape: Some(if is_block {
+                        CursorShape::Default
+                    } else {
+                        shape
+                    }),
+                    cursor_style,
+                    cursor_thickness: style.thickness,
                     cursor_border_color: color,
                     cursor_border_color_alt: color,
                     cursor_border_mix: 0.,
@@ -601,15 +686,19 @@ impl crate::TermWindow {
             }
         }
 
-        let (cursor_shape, visibility) = match params.cursor {
-            Some(cursor) => (
-                params
-                    .config
-                    .default_cursor_style
-                    .effective_shape(cursor.shape),
-                cursor.visibility,
-            ),
-            _ => (CursorShape::default(), CursorVisibility::Hidden),
+        let style = self.cursor_style(&params);
+        let (cursor_shape, visibility, cursor_style) = match params.cursor {
+            Some(cursor) => {
+                let (shape, cursor_style) = apply_cursor_style(
+                    style,
+                    params
+                        .config
+                        .default_cursor_style
+                        .effective_shape(cursor.shape),
+                );
+                (shape, cursor.visibility, cursor_style)
+            }
+            _ => (CursorShape::default(), CursorVisibility::Hidden, None),
         };
 
         let focused_and_active = self.focused.is_some() && params.is_active_pane;
@@ -719,6 +808,33 @@ impl crate::TermWindow {
             } else {
                 None
             },
+            cursor_style: if visibility == CursorVisibility::Visible && focused_and_active {
+                cursor_style
+            } else {
+                None
+            },
+            cursor_thickness: style.thickness,
+        }
+    }
+
+    /// Returns the entry from cursor_styles for the state of the pane
+    /// whose cursor is being drawn
+    fn cursor_style<'a>(&self, params: &ComputeCellFgBgParams<'a>) -> &'a CursorStyle {
+        let styles = &params.config.cursor_styles;
+        let pane = match params.pane {
+            Some(pane) => pane,
+            None => return &styles.normal,
+        };
+        if params.is_active_pane && self.dead_key_status.composing_text().is_some() {
+            &styles.ime_preedit
+        } else if pane
+            .downcast_ref::<CopyOverlay>()
+            .map(|copy| !copy.get_params().editing_search)
+            .unwrap_or(false)
+        {
+            &styles.copy_mode
+        } else {
+            &styles.normal
         }
     }
 
@@ -848,6 +964,133 @@ impl crate::TermWindow {
         Ok(glyph_info)
     }
 
+    /// Like cached_cluster_shape, except that long clusters missing
+    /// from the shape cache are shaped on the background shaping pool.
+    /// Returns None while that is in progress; the window is repainted
+    /// once the result has been added to the shape cache.
+    fn cached_cluster_shape_nonblocking(
+        &self,
+        style: &TextStyle,
+        cluster: &CellCluster,
+        gl_state: &RenderState,
+        metrics: &RenderMetrics,
+    ) -> anyhow::Result<Option<Rc<Vec<ShapedInfo>>>> {
+        if cluster.text.len() < BACKGROUND_SHAPING_THRESHOLD {
+            return self
+                .cached_cluster_shape(style, cluster, gl_state, None, metrics)
+                .map(Some);
+        }
+
+        let key = BorrowedShapeCacheKey {
+            style,
+            text: &cluster.text,
+        };
+        match self.lookup_cached_shape(&key) {
+            Some(Ok(info)) => return Ok(Some(info)),
+            Some(Err(err)) => return Err(err),
+            None => {}
+        }
+
+        let key = key.to_owned();
+        let generation = self.shape_generation;
+        match self.background_shapes.borrow().get(&key) {
+            Some(BackgroundShapeState::Pending(g)) if *g == generation => return Ok(None),
+            Some(BackgroundShapeState::Foreground(g)) if *g == generation => {
+                return self
+                    .cached_cluster_shape(style, cluster, gl_state, None, metrics)
+                    .map(Some);
+            }
+            _ => {}
+        }
+
+        let font = self.fonts.resolve_font(style)?;
+        let window = self.window.as_ref().unwrap().clone();
+        let notif_key = key.clone();
+        font.shape_in_background(
+            cluster.clone(),
+            BlockKey::filter_out_synthetic,
+            move |result| {
+                window.notify(TermWindowNotif::Apply(Box::new(move |tw| {
+                    tw.background_shape_done(notif_key, generation, result);
+                })));
+            },
+        )?;
+
+        let mut background_shapes = self.background_shapes.borrow_mut();
+        background_shapes.retain(|_, state| match state {
+            BackgroundShapeState::Pending(g) | BackgroundShapeState::Foreground(g) => {
+                *g == generation
+            }
+        });
+        background_shapes.insert(key, BackgroundShapeState::Pending(generation));
+        Ok(None)
+    }
+
+    /// Called on the gui thread when the background shaping pool has
+    /// finished with a cluster submitted by cached_cluster_shape_nonblocking
+    fn background_shape_done(
+        &mut self,
+        key: ShapeCacheKey,
+        generation: usize,
+        result: BackgroundShapeResult,
+    ) {
+        if self.background_shapes.borrow().get(&key)
+            != Some(&BackgroundShapeState::Pending(generation))
+        {
+            // Superseded by a change to the fonts or configuration
+            return;
+        }
+        if generation != self.shape_generation {
+            self.background_shapes.borrow_mut().remove(&key);
+            return;
+        }
+
+        let shaped = match result {
+            Ok(Some(infos)) => self.render_state.as_ref().and_then(|gl_state| {
+                let shaped = self.fonts.resolve_font(&key.style).and_then(|font| {
+                    let glyphs = self.glyph_infos_to_glyphs(
+                        &key.style,
+                        &mut gl_state.glyph_cache.borrow_mut(),
+                        &infos,
+                        &font,
+                        &self.render_metrics,
+                    )?;
+                    Ok(Rc::new(ShapedInfo::process(&infos, &glyphs)))
+                });
+                match shaped {
+                    Ok(shaped) => Some(shaped),
+                    Err(err) => {
+                        log::debug!("background shaping result not usable: {err:#}");
+                        None
+                    }
+                }
+            }),
+            Ok(None) => None,
+            Err(err) => {
+                log::debug!("background shaping failed: {err:#}");
+                None
+            }
+        };
+
+        // If the result could not be used, let the paint path shape it
+        // and deal with fallback and errors in the usual way
+        match shaped {
+            Some(shaped) => {
+                self.background_shapes.borrow_mut().remove(&key);
+                self.shape_cache.borrow_mut().put(key, Ok(shaped));
+            }
+            None => {
+                self.background_shapes
+                    .borrow_mut()
+                    .insert(key, BackgroundShapeState::Foreground(generation));
+            }
+        }
+
+        if let Some(window) = self.window.as_ref() {
+            window.invalidate();
+        }
+    }
+
     fn lookup_cached_shape(
         &self,
         key: &dyn ShapeCacheKeyTrait,
@@ -859,6 +1102,22 @@ impl crate::TermWindow {
         }
     }
 
+    /// Makes space in the texture atlas by evicting the glyphs and
+    /// images that were least recently used, which is much cheaper
+    /// than recreating it.  Returns false if nothing was evicted.
+    pub fn evict_texture_atlas(&mut self) -> anyhow::Result<bool> {
+        let evicted = match self.render_state.as_mut() {
+            Some(render_state) => render_state.evict_texture_atlas(&self.render_metrics)?,
+            None => false,
+        };
+        if evicted {
+            self.shape_generation += 1;
+            self.shape_cache.borrow_mut().clear();
+            self.line_to_ele_shape_cache.borrow_mut().clear();
+        }
+        Ok(evicted)
+    }
+
     pub fn recreate_texture_atlas(&mut self, size: Option<usize>) -> anyhow::Result<()> {
         self.shape_generation += 1;
         self.shape_cache.borrow_mut().clear();
@@ -904,6 +1163,46 @@ impl crate::TermWindow {
     }
 }
 
+/// Applies the shape from cursor_styles to the shape requested by the
+/// application, keeping its blinking.  The shapes that don't have an
+/// equivalent CursorShape are returned alongside a bar, so that the
+/// cell beneath them keeps its colors.
+fn apply_cursor_style(
+    style: &CursorStyle,
+    shape: CursorShape,
+) -> (CursorShape, Option<CursorStyleShape>) {
+    let blinking = shape.is_blinking();
+    let pick = |blinking_shape, steady_shape| {
+        if blinking {
+            blinking_shape
+        } else {
+            steady_shape
+        }
+    };
+    match &style.shape {
+        None => (shape, None),
+        Some(CursorStyleShape::Block) => (
+            pick(CursorShape::BlinkingBlock, CursorShape::SteadyBlock),
+            None,
+        ),
+        Some(CursorStyleShape::Bar) => {
+            (pick(CursorShape::BlinkingBar, CursorShape::SteadyBar), None)
+        }
+        Some(CursorStyleShape::Underline) => (
+            pick(CursorShape::BlinkingUnderline, CursorShape::SteadyUnderline),
+            None,
+        ),
+        Some(
+            other @ (CursorStyleShape::HollowBlock
+            | CursorStyleShape::HollowRing
+            | CursorStyleShape::Glyph(_)),
+        ) => (
+            pick(CursorShape::BlinkingBar, CursorShape::SteadyBar),
+            Some(other.clone()),
+        ),
+    }
+}
+
 fn resolve_fg_color_attr(
     attrs: &CellAttributes,
     fg: ColorAttribute,
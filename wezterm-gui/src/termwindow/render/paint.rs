@@ -1,7 +1,10 @@
 use crate::termwindow::{RenderFrame, TermWindowNotif};
 use ::window::bitmaps::atlas::OutOfTextureSpace;
-use ::window::WindowOps;
+use ::window::color::{LinearRgba, SrgbaTuple};
+use ::window::{Connection, ConnectionOps, Point, Rect, Size, WindowOps};
 use anyhow::Context;
+use config::BlurRegion;
+use mux::tab::PositionedPane;
 use smol::Timer;
 use std::time::{Duration, Instant};
 use wezterm_font::ClearShapeCache;
@@ -19,10 +22,14 @@ impl crate::TermWindow {
         // If nothing on screen needs animating, then we can avoid
         // invalidating as frequently
         *self.has_animation.borrow_mut() = None;
+        self.animate_unfocused.set(false);
         // Start with the assumption that we should allow images to render
         self.allow_images = AllowImage::Yes;
         // Clear dirty rectangles from previous frame for Wayland damage tracking
         self.dirty_rects.borrow_mut().clear();
+        if std::mem::take(&mut self.full_damage_pending) {
+            self.push_full_window_dirty_rect();
+        }
 
         let start = Instant::now();
 
@@ -36,6 +43,12 @@ impl crate::TermWindow {
             }
         }
 
+        if let Some(render_state) = self.render_state.as_ref() {
+            render_state.glyph_cache.borrow().atlas.advance_clock();
+        }
+
+        self.capture_tab_thumbnail_if_needed();
+
         'pass: for pass in 0.. {
             match self.paint_pass() {
                 Ok(_) => match self.render_state.as_mut().unwrap().allocated_more_quads() {
@@ -57,7 +70,12 @@ impl crate::TermWindow {
                         current_size,
                     }) = err.root_cause().downcast_ref::<OutOfTextureSpace>()
                     {
-                        let result = if pass == 0 {
+                        let result = if pass == 0 && self.evict_texture_atlas().unwrap_or(false) {
+                            // Evicting the glyphs that we haven't used for a while
+                            // is usually enough, and keeps the rest of the atlas
+                            log::trace!("evicted least recently used glyphs from atlas");
+                            Ok(())
+                        } else if pass == 0 {
                             // Let's try clearing out the atlas and trying again
                             // self.clear_texture_atlas()
                             log::trace!("recreate_texture_atlas");
@@ -108,6 +126,7 @@ impl crate::TermWindow {
         log::debug!("paint_impl before call_draw elapsed={:?}", start.elapsed());
 
         self.call_draw(frame).ok();
+        self.schedule_post_process_animation();
         self.last_frame_duration = start.elapsed();
         log::debug!(
             "paint_impl elapsed={:?}, fps={}",
@@ -119,8 +138,14 @@ impl crate::TermWindow {
 
         // If self.has_animation is some, then the last render detected
         // image attachments with multiple frames, so we also need to
-        // invalidate the viewport when the next frame is due
-        if self.focused.is_some() {
+        // invalidate the viewport when the next frame is due.
+        // There's no point animating when nobody is watching,
+        // unless a background layer has asked to keep playing,
+        // and never while the window can't be seen at all.
+        if (self.focused.is_some() || self.animate_unfocused.get())
+            && !self.user_idle
+            && self.window_state.is_visible()
+        {
             if let Some(next_due) = *self.has_animation.borrow() {
                 let prior = self.scheduled_animation.borrow_mut().take();
                 match prior {
@@ -155,6 +180,7 @@ impl crate::TermWindow {
 
                 self.ui_items.append(&mut ui_items);
             }
+            modal.paint(self)?;
         }
 
         Ok(())
@@ -274,6 +300,8 @@ impl crate::TermWindow {
 
         // Clear out UI item positions; we'll rebuild these as we render
         self.ui_items.clear();
+        // and likewise the regions of the panes that have an effect
+        self.pane_effect_regions.borrow_mut().clear();
 
         let panes = self.get_panes_to_render();
         let focused = self.focused.is_some();
@@ -289,6 +317,24 @@ impl crate::TermWindow {
         log::trace!("quad map elapsed {:?}", start.elapsed());
         metrics::histogram!("quad.map").record(start.elapsed());
 
+        // When the background is a single solid color, let the windowing
+        // system fill it in for us rather than drawing it ourselves
+        let solid_background = self.solid_window_background(&panes);
+        if solid_background != self.solid_background {
+            if let Some(window) = self.window.as_ref() {
+                window.set_solid_background(solid_background);
+            }
+            self.solid_background = solid_background;
+        }
+
+        let blur_region = self.blur_region();
+        if blur_region != self.blur_region {
+            if let Some(window) = self.window.as_ref() {
+                window.set_blur_region(blur_region);
+            }
+            self.blur_region = blur_region;
+        }
+
         let mut paint_terminal_background = false;
 
         // Render the full window background
@@ -321,6 +367,9 @@ impl crate::TermWindow {
                 // will render out through the padding so there
                 // should be no gaps that need filling in
             }
+            _ if self.solid_background.is_some() => {
+                // The windowing system is drawing it for us
+            }
             _ => {
                 paint_terminal_background = true;
             }
@@ -352,25 +401,33 @@ impl crate::TermWindow {
             .context("filled_rectangle for window background")?;
         }
 
-        for pos in panes {
+        for pos in &panes {
             if pos.is_active {
-                self.update_text_cursor(&pos);
+                self.update_text_cursor(pos);
                 if focused {
                     pos.pane.advise_focus();
                     mux::Mux::get().record_focus_for_current_identity(pos.pane.pane_id());
                 }
             }
-            self.paint_pane(&pos, &mut layers).context("paint_pane")?;
+            self.paint_pane(pos, &mut layers).context("paint_pane")?;
         }
 
         if let Some(pane) = self.get_active_pane_or_overlay() {
             let splits = self.get_splits();
+            let active = panes.iter().find(|pos| pos.is_active);
             for split in &splits {
-                self.paint_split(&mut layers, split, &pane)
+                self.paint_split(&mut layers, split, &pane, active)
                     .context("paint_split")?;
             }
         }
 
+        if self.show_minimap {
+            if let Some(active) = panes.iter().find(|pos| pos.is_active) {
+                self.paint_minimap(active, &mut layers)
+                    .context("paint_minimap")?;
+            }
+        }
+
         if self.show_tab_bar {
             self.paint_tab_bar(&mut layers).context("paint_tab_bar")?;
         }
@@ -378,8 +435,112 @@ impl crate::TermWindow {
         self.paint_window_borders(&mut layers)
             .context("paint_window_borders")?;
         drop(layers);
+        if self.show_tab_bar {
+            self.paint_bell_tab_titles()
+                .context("paint_bell_tab_titles")?;
+        }
+        self.paint_toasts().context("paint_toasts")?;
         self.paint_modal().context("paint_modal")?;
+        if self.config.debug_visualize_damage {
+            self.paint_damage_visualization()
+                .context("paint_damage_visualization")?;
+        }
+        if self.show_debug_hud {
+            self.paint_debug_hud().context("paint_debug_hud")?;
+        }
+
+        Ok(())
+    }
+
+    /// Tints the rectangles that this frame will report as damaged,
+    /// cycling through a few colors so that consecutive frames can
+    /// be told apart
+    fn paint_damage_visualization(&mut self) -> anyhow::Result<()> {
+        const TINTS: [(f32, f32, f32); 3] = [(1., 0., 1.), (0., 1., 1.), (1., 1., 0.)];
+        self.damage_visualization_frame = self.damage_visualization_frame.wrapping_add(1);
+        let (r, g, b) = TINTS[self.damage_visualization_frame % TINTS.len()];
+        let color = LinearRgba::with_components(r, g, b, 0.25);
+
+        let gl_state = self.render_state.as_ref().unwrap();
+        let layer = gl_state
+            .layer_for_zindex(110)
+            .context("layer_for_zindex(110)")?;
+        let mut layers = layer.quad_allocator();
+        for &(x, y, width, height) in self.dirty_rects.borrow().iter() {
+            self.filled_rectangle(
+                &mut layers,
+                0,
+                euclid::rect(x as f32, y as f32, width as f32, height as f32),
+                color,
+            )?;
+        }
 
         Ok(())
     }
+
+    /// Returns true if the frame is transformed on its way to the
+    /// display, by rendering it as scRGB, converting it to the color
+    /// profile of the display, or applying the post_process_shader
+    fn output_is_transformed(&self) -> bool {
+        self.hdr
+            || self
+                .webgpu
+                .as_ref()
+                .map_or(false, |webgpu| webgpu.has_color_lut.get())
+            || self.config.post_process_shader.is_some()
+    }
+
+    /// If the window background consists of nothing more than a single
+    /// solid color, and the windowing system is able to draw that for us,
+    /// returns that color.
+    /// A background drawn by the windowing system wouldn't pass through
+    /// the transformations applied to the rest of the frame, so in that
+    /// case we render it ourselves.
+    fn solid_window_background(&self, panes: &[PositionedPane]) -> Option<SrgbaTuple> {
+        if !self.window_background.is_empty()
+            || panes.len() != 1
+            || self.config.use_box_model_render
+            || !panes[0].pane.get_pane_background().is_default()
+            || self.output_is_transformed()
+        {
+            return None;
+        }
+        if !Connection::get()?.supports_solid_background() {
+            return None;
+        }
+        Some(
+            panes[0]
+                .pane
+                .palette()
+                .background
+                .mul_alpha(self.config.window_background_opacity),
+        )
+    }
+
+    /// If the background blur should be restricted to the terminal
+    /// cells, rather than the whole window, returns their area
+    fn blur_region(&self) -> Option<Rect> {
+        if !self.config.kde_window_background_blur
+            || self.config.kde_window_background_blur_region != BlurRegion::Content
+        {
+            return None;
+        }
+        let (padding_left, padding_top) = self.padding_left_top();
+        let border = self.get_os_border();
+        let tab_bar_height = if self.show_tab_bar && !self.config.tab_bar_at_bottom {
+            self.tab_bar_pixel_height().unwrap_or(0.)
+        } else {
+            0.
+        };
+        Some(Rect::new(
+            Point::new(
+                (border.left.get() as f32 + padding_left) as isize,
+                (border.top.get() as f32 + tab_bar_height + padding_top) as isize,
+            ),
+            Size::new(
+                self.terminal_size.pixel_width as isize,
+                self.terminal_size.pixel_height as isize,
+            ),
+        ))
+    }
 }
@@ -1,4 +1,6 @@
+use crate::overlay::CopyOverlay;
 use crate::quad::{HeapQuadAllocator, QuadTrait, TripleLayerQuadAllocator};
+use crate::scrollbar::ScrollMarker;
 use crate::selection::SelectionRange;
 use crate::termwindow::box_model::*;
 use crate::termwindow::render::{
@@ -9,11 +11,14 @@ use crate::termwindow::{ScrollHit, UIItem, UIItemType};
 use ::window::bitmaps::TextureRect;
 use ::window::DeadKeyStatus;
 use anyhow::Context;
-use config::VisualBellTarget;
-use mux::pane::{PaneId, WithPaneLines};
+use config::{ConfigHandle, HsbTransform, VisualBellTarget};
+use mux::pane::{CachePolicy, Pane, PaneId, WithPaneLines};
 use mux::renderable::{RenderableDimensions, StableCursorPosition};
 use mux::tab::PositionedPane;
 use ordered_float::NotNan;
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::sync::Arc;
 use std::time::Instant;
 use wezterm_dynamic::Value;
 use wezterm_term::color::{ColorAttribute, ColorPalette};
@@ -21,6 +26,89 @@ use wezterm_term::{Line, StableRowIndex};
 use window::color::LinearRgba;
 
 impl crate::TermWindow {
+    /// Whether the lines of `pane` are shaped with ligatures: as set
+    /// by window:set_pane_ligatures, or else unless its foreground
+    /// process is listed in disable_ligatures_for_processes_named
+    pub fn pane_ligatures_enabled(&self, pane: &Arc<dyn Pane>) -> bool {
+        if let Some(enabled) = self.pane_state(pane.pane_id()).ligatures {
+            return enabled;
+        }
+        let names = &self.config.disable_ligatures_for_processes_named;
+        if names.is_empty() {
+            return true;
+        }
+        match pane.get_foreground_process_name(CachePolicy::AllowStale) {
+            Some(path) => {
+                let name = std::path::Path::new(&path)
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or(path);
+                !names.contains(&name)
+            }
+            None => true,
+        }
+    }
+
+    /// Overrides whether the lines of the pane are shaped with
+    /// ligatures; None reverts to disable_ligatures_for_processes_named
+    pub fn set_pane_ligatures(&mut self, pane_id: PaneId, enabled: Option<bool>) {
+        self.pane_state(pane_id).ligatures = enabled;
+        if let Some(window) = self.window.as_ref() {
+            window.invalidate();
+        }
+    }
+
+    /// The configuration used to shape lines without ligatures.
+    /// Its fonts are distinct from those of the window configuration,
+    /// so the glyphs shaped either way are cached side by side.
+    fn ligature_free_config(&mut self) -> ConfigHandle {
+        match &self.ligature_free_config {
+            Some(config) if config.generation() == self.config.generation() => config.clone(),
+            _ => {
+                let config = self.config.with_ligatures_disabled();
+                self.ligature_free_config.replace(config.clone());
+                config
+            }
+        }
+    }
+
+    /// Returns the markers to show on the scroll bar for `pane`, keyed
+    /// by their offset from the top of the scroll bar.  Where several
+    /// rows share an offset, the most important kind of marker is kept.
+    fn scroll_bar_markers(
+        &mut self,
+        pane: &Arc<dyn Pane>,
+        max_thumb_height: usize,
+    ) -> BTreeMap<usize, (StableRowIndex, ScrollMarker)> {
+        let mut rows: Vec<(StableRowIndex, ScrollMarker)> = self
+            .get_semantic_prompt_zones(pane)
+            .iter()
+            .map(|&row| (row, ScrollMarker::Prompt))
+            .collect();
+        if let Some(copy) = pane.downcast_ref::<CopyOverlay>() {
+            rows.extend(
+                copy.search_result_rows()
+                    .into_iter()
+                    .map(|row| (row, ScrollMarker::SearchMatch)),
+            );
+        }
+        rows.extend(
+            pane.get_failed_command_rows()
+                .into_iter()
+                .map(|row| (row, ScrollMarker::Error)),
+        );
+
+        let mut markers = BTreeMap::new();
+        for (row, kind) in rows {
+            let top = ScrollHit::marker_top(&**pane, row, max_thumb_height);
+            let entry = markers.entry(top).or_insert((row, kind));
+            if kind > entry.1 {
+                *entry = (row, kind);
+            }
+        }
+        markers
+    }
+
     fn paint_pane_box_model(&mut self, pos: &PositionedPane) -> anyhow::Result<()> {
         let computed = self.build_pane(pos)?;
         let mut ui_items = computed.ui_items();
@@ -85,6 +173,7 @@ impl crate::TermWindow {
         let pane_id = pos.pane.pane_id();
         let current_viewport = self.get_viewport(pane_id);
         let dims = pos.pane.get_dimensions();
+        let viewport_top = current_viewport.unwrap_or(dims.physical_top);
 
         let gl_state = self.render_state.as_ref().unwrap();
 
@@ -93,8 +182,17 @@ impl crate::TermWindow {
         let white_space = gl_state.util_sprites.white_space.texture_coords();
         let filled_box = gl_state.util_sprites.filled_box.texture_coords();
 
-        let window_is_transparent =
-            !self.window_background.is_empty() || config.window_background_opacity != 1.0;
+        // The background of the pane may be overridden by the
+        // application or via pane:set_background
+        let pane_background = pos.pane.get_pane_background();
+        let background_color = pane_background.color.unwrap_or(palette.background);
+        let background_opacity = pane_background
+            .opacity
+            .unwrap_or(config.window_background_opacity);
+
+        let window_is_transparent = !self.window_background.is_empty()
+            || background_opacity != 1.0
+            || pane_background.image.is_some();
 
         let default_bg = palette
             .resolve_bg(ColorAttribute::Default)
@@ -151,25 +249,51 @@ impl crate::TermWindow {
             )
         };
 
-        if self.window_background.is_empty() {
-            // Per-pane, palette-specified background
+        // The effect of an inactive pane, such as dimming, is applied to its
+        // region of the window while compositing, excluding the tab bar.
+        // Otherwise, we have to adjust the colors of its quads instead.
+        let hsv = {
+            let max_y = self.dimensions.pixel_height as f32 - bottom_bar_height;
+            let effect_rect = euclid::rect(
+                background_rect.origin.x,
+                background_rect.origin.y,
+                background_rect.size.width,
+                background_rect
+                    .size
+                    .height
+                    .min(max_y - background_rect.origin.y),
+            );
+            self.apply_pane_effect(pos, effect_rect)
+        };
+
+        if let Some(image) = &pane_background.image {
+            // Per-pane background image, in place of the window background
+            if let Some(mut quad) = self
+                .paint_pane_background_image(
+                    layers,
+                    pane_id,
+                    image,
+                    background_rect,
+                    LinearRgba::with_components(1., 1., 1., background_opacity),
+                )
+                .context("paint_pane_background_image")?
+            {
+                quad.set_hsv(hsv);
+            }
+        } else if !pane_background.is_default()
+            || (self.window_background.is_empty() && self.solid_background.is_none())
+        {
+            // Per-pane, palette-specified or overridden background
 
             let mut quad = self
                 .filled_rectangle(
                     layers,
                     0,
                     background_rect,
-                    palette
-                        .background
-                        .to_linear()
-                        .mul_alpha(config.window_background_opacity),
+                    background_color.to_linear().mul_alpha(background_opacity),
                 )
                 .context("filled_rectangle")?;
-            quad.set_hsv(if pos.is_active {
-                None
-            } else {
-                Some(config.inactive_pane_hsb)
-            });
+            quad.set_hsv(hsv);
         }
 
         {
@@ -195,10 +319,9 @@ impl crate::TermWindow {
                 } else {
                     // otherwise We'll interpolate between the background color
                     // and the the target color
-                    let (r1, g1, b1, a) = palette
-                        .background
+                    let (r1, g1, b1, a) = background_color
                         .to_linear()
-                        .mul_alpha(config.window_background_opacity)
+                        .mul_alpha(background_opacity)
                         .tuple();
                     LinearRgba::with_components(
                         r1 + (r - r1) * intensity,
@@ -213,11 +336,7 @@ impl crate::TermWindow {
                     .filled_rectangle(layers, 0, background_rect, background)
                     .context("filled_rectangle")?;
 
-                quad.set_hsv(if pos.is_active {
-                    None
-                } else {
-                    Some(config.inactive_pane_hsb)
-                });
+                quad.set_hsv(hsv);
             }
         }
 
@@ -231,12 +350,14 @@ impl crate::TermWindow {
 
             let min_height = self.min_scroll_bar_height();
 
+            let max_thumb_height = self
+                .dimensions
+                .pixel_height
+                .saturating_sub(thumb_y_offset + border.bottom.get() + bottom_bar_height as usize);
             let info = ScrollHit::thumb(
                 &*pos.pane,
                 current_viewport,
-                self.dimensions.pixel_height.saturating_sub(
-                    thumb_y_offset + border.bottom.get() + bottom_bar_height as usize,
-                ),
+                max_thumb_height,
                 min_height as usize,
             );
             let abs_thumb_top = thumb_y_offset + info.top;
@@ -249,7 +370,8 @@ impl crate::TermWindow {
 
             let thumb_x = self.dimensions.pixel_width - padding as usize - border.right.get();
 
-            // Register the scroll bar location
+            // Register the scroll bar location.  The thumb is registered
+            // last so that it takes precedence over any markers beneath it
             self.ui_items.push(UIItem {
                 x: thumb_x,
                 width: padding as usize,
@@ -257,13 +379,6 @@ impl crate::TermWindow {
                 height: info.top,
                 item_type: UIItemType::AboveScrollThumb,
             });
-            self.ui_items.push(UIItem {
-                x: thumb_x,
-                width: padding as usize,
-                y: abs_thumb_top,
-                height: thumb_size,
-                item_type: UIItemType::ScrollThumb,
-            });
             self.ui_items.push(UIItem {
                 x: thumb_x,
                 width: padding as usize,
@@ -275,6 +390,30 @@ impl crate::TermWindow {
                 item_type: UIItemType::BelowScrollThumb,
             });
 
+            let markers = if self.config.show_scroll_bar_markers {
+                self.scroll_bar_markers(&pos.pane, max_thumb_height)
+            } else {
+                BTreeMap::new()
+            };
+            let marker_height = (self.render_metrics.underline_height as usize * 2).max(2);
+            for (marker_top, (row, _)) in &markers {
+                self.ui_items.push(UIItem {
+                    x: thumb_x,
+                    width: padding as usize,
+                    y: thumb_y_offset + marker_top,
+                    height: marker_height,
+                    item_type: UIItemType::ScrollMarker(*row),
+                });
+            }
+
+            self.ui_items.push(UIItem {
+                x: thumb_x,
+                width: padding as usize,
+                y: abs_thumb_top,
+                height: thumb_size,
+                item_type: UIItemType::ScrollThumb,
+            });
+
             self.filled_rectangle(
                 layers,
                 2,
@@ -287,6 +426,43 @@ impl crate::TermWindow {
                 color,
             )
             .context("filled_rectangle")?;
+
+            // The markers are drawn over the thumb so that they
+            // remain visible while it is over them
+            let resolved_palette = &self.config.resolved_palette;
+            let marker_color = |color: Option<config::RgbaColor>, default: LinearRgba| {
+                color.map_or(default, |c| c.to_linear())
+            };
+            let search_color = marker_color(
+                resolved_palette.scrollbar_search_marker,
+                LinearRgba::with_srgba(0xe5, 0xc0, 0x7b, 0xff),
+            );
+            let prompt_color = marker_color(
+                resolved_palette.scrollbar_prompt_marker,
+                LinearRgba::with_srgba(0x80, 0x80, 0x80, 0xff),
+            );
+            let error_color = marker_color(
+                resolved_palette.scrollbar_error_marker,
+                LinearRgba::with_srgba(0xe0, 0x6c, 0x75, 0xff),
+            );
+            for (marker_top, (_, kind)) in markers {
+                self.filled_rectangle(
+                    layers,
+                    2,
+                    euclid::rect(
+                        thumb_x as f32,
+                        (thumb_y_offset + marker_top) as f32,
+                        padding,
+                        marker_height as f32,
+                    ),
+                    match kind {
+                        ScrollMarker::SearchMatch => search_color,
+                        ScrollMarker::Prompt => prompt_color,
+                        ScrollMarker::Error => error_color,
+                    },
+                )
+                .context("filled_rectangle")?;
+            }
         }
 
         let (selrange, rectangular) = {
@@ -308,14 +484,58 @@ impl crate::TermWindow {
                 None => dims.physical_top..dims.physical_top + dims.viewport_rows as StableRowIndex,
             };
 
-            pos.pane
-                .apply_hyperlinks(stable_range.clone(), &self.config.hyperlink_rules);
+            // While smooth scrolling, the lines are drawn offset from their
+            // true position and clipped to the pane, so additional rows
+            // are needed to fill in the edge that is being revealed
+            let scroll_offset = self.smooth_scroll_offset(pane_id);
+            let scroll_offset_rows = scroll_offset.unwrap_or(0.);
+            let render_range = if scroll_offset_rows > 0. {
+                (stable_range.start - scroll_offset_rows.ceil() as StableRowIndex)
+                    .max(dims.scrollback_top)..stable_range.end
+            } else if scroll_offset_rows < 0. {
+                stable_range.start
+                    ..(stable_range.end + (-scroll_offset_rows).ceil() as StableRowIndex)
+                        .min(dims.physical_top + dims.viewport_rows as StableRowIndex)
+            } else {
+                stable_range.clone()
+            };
+            let pane_top_pixel_y = top_pixel_y + pos.top as f32 * cell_height;
+            let scroll_clip = if scroll_offset.is_some() {
+                let pane_top = pane_top_pixel_y - self.dimensions.pixel_height as f32 / 2.;
+                self.dirty_rects.borrow_mut().push((
+                    background_rect.min_x() as i32,
+                    pane_top_pixel_y as i32,
+                    background_rect.width().ceil() as i32,
+                    (dims.viewport_rows as f32 * cell_height).ceil() as i32,
+                ));
+                if scroll_offset_rows != 0. {
+                    self.update_next_frame_time(Some(
+                        Instant::now() + self.animation_frame_interval(),
+                    ));
+                }
+                Some((
+                    scroll_offset_rows * cell_height,
+                    pane_top..pane_top + dims.viewport_rows as f32 * cell_height,
+                ))
+            } else {
+                None
+            };
+
+            let pane_rules = pos.pane.get_hyperlink_rules();
+            pos.pane.apply_hyperlinks(
+                render_range.clone(),
+                pane_rules
+                    .as_deref()
+                    .unwrap_or(&self.config.hyperlink_rules),
+            );
 
             struct LineRender<'a, 'b> {
                 term_window: &'a mut crate::TermWindow,
                 selrange: Option<SelectionRange>,
                 rectangular: bool,
                 dims: RenderableDimensions,
+                viewport_top: StableRowIndex,
+                scroll_clip: Option<(f32, Range<f32>)>,
                 top_pixel_y: f32,
                 left_pixel_x: f32,
                 pane_pixel_width: f32,
@@ -334,6 +554,10 @@ impl crate::TermWindow {
                 white_space: TextureRect,
                 filled_box: TextureRect,
                 window_is_transparent: bool,
+                hsv: Option<HsbTransform>,
+                /// The configuration whose fonts are used to shape the lines
+                config: ConfigHandle,
+                ligatures: bool,
                 layers: &'a mut TripleLayerQuadAllocator<'b>,
                 error: Option<anyhow::Error>,
             }
@@ -347,11 +571,22 @@ impl crate::TermWindow {
             // rect width which includes padding/border adjustments.
             let pane_pixel_width = dims.cols as f32 * self.render_metrics.cell_size.width as f32;
 
+            let ligatures = self.pane_ligatures_enabled(&pos.pane);
+            let config = if ligatures {
+                self.config.clone()
+            } else {
+                self.ligature_free_config()
+            };
+
+            self.pane_state(pane_id).double_width_rows.clear();
+
             let mut render = LineRender {
                 term_window: self,
                 selrange,
                 rectangular,
                 dims,
+                viewport_top: stable_range.start,
+                scroll_clip,
                 top_pixel_y,
                 left_pixel_x,
                 pane_pixel_width,
@@ -370,10 +605,26 @@ impl crate::TermWindow {
                 white_space,
                 filled_box,
                 window_is_transparent,
+                hsv,
+                config,
+                ligatures,
                 layers,
                 error: None,
             };
 
+            fn apply_line_quads(
+                quads: &HeapQuadAllocator,
+                layers: &mut TripleLayerQuadAllocator,
+                scroll_clip: &Option<(f32, Range<f32>)>,
+            ) -> anyhow::Result<()> {
+                match scroll_clip {
+                    Some((y_offset, clip)) => {
+                        quads.apply_to_clipped(layers, *y_offset, clip.clone())
+                    }
+                    None => quads.apply_to(layers),
+                }
+            }
+
             impl<'a, 'b> LineRender<'a, 'b> {
                 fn render_line(
                     &mut self,
@@ -382,9 +633,30 @@ impl crate::TermWindow {
                     line: &mut &mut Line,
                 ) -> anyhow::Result<()> {
                     let stable_row = stable_top + line_idx as StableRowIndex;
+                    if !line.is_single_width() {
+                        self.term_window
+                            .pane_state(self.pane_id)
+                            .double_width_rows
+                            .push(stable_row);
+                    }
+                    // Relative to the top of the viewport; this is negative
+                    // for rows above it that are revealed while scrolling
+                    let row = stable_row - self.viewport_top;
                     let selrange = self
                         .selrange
                         .map_or(0..0, |sel| sel.cols_for_row(stable_row, self.rectangular));
+                    // A block that extends to the end of every line is
+                    // only shown as far as the content of each line
+                    let selrange = if self.rectangular && selrange.end == usize::MAX {
+                        let end = line
+                            .visible_cells()
+                            .filter(|cell| cell.str() != " ")
+                            .last()
+                            .map_or(0, |cell| cell.cell_index() + cell.width());
+                        selrange.start..end.max(selrange.start)
+                    } else {
+                        selrange
+                    };
                     // Constrain to the pane width!
                     let selrange = selrange.start..selrange.end.min(self.dims.cols);
 
@@ -404,10 +676,8 @@ impl crate::TermWindow {
                                 cursor_is_default_color: self.cursor_is_default_color,
                             }),
                             match (self.pos.is_active, &self.term_window.dead_key_status) {
-                                (true, DeadKeyStatus::Composing(composing)) => {
-                                    Some(composing.to_string())
-                                }
-                                _ => None,
+                                (true, DeadKeyStatus::None) | (false, _) => None,
+                                (true, status) => Some(status.clone()),
                             },
                             if self.term_window.config.detect_password_input {
                                 match self.pos.pane.get_metadata() {
@@ -442,11 +712,12 @@ impl crate::TermWindow {
                         cursor,
                         shape_hash,
                         top_pixel_y: NotNan::new(self.top_pixel_y).unwrap()
-                            + (line_idx + self.pos.top) as f32
+                            + (row + self.pos.top as StableRowIndex) as f32
                                 * self.term_window.render_metrics.cell_size.height as f32,
                         left_pixel_x: NotNan::new(self.left_pixel_x).unwrap(),
                         phys_line_idx: line_idx,
                         reverse_video: self.dims.reverse_video,
+                        ligatures: self.ligatures,
                     };
 
                     // DAMAGE TRACKING: Check if line is dirty (needs re-rendering).
@@ -477,10 +748,12 @@ impl crate::TermWindow {
                                 false
                             };
                             if !expired && !hover_changed {
-                                cached_quad
-                                    .layers
-                                    .apply_to(self.layers)
-                                    .context("cached_quad.layers.apply_to")?;
+                                apply_line_quads(
+                                    &cached_quad.layers,
+                                    self.layers,
+                                    &self.scroll_clip,
+                                )
+                                .context("cached_quad.layers.apply_to")?;
                                 self.term_window.update_next_frame_time(cached_quad.expires);
                                 return Ok(());
                             }
@@ -492,18 +765,12 @@ impl crate::TermWindow {
 
                     let shape_key = LineToEleShapeCacheKey {
                         shape_hash,
+                        ligatures: self.ligatures,
                         shape_generation: quad_key.shape_generation,
-                        composing: if self.cursor.y == stable_row && self.pos.is_active {
-                            if let DeadKeyStatus::Composing(composing) =
-                                &self.term_window.dead_key_status
-                            {
-                                Some((self.cursor.x, composing.to_string()))
-                            } else {
-                                None
-                            }
-                        } else {
-                            None
-                        },
+                        composing: quad_key
+                            .composing
+                            .as_ref()
+                            .map(|status| (self.cursor.x, status.clone())),
                     };
 
                     let render_result = self
@@ -522,10 +789,11 @@ impl crate::TermWindow {
                                 cursor: &self.cursor,
                                 palette: &self.palette,
                                 dims: &self.dims,
-                                config: &self.term_window.config,
+                                config: &self.config,
                                 cursor_border_color: self.cursor_border_color,
                                 foreground: self.foreground,
                                 is_active: self.pos.is_active,
+                                hsv: self.hsv,
                                 pane: Some(&self.pos.pane),
                                 selection_fg: self.selection_fg,
                                 selection_bg: self.selection_bg,
@@ -553,35 +821,40 @@ impl crate::TermWindow {
                     let expires = self.term_window.has_animation.borrow().as_ref().cloned();
                     self.term_window.update_next_frame_time(next_due);
 
-                    buf.apply_to(self.layers)
+                    apply_line_quads(&buf, self.layers, &self.scroll_clip)
                         .context("HeapQuadAllocator::apply_to")?;
 
-                    let quad_value = LineQuadCacheValue {
-                        layers: buf,
-                        expires,
-                        invalidate_on_hover_change: render_result.invalidate_on_hover_change,
-                        current_highlight: if render_result.invalidate_on_hover_change {
-                            self.term_window.current_highlight.clone()
-                        } else {
-                            None
-                        },
-                    };
+                    // Text that is still being shaped in the background was
+                    // rendered as blank space, so leave the line uncached and
+                    // dirty; it is rendered again once shaping completes.
+                    if !render_result.shaping_pending {
+                        let quad_value = LineQuadCacheValue {
+                            layers: buf,
+                            expires,
+                            invalidate_on_hover_change: render_result.invalidate_on_hover_change,
+                            current_highlight: if render_result.invalidate_on_hover_change {
+                                self.term_window.current_highlight.clone()
+                            } else {
+                                None
+                            },
+                        };
 
-                    self.term_window
-                        .line_quad_cache
-                        .borrow_mut()
-                        .put(quad_key, quad_value);
+                        self.term_window
+                            .line_quad_cache
+                            .borrow_mut()
+                            .put(quad_key, quad_value);
 
-                    // DAMAGE TRACKING: Mark line as clean after successful render.
-                    // This ensures we can skip re-rendering this line on the next frame
-                    // unless it's modified (which will set the dirty flag again).
-                    line.mark_clean();
+                        // DAMAGE TRACKING: Mark line as clean after successful render.
+                        // This ensures we can skip re-rendering this line on the next frame
+                        // unless it's modified (which will set the dirty flag again).
+                        line.mark_clean();
+                    }
 
                     // WAYLAND DAMAGE RECTANGLES: Record this line as a dirty rect for compositor.
                     // Convert line index to pixel coordinates for Wayland damage_buffer().
                     if is_dirty || is_cursor_line || has_selection {
                         let cell_height = self.term_window.render_metrics.cell_size.height as i32;
-                        let y = self.top_pixel_y as i32 + (line_idx as i32 * cell_height);
+                        let y = self.top_pixel_y as i32 + (row as i32 * cell_height);
                         let width = self.pane_pixel_width as i32;
                         self.term_window.dirty_rects.borrow_mut().push((
                             self.left_pixel_x as i32,
@@ -606,12 +879,63 @@ impl crate::TermWindow {
                 }
             }
 
-            pos.pane.with_lines_mut(stable_range.clone(), &mut render);
+            pos.pane.with_lines_mut(render_range.clone(), &mut render);
             if let Some(error) = render.error.take() {
                 return Err(error).context("error while calling with_lines_mut");
             }
+
+            if self.config.show_command_status_markers {
+                // Mark the prompts of completed commands in the gutter
+                // to the left of the pane, using green for success and
+                // red for failure
+                let marker_width = (cell_width / 4.).max(1.);
+                let marker_x = (left_pixel_x - marker_width).max(background_rect.min_x());
+                let y_offset = scroll_offset_rows * cell_height;
+                let clip =
+                    pane_top_pixel_y..pane_top_pixel_y + dims.viewport_rows as f32 * cell_height;
+                let success_color = palette.colors.0[2].to_linear();
+                let failure_color = palette.colors.0[1].to_linear();
+
+                let markers = self.get_command_status_rows(&pos.pane).to_vec();
+                for (row, status) in markers {
+                    if !render_range.contains(&row) {
+                        continue;
+                    }
+                    let y = pane_top_pixel_y
+                        + (row - stable_range.start) as f32 * cell_height
+                        + y_offset;
+                    if y + cell_height <= clip.start || y >= clip.end {
+                        continue;
+                    }
+                    let top = y.max(clip.start);
+                    let bottom = (y + cell_height).min(clip.end);
+                    let mut quad = self
+                        .filled_rectangle(
+                            layers,
+                            0,
+                            euclid::rect(marker_x, top, marker_width, bottom - top),
+                            if status == 0 {
+                                success_color
+                            } else {
+                                failure_color
+                            },
+                        )
+                        .context("filled_rectangle")?;
+                    quad.set_hsv(hsv);
+                }
+            }
         }
 
+        self.paint_bell_effects(
+            pos,
+            layers,
+            background_rect,
+            top_pixel_y,
+            &cursor,
+            viewport_top,
+        )
+        .context("paint_bell_effects")?;
+
         /*
         if let Some(zone) = zone {
             // TODO: render a thingy to jump to prior prompt
@@ -696,6 +1020,7 @@ impl crate::TermWindow {
         );
 
         let palette = pos.pane.palette();
+        let pane_background = pos.pane.get_pane_background();
 
         // TODO: visual bell background layer
         // TODO: scrollbar
@@ -709,11 +1034,19 @@ impl crate::TermWindow {
             border_corners: None,
             colors: ElementColors {
                 border: BorderColor::default(),
-                bg: if self.window_background.is_empty() {
-                    palette
-                        .background
+                bg: if self.window_background.is_empty()
+                    || pane_background.color.is_some()
+                    || pane_background.opacity.is_some()
+                {
+                    pane_background
+                        .color
+                        .unwrap_or(palette.background)
                         .to_linear()
-                        .mul_alpha(self.config.window_background_opacity)
+                        .mul_alpha(
+                            pane_background
+                                .opacity
+                                .unwrap_or(self.config.window_background_opacity),
+                        )
                         .into()
                 } else {
                     InheritableColor::Inherited
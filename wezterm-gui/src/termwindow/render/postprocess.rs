@@ -0,0 +1,664 @@
+// Clippy hates the implement_vertex macro and won't let me scope
+// this warning to its use
+#![allow(clippy::unneeded_field_pattern)]
+
+use crate::renderstate::{RenderContext, RenderState};
+use crate::termwindow::webgpu::WebGpuState;
+use crate::uniforms::UniformBuilder;
+use ::window::glium;
+use ::window::glium::backend::Context as GliumContext;
+use ::window::glium::texture::SrgbTexture2d;
+use ::window::glium::uniforms::{
+    MagnifySamplerFilter, MinifySamplerFilter, Sampler, SamplerWrapFunction,
+};
+use ::window::glium::Surface;
+use ::window::{RectF, WindowOps};
+use anyhow::Context;
+use config::{HsbTransform, PaneEffect};
+use mux::pane::PaneId;
+use mux::tab::PositionedPane;
+use std::cell::RefCell;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use wgpu::util::DeviceExt;
+
+#[derive(Copy, Clone)]
+struct PostProcessVertex {
+    position: [f32; 2],
+}
+::window::glium::implement_vertex!(PostProcessVertex, position);
+
+/// Matches PostProcessUniform in post-process.wgsl
+#[repr(C)]
+#[derive(Copy, Clone, Default, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct PostProcessUniform {
+    cursor: [f32; 4],
+    resolution: [f32; 2],
+    time: f32,
+    pane_count: u32,
+    pane_rects: [[f32; 4]; MAX_PANE_EFFECTS],
+    pane_effects: [[f32; 4]; MAX_PANE_EFFECTS],
+}
+
+/// The size of the pane_rects and pane_effects arrays
+/// in the post processing preludes
+const MAX_PANE_EFFECTS: usize = 16;
+
+/// A pane whose region of the window has an effect applied
+/// while compositing; see `inactive_pane_hsb`
+#[derive(Clone, Debug)]
+pub struct PaneEffectRegion {
+    /// The position and size of the pane in pixels,
+    /// relative to the top left of the window
+    pub rect: [f32; 4],
+    pub effect: PaneEffect,
+}
+
+impl PaneEffectRegion {
+    fn effect(&self) -> [f32; 4] {
+        [
+            self.effect.hsb.hue,
+            self.effect.hsb.saturation,
+            self.effect.hsb.brightness,
+            self.effect.blur,
+        ]
+    }
+}
+
+/// The values that are passed to the post_process_shader
+pub struct PostProcessInputs {
+    /// Seconds since the window was created
+    pub time: f32,
+    pub width: usize,
+    pub height: usize,
+    /// The position and size of the cursor in pixels,
+    /// relative to the top left of the window
+    pub cursor: [f32; 4],
+    /// The panes that have an effect applied
+    pub panes: Vec<PaneEffectRegion>,
+}
+
+enum Backend {
+    Glium {
+        context: Rc<GliumContext>,
+        program: glium::Program,
+        vertices: glium::VertexBuffer<PostProcessVertex>,
+        texture: RefCell<Option<Rc<SrgbTexture2d>>>,
+    },
+    WebGpu {
+        state: Rc<WebGpuState>,
+        shader: wgpu::ShaderModule,
+        uniform_layout: wgpu::BindGroupLayout,
+        pipeline_layout: wgpu::PipelineLayout,
+        /// The pipeline for the current surface format, which
+        /// changes when switching between sdr and hdr output
+        pipeline: RefCell<(wgpu::TextureFormat, wgpu::RenderPipeline)>,
+        texture: RefCell<Option<wgpu::Texture>>,
+    },
+}
+
+/// A shader that is applied to the rendered window as a full screen
+/// pass; either the user supplied `post_process_shader`, or the
+/// built-in shader that applies the effects of inactive panes.
+/// The window is rendered into a texture, which the shader then
+/// samples while drawing into the frame, or into the texture
+/// of the next pass.
+pub struct PostProcess {
+    backend: Backend,
+    /// Whether the shader refers to the time, in which case we
+    /// need to keep painting frames to animate it
+    pub animated: bool,
+}
+
+impl PostProcess {
+    pub fn load(path: &Path, context: &RenderContext) -> anyhow::Result<Self> {
+        let source =
+            std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        let is_wgsl = path
+            .extension()
+            .map_or(false, |ext| ext.eq_ignore_ascii_case("wgsl"));
+        Self::with_source(&source, is_wgsl, context)
+    }
+
+    /// Loads the built-in shader that applies PaneEffects to
+    /// the regions of the window described by pane_rects
+    pub fn pane_effects(context: &RenderContext) -> anyhow::Result<Self> {
+        match context {
+            RenderContext::Glium(_) => {
+                Self::with_source(include_str!("../../pane-effects.glsl"), false, context)
+            }
+            RenderContext::WebGpu(_) => {
+                Self::with_source(include_str!("../../pane-effects.wgsl"), true, context)
+            }
+            RenderContext::Software => {
+                anyhow::bail!("post processing is not supported with front_end=\"Software\"")
+            }
+        }
+    }
+
+    fn with_source(source: &str, is_wgsl: bool, context: &RenderContext) -> anyhow::Result<Self> {
+        let backend = match context {
+            RenderContext::Glium(context) => {
+                anyhow::ensure!(!is_wgsl, "front_end=\"OpenGL\" requires a GLSL shader");
+                Self::load_glium(context, source)?
+            }
+            RenderContext::WebGpu(state) => {
+                anyhow::ensure!(
+                    is_wgsl,
+                    "front_end=\"WebGpu\" requires a WGSL shader with a .wgsl extension"
+                );
+                Self::load_webgpu(state, source)?
+            }
+            RenderContext::Software => {
+                anyhow::bail!("post processing is not supported with front_end=\"Software\"")
+            }
+        };
+
+        Ok(Self {
+            backend,
+            animated: source.contains("time"),
+        })
+    }
+
+    fn load_glium(context: &Rc<GliumContext>, source: &str) -> anyhow::Result<Backend> {
+        let program = RenderState::compile_prog(context, |version| {
+            (
+                format!(
+                    "#version {}\n{}",
+                    version,
+                    include_str!("../../post-process-vertex.glsl")
+                ),
+                format!(
+                    "#version {}\n{}{}",
+                    version,
+                    include_str!("../../post-process-prelude.glsl"),
+                    source
+                ),
+            )
+        })?;
+
+        // A strip of two triangles that covers the window
+        let quad: Vec<PostProcessVertex> = [[-1., -1.], [1., -1.], [-1., 1.], [1., 1.]]
+            .into_iter()
+            .map(|position| PostProcessVertex { position })
+            .collect();
+        let vertices = glium::VertexBuffer::new(context, &quad)?;
+
+        Ok(Backend::Glium {
+            context: Rc::clone(context),
+            program,
+            vertices,
+            texture: RefCell::new(None),
+        })
+    }
+
+    fn load_webgpu(state: &Rc<WebGpuState>, source: &str) -> anyhow::Result<Backend> {
+        let device = &state.device;
+
+        // Capture problems with the shader so that we can report
+        // them, rather than have wgpu panic
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("post_process_shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                format!("{}{}", include_str!("../../post-process.wgsl"), source).into(),
+            ),
+        });
+        if let Some(err) = smol::block_on(device.pop_error_scope()) {
+            anyhow::bail!("{err}");
+        }
+
+        let uniform_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+            label: Some("PostProcessUniform bind group layout"),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Post Process Pipeline Layout"),
+            bind_group_layouts: &[&uniform_layout, &state.texture_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let format = state.config.borrow().format;
+        let pipeline = Self::create_webgpu_pipeline(state, &pipeline_layout, &shader, format)?;
+
+        Ok(Backend::WebGpu {
+            state: Rc::clone(state),
+            shader,
+            uniform_layout,
+            pipeline_layout,
+            pipeline: RefCell::new((format, pipeline)),
+            texture: RefCell::new(None),
+        })
+    }
+
+    fn create_webgpu_pipeline(
+        state: &WebGpuState,
+        layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+        format: wgpu::TextureFormat,
+    ) -> anyhow::Result<wgpu::RenderPipeline> {
+        state.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let pipeline = state
+            .device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Post Process Pipeline"),
+                layout: Some(layout),
+                vertex: wgpu::VertexState {
+                    module: shader,
+                    entry_point: Some("vs_main"),
+                    buffers: &[],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: shader,
+                    entry_point: Some("fs_main"),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            });
+        if let Some(err) = smol::block_on(state.device.pop_error_scope()) {
+            anyhow::bail!("{err}");
+        }
+        Ok(pipeline)
+    }
+
+    /// Returns the texture into which the glium front end should
+    /// render the window, recreating it if the size has changed
+    pub fn glium_texture(&self, width: u32, height: u32) -> anyhow::Result<Rc<SrgbTexture2d>> {
+        let (context, texture) = match &self.backend {
+            Backend::Glium {
+                context, texture, ..
+            } => (context, texture),
+            Backend::WebGpu { .. } => anyhow::bail!("shader was loaded for WebGpu"),
+        };
+
+        let mut texture = texture.borrow_mut();
+        if let Some(texture) = texture.as_ref() {
+            if texture.width() == width && texture.height() == height {
+                return Ok(Rc::clone(texture));
+            }
+        }
+        let new_texture = Rc::new(SrgbTexture2d::empty(context, width, height)?);
+        texture.replace(Rc::clone(&new_texture));
+        Ok(new_texture)
+    }
+
+    /// Draws the texture returned by glium_texture into `target`,
+    /// applying the shader. `transform` rotates the window to match
+    /// the orientation of the target.
+    pub fn draw_glium<S: Surface>(
+        &self,
+        target: &mut S,
+        inputs: &PostProcessInputs,
+        transform: [[f32; 4]; 4],
+    ) -> anyhow::Result<()> {
+        let (program, vertices, texture) = match &self.backend {
+            Backend::Glium {
+                program,
+                vertices,
+                texture,
+                ..
+            } => (program, vertices, texture),
+            Backend::WebGpu { .. } => anyhow::bail!("shader was loaded for WebGpu"),
+        };
+        let texture = texture.borrow();
+        let texture = texture
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("window was not rendered for post processing"))?;
+
+        let source = Sampler::new(&**texture)
+            .wrap_function(SamplerWrapFunction::Clamp)
+            .magnify_filter(MagnifySamplerFilter::Linear)
+            .minify_filter(MinifySamplerFilter::Linear);
+        let resolution = [inputs.width as f32, inputs.height as f32];
+        // gl_FragCoord has its origin at the bottom left
+        let [x, y, width, height] = inputs.cursor;
+        let cursor = [x, resolution[1] - (y + height), width, height];
+        let panes: Vec<_> = inputs
+            .panes
+            .iter()
+            .take(MAX_PANE_EFFECTS)
+            .map(|pane| {
+                let [x, y, width, height] = pane.rect;
+                (
+                    [x, resolution[1] - (y + height), width, height],
+                    pane.effect(),
+                )
+            })
+            .collect();
+        let pane_count = panes.len() as i32;
+
+        let mut uniforms = UniformBuilder::default();
+        uniforms.add("source", &source);
+        uniforms.add("time", &inputs.time);
+        uniforms.add("resolution", &resolution);
+        uniforms.add("cursor", &cursor);
+        uniforms.add("transform", &transform);
+        uniforms.add("pane_count", &pane_count);
+        for (idx, (rect, effect)) in panes.iter().enumerate() {
+            uniforms.add_array_element("pane_rects", idx, rect);
+            uniforms.add_array_element("pane_effects", idx, effect);
+        }
+
+        target.draw(
+            vertices,
+            glium::index::NoIndices(glium::index::PrimitiveType::TriangleStrip),
+            program,
+            &uniforms,
+            &glium::DrawParameters::default(),
+        )?;
+        Ok(())
+    }
+
+    /// Returns a view of the texture into which the webgpu front end
+    /// should render the window, recreating it if the size or the
+    /// surface format has changed
+    pub fn webgpu_view(&self, width: u32, height: u32) -> anyhow::Result<wgpu::TextureView> {
+        let (state, texture) = match &self.backend {
+            Backend::WebGpu { state, texture, .. } => (state, texture),
+            Backend::Glium { .. } => anyhow::bail!("shader was loaded for OpenGL"),
+        };
+
+        let format = state.config.borrow().format;
+        let mut texture = texture.borrow_mut();
+        let stale = texture.as_ref().map_or(true, |texture| {
+            texture.width() != width || texture.height() != height || texture.format() != format
+        });
+        if stale {
+            texture.replace(state.device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("post process source"),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            }));
+        }
+
+        Ok(texture
+            .as_ref()
+            .unwrap()
+            .create_view(&wgpu::TextureViewDescriptor::default()))
+    }
+
+    /// Records a pass that draws the texture returned by webgpu_view
+    /// into `view`, applying the shader
+    pub fn draw_webgpu(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        inputs: &PostProcessInputs,
+    ) -> anyhow::Result<()> {
+        let (state, shader, uniform_layout, pipeline_layout, pipeline, texture) =
+            match &self.backend {
+                Backend::WebGpu {
+                    state,
+                    shader,
+                    uniform_layout,
+                    pipeline_layout,
+                    pipeline,
+                    texture,
+                } => (
+                    state,
+                    shader,
+                    uniform_layout,
+                    pipeline_layout,
+                    pipeline,
+                    texture,
+                ),
+                Backend::Glium { .. } => anyhow::bail!("shader was loaded for OpenGL"),
+            };
+
+        let format = state.config.borrow().format;
+        if pipeline.borrow().0 != format {
+            let new_pipeline =
+                Self::create_webgpu_pipeline(state, pipeline_layout, shader, format)?;
+            *pipeline.borrow_mut() = (format, new_pipeline);
+        }
+
+        let source_view = texture
+            .borrow()
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("window was not rendered for post processing"))?
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let source_bind_group = state.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &state.texture_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&source_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&state.texture_linear_sampler),
+                },
+            ],
+            label: Some("post process source bind group"),
+        });
+
+        let mut pane_rects = [[0.; 4]; MAX_PANE_EFFECTS];
+        let mut pane_effects = [[0.; 4]; MAX_PANE_EFFECTS];
+        for (idx, pane) in inputs.panes.iter().take(MAX_PANE_EFFECTS).enumerate() {
+            pane_rects[idx] = pane.rect;
+            pane_effects[idx] = pane.effect();
+        }
+
+        let buffer = state
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("PostProcessUniform Buffer"),
+                contents: bytemuck::cast_slice(&[PostProcessUniform {
+                    cursor: inputs.cursor,
+                    resolution: [inputs.width as f32, inputs.height as f32],
+                    time: inputs.time,
+                    pane_count: inputs.panes.len().min(MAX_PANE_EFFECTS) as u32,
+                    pane_rects,
+                    pane_effects,
+                }]),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+        let uniform_bind_group = state.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: uniform_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+            label: Some("PostProcessUniform Bind Group"),
+        });
+
+        let pipeline = pipeline.borrow();
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Post Process Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        render_pass.set_pipeline(&pipeline.1);
+        render_pass.set_bind_group(0, &uniform_bind_group, &[]);
+        render_pass.set_bind_group(1, &source_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+        Ok(())
+    }
+}
+
+impl crate::TermWindow {
+    /// Returns the post_process_shader to apply to this frame, loading
+    /// it if it hasn't been loaded since the configuration changed
+    pub fn post_process(&mut self) -> Option<Rc<PostProcess>> {
+        if self.post_process.is_none() {
+            let path = self.config.post_process_shader.as_ref()?;
+            let render_state = self.render_state.as_ref()?;
+            let loaded = match PostProcess::load(path, &render_state.context) {
+                Ok(post_process) => Some(Rc::new(post_process)),
+                Err(err) => {
+                    let err = format!(
+                        "Failed to load post_process_shader {}: {:#}",
+                        path.display(),
+                        err
+                    );
+                    log::error!("{err}");
+                    config::show_error(&err);
+                    None
+                }
+            };
+            self.post_process = Some(loaded);
+        }
+        self.post_process.clone().flatten()
+    }
+
+    /// Returns the shaders to apply to this frame, in order: the
+    /// effects of inactive panes, followed by the post_process_shader
+    pub fn post_process_passes(&mut self) -> Vec<Rc<PostProcess>> {
+        let has_pane_effects = !self.pane_effect_regions.borrow().is_empty();
+        let pane_effects = if has_pane_effects {
+            self.pane_effects()
+        } else {
+            None
+        };
+        pane_effects
+            .into_iter()
+            .chain(self.post_process())
+            .collect()
+    }
+
+    /// Returns the built-in shader that applies the effects of inactive
+    /// panes while compositing, loading it if it hasn't been loaded since
+    /// the configuration changed.  Returns None for the Software front end,
+    /// whose quads have the effect applied to their colors instead.
+    pub fn pane_effects(&mut self) -> Option<Rc<PostProcess>> {
+        if self.pane_effects.is_none() {
+            let render_state = self.render_state.as_ref()?;
+            let loaded = match &render_state.context {
+                RenderContext::Software => None,
+                context => match PostProcess::pane_effects(context) {
+                    Ok(pane_effects) => Some(Rc::new(pane_effects)),
+                    Err(err) => {
+                        log::error!("Failed to load the pane effects shader: {:#}", err);
+                        None
+                    }
+                },
+            };
+            self.pane_effects = Some(loaded);
+        }
+        self.pane_effects.clone().flatten()
+    }
+
+    /// Returns the effect to apply to the pane, taking into account
+    /// any effect set for it via `window:set_inactive_pane_effect`
+    fn effect_for_pane(&self, pos: &PositionedPane) -> Option<PaneEffect> {
+        if pos.is_active {
+            return None;
+        }
+        let effect = self
+            .pane_state(pos.pane.pane_id())
+            .inactive_effect
+            .unwrap_or_else(|| self.config.inactive_pane_effect());
+        if effect.is_identity() {
+            None
+        } else {
+            Some(effect)
+        }
+    }
+
+    /// Overrides the effect applied to the pane while it is inactive;
+    /// None reverts to the effect from the configuration
+    pub fn set_inactive_pane_effect(&mut self, pane_id: PaneId, effect: Option<PaneEffect>) {
+        self.pane_state(pane_id).inactive_effect = effect;
+        // The quads of the pane may have had the previous
+        // effect applied to their colors
+        self.quad_generation += 1;
+        if let Some(window) = self.window.as_ref() {
+            window.invalidate();
+        }
+    }
+
+    /// Arranges for the effect of the pane to be applied to `rect` while
+    /// compositing the window.  When that isn't possible, returns the
+    /// transform to apply to the colors of the quads of the pane instead.
+    pub fn apply_pane_effect(&mut self, pos: &PositionedPane, rect: RectF) -> Option<HsbTransform> {
+        let effect = self.effect_for_pane(pos)?;
+        let composited = self.pane_effects().is_some();
+        let mut regions = self.pane_effect_regions.borrow_mut();
+        if composited && regions.len() < MAX_PANE_EFFECTS {
+            regions.push(PaneEffectRegion {
+                rect: [
+                    rect.origin.x,
+                    rect.origin.y,
+                    rect.size.width,
+                    rect.size.height,
+                ],
+                effect,
+            });
+            None
+        } else {
+            Some(effect.hsb)
+        }
+    }
+
+    /// The values passed to the post_process_shader for this frame
+    pub fn post_process_inputs(&self) -> PostProcessInputs {
+        PostProcessInputs {
+            time: self.created.elapsed().as_secs_f32(),
+            width: self.dimensions.pixel_width,
+            height: self.dimensions.pixel_height,
+            cursor: self.text_cursor_rect.map_or([0.; 4], |r| {
+                [
+                    r.origin.x as f32,
+                    r.origin.y as f32,
+                    r.size.width as f32,
+                    r.size.height as f32,
+                ]
+            }),
+            panes: self.pane_effect_regions.borrow().clone(),
+        }
+    }
+
+    /// Keeps painting frames while the post_process_shader
+    /// is animated
+    pub fn schedule_post_process_animation(&self) {
+        let animated = self
+            .post_process
+            .as_ref()
+            .and_then(|post_process| post_process.as_ref())
+            .map_or(false, |post_process| post_process.animated);
+        if animated {
+            let interval = Duration::from_millis(1000 / self.config.animation_fps.max(1) as u64);
+            self.update_next_frame_time(Some(Instant::now() + interval));
+        }
+    }
+}
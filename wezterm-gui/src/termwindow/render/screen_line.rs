@@ -7,12 +7,13 @@ use crate::termwindow::render::{
 use crate::termwindow::LineToElementShapeItem;
 use ::window::DeadKeyStatus;
 use anyhow::Context;
-use config::{HsbTransform, TextStyle};
+use config::{CursorStyleShape, HsbTransform, TextStyle};
 use std::ops::Range;
 use std::rc::Rc;
 use std::time::Instant;
-use termwiz::cell::{unicode_column_width, Blink};
+use termwiz::cell::{unicode_column_width, Blink, Underline};
 use termwiz::color::LinearRgba;
+use termwiz::hyperlink::rule_hover_underline;
 use termwiz::surface::CursorShape;
 use wezterm_bidi::Direction;
 use wezterm_term::color::ColorAttribute;
@@ -34,6 +35,7 @@ impl crate::TermWindow {
             // rendering the top row, so we have nothing more to do here.
             return Ok(RenderScreenLineResult {
                 invalidate_on_hover_change: false,
+                shaping_pending: false,
             });
         }
 
@@ -41,11 +43,7 @@ impl crate::TermWindow {
 
         let num_cols = params.dims.cols;
 
-        let hsv = if params.is_active {
-            None
-        } else {
-            Some(params.config.inactive_pane_hsb)
-        };
+        let hsv = params.hsv;
 
         let width_scale = if !params.line.is_single_width() {
             2.0
@@ -77,19 +75,14 @@ impl crate::TermWindow {
 
         // Referencing the text being composed, but only if it belongs to this pane
         let composing = if cursor_idx.is_some() {
-            if let DeadKeyStatus::Composing(composing) = &self.dead_key_status {
-                Some(composing)
-            } else {
-                None
-            }
+            self.dead_key_status.composing_text()
         } else {
             None
         };
 
         let mut composition_width = 0;
 
-        let (_bidi_enabled, bidi_direction) = params.line.bidi_info();
-        let direction = bidi_direction.direction();
+        let direction = params.line.resolved_direction();
 
         // Do we need to shape immediately, or can we use the pre-shaped data?
         if let Some(composing) = composing {
@@ -103,18 +96,44 @@ impl crate::TermWindow {
         };
 
         let cursor_range = if composition_width > 0 {
-            params.cursor.x..params.cursor.x + composition_width
+            match &self.dead_key_status {
+                DeadKeyStatus::Preedit { text, cursor } => {
+                    preedit_cursor_range(params.cursor.x, text, cursor.as_ref())
+                }
+                _ => params.cursor.x..params.cursor.x + composition_width,
+            }
         } else if params.stable_line_idx == Some(params.cursor.y) {
             params.cursor.x..params.cursor.x + cursor_cell.as_ref().map(|c| c.width()).unwrap_or(1)
         } else {
             0..0
         };
 
-        let cursor_range_pixels = params.left_pixel_x + cursor_range.start as f32 * cell_width
-            ..params.left_pixel_x + cursor_range.end as f32 * cell_width;
+        let animated_cursor = match params.pane {
+            Some(pane) if cursor_idx.is_some() && !cursor_range.is_empty() => {
+                let target = euclid::rect(
+                    gl_x + params.left_pixel_x
+                        + (phys(cursor_range.start, num_cols, direction) as f32 * cell_width),
+                    pos_y,
+                    (cursor_range.end - cursor_range.start) as f32 * cell_width,
+                    cell_height,
+                );
+                Some(self.animate_text_cursor(pane.pane_id(), target))
+            }
+            _ => None,
+        };
+
+        // While the cursor is still gliding towards this cell, the text
+        // beneath it keeps its usual colors until the cursor arrives
+        let cursor_range_pixels = if animated_cursor.as_ref().map_or(false, |c| c.moving) {
+            0.0..0.0
+        } else {
+            params.left_pixel_x + cursor_range.start as f32 * cell_width
+                ..params.left_pixel_x + cursor_range.end as f32 * cell_width
+        };
 
         let mut shaped = None;
         let mut invalidate_on_hover_change = false;
+        let mut shaping_pending = false;
 
         if let Some(shape_key) = &params.shape_key {
             let mut cache = self.line_to_ele_shape_cache.borrow_mut();
@@ -150,8 +169,9 @@ impl crate::TermWindow {
                 shape_key: &params.shape_key,
             };
 
-            let (shaped, invalidate_on_hover) = self.build_line_element_shape(params)?;
+            let (shaped, invalidate_on_hover, pending) = self.build_line_element_shape(params)?;
             invalidate_on_hover_change = invalidate_on_hover;
+            shaping_pending = pending;
             shaped
         };
 
@@ -325,6 +345,8 @@ impl crate::TermWindow {
 
             let ComputeCellFgBgResult {
                 cursor_shape,
+                cursor_style,
+                cursor_thickness,
                 cursor_border_color,
                 cursor_border_color_alt,
                 cursor_border_mix,
@@ -346,11 +368,13 @@ impl crate::TermWindow {
             });
             let pos_x = (self.dimensions.pixel_width as f32 / -2.)
                 + params.left_pixel_x
-                + (phys(params.cursor.x, num_cols, direction) as f32 * cell_width);
+                + (phys(cursor_range.start, num_cols, direction) as f32 * cell_width);
 
             if let Some(shape) = cursor_shape {
-                let cursor_layer = match shape {
-                    CursorShape::BlinkingBar | CursorShape::SteadyBar => 2,
+                let cursor_layer = match (&cursor_style, shape) {
+                    (Some(CursorStyleShape::Glyph(_)), _) => 2,
+                    (Some(_), _) => 0,
+                    (None, CursorShape::BlinkingBar | CursorShape::SteadyBar) => 2,
                     _ => 0,
                 };
                 let mut quad = layers
@@ -361,21 +385,28 @@ impl crate::TermWindow {
 
                 let mut draw_basic = true;
 
-                if params.password_input {
+                let glyph_text = match &cursor_style {
+                    _ if params.password_input => Some("\u{f023}"),
+                    Some(CursorStyleShape::Glyph(text)) if !text.is_empty() => Some(text.as_str()),
+                    _ => None,
+                };
+
+                if let Some(text) = glyph_text {
                     let attrs = cursor_cell
                         .as_ref()
                         .map(|cell| cell.attrs().clone())
                         .unwrap_or_else(|| CellAttributes::blank());
 
                     let glyph = self
-                        .resolve_lock_glyph(
+                        .resolve_cursor_glyph(
+                            text,
                             &TextStyle::default(),
                             &attrs,
                             params.font.as_ref(),
                             gl_state,
                             &params.render_metrics,
                         )
-                        .context("resolve_lock_glyph")?;
+                        .context("resolve_cursor_glyph")?;
 
                     if let Some(sprite) = &glyph.texture {
                         let width = sprite.coords.size.width as f32 * glyph.scale as f32;
@@ -396,18 +427,33 @@ impl crate::TermWindow {
                 }
 
                 if draw_basic {
-                    quad.set_position(
-                        pos_x,
-                        pos_y,
-                        pos_x + (cursor_range.end - cursor_range.start) as f32 * cell_width,
-                        pos_y + cell_height,
-                    );
+                    match &animated_cursor {
+                        Some(animated) => quad.set_position(
+                            animated.rect.min_x(),
+                            animated.rect.min_y(),
+                            animated.rect.max_x(),
+                            animated.rect.max_y(),
+                        ),
+                        None => quad.set_position(
+                            pos_x,
+                            pos_y,
+                            pos_x + (cursor_range.end - cursor_range.start) as f32 * cell_width,
+                            pos_y + cell_height,
+                        ),
+                    }
                     quad.set_texture(
                         gl_state
                             .glyph_cache
                             .borrow_mut()
                             .cursor_sprite(
-                                Some(shape),
+                                match cursor_style {
+                                    Some(CursorStyleShape::HollowBlock) => {
+                                        Some(CursorShape::SteadyBlock)
+                                    }
+                                    _ => Some(shape),
+                                },
+                                cursor_style == Some(CursorStyleShape::HollowRing),
+                                cursor_thickness,
                                 &params.render_metrics,
                                 (cursor_range.end - cursor_range.start) as u8,
                             )?
@@ -735,13 +781,14 @@ impl crate::TermWindow {
 
         Ok(RenderScreenLineResult {
             invalidate_on_hover_change,
+            shaping_pending,
         })
     }
 
     fn build_line_element_shape(
         &self,
         params: LineToElementParams,
-    ) -> anyhow::Result<(Rc<Vec<LineToElementShape>>, bool)> {
+    ) -> anyhow::Result<(Rc<Vec<LineToElementShape>>, bool, bool)> {
         let (bidi_enabled, bidi_direction) = params.line.bidi_info();
         let bidi_hint = if bidi_enabled {
             Some(bidi_direction)
@@ -754,7 +801,26 @@ impl crate::TermWindow {
             // Create an updated line with the composition overlaid
             let mut line = params.line.clone();
             let seqno = line.current_seqno();
-            line.overlay_text_with_attribute(*cursor_x, &composing, CellAttributes::blank(), seqno);
+            match composing {
+                DeadKeyStatus::Preedit { text, .. } => {
+                    // Input methods expect their preedit text to be
+                    // underlined so that it can be told apart from
+                    // committed text
+                    let mut attrs = CellAttributes::blank();
+                    attrs.set_underline(Underline::Single);
+                    line.overlay_text_with_attribute(*cursor_x, text, attrs, seqno);
+                }
+                status => {
+                    if let Some(text) = status.composing_text() {
+                        line.overlay_text_with_attribute(
+                            *cursor_x,
+                            text,
+                            CellAttributes::blank(),
+                            seqno,
+                        );
+                    }
+                }
+            }
             line.cluster(bidi_hint)
         } else {
             params.line.cluster(bidi_hint)
@@ -766,6 +832,7 @@ impl crate::TermWindow {
         let mut x_pos = 0.;
         let mut expires = None;
         let mut invalidate_on_hover_change = false;
+        let mut shaping_pending = false;
 
         for cluster in &cell_clusters {
             if !matches!(last_style.as_ref(), Some(ClusterStyleCache{attrs,..}) if *attrs == &cluster.attrs)
@@ -775,6 +842,17 @@ impl crate::TermWindow {
                 let hyperlink = attrs.hyperlink();
                 let is_highlited_hyperlink =
                     same_hyperlink(hyperlink, self.current_highlight.as_ref());
+                // Links created by hyperlink_rules may ask for their
+                // own underline style while hovered
+                let hover_underline = if is_highlited_hyperlink {
+                    Some(
+                        hyperlink
+                            .and_then(|link| rule_hover_underline(link))
+                            .unwrap_or(Underline::Single),
+                    )
+                } else {
+                    None
+                };
                 if hyperlink.is_some() {
                     invalidate_on_hover_change = true;
                 }
@@ -783,7 +861,7 @@ impl crate::TermWindow {
                     .glyph_cache
                     .borrow_mut()
                     .cached_line_sprite(
-                        is_highlited_hyperlink,
+                        hover_underline,
                         attrs.strikethrough(),
                         attrs.underline(),
                         attrs.overline(),
@@ -876,17 +954,41 @@ impl crate::TermWindow {
 
             let style_params = last_style.as_ref().expect("we just set it up").clone();
 
-            let glyph_info = self.cached_cluster_shape(
-                style_params.style,
-                &cluster,
-                &gl_state,
-                None,
-                &self.render_metrics,
-            )?;
-            let pixel_width = glyph_info
-                .iter()
-                .map(|info| info.glyph.x_advance.get() as f32)
-                .sum();
+            // Only terminal lines, which get repainted when their shaping
+            // completes, can defer shaping to the background pool
+            let glyph_info = if params.shape_key.is_some() {
+                self.cached_cluster_shape_nonblocking(
+                    style_params.style,
+                    &cluster,
+                    &gl_state,
+                    &self.render_metrics,
+                )?
+            } else {
+                Some(self.cached_cluster_shape(
+                    style_params.style,
+                    &cluster,
+                    &gl_state,
+                    None,
+                    &self.render_metrics,
+                )?)
+            };
+            let (glyph_info, pixel_width) = match glyph_info {
+                Some(glyph_info) => {
+                    let pixel_width = glyph_info
+                        .iter()
+                        .map(|info| info.glyph.x_advance.get() as f32)
+                        .sum();
+                    (glyph_info, pixel_width)
+                }
+                None => {
+                    // Leave blank space where the text will go
+                    shaping_pending = true;
+                    (
+                        Rc::new(vec![]),
+                        cluster.width as f32 * self.render_metrics.cell_size.width as f32,
+                    )
+                }
+            };
 
             shaped.push(LineToElementShape {
                 underline_tex_rect: style_params.underline_tex_rect,
@@ -904,7 +1006,14 @@ impl crate::TermWindow {
 
         let shaped = Rc::new(shaped);
 
-        if let Some(shape_key) = params.shape_key {
+        // Placeholders must not be cached; the line is shaped again
+        // once the background shaping has completed
+        let shape_key = if shaping_pending {
+            None
+        } else {
+            params.shape_key.as_ref()
+        };
+        if let Some(shape_key) = shape_key {
             self.line_to_ele_shape_cache.borrow_mut().put(
                 shape_key.clone(),
                 LineToElementShapeItem {
@@ -920,6 +1029,24 @@ impl crate::TermWindow {
             );
         }
 
-        Ok((shaped, invalidate_on_hover_change))
+        Ok((shaped, invalidate_on_hover_change, shaping_pending))
+    }
+}
+
+/// Returns the columns of input method preedit `text`, starting at
+/// column `x`, that should be drawn using the cursor colors: the
+/// portion that the input method has highlighted, or the cell at its
+/// caret.  The range is empty if the input method has hidden the caret.
+fn preedit_cursor_range(x: usize, text: &str, cursor: Option<&Range<usize>>) -> Range<usize> {
+    let cursor = match cursor {
+        Some(cursor) => cursor,
+        None => return x..x,
+    };
+    match (text.get(..cursor.start), text.get(cursor.clone())) {
+        (Some(before), Some(highlight)) => {
+            let start = x + unicode_column_width(before, None);
+            start..start + unicode_column_width(highlight, None).max(1)
+        }
+        _ => x..x + unicode_column_width(text, None),
     }
 }
@@ -1,15 +1,94 @@
 use crate::termwindow::render::TripleLayerQuadAllocator;
 use crate::termwindow::{UIItem, UIItemType};
+use config::DimensionContext;
 use mux::pane::Pane;
-use mux::tab::{PositionedSplit, SplitDirection};
+use mux::tab::{PositionedPane, PositionedSplit, SplitDirection};
 use std::sync::Arc;
+use window::color::LinearRgba;
+use window::RectF;
+
+/// Which side of a split a pane is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SplitSide {
+    /// Left of a horizontal split, or above a vertical split
+    Before,
+    /// Right of a horizontal split, or below a vertical split
+    After,
+}
+
+/// Returns the side of the split that `pane` is on, and the range of
+/// cells along the split that it borders, or None if it doesn't border
+/// the split
+fn pane_bordering_split(
+    pane: &PositionedPane,
+    split: &PositionedSplit,
+) -> Option<(SplitSide, std::ops::Range<usize>)> {
+    // "across" is perpendicular to the split line, "along" is parallel to it
+    let (pane_across, pane_across_size, pane_along, pane_along_size, split_across, split_along) =
+        match split.direction {
+            SplitDirection::Horizontal => (
+                pane.left,
+                pane.width,
+                pane.top,
+                pane.height,
+                split.left,
+                split.top,
+            ),
+            SplitDirection::Vertical => (
+                pane.top,
+                pane.height,
+                pane.left,
+                pane.width,
+                split.top,
+                split.left,
+            ),
+        };
+
+    let side = if pane_across + pane_across_size == split_across {
+        SplitSide::Before
+    } else if pane_across == split_across + 1 {
+        SplitSide::After
+    } else {
+        return None;
+    };
+
+    let start = pane_along.max(split_along);
+    let end = (pane_along + pane_along_size).min(split_along + split.size);
+    if start < end {
+        Some((side, start - split_along..end - split_along))
+    } else {
+        None
+    }
+}
+
+fn lerp_color(a: LinearRgba, b: LinearRgba, t: f32) -> LinearRgba {
+    LinearRgba::with_components(
+        a.0 + (b.0 - a.0) * t,
+        a.1 + (b.1 - a.1) * t,
+        a.2 + (b.2 - a.2) * t,
+        a.3 + (b.3 - a.3) * t,
+    )
+}
 
 impl crate::TermWindow {
+    /// Evaluates a pane_split_thickness style dimension, where
+    /// `cell_across` is the size of a cell perpendicular to the split
+    fn split_dimension(&self, dim: &config::Dimension, cell_across: f32) -> f32 {
+        dim.evaluate_as_pixels(DimensionContext {
+            dpi: self.dimensions.dpi as f32,
+            pixel_max: cell_across,
+            pixel_cell: cell_across,
+        })
+        .round()
+        .max(0.)
+    }
+
     pub fn paint_split(
         &mut self,
         layers: &mut TripleLayerQuadAllocator,
         split: &PositionedSplit,
         pane: &Arc<dyn Pane>,
+        active: Option<&PositionedPane>,
     ) -> anyhow::Result<()> {
         let palette = pane.palette();
         let foreground = palette.split.to_linear();
@@ -28,23 +107,112 @@ impl crate::TermWindow {
         let pos_y = split.top as f32 * cell_height + first_row_offset + padding_top;
         let pos_x = split.left as f32 * cell_width + padding_left + border.left.get() as f32;
 
-        if split.direction == SplitDirection::Horizontal {
-            self.filled_rectangle(
-                layers,
-                2,
-                euclid::rect(
-                    pos_x + (cell_width / 2.0),
-                    pos_y - (cell_height / 2.0),
-                    self.render_metrics.underline_height as f32,
-                    (1. + split.size as f32) * cell_height,
-                ),
-                foreground,
-            )?;
+        let horizontal = split.direction == SplitDirection::Horizontal;
+        let (cell_across, cell_along, pos_across, pos_along) = if horizontal {
+            (cell_width, cell_height, pos_x, pos_y)
+        } else {
+            (cell_height, cell_width, pos_y, pos_x)
+        };
+
+        let thickness = match &self.config.pane_split_thickness {
+            None => self.render_metrics.underline_height as f32,
+            Some(d) => self.split_dimension(d, cell_across).max(1.),
+        };
+        // The line is centered in the column (or row) of the split
+        let line_start = pos_across + (cell_across / 2. - thickness / 2.).round();
+
+        // Builds the rect that covers `across_size` pixels starting at
+        // `across`, and the cells in `along` plus the half cells that
+        // extend the line to the edges of the panes at either end
+        let rect = |across: f32, across_size: f32, along: std::ops::Range<usize>| -> RectF {
+            let mut start = pos_along + along.start as f32 * cell_along;
+            let mut end = pos_along + along.end as f32 * cell_along;
+            if along.start == 0 {
+                start -= cell_along / 2.;
+            }
+            if along.end == split.size {
+                end += cell_along / 2.;
+            }
+            if horizontal {
+                euclid::rect(across, start, across_size, end - start)
+            } else {
+                euclid::rect(start, across, end - start, across_size)
+            }
+        };
+
+        self.filled_rectangle(
+            layers,
+            2,
+            rect(line_start, thickness, 0..split.size),
+            foreground,
+        )?;
+
+        if let Some((side, along)) = active.and_then(|active| pane_bordering_split(active, split)) {
+            // Highlight the portion of the line that borders the active pane
+            if let Some(active_color) = self.config.resolved_palette.split_active {
+                let active_color = active_color.to_linear();
+                if self.config.pane_split_gradient && thickness > 1. {
+                    // Shade it one pixel at a time from the active
+                    // color on the active side to the split color
+                    let steps = thickness as usize;
+                    for step in 0..steps {
+                        let t = step as f32 / (steps - 1) as f32;
+                        let t = match side {
+                            SplitSide::Before => t,
+                            SplitSide::After => 1. - t,
+                        };
+                        self.filled_rectangle(
+                            layers,
+                            2,
+                            rect(line_start + step as f32, 1., along.clone()),
+                            lerp_color(active_color, foreground, t),
+                        )?;
+                    }
+                } else {
+                    self.filled_rectangle(
+                        layers,
+                        2,
+                        rect(line_start, thickness, along.clone()),
+                        active_color,
+                    )?;
+                }
+            }
+
+            // and cast a shadow from it over the pane on the other side,
+            // fading out one pixel at a time
+            if let Some(size) = &self.config.active_pane_shadow_size {
+                let size = self.split_dimension(size, cell_across) as usize;
+                let shadow = self
+                    .config
+                    .resolved_palette
+                    .active_pane_shadow
+                    .map(|c| c.to_linear())
+                    .unwrap_or(LinearRgba::with_components(0., 0., 0., 0.5));
+                for step in 0..size {
+                    let across = match side {
+                        SplitSide::Before => line_start + thickness + step as f32,
+                        SplitSide::After => line_start - 1. - step as f32,
+                    };
+                    self.filled_rectangle(
+                        layers,
+                        2,
+                        rect(across, 1., along.clone()),
+                        shadow.mul_alpha(1. - step as f32 / size as f32),
+                    )?;
+                }
+            }
+        }
+
+        // The hit target for resizing covers the column (or row) of the
+        // split, widened to cover the line if it is thicker than a cell
+        let extra = (thickness.ceil() as usize).saturating_sub(cell_across as usize);
+        if horizontal {
             self.ui_items.push(UIItem {
-                x: border.left.get() as usize
+                x: (border.left.get() as usize
                     + padding_left as usize
-                    + (split.left * cell_width as usize),
-                width: cell_width as usize,
+                    + (split.left * cell_width as usize))
+                    .saturating_sub(extra / 2),
+                width: cell_width as usize + extra,
                 y: padding_top as usize
                     + first_row_offset as usize
                     + split.top * cell_height as usize,
@@ -52,26 +220,16 @@ impl crate::TermWindow {
                 item_type: UIItemType::Split(split.clone()),
             });
         } else {
-            self.filled_rectangle(
-                layers,
-                2,
-                euclid::rect(
-                    pos_x - (cell_width / 2.0),
-                    pos_y + (cell_height / 2.0),
-                    (1.0 + split.size as f32) * cell_width,
-                    self.render_metrics.underline_height as f32,
-                ),
-                foreground,
-            )?;
             self.ui_items.push(UIItem {
                 x: border.left.get() as usize
                     + padding_left as usize
                     + (split.left * cell_width as usize),
                 width: split.size * cell_width as usize,
-                y: padding_top as usize
+                y: (padding_top as usize
                     + first_row_offset as usize
-                    + split.top * cell_height as usize,
-                height: cell_height as usize,
+                    + split.top * cell_height as usize)
+                    .saturating_sub(extra / 2),
+                height: cell_height as usize + extra,
                 item_type: UIItemType::Split(split.clone()),
             });
         }
@@ -78,6 +78,7 @@ impl crate::TermWindow {
                 foreground: palette.foreground.to_linear(),
                 pane: None,
                 is_active: true,
+                hsv: None,
                 selection_fg: LinearRgba::default(),
                 selection_bg: LinearRgba::default(),
                 cursor_fg: LinearRgba::default(),
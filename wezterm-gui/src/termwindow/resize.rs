@@ -37,8 +37,11 @@ impl super::TermWindow {
         );
         if dimensions.pixel_width == 0 || dimensions.pixel_height == 0 {
             // on windows, this can happen when minimizing the window.
-            // NOP!
+            // NOP! other than to stop painting while it is minimized
             log::trace!("new dimensions are zero: NOP!");
+            let last_state = self.window_state;
+            self.window_state |= window_state & (WindowState::HIDDEN | WindowState::SUSPENDED);
+            self.check_visibility_change(last_state, window);
             return;
         }
         if self.dimensions == dimensions && self.window_state == window_state {
@@ -46,11 +49,26 @@ impl super::TermWindow {
             log::trace!("dimensions didn't change NOP!");
             return;
         }
+        if self.dimensions == dimensions
+            && (WindowState::TILED | WindowState::SUSPENDED | WindowState::HIDDEN)
+                .contains(self.window_state ^ window_state)
+        {
+            // Only states that don't influence our layout changed,
+            // so there is nothing to recompute
+            let last_state = self.window_state;
+            self.window_state = window_state;
+            self.check_visibility_change(last_state, window);
+            self.emit_window_event("window-state-changed", None);
+            return;
+        }
         let last_state = self.window_state;
         self.window_state = window_state;
+        self.check_visibility_change(last_state, window);
         self.quad_generation += 1;
         if last_state != self.window_state {
             self.load_os_parameters();
+            // The integrated title buttons depend upon the decoration mode
+            self.update_title();
         }
 
         if let Some(webgpu) = self.webgpu.as_mut() {
@@ -68,6 +86,25 @@ impl super::TermWindow {
             modal.reconfigure(self);
         }
         self.emit_window_event("window-resized", None);
+        if last_state != self.window_state {
+            self.emit_window_event("window-state-changed", None);
+        }
+    }
+
+    /// We don't paint while the window is minimized or suspended, as
+    /// nothing that we paint would be seen.  When it becomes visible
+    /// again, all of it is repainted, as it may be out of date.
+    fn check_visibility_change(&mut self, last_state: WindowState, window: &Window) {
+        if last_state.is_visible() == self.window_state.is_visible() {
+            return;
+        }
+        if self.window_state.is_visible() {
+            log::debug!("window is visible again; resuming painting");
+            self.full_damage_pending = true;
+            window.invalidate();
+        } else {
+            log::debug!("window is hidden; suspending painting");
+        }
     }
 
     pub fn apply_pending_scale_changes(&mut self) {
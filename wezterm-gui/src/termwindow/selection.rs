@@ -1,9 +1,12 @@
+use crate::scripting::guiwin::GuiWin;
 use crate::selection::{Selection, SelectionCoordinate, SelectionMode, SelectionRange, SelectionX};
 use ::window::WindowOps;
 use mux::pane::{Pane, PaneId};
+use mux_lua::MuxPane;
 use std::cell::RefMut;
 use std::sync::Arc;
 use termwiz::surface::Line;
+use wezterm_dynamic::{FromDynamic, ToDynamic};
 use wezterm_term::StableRowIndex;
 
 impl super::TermWindow {
@@ -64,44 +67,55 @@ impl super::TermWindow {
 
     /// Returns the selection text only
     pub fn selection_text(&self, pane: &Arc<dyn Pane>) -> String {
-        let mut s = String::new();
         let rectangular = self.selection(pane.pane_id()).rectangular;
-        if let Some(sel) = self
-            .selection(pane.pane_id())
-            .range
-            .as_ref()
-            .map(|r| r.normalize())
-        {
-            let mut last_was_wrapped = false;
-            let first_row = sel.rows().start;
-            let last_row = sel.rows().end;
+        match self.selection(pane.pane_id()).range.as_ref() {
+            Some(sel) => Self::text_for_selection_range(pane, sel, rectangular),
+            None => String::new(),
+        }
+    }
 
-            for line in pane.get_logical_lines(sel.rows()) {
-                if !s.is_empty() && !last_was_wrapped {
-                    s.push('\n');
-                }
-                let last_idx = line.physical_lines.len().saturating_sub(1);
-                for (idx, phys) in line.physical_lines.iter().enumerate() {
-                    let this_row = line.first_row + idx as StableRowIndex;
-                    if this_row >= first_row && this_row < last_row {
-                        let last_phys_idx = phys.len().saturating_sub(1);
-                        let cols = sel.cols_for_row(this_row, rectangular);
-                        let last_col_idx = cols.end.saturating_sub(1).min(last_phys_idx);
-                        let col_span = phys.columns_as_str(cols);
-                        // Only trim trailing whitespace if we are the last line
-                        // in a wrapped sequence
-                        if idx == last_idx {
-                            s.push_str(col_span.trim_end());
-                        } else {
-                            s.push_str(&col_span);
-                        }
+    /// Returns the text spanned by `sel` in `pane`, joining the
+    /// physical lines of wrapped logical lines together
+    pub fn text_for_selection_range(
+        pane: &Arc<dyn Pane>,
+        sel: &SelectionRange,
+        rectangular: bool,
+    ) -> String {
+        if rectangular {
+            return Self::text_for_rectangular_range(pane, sel);
+        }
 
-                        last_was_wrapped = last_col_idx == last_phys_idx
-                            && phys
-                                .get_cell(last_col_idx)
-                                .map(|c| c.attrs().wrapped())
-                                .unwrap_or(false);
+        let mut s = String::new();
+        let sel = sel.normalize();
+        let mut last_was_wrapped = false;
+        let first_row = sel.rows().start;
+        let last_row = sel.rows().end;
+
+        for line in pane.get_logical_lines(sel.rows()) {
+            if !s.is_empty() && !last_was_wrapped {
+                s.push('\n');
+            }
+            let last_idx = line.physical_lines.len().saturating_sub(1);
+            for (idx, phys) in line.physical_lines.iter().enumerate() {
+                let this_row = line.first_row + idx as StableRowIndex;
+                if this_row >= first_row && this_row < last_row {
+                    let last_phys_idx = phys.len().saturating_sub(1);
+                    let cols = sel.cols_for_row(this_row, rectangular);
+                    let last_col_idx = cols.end.saturating_sub(1).min(last_phys_idx);
+                    let col_span = phys.columns_as_str(cols);
+                    // Only trim trailing whitespace if we are the last line
+                    // in a wrapped sequence
+                    if idx == last_idx {
+                        s.push_str(col_span.trim_end());
+                    } else {
+                        s.push_str(&col_span);
                     }
+
+                    last_was_wrapped = last_col_idx == last_phys_idx
+                        && phys
+                            .get_cell(last_col_idx)
+                            .map(|c| c.attrs().wrapped())
+                            .unwrap_or(false);
                 }
             }
         }
@@ -109,6 +123,45 @@ impl super::TermWindow {
         s
     }
 
+    /// Returns the text spanned by the rectangular selection `sel`, with
+    /// each row as a separate line.  Rows are padded with spaces to the
+    /// width of the block, unless the block extends to the end of each line.
+    fn text_for_rectangular_range(pane: &Arc<dyn Pane>, sel: &SelectionRange) -> String {
+        let sel = sel.normalize();
+        let (first_row, lines) = pane.get_lines(sel.rows());
+        let mut rows = vec![];
+
+        for (idx, line) in lines.iter().enumerate() {
+            let cols = sel.cols_for_row(first_row + idx as StableRowIndex, true);
+            let mut text = String::new();
+            // The column following the last cell that was added
+            let mut x = cols.start;
+            for cell in line.visible_cells() {
+                let cell_idx = cell.cell_index();
+                if cell_idx < cols.start {
+                    continue;
+                }
+                if cell_idx >= cols.end {
+                    break;
+                }
+                // Pad any columns that were skipped, such as the
+                // right half of a wide character at the left edge
+                text.extend(std::iter::repeat(' ').take(cell_idx.saturating_sub(x)));
+                text.push_str(cell.str());
+                x = cell_idx + cell.width();
+            }
+
+            if cols.end == usize::MAX {
+                rows.push(text.trim_end().to_string());
+            } else {
+                text.extend(std::iter::repeat(' ').take(cols.end.saturating_sub(x)));
+                rows.push(text);
+            }
+        }
+
+        rows.join("\n")
+    }
+
     pub fn clear_selection(&mut self, pane: &Arc<dyn Pane>) {
         let mut selection = self.selection(pane.pane_id());
         selection.clear();
@@ -182,14 +235,14 @@ impl super::TermWindow {
                     };
             }
             SelectionMode::Word => {
-                let end_word = SelectionRange::word_around(SelectionCoordinate::x_y(x, y), &**pane);
+                let end_word = self.word_around(SelectionCoordinate::x_y(x, y), pane);
 
                 let start_coord = self
                     .selection(pane.pane_id())
                     .origin
                     .clone()
                     .unwrap_or(end_word.start);
-                let start_word = SelectionRange::word_around(start_coord, &**pane);
+                let start_word = self.word_around(start_coord, pane);
 
                 let selection_range = start_word.extend_with(end_word);
                 self.selection(pane.pane_id()).range = Some(selection_range);
@@ -255,8 +308,7 @@ impl super::TermWindow {
                 self.selection(pane.pane_id()).rectangular = false;
             }
             SelectionMode::Word => {
-                let selection_range =
-                    SelectionRange::word_around(SelectionCoordinate::x_y(x, y), &**pane);
+                let selection_range = self.word_around(SelectionCoordinate::x_y(x, y), pane);
 
                 self.selection(pane.pane_id()).origin = Some(selection_range.start);
                 self.selection(pane.pane_id()).range = Some(selection_range);
@@ -280,4 +332,67 @@ impl super::TermWindow {
         self.selection(pane.pane_id()).seqno = pane.get_current_seqno();
         self.window.as_ref().unwrap().invalidate();
     }
+
+    /// Computes the selection range for the word around the specified
+    /// coords, giving the `adjust-word-selection` event the opportunity
+    /// to replace it
+    fn word_around(&mut self, start: SelectionCoordinate, pane: &Arc<dyn Pane>) -> SelectionRange {
+        let proposed = SelectionRange::word_around(start, &**pane);
+        let window = GuiWin::new(self);
+        let selection = WordSelection::from(proposed);
+
+        match config::run_immediate_with_lua_config(|lua| {
+            if let Some(lua) = lua {
+                let selection = luahelper::to_lua(&*lua, selection)?;
+                let v = config::lua::emit_sync_callback(
+                    &*lua,
+                    (
+                        "adjust-word-selection".to_string(),
+                        (window, MuxPane(pane.pane_id()), selection),
+                    ),
+                )?;
+                match &v {
+                    mlua::Value::Nil => Ok(None),
+                    _ => Ok(Some(luahelper::from_lua::<WordSelection>(v)?)),
+                }
+            } else {
+                Ok(None)
+            }
+        }) {
+            Ok(Some(selection)) => SelectionRange {
+                start: SelectionCoordinate::x_y(selection.start_x, selection.start_y),
+                end: SelectionCoordinate::x_y(selection.end_x, selection.end_y),
+            },
+            Ok(None) => proposed,
+            Err(err) => {
+                log::warn!("adjust-word-selection: {err:#}");
+                proposed
+            }
+        }
+    }
+}
+
+/// The word selection passed to, and optionally returned from,
+/// the `adjust-word-selection` event.  The end is inclusive.
+#[derive(Debug, FromDynamic, ToDynamic)]
+struct WordSelection {
+    start_x: usize,
+    start_y: StableRowIndex,
+    end_x: usize,
+    end_y: StableRowIndex,
+}
+
+impl From<SelectionRange> for WordSelection {
+    fn from(range: SelectionRange) -> Self {
+        let x = |x: SelectionX| match x {
+            SelectionX::Cell(x) => x,
+            SelectionX::BeforeZero => 0,
+        };
+        Self {
+            start_x: x(range.start.x),
+            start_y: range.start.y,
+            end_x: x(range.end.x),
+            end_y: range.end.y,
+        }
+    }
 }
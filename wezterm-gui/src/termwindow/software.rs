@@ -0,0 +1,374 @@
+//! Drawing our quads on the CPU, for use when there is no usable GPU.
+//! This follows what glyph-frag.glsl does, except that subpixel
+//! antialiasing is treated as grayscale.
+use crate::quad::{Vertex, VERTICES_PER_CELL, V_BOT_RIGHT, V_TOP_LEFT};
+use window::bitmaps::{BitmapImage, Image};
+use window::BufferTransform;
+
+// These match the has_color values in quad.rs and the shaders
+const IS_GLYPH: f32 = 0.0;
+const IS_COLOR_EMOJI: f32 = 1.0;
+const IS_BG_IMAGE: f32 = 2.0;
+const IS_SOLID_COLOR: f32 = 3.0;
+const IS_GRAY_SCALE: f32 = 4.0;
+const IS_IMAGE_NEAREST: f32 = 5.0;
+const IS_IMAGE_CUBIC: f32 = 6.0;
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c < 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn rgb_to_hsv([r, g, b]: [f32; 3]) -> [f32; 3] {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+    let hue = if delta <= 0. {
+        0.
+    } else if max == r {
+        ((g - b) / delta).rem_euclid(6.) / 6.
+    } else if max == g {
+        ((b - r) / delta + 2.) / 6.
+    } else {
+        ((r - g) / delta + 4.) / 6.
+    };
+    let saturation = if max <= 0. { 0. } else { delta / max };
+    [hue, saturation, max]
+}
+
+fn hsv_to_rgb([h, s, v]: [f32; 3]) -> [f32; 3] {
+    let channel = |n: f32| {
+        let k = (n + h * 6.).rem_euclid(6.);
+        v - v * s * k.min(4. - k).clamp(0., 1.)
+    };
+    [channel(5.), channel(3.), channel(1.)]
+}
+
+/// Equivalent to apply_hsv in the shaders
+/// Adjusts the antialiasing coverage of text drawn in the linear
+/// color `fg`, as adjust_coverage in glyph-frag.glsl does
+fn adjust_coverage(coverage: f32, [r, g, b]: [f32; 3], gamma: f32, contrast: f32) -> f32 {
+    let luminance = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let exponent = (1. / gamma) + (gamma - 1. / gamma) * luminance;
+    (coverage.powf(exponent) * contrast).clamp(0., 1.)
+}
+
+fn apply_hsv(rgb: [f32; 3], transform: [f32; 3]) -> [f32; 3] {
+    if transform == [1., 1., 1.] {
+        return rgb;
+    }
+    let [h, s, v] = rgb_to_hsv(rgb);
+    hsv_to_rgb([h * transform[0], s * transform[1], v * transform[2]])
+}
+
+/// The frame that we render into, as RGBA bytes.
+/// Like the OpenGL framebuffer, the colors are sRGB encoded and
+/// blending happens on those encoded values.
+pub struct SoftwareFrame {
+    width: usize,
+    height: usize,
+    pixels: Vec<[u8; 4]>,
+    srgb_to_linear: [f32; 256],
+}
+
+struct Texels<'a> {
+    data: &'a [u8],
+    width: usize,
+    height: usize,
+}
+
+impl<'a> Texels<'a> {
+    fn nearest(&self, u: f32, v: f32) -> [u8; 4] {
+        let x = ((u * self.width as f32) as usize).min(self.width - 1);
+        let y = ((v * self.height as f32) as usize).min(self.height - 1);
+        let idx = (y * self.width + x) * 4;
+        [
+            self.data[idx],
+            self.data[idx + 1],
+            self.data[idx + 2],
+            self.data[idx + 3],
+        ]
+    }
+
+    /// Bilinear filtering, performed on the sRGB encoded values.
+    /// That is a little less accurate than the gpu, but this is
+    /// only used for images.
+    fn linear(&self, u: f32, v: f32) -> [f32; 4] {
+        let x = (u * self.width as f32 - 0.5).max(0.);
+        let y = (v * self.height as f32 - 0.5).max(0.);
+        let fx = x.fract();
+        let fy = y.fract();
+        let x0 = (x as usize).min(self.width - 1);
+        let y0 = (y as usize).min(self.height - 1);
+        let x1 = (x0 + 1).min(self.width - 1);
+        let y1 = (y0 + 1).min(self.height - 1);
+
+        let texel = |x: usize, y: usize| {
+            let idx = (y * self.width + x) * 4;
+            &self.data[idx..idx + 4]
+        };
+        let (tl, tr, bl, br) = (texel(x0, y0), texel(x1, y0), texel(x0, y1), texel(x1, y1));
+
+        let mut result = [0.; 4];
+        for (i, c) in result.iter_mut().enumerate() {
+            let top = tl[i] as f32 * (1. - fx) + tr[i] as f32 * fx;
+            let bottom = bl[i] as f32 * (1. - fx) + br[i] as f32 * fx;
+            *c = (top * (1. - fy) + bottom * fy) / 255.;
+        }
+        result
+    }
+
+    /// Catmull-Rom bicubic filtering, performed on the sRGB
+    /// encoded values like linear()
+    fn cubic(&self, u: f32, v: f32) -> [f32; 4] {
+        let x = u * self.width as f32 - 0.5;
+        let y = v * self.height as f32 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let weights = |t: f32| {
+            [
+                t * (-0.5 + t * (1.0 - 0.5 * t)),
+                1.0 + t * t * (-2.5 + 1.5 * t),
+                t * (0.5 + t * (2.0 - 1.5 * t)),
+                t * t * (-0.5 + 0.5 * t),
+            ]
+        };
+        let (wx, wy) = (weights(x - x0), weights(y - y0));
+
+        let mut result = [0.; 4];
+        for (j, wy) in wy.iter().enumerate() {
+            let ty = (y0 as isize + j as isize - 1).clamp(0, self.height as isize - 1) as usize;
+            for (i, wx) in wx.iter().enumerate() {
+                let tx = (x0 as isize + i as isize - 1).clamp(0, self.width as isize - 1) as usize;
+                let idx = (ty * self.width + tx) * 4;
+                for (c, result) in result.iter_mut().enumerate() {
+                    *result += self.data[idx + c] as f32 * wx * wy;
+                }
+            }
+        }
+        result.map(|c| (c / 255.).clamp(0., 1.))
+    }
+}
+
+impl SoftwareFrame {
+    pub fn new(width: usize, height: usize) -> Self {
+        let mut srgb_to_linear = [0.; 256];
+        for (i, c) in srgb_to_linear.iter_mut().enumerate() {
+            *c = self::srgb_to_linear(i as f32 / 255.);
+        }
+        Self {
+            width,
+            height,
+            pixels: vec![[0; 4]; width * height],
+            srgb_to_linear,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Draws the quads from a vertex buffer, in order, sampling
+    /// from the pages of the glyph atlas
+    pub fn draw_quads(
+        &mut self,
+        vertices: &[Vertex],
+        atlas_pages: &[&Image],
+        foreground_text_hsb: [f32; 3],
+        text_gamma: f32,
+        text_contrast: f32,
+    ) {
+        let pages: Vec<Texels> = atlas_pages
+            .iter()
+            .map(|atlas| {
+                let (width, height) = atlas.image_dimensions();
+                Texels {
+                    data: atlas.pixel_data_slice(),
+                    width,
+                    height,
+                }
+            })
+            .collect();
+        for quad in vertices.chunks_exact(VERTICES_PER_CELL) {
+            self.draw_quad(quad, &pages, foreground_text_hsb, text_gamma, text_contrast);
+        }
+    }
+
+    fn draw_quad(
+        &mut self,
+        quad: &[Vertex],
+        pages: &[Texels],
+        foreground_text_hsb: [f32; 3],
+        text_gamma: f32,
+        text_contrast: f32,
+    ) {
+        let top_left = &quad[V_TOP_LEFT];
+        let bot_right = &quad[V_BOT_RIGHT];
+
+        // The integer part of the texture x coordinate is the atlas page
+        let page = top_left.tex[0].floor();
+        let texels = match pages.get(page as usize) {
+            Some(texels) => texels,
+            None => return,
+        };
+
+        // Vertex positions have their origin at the center of the window
+        let half_width = self.width as f32 / 2.;
+        let half_height = self.height as f32 / 2.;
+        let left = top_left.position[0] + half_width;
+        let top = top_left.position[1] + half_height;
+        let right = bot_right.position[0] + half_width;
+        let bottom = bot_right.position[1] + half_height;
+        if !(right > left && bottom > top) {
+            return;
+        }
+
+        // Cover the pixels whose centers are inside the quad, as
+        // opengl does
+        let first_pixel =
+            |edge: f32, limit: usize| ((edge - 0.5).ceil().max(0.) as usize).min(limit);
+        let x0 = first_pixel(left, self.width);
+        let x1 = first_pixel(right, self.width);
+        let y0 = first_pixel(top, self.height);
+        let y1 = first_pixel(bottom, self.height);
+        if x0 >= x1 || y0 >= y1 {
+            return;
+        }
+
+        let du = (bot_right.tex[0] - top_left.tex[0]) / (right - left);
+        let dv = (bot_right.tex[1] - top_left.tex[1]) / (bottom - top);
+        let tex_u = |x: usize| top_left.tex[0] - page + (x as f32 + 0.5 - left) * du;
+        let tex_v = |y: usize| top_left.tex[1] + (y as f32 + 0.5 - top) * dv;
+
+        let mix = top_left.mix_value;
+        let fg: [f32; 4] = std::array::from_fn(|i| {
+            top_left.fg_color[i] + (top_left.alt_color[i] - top_left.fg_color[i]) * mix
+        });
+        let mode = top_left.has_color;
+        let hsv = if mode == IS_GLYPH {
+            std::array::from_fn(|i| top_left.hsv[i] * foreground_text_hsb[i])
+        } else {
+            top_left.hsv
+        };
+
+        // For these, only the alpha varies across the quad, so we can
+        // compute the encoded color up front
+        let srgb = |rgb: [f32; 3]| rgb.map(linear_to_srgb);
+        let fg_srgb = srgb(apply_hsv([fg[0], fg[1], fg[2]], hsv));
+
+        for y in y0..y1 {
+            let v = tex_v(y);
+            for x in x0..x1 {
+                let (rgb, alpha) = if mode == IS_SOLID_COLOR {
+                    (fg_srgb, fg[3])
+                } else if mode == IS_GLYPH {
+                    let coverage = texels.nearest(tex_u(x), v)[3] as f32 / 255.;
+                    (
+                        fg_srgb,
+                        adjust_coverage(coverage, [fg[0], fg[1], fg[2]], text_gamma, text_contrast),
+                    )
+                } else if mode == IS_GRAY_SCALE {
+                    (
+                        fg_srgb,
+                        fg[3] * texels.nearest(tex_u(x), v)[3] as f32 / 255.,
+                    )
+                } else if mode == IS_COLOR_EMOJI
+                    || mode == IS_BG_IMAGE
+                    || mode == IS_IMAGE_NEAREST
+                    || mode == IS_IMAGE_CUBIC
+                {
+                    let [r, g, b, a] = if mode == IS_BG_IMAGE {
+                        texels.linear(tex_u(x), v)
+                    } else if mode == IS_IMAGE_CUBIC {
+                        texels.cubic(tex_u(x), v)
+                    } else {
+                        texels.nearest(tex_u(x), v).map(|c| c as f32 / 255.)
+                    };
+                    let rgb = if hsv == [1., 1., 1.] {
+                        [r, g, b]
+                    } else {
+                        let linear = [r, g, b]
+                            .map(|c| self.srgb_to_linear[((c * 255.).round() as usize).min(255)]);
+                        srgb(apply_hsv(linear, hsv))
+                    };
+                    let alpha = if mode == IS_COLOR_EMOJI { a } else { a * fg[3] };
+                    (rgb, alpha)
+                } else {
+                    continue;
+                };
+
+                self.blend(x, y, rgb, alpha);
+            }
+        }
+    }
+
+    /// Blends an sRGB encoded color over the frame using the same
+    /// function as the opengl renderer's alpha_blending
+    fn blend(&mut self, x: usize, y: usize, rgb: [f32; 3], alpha: f32) {
+        let alpha = alpha.clamp(0., 1.);
+        if alpha <= 0. {
+            return;
+        }
+        let dest = &mut self.pixels[y * self.width + x];
+        for i in 0..3 {
+            let c = rgb[i] * alpha + (dest[i] as f32 / 255.) * (1. - alpha);
+            dest[i] = (c * 255.).round().clamp(0., 255.) as u8;
+        }
+        let a = alpha + (dest[3] as f32 / 255.) * (1. - alpha);
+        dest[3] = (a * 255.).round().clamp(0., 255.) as u8;
+    }
+
+    /// Returns RGBA bytes with the rows ordered bottom-up, as
+    /// expected by complete_captures
+    pub fn to_rgba_bottom_up(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(self.width * self.height * 4);
+        for row in self.pixels.chunks_exact(self.width.max(1)).rev() {
+            for pixel in row {
+                data.extend_from_slice(pixel);
+            }
+        }
+        data
+    }
+
+    /// Consumes the frame, returning ARGB values as expected by
+    /// present_software_frame, laid out in a buffer that has
+    /// `transform` applied.
+    /// Since we blend onto a transparent frame, the colors are
+    /// effectively premultiplied by their alpha already.
+    pub fn into_argb(self, transform: BufferTransform) -> Vec<u32> {
+        let argb = |[r, g, b, a]: [u8; 4]| u32::from_be_bytes([a, r, g, b]);
+        if transform == BufferTransform::Normal {
+            return self.pixels.into_iter().map(argb).collect();
+        }
+
+        let (buffer_width, _) = transform.buffer_size(self.width, self.height);
+        let mut data = vec![0; self.pixels.len()];
+        for (idx, pixel) in self.pixels.into_iter().enumerate() {
+            let (x, y) = (idx % self.width, idx / self.width);
+            // Map the center of the pixel, so that we land squarely
+            // in the corresponding buffer pixel
+            let (bx, by) = transform.transform_point(
+                x as f32 + 0.5,
+                y as f32 + 0.5,
+                self.width as f32,
+                self.height as f32,
+            );
+            data[by as usize * buffer_width + bx as usize] = argb(pixel);
+        }
+        data
+    }
+}
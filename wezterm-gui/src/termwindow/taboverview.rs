@@ -0,0 +1,502 @@
+//! The tab overview shows a thumbnail of each tab in a grid,
+//! from which a tab can be picked using the keyboard or the mouse.
+//!
+//! The thumbnails are offscreen captures of the tabs, so they show
+//! each tab as it looked when it was last activated, or, for the
+//! active tab, when the overview was opened.
+use crate::glyphcache::{ImagePlayback, LoadState};
+use crate::quad::{QuadTrait, TripleLayerQuadAllocatorTrait};
+use crate::termwindow::box_model::*;
+use crate::termwindow::capture::{CaptureDestination, CaptureTarget};
+use crate::termwindow::modal::Modal;
+use crate::termwindow::{DimensionContext, TermWindow, UIItemType};
+use crate::utilsprites::RenderMetrics;
+use config::keyassignment::{KeyAssignment, TabOverviewArguments, TabOverviewScope};
+use config::{Dimension, ImageFilter};
+use mux::tab::TabId;
+use mux::window::WindowId as MuxWindowId;
+use mux::Mux;
+use std::cell::{Ref, RefCell};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use termwiz::image::{ImageData, ImageDataType};
+use wezterm_term::{KeyCode, KeyModifiers, MouseEvent};
+use window::color::LinearRgba;
+use window::{MouseEventKind as WMEK, MousePress, RectF};
+
+/// The largest width, in pixels, at which thumbnails are kept
+const THUMBNAIL_MAX_WIDTH: u32 = 640;
+
+lazy_static::lazy_static! {
+    /// The most recent thumbnail of each tab.  Tab ids are unique
+    /// across windows, so this is shared by all of them.
+    static ref THUMBNAILS: Mutex<HashMap<TabId, Arc<ImageData>>> = Mutex::new(HashMap::new());
+}
+
+/// Keeps a scaled down copy of `image` as the thumbnail of `tab_id`
+pub fn store_thumbnail(tab_id: TabId, image: &image::RgbaImage) {
+    if image.width() == 0 || image.height() == 0 {
+        return;
+    }
+    let width = image.width().min(THUMBNAIL_MAX_WIDTH);
+    let height = ((image.height() as u64 * width as u64) / image.width() as u64).max(1) as u32;
+    let thumbnail = image::imageops::thumbnail(image, width, height);
+    let image = ImageData::with_data(ImageDataType::new_single_frame(
+        width,
+        height,
+        thumbnail.into_raw(),
+    ));
+    THUMBNAILS.lock().unwrap().insert(tab_id, Arc::new(image));
+}
+
+fn thumbnail(tab_id: TabId) -> Option<Arc<ImageData>> {
+    THUMBNAILS.lock().unwrap().get(&tab_id).cloned()
+}
+
+/// Forgets the thumbnails of tabs that no longer exist
+fn prune_thumbnails() {
+    let mux = Mux::get();
+    THUMBNAILS
+        .lock()
+        .unwrap()
+        .retain(|tab_id, _| mux.get_tab(*tab_id).is_some());
+}
+
+struct OverviewEntry {
+    tab_id: TabId,
+    window_id: MuxWindowId,
+    workspace: String,
+    title: String,
+}
+
+pub struct TabOverview {
+    element: RefCell<Option<Vec<ComputedElement>>>,
+    entries: Vec<OverviewEntry>,
+    selected: RefCell<usize>,
+    columns: RefCell<usize>,
+    /// Where the thumbnail of each entry is drawn; computed
+    /// along with the elements
+    thumbnail_rects: RefCell<Vec<RectF>>,
+}
+
+impl TabOverview {
+    pub fn new(term_window: &mut TermWindow, args: &TabOverviewArguments) -> Self {
+        prune_thumbnails();
+
+        let mux = Mux::get();
+        let active_workspace = mux.active_workspace();
+        let window_ids = match args.scope {
+            TabOverviewScope::Window => vec![term_window.mux_window_id],
+            TabOverviewScope::Workspace => mux.iter_windows_in_workspace(&active_workspace),
+            TabOverviewScope::AllWorkspaces => mux.iter_windows(),
+        };
+
+        let mut entries = vec![];
+        let mut selected = 0;
+        for window_id in window_ids {
+            let window = match mux.get_window(window_id) {
+                Some(window) => window,
+                None => continue,
+            };
+            let active_idx = window.get_active_idx();
+            for (tab_idx, tab) in window.iter().enumerate() {
+                if window_id == term_window.mux_window_id && tab_idx == active_idx {
+                    selected = entries.len();
+                }
+
+                let mut title = tab.get_title();
+                if title.is_empty() {
+                    title = tab
+                        .get_active_pane()
+                        .map(|pane| pane.get_title())
+                        .unwrap_or_default();
+                }
+                let title = match args.scope {
+                    TabOverviewScope::Window => format!("{}: {title}", tab_idx + 1),
+                    TabOverviewScope::Workspace | TabOverviewScope::AllWorkspaces
+                        if window.get_workspace() != active_workspace =>
+                    {
+                        format!("[{}] {title}", window.get_workspace())
+                    }
+                    TabOverviewScope::Workspace | TabOverviewScope::AllWorkspaces => title,
+                };
+
+                entries.push(OverviewEntry {
+                    tab_id: tab.tab_id(),
+                    window_id,
+                    workspace: window.get_workspace().to_string(),
+                    title,
+                });
+            }
+        }
+
+        Self {
+            element: RefCell::new(None),
+            entries,
+            selected: RefCell::new(selected),
+            columns: RefCell::new(1),
+            thumbnail_rects: RefCell::new(vec![]),
+        }
+    }
+
+    fn compute(
+        &self,
+        term_window: &mut TermWindow,
+    ) -> anyhow::Result<(Vec<ComputedElement>, usize, Vec<RectF>)> {
+        let font = term_window
+            .fonts
+            .command_palette_font()
+            .expect("to resolve tab overview font");
+        let metrics = RenderMetrics::with_font_metrics(&font.metrics());
+        let dimensions = term_window.dimensions;
+        let window_width = dimensions.pixel_width as f32;
+        let window_height = dimensions.pixel_height as f32;
+
+        let bg_color = term_window.config.command_palette_bg_color.to_linear();
+        let fg_color = term_window.config.command_palette_fg_color.to_linear();
+
+        let context = LayoutContext {
+            height: DimensionContext {
+                dpi: dimensions.dpi as f32,
+                pixel_max: window_height,
+                pixel_cell: metrics.cell_size.height as f32,
+            },
+            width: DimensionContext {
+                dpi: dimensions.dpi as f32,
+                pixel_max: window_width,
+                pixel_cell: metrics.cell_size.width as f32,
+            },
+            bounds: euclid::rect(0., 0., window_width, window_height),
+            metrics: &metrics,
+            gl_state: term_window.render_state.as_ref().unwrap(),
+            zindex: 100,
+        };
+
+        // Dim the window behind the grid; clicking outside of
+        // the thumbnails closes the overview
+        let backdrop = Element::new(&font, ElementContent::Text(String::new()))
+            .colors(ElementColors {
+                border: BorderColor::default(),
+                bg: bg_color.mul_alpha(0.85).into(),
+                text: fg_color.into(),
+            })
+            .display(DisplayType::Block)
+            .min_width(Some(Dimension::Pixels(window_width)))
+            .min_height(Some(Dimension::Pixels(window_height)))
+            .item_type(UIItemType::TabOverview(None));
+        let mut elements = vec![term_window.compute_element(&context, &backdrop)?];
+
+        let num_entries = self.entries.len().max(1);
+        let columns = (num_entries as f32).sqrt().ceil() as usize;
+        let rows = (num_entries + columns - 1) / columns;
+
+        let cell_width = metrics.cell_size.width as f32;
+        let cell_height = metrics.cell_size.height as f32;
+        let gap = cell_width * 2.;
+        let title_height = cell_height * 1.5;
+        let item_width = ((window_width - gap * (columns + 1) as f32) / columns as f32).max(1.);
+        let item_height = ((window_height - gap * (rows + 1) as f32) / rows as f32).max(1.);
+        let max_title_chars = ((item_width / cell_width) as usize).saturating_sub(2);
+
+        let selected = *self.selected.borrow();
+        let mut thumbnail_rects = vec![];
+        for (idx, entry) in self.entries.iter().enumerate() {
+            let x = gap + (idx % columns) as f32 * (item_width + gap);
+            let y = gap + (idx / columns) as f32 * (item_height + gap);
+
+            let border = if idx == selected { fg_color } else { bg_color };
+            let title: String = entry.title.chars().take(max_title_chars).collect();
+            let item = Element::new(&font, ElementContent::Text(title))
+                .colors(ElementColors {
+                    border: BorderColor::new(border),
+                    bg: bg_color.into(),
+                    text: fg_color.into(),
+                })
+                .display(DisplayType::Block)
+                .padding(BoxDimension {
+                    left: Dimension::Cells(0.5),
+                    right: Dimension::Cells(0.5),
+                    top: Dimension::Pixels(item_height - title_height),
+                    bottom: Dimension::Cells(0.25),
+                })
+                .border(BoxDimension::new(Dimension::Pixels(2.)))
+                .min_width(Some(Dimension::Pixels(item_width)))
+                .item_type(UIItemType::TabOverview(Some(idx)));
+
+            let computed = term_window.compute_element(
+                &LayoutContext {
+                    bounds: euclid::rect(x, y, item_width, item_height),
+                    ..context
+                },
+                &item,
+            )?;
+            elements.push(computed);
+
+            thumbnail_rects.push(euclid::rect(
+                x + 2.,
+                y + 2.,
+                item_width - 4.,
+                (item_height - title_height - 2.).max(0.),
+            ));
+        }
+
+        Ok((elements, columns, thumbnail_rects))
+    }
+
+    fn move_selection(&self, delta: isize, term_window: &mut TermWindow) {
+        let len = self.entries.len() as isize;
+        if len == 0 {
+            return;
+        }
+        let mut selected = self.selected.borrow_mut();
+        let target = *selected as isize + delta;
+        if (0..len).contains(&target) {
+            *selected = target as usize;
+            drop(selected);
+            term_window.invalidate_modal();
+        }
+    }
+
+    fn select(&self, idx: usize, term_window: &mut TermWindow) {
+        if idx < self.entries.len() && *self.selected.borrow() != idx {
+            *self.selected.borrow_mut() = idx;
+            term_window.invalidate_modal();
+        }
+    }
+
+    /// Switches to the tab at `idx`, and to its window
+    /// and workspace if they are not the current ones
+    pub fn activate(&self, idx: usize, term_window: &mut TermWindow) {
+        let entry = match self.entries.get(idx) {
+            Some(entry) => entry,
+            None => return,
+        };
+        term_window.cancel_modal();
+
+        let mux = Mux::get();
+        if entry.workspace != mux.active_workspace() {
+            crate::frontend::front_end().switch_workspace(&entry.workspace);
+        }
+        if let Some(mut window) = mux.get_window_mut(entry.window_id) {
+            if let Some(tab_idx) = window.idx_by_id(entry.tab_id) {
+                window.save_and_then_set_active(tab_idx);
+            }
+        }
+        if entry.window_id == term_window.mux_window_id {
+            if let Some(pane) = term_window.get_active_pane_or_overlay() {
+                pane.focus_changed(true);
+            }
+            term_window.update_title();
+            term_window.update_scrollbar();
+        } else if let Some(gui_window) =
+            crate::frontend::front_end().gui_window_for_mux_window(entry.window_id)
+        {
+            gui_window.window.focus();
+        }
+    }
+}
+
+impl Modal for TabOverview {
+    fn perform_assignment(
+        &self,
+        _assignment: &KeyAssignment,
+        _term_window: &mut TermWindow,
+    ) -> bool {
+        false
+    }
+
+    fn mouse_event(&self, _event: MouseEvent, _term_window: &mut TermWindow) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn key_down(
+        &self,
+        key: KeyCode,
+        mods: KeyModifiers,
+        term_window: &mut TermWindow,
+    ) -> anyhow::Result<bool> {
+        let columns = *self.columns.borrow() as isize;
+        match (key, mods) {
+            (KeyCode::Escape, KeyModifiers::NONE) | (KeyCode::Char('g'), KeyModifiers::CTRL) => {
+                term_window.cancel_modal();
+            }
+            (KeyCode::LeftArrow, KeyModifiers::NONE) | (KeyCode::Char('h'), KeyModifiers::NONE) => {
+                self.move_selection(-1, term_window);
+            }
+            (KeyCode::RightArrow, KeyModifiers::NONE)
+            | (KeyCode::Char('l'), KeyModifiers::NONE) => {
+                self.move_selection(1, term_window);
+            }
+            (KeyCode::UpArrow, KeyModifiers::NONE) | (KeyCode::Char('k'), KeyModifiers::NONE) => {
+                self.move_selection(-columns, term_window);
+            }
+            (KeyCode::DownArrow, KeyModifiers::NONE) | (KeyCode::Char('j'), KeyModifiers::NONE) => {
+                self.move_selection(columns, term_window);
+            }
+            (KeyCode::Tab, KeyModifiers::NONE) => {
+                let len = self.entries.len();
+                if len > 0 {
+                    self.select((*self.selected.borrow() + 1) % len, term_window);
+                }
+            }
+            (KeyCode::Tab, KeyModifiers::SHIFT) => {
+                let len = self.entries.len();
+                if len > 0 {
+                    self.select((*self.selected.borrow() + len - 1) % len, term_window);
+                }
+            }
+            (KeyCode::Char(c), KeyModifiers::NONE) if ('1'..='9').contains(&c) => {
+                // Digits pick the corresponding tab directly
+                let idx = c as usize - '1' as usize;
+                if idx < self.entries.len() {
+                    self.activate(idx, term_window);
+                }
+            }
+            (KeyCode::Enter, KeyModifiers::NONE) => {
+                let selected = *self.selected.borrow();
+                self.activate(selected, term_window);
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    fn computed_element(
+        &self,
+        term_window: &mut TermWindow,
+    ) -> anyhow::Result<Ref<'_, [ComputedElement]>> {
+        if self.element.borrow().is_none() {
+            let (element, columns, thumbnail_rects) = self.compute(term_window)?;
+            self.element.borrow_mut().replace(element);
+            *self.columns.borrow_mut() = columns;
+            *self.thumbnail_rects.borrow_mut() = thumbnail_rects;
+        }
+        Ok(Ref::map(self.element.borrow(), |v| {
+            v.as_ref().unwrap().as_slice()
+        }))
+    }
+
+    fn reconfigure(&self, _term_window: &mut TermWindow) {
+        self.element.borrow_mut().take();
+    }
+
+    fn paint(&self, term_window: &mut TermWindow) -> anyhow::Result<()> {
+        let gl_state = term_window.render_state.as_ref().unwrap();
+        let layer = gl_state.layer_for_zindex(101)?;
+        let mut layers = layer.quad_allocator();
+        let left_offset = term_window.dimensions.pixel_width as f32 / 2.;
+        let top_offset = term_window.dimensions.pixel_height as f32 / 2.;
+
+        for (entry, rect) in self
+            .entries
+            .iter()
+            .zip(self.thumbnail_rects.borrow().iter())
+        {
+            let image = match thumbnail(entry.tab_id) {
+                Some(image) => image,
+                None => continue,
+            };
+            let (sprite, _next_due, load_state) = gl_state.glyph_cache.borrow_mut().cached_image(
+                &image,
+                None,
+                term_window.allow_images,
+                ImagePlayback::default(),
+            )?;
+            if load_state == LoadState::Loading {
+                continue;
+            }
+
+            // Scale the thumbnail to fit, preserving its aspect ratio
+            let tex_width = sprite.coords.width() as f32;
+            let tex_height = sprite.coords.height() as f32;
+            if tex_width <= 0. || tex_height <= 0. || rect.is_empty() {
+                continue;
+            }
+            let scale = (rect.width() / tex_width).min(rect.height() / tex_height);
+            let width = tex_width * scale;
+            let height = tex_height * scale;
+            let x = rect.min_x() + (rect.width() - width) / 2. - left_offset;
+            let y = rect.min_y() + (rect.height() - height) / 2. - top_offset;
+
+            let mut quad = layers.allocate(0)?;
+            quad.set_position(x, y, x + width, y + height);
+            let coords = sprite.texture_coords();
+            quad.set_texture_discrete(
+                coords.min_x(),
+                coords.max_x(),
+                coords.min_y(),
+                coords.max_y(),
+            );
+            quad.set_is_image(ImageFilter::Linear);
+            quad.set_hsv(None);
+            quad.set_fg_color(LinearRgba::with_components(1., 1., 1., 1.));
+        }
+        Ok(())
+    }
+}
+
+impl TermWindow {
+    /// Opens the tab overview, once a fresh thumbnail
+    /// of the active tab has been captured
+    pub fn show_tab_overview(&mut self, args: &TabOverviewArguments) {
+        let mux = Mux::get();
+        match mux.get_active_tab_for_window(self.mux_window_id) {
+            Some(tab) => {
+                self.thumbnail_tab_id.replace(tab.tab_id());
+                self.request_capture(
+                    CaptureTarget::Tab,
+                    CaptureDestination::Thumbnail {
+                        tab_id: tab.tab_id(),
+                        overview: Some(args.clone()),
+                    },
+                );
+            }
+            None => {
+                let modal = TabOverview::new(self, args);
+                self.set_modal(std::rc::Rc::new(modal));
+            }
+        }
+    }
+
+    /// Captures a thumbnail of the active tab the first
+    /// time that it is painted after becoming active
+    pub fn capture_tab_thumbnail_if_needed(&mut self) {
+        if self.get_modal().is_some() {
+            return;
+        }
+        let mux = Mux::get();
+        let tab_id = match mux.get_active_tab_for_window(self.mux_window_id) {
+            Some(tab) => tab.tab_id(),
+            None => return,
+        };
+        if self.thumbnail_tab_id == Some(tab_id) {
+            return;
+        }
+        self.thumbnail_tab_id.replace(tab_id);
+        self.request_capture(
+            CaptureTarget::Tab,
+            CaptureDestination::Thumbnail {
+                tab_id,
+                overview: None,
+            },
+        );
+    }
+
+    /// Called when a thumbnail in the tab overview is clicked or hovered
+    pub fn mouse_event_tab_overview(&mut self, idx: Option<usize>, event: &window::MouseEvent) {
+        let modal = match self.get_modal() {
+            Some(modal) => modal,
+            None => return,
+        };
+        let overview = match modal.downcast_ref::<TabOverview>() {
+            Some(overview) => overview,
+            None => return,
+        };
+        match (idx, &event.kind) {
+            (Some(idx), WMEK::Move) => overview.select(idx, self),
+            (Some(idx), WMEK::Press(MousePress::Left)) => overview.activate(idx, self),
+            (None, WMEK::Press(_)) => self.cancel_modal(),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,313 @@
+//! Toasts are short messages that are shown in the top right corner of
+//! the window, on top of the panes.  They are dismissed by clicking on
+//! them, and most of them also expire after a while.
+use crate::termwindow::box_model::*;
+use crate::termwindow::{TermWindow, TermWindowNotif, UIItemType};
+use crate::utilsprites::RenderMetrics;
+use anyhow::Context;
+use config::{Dimension, DimensionContext, InlineToastEvent};
+use mux::pane::PaneId;
+use mux::Mux;
+use std::time::{Duration, Instant};
+use wezterm_dynamic::{FromDynamic, ToDynamic};
+use window::color::LinearRgba;
+use window::{MouseEvent, MouseEventKind as WMEK, MousePress, WindowOps};
+
+/// No more than this many toasts are shown at once;
+/// the oldest are discarded to make room for newer ones
+const MAX_TOASTS: usize = 5;
+/// The widest that a toast can be, in cells
+const MAX_TOAST_COLS: usize = 60;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, FromDynamic, ToDynamic)]
+pub enum ToastLevel {
+    #[default]
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Default, FromDynamic, ToDynamic)]
+pub struct ToastOptions {
+    #[dynamic(default)]
+    pub level: ToastLevel,
+    /// How long to show the toast; 0 means that it remains until it
+    /// is clicked.  Defaults to the inline_toast_duration_ms config
+    pub timeout_milliseconds: Option<u64>,
+}
+
+pub struct Toast {
+    id: usize,
+    title: String,
+    message: String,
+    level: ToastLevel,
+    expires: Option<Instant>,
+}
+
+impl TermWindow {
+    pub fn show_toast(&mut self, title: &str, message: &str, options: ToastOptions) {
+        let timeout = options
+            .timeout_milliseconds
+            .unwrap_or(self.config.inline_toast_duration_ms);
+        let expires = if timeout == 0 {
+            None
+        } else {
+            Some(Instant::now() + Duration::from_millis(timeout))
+        };
+
+        self.next_toast_id += 1;
+        self.toasts.push(Toast {
+            id: self.next_toast_id,
+            title: title.to_string(),
+            message: message.to_string(),
+            level: options.level,
+            expires,
+        });
+        if self.toasts.len() > MAX_TOASTS {
+            let excess = self.toasts.len() - MAX_TOASTS;
+            self.toasts.drain(0..excess);
+        }
+
+        if let Some(window) = self.window.as_ref() {
+            window.invalidate();
+        }
+    }
+
+    /// Shows a toast for one of wezterm's own events, if
+    /// inline_toast_events says that it should be shown
+    pub fn show_toast_for_event(&mut self, event: InlineToastEvent, title: &str, message: &str) {
+        if !self.config.inline_toast_events.contains(&event) {
+            return;
+        }
+        let level = match event {
+            InlineToastEvent::ConfigError => ToastLevel::Error,
+            InlineToastEvent::BackgroundBell => ToastLevel::Warning,
+            InlineToastEvent::Copy => ToastLevel::Info,
+        };
+        // Errors remain until they have been seen and dismissed
+        let timeout_milliseconds = match event {
+            InlineToastEvent::ConfigError => Some(0),
+            InlineToastEvent::BackgroundBell | InlineToastEvent::Copy => None,
+        };
+        self.show_toast(
+            title,
+            message,
+            ToastOptions {
+                level,
+                timeout_milliseconds,
+            },
+        );
+    }
+
+    /// Reports the bell ringing in `pane_id` when it isn't in the active tab
+    pub fn show_background_bell_toast(&mut self, pane_id: PaneId) {
+        let mux = Mux::get();
+        let tab_id = match mux.resolve_pane_id(pane_id) {
+            Some((_domain_id, _window_id, tab_id)) => tab_id,
+            None => return,
+        };
+        let active_tab_id = mux
+            .get_active_tab_for_window(self.mux_window_id)
+            .map(|tab| tab.tab_id());
+        if active_tab_id == Some(tab_id) {
+            return;
+        }
+
+        let tab_idx = mux
+            .get_window(self.mux_window_id)
+            .and_then(|window| window.idx_by_id(tab_id));
+        let title = mux
+            .get_pane(pane_id)
+            .map(|pane| pane.get_title())
+            .unwrap_or_default();
+        let message = match tab_idx {
+            Some(idx) => format!("Bell in tab {}: {title}", idx + 1),
+            None => format!("Bell in {title}"),
+        };
+        self.show_toast_for_event(InlineToastEvent::BackgroundBell, "", &message);
+    }
+
+    pub fn dismiss_toast(&mut self, id: usize) {
+        self.toasts.retain(|toast| toast.id != id);
+        if let Some(window) = self.window.as_ref() {
+            window.invalidate();
+        }
+    }
+
+    pub fn mouse_event_toast(&mut self, id: usize, event: &MouseEvent) {
+        if let WMEK::Press(MousePress::Left) = event.kind {
+            self.dismiss_toast(id);
+        }
+    }
+
+    fn toast_accent_color(&self, level: ToastLevel) -> LinearRgba {
+        let palette = self.palette();
+        match level {
+            ToastLevel::Info => palette.colors.0[4],
+            ToastLevel::Warning => palette.colors.0[3],
+            ToastLevel::Error => palette.colors.0[1],
+        }
+        .to_linear()
+    }
+
+    /// Draws the toasts that haven't yet expired, stacked
+    /// downwards from the top right corner of the window
+    pub fn paint_toasts(&mut self) -> anyhow::Result<()> {
+        let now = Instant::now();
+        self.toasts
+            .retain(|toast| toast.expires.map(|when| when > now).unwrap_or(true));
+        if self.toasts.is_empty() {
+            return Ok(());
+        }
+        let next_expiry = self.toasts.iter().filter_map(|toast| toast.expires).min();
+        self.update_next_frame_time(next_expiry);
+
+        let font = self
+            .fonts
+            .command_palette_font()
+            .context("command_palette_font")?;
+        let metrics = RenderMetrics::with_font_metrics(&font.metrics());
+        let dimensions = self.dimensions;
+        let border = self.get_os_border();
+        let bg_color = self.config.command_palette_bg_color.to_linear();
+        let fg_color = self.config.command_palette_fg_color.to_linear();
+
+        let cell_width = metrics.cell_size.width as f32;
+        let max_cols = ((dimensions.pixel_width as f32 / 2. / cell_width) as usize)
+            .min(MAX_TOAST_COLS)
+            .max(10);
+
+        let tab_bar_height = if self.show_tab_bar && !self.config.tab_bar_at_bottom {
+            self.tab_bar_pixel_height()?
+        } else {
+            0.
+        };
+        let mut top =
+            border.top.get() as f32 + tab_bar_height + metrics.cell_size.height as f32 / 2.;
+        let right = dimensions.pixel_width as f32 - border.right.get() as f32 - cell_width;
+
+        let mut computed_toasts = vec![];
+        for toast in &self.toasts {
+            let mut lines = vec![];
+            if !toast.title.is_empty() {
+                lines.push(
+                    Element::new(&font, ElementContent::Text(toast.title.clone()))
+                        .colors(ElementColors {
+                            border: BorderColor::default(),
+                            bg: InheritableColor::Inherited,
+                            text: self.toast_accent_color(toast.level).into(),
+                        })
+                        .display(DisplayType::Block),
+                );
+            }
+            for line in toast.message.lines() {
+                for wrapped in textwrap::wrap(line, max_cols) {
+                    lines.push(
+                        Element::new(&font, ElementContent::Text(wrapped.into_owned()))
+                            .display(DisplayType::Block),
+                    );
+                }
+            }
+
+            let element = Element::new(&font, ElementContent::Children(lines))
+                .colors(ElementColors {
+                    border: BorderColor::new(self.toast_accent_color(toast.level)),
+                    bg: bg_color.into(),
+                    text: fg_color.into(),
+                })
+                .padding(BoxDimension {
+                    left: Dimension::Cells(0.75),
+                    right: Dimension::Cells(0.75),
+                    top: Dimension::Cells(0.25),
+                    bottom: Dimension::Cells(0.25),
+                })
+                .border(BoxDimension {
+                    left: Dimension::Pixels(4.),
+                    right: Dimension::Pixels(1.),
+                    top: Dimension::Pixels(1.),
+                    bottom: Dimension::Pixels(1.),
+                })
+                .item_type(UIItemType::Toast(toast.id));
+
+            let mut computed = self.compute_element(
+                &LayoutContext {
+                    height: DimensionContext {
+                        dpi: dimensions.dpi as f32,
+                        pixel_max: dimensions.pixel_height as f32,
+                        pixel_cell: metrics.cell_size.height as f32,
+                    },
+                    width: DimensionContext {
+                        dpi: dimensions.dpi as f32,
+                        pixel_max: dimensions.pixel_width as f32,
+                        pixel_cell: cell_width,
+                    },
+                    bounds: euclid::rect(
+                        0.,
+                        0.,
+                        dimensions.pixel_width as f32,
+                        dimensions.pixel_height as f32,
+                    ),
+                    metrics: &metrics,
+                    gl_state: self.render_state.as_ref().unwrap(),
+                    zindex: 90,
+                },
+                &element,
+            )?;
+            computed.translate(euclid::vec2((right - computed.bounds.width()).max(0.), top));
+            top += computed.bounds.height() + metrics.cell_size.height as f32 / 4.;
+            computed_toasts.push(computed);
+        }
+
+        let gl_state = self.render_state.as_ref().unwrap();
+        for computed in &computed_toasts {
+            self.render_element(computed, gl_state, None)?;
+        }
+        for computed in computed_toasts {
+            let bounds = computed.bounds;
+            self.dirty_rects.borrow_mut().push((
+                bounds.min_x() as i32,
+                bounds.min_y() as i32,
+                bounds.width().ceil() as i32,
+                bounds.height().ceil() as i32,
+            ));
+            self.ui_items.append(&mut computed.ui_items());
+        }
+
+        Ok(())
+    }
+}
+
+/// Shows an error in the configuration as a toast in each of the
+/// gui windows, when inline_toast_events includes ConfigError,
+/// or else in the configuration error window
+pub fn show_configuration_error(err: &str) {
+    let err = err.to_string();
+    promise::spawn::spawn_into_main_thread(async move {
+        let config = config::configuration();
+        let windows = crate::frontend::try_front_end()
+            .map(|fe| fe.gui_windows())
+            .unwrap_or_default();
+        if windows.is_empty()
+            || !config
+                .inline_toast_events
+                .contains(&InlineToastEvent::ConfigError)
+        {
+            mux::connui::show_configuration_error_message(&err);
+            return;
+        }
+
+        log::error!("Configuration Error: {}", err);
+        for win in windows {
+            let err = err.clone();
+            win.window
+                .notify(TermWindowNotif::Apply(Box::new(move |term_window| {
+                    term_window.show_toast_for_event(
+                        InlineToastEvent::ConfigError,
+                        "Configuration Error",
+                        &err,
+                    );
+                })));
+        }
+    })
+    .detach();
+}
@@ -1,7 +1,9 @@
 use crate::quad::Vertex;
+use crate::termwindow::gpuselection::GpuSelection;
+use crate::termwindow::icc::{ColorLut, LUT_SIZE};
 use anyhow::anyhow;
 use config::{ConfigHandle, GpuInfo, WebGpuPowerPreference};
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::sync::Arc;
 use wgpu::util::DeviceExt;
 use window::bitmaps::Texture2d;
@@ -17,6 +19,17 @@ pub struct ShaderUniform {
     pub foreground_text_hsb: [f32; 3],
     pub milliseconds: u32,
     pub projection: [[f32; 4]; 4],
+    /// Multiplier applied to the output color; 1.0 except in
+    /// hdr mode, where it maps sRGB white to the SDR reference white
+    pub sdr_scale: f32,
+    /// Non-zero if the output is converted to the display color
+    /// space via the color lut
+    pub color_lut: u32,
+    /// The page of the texture atlas that is bound for this draw
+    pub atlas_page: f32,
+    pub text_gamma: f32,
+    pub text_contrast: f32,
+    pub _padding: [f32; 3],
     // sampler2D atlas_nearest_sampler;
     // sampler2D atlas_linear_sampler;
 }
@@ -29,12 +42,42 @@ pub struct WebGpuState {
     pub queue: Arc<wgpu::Queue>,
     pub config: RefCell<wgpu::SurfaceConfiguration>,
     pub dimensions: RefCell<Dimensions>,
-    pub render_pipeline: wgpu::RenderPipeline,
+    pub render_pipeline: RefCell<wgpu::RenderPipeline>,
+    /// Blends glyphs using their subpixel coverage; only available
+    /// when the adapter supports dual source blending
+    pub subpixel_pipeline: RefCell<Option<wgpu::RenderPipeline>>,
+    shader: wgpu::ShaderModule,
+    subpixel_shader: Option<wgpu::ShaderModule>,
+    render_pipeline_layout: wgpu::PipelineLayout,
+    /// The format that we use for SDR output
+    sdr_format: wgpu::TextureFormat,
+    sdr_view_formats: Vec<wgpu::TextureFormat>,
+    /// The half-float format that we use for scRGB output, if the
+    /// surface supports it
+    hdr_format: Option<wgpu::TextureFormat>,
+    pub hdr: Cell<bool>,
     shader_uniform_bind_group_layout: wgpu::BindGroupLayout,
     pub texture_bind_group_layout: wgpu::BindGroupLayout,
+    color_lut_bind_group_layout: wgpu::BindGroupLayout,
+    /// The lookup table that converts our output to the display
+    /// color space, or a placeholder if there is no conversion
+    pub color_lut_bind_group: RefCell<wgpu::BindGroup>,
+    pub has_color_lut: Cell<bool>,
     pub texture_nearest_sampler: wgpu::Sampler,
     pub texture_linear_sampler: wgpu::Sampler,
     pub handle: RawHandlePair,
+    /// Which adapter was chosen, and why
+    pub selection: GpuSelection,
+}
+
+/// An adapter that is able to present to the surface
+struct OpenedAdapter {
+    adapter_info: wgpu::AdapterInfo,
+    caps: wgpu::SurfaceCapabilities,
+    downlevel_caps: wgpu::DownlevelCapabilities,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    config: wgpu::SurfaceConfiguration,
 }
 
 pub struct RawHandlePair {
@@ -188,6 +231,20 @@ pub fn adapter_info_to_gpu_info(info: wgpu::AdapterInfo) -> GpuInfo {
     }
 }
 
+/// Returns information about all of the GPUs that wgpu knows about
+pub fn enumerate_gpus() -> Vec<GpuInfo> {
+    let backends = wgpu::Backends::all();
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+        backends,
+        ..Default::default()
+    });
+    instance
+        .enumerate_adapters(backends)
+        .into_iter()
+        .map(|adapter| adapter_info_to_gpu_info(adapter.get_info()))
+        .collect()
+}
+
 fn compute_compatibility_list(
     instance: &wgpu::Instance,
     backends: wgpu::Backends,
@@ -232,64 +289,66 @@ impl WebGpuState {
             instance.create_surface_unsafe(wgpu::SurfaceTargetUnsafe::from_window(&handle)?)?
         };
 
-        let mut adapter: Option<wgpu::Adapter> = None;
+        let preferences = &config.webgpu_preferred_adapter;
+        let mut rejected = vec![];
+        let mut opened = None;
 
-        if let Some(preference) = &config.webgpu_preferred_adapter {
+        if !preferences.is_empty() {
+            // Order the matching adapters by the position of the first
+            // preference that they match; the sort is stable, so adapters
+            // that match the same preference remain in the order in
+            // which wgpu enumerated them
+            let mut candidates = vec![];
             for a in instance.enumerate_adapters(backends) {
+                let info = adapter_info_to_gpu_info(a.get_info());
+                let rank = match preferences.position(&info) {
+                    Some(rank) => rank,
+                    None => continue,
+                };
                 if !a.is_surface_supported(&surface) {
-                    let info = adapter_info_to_gpu_info(a.get_info());
                     log::warn!("{} is not compatible with surface", info.to_string());
+                    rejected.push(format!(
+                        "{}: not compatible with the surface",
+                        info.to_string()
+                    ));
                     continue;
                 }
-
-                let info = a.get_info();
-
-                if preference.name != info.name {
-                    continue;
-                }
-
-                if preference.device_type != format!("{:?}", info.device_type) {
-                    continue;
-                }
-
-                if preference.backend != format!("{:?}", info.backend) {
-                    continue;
-                }
-
-                if let Some(driver) = &preference.driver {
-                    if *driver != info.driver {
-                        continue;
-                    }
-                }
-                if let Some(vendor) = &preference.vendor {
-                    if *vendor != info.vendor {
-                        continue;
-                    }
-                }
-                if let Some(device) = &preference.device {
-                    if *device != info.device {
-                        continue;
-                    }
-                }
-
-                adapter.replace(a);
-                break;
+                candidates.push((rank, a));
             }
+            candidates.sort_by_key(|(rank, _)| *rank);
 
-            if adapter.is_none() {
+            if candidates.is_empty() && rejected.is_empty() {
                 let adapters = compute_compatibility_list(&instance, backends, &surface);
                 log::warn!(
-                    "Your webgpu preferred adapter '{}' was either not \
-                     found or is not compatible with your display. Available:\n{}",
-                    preference.to_string(),
+                    "None of your webgpu preferred adapters were found. Available:\n{}",
                     adapters.join("\n")
                 );
             }
+
+            for (rank, adapter) in candidates {
+                let info = adapter_info_to_gpu_info(adapter.get_info());
+                match Self::open_adapter(adapter, &surface, dimensions).await {
+                    Ok(adapter) => {
+                        let reason = format!(
+                            "matched webgpu_preferred_adapter entry {} ({})",
+                            rank + 1,
+                            preferences.0[rank]
+                        );
+                        opened.replace((adapter, reason));
+                        break;
+                    }
+                    Err(err) => {
+                        log::warn!("Unable to use {}: {err:#}", info.to_string());
+                        rejected.push(format!("{}: {err:#}", info.to_string()));
+                    }
+                }
+            }
         }
 
-        if adapter.is_none() {
-            adapter = Some(
-                instance
+        let (opened, reason) = match opened {
+            Some(opened) => opened,
+            None => {
+                let adapter = instance
                     .request_adapter(&wgpu::RequestAdapterOptions {
                         power_preference: match config.webgpu_power_preference {
                             WebGpuPowerPreference::HighPerformance => {
@@ -300,90 +359,83 @@ impl WebGpuState {
                         compatible_surface: Some(&surface),
                         force_fallback_adapter: config.webgpu_force_fallback_adapter,
                     })
-                    .await?,
-            );
-        }
-
-        let adapter = adapter.ok_or_else(|| {
-            let adapters = compute_compatibility_list(&instance, backends, &surface);
-            anyhow!(
-                "no compatible adapter found. Available:\n{}",
-                adapters.join("\n")
-            )
-        })?;
-
-        let adapter_info = adapter.get_info();
-        log::trace!("Using adapter: {adapter_info:?}");
-        let caps = surface.get_capabilities(&adapter);
-        log::trace!("caps: {caps:?}");
-        let downlevel_caps = adapter.get_downlevel_capabilities();
-        log::trace!("downlevel_caps: {downlevel_caps:?}");
-
-        let (device, queue) = adapter
-            .request_device(&wgpu::DeviceDescriptor {
-                required_features: wgpu::Features::empty(),
-                // WebGL doesn't support all of wgpu's features, so if
-                // we're building for the web we'll have to disable some.
-                required_limits: if cfg!(target_arch = "wasm32") {
-                    wgpu::Limits::downlevel_webgl2_defaults()
-                } else {
-                    wgpu::Limits::downlevel_defaults()
+                    .await
+                    .map_err(|err| {
+                        let adapters = compute_compatibility_list(&instance, backends, &surface);
+                        anyhow!(
+                            "no compatible adapter found ({err:#}). Available:\n{}",
+                            adapters.join("\n")
+                        )
+                    })?;
+                let opened = Self::open_adapter(adapter, &surface, dimensions)
+                    .await
+                    .map_err(|err| {
+                        if rejected.is_empty() {
+                            err
+                        } else {
+                            err.context(format!("also tried:\n{}", rejected.join("\n")))
+                        }
+                    })?;
+                let mut reason = format!(
+                    "selected by webgpu_power_preference={:?}",
+                    config.webgpu_power_preference
+                );
+                if config.webgpu_force_fallback_adapter {
+                    reason.push_str(" with webgpu_force_fallback_adapter");
                 }
-                .using_resolution(adapter.limits()),
-                label: None,
-                memory_hints: Default::default(),
-                trace: wgpu::Trace::Off,
-            })
-            .await?;
+                if !preferences.is_empty() {
+                    reason = format!("no webgpu_preferred_adapter entry was usable; {reason}");
+                }
+                (opened, reason)
+            }
+        };
 
+        let OpenedAdapter {
+            adapter_info,
+            caps,
+            downlevel_caps,
+            device,
+            queue,
+            config,
+        } = opened;
+        log::trace!("Using adapter: {adapter_info:?}");
         let queue = Arc::new(queue);
-
-        // Explicitly request an SRGB format, if available
-        let pref_format_srgb = caps.formats[0].add_srgb_suffix();
-        let format = if caps.formats.contains(&pref_format_srgb) {
-            pref_format_srgb
-        } else {
-            caps.formats[0]
+        let selection = GpuSelection {
+            adapter: format!(
+                "WebGPU: {}",
+                adapter_info_to_gpu_info(adapter_info.clone()).to_string()
+            ),
+            reason,
+            rejected,
         };
 
-        // Need to check that this is supported, as trying to set
-        // view_formats without it will cause surface.configure
-        // to panic
-        // <https://github.com/wezterm/wezterm/issues/3565>
-        let view_formats = if downlevel_caps
-            .flags
-            .contains(wgpu::DownlevelFlags::SURFACE_VIEW_FORMATS)
+        let hdr_format = caps
+            .formats
+            .iter()
+            .copied()
+            .find(|&f| f == wgpu::TextureFormat::Rgba16Float);
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("../shader.wgsl"));
+        let subpixel_shader = if device
+            .features()
+            .contains(wgpu::Features::DUAL_SOURCE_BLENDING)
         {
-            vec![format.add_srgb_suffix(), format.remove_srgb_suffix()]
+            Some(
+                device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                    label: Some("subpixel shader"),
+                    source: wgpu::ShaderSource::Wgsl(
+                        format!(
+                            "enable dual_source_blending;\n{}\n{}",
+                            include_str!("../shader.wgsl"),
+                            include_str!("../shader-subpixel.wgsl")
+                        )
+                        .into(),
+                    ),
+                }),
+            )
         } else {
-            vec![]
-        };
-
-        let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format,
-            width: dimensions.pixel_width as u32,
-            height: dimensions.pixel_height as u32,
-            present_mode: wgpu::PresentMode::Fifo,
-            alpha_mode: if caps
-                .alpha_modes
-                .contains(&wgpu::CompositeAlphaMode::PostMultiplied)
-            {
-                wgpu::CompositeAlphaMode::PostMultiplied
-            } else if caps
-                .alpha_modes
-                .contains(&wgpu::CompositeAlphaMode::PreMultiplied)
-            {
-                wgpu::CompositeAlphaMode::PreMultiplied
-            } else {
-                wgpu::CompositeAlphaMode::Auto
-            },
-            view_formats,
-            desired_maximum_frame_latency: 2,
+            None
         };
-        surface.configure(&device, &config);
-
-        let shader = device.create_shader_module(wgpu::include_wgsl!("../shader.wgsl"));
 
         let shader_uniform_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
@@ -442,6 +494,37 @@ impl WebGpuState {
                 label: Some("texture bind group layout"),
             });
 
+        let color_lut_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D3,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+                label: Some("color lut bind group layout"),
+            });
+        let color_lut_bind_group = Self::create_color_lut_bind_group(
+            &device,
+            &queue,
+            &color_lut_bind_group_layout,
+            &texture_linear_sampler,
+            1,
+            &[0xff; 4],
+        );
+
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Render Pipeline Layout"),
@@ -449,25 +532,267 @@ impl WebGpuState {
                     &shader_uniform_bind_group_layout,
                     &texture_bind_group_layout,
                     &texture_bind_group_layout,
+                    &color_lut_bind_group_layout,
                 ],
                 push_constant_ranges: &[],
             });
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        let render_pipeline =
+            Self::create_render_pipeline(&device, &render_pipeline_layout, &shader, config.format);
+        let subpixel_pipeline = subpixel_shader.as_ref().map(|shader| {
+            Self::create_subpixel_pipeline(&device, &render_pipeline_layout, shader, config.format)
+        });
+
+        Ok(Self {
+            adapter_info,
+            downlevel_caps,
+            surface,
+            device,
+            queue,
+            sdr_format: config.format,
+            sdr_view_formats: config.view_formats.clone(),
+            hdr_format,
+            hdr: Cell::new(false),
+            config: RefCell::new(config),
+            dimensions: RefCell::new(dimensions),
+            render_pipeline: RefCell::new(render_pipeline),
+            subpixel_pipeline: RefCell::new(subpixel_pipeline),
+            shader,
+            subpixel_shader,
+            render_pipeline_layout,
+            handle,
+            shader_uniform_bind_group_layout,
+            texture_bind_group_layout,
+            color_lut_bind_group_layout,
+            color_lut_bind_group: RefCell::new(color_lut_bind_group),
+            has_color_lut: Cell::new(false),
+            texture_nearest_sampler,
+            texture_linear_sampler,
+            selection,
+        })
+    }
+
+    /// Creates a device for `adapter` and configures the surface to
+    /// present with it, reporting validation errors rather than
+    /// letting them panic, so that the next adapter can be tried
+    async fn open_adapter(
+        adapter: wgpu::Adapter,
+        surface: &wgpu::Surface<'static>,
+        dimensions: Dimensions,
+    ) -> anyhow::Result<OpenedAdapter> {
+        let adapter_info = adapter.get_info();
+        let caps = surface.get_capabilities(&adapter);
+        log::trace!("caps: {caps:?}");
+        let downlevel_caps = adapter.get_downlevel_capabilities();
+        log::trace!("downlevel_caps: {downlevel_caps:?}");
+        if caps.formats.is_empty() {
+            anyhow::bail!("the surface has no formats that can be used with this adapter");
+        }
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor {
+                // Dual source blending is needed for subpixel
+                // antialiasing, but we can manage without it
+                required_features: adapter.features() & wgpu::Features::DUAL_SOURCE_BLENDING,
+                // WebGL doesn't support all of wgpu's features, so if
+                // we're building for the web we'll have to disable some.
+                required_limits: if cfg!(target_arch = "wasm32") {
+                    wgpu::Limits::downlevel_webgl2_defaults()
+                } else {
+                    wgpu::Limits::downlevel_defaults()
+                }
+                .using_resolution(adapter.limits()),
+                label: None,
+                memory_hints: Default::default(),
+                trace: wgpu::Trace::Off,
+            })
+            .await?;
+
+        // Explicitly request an SRGB format, if available
+        let pref_format_srgb = caps.formats[0].add_srgb_suffix();
+        let format = if caps.formats.contains(&pref_format_srgb) {
+            pref_format_srgb
+        } else {
+            caps.formats[0]
+        };
+
+        // Need to check that this is supported, as trying to set
+        // view_formats without it will cause surface.configure
+        // to panic
+        // <https://github.com/wezterm/wezterm/issues/3565>
+        let view_formats = if downlevel_caps
+            .flags
+            .contains(wgpu::DownlevelFlags::SURFACE_VIEW_FORMATS)
+        {
+            vec![format.add_srgb_suffix(), format.remove_srgb_suffix()]
+        } else {
+            vec![]
+        };
+
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format,
+            width: dimensions.pixel_width as u32,
+            height: dimensions.pixel_height as u32,
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: if caps
+                .alpha_modes
+                .contains(&wgpu::CompositeAlphaMode::PostMultiplied)
+            {
+                wgpu::CompositeAlphaMode::PostMultiplied
+            } else if caps
+                .alpha_modes
+                .contains(&wgpu::CompositeAlphaMode::PreMultiplied)
+            {
+                wgpu::CompositeAlphaMode::PreMultiplied
+            } else {
+                wgpu::CompositeAlphaMode::Auto
+            },
+            view_formats,
+            desired_maximum_frame_latency: 2,
+        };
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        surface.configure(&device, &config);
+        if let Some(err) = device.pop_error_scope().await {
+            anyhow::bail!("failed to configure the surface: {err}");
+        }
+
+        Ok(OpenedAdapter {
+            adapter_info,
+            caps,
+            downlevel_caps,
+            device,
+            queue,
+            config,
+        })
+    }
+
+    fn create_color_lut_bind_group(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        size: u32,
+        data: &[u8],
+    ) -> wgpu::BindGroup {
+        let texture = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: Some("color lut"),
+                size: wgpu::Extent3d {
+                    width: size,
+                    height: size,
+                    depth_or_array_layers: size,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D3,
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            data,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+            label: Some("color lut bind group"),
+        })
+    }
+
+    /// Sets the lookup table used to convert our output to the
+    /// color space of the display, or removes it if `lut` is None
+    pub fn set_color_lut(&self, lut: Option<&ColorLut>) {
+        let (size, data) = match lut {
+            Some(lut) => (LUT_SIZE, lut.data.as_slice()),
+            None => (1, &[0xff; 4][..]),
+        };
+        *self.color_lut_bind_group.borrow_mut() = Self::create_color_lut_bind_group(
+            &self.device,
+            &self.queue,
+            &self.color_lut_bind_group_layout,
+            &self.texture_linear_sampler,
+            size,
+            data,
+        );
+        self.has_color_lut.set(lut.is_some());
+    }
+
+    fn create_render_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+        format: wgpu::TextureFormat,
+    ) -> wgpu::RenderPipeline {
+        Self::create_pipeline(
+            device,
+            layout,
+            shader,
+            "fs_main",
+            wgpu::BlendState::ALPHA_BLENDING,
+            format,
+        )
+    }
+
+    fn create_subpixel_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+        format: wgpu::TextureFormat,
+    ) -> wgpu::RenderPipeline {
+        // The second output of the shader holds the coverage of
+        // each channel, which is used in place of the alpha
+        let component = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::Src1,
+            dst_factor: wgpu::BlendFactor::OneMinusSrc1,
+            operation: wgpu::BlendOperation::Add,
+        };
+        Self::create_pipeline(
+            device,
+            layout,
+            shader,
+            "fs_subpixel",
+            wgpu::BlendState {
+                color: component,
+                alpha: component,
+            },
+            format,
+        )
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+        fragment_entry_point: &str,
+        blend: wgpu::BlendState,
+        format: wgpu::TextureFormat,
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
+            layout: Some(layout),
             vertex: wgpu::VertexState {
-                module: &shader,
+                module: shader,
                 entry_point: Some("vs_main"),
                 buffers: &[Vertex::desc()],
                 compilation_options: wgpu::PipelineCompilationOptions::default(),
             },
             fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: Some("fs_main"),
+                module: shader,
+                entry_point: Some(fragment_entry_point),
                 targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    format,
+                    blend: Some(blend),
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
                 compilation_options: wgpu::PipelineCompilationOptions::default(),
@@ -490,23 +815,59 @@ impl WebGpuState {
             },
             multiview: None,
             cache: None,
+        })
+    }
+
+    /// Switches between rendering sRGB and rendering scRGB into a
+    /// half-float surface.  Returns false if hdr was requested but
+    /// the surface doesn't support a suitable format.
+    pub fn set_hdr(&self, hdr: bool) -> bool {
+        let (format, view_formats) = if hdr {
+            match self.hdr_format {
+                Some(format) => (format, vec![]),
+                None => return false,
+            }
+        } else {
+            (self.sdr_format, self.sdr_view_formats.clone())
+        };
+        if self.hdr.get() == hdr {
+            return true;
+        }
+        self.hdr.set(hdr);
+
+        let mut config = self.config.borrow_mut();
+        config.format = format;
+        config.view_formats = view_formats;
+        if config.width > 0 && config.height > 0 {
+            self.surface.configure(&self.device, &config);
+        }
+        *self.render_pipeline.borrow_mut() = Self::create_render_pipeline(
+            &self.device,
+            &self.render_pipeline_layout,
+            &self.shader,
+            format,
+        );
+        *self.subpixel_pipeline.borrow_mut() = self.subpixel_shader.as_ref().map(|shader| {
+            Self::create_subpixel_pipeline(
+                &self.device,
+                &self.render_pipeline_layout,
+                shader,
+                format,
+            )
         });
+        true
+    }
 
-        Ok(Self {
-            adapter_info,
-            downlevel_caps,
-            surface,
-            device,
-            queue,
-            config: RefCell::new(config),
-            dimensions: RefCell::new(dimensions),
-            render_pipeline,
-            handle,
-            shader_uniform_bind_group_layout,
-            texture_bind_group_layout,
-            texture_nearest_sampler,
-            texture_linear_sampler,
-        })
+    /// The factor that maps our sRGB colors into the output
+    pub fn sdr_scale(&self) -> f32 {
+        if self.hdr.get() {
+            // scRGB treats 1.0 as 80 nits, whereas SDR content is
+            // expected to have its white at the 203 nit reference
+            // white recommended by ITU-R BT.2408
+            203. / 80.
+        } else {
+            1.0
+        }
     }
 
     pub fn create_uniform(&self, uniform: ShaderUniform) -> wgpu::BindGroup {
@@ -12,7 +12,7 @@ use std::borrow::Cow;
 /// using a name like "foo.bar".
 /// A companion trait `UniformStruct` is used to aid in defining structs
 /// that can be passed as uniforms.
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct UniformBuilder<'a> {
     entries: Vec<(Cow<'a, str>, UniformValue<'a>)>,
 }
@@ -45,6 +45,16 @@ impl<'a> UniformBuilder<'a> {
             v.as_uniform_value(),
         ));
     }
+
+    /// Add an element of an array uniform to the shader.
+    /// glsl expects to bind element `idx` of array `foo` using
+    /// a name like "foo[idx]"
+    pub fn add_array_element<V: AsUniformValue>(&mut self, array_name: &str, idx: usize, v: &'a V) {
+        self.entries.push((
+            Cow::Owned(format!("{array_name}[{idx}]")),
+            v.as_uniform_value(),
+        ));
+    }
 }
 
 /// This is the glue that allows glium to bind the uniforms
@@ -15,6 +15,11 @@ pub struct RenderMetrics {
     pub descender_row: IntPixelLength,
     pub descender_plus_two: IntPixelLength,
     pub underline_height: IntPixelLength,
+    /// The space between the two lines of double underlines
+    /// and double undercurls
+    pub double_underline_gap: IntPixelLength,
+    /// The distance from the peak to the trough of curly underlines
+    pub curly_underline_height: IntPixelLength,
     pub strike_row: IntPixelLength,
     pub cell_size: Size,
 }
@@ -33,6 +38,8 @@ impl RenderMetrics {
         let descender_plus_two =
             (2 * underline_height + descender_row).min(cell_height as isize - underline_height);
         let strike_row = descender_row / 2;
+        let curly_underline_height =
+            default_curly_underline_height(cell_height as isize, descender_row, underline_height);
 
         Self {
             descender: metrics.descender,
@@ -41,6 +48,8 @@ impl RenderMetrics {
             strike_row,
             cell_size: Size::new(cell_width as isize, cell_height as isize),
             underline_height,
+            double_underline_gap: underline_height,
+            curly_underline_height,
         }
     }
 
@@ -57,6 +66,8 @@ impl RenderMetrics {
             descender_row: self.descender_row - adjust,
             descender_plus_two: self.descender_plus_two - adjust,
             underline_height: self.underline_height,
+            double_underline_gap: self.double_underline_gap,
+            curly_underline_height: self.curly_underline_height,
             strike_row: self.strike_row,
             cell_size: size,
         }
@@ -108,8 +119,36 @@ impl RenderMetrics {
 
         let descender_row = (cell_height as f64 + (metrics.descender.get() - underline_position)
             - line_height_y_adjust) as isize;
-        let descender_plus_two =
-            (2 * underline_height + descender_row).min(cell_height as isize - underline_height);
+
+        let double_underline_gap = match &config.double_underline_gap {
+            None => underline_height,
+            Some(d) => d
+                .evaluate_as_pixels(DimensionContext {
+                    dpi: fonts.get_dpi() as f32,
+                    pixel_max: underline_height as f32,
+                    pixel_cell: cell_height as f32,
+                })
+                .round()
+                .max(0.) as isize,
+        };
+
+        let default_curly_height =
+            default_curly_underline_height(cell_height as isize, descender_row, underline_height);
+        let curly_underline_height = match &config.curly_underline_height {
+            None => default_curly_height,
+            Some(d) => d
+                .evaluate_as_pixels(DimensionContext {
+                    dpi: fonts.get_dpi() as f32,
+                    pixel_max: default_curly_height as f32,
+                    pixel_cell: cell_height as f32,
+                })
+                .round()
+                .max(1.) as isize,
+        };
+
+        // This is the row of the second line of a double underline
+        let descender_plus_two = (descender_row + underline_height + double_underline_gap)
+            .min(cell_height as isize - underline_height);
         let strike_row = match &config.strikethrough_position {
             None => {
                 ((cell_height as f64 + (metrics.descender.get() - underline_position)) / 2.)
@@ -131,10 +170,23 @@ impl RenderMetrics {
             strike_row,
             cell_size: Size::new(cell_width as isize, cell_height as isize),
             underline_height,
+            double_underline_gap,
+            curly_underline_height,
         })
     }
 }
 
+/// The curly underline defaults to occupying half of the space between
+/// the underline position and the bottom of the cell, but is always
+/// tall enough for its wave to be distinguishable from a straight line.
+fn default_curly_underline_height(
+    cell_height: IntPixelLength,
+    descender_row: IntPixelLength,
+    underline_height: IntPixelLength,
+) -> IntPixelLength {
+    ((cell_height - descender_row) / 2).max(2 * underline_height)
+}
+
 pub struct UtilSprites {
     pub white_space: Sprite,
     pub filled_box: Sprite,
@@ -0,0 +1,155 @@
+//! Decodes video files so that they can be used as animated window
+//! backgrounds.  Decoding is delegated to `ffmpeg`, which must be
+//! installed and in the PATH, as there isn't a practical rust decoder
+//! for the codecs that are commonly found in webm and mp4 files.
+//! If it isn't installed, decoding fails with a `MissingTool` error.
+use anyhow::Context;
+use std::io::{ErrorKind, Read};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+
+/// Used when ffprobe doesn't know the frame rate
+const DEFAULT_FRAME_DURATION: Duration = Duration::from_nanos(1_000_000_000 / 30);
+
+/// Returns true if `magic`, the first 12 bytes of a file, indicate
+/// that it is a video, rather than an image
+pub fn is_video(magic: &[u8]) -> bool {
+    // Matroska, and WebM which is based on it, start with an EBML header
+    if magic.starts_with(&[0x1a, 0x45, 0xdf, 0xa3]) {
+        return true;
+    }
+    // MP4 and QuickTime files start with an ftyp box, but so do
+    // AVIF and HEIF images, which we leave to the image crate
+    match (magic.get(4..8), magic.get(8..12)) {
+        (Some(b"ftyp"), Some(brand)) => !matches!(
+            brand,
+            b"avif" | b"avis" | b"heic" | b"heix" | b"mif1" | b"msf1"
+        ),
+        _ => false,
+    }
+}
+
+/// Returned when one of the ffmpeg tools is not installed
+#[derive(Debug, thiserror::Error)]
+#[error("{0} was not found in the PATH; ffmpeg must be installed to use video backgrounds")]
+pub struct MissingTool(&'static str);
+
+/// Creates the command to run one of the ffmpeg tools, without
+/// opening a console window for it on Windows
+fn tool_command(program: &str) -> Command {
+    let mut cmd = Command::new(program);
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        cmd.creation_flags(winapi::um::winbase::CREATE_NO_WINDOW);
+    }
+    cmd
+}
+
+/// Produces the error for a failure to run `program`
+fn spawn_error(program: &'static str, err: std::io::Error) -> anyhow::Error {
+    if err.kind() == ErrorKind::NotFound {
+        MissingTool(program).into()
+    } else {
+        anyhow::Error::new(err).context(format!("running {program}"))
+    }
+}
+
+struct VideoInfo {
+    width: usize,
+    height: usize,
+    frame_duration: Duration,
+}
+
+fn probe(path: &Path) -> anyhow::Result<VideoInfo> {
+    let output = tool_command("ffprobe")
+        .args(["-v", "error", "-select_streams", "v:0"])
+        .args(["-show_entries", "stream=width,height,avg_frame_rate"])
+        .args(["-of", "csv=p=0"])
+        .arg(path)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|err| spawn_error("ffprobe", err))?;
+    anyhow::ensure!(
+        output.status.success(),
+        "ffprobe failed: {}",
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+
+    // The output looks like `1920,1080,30000/1001`
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let fields: Vec<&str> = stdout.trim().split(',').collect();
+    let (width, height, rate) = match fields.as_slice() {
+        [width, height, rate, ..] => (width, height, rate),
+        _ => anyhow::bail!("unexpected ffprobe output {stdout:?}"),
+    };
+    let width: usize = width.parse().context("parsing video width")?;
+    let height: usize = height.parse().context("parsing video height")?;
+    anyhow::ensure!(width > 0 && height > 0, "video has no frames");
+
+    let (numer, denom) = rate.split_once('/').unwrap_or((rate, "1"));
+    let frame_duration = match (numer.parse::<f64>(), denom.parse::<f64>()) {
+        (Ok(numer), Ok(denom)) if numer > 0. && denom > 0. => {
+            Duration::from_secs_f64(denom / numer)
+        }
+        _ => DEFAULT_FRAME_DURATION,
+    };
+
+    Ok(VideoInfo {
+        width,
+        height,
+        frame_duration,
+    })
+}
+
+/// Kills ffmpeg when we stop reading from it
+struct ChildGuard(Child);
+
+impl Drop for ChildGuard {
+    fn drop(&mut self) {
+        self.0.kill().ok();
+        self.0.wait().ok();
+    }
+}
+
+/// Decodes the video at `path` as RGBA, calling `on_frame` with the
+/// data, width, height and duration of each frame.  ffmpeg starts the
+/// video over when it reaches the end, so this only returns if
+/// decoding fails, or if `on_frame` returns an error.
+pub fn decode_frames<F>(path: &Path, mut on_frame: F) -> anyhow::Result<()>
+where
+    F: FnMut(Vec<u8>, usize, usize, Duration) -> anyhow::Result<()>,
+{
+    let info = probe(path)?;
+    let frame_size = info.width * info.height * 4;
+
+    let child = tool_command("ffmpeg")
+        .args(["-v", "error", "-nostdin", "-stream_loop", "-1", "-i"])
+        .arg(path)
+        .args(["-an", "-f", "rawvideo", "-pix_fmt", "rgba", "-"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|err| spawn_error("ffmpeg", err))?;
+    let mut child = ChildGuard(child);
+    let mut stdout = child.0.stdout.take().context("ffmpeg stdout")?;
+
+    let mut num_frames = 0;
+    loop {
+        let mut data = vec![0u8; frame_size];
+        match stdout.read_exact(&mut data) {
+            Ok(()) => {}
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err).context("reading frame from ffmpeg"),
+        }
+        num_frames += 1;
+        on_frame(data, info.width, info.height, info.frame_duration)?;
+    }
+
+    // Since ffmpeg loops the video, it only stops if it fails
+    anyhow::ensure!(num_frames > 0, "ffmpeg did not produce any frames");
+    let status = child.0.wait().context("waiting for ffmpeg")?;
+    anyhow::bail!("ffmpeg stopped after {num_frames} frames: {status}")
+}
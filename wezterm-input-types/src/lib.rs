@@ -1253,7 +1253,7 @@ bitflags! {
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FromDynamic, ToDynamic)]
 pub enum MousePress {
     Left,
     Right,
@@ -1709,12 +1709,30 @@ impl KeyEvent {
     }
 
     pub fn encode_kitty(&self, flags: KittyKeyboardFlags) -> String {
+        self.encode_kitty_event(flags, false)
+    }
+
+    /// Like encode_kitty, but `is_repeat` says that this key down
+    /// is the key repeating while held, which is reported as a
+    /// repeat event when REPORT_EVENT_TYPES is enabled
+    pub fn encode_kitty_event(&self, flags: KittyKeyboardFlags, is_repeat: bool) -> String {
         use KeyCode::*;
 
         if !flags.contains(KittyKeyboardFlags::REPORT_EVENT_TYPES) && !self.key_is_down {
             return String::new();
         }
 
+        // Enter, Tab and Backspace keep their legacy encoding so that
+        // the shell remains usable if a program exits without popping
+        // its flags; their releases are only reported together with
+        // all of the other keys
+        if !self.key_is_down
+            && !flags.contains(KittyKeyboardFlags::REPORT_ALL_KEYS_AS_ESCAPE_CODES)
+            && matches!(self.key, Char('\r') | Char('\t') | Char('\x08'))
+        {
+            return String::new();
+        }
+
         if self.modifiers.is_empty()
             && !flags.contains(KittyKeyboardFlags::REPORT_ALL_KEYS_AS_ESCAPE_CODES)
             && self.key_is_down
@@ -1758,12 +1776,15 @@ impl KeyEvent {
         }
         modifiers += 1;
 
-        let event_type =
-            if flags.contains(KittyKeyboardFlags::REPORT_EVENT_TYPES) && !self.key_is_down {
-                ":3"
-            } else {
-                ""
-            };
+        let event_type = if !flags.contains(KittyKeyboardFlags::REPORT_EVENT_TYPES) {
+            ""
+        } else if !self.key_is_down {
+            ":3"
+        } else if is_repeat {
+            ":2"
+        } else {
+            ""
+        };
 
         let is_legacy_key = match &self.key {
             Char(c) => c.is_ascii_alphanumeric() || c.is_ascii_punctuation(),
@@ -1773,6 +1794,8 @@ impl KeyEvent {
         let generated_text =
             if self.key_is_down && flags.contains(KittyKeyboardFlags::REPORT_ASSOCIATED_TEXT) {
                 match &self.key {
+                    // Enter, Tab, Backspace and friends don't produce text
+                    Char(c) if c.is_control() => String::new(),
                     Char(c) => format!(";{}", *c as u32),
                     KeyCode::Numpad(n) => format!(";{}", '0' as u32 + *n as u32),
                     Composed(s) => {
@@ -2336,6 +2359,47 @@ mod test {
         );
     }
 
+    #[test]
+    fn encode_kitty_repeat_and_release() {
+        let flags =
+            KittyKeyboardFlags::DISAMBIGUATE_ESCAPE_CODES | KittyKeyboardFlags::REPORT_EVENT_TYPES;
+        let event = |key, modifiers, key_is_down| KeyEvent {
+            key,
+            modifiers,
+            leds: KeyboardLedStatus::empty(),
+            repeat_count: 1,
+            key_is_down,
+            raw: None,
+            #[cfg(windows)]
+            win32_uni_char: None,
+        };
+
+        assert_eq!(
+            event(KeyCode::Char('o'), Modifiers::CTRL, true).encode_kitty_event(flags, true),
+            "\x1b[111;5:2u".to_string()
+        );
+        assert_eq!(
+            event(KeyCode::LeftArrow, Modifiers::NONE, true).encode_kitty_event(flags, true),
+            "\x1b[1;1:2D".to_string()
+        );
+        // Repeated text is still sent as text
+        assert_eq!(
+            event(KeyCode::Char('o'), Modifiers::NONE, true).encode_kitty_event(flags, true),
+            "o".to_string()
+        );
+
+        // The release of Enter is only reported with all of the other keys
+        assert_eq!(
+            event(KeyCode::Char('\r'), Modifiers::NONE, false).encode_kitty(flags),
+            "".to_string()
+        );
+        assert_eq!(
+            event(KeyCode::Char('\r'), Modifiers::NONE, false)
+                .encode_kitty(flags | KittyKeyboardFlags::REPORT_ALL_KEYS_AS_ESCAPE_CODES),
+            "\x1b[13;1:3u".to_string()
+        );
+    }
+
     #[test]
     fn encode_issue_3473() {
         let flags = KittyKeyboardFlags::DISAMBIGUATE_ESCAPE_CODES
@@ -127,6 +127,8 @@ where
                 handler.schedule_pane_push(pane_id);
             }
             Ok(Item::Notif(MuxNotification::SaveToDownloads { .. })) => {}
+            Ok(Item::Notif(MuxNotification::CaptureImage { .. })) => {}
+            Ok(Item::Notif(MuxNotification::ActivateWindow { .. })) => {}
             Ok(Item::Notif(MuxNotification::AssignClipboard {
                 pane_id,
                 selection,
@@ -203,6 +205,11 @@ where
                 stream.flush().await.context("flushing PDU to client")?;
             }
             Ok(Item::Notif(MuxNotification::ActiveWorkspaceChanged(_))) => {}
+            // Clipboard reads are not relayed to clients; they are answered
+            // by whichever process hosts the mux: the gui reads its own
+            // clipboard, while the headless server replies with an empty
+            // selection
+            Ok(Item::Notif(MuxNotification::QueryClipboard { .. })) => {}
             Ok(Item::Notif(MuxNotification::Empty)) => {}
             Err(err) => {
                 log::error!("process_async Err {}", err);
@@ -725,6 +725,13 @@ impl SessionHandler {
                 .detach();
             }
 
+            Pdu::CaptureImage(request) => {
+                spawn_into_main_thread(async move {
+                    schedule_capture_image(request, send_response);
+                })
+                .detach();
+            }
+
             Pdu::MovePaneToNewTab(request) => {
                 let client_id = self.client_id.clone();
                 spawn_into_main_thread(async move {
@@ -924,9 +931,13 @@ impl SessionHandler {
                                 .get_pane(pane_id)
                                 .ok_or_else(|| anyhow!("no such pane {}", pane_id))?;
 
+                            // The config may be shared with other panes, so
+                            // replace it with a copy rather than changing it
                             match pane.get_config() {
                                 Some(config) => match config.downcast_ref::<TermConfig>() {
-                                    Some(tc) => tc.set_client_palette(palette),
+                                    Some(tc) => {
+                                        pane.set_config(Arc::new(tc.with_client_palette(palette)))
+                                    }
                                     None => {
                                         log::error!(
                                             "pane {pane_id} doesn't \
@@ -1007,6 +1018,7 @@ impl SessionHandler {
             | Pdu::PaneFocused { .. }
             | Pdu::TabResized { .. }
             | Pdu::GetImageCellResponse { .. }
+            | Pdu::CaptureImageResponse { .. }
             | Pdu::MovePaneToNewTabResponse { .. }
             | Pdu::TabAddedToWindow { .. }
             | Pdu::GetPaneRenderableDimensionsResponse { .. }
@@ -1086,6 +1098,13 @@ async fn domain_spawn_v2(spawn: SpawnV2, client_id: Option<Arc<ClientId>>) -> an
         )
         .await?;
 
+    if let Some(activation_token) = spawn.activation_token {
+        mux.notify(MuxNotification::ActivateWindow {
+            window_id,
+            activation_token,
+        });
+    }
+
     Ok::<Pdu, anyhow::Error>(Pdu::SpawnResponse(SpawnResponse {
         pane_id: pane.pane_id(),
         tab_id: tab.tab_id(),
@@ -1094,6 +1113,18 @@ async fn domain_spawn_v2(spawn: SpawnV2, client_id: Option<Arc<ClientId>>) -> an
     }))
 }
 
+fn schedule_capture_image<SND>(request: CaptureImage, send_response: SND)
+where
+    SND: Fn(anyhow::Result<Pdu>) + 'static,
+{
+    promise::spawn::spawn(async move { send_response(capture_image(request).await) }).detach();
+}
+
+async fn capture_image(request: CaptureImage) -> anyhow::Result<Pdu> {
+    let png = Mux::capture_image(request.pane_id, request.whole_window).await?;
+    Ok::<Pdu, anyhow::Error>(Pdu::CaptureImageResponse(CaptureImageResponse { png }))
+}
+
 fn schedule_move_pane<SND>(
     request: MovePaneToNewTab,
     send_response: SND,
@@ -2,7 +2,7 @@ use clap::*;
 use config::configuration;
 use mux::activity::Activity;
 use mux::domain::{Domain, LocalDomain};
-use mux::Mux;
+use mux::{Mux, MuxNotification};
 use portable_pty::cmdbuilder::CommandBuilder;
 use std::ffi::OsString;
 use std::process::Command;
@@ -273,6 +273,24 @@ async fn async_run(cmd: Option<CommandBuilder>) -> anyhow::Result<()> {
         true
     });
 
+    // There is no gui attached to the headless server to read a
+    // clipboard from, so answer OSC 52 queries with an empty selection
+    // rather than leaving the application waiting for a reply.
+    // The query is raised while the terminal is locked, so defer the
+    // response to the main thread.
+    mux.subscribe(|n| {
+        if let MuxNotification::QueryClipboard { pane_id, selection } = n {
+            promise::spawn::spawn_into_main_thread(async move {
+                let mux = Mux::get();
+                if let Some(pane) = mux.get_pane(pane_id) {
+                    pane.send_clipboard_contents(selection, "");
+                }
+            })
+            .detach();
+        }
+        true
+    });
+
     let domain = mux.default_domain();
 
     {
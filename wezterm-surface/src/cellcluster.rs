@@ -155,6 +155,11 @@ impl CellCluster {
         if let Some(hint) = bidi_hint {
             let mut resolved_clusters = vec![];
 
+            // The line is the paragraph; detect its direction once,
+            // rather than separately for each of its clusters
+            let hint = hint
+                .resolve_for_paragraph(clusters.iter().flat_map(|cluster| cluster.text.chars()));
+
             let mut context = BidiContext::new();
             for cluster in clusters {
                 Self::resolve_bidi(&mut context, hint, cluster, &mut resolved_clusters);
@@ -10,16 +10,94 @@ use fancy_regex::{Captures, Regex};
 #[cfg(feature = "use_serde")]
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use wezterm_dynamic::{FromDynamic, FromDynamicOptions, ToDynamic, Value};
+use wezterm_escape_parser::csi::Underline;
 
 extern crate alloc;
 use crate::alloc::borrow::ToOwned;
 use crate::alloc::string::ToString;
 use alloc::format;
 use alloc::string::String;
+use alloc::vec;
 use alloc::vec::Vec;
 
 pub use wezterm_escape_parser::hyperlink::Hyperlink;
 
+/// The params of an implicit Hyperlink that record how the Rule
+/// that produced it wants it to be treated
+const ACTION_PARAM: &str = "wezterm.action";
+const EVENT_PARAM: &str = "wezterm.event";
+const CAPTURE_PARAM_PREFIX: &str = "wezterm.capture.";
+const HOVER_UNDERLINE_PARAM: &str = "wezterm.hover_underline";
+
+/// What to do when the text matched by a Rule is clicked
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Default, FromDynamic, ToDynamic)]
+pub enum RuleAction {
+    /// Open the expanded `format` with the system URL handler
+    #[default]
+    OpenUrl,
+    /// Open the file named by the expanded `format` in $EDITOR.
+    /// A trailing `:line` or `:line:column` selects the position
+    /// in the file.
+    OpenInEditor,
+    /// Emit the named event, passing the expanded `format` and
+    /// the text of each of the capture groups
+    EmitEvent(String),
+}
+
+impl RuleAction {
+    /// Returns the action that should be taken when `link` is clicked
+    pub fn for_link(link: &Hyperlink) -> Self {
+        if !link.is_implicit() {
+            return Self::OpenUrl;
+        }
+        match link.params().get(ACTION_PARAM).map(|s| s.as_str()) {
+            Some("OpenInEditor") => Self::OpenInEditor,
+            Some("EmitEvent") => match link.params().get(EVENT_PARAM) {
+                Some(name) => Self::EmitEvent(name.to_string()),
+                None => Self::OpenUrl,
+            },
+            _ => Self::OpenUrl,
+        }
+    }
+}
+
+/// Returns the text of the capture groups of the rule that produced
+/// `link`, indexed by capture number.  Groups that didn't participate
+/// in the match are represented by empty strings.
+pub fn rule_captures(link: &Hyperlink) -> Vec<String> {
+    let mut captures = vec![];
+    for (key, value) in link.params() {
+        if let Some(idx) = key
+            .strip_prefix(CAPTURE_PARAM_PREFIX)
+            .and_then(|n| n.parse::<usize>().ok())
+        {
+            if captures.len() <= idx {
+                captures.resize(idx + 1, String::new());
+            }
+            captures[idx] = value.to_string();
+        }
+    }
+    captures
+}
+
+/// Returns the underline that the rule that produced `link` wants to
+/// be used while the mouse is hovering over it, if it specified one
+pub fn rule_hover_underline(link: &Hyperlink) -> Option<Underline> {
+    if !link.is_implicit() {
+        return None;
+    }
+    match link.params().get(HOVER_UNDERLINE_PARAM)?.as_str() {
+        "None" => Some(Underline::None),
+        "Single" => Some(Underline::Single),
+        "Double" => Some(Underline::Double),
+        "Curly" => Some(Underline::Curly),
+        "Dotted" => Some(Underline::Dotted),
+        "Dashed" => Some(Underline::Dashed),
+        _ => None,
+    }
+}
+
 /// In addition to handling explicit escape sequences to enable
 /// hyperlinks, we also support defining rules that match text
 /// from screen lines and generate implicit hyperlinks.  This
@@ -59,6 +137,16 @@ pub struct Rule {
     /// Which capture to highlight
     #[dynamic(default)]
     pub highlight: usize,
+
+    /// What to do when the matched text is clicked
+    #[dynamic(default)]
+    pub action: RuleAction,
+
+    /// How to underline the matched text while the mouse is hovering
+    /// over it.  When not set, it is styled in the same way as
+    /// explicit hyperlinks.
+    #[dynamic(default)]
+    pub hover_underline: Option<Underline>,
 }
 
 struct RegexWrap(Regex);
@@ -143,6 +231,37 @@ impl<'t> Match<'t> {
         self.captures.get(self.rule.highlight)
     }
 
+    /// Produces the params that record the action, captures and styling
+    /// of the rule, so that they are available when the link is used
+    fn params(&self) -> Vec<(String, String)> {
+        let mut params = vec![];
+        match &self.rule.action {
+            RuleAction::OpenUrl => {}
+            RuleAction::OpenInEditor => {
+                params.push((ACTION_PARAM.to_string(), "OpenInEditor".to_string()));
+            }
+            RuleAction::EmitEvent(name) => {
+                params.push((ACTION_PARAM.to_string(), "EmitEvent".to_string()));
+                params.push((EVENT_PARAM.to_string(), name.to_string()));
+                for n in 0..self.captures.len() {
+                    if let Some(cap) = self.captures.get(n) {
+                        params.push((
+                            format!("{}{}", CAPTURE_PARAM_PREFIX, n),
+                            cap.as_str().to_string(),
+                        ));
+                    }
+                }
+            }
+        }
+        if let Some(underline) = self.rule.hover_underline {
+            params.push((
+                HOVER_UNDERLINE_PARAM.to_string(),
+                format!("{:?}", underline),
+            ));
+        }
+        params
+    }
+
     /// Expand replacements in the format string to yield the URL
     /// The replacement is as described on Rule::format.
     fn expand(&self) -> String {
@@ -179,6 +298,8 @@ impl Rule {
             regex: Regex::new(regex)?,
             format: format.to_owned(),
             highlight,
+            action: RuleAction::default(),
+            hover_underline: None,
         })
     }
 
@@ -205,7 +326,12 @@ impl Rule {
             .into_iter()
             .map(|m| {
                 let url = m.expand();
-                let link = Arc::new(Hyperlink::new_implicit(url));
+                let params = m.params();
+                let link = Arc::new(if params.is_empty() {
+                    Hyperlink::new_implicit(url)
+                } else {
+                    Hyperlink::new_implicit_with_params(url, params)
+                });
                 RuleMatch {
                     link,
                     range: m.range(),
@@ -250,6 +376,35 @@ mod test {
         );
     }
 
+    #[test]
+    fn rule_action() {
+        let mut rule = Rule::new(r"\b([\w/.-]+\.rs):(\d+)\b", "$1:$2").unwrap();
+        rule.action = RuleAction::EmitEvent("open-rust-file".to_string());
+        rule.hover_underline = Some(Underline::Dotted);
+
+        let matches = Rule::match_hyperlinks("error at src/lib.rs:42 here", &[rule]);
+        assert_eq!(matches.len(), 1);
+        let link = &matches[0].link;
+        assert_eq!(link.uri(), "src/lib.rs:42");
+        assert_eq!(
+            RuleAction::for_link(link),
+            RuleAction::EmitEvent("open-rust-file".to_string())
+        );
+        assert_eq!(
+            rule_captures(link),
+            vec![
+                "src/lib.rs:42".to_string(),
+                "src/lib.rs".to_string(),
+                "42".to_string()
+            ]
+        );
+        assert_eq!(rule_hover_underline(link), Some(Underline::Dotted));
+
+        let plain = Hyperlink::new_implicit("http://example.com");
+        assert_eq!(RuleAction::for_link(&plain), RuleAction::OpenUrl);
+        assert_eq!(rule_hover_underline(&plain), None);
+    }
+
     #[test]
     fn parse_with_parentheses() {
         fn assert_helper(test_uri: &str, expected_uri: &str, msg: &str) {
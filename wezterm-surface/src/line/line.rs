@@ -380,7 +380,7 @@ impl Line {
     /// before falling back to the specified direction.
     pub fn set_direction(&mut self, direction: Direction, auto_detect: bool, seqno: SequenceNo) {
         self.bits
-            .set(LineBits::RTL, direction == Direction::LeftToRight);
+            .set(LineBits::RTL, direction == Direction::RightToLeft);
         self.bits.set(LineBits::AUTO_DETECT_DIRECTION, auto_detect);
         self.update_last_change_seqno(seqno);
     }
@@ -421,6 +421,22 @@ impl Line {
         )
     }
 
+    /// Returns the base direction of the line.  When the direction
+    /// is auto-detected, this is taken from the first strong character
+    /// in the line, falling back to the direction specified for it.
+    pub fn resolved_direction(&self) -> Direction {
+        let (_enabled, hint) = self.bidi_info();
+        match hint {
+            ParagraphDirectionHint::LeftToRight | ParagraphDirectionHint::RightToLeft => {
+                hint.direction()
+            }
+            ParagraphDirectionHint::AutoLeftToRight | ParagraphDirectionHint::AutoRightToLeft => {
+                hint.resolve_for_paragraph(self.as_str().chars())
+                    .direction()
+            }
+        }
+    }
+
     fn invalidate_zones(&mut self) {
         self.zones.clear();
     }
@@ -0,0 +1,85 @@
+use anyhow::Context;
+use clap::{Parser, ValueEnum, ValueHint};
+use codec::CaptureImage;
+use mux::pane::PaneId;
+use std::io::Write;
+use std::path::PathBuf;
+use wezterm_client::client::Client;
+
+#[derive(Debug, Parser, Clone)]
+pub struct Capture {
+    /// Specify the target pane.
+    /// The default is to use the current pane based on the
+    /// environment variable WEZTERM_PANE.
+    #[arg(long)]
+    pane_id: Option<PaneId>,
+
+    /// Capture the whole gui window that contains the pane,
+    /// including the tab bar, rather than just the pane
+    #[arg(long)]
+    window: bool,
+
+    /// The format in which the image is written
+    #[arg(long, value_enum, default_value = "png")]
+    format: CaptureFormat,
+
+    /// The file to which the image will be written.
+    /// If omitted, the image is written to stdout.
+    #[arg(value_hint=ValueHint::FilePath)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum CaptureFormat {
+    Png,
+    Jpeg,
+    Bmp,
+}
+
+impl CaptureFormat {
+    /// Converts the PNG data produced by the gui to this format
+    fn encode(self, png: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+        let (format, image) = match self {
+            Self::Png => return Ok(png),
+            // JPEG has no alpha channel
+            Self::Jpeg => (
+                image::ImageFormat::Jpeg,
+                image::DynamicImage::ImageRgb8(decode_png(&png)?.to_rgb8()),
+            ),
+            Self::Bmp => (image::ImageFormat::Bmp, decode_png(&png)?),
+        };
+        let mut data = vec![];
+        image
+            .write_to(&mut std::io::Cursor::new(&mut data), format)
+            .with_context(|| format!("encoding capture as {format:?}"))?;
+        Ok(data)
+    }
+}
+
+fn decode_png(png: &[u8]) -> anyhow::Result<image::DynamicImage> {
+    image::load_from_memory_with_format(png, image::ImageFormat::Png).context("decoding capture")
+}
+
+impl Capture {
+    pub async fn run(self, client: Client) -> anyhow::Result<()> {
+        let pane_id = client.resolve_pane_id(self.pane_id).await?;
+        let response = client
+            .capture_image(CaptureImage {
+                pane_id,
+                whole_window: self.window,
+            })
+            .await?;
+        let data = self.format.encode(response.png)?;
+
+        match &self.output {
+            Some(path) => std::fs::write(path, &data)
+                .with_context(|| format!("writing {}", path.display()))?,
+            None => {
+                let mut stdout = std::io::stdout();
+                stdout.write_all(&data)?;
+                stdout.flush()?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -116,6 +116,13 @@ enum SubCommand {
     #[command(name = "show-keys", about = "Show key assignments")]
     ShowKeys(ShowKeysCommand),
 
+    #[command(
+        name = "show-gpu-info",
+        visible_alias = "show-gpus",
+        about = "Show information about GPUs and zero-copy scanout"
+    )]
+    ShowGpuInfo(ShowGpuInfoCommand),
+
     #[command(name = "cli", about = "Interact with experimental mux server")]
     Cli(cli::CliCommand),
 
@@ -747,6 +754,7 @@ fn run() -> anyhow::Result<()> {
         | SubCommand::BlockingStart(_)
         | SubCommand::LsFonts(_)
         | SubCommand::ShowKeys(_)
+        | SubCommand::ShowGpuInfo(_)
         | SubCommand::Ssh(_)
         | SubCommand::Serial(_)
         | SubCommand::Connect(_) => delegate_to_gui(saver),
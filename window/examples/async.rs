@@ -87,8 +87,19 @@ impl MyWindow {
             | WindowEvent::DroppedFile(_)
             | WindowEvent::DroppedUrl(_)
             | WindowEvent::DroppedString(_)
+            | WindowEvent::DroppedUrlsAt { .. }
             | WindowEvent::PerformKeyAssignment(_)
             | WindowEvent::MouseLeave
+            | WindowEvent::FocusedToplevelChanged
+            | WindowEvent::UserIdleChanged(_)
+            | WindowEvent::InputLatencyMeasured(_)
+            | WindowEvent::FrameStatsUpdated(_)
+            | WindowEvent::RefreshIntervalChanged(_)
+            | WindowEvent::PreferredBufferTransformChanged(_)
+            | WindowEvent::HdrOutputChanged(_)
+            | WindowEvent::KineticScroll { .. }
+            | WindowEvent::KineticScrollStop
+            | WindowEvent::OutputConfigurationChanged
             | WindowEvent::SetInnerSizeCompleted => {}
         }
     }
@@ -1,13 +1,19 @@
 use crate::bitmaps::{BitmapImage, Texture2d, TextureRect};
 use crate::{Point, Rect, Size};
 use anyhow::{ensure, Result as Fallible};
-use guillotiere::{SimpleAtlasAllocator, Size as AtlasSize};
+use guillotiere::{AllocId, AtlasAllocator, Size as AtlasSize};
+use std::cell::Cell;
+use std::collections::HashMap;
 use std::convert::TryInto;
 use std::rc::Rc;
 use thiserror::*;
 
 const PADDING: i32 = 1;
 
+/// Pages whose sprites have all gone unused for at least this many
+/// ticks of the atlas clock are emptied entirely by eviction
+const IDLE_PAGE_AGE: u64 = 600;
+
 #[derive(Debug, Error)]
 #[error("Texture Size exceeded, need {:?}", size)]
 pub struct OutOfTextureSpace {
@@ -15,16 +21,109 @@ pub struct OutOfTextureSpace {
     pub current_size: usize,
 }
 
+/// Allocates the texture for an additional atlas page of the given size
+pub type PageAllocator = Box<dyn Fn(usize) -> Fallible<Rc<dyn Texture2d>>>;
+
+/// Bookkeeping for a region of an atlas page, shared between the
+/// page and the sprite that occupies the region
+struct Allocation {
+    id: AllocId,
+    /// The reserved rectangle, including padding
+    reserved: Rect,
+    clock: Rc<Cell<u64>>,
+    last_used: Cell<u64>,
+    evicted: Cell<bool>,
+}
+
+impl Allocation {
+    fn area(&self) -> usize {
+        (self.reserved.size.width * self.reserved.size.height) as usize
+    }
+}
+
+struct AtlasPage {
+    texture: Rc<dyn Texture2d>,
+    allocator: AtlasAllocator,
+    allocations: HashMap<AllocId, Rc<Allocation>>,
+    /// The area reserved by allocations on this page, including padding
+    allocated_area: usize,
+}
+
+impl AtlasPage {
+    fn new(texture: Rc<dyn Texture2d>, side: usize) -> Fallible<Self> {
+        let iside = side as isize;
+        let image = crate::Image::new(side, side);
+        let rect = Rect::new(Point::new(0, 0), Size::new(iside, iside));
+        texture.write(rect, &image);
+
+        let allocator = AtlasAllocator::new(AtlasSize::new(side.try_into()?, side.try_into()?));
+        Ok(Self {
+            texture,
+            allocator,
+            allocations: HashMap::new(),
+            allocated_area: 0,
+        })
+    }
+
+    /// Releases the region held by an allocation, zeroing it so that
+    /// interpolation at the edges of whatever is allocated there next
+    /// doesn't pick up stale texels
+    fn free(&mut self, id: AllocId) {
+        if let Some(allocation) = self.allocations.remove(&id) {
+            allocation.evicted.set(true);
+            self.allocator.deallocate(id);
+            self.allocated_area -= allocation.area();
+            let image = crate::Image::new(
+                allocation.reserved.size.width as usize,
+                allocation.reserved.size.height as usize,
+            );
+            self.texture.write(allocation.reserved, &image);
+        }
+    }
+
+    fn clear(&mut self) {
+        for allocation in self.allocations.values() {
+            allocation.evicted.set(true);
+        }
+        self.allocations.clear();
+        let side = self.texture.width();
+        let iside = side as isize;
+        let image = crate::Image::new(side, side);
+        let rect = Rect::new(Point::new(0, 0), Size::new(iside, iside));
+        self.texture.write(rect, &image);
+        self.allocator.clear();
+        self.allocated_area = 0;
+    }
+
+    fn last_used(&self) -> Option<u64> {
+        self.allocations
+            .values()
+            .map(|allocation| allocation.last_used.get())
+            .max()
+    }
+}
+
 /// Atlases are bitmaps of srgba data that are sized as a power of 2.
 /// We allocate sprites out of the available space, using AtlasAllocator
 /// to manage the available rectangles.
+///
+/// When a PageAllocator is provided, additional pages of the same size
+/// are added as the existing pages fill up, rather than requiring the
+/// whole atlas to be recreated at a larger size.  Sprites address their
+/// page via the integer part of their horizontal texture coordinate.
 pub struct Atlas {
-    texture: Rc<dyn Texture2d>,
+    pages: Vec<AtlasPage>,
 
-    allocator: SimpleAtlasAllocator,
+    new_page: Option<PageAllocator>,
+    max_pages: usize,
 
-    /// Dimensions of the texture
+    /// Dimensions of each page
     side: usize,
+
+    /// Advanced once per frame by the renderer; sprites record the
+    /// value when they were last used so that the least recently
+    /// used sprites can be evicted when space runs out
+    clock: Rc<Cell<u64>>,
 }
 
 impl Atlas {
@@ -34,24 +133,50 @@ impl Atlas {
             "texture must be square!"
         );
         let side = texture.width();
-        let iside = side as isize;
-
-        let image = crate::Image::new(side, side);
-        let rect = Rect::new(Point::new(0, 0), Size::new(iside, iside));
-        texture.write(rect, &image);
-
-        let allocator =
-            SimpleAtlasAllocator::new(AtlasSize::new(side.try_into()?, side.try_into()?));
         Ok(Self {
-            texture: Rc::clone(texture),
+            pages: vec![AtlasPage::new(Rc::clone(texture), side)?],
+            new_page: None,
+            max_pages: 1,
             side,
-            allocator,
+            clock: Rc::new(Cell::new(0)),
         })
     }
 
+    /// Creates an atlas that can grow to up to `max_pages` pages,
+    /// using `new_page` to allocate the textures for them
+    pub fn with_pages(
+        texture: &Rc<dyn Texture2d>,
+        max_pages: usize,
+        new_page: PageAllocator,
+    ) -> Fallible<Self> {
+        let mut atlas = Self::new(texture)?;
+        atlas.new_page.replace(new_page);
+        atlas.max_pages = max_pages.max(1);
+        Ok(atlas)
+    }
+
+    /// Returns the texture of the first page
     #[inline]
     pub fn texture(&self) -> Rc<dyn Texture2d> {
-        Rc::clone(&self.texture)
+        Rc::clone(&self.pages[0].texture)
+    }
+
+    /// Returns the textures of all pages, indexed by page number
+    pub fn page_textures(&self) -> Vec<Rc<dyn Texture2d>> {
+        self.pages
+            .iter()
+            .map(|page| Rc::clone(&page.texture))
+            .collect()
+    }
+
+    pub fn num_pages(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Advances the clock used to determine which sprites
+    /// were least recently used
+    pub fn advance_clock(&self) {
+        self.clock.set(self.clock.get() + 1);
     }
 
     /// Reserve space for a sprite of the given size
@@ -92,25 +217,62 @@ impl Atlas {
         // interpolated on to the render surface.
         let reserve_width = reserve_width + padding.unwrap_or(0) as i32 + PADDING * 2;
         let reserve_height = reserve_height + padding.unwrap_or(0) as i32 + PADDING * 2;
+        let reserve_size = AtlasSize::new(reserve_width, reserve_height);
 
         let start = std::time::Instant::now();
-        let res = if let Some(allocation) = self
-            .allocator
-            .allocate(AtlasSize::new(reserve_width, reserve_height))
-        {
-            let left = allocation.min.x;
-            let top = allocation.min.y;
+        let mut found = self
+            .pages
+            .iter_mut()
+            .enumerate()
+            .find_map(|(idx, page)| page.allocator.allocate(reserve_size).map(|a| (idx, a)));
+
+        let fits_on_page = reserve_width.max(reserve_height) as usize <= self.side;
+        if found.is_none() && fits_on_page && self.pages.len() < self.max_pages {
+            if let Some(new_page) = &self.new_page {
+                match new_page(self.side).and_then(|texture| AtlasPage::new(texture, self.side)) {
+                    Ok(mut page) => {
+                        if let Some(allocation) = page.allocator.allocate(reserve_size) {
+                            found = Some((self.pages.len(), allocation));
+                        }
+                        self.pages.push(page);
+                        log::trace!("added texture atlas page {}", self.pages.len());
+                    }
+                    Err(err) => log::warn!("Failed to add texture atlas page: {err:#}"),
+                }
+            }
+        }
+
+        let res = if let Some((page_idx, allocation)) = found {
+            let page = &mut self.pages[page_idx];
+            let left = allocation.rectangle.min.x;
+            let top = allocation.rectangle.min.y;
             let rect = Rect::new(
                 Point::new((left + PADDING) as isize, (top + PADDING) as isize),
                 Size::new(width as isize, height as isize),
             );
 
-            self.texture.write(rect, im);
+            page.texture.write(rect, im);
+            page.allocated_area += (reserve_width * reserve_height) as usize;
+
+            let allocation = Rc::new(Allocation {
+                id: allocation.id,
+                reserved: Rect::new(
+                    Point::new(left as isize, top as isize),
+                    Size::new(reserve_width as isize, reserve_height as isize),
+                ),
+                clock: Rc::clone(&self.clock),
+                last_used: Cell::new(self.clock.get()),
+                evicted: Cell::new(false),
+            });
+            page.allocations
+                .insert(allocation.id, Rc::clone(&allocation));
 
             metrics::histogram!("window.atlas.allocate.success.rate").record(1.);
             Ok(Sprite {
-                texture: Rc::clone(&self.texture),
+                texture: Rc::clone(&page.texture),
                 coords: rect,
+                page: page_idx,
+                allocation: Some(allocation),
             })
         } else {
             // It's not possible to satisfy that request
@@ -130,25 +292,100 @@ impl Atlas {
         self.side
     }
 
+    /// Returns the fraction of the texture space that has been allocated
+    pub fn utilization(&self) -> f32 {
+        let allocated: usize = self.pages.iter().map(|page| page.allocated_area).sum();
+        allocated as f32 / (self.side * self.side * self.pages.len()) as f32
+    }
+
+    /// Frees space for new sprites by evicting those that have been
+    /// used least recently, until at least half of the space is free.
+    /// Pages that have gone unused for a while are emptied first, and
+    /// trailing empty pages are released.
+    /// Sprites used since the clock was last advanced are retained.
+    /// Evicted sprites report `is_evicted`, and any texture coordinates
+    /// derived from them must no longer be used.
+    /// Returns false if nothing could be evicted.
+    pub fn evict_least_recently_used(&mut self) -> bool {
+        let now = self.clock.get();
+        let mut evicted = false;
+
+        for page in &mut self.pages {
+            let idle = page
+                .last_used()
+                .map(|last_used| now.saturating_sub(last_used) >= IDLE_PAGE_AGE)
+                .unwrap_or(false);
+            if idle {
+                page.clear();
+                evicted = true;
+            }
+        }
+
+        let total_area = self.side * self.side * self.pages.len();
+        let mut allocated: usize = self.pages.iter().map(|page| page.allocated_area).sum();
+
+        let mut candidates: Vec<(u64, usize, AllocId)> = self
+            .pages
+            .iter()
+            .enumerate()
+            .flat_map(|(page_idx, page)| {
+                page.allocations
+                    .values()
+                    .map(move |allocation| (allocation.last_used.get(), page_idx, allocation.id))
+            })
+            .filter(|(last_used, _, _)| *last_used < now)
+            .collect();
+        candidates.sort_unstable_by_key(|(last_used, _, _)| *last_used);
+
+        for (_, page_idx, id) in candidates {
+            if allocated * 2 <= total_area {
+                break;
+            }
+            let page = &mut self.pages[page_idx];
+            let before = page.allocated_area;
+            page.free(id);
+            allocated -= before - page.allocated_area;
+            evicted = true;
+        }
+
+        while self.pages.len() > 1
+            && self
+                .pages
+                .last()
+                .map(|page| page.allocations.is_empty())
+                .unwrap_or(false)
+        {
+            self.pages.pop();
+        }
+
+        if evicted {
+            metrics::histogram!("window.atlas.evict.rate").record(1.);
+        }
+        evicted
+    }
+
     /// Zero out the texture, and forget all allocated regions
     pub fn clear(&mut self) {
-        let iside = self.side as isize;
-        let image = crate::Image::new(self.side, self.side);
-        let rect = Rect::new(Point::new(0, 0), Size::new(iside, iside));
-        self.texture.write(rect, &image);
-        self.allocator.clear();
+        for page in &mut self.pages {
+            page.clear();
+        }
+        self.pages.truncate(1);
     }
 }
 
 pub struct Sprite {
     pub texture: Rc<dyn Texture2d>,
     pub coords: Rect,
+    /// The index of the atlas page that holds the sprite
+    pub page: usize,
+    allocation: Option<Rc<Allocation>>,
 }
 
 impl std::fmt::Debug for Sprite {
     fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::result::Result<(), std::fmt::Error> {
         fmt.debug_struct("Sprite")
             .field("coords", &self.coords)
+            .field("page", &self.page)
             .field("texture_width", &self.texture.width())
             .field("texture_height", &self.texture.height())
             .finish()
@@ -160,13 +397,38 @@ impl Clone for Sprite {
         Self {
             texture: Rc::clone(&self.texture),
             coords: self.coords,
+            page: self.page,
+            allocation: self.allocation.clone(),
         }
     }
 }
 
 impl Sprite {
-    /// Returns the texture coordinates of the sprite
+    /// Returns the texture coordinates of the sprite, offset
+    /// horizontally by its page number, and marks the sprite
+    /// as having been used
     pub fn texture_coords(&self) -> TextureRect {
-        self.texture.to_texture_coords(self.coords)
+        self.mark_used();
+        let mut coords = self.texture.to_texture_coords(self.coords);
+        coords.origin.x += self.page as f32;
+        coords
+    }
+
+    /// Marks the sprite as having been used, so that it is not
+    /// evicted in favor of less recently used sprites.
+    /// texture_coords does this implicitly.
+    pub fn mark_used(&self) {
+        if let Some(allocation) = &self.allocation {
+            allocation.last_used.set(allocation.clock.get());
+        }
+    }
+
+    /// Returns true if the sprite has been evicted from the atlas,
+    /// and its region may now hold some other sprite
+    pub fn is_evicted(&self) -> bool {
+        self.allocation
+            .as_ref()
+            .map(|allocation| allocation.evicted.get())
+            .unwrap_or(false)
     }
 }
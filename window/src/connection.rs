@@ -1,4 +1,7 @@
+use crate::output::OutputInfo;
+use crate::scanout::ScanoutInfo;
 use crate::screen::Screens;
+use crate::toplevel::ToplevelInfo;
 use crate::{Appearance, Connection, GeometryOrigin, RequestedWindowGeometry, ResolvedGeometry};
 use anyhow::Result as Fallible;
 use config::keyassignment::KeyAssignment;
@@ -11,6 +14,10 @@ thread_local! {
     static CONN: RefCell<Option<Rc<Connection>>> = RefCell::new(None);
 }
 
+/// The name of the environment variable used to pass an
+/// xdg-activation token to a newly launched program
+pub const ACTIVATION_TOKEN_ENV: &str = "XDG_ACTIVATION_TOKEN";
+
 fn nop_event_handler(_event: ApplicationEvent) {}
 
 static EVENT_HANDLER: Mutex<fn(ApplicationEvent)> = Mutex::new(nop_event_handler);
@@ -76,11 +83,60 @@ pub trait ConnectionOps {
     /// Perform the system beep/notification sound
     fn beep(&self) {}
 
+    /// Returns an activation token that can be passed to a child
+    /// process, in the ACTIVATION_TOKEN_ENV environment variable,
+    /// so that the windowing system allows it to focus the
+    /// window(s) that it creates.  The token is only good for a
+    /// single use.
+    /// This is only implemented on Wayland (xdg-activation).
+    fn take_activation_token(&self) -> Option<String> {
+        None
+    }
+
+    /// Returns true if windows can have their solid background color
+    /// drawn by the windowing system, see WindowOps::set_solid_background.
+    /// This is only implemented on Wayland (single-pixel-buffer).
+    fn supports_solid_background(&self) -> bool {
+        false
+    }
+
+    /// Returns true if a window can be presented as a modal dialog
+    /// of another, see WindowOps::set_modal_parent.
+    /// This is only implemented on Wayland (xdg-dialog).
+    fn supports_modal_dialogs(&self) -> bool {
+        false
+    }
+
     /// Returns information about the screens
     fn screens(&self) -> anyhow::Result<Screens> {
         anyhow::bail!("Unable to query screen information");
     }
 
+    /// Returns the toplevel windows of all applications, as known
+    /// to the windowing system.
+    /// This is only implemented on Wayland, and requires that the
+    /// compositor implement ext-foreign-toplevel-list or
+    /// wlr-foreign-toplevel-management.
+    fn toplevels(&self) -> anyhow::Result<Vec<ToplevelInfo>> {
+        anyhow::bail!("Unable to query toplevel windows");
+    }
+
+    /// Returns information about the physical outputs, including
+    /// those that are disabled.
+    /// This is only implemented on Wayland, and requires that the
+    /// compositor implement wlr-output-management.
+    fn outputs(&self) -> anyhow::Result<Vec<OutputInfo>> {
+        anyhow::bail!("Unable to query outputs");
+    }
+
+    /// Reports whether the windowing system is able to display
+    /// our frames without copying them.
+    /// This is only implemented on Wayland, and requires that the
+    /// compositor implement version 4 of linux-dmabuf.
+    fn scanout_info(&self) -> anyhow::Result<ScanoutInfo> {
+        anyhow::bail!("Unable to query scanout information");
+    }
+
     fn resolve_geometry(&self, geometry: RequestedWindowGeometry) -> ResolvedGeometry {
         let bounds = match self.screens() {
             Ok(screens) => {
@@ -6,6 +6,7 @@ use promise::Future;
 use std::any::Any;
 use std::path::PathBuf;
 use std::rc::Rc;
+use std::time::Duration;
 use thiserror::Error;
 use url::Url;
 pub mod bitmaps;
@@ -13,8 +14,11 @@ pub use wezterm_color_types as color;
 mod configuration;
 pub mod connection;
 pub mod os;
+pub mod output;
 pub mod screen;
 mod spawn;
+pub mod scanout;
+pub mod toplevel;
 
 pub use raw_window_handle;
 
@@ -50,6 +54,27 @@ impl Default for Clipboard {
     }
 }
 
+/// Clipboard content that is offered in more than one format
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClipboardContents {
+    /// Offered as text/plain
+    pub text: String,
+    /// Offered as text/html, when present
+    pub html: Option<String>,
+    /// Offered as image/png, when present
+    pub png: Option<Vec<u8>>,
+}
+
+/// The content offered to other applications when dragging
+/// out of a window
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DragData {
+    /// Offered as text/plain
+    pub text: String,
+    /// Offered as text/uri-list, when non-empty
+    pub uris: Vec<String>,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Dimensions {
     pub pixel_width: usize,
@@ -57,6 +82,85 @@ pub struct Dimensions {
     pub dpi: usize,
 }
 
+/// How the contents of a buffer are rotated and/or flipped relative
+/// to the window, to match the orientation of the display that it is on.
+/// The names follow those of wl_output.transform: rotations are
+/// counter-clockwise, and flips are about the vertical axis and are
+/// applied before rotating.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BufferTransform {
+    #[default]
+    Normal,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    Flipped,
+    Flipped90,
+    Flipped180,
+    Flipped270,
+}
+
+impl BufferTransform {
+    /// Returns true if the width and height of the buffer are
+    /// swapped relative to those of the window
+    pub fn swaps_dimensions(self) -> bool {
+        matches!(
+            self,
+            Self::Rotate90 | Self::Rotate270 | Self::Flipped90 | Self::Flipped270
+        )
+    }
+
+    /// Returns the (width, height) of the buffer for a window
+    /// that is `width` x `height` pixels in size
+    pub fn buffer_size(self, width: usize, height: usize) -> (usize, usize) {
+        if self.swaps_dimensions() {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+
+    /// Maps the point (x, y) in a window that is `width` x `height`
+    /// pixels in size to the corresponding point in the buffer
+    pub fn transform_point(self, x: f32, y: f32, width: f32, height: f32) -> (f32, f32) {
+        match self {
+            Self::Normal => (x, y),
+            Self::Rotate90 => (y, width - x),
+            Self::Rotate180 => (width - x, height - y),
+            Self::Rotate270 => (height - y, x),
+            Self::Flipped => (width - x, y),
+            Self::Flipped90 => (y, x),
+            Self::Flipped180 => (x, height - y),
+            Self::Flipped270 => (height - y, width - x),
+        }
+    }
+}
+
+/// Statistics about the frames that were presented for a window over
+/// an interval, as reported by the compositor
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrameStats {
+    /// The length of the interval covered by these statistics
+    pub interval: Duration,
+    /// The number of frames that were presented
+    pub presented: u64,
+    /// The number of frames that the compositor never displayed
+    pub discarded: u64,
+    /// The number of presented frames that were synchronized
+    /// to the vertical blank of the display
+    pub vsync: u64,
+    /// The number of presented frames that were scanned out
+    /// directly from our buffers, without being copied
+    pub zero_copy: u64,
+    /// The number of presented frames that missed the first
+    /// vertical blank following their submission
+    pub missed_deadlines: u64,
+    /// The time from submitting a frame to its presentation
+    pub frame_time_p50: Option<Duration>,
+    pub frame_time_p95: Option<Duration>,
+    pub frame_time_max: Option<Duration>,
+}
+
 pub type ULength = euclid::Length<usize, PixelUnit>;
 pub type Rect = euclid::Rect<isize, PixelUnit>;
 pub type RectF = euclid::Rect<f32, PixelUnit>;
@@ -103,7 +207,7 @@ impl std::string::ToString for Appearance {
 
 bitflags! {
     #[derive(Default)]
-    pub struct WindowState: u8 {
+    pub struct WindowState: u16 {
         /// Occupies the whole screen; cannot be resized while in this state.
         const FULL_SCREEN = 1<<1;
         /// Maximized along either or both of horizontal or vertical dimensions;
@@ -116,6 +220,24 @@ bitflags! {
         const ALWAYS_ON_TOP = 1<<4;
         /// Always on bottom (docked) window
         const ALWAYS_ON_BOTTOM = 1<<5;
+        /// The window manager draws the title bar and window buttons
+        /// for this window, so we shouldn't draw our own.
+        const SERVER_DECORATIONS = 1<<6;
+        /// The corresponding edge of the window is adjacent to another
+        /// window or the edge of the screen, as when a tiling window
+        /// manager has arranged it
+        const TILED_LEFT = 1<<7;
+        const TILED_RIGHT = 1<<8;
+        const TILED_TOP = 1<<9;
+        const TILED_BOTTOM = 1<<10;
+        const TILED = Self::TILED_LEFT.bits
+            | Self::TILED_RIGHT.bits
+            | Self::TILED_TOP.bits
+            | Self::TILED_BOTTOM.bits;
+        /// The window is not visible, for example because it is on
+        /// another workspace, and frames may not be presented at all
+        /// while in this state.
+        const SUSPENDED = 1<<11;
     }
 }
 
@@ -124,6 +246,12 @@ impl WindowState {
         !self.intersects(Self::FULL_SCREEN | Self::MAXIMIZED)
     }
 
+    /// Returns false while the window is minimized or suspended,
+    /// when nothing that it paints would be seen
+    pub fn is_visible(self) -> bool {
+        !self.intersects(Self::HIDDEN | Self::SUSPENDED)
+    }
+
     pub fn can_paint(self) -> bool {
         !self.contains(Self::HIDDEN)
     }
@@ -145,13 +273,31 @@ pub enum WindowKeyEvent {
     KeyEvent(KeyEvent),
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum DeadKeyStatus {
     /// Not in a dead key processing hold
     None,
     /// Holding until composition is done; the string is the uncommitted
     /// composition text to show as a placeholder
     Composing(String),
+    /// An input method is composing `text`.
+    /// `cursor` is the byte range within `text` that the input method
+    /// wants highlighted; an empty range is the position of its caret,
+    /// and `None` means that the caret should be hidden.
+    Preedit {
+        text: String,
+        cursor: Option<std::ops::Range<usize>>,
+    },
+}
+
+impl DeadKeyStatus {
+    /// Returns the uncommitted text, if any
+    pub fn composing_text(&self) -> Option<&str> {
+        match self {
+            Self::None => None,
+            Self::Composing(text) | Self::Preedit { text, .. } => Some(text),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -212,10 +358,74 @@ pub enum WindowEvent {
     // Called when text is dropped into the window
     DroppedString(String),
 
+    /// Called when urls are dropped into the window by a backend that
+    /// knows where they were dropped.  coords are in pixels relative
+    /// to the top left of the window.
+    DroppedUrlsAt {
+        urls: Vec<Url>,
+        coords: Point,
+    },
+
     /// Called by menubar dispatching stuff on some systems
     PerformKeyAssignment(config::keyassignment::KeyAssignment),
 
     AdviseModifiersLedStatus(Modifiers, KeyboardLedStatus),
+
+    /// Called when keyboard focus moves between the toplevel windows
+    /// of any application; see ConnectionOps::toplevels
+    FocusedToplevelChanged,
+
+    /// Called when the configuration of the outputs changes, for
+    /// example when a monitor is plugged in or its mode is changed;
+    /// see ConnectionOps::outputs
+    OutputConfigurationChanged,
+
+    /// Called when the user has been idle for the configured
+    /// idle_animation_pause_delay_ms (true), and when they become
+    /// active again (false)
+    UserIdleChanged(bool),
+
+    /// Reports the time between an input event and the presentation
+    /// of the frame that followed it
+    InputLatencyMeasured(Duration),
+
+    /// Periodically reports statistics about the frames that were
+    /// presented for the window.  Only sent by backends that receive
+    /// presentation feedback from the compositor
+    FrameStatsUpdated(FrameStats),
+
+    /// Reports how often the display that the window is on refreshes.
+    /// Only sent by backends that are able to track this per-window;
+    /// elsewhere, `max_fps` is used instead
+    RefreshIntervalChanged(Duration),
+
+    /// Called when the compositor suggests a transform for our buffers,
+    /// typically because the window is on a rotated display.
+    /// Rendering with that transform applied, and then calling
+    /// WindowOps::set_buffer_transform, saves the compositor
+    /// from having to transform each frame itself
+    PreferredBufferTransformChanged(BufferTransform),
+
+    /// Called when the window moves onto or off of displays that are
+    /// able to present HDR content; the parameter is true while any
+    /// of the displays that the window is on are HDR capable.
+    /// `WindowOps::set_hdr_content` can then be used to tag the content
+    /// of the window accordingly
+    HdrOutputChanged(bool),
+
+    /// Called when the fingers are lifted from a touchpad while they
+    /// are still scrolling.  The velocities are measured in the same
+    /// units as `MouseEventKind::HorzWheel` and `VertWheel`, per second;
+    /// the application can keep scrolling, slowing down over time,
+    /// to give the scroll some momentum
+    KineticScroll {
+        horizontal: f64,
+        vertical: f64,
+    },
+
+    /// Called when the fingers are placed back on the touchpad;
+    /// any kinetic scrolling should stop
+    KineticScrollStop,
 }
 
 pub struct WindowEventSender {
@@ -265,6 +475,50 @@ pub trait WindowOps {
         Ok(())
     }
 
+    /// Setup rendering on the CPU, for use when opengl is unavailable.
+    /// Frames are then submitted via present_software_frame rather
+    /// than finish_frame.
+    async fn enable_software_rendering(&self) -> anyhow::Result<()> {
+        anyhow::bail!("software rendering is not supported on this platform");
+    }
+    /// Present a frame that was rendered on the CPU.
+    /// `pixels` holds `width` x `height` premultiplied ARGB values,
+    /// row by row, with alpha in the most significant byte.
+    fn present_software_frame(
+        &self,
+        _width: usize,
+        _height: usize,
+        _pixels: Vec<u32>,
+    ) -> anyhow::Result<()> {
+        anyhow::bail!("software rendering is not supported on this platform");
+    }
+
+    /// Advise the window that subsequent frames are rendered with
+    /// `transform` applied, and so have the dimensions reported by
+    /// BufferTransform::buffer_size.
+    /// Should only be called in response to
+    /// WindowEvent::PreferredBufferTransformChanged
+    fn set_buffer_transform(&self, _transform: BufferTransform) {}
+
+    /// Returns true if the windowing system accepts HDR content
+    /// for this window, regardless of whether the display is HDR
+    fn supports_hdr_content(&self) -> bool {
+        false
+    }
+
+    /// Advise the windowing system that the window content is
+    /// extended range linear sRGB (scRGB, where 1.0 is the SDR
+    /// reference white) if `hdr` is true, or regular sRGB otherwise.
+    /// Has no effect unless supports_hdr_content returns true.
+    fn set_hdr_content(&self, _hdr: bool) {}
+
+    /// Returns the ICC profile that the user has assigned to the
+    /// display that the window is on, if the windowing system
+    /// publishes one for applications to apply themselves
+    fn get_icc_profile(&self) -> Option<Vec<u8>> {
+        None
+    }
+
     /// Hide a visible window
     fn hide(&self);
 
@@ -320,6 +574,19 @@ pub trait WindowOps {
     /// Set some text in the clipboard
     fn set_clipboard(&self, clipboard: Clipboard, text: String);
 
+    /// Set the clipboard, offering each of the formats present in
+    /// `contents`.  Backends that only support text set just the text.
+    fn set_clipboard_contents(&self, clipboard: Clipboard, contents: ClipboardContents) {
+        self.set_clipboard(clipboard, contents.text);
+    }
+
+    /// Retrieve PNG image data from the clipboard, if that is what it
+    /// holds.
+    /// Only supported on Wayland.
+    fn get_clipboard_image(&self, _clipboard: Clipboard) -> Future<Option<Vec<u8>>> {
+        Future::ok(None)
+    }
+
     /// Set window level. Depending on the environment and user preferences
     fn set_window_level(&self, _level: WindowLevel) {}
 
@@ -332,8 +599,61 @@ pub trait WindowOps {
     fn restore(&self) {}
     fn focus(&self) {}
 
+    /// Like focus, but using an activation token that was handed to
+    /// us by another program, such as the one that was given to a
+    /// `wezterm start` that asked this instance to spawn the window.
+    /// Only Wayland (xdg-activation) makes use of the token.
+    fn focus_with_activation_token(&self, _token: String) {
+        self.focus()
+    }
+
     fn toggle_fullscreen(&self) {}
 
+    /// Toggle full screen mode, placing the window on the screen
+    /// named `screen_name` (see ConnectionOps::screens) when entering
+    /// full screen mode.  Backends that cannot choose the screen
+    /// fall back to toggle_fullscreen.
+    fn toggle_fullscreen_on_screen(&self, _screen_name: String) {
+        self.toggle_fullscreen()
+    }
+
+    /// Ask the windowing system to deliver all keys to this window,
+    /// including those that it would normally intercept for its own
+    /// shortcuts, or to stop doing so.
+    /// Only supported on Wayland.
+    fn toggle_shortcut_inhibit(&self) {}
+
+    /// Switch between presenting frames in sync with the display
+    /// and presenting them as soon as they are ready, overriding
+    /// the presentation_mode config for this window.
+    /// Only supported on Wayland.
+    fn toggle_presentation_mode(&self) {}
+
+    /// Begin dragging `data` out of the window.  This must be called
+    /// while a mouse button is held down, in response to the user
+    /// dragging the mouse.
+    /// Only supported on Wayland.
+    fn start_drag(&self, _data: DragData) {}
+
+    /// Have the windowing system fill the window with a solid color
+    /// beneath whatever we render, or stop doing so when None.
+    /// Only has an effect when ConnectionOps::supports_solid_background
+    /// returns true.
+    fn set_solid_background(&self, _color: Option<color::SrgbaTuple>) {}
+
+    /// Have the windowing system treat this window as a modal dialog
+    /// of `parent`, eg: by centering it over `parent` and dimming
+    /// `parent`.  The caller is responsible for ignoring input to
+    /// `parent` while the dialog is open.
+    /// Only has an effect when ConnectionOps::supports_modal_dialogs
+    /// returns true.
+    fn set_modal_parent(&self, _parent: &Window) {}
+
+    /// Restrict the background blur, if enabled, to `region` (in
+    /// pixels) rather than applying it to the whole window.
+    /// Only supported on KDE Wayland.
+    fn set_blur_region(&self, _region: Option<Rect>) {}
+
     fn config_did_change(&self, _config: &config::ConfigHandle) {}
 
     /// Configure the Window so that the desktop environment
@@ -247,6 +247,11 @@ pub fn nsscreen_to_screen_info(screen: *mut Object) -> ScreenInfo {
         scale,
         max_fps,
         effective_dpi,
+        description: None,
+        make: None,
+        model: None,
+        physical_size_mm: None,
+        logical_rect: None,
     }
 }
 
@@ -0,0 +1,119 @@
+use smithay_client_toolkit::activation::{ActivationHandler, RequestData, RequestDataExt};
+use smithay_client_toolkit::delegate_activation;
+use wayland_client::protocol::wl_seat::WlSeat;
+use wayland_client::protocol::wl_surface::WlSurface;
+use wayland_client::{Proxy, QueueHandle};
+
+use super::state::WaylandState;
+pub(super) use crate::connection::ACTIVATION_TOKEN_ENV;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum ActivationPurpose {
+    /// Activate the window with this id when the token arrives
+    Focus(usize),
+    /// Hold on to the token so that it can be handed to the
+    /// next child process that we spawn
+    Spawn,
+}
+
+pub(super) struct ActivationRequest {
+    purpose: ActivationPurpose,
+    request: RequestData,
+}
+
+impl RequestDataExt for ActivationRequest {
+    fn app_id(&self) -> Option<&str> {
+        self.request.app_id()
+    }
+
+    fn seat_and_serial(&self) -> Option<(&WlSeat, u32)> {
+        self.request.seat_and_serial()
+    }
+
+    fn surface(&self) -> Option<&WlSurface> {
+        self.request.surface()
+    }
+}
+
+impl ActivationHandler for WaylandState {
+    type RequestData = ActivationRequest;
+
+    fn new_token(&mut self, token: String, data: &ActivationRequest) {
+        log::trace!("new activation token for {:?}", data.purpose);
+        match data.purpose {
+            ActivationPurpose::Focus(window_id) => {
+                self.activate_window(window_id, token);
+            }
+            ActivationPurpose::Spawn => {
+                self.spawn_activation_token.replace(token);
+            }
+        }
+    }
+}
+
+impl WaylandState {
+    /// Activates the window with this id using `token`
+    pub(super) fn activate_window(&self, window_id: usize, token: String) {
+        let activation = match self.activation.as_ref() {
+            Some(activation) => activation,
+            None => return,
+        };
+        if let Some(window) = self.window_by_id(window_id) {
+            let inner = window.borrow();
+            if inner.has_surface() {
+                activation.activate::<WaylandState>(inner.surface(), token);
+            }
+        }
+    }
+
+    /// Ask the compositor for an activation token.
+    /// Compositors generally only hand out usable tokens when the
+    /// request carries a recent input serial and the surface that
+    /// currently has keyboard focus, so we include both when we
+    /// have them.
+    pub(super) fn request_activation_token(
+        &self,
+        qh: &QueueHandle<WaylandState>,
+        purpose: ActivationPurpose,
+    ) {
+        let activation = match self.activation.as_ref() {
+            Some(activation) => activation,
+            None => return,
+        };
+
+        let seat = self.active_seat();
+        let seat_and_serial = match seat {
+            Some(seat) if seat.last_serial != 0 => Some((seat.seat.clone(), seat.last_serial)),
+            _ => None,
+        };
+
+        let surface = seat
+            .and_then(|seat| seat.keyboard_surface_id.as_ref())
+            .and_then(|id| {
+                self.windows.borrow().values().find_map(|window| {
+                    // The window may be mutably borrowed further up the
+                    // stack; it isn't worth panicking over a hint
+                    let inner = window.try_borrow().ok()?;
+                    if !inner.has_surface() {
+                        return None;
+                    }
+                    let surface = inner.surface();
+                    (surface.id() == *id).then(|| surface.clone())
+                })
+            });
+
+        activation.request_token_with_data::<WaylandState, ActivationRequest>(
+            qh,
+            ActivationRequest {
+                purpose,
+                request: RequestData {
+                    app_id: None,
+                    seat_and_serial,
+                    surface,
+                },
+            },
+        );
+    }
+}
+
+delegate_activation!(WaylandState, ActivationRequest);
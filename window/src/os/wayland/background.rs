@@ -0,0 +1,157 @@
+use smithay_client_toolkit::compositor::Region;
+use wayland_client::protocol::wl_buffer::WlBuffer;
+use wayland_client::protocol::wl_subsurface::WlSubsurface;
+use wayland_client::protocol::wl_surface::WlSurface;
+use wayland_client::QueueHandle;
+use wayland_protocols::wp::alpha_modifier::v1::client::wp_alpha_modifier_surface_v1::WpAlphaModifierSurfaceV1;
+use wayland_protocols::wp::viewporter::client::wp_viewport::WpViewport;
+
+use crate::color::SrgbaTuple;
+
+use super::state::WaylandState;
+
+/// A subsurface that sits below the main window surface and shows a
+/// single solid color.  The color is a 1x1 wp_single_pixel_buffer_v1
+/// buffer that the compositor stretches to the window size using
+/// wp_viewporter, so there is nothing for us to rasterize and the
+/// compositor is free to scan it out directly.
+/// When wp_alpha_modifier_v1 is available, the buffer is kept opaque
+/// and the opacity is applied by the compositor as a multiplier, so
+/// that changing the opacity doesn't require a new buffer.
+/// Neither the color nor the opacity pass through the transformations
+/// that the application applies to its own frames, so it must only ask
+/// for a solid background when its frames aren't transformed.
+pub(super) struct SolidBackground {
+    surface: WlSurface,
+    subsurface: WlSubsurface,
+    viewport: WpViewport,
+    alpha_modifier: Option<WpAlphaModifierSurfaceV1>,
+    buffer: Option<WlBuffer>,
+    color: Option<SrgbaTuple>,
+    alpha: Option<f32>,
+    size: Option<(i32, i32)>,
+}
+
+impl SolidBackground {
+    /// Returns None if the compositor lacks either of the
+    /// single-pixel-buffer or viewporter protocols
+    pub(super) fn new(
+        state: &WaylandState,
+        qh: &QueueHandle<WaylandState>,
+        parent: &WlSurface,
+    ) -> Option<Self> {
+        state.single_pixel_buffer_manager.as_ref()?;
+        let viewporter = state.viewporter.as_ref()?;
+
+        let (subsurface, surface) = state.subcompositor.create_subsurface(parent.clone(), qh);
+        subsurface.place_below(parent);
+        subsurface.set_position(0, 0);
+
+        // Leave all input to the window surface above us
+        if let Ok(region) = Region::new(&state.compositor) {
+            surface.set_input_region(Some(region.wl_region()));
+        }
+
+        let viewport = viewporter.get_viewport(&surface, qh, surface.clone());
+        let alpha_modifier = state
+            .alpha_modifier
+            .as_ref()
+            .map(|alpha_modifier| alpha_modifier.get_surface(&surface, qh, surface.clone()));
+
+        Some(Self {
+            surface,
+            subsurface,
+            viewport,
+            alpha_modifier,
+            buffer: None,
+            color: None,
+            alpha: None,
+            size: None,
+        })
+    }
+
+    /// Show `color` (sRGB, straight alpha) at the given surface-local
+    /// size.  As this is a synchronized subsurface, the change is
+    /// applied together with the next commit of the window surface.
+    pub(super) fn update(
+        &mut self,
+        state: &WaylandState,
+        qh: &QueueHandle<WaylandState>,
+        color: SrgbaTuple,
+        width: i32,
+        height: i32,
+    ) {
+        let manager = match state.single_pixel_buffer_manager.as_ref() {
+            Some(manager) => manager,
+            None => return,
+        };
+        if width <= 0 || height <= 0 {
+            return;
+        }
+
+        let mut changed = false;
+
+        let SrgbaTuple(r, g, b, a) = color;
+        let a = a.clamp(0., 1.);
+        let (color, buffer_alpha) = match &self.alpha_modifier {
+            Some(alpha_modifier) => {
+                if self.alpha != Some(a) {
+                    alpha_modifier.set_multiplier(to_u32(a));
+                    self.alpha.replace(a);
+                    changed = true;
+                }
+                (SrgbaTuple(r, g, b, 1.), 1.)
+            }
+            None => (color, a),
+        };
+
+        if self.color != Some(color) {
+            // The buffer is defined to use premultiplied alpha
+            let buffer = manager.create_u32_rgba_buffer(
+                to_u32(r * buffer_alpha),
+                to_u32(g * buffer_alpha),
+                to_u32(b * buffer_alpha),
+                to_u32(buffer_alpha),
+                qh,
+                (),
+            );
+            self.surface.attach(Some(&buffer), 0, 0);
+            self.surface.damage_buffer(0, 0, 1, 1);
+            if let Some(old) = self.buffer.replace(buffer) {
+                old.destroy();
+            }
+            self.color.replace(color);
+            changed = true;
+        }
+
+        if self.size != Some((width, height)) {
+            self.viewport.set_destination(width, height);
+            self.size.replace((width, height));
+            changed = true;
+        }
+
+        if changed {
+            self.surface.commit();
+        }
+    }
+}
+
+/// Map 0.0-1.0 to the full u32 range used by both the single pixel
+/// buffer and alpha modifier protocols
+fn to_u32(value: f32) -> u32 {
+    (value.clamp(0., 1.) as f64 * u32::MAX as f64) as u32
+}
+
+impl Drop for SolidBackground {
+    fn drop(&mut self) {
+        if let Some(alpha_modifier) = self.alpha_modifier.take() {
+            alpha_modifier.destroy();
+        }
+        self.viewport.destroy();
+        self.subsurface.destroy();
+        self.surface.destroy();
+        if let Some(buffer) = self.buffer.take() {
+            buffer.destroy();
+        }
+    }
+}
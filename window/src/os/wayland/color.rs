@@ -0,0 +1,325 @@
+use std::sync::Mutex;
+
+use smithay_client_toolkit::globals::GlobalData;
+use wayland_client::globals::GlobalList;
+use wayland_client::protocol::wl_output::WlOutput;
+use wayland_client::protocol::wl_surface::WlSurface;
+use wayland_client::{Connection, Dispatch, Proxy, QueueHandle, WEnum};
+use wayland_protocols::wp::color_management::v1::client::wp_color_management_output_v1::{
+    self, WpColorManagementOutputV1,
+};
+use wayland_protocols::wp::color_management::v1::client::wp_color_management_surface_v1::WpColorManagementSurfaceV1;
+use wayland_protocols::wp::color_management::v1::client::wp_color_manager_v1::{
+    self, Feature, Primaries, RenderIntent, TransferFunction, WpColorManagerV1,
+};
+use wayland_protocols::wp::color_management::v1::client::wp_image_description_creator_params_v1::WpImageDescriptionCreatorParamsV1;
+use wayland_protocols::wp::color_management::v1::client::wp_image_description_info_v1::{
+    self, WpImageDescriptionInfoV1,
+};
+use wayland_protocols::wp::color_management::v1::client::wp_image_description_v1::{
+    self, WpImageDescriptionV1,
+};
+
+use super::state::WaylandState;
+use super::WaylandConnection;
+
+/// Tracks the wp_color_manager_v1 global along with the capabilities
+/// that the compositor advertised for it
+pub(super) struct ColorManager {
+    manager: WpColorManagerV1,
+    parametric: bool,
+    perceptual: bool,
+    windows_scrgb: bool,
+    transfer_functions: Vec<TransferFunction>,
+    primaries: Vec<Primaries>,
+}
+
+impl ColorManager {
+    pub(super) fn bind(globals: &GlobalList, qh: &QueueHandle<WaylandState>) -> Option<Self> {
+        let manager: WpColorManagerV1 = globals.bind(qh, 1..=1, GlobalData).ok()?;
+        Some(Self {
+            manager,
+            parametric: false,
+            perceptual: false,
+            windows_scrgb: false,
+            transfer_functions: vec![],
+            primaries: vec![],
+        })
+    }
+
+    /// The transfer function that we use to describe our buffers.
+    /// Everything we render is sRGB encoded; some compositors
+    /// only advertise the gamma 2.2 curve that sRGB displays use
+    /// in practice, so we'll settle for that
+    fn srgb_transfer_function(&self) -> Option<TransferFunction> {
+        [TransferFunction::Srgb, TransferFunction::Gamma22]
+            .into_iter()
+            .find(|tf| self.transfer_functions.contains(tf))
+    }
+
+    /// Whether we can describe our content as scRGB, which is how
+    /// we present HDR content
+    pub(super) fn supports_hdr(&self) -> bool {
+        self.windows_scrgb && self.perceptual
+    }
+
+    /// Begin tagging `surface` as containing sRGB content, or scRGB
+    /// content if `hdr` is true.
+    /// The description isn't usable until the compositor says that
+    /// it is ready, which is when we attach it to the surface; see
+    /// the Dispatch impl for WpImageDescriptionV1 below.
+    pub(super) fn describe_surface(
+        &self,
+        qh: &QueueHandle<WaylandState>,
+        surface: &WlSurface,
+        hdr: bool,
+    ) -> Option<WpColorManagementSurfaceV1> {
+        if hdr && self.supports_hdr() {
+            let color_surface = self.manager.get_surface(surface, qh, surface.clone());
+            self.manager
+                .create_windows_scrgb(qh, ImageDescriptionData::Surface(color_surface.clone()));
+            return Some(color_surface);
+        }
+
+        if !self.parametric || !self.perceptual || !self.primaries.contains(&Primaries::Srgb) {
+            return None;
+        }
+        let tf = self.srgb_transfer_function()?;
+
+        let color_surface = self.manager.get_surface(surface, qh, surface.clone());
+
+        let creator = self.manager.create_parametric_creator(qh, ());
+        creator.set_tf_named(tf);
+        creator.set_primaries_named(Primaries::Srgb);
+        creator.create(qh, ImageDescriptionData::Surface(color_surface.clone()));
+
+        Some(color_surface)
+    }
+
+    /// Ask for the colorimetry of `output`
+    pub(super) fn watch_output(&self, qh: &QueueHandle<WaylandState>, output: &WlOutput) {
+        let color_output = self.manager.get_output(output, qh, output.clone());
+        color_output.get_image_description(qh, ImageDescriptionData::Output(output.clone()));
+    }
+}
+
+/// What a WpImageDescriptionV1 was created for
+pub(super) enum ImageDescriptionData {
+    /// Our own description of the content of a surface
+    Surface(WpColorManagementSurfaceV1),
+    /// The compositor's description of an output
+    Output(WlOutput),
+}
+
+/// What we learned about the color characteristics of an output
+#[derive(Debug, Default, Clone)]
+pub(super) struct OutputColorInfo {
+    pub transfer_function: Option<TransferFunction>,
+    pub primaries: Option<Primaries>,
+    /// In cd/m²
+    pub max_luminance: Option<u32>,
+    pub reference_luminance: Option<u32>,
+}
+
+impl OutputColorInfo {
+    pub fn is_hdr(&self) -> bool {
+        matches!(
+            self.transfer_function,
+            Some(TransferFunction::St2084Pq | TransferFunction::Hlg)
+        )
+    }
+}
+
+pub(super) struct ImageDescriptionInfoData {
+    output: WlOutput,
+    info: Mutex<OutputColorInfo>,
+}
+
+impl Dispatch<WpColorManagerV1, GlobalData> for WaylandState {
+    fn event(
+        state: &mut Self,
+        _proxy: &WpColorManagerV1,
+        event: <WpColorManagerV1 as Proxy>::Event,
+        _data: &GlobalData,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        let color_manager = match state.color_manager.as_mut() {
+            Some(color_manager) => color_manager,
+            None => return,
+        };
+        match event {
+            wp_color_manager_v1::Event::SupportedIntent {
+                render_intent: WEnum::Value(RenderIntent::Perceptual),
+            } => {
+                color_manager.perceptual = true;
+            }
+            wp_color_manager_v1::Event::SupportedFeature {
+                feature: WEnum::Value(Feature::Parametric),
+            } => {
+                color_manager.parametric = true;
+            }
+            wp_color_manager_v1::Event::SupportedFeature {
+                feature: WEnum::Value(Feature::WindowsScrgb),
+            } => {
+                color_manager.windows_scrgb = true;
+            }
+            wp_color_manager_v1::Event::SupportedTfNamed {
+                tf: WEnum::Value(tf),
+            } => {
+                color_manager.transfer_functions.push(tf);
+            }
+            wp_color_manager_v1::Event::SupportedPrimariesNamed {
+                primaries: WEnum::Value(primaries),
+            } => {
+                color_manager.primaries.push(primaries);
+            }
+            wp_color_manager_v1::Event::Done => {
+                log::debug!(
+                    "color manager: parametric={} perceptual={} scrgb={} tf={:?} primaries={:?}",
+                    color_manager.parametric,
+                    color_manager.perceptual,
+                    color_manager.windows_scrgb,
+                    color_manager.transfer_functions,
+                    color_manager.primaries
+                );
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<WpColorManagementOutputV1, WlOutput> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        proxy: &WpColorManagementOutputV1,
+        event: <WpColorManagementOutputV1 as Proxy>::Event,
+        output: &WlOutput,
+        _conn: &Connection,
+        qhandle: &QueueHandle<Self>,
+    ) {
+        if let wp_color_management_output_v1::Event::ImageDescriptionChanged = event {
+            proxy.get_image_description(qhandle, ImageDescriptionData::Output(output.clone()));
+        }
+    }
+}
+
+impl Dispatch<WpColorManagementSurfaceV1, WlSurface> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &WpColorManagementSurfaceV1,
+        _event: <WpColorManagementSurfaceV1 as Proxy>::Event,
+        _surface: &WlSurface,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        // Color management surface has no events
+    }
+}
+
+impl Dispatch<WpImageDescriptionCreatorParamsV1, ()> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &WpImageDescriptionCreatorParamsV1,
+        _event: <WpImageDescriptionCreatorParamsV1 as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        // Creator has no events
+    }
+}
+
+impl Dispatch<WpImageDescriptionV1, ImageDescriptionData> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        proxy: &WpImageDescriptionV1,
+        event: <WpImageDescriptionV1 as Proxy>::Event,
+        data: &ImageDescriptionData,
+        _conn: &Connection,
+        qhandle: &QueueHandle<Self>,
+    ) {
+        match event {
+            wp_image_description_v1::Event::Ready { .. } => match data {
+                ImageDescriptionData::Surface(color_surface) => {
+                    if color_surface.is_alive() {
+                        color_surface.set_image_description(proxy, RenderIntent::Perceptual);
+                        log::debug!("tagged surface color space");
+                    }
+                    // The surface holds on to the description for us
+                    proxy.destroy();
+                }
+                ImageDescriptionData::Output(output) => {
+                    proxy.get_information(
+                        qhandle,
+                        ImageDescriptionInfoData {
+                            output: output.clone(),
+                            info: Mutex::new(OutputColorInfo::default()),
+                        },
+                    );
+                    proxy.destroy();
+                }
+            },
+            wp_image_description_v1::Event::Failed { cause, msg } => {
+                log::warn!("image description failed: {cause:?} {msg}");
+                proxy.destroy();
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<WpImageDescriptionInfoV1, ImageDescriptionInfoData> for WaylandState {
+    fn event(
+        state: &mut Self,
+        _proxy: &WpImageDescriptionInfoV1,
+        event: <WpImageDescriptionInfoV1 as Proxy>::Event,
+        data: &ImageDescriptionInfoData,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        let mut info = data.info.lock().unwrap();
+        match event {
+            wp_image_description_info_v1::Event::TfNamed {
+                tf: WEnum::Value(tf),
+            } => {
+                info.transfer_function.replace(tf);
+            }
+            wp_image_description_info_v1::Event::PrimariesNamed {
+                primaries: WEnum::Value(primaries),
+            } => {
+                info.primaries.replace(primaries);
+            }
+            wp_image_description_info_v1::Event::Luminances {
+                max_lum,
+                reference_lum,
+                ..
+            } => {
+                info.max_luminance.replace(max_lum);
+                info.reference_luminance.replace(reference_lum);
+            }
+            wp_image_description_info_v1::Event::Done => {
+                let name = state
+                    .output
+                    .info(&data.output)
+                    .and_then(|i| i.name)
+                    .unwrap_or_else(|| format!("{:?}", data.output.id()));
+                log::info!(
+                    "output {name} colorimetry: hdr={} {:?}",
+                    info.is_hdr(),
+                    *info
+                );
+                state.output_color.insert(data.output.id(), info.clone());
+                // Windows on this output may have moved into or
+                // out of HDR
+                for window_id in state.windows.borrow().keys() {
+                    WaylandConnection::with_window_inner(*window_id, |inner| {
+                        inner.update_hdr_output();
+                        Ok(())
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+}
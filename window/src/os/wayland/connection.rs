@@ -15,6 +15,7 @@ use crate::screen::{ScreenInfo, Screens};
 use crate::spawn::SPAWN_QUEUE;
 use crate::{Appearance, Connection, ConnectionOps, ScreenRect};
 
+use super::activation::ActivationPurpose;
 use super::state::WaylandState;
 use super::WaylandWindowInner;
 
@@ -188,6 +189,82 @@ impl ConnectionOps for WaylandConnection {
         Appearance::Light
     }
 
+    fn take_activation_token(&self) -> Option<String> {
+        let token = self
+            .wayland_state
+            .borrow_mut()
+            .spawn_activation_token
+            .take();
+        if token.is_some() {
+            // Tokens are single use; get another one ready
+            let qh = self.event_queue.borrow().handle();
+            self.wayland_state
+                .borrow()
+                .request_activation_token(&qh, ActivationPurpose::Spawn);
+        }
+        token
+    }
+
+    fn supports_solid_background(&self) -> bool {
+        let state = self.wayland_state.borrow();
+        state.single_pixel_buffer_manager.is_some() && state.viewporter.is_some()
+    }
+
+    fn supports_modal_dialogs(&self) -> bool {
+        self.wayland_state.borrow().xdg_wm_dialog.is_some()
+    }
+
+    fn toplevels(&self) -> anyhow::Result<Vec<crate::toplevel::ToplevelInfo>> {
+        match &self.wayland_state.borrow().foreign_toplevel {
+            Some(toplevels) => Ok(toplevels.toplevels()),
+            None => anyhow::bail!("compositor doesn't support the foreign toplevel protocols"),
+        }
+    }
+
+    fn outputs(&self) -> anyhow::Result<Vec<crate::output::OutputInfo>> {
+        match &self.wayland_state.borrow().output_manager {
+            Some(output_manager) => Ok(output_manager.outputs()),
+            None => anyhow::bail!(
+                "wlr-output-management is not available; \
+                 check that enable_zwlr_output_manager is set"
+            ),
+        }
+    }
+
+    fn scanout_info(&self) -> anyhow::Result<crate::scanout::ScanoutInfo> {
+        let needs_roundtrip = {
+            let state = self.wayland_state.borrow();
+            if state.dmabuf_default_feedback.is_none() {
+                anyhow::bail!("compositor doesn't support linux-dmabuf feedback");
+            }
+            state.dmabuf_default_summary.is_none() && state.windows.borrow().is_empty()
+        };
+        if needs_roundtrip {
+            // We've only just connected and haven't yet processed the
+            // feedback.  This is only safe to do before we have any
+            // windows, as dispatching may need to borrow them
+            let mut state = self.wayland_state.borrow_mut();
+            self.event_queue.borrow_mut().roundtrip(&mut state)?;
+        }
+
+        let state = self.wayland_state.borrow();
+        let summary = state.dmabuf_default_summary.unwrap_or_default();
+        let mut info = crate::scanout::ScanoutInfo {
+            main_device: Some(summary.main_device_name()),
+            formats: summary.formats,
+            scanout_formats: summary.scanout_formats,
+            ..Default::default()
+        };
+        for window in state.windows.borrow().values() {
+            if let Ok(inner) = window.try_borrow() {
+                info.scanout_formats = info.scanout_formats.max(inner.scanout_formats);
+                info.frames_presented += inner.frames_presented;
+                info.frames_zero_copy += inner.frames_zero_copy;
+            }
+        }
+        Ok(info)
+    }
+
     fn screens(&self) -> anyhow::Result<crate::screen::Screens> {
         log::trace!("Getting screens for wayland connection");
 
@@ -213,12 +290,15 @@ impl ConnectionOps for WaylandConnection {
                 None => format!("{} {}", info.model, info.make),
             };
 
-            let (width, height) = info
-                .modes
-                .iter()
-                .find(|mode| mode.current)
+            let current_mode = info.modes.iter().find(|mode| mode.current);
+            let (width, height) = current_mode
                 .map(|mode| mode.dimensions)
                 .unwrap_or((info.physical_size.0, info.physical_size.1));
+            // refresh_rate is in millihertz
+            let max_fps = current_mode
+                .filter(|mode| mode.refresh_rate > 0)
+                .map(|mode| (mode.refresh_rate as usize + 500) / 1000)
+                .filter(|&fps| fps > 0);
 
             let rect = euclid::rect(
                 info.location.0 as isize,
@@ -229,6 +309,18 @@ impl ConnectionOps for WaylandConnection {
 
             let scale = info.scale_factor as f64;
 
+            // Populated via xdg-output, when available
+            let logical_rect = match (info.logical_position, info.logical_size) {
+                (Some((x, y)), Some((width, height))) => Some(euclid::rect(
+                    x as isize,
+                    y as isize,
+                    width as isize,
+                    height as isize,
+                )),
+                _ => None,
+            };
+            let non_empty = |s: &str| Some(s.to_string()).filter(|s| !s.is_empty());
+
             // FIXME: teach this how to resolve dpi_by_screen once
             // dispatch_pending_event knows how to do the same
             let effective_dpi = Some(config.dpi.unwrap_or(scale * crate::DEFAULT_DPI));
@@ -240,8 +332,17 @@ impl ConnectionOps for WaylandConnection {
                     name,
                     rect,
                     scale,
-                    max_fps: None,
+                    max_fps,
                     effective_dpi,
+                    description: info.description.clone(),
+                    make: non_empty(&info.make),
+                    model: non_empty(&info.model),
+                    physical_size_mm: Some((
+                        info.physical_size.0 as isize,
+                        info.physical_size.1 as isize,
+                    ))
+                    .filter(|&(w, h)| w > 0 && h > 0),
+                    logical_rect,
                 },
             );
         }
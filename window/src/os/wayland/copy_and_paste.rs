@@ -3,30 +3,25 @@ use smithay_client_toolkit as toolkit;
 use std::io::Write;
 use std::os::fd::AsRawFd;
 use std::sync::{Arc, Mutex};
-use toolkit::data_device_manager::data_offer::SelectionOffer;
 use toolkit::data_device_manager::{ReadPipe, WritePipe};
 use toolkit::primary_selection::device::PrimarySelectionDeviceHandler;
 use toolkit::primary_selection::selection::PrimarySelectionSourceHandler;
 use wayland_protocols::wp::primary_selection::zv1::client::zwp_primary_selection_device_v1::ZwpPrimarySelectionDeviceV1;
 use wayland_protocols::wp::primary_selection::zv1::client::zwp_primary_selection_source_v1::ZwpPrimarySelectionSourceV1;
 
-use crate::{Clipboard, ConnectionOps};
+use crate::{Clipboard, ClipboardContents, ConnectionOps};
 
-use super::data_device::TEXT_MIME_TYPE;
+use super::data_device::{
+    HTML_MIME_TYPE, PLAIN_TEXT_MIME_TYPE, PNG_MIME_TYPE, TEXT_MIME_TYPE, UTF8_STRING_MIME_TYPE,
+};
 use super::state::WaylandState;
 
-#[derive(Default)]
-pub struct CopyAndPaste {
-    data_offer: Option<SelectionOffer>,
-}
-
-impl std::fmt::Debug for CopyAndPaste {
-    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
-        fmt.debug_struct("CopyAndPaste")
-            .field("data_offer", &self.data_offer.is_some())
-            .finish()
-    }
-}
+/// Clipboard operations on behalf of a window.
+/// The clipboard and primary selection belong to a seat rather than
+/// to a window, so these act on the seat that most recently sent us
+/// input.
+#[derive(Default, Debug)]
+pub struct CopyAndPaste {}
 
 impl CopyAndPaste {
     pub(super) fn create() -> Arc<Mutex<Self>> {
@@ -34,13 +29,35 @@ impl CopyAndPaste {
     }
 
     pub(super) fn get_clipboard_data(&mut self, clipboard: Clipboard) -> anyhow::Result<ReadPipe> {
+        self.receive_clipboard(clipboard, TEXT_MIME_TYPE)?
+            .ok_or_else(|| anyhow!("clipboard doesn't hold text"))
+    }
+
+    /// Returns a pipe from which PNG data can be read, or None if the
+    /// clipboard doesn't hold an image
+    pub(super) fn get_clipboard_image(
+        &mut self,
+        clipboard: Clipboard,
+    ) -> anyhow::Result<Option<ReadPipe>> {
+        self.receive_clipboard(clipboard, PNG_MIME_TYPE)
+    }
+
+    fn receive_clipboard(
+        &mut self,
+        clipboard: Clipboard,
+        mime_type: &str,
+    ) -> anyhow::Result<Option<ReadPipe>> {
         let conn = crate::Connection::get().unwrap().wayland();
         let wayland_state = conn.wayland_state.borrow();
+        let seat = wayland_state
+            .active_seat()
+            .ok_or_else(|| anyhow!("no seat"))?;
         let primary_selection = if let Clipboard::PrimarySelection = clipboard {
-            wayland_state.primary_selection_device.as_ref()
+            seat.primary_selection_device.as_ref()
         } else {
             None
         };
+        let has_mime_type = |mime_types: &[String]| mime_types.iter().any(|m| m == mime_type);
 
         match primary_selection {
             Some(primary_selection) => {
@@ -48,68 +65,108 @@ impl CopyAndPaste {
                     .data()
                     .selection_offer()
                     .ok_or_else(|| anyhow!("no primary selection offer"))?;
-                let pipe = offer.receive(TEXT_MIME_TYPE.to_string())?;
-                Ok(pipe)
+                if !offer.with_mime_types(has_mime_type) {
+                    return Ok(None);
+                }
+                let pipe = offer.receive(mime_type.to_string())?;
+                Ok(Some(pipe))
             }
             None => {
-                let offer = self
-                    .data_offer
+                let offer = seat
+                    .data_device
                     .as_ref()
+                    .and_then(|data_device| data_device.data().selection_offer())
                     .ok_or_else(|| anyhow!("no data offer"))?;
-                let pipe = offer.receive(TEXT_MIME_TYPE.to_string())?;
-                Ok(pipe)
+                if !offer.with_mime_types(has_mime_type) {
+                    return Ok(None);
+                }
+                let pipe = offer.receive(mime_type.to_string())?;
+                Ok(Some(pipe))
             }
         }
     }
 
-    pub(super) fn set_clipboard_data(&mut self, clipboard: Clipboard, data: String) {
+    pub(super) fn set_clipboard_data(&mut self, clipboard: Clipboard, data: ClipboardContents) {
         let conn = crate::Connection::get().unwrap().wayland();
         let qh = conn.event_queue.borrow().handle();
         let mut wayland_state = conn.wayland_state.borrow_mut();
-        let last_serial = *wayland_state.last_serial.borrow();
+        let seat = match wayland_state.active_seat() {
+            Some(seat) => seat,
+            None => {
+                log::warn!("set_clipboard_data: no seat");
+                return;
+            }
+        };
+        let last_serial = seat.last_serial;
 
         let primary_selection = if let Clipboard::PrimarySelection = clipboard {
-            wayland_state.primary_selection_device.as_ref()
+            seat.primary_selection_device.as_ref()
         } else {
             None
         };
 
+        let mime_types = offered_mime_types(&data);
+
         match primary_selection {
             Some(primary_selection) => {
                 let manager = wayland_state.primary_selection_manager.as_ref().unwrap();
-                let source = manager.create_selection_source(&qh, [TEXT_MIME_TYPE]);
+                let source = manager.create_selection_source(&qh, mime_types);
                 source.set_selection(&primary_selection, last_serial);
                 wayland_state
                     .primary_selection_source
                     .replace((source, data));
             }
             None => {
-                let data_device = &wayland_state.data_device;
+                let data_device = match seat.data_device.as_ref() {
+                    Some(data_device) => data_device,
+                    None => return,
+                };
                 let source = wayland_state
                     .data_device_manager_state
-                    .create_copy_paste_source(&qh, vec![TEXT_MIME_TYPE]);
-                source.set_selection(data_device.as_ref().unwrap(), last_serial);
+                    .create_copy_paste_source(&qh, mime_types);
+                source.set_selection(data_device, last_serial);
                 wayland_state.copy_paste_source.replace((source, data));
             }
         }
     }
+}
 
-    pub(super) fn confirm_selection(&mut self, offer: SelectionOffer) {
-        self.data_offer.replace(offer);
+/// Returns the mime types that we can offer for `contents`
+fn offered_mime_types(contents: &ClipboardContents) -> Vec<&'static str> {
+    let mut mime_types = vec![];
+    if !contents.text.is_empty() || contents.png.is_none() {
+        mime_types.extend_from_slice(&[
+            TEXT_MIME_TYPE,
+            PLAIN_TEXT_MIME_TYPE,
+            UTF8_STRING_MIME_TYPE,
+        ]);
     }
+    if contents.html.is_some() {
+        mime_types.push(HTML_MIME_TYPE);
+    }
+    if contents.png.is_some() {
+        mime_types.push(PNG_MIME_TYPE);
+    }
+    mime_types
 }
 
-impl WaylandState {
-    pub(super) fn resolve_copy_and_paste(&mut self) -> Option<Arc<Mutex<CopyAndPaste>>> {
-        let active_surface_id = self.active_surface_id.borrow();
-        let active_surface_id = active_surface_id.as_ref()?;
-        let pending = self.surface_to_pending.get(&active_surface_id)?;
-        Some(Arc::clone(&pending.lock().unwrap().copy_and_paste))
+/// Returns the data that corresponds to the requested mime type
+pub(super) fn contents_for_mime<'a>(
+    contents: &'a ClipboardContents,
+    mime: &str,
+) -> Option<&'a [u8]> {
+    match mime {
+        TEXT_MIME_TYPE | PLAIN_TEXT_MIME_TYPE | UTF8_STRING_MIME_TYPE => {
+            Some(contents.text.as_bytes())
+        }
+        HTML_MIME_TYPE => contents.html.as_ref().map(|html| html.as_bytes()),
+        PNG_MIME_TYPE => contents.png.as_deref(),
+        _ => None,
     }
 }
 
-pub(super) fn write_selection_to_pipe(fd: WritePipe, text: &str) {
-    if let Err(e) = write_pipe_with_timeout(fd, text.as_bytes()) {
+pub(super) fn write_selection_to_pipe(fd: WritePipe, data: &[u8]) {
+    if let Err(e) = write_pipe_with_timeout(fd, data) {
         log::error!("while sending primary selection to pipe: {}", e);
     }
 }
@@ -171,15 +228,13 @@ impl PrimarySelectionSourceHandler for WaylandState {
         mime: String,
         write_pipe: toolkit::data_device_manager::WritePipe,
     ) {
-        if mime != TEXT_MIME_TYPE {
-            return;
-        };
-
-        if let Some((ps_source, data)) = &self.primary_selection_source {
+        if let Some((ps_source, contents)) = &self.primary_selection_source {
             if ps_source.inner() != source {
                 return;
             }
-            write_selection_to_pipe(write_pipe, data);
+            if let Some(data) = contents_for_mime(contents, &mime) {
+                write_selection_to_pipe(write_pipe, data);
+            }
         }
     }
 
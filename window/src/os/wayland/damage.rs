@@ -0,0 +1,203 @@
+//! Coalescing the damage reported by the gui before we pass it on to
+//! the compositor.
+//!
+//! The gui reports one rectangle per repainted line, which can mean
+//! hundreds of thin rectangles for a busy frame. Some compositors handle
+//! that worse than damage for the whole surface, so we merge them into
+//! a handful of rectangles first.
+
+use crate::BufferTransform;
+use euclid::default::{Box2D, Point2D};
+
+/// The most rectangles that we'll report for a single frame
+const MAX_DAMAGE_RECTS: usize = 16;
+
+/// If the damage covers more than this fraction of the surface, we
+/// report the whole surface instead
+const FULL_DAMAGE_FRACTION: f64 = 0.75;
+
+fn area(b: &Box2D<i32>) -> i64 {
+    if b.is_empty() {
+        0
+    } else {
+        b.width() as i64 * b.height() as i64
+    }
+}
+
+/// How much area merging `a` and `b` into their bounding box would
+/// damage that neither of them covers
+fn merge_waste(a: &Box2D<i32>, b: &Box2D<i32>) -> i64 {
+    let overlap = a.intersection(b).map_or(0, |i| area(&i));
+    area(&a.union(b)) - (area(a) + area(b) - overlap)
+}
+
+/// Merges `rects`, given as (x, y, width, height) in pixels, into at
+/// most MAX_DAMAGE_RECTS rectangles that together cover all of them.
+/// Rectangles are clipped to a buffer of `width` x `height` pixels.
+pub(super) fn coalesce_damage(
+    rects: &[(i32, i32, i32, i32)],
+    width: i32,
+    height: i32,
+) -> Vec<Box2D<i32>> {
+    let bounds = Box2D::new(Point2D::new(0, 0), Point2D::new(width, height));
+
+    let mut boxes: Vec<Box2D<i32>> = rects
+        .iter()
+        .filter_map(|&(x, y, w, h)| {
+            Box2D::new(Point2D::new(x, y), Point2D::new(x + w, y + h)).intersection(&bounds)
+        })
+        .filter(|b| !b.is_empty())
+        .collect();
+    boxes.sort_by_key(|b| (b.min.y, b.min.x));
+
+    // Lines are reported top to bottom, so runs of adjacent lines
+    // of the same width merge without damaging anything extra
+    let mut merged: Vec<Box2D<i32>> = Vec::with_capacity(boxes.len());
+    for b in boxes {
+        match merged.last_mut() {
+            Some(last) if merge_waste(last, &b) == 0 => *last = last.union(&b),
+            _ => merged.push(b),
+        }
+    }
+
+    // Then trade some precision for fewer rectangles by merging the
+    // vertically neighbouring pair that wastes the least
+    while merged.len() > MAX_DAMAGE_RECTS {
+        let idx = (0..merged.len() - 1)
+            .min_by_key(|&i| merge_waste(&merged[i], &merged[i + 1]))
+            .expect("more than MAX_DAMAGE_RECTS entries");
+        let next = merged.remove(idx + 1);
+        merged[idx] = merged[idx].union(&next);
+    }
+
+    let damaged: i64 = merged.iter().map(area).sum();
+    if damaged as f64 > area(&bounds) as f64 * FULL_DAMAGE_FRACTION {
+        return vec![bounds];
+    }
+
+    merged
+}
+
+/// Converts a rectangle in buffer pixels to surface-local coordinates,
+/// for compositors that only support wl_surface.damage.
+/// Under fractional scaling the edges don't generally fall on surface
+/// coordinates, so we round outwards to be sure to cover the damage.
+pub(super) fn buffer_to_surface(b: &Box2D<i32>, dpi_factor: f64) -> Box2D<i32> {
+    Box2D::new(
+        Point2D::new(
+            (b.min.x as f64 / dpi_factor).floor() as i32,
+            (b.min.y as f64 / dpi_factor).floor() as i32,
+        ),
+        Point2D::new(
+            (b.max.x as f64 / dpi_factor).ceil() as i32,
+            (b.max.y as f64 / dpi_factor).ceil() as i32,
+        ),
+    )
+}
+
+/// Maps a rectangle in the pixels of a window that is `width` x `height`
+/// to the buffer, which differs when the buffer is rotated or flipped
+pub(super) fn window_to_buffer(
+    b: &Box2D<i32>,
+    transform: BufferTransform,
+    width: i32,
+    height: i32,
+) -> Box2D<i32> {
+    let (width, height) = (width as f32, height as f32);
+    let (x0, y0) = transform.transform_point(b.min.x as f32, b.min.y as f32, width, height);
+    let (x1, y1) = transform.transform_point(b.max.x as f32, b.max.y as f32, width, height);
+    Box2D::new(
+        Point2D::new(x0.min(x1) as i32, y0.min(y1) as i32),
+        Point2D::new(x0.max(x1) as i32, y0.max(y1) as i32),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rect(x0: i32, y0: i32, x1: i32, y1: i32) -> Box2D<i32> {
+        Box2D::new(Point2D::new(x0, y0), Point2D::new(x1, y1))
+    }
+
+    #[test]
+    fn adjacent_lines_merge() {
+        assert_eq!(
+            coalesce_damage(&[(0, 0, 100, 10), (0, 10, 100, 10)], 1000, 1000),
+            vec![rect(0, 0, 100, 20)]
+        );
+    }
+
+    #[test]
+    fn overlapping_rects() {
+        // A rect that is contained by another adds nothing
+        assert_eq!(
+            coalesce_damage(&[(0, 0, 20, 20), (5, 5, 10, 10)], 1000, 1000),
+            vec![rect(0, 0, 20, 20)]
+        );
+        // but a partial overlap would damage extra area if merged
+        assert_eq!(
+            coalesce_damage(&[(0, 0, 10, 10), (5, 5, 10, 10)], 1000, 1000),
+            vec![rect(0, 0, 10, 10), rect(5, 5, 15, 15)]
+        );
+    }
+
+    #[test]
+    fn disjoint_rects() {
+        assert_eq!(
+            coalesce_damage(&[(50, 50, 10, 10), (0, 0, 10, 10)], 1000, 1000),
+            vec![rect(0, 0, 10, 10), rect(50, 50, 60, 60)]
+        );
+    }
+
+    #[test]
+    fn clipped_to_buffer() {
+        assert_eq!(
+            coalesce_damage(&[(-5, 990, 20, 20), (2000, 0, 10, 10)], 1000, 1000),
+            vec![rect(0, 990, 15, 1000)]
+        );
+    }
+
+    #[test]
+    fn limits_rect_count() {
+        let rects: Vec<_> = (0..40).map(|i| (0, i * 20, 10, 10)).collect();
+        let damage = coalesce_damage(&rects, 1000, 1000);
+        assert_eq!(damage.len(), MAX_DAMAGE_RECTS);
+        for &(x, y, w, h) in &rects {
+            let r = rect(x, y, x + w, y + h);
+            assert!(damage.iter().any(|d| d.contains_box(&r)), "{:?}", r);
+        }
+    }
+
+    #[test]
+    fn mostly_damaged_is_full() {
+        assert_eq!(
+            coalesce_damage(&[(0, 0, 1000, 800)], 1000, 1000),
+            vec![rect(0, 0, 1000, 1000)]
+        );
+    }
+
+    #[test]
+    fn surface_coords() {
+        let b = rect(10, 10, 30, 31);
+        assert_eq!(buffer_to_surface(&b, 1.0), b);
+        assert_eq!(buffer_to_surface(&b, 2.0), rect(5, 5, 15, 16));
+        // Fractional edges are rounded outwards
+        assert_eq!(
+            buffer_to_surface(&rect(10, 10, 20, 20), 1.5),
+            rect(6, 6, 14, 14)
+        );
+    }
+
+    #[test]
+    fn transformed_buffer() {
+        let b = rect(10, 0, 30, 5);
+        let to_buffer = |transform| window_to_buffer(&b, transform, 100, 50);
+        assert_eq!(to_buffer(BufferTransform::Normal), b);
+        assert_eq!(to_buffer(BufferTransform::Rotate90), rect(0, 70, 5, 90));
+        assert_eq!(to_buffer(BufferTransform::Rotate180), rect(70, 45, 90, 50));
+        assert_eq!(to_buffer(BufferTransform::Rotate270), rect(45, 10, 50, 30));
+        assert_eq!(to_buffer(BufferTransform::Flipped), rect(70, 0, 90, 5));
+        assert_eq!(to_buffer(BufferTransform::Flipped90), rect(0, 10, 5, 30));
+    }
+}
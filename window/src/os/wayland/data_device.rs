@@ -3,20 +3,28 @@ use smithay_client_toolkit::data_device_manager::data_offer::DataOfferHandler;
 use smithay_client_toolkit::data_device_manager::data_source::DataSourceHandler;
 use smithay_client_toolkit::data_device_manager::WritePipe;
 use smithay_client_toolkit::reexports::client::protocol::wl_data_device::WlDataDevice;
+use std::sync::Mutex;
 use wayland_client::protocol::wl_data_device_manager::DndAction;
+use wayland_client::protocol::wl_data_source::WlDataSource;
 use wayland_client::protocol::wl_surface::WlSurface;
 use wayland_client::Proxy;
 
 use crate::wayland::drag_and_drop::SurfaceAndOffer;
-use crate::wayland::pointer::PointerUserData;
+use crate::wayland::pointer::{PointerState, PointerUserData};
 use crate::wayland::SurfaceUserData;
 
-use super::copy_and_paste::write_selection_to_pipe;
+use super::copy_and_paste::{contents_for_mime, write_selection_to_pipe};
 use super::drag_and_drop::{DragAndDrop, SurfaceAndPipe};
 use super::state::WaylandState;
 
 pub(super) const TEXT_MIME_TYPE: &str = "text/plain;charset=utf-8";
 pub(super) const URI_MIME_TYPE: &str = "text/uri-list";
+/// Some drop targets only look for plain text without a charset
+pub(super) const PLAIN_TEXT_MIME_TYPE: &str = "text/plain";
+/// Used by X11 applications running under Xwayland
+pub(super) const UTF8_STRING_MIME_TYPE: &str = "UTF8_STRING";
+pub(super) const HTML_MIME_TYPE: &str = "text/html";
+pub(super) const PNG_MIME_TYPE: &str = "image/png";
 
 impl DataDeviceHandler for WaylandState {
     fn enter(
@@ -24,19 +32,25 @@ impl DataDeviceHandler for WaylandState {
         _conn: &wayland_client::Connection,
         _qh: &wayland_client::QueueHandle<Self>,
         data_device: &WlDataDevice,
-        _x: f64,
-        _y: f64,
+        x: f64,
+        y: f64,
         _surface: &WlSurface,
     ) {
-        let data = match self.data_device {
-            Some(ref dv) if dv.inner() == data_device => dv.data(),
-            _ => {
+        let seat = match self.seat_for_data_device(data_device) {
+            Some(seat) => seat,
+            None => {
                 log::warn!("No existing device manager for {:?}", data_device);
                 return;
             }
         };
 
-        let offer = data.drag_offer().unwrap();
+        let offer = seat
+            .data_device
+            .as_ref()
+            .unwrap()
+            .data()
+            .drag_offer()
+            .unwrap();
 
         offer.with_mime_types(|mime_types| {
             log::trace!(
@@ -46,40 +60,33 @@ impl DataDeviceHandler for WaylandState {
             );
 
             if let Some(mime) = mime_types.iter().find(|s| *s == URI_MIME_TYPE) {
-                offer.accept_mime_type(*self.last_serial.borrow(), Some(mime.clone()));
+                offer.accept_mime_type(seat.last_serial, Some(mime.clone()));
             }
         });
 
         offer.set_actions(DndAction::None | DndAction::Copy, DndAction::None);
 
-        let pointer = self.pointer.as_mut().unwrap();
-        let mut pstate = pointer
-            .pointer()
-            .data::<PointerUserData>()
-            .unwrap()
-            .state
-            .lock()
-            .unwrap();
+        let Some(pstate) = self.drag_pointer_state(data_device) else {
+            return;
+        };
+        let mut pstate = pstate.lock().unwrap();
 
         let window_id = SurfaceUserData::from_wl(&offer.surface).window_id;
 
         pstate.drag_and_drop.offer = Some(SurfaceAndOffer { window_id, offer });
+        pstate.drag_and_drop.position = (x, y);
     }
 
     fn leave(
         &mut self,
         _conn: &wayland_client::Connection,
         _qh: &wayland_client::QueueHandle<Self>,
-        _data_device: &WlDataDevice,
+        data_device: &WlDataDevice,
     ) {
-        let pointer = self.pointer.as_mut().unwrap();
-        let mut pstate = pointer
-            .pointer()
-            .data::<PointerUserData>()
-            .unwrap()
-            .state
-            .lock()
-            .unwrap();
+        let Some(pstate) = self.drag_pointer_state(data_device) else {
+            return;
+        };
+        let mut pstate = pstate.lock().unwrap();
         if let Some(SurfaceAndOffer { offer, .. }) = pstate.drag_and_drop.offer.take() {
             offer.destroy();
         }
@@ -89,54 +96,48 @@ impl DataDeviceHandler for WaylandState {
         &mut self,
         _conn: &wayland_client::Connection,
         _qh: &wayland_client::QueueHandle<Self>,
-        _data_device: &WlDataDevice,
-        _x: f64,
-        _y: f64,
+        data_device: &WlDataDevice,
+        x: f64,
+        y: f64,
     ) {
+        let Some(pstate) = self.drag_pointer_state(data_device) else {
+            return;
+        };
+        let mut pstate = pstate.lock().unwrap();
+        pstate.drag_and_drop.position = (x, y);
     }
 
     fn selection(
         &mut self,
         _conn: &wayland_client::Connection,
         _qh: &wayland_client::QueueHandle<Self>,
-        data_device: &WlDataDevice,
+        _data_device: &WlDataDevice,
     ) {
-        let offer = match self.data_device {
-            Some(ref dv) if dv.inner() == data_device => dv.data().selection_offer(),
-            _ => {
-                return;
-            }
-        };
-        if let Some(offer) = offer {
-            if !offer.with_mime_types(|mime_types| mime_types.iter().any(|s| s == TEXT_MIME_TYPE)) {
-                return;
-            }
-
-            if let Some(copy_and_paste) = self.resolve_copy_and_paste() {
-                copy_and_paste.lock().unwrap().confirm_selection(offer);
-            }
-        }
+        // The DataDevice of each seat keeps track of its own selection
+        // offer; we consult the one belonging to the active seat when
+        // we paste.
     }
 
     fn drop_performed(
         &mut self,
         _conn: &wayland_client::Connection,
         _qh: &wayland_client::QueueHandle<Self>,
-        _data_device: &WlDataDevice,
+        data_device: &WlDataDevice,
     ) {
-        let pointer = self.pointer.as_mut().unwrap();
-        let mut pstate = pointer
-            .pointer()
-            .data::<PointerUserData>()
-            .unwrap()
-            .state
-            .lock()
-            .unwrap();
+        let Some(pstate) = self.drag_pointer_state(data_device) else {
+            return;
+        };
+        let mut pstate = pstate.lock().unwrap();
         let drag_and_drop = &mut pstate.drag_and_drop;
-        if let Some(SurfaceAndPipe { window_id, read }) = drag_and_drop.create_pipe_for_drop() {
+        if let Some(SurfaceAndPipe {
+            window_id,
+            read,
+            position,
+        }) = drag_and_drop.create_pipe_for_drop()
+        {
             std::thread::spawn(move || {
-                if let Some(paths) = DragAndDrop::read_paths_from_pipe(read) {
-                    DragAndDrop::dispatch_dropped_files(window_id, paths);
+                if let Some(urls) = DragAndDrop::read_urls_from_pipe(read) {
+                    DragAndDrop::dispatch_dropped_urls(window_id, urls, position);
                 }
             });
         }
@@ -165,7 +166,29 @@ impl DataOfferHandler for WaylandState {
     }
 }
 
-// We seem to ignore all events other than sending_request and cancelled
+impl WaylandState {
+    /// Drag and drop state is kept alongside the pointer of the seat
+    /// to which `data_device` belongs
+    fn drag_pointer_state(&self, data_device: &WlDataDevice) -> Option<&Mutex<PointerState>> {
+        let pointer = self.seat_for_data_device(data_device)?.pointer.as_ref()?;
+        Some(&pointer.pointer().data::<PointerUserData>()?.state)
+    }
+
+    /// If `source` is the source of a drag that we started, release it,
+    /// returning true.
+    fn take_drag_source(&mut self, source: &WlDataSource) -> bool {
+        match &self.drag_source {
+            Some((drag_source, _)) if drag_source.inner() == source => {
+                // Dropping the DragSource destroys the wl_data_source
+                self.drag_source.take();
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+// We ignore all events other than send_request, cancelled and dnd_finished
 impl DataSourceHandler for WaylandState {
     fn accept_mime(
         &mut self,
@@ -184,15 +207,31 @@ impl DataSourceHandler for WaylandState {
         mime: String,
         fd: WritePipe,
     ) {
-        if mime != TEXT_MIME_TYPE {
-            return;
+        if let Some((drag_source, data)) = &self.drag_source {
+            if drag_source.inner() == source {
+                match mime.as_str() {
+                    TEXT_MIME_TYPE | PLAIN_TEXT_MIME_TYPE => {
+                        write_selection_to_pipe(fd, data.text.as_bytes())
+                    }
+                    URI_MIME_TYPE => {
+                        // text/uri-list lines are terminated by CRLF
+                        let mut uri_list = data.uris.join("\r\n");
+                        uri_list.push_str("\r\n");
+                        write_selection_to_pipe(fd, uri_list.as_bytes());
+                    }
+                    _ => {}
+                }
+                return;
+            }
         }
 
-        if let Some((cp_source, data)) = &self.copy_paste_source {
+        if let Some((cp_source, contents)) = &self.copy_paste_source {
             if cp_source.inner() != source {
                 return;
             }
-            write_selection_to_pipe(fd, data);
+            if let Some(data) = contents_for_mime(contents, &mime) {
+                write_selection_to_pipe(fd, data);
+            }
         }
     }
 
@@ -202,6 +241,9 @@ impl DataSourceHandler for WaylandState {
         _qh: &wayland_client::QueueHandle<Self>,
         source: &wayland_client::protocol::wl_data_source::WlDataSource,
     ) {
+        if self.take_drag_source(source) {
+            return;
+        }
         self.copy_paste_source.take();
         source.destroy();
     }
@@ -218,8 +260,9 @@ impl DataSourceHandler for WaylandState {
         &mut self,
         _conn: &wayland_client::Connection,
         _qh: &wayland_client::QueueHandle<Self>,
-        _source: &wayland_client::protocol::wl_data_source::WlDataSource,
+        source: &wayland_client::protocol::wl_data_source::WlDataSource,
     ) {
+        self.take_drag_source(source);
     }
 
     fn action(
@@ -0,0 +1,111 @@
+//! Tracking linux-dmabuf feedback, so that we can tell whether
+//! our surfaces are candidates for direct scanout.
+//!
+//! Our GPU buffers are allocated by the EGL or Vulkan driver, which
+//! requests its own feedback and picks formats and modifiers from it,
+//! so we only use what we receive here for diagnostics.
+
+use smithay_client_toolkit::delegate_dmabuf;
+use smithay_client_toolkit::dmabuf::{DmabufFeedback, DmabufHandler, DmabufState};
+use smithay_client_toolkit::reexports::protocols::wp::linux_dmabuf::zv1::client::zwp_linux_buffer_params_v1::ZwpLinuxBufferParamsV1;
+use smithay_client_toolkit::reexports::protocols::wp::linux_dmabuf::zv1::client::zwp_linux_dmabuf_feedback_v1::{
+    TrancheFlags, ZwpLinuxDmabufFeedbackV1,
+};
+use wayland_client::protocol::wl_buffer::WlBuffer;
+use wayland_client::{Connection, QueueHandle, WEnum};
+
+use super::state::WaylandState;
+
+/// The parts of a dmabuf feedback that we care about
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(super) struct FeedbackSummary {
+    pub(super) main_device: libc::dev_t,
+    pub(super) formats: usize,
+    pub(super) scanout_formats: usize,
+}
+
+impl FeedbackSummary {
+    fn new(feedback: &DmabufFeedback) -> Self {
+        let scanout_formats = feedback
+            .tranches()
+            .iter()
+            .filter(|tranche| match tranche.flags {
+                WEnum::Value(flags) => flags.contains(TrancheFlags::Scanout),
+                WEnum::Unknown(_) => false,
+            })
+            .map(|tranche| tranche.formats.len())
+            .sum();
+        Self {
+            main_device: feedback.main_device(),
+            formats: feedback.format_table().len(),
+            scanout_formats,
+        }
+    }
+
+    pub(super) fn main_device_name(&self) -> String {
+        format!(
+            "{}:{}",
+            libc::major(self.main_device),
+            libc::minor(self.main_device)
+        )
+    }
+}
+
+impl DmabufHandler for WaylandState {
+    fn dmabuf_state(&mut self) -> &mut DmabufState {
+        &mut self.dmabuf
+    }
+
+    fn dmabuf_feedback(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        proxy: &ZwpLinuxDmabufFeedbackV1,
+        feedback: DmabufFeedback,
+    ) {
+        let summary = FeedbackSummary::new(&feedback);
+        log::debug!("dmabuf feedback {summary:?}");
+
+        if self.dmabuf_default_feedback.as_ref() == Some(proxy) {
+            self.dmabuf_default_summary.replace(summary);
+            return;
+        }
+
+        for window in self.windows.borrow().values() {
+            let mut inner = window.borrow_mut();
+            if inner.dmabuf_feedback.as_ref() == Some(proxy) {
+                if inner.scanout_formats != summary.scanout_formats {
+                    log::info!(
+                        "compositor offers {} formats for direct scanout of window",
+                        summary.scanout_formats
+                    );
+                }
+                inner.scanout_formats = summary.scanout_formats;
+                break;
+            }
+        }
+    }
+
+    // We never create dmabuf buffers ourselves
+
+    fn created(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _params: &ZwpLinuxBufferParamsV1,
+        _buffer: WlBuffer,
+    ) {
+    }
+
+    fn failed(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _params: &ZwpLinuxBufferParamsV1,
+    ) {
+    }
+
+    fn released(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _buffer: &WlBuffer) {}
+}
+
+delegate_dmabuf!(WaylandState);
@@ -1,7 +1,6 @@
 use crate::wayland::read_pipe_with_timeout;
 use crate::ConnectionOps;
 use smithay_client_toolkit as toolkit;
-use std::path::PathBuf;
 use toolkit::data_device_manager::data_offer::DragOffer;
 use toolkit::data_device_manager::ReadPipe;
 use url::Url;
@@ -12,6 +11,8 @@ use super::WaylandConnection;
 #[derive(Default)]
 pub struct DragAndDrop {
     pub(super) offer: Option<SurfaceAndOffer>,
+    /// The most recent position of the drag, in surface coordinates
+    pub(super) position: (f64, f64),
 }
 
 pub(super) struct SurfaceAndOffer {
@@ -22,6 +23,7 @@ pub(super) struct SurfaceAndOffer {
 pub(super) struct SurfaceAndPipe {
     pub(super) window_id: usize,
     pub(super) read: ReadPipe,
+    pub(super) position: (f64, f64),
 }
 
 impl DragAndDrop {
@@ -34,10 +36,14 @@ impl DragAndDrop {
             .map_err(|err| log::error!("Unable to receive data: {:#}", err))
             .ok()?;
         offer.finish();
-        Some(SurfaceAndPipe { window_id, read })
+        Some(SurfaceAndPipe {
+            window_id,
+            read,
+            position: self.position,
+        })
     }
 
-    pub(super) fn read_paths_from_pipe(read: ReadPipe) -> Option<Vec<PathBuf>> {
+    pub(super) fn read_urls_from_pipe(read: ReadPipe) -> Option<Vec<Url>> {
         read_pipe_with_timeout(read)
             .map_err(|err| {
                 log::error!("Error while reading pipe from drop result: {:#}", err);
@@ -50,27 +56,22 @@ impl DragAndDrop {
                     // are comment lines and are ignored during processing
                     return None;
                 }
-                let url = Url::parse(line)
+                Url::parse(line)
                     .map_err(|err| {
                         log::error!("Error parsing dropped file line {} as url: {:#}", line, err);
                     })
-                    .ok()?;
-                url.to_file_path()
-                    .map_err(|_| {
-                        log::error!("Error converting url {} from line {} to pathbuf", url, line);
-                    })
                     .ok()
             })
             .collect::<Vec<_>>()
             .into()
     }
 
-    pub(super) fn dispatch_dropped_files(window_id: usize, paths: Vec<PathBuf>) {
+    pub(super) fn dispatch_dropped_urls(window_id: usize, urls: Vec<Url>, position: (f64, f64)) {
         promise::spawn::spawn_into_main_thread(async move {
             let conn = WaylandConnection::get().unwrap().wayland();
             if let Some(handle) = conn.window_by_id(window_id) {
                 let mut inner = handle.borrow_mut();
-                inner.dispatch_dropped_files(paths);
+                inner.dispatch_dropped_urls(urls, position);
             }
         })
         .detach();
@@ -0,0 +1,265 @@
+//! Tracking the toplevel windows of other applications
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use smithay_client_toolkit::globals::GlobalData;
+use smithay_client_toolkit::reexports::protocols_wlr::foreign_toplevel::v1::client::zwlr_foreign_toplevel_handle_v1::{
+    self, ZwlrForeignToplevelHandleV1,
+};
+use smithay_client_toolkit::reexports::protocols_wlr::foreign_toplevel::v1::client::zwlr_foreign_toplevel_manager_v1::{
+    self, ZwlrForeignToplevelManagerV1,
+};
+use wayland_client::backend::ObjectId;
+use wayland_client::globals::GlobalList;
+use wayland_client::{event_created_child, Connection, Dispatch, Proxy, QueueHandle};
+use wayland_protocols::ext::foreign_toplevel_list::v1::client::ext_foreign_toplevel_handle_v1::{
+    self, ExtForeignToplevelHandleV1,
+};
+use wayland_protocols::ext::foreign_toplevel_list::v1::client::ext_foreign_toplevel_list_v1::{
+    self, ExtForeignToplevelListV1,
+};
+
+use crate::toplevel::ToplevelInfo;
+use crate::WindowEvent;
+
+use super::state::WaylandState;
+
+/// The `activated` entry of zwlr_foreign_toplevel_handle_v1::state
+const WLR_STATE_ACTIVATED: u32 = 2;
+
+#[derive(Default, Debug)]
+struct Inner {
+    /// Toplevels as reported by ext_foreign_toplevel_list_v1.
+    /// `pending` accumulates changes until the `done` event.
+    ext: HashMap<ObjectId, ToplevelInfo>,
+    ext_pending: HashMap<ObjectId, ToplevelInfo>,
+    /// Toplevels as reported by zwlr_foreign_toplevel_manager_v1,
+    /// which is the only one of the two that knows about focus
+    wlr: HashMap<ObjectId, ToplevelInfo>,
+    wlr_pending: HashMap<ObjectId, ToplevelInfo>,
+}
+
+/// Maintains the list of toplevels known to the compositor.
+/// ext_foreign_toplevel_list_v1 is preferred as the source of the list;
+/// zwlr_foreign_toplevel_manager_v1 is used as a fallback for the list,
+/// and to find out which toplevel is focused.
+pub(super) struct ForeignToplevelState {
+    ext: Option<ExtForeignToplevelListV1>,
+    wlr: Option<ZwlrForeignToplevelManagerV1>,
+    inner: Mutex<Inner>,
+}
+
+impl ForeignToplevelState {
+    pub(super) fn bind(globals: &GlobalList, qh: &QueueHandle<WaylandState>) -> Option<Self> {
+        let ext: Option<ExtForeignToplevelListV1> = globals.bind(qh, 1..=1, GlobalData).ok();
+        let wlr: Option<ZwlrForeignToplevelManagerV1> = globals.bind(qh, 1..=3, GlobalData).ok();
+        if ext.is_none() && wlr.is_none() {
+            return None;
+        }
+        Some(Self {
+            ext,
+            wlr,
+            inner: Mutex::new(Inner::default()),
+        })
+    }
+
+    pub(super) fn has_focus_information(&self) -> bool {
+        self.wlr.is_some()
+    }
+
+    pub(super) fn toplevels(&self) -> Vec<ToplevelInfo> {
+        let inner = self.inner.lock().unwrap();
+
+        if self.ext.is_none() {
+            return inner.wlr.values().cloned().collect();
+        }
+
+        // The two protocols don't share identifiers, so the best that
+        // we can do is to match up the focused toplevel by its content
+        let focused = inner.wlr.values().find(|info| info.focused);
+        inner
+            .ext
+            .values()
+            .map(|info| {
+                let mut info = info.clone();
+                info.focused = focused
+                    .map(|f| f.app_id == info.app_id && f.title == info.title)
+                    .unwrap_or(false);
+                info
+            })
+            .collect()
+    }
+}
+
+/// Let all of our windows know that focus moved to a different toplevel
+fn notify_focus_changed(state: &WaylandState) {
+    for window in state.windows.borrow().values() {
+        window
+            .borrow()
+            .events
+            .dispatch(WindowEvent::FocusedToplevelChanged);
+    }
+}
+
+impl Dispatch<ExtForeignToplevelListV1, GlobalData> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ExtForeignToplevelListV1,
+        event: <ExtForeignToplevelListV1 as Proxy>::Event,
+        _data: &GlobalData,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        if let ext_foreign_toplevel_list_v1::Event::Finished = event {
+            log::debug!("ext_foreign_toplevel_list_v1 finished");
+        }
+    }
+
+    event_created_child!(WaylandState, ExtForeignToplevelListV1, [
+        ext_foreign_toplevel_list_v1::EVT_TOPLEVEL_OPCODE => (ExtForeignToplevelHandleV1, ())
+    ]);
+}
+
+impl Dispatch<ExtForeignToplevelHandleV1, ()> for WaylandState {
+    fn event(
+        state: &mut Self,
+        handle: &ExtForeignToplevelHandleV1,
+        event: <ExtForeignToplevelHandleV1 as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        let toplevels = match state.foreign_toplevel.as_ref() {
+            Some(toplevels) => toplevels,
+            None => return,
+        };
+        let mut inner = toplevels.inner.lock().unwrap();
+        let id = handle.id();
+
+        if let ext_foreign_toplevel_handle_v1::Event::Closed = event {
+            inner.ext.remove(&id);
+            inner.ext_pending.remove(&id);
+            handle.destroy();
+            return;
+        }
+
+        let Inner {
+            ext, ext_pending, ..
+        } = &mut *inner;
+        let pending = ext_pending
+            .entry(id.clone())
+            .or_insert_with(|| ext.get(&id).cloned().unwrap_or_default());
+
+        match event {
+            ext_foreign_toplevel_handle_v1::Event::Title { title } => {
+                pending.title = title;
+            }
+            ext_foreign_toplevel_handle_v1::Event::AppId { app_id } => {
+                pending.app_id = app_id;
+            }
+            ext_foreign_toplevel_handle_v1::Event::Identifier { identifier } => {
+                pending.identifier.replace(identifier);
+            }
+            ext_foreign_toplevel_handle_v1::Event::Done => {
+                if let Some(info) = ext_pending.remove(&id) {
+                    ext.insert(id, info);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ZwlrForeignToplevelManagerV1, GlobalData> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ZwlrForeignToplevelManagerV1,
+        event: <ZwlrForeignToplevelManagerV1 as Proxy>::Event,
+        _data: &GlobalData,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        if let zwlr_foreign_toplevel_manager_v1::Event::Finished = event {
+            log::debug!("zwlr_foreign_toplevel_manager_v1 finished");
+        }
+    }
+
+    event_created_child!(WaylandState, ZwlrForeignToplevelManagerV1, [
+        zwlr_foreign_toplevel_manager_v1::EVT_TOPLEVEL_OPCODE => (ZwlrForeignToplevelHandleV1, ())
+    ]);
+}
+
+impl Dispatch<ZwlrForeignToplevelHandleV1, ()> for WaylandState {
+    fn event(
+        state: &mut Self,
+        handle: &ZwlrForeignToplevelHandleV1,
+        event: <ZwlrForeignToplevelHandleV1 as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        let toplevels = match state.foreign_toplevel.as_ref() {
+            Some(toplevels) => toplevels,
+            None => return,
+        };
+        let mut inner = toplevels.inner.lock().unwrap();
+        let id = handle.id();
+
+        let focus_changed = match event {
+            zwlr_foreign_toplevel_handle_v1::Event::Closed => {
+                inner.wlr_pending.remove(&id);
+                let was_focused = inner.wlr.remove(&id).map(|info| info.focused);
+                handle.destroy();
+                was_focused.unwrap_or(false)
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::Done => {
+                let previous = inner.wlr.get(&id).cloned();
+                match inner.wlr_pending.remove(&id) {
+                    Some(info) => {
+                        let changed = match &previous {
+                            Some(prev) => {
+                                prev.focused != info.focused
+                                    || (info.focused
+                                        && (prev.title != info.title || prev.app_id != info.app_id))
+                            }
+                            None => info.focused,
+                        };
+                        inner.wlr.insert(id, info);
+                        changed
+                    }
+                    None => false,
+                }
+            }
+            event => {
+                let Inner {
+                    wlr, wlr_pending, ..
+                } = &mut *inner;
+                let pending = wlr_pending
+                    .entry(id.clone())
+                    .or_insert_with(|| wlr.get(&id).cloned().unwrap_or_default());
+                match event {
+                    zwlr_foreign_toplevel_handle_v1::Event::Title { title } => {
+                        pending.title = title;
+                    }
+                    zwlr_foreign_toplevel_handle_v1::Event::AppId { app_id } => {
+                        pending.app_id = app_id;
+                    }
+                    zwlr_foreign_toplevel_handle_v1::Event::State { state } => {
+                        pending.focused = state
+                            .chunks_exact(4)
+                            .map(|c| u32::from_ne_bytes([c[0], c[1], c[2], c[3]]))
+                            .any(|s| s == WLR_STATE_ACTIVATED);
+                    }
+                    _ => {}
+                }
+                false
+            }
+        };
+
+        drop(inner);
+        if focus_changed {
+            notify_focus_changed(state);
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! Aggregating wp_presentation_feedback into per-window frame
+//! statistics, which are recorded to the metrics and periodically
+//! reported to the gui.
+
+use std::time::{Duration, Instant};
+
+use crate::FrameStats;
+
+/// How often we report the statistics for a window that is
+/// presenting frames
+const REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Frame times longer than this are assumed to be the result of
+/// the compositor holding back frames for a hidden window, rather
+/// than of a slow frame
+const MAX_PLAUSIBLE_FRAME_TIME: Duration = Duration::from_secs(1);
+
+/// How a single frame was presented
+pub(super) struct PresentedFrame {
+    pub(super) vsync: bool,
+    pub(super) zero_copy: bool,
+    /// The time from committing the frame to its presentation,
+    /// if known
+    pub(super) frame_time: Option<Duration>,
+    /// The refresh interval of the output, if known
+    pub(super) refresh: Option<Duration>,
+}
+
+#[derive(Default)]
+pub(super) struct FrameStatsTracker {
+    /// The statistics for the current reporting interval
+    stats: FrameStats,
+    frame_times: Vec<Duration>,
+    interval_start: Option<Instant>,
+}
+
+impl FrameStatsTracker {
+    /// Accounts for a presented frame.  Returns the statistics for
+    /// the interval when it is time to report them.
+    pub(super) fn presented(&mut self, frame: PresentedFrame) -> Option<FrameStats> {
+        metrics::counter!("window.frames.presented").increment(1);
+        self.stats.presented += 1;
+        if frame.vsync {
+            metrics::counter!("window.frames.vsync").increment(1);
+            self.stats.vsync += 1;
+        }
+        if frame.zero_copy {
+            metrics::counter!("window.frames.zero_copy").increment(1);
+            self.stats.zero_copy += 1;
+        }
+
+        if let Some(frame_time) = frame
+            .frame_time
+            .filter(|&frame_time| frame_time <= MAX_PLAUSIBLE_FRAME_TIME)
+        {
+            metrics::histogram!("window.frame_time").record(frame_time);
+            self.frame_times.push(frame_time);
+            // A frame that took longer than a refresh cycle to be
+            // presented missed the vblank that followed its commit
+            if frame.refresh.map_or(false, |refresh| frame_time > refresh) {
+                metrics::counter!("window.frames.missed_deadline").increment(1);
+                self.stats.missed_deadlines += 1;
+            }
+        }
+
+        self.maybe_report()
+    }
+
+    /// Accounts for a frame that the compositor never displayed
+    pub(super) fn discarded(&mut self) -> Option<FrameStats> {
+        metrics::counter!("window.frames.discarded").increment(1);
+        self.stats.discarded += 1;
+        self.maybe_report()
+    }
+
+    fn maybe_report(&mut self) -> Option<FrameStats> {
+        let start = *self.interval_start.get_or_insert_with(Instant::now);
+        let elapsed = start.elapsed();
+        if elapsed < REPORT_INTERVAL {
+            return None;
+        }
+
+        let mut stats = std::mem::take(&mut self.stats);
+        stats.interval = elapsed;
+        self.frame_times.sort_unstable();
+        let percentile = |p: usize| {
+            if self.frame_times.is_empty() {
+                None
+            } else {
+                Some(self.frame_times[(self.frame_times.len() - 1) * p / 100])
+            }
+        };
+        stats.frame_time_p50 = percentile(50);
+        stats.frame_time_p95 = percentile(95);
+        stats.frame_time_max = self.frame_times.last().copied();
+
+        self.frame_times.clear();
+        self.interval_start.replace(Instant::now());
+        Some(stats)
+    }
+}
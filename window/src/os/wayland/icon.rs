@@ -0,0 +1,93 @@
+use smithay_client_toolkit::globals::GlobalData;
+use wayland_client::globals::GlobalList;
+use wayland_client::{Connection, Dispatch, Proxy, QueueHandle};
+use wayland_protocols::xdg::toplevel_icon::v1::client::xdg_toplevel_icon_manager_v1::{
+    self, XdgToplevelIconManagerV1,
+};
+use wayland_protocols::xdg::toplevel_icon::v1::client::xdg_toplevel_icon_v1::XdgToplevelIconV1;
+
+use crate::bitmaps::{BitmapImage, Image};
+use crate::Point;
+
+use super::state::WaylandState;
+
+/// Tracks the xdg_toplevel_icon_manager_v1 global along with the
+/// icon sizes that the compositor would like us to provide
+pub(super) struct ToplevelIconManager {
+    pub(super) manager: XdgToplevelIconManagerV1,
+    /// Edge sizes, in surface-local coordinates, of the square icons
+    /// that the compositor prefers.  Empty if it has no preference.
+    pub(super) sizes: Vec<i32>,
+    pending_sizes: Vec<i32>,
+}
+
+impl ToplevelIconManager {
+    pub(super) fn bind(globals: &GlobalList, qh: &QueueHandle<WaylandState>) -> Option<Self> {
+        let manager: XdgToplevelIconManagerV1 = globals.bind(qh, 1..=1, GlobalData).ok()?;
+        Some(Self {
+            manager,
+            sizes: vec![],
+            pending_sizes: vec![],
+        })
+    }
+}
+
+/// Scales `image` to fit a square icon of `edge` pixels, preserving
+/// its aspect ratio by centering it on a transparent background
+pub(super) fn letterbox(image: &Image, edge: usize) -> Image {
+    let (width, height) = image.image_dimensions();
+    let longest = width.max(height).max(1);
+    let scaled_width = (width * edge / longest).max(1);
+    let scaled_height = (height * edge / longest).max(1);
+    let scaled = image.resize(scaled_width, scaled_height);
+
+    let mut icon = Image::new(edge, edge);
+    icon.draw_image(
+        Point::new(
+            ((edge - scaled_width) / 2) as isize,
+            ((edge - scaled_height) / 2) as isize,
+        ),
+        None,
+        &scaled,
+    );
+    icon
+}
+
+impl Dispatch<XdgToplevelIconManagerV1, GlobalData> for WaylandState {
+    fn event(
+        state: &mut Self,
+        _proxy: &XdgToplevelIconManagerV1,
+        event: <XdgToplevelIconManagerV1 as Proxy>::Event,
+        _data: &GlobalData,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        let icon_manager = match state.toplevel_icon_manager.as_mut() {
+            Some(icon_manager) => icon_manager,
+            None => return,
+        };
+        match event {
+            xdg_toplevel_icon_manager_v1::Event::IconSize { size } => {
+                icon_manager.pending_sizes.push(size);
+            }
+            xdg_toplevel_icon_manager_v1::Event::Done => {
+                icon_manager.sizes = std::mem::take(&mut icon_manager.pending_sizes);
+                log::debug!("preferred toplevel icon sizes: {:?}", icon_manager.sizes);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<XdgToplevelIconV1, ()> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &XdgToplevelIconV1,
+        _event: <XdgToplevelIconV1 as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        // Icon has no events
+    }
+}
@@ -0,0 +1,90 @@
+//! Pausing animations while the user is idle
+
+use smithay_client_toolkit::globals::GlobalData;
+use wayland_client::globals::GlobalList;
+use wayland_client::protocol::wl_seat::WlSeat;
+use wayland_client::{Connection, Dispatch, Proxy, QueueHandle};
+use wayland_protocols::ext::idle_notify::v1::client::ext_idle_notification_v1::{
+    self, ExtIdleNotificationV1,
+};
+use wayland_protocols::ext::idle_notify::v1::client::ext_idle_notifier_v1::ExtIdleNotifierV1;
+
+use crate::WindowEvent;
+
+use super::state::WaylandState;
+
+/// Lets our windows know when the user has stopped interacting with
+/// the system for a while, so that they can pause their animations
+pub(super) struct IdleNotifier {
+    notifier: ExtIdleNotifierV1,
+    notification: Option<ExtIdleNotificationV1>,
+}
+
+impl IdleNotifier {
+    pub(super) fn bind(globals: &GlobalList, qh: &QueueHandle<WaylandState>) -> Option<Self> {
+        let notifier: ExtIdleNotifierV1 = globals.bind(qh, 1..=2, GlobalData).ok()?;
+        Some(Self {
+            notifier,
+            notification: None,
+        })
+    }
+
+    /// Start watching for idleness on `seat`.
+    /// We only watch the first seat.
+    pub(super) fn advise_seat(&mut self, seat: &WlSeat, qh: &QueueHandle<WaylandState>) {
+        if self.notification.is_some() {
+            return;
+        }
+        let timeout = config::configuration().idle_animation_pause_delay_ms;
+        if timeout == 0 {
+            return;
+        }
+        let timeout = timeout.min(u32::MAX as u64) as u32;
+        // Version 2 lets us ignore idle inhibitors, such as a video
+        // player in another window; what matters to us is whether
+        // anyone is actually using the input devices
+        self.notification.replace(if self.notifier.version() >= 2 {
+            self.notifier
+                .get_input_idle_notification(timeout, seat, qh, ())
+        } else {
+            self.notifier.get_idle_notification(timeout, seat, qh, ())
+        });
+    }
+}
+
+impl Dispatch<ExtIdleNotifierV1, GlobalData> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ExtIdleNotifierV1,
+        _event: <ExtIdleNotifierV1 as Proxy>::Event,
+        _data: &GlobalData,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        // Notifier has no events
+    }
+}
+
+impl Dispatch<ExtIdleNotificationV1, ()> for WaylandState {
+    fn event(
+        state: &mut Self,
+        _proxy: &ExtIdleNotificationV1,
+        event: <ExtIdleNotificationV1 as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        let idle = match event {
+            ext_idle_notification_v1::Event::Idled => true,
+            ext_idle_notification_v1::Event::Resumed => false,
+            _ => return,
+        };
+        log::debug!("user idle: {idle}");
+        for window in state.windows.borrow().values() {
+            window
+                .borrow()
+                .events
+                .dispatch(WindowEvent::UserIdleChanged(idle));
+        }
+    }
+}
@@ -1,5 +1,4 @@
 //! Implements zwp_text_input_v3 for handling IME
-use std::borrow::Borrow;
 use std::collections::HashMap;
 use std::sync::Mutex;
 
@@ -23,6 +22,10 @@ use super::state::WaylandState;
 #[derive(Clone, Default, Debug)]
 struct PendingState {
     pre_edit: Option<String>,
+    /// Byte offsets of the highlighted part of pre_edit;
+    /// -1 for both means that the caret is hidden
+    cursor_begin: i32,
+    cursor_end: i32,
     commit: Option<String>,
 }
 
@@ -107,6 +110,28 @@ impl TextInputState {
             .insert(keyboard_id, seat_id);
     }
 
+    fn seat_for_input(&self, input: &ZwpTextInputV3) -> Option<ObjectId> {
+        self.inner
+            .lock()
+            .unwrap()
+            .input_by_seat
+            .iter()
+            .find_map(|(seat_id, candidate)| (candidate == input).then(|| seat_id.clone()))
+    }
+
+    /// Destroy the text input associated with a seat that has gone away
+    pub(super) fn forget_seat(&self, seat: &WlSeat) {
+        let mut inner = self.inner.lock().unwrap();
+        let seat_id = seat.id();
+        if let Some(input) = inner.input_by_seat.remove(&seat_id) {
+            inner.pending_state.remove(&input.id());
+            input.destroy();
+        }
+        inner
+            .keyboard_to_seat
+            .retain(|_keyboard_id, id| *id != seat_id);
+    }
+
     /// Workaround for <https://gitlab.gnome.org/GNOME/gnome-shell/-/issues/4776>
     /// If we make sure to disable things before we close the app,
     /// mutter is less likely to get in a bad state
@@ -157,6 +182,7 @@ impl Dispatch<ZwpTextInputV3, TextInputData, WaylandState> for TextInputState {
         _qhandle: &QueueHandle<WaylandState>,
     ) {
         log::trace!("ZwpTextInputEvent: {event:?}");
+        let seat_id = state.text_input.as_ref().unwrap().seat_for_input(input);
         let mut pending_state = {
             let text_input = state.text_input.as_mut().unwrap();
             let mut inner = text_input.inner.lock().unwrap();
@@ -166,35 +192,55 @@ impl Dispatch<ZwpTextInputV3, TextInputData, WaylandState> for TextInputState {
         match event {
             TextInputEvent::PreeditString {
                 text,
-                cursor_begin: _,
-                cursor_end: _,
+                cursor_begin,
+                cursor_end,
             } => {
                 pending_state.pre_edit = text;
+                pending_state.cursor_begin = cursor_begin;
+                pending_state.cursor_end = cursor_end;
             }
             TextInputEvent::CommitString { text } => {
                 pending_state.commit = text;
-                state.dispatch_to_focused_window(WindowEvent::AdviseDeadKeyStatus(
-                    DeadKeyStatus::None,
-                ));
+                state.dispatch_to_focused_window(
+                    seat_id.as_ref(),
+                    WindowEvent::AdviseDeadKeyStatus(DeadKeyStatus::None),
+                );
             }
             TextInputEvent::Done { serial } => {
-                *state.last_serial.borrow_mut() = serial;
+                if let Some(seat_id) = &seat_id {
+                    state.record_serial(seat_id, serial);
+                }
                 if let Some(text) = pending_state.commit.take() {
-                    state.dispatch_to_focused_window(WindowEvent::KeyEvent(KeyEvent {
-                        key: KeyCode::composed(&text),
-                        modifiers: Modifiers::NONE,
-                        leds: KeyboardLedStatus::empty(),
-                        repeat_count: 1,
-                        key_is_down: true,
-                        raw: None,
-                    }));
+                    state.dispatch_to_focused_window(
+                        seat_id.as_ref(),
+                        WindowEvent::KeyEvent(KeyEvent {
+                            key: KeyCode::composed(&text),
+                            modifiers: Modifiers::NONE,
+                            leds: KeyboardLedStatus::empty(),
+                            repeat_count: 1,
+                            key_is_down: true,
+                            raw: None,
+                        }),
+                    );
                 }
                 let status = if let Some(text) = pending_state.pre_edit.take() {
-                    DeadKeyStatus::Composing(text)
+                    let cursor = match (
+                        usize::try_from(pending_state.cursor_begin),
+                        usize::try_from(pending_state.cursor_end),
+                    ) {
+                        (Ok(begin), Ok(end)) if begin <= end && end <= text.len() => {
+                            Some(begin..end)
+                        }
+                        _ => None,
+                    };
+                    DeadKeyStatus::Preedit { text, cursor }
                 } else {
                     DeadKeyStatus::None
                 };
-                state.dispatch_to_focused_window(WindowEvent::AdviseDeadKeyStatus(status));
+                state.dispatch_to_focused_window(
+                    seat_id.as_ref(),
+                    WindowEvent::AdviseDeadKeyStatus(status),
+                );
             }
             _ => {}
         }
@@ -212,8 +258,12 @@ impl Dispatch<ZwpTextInputV3, TextInputData, WaylandState> for TextInputState {
 }
 
 impl WaylandState {
-    fn dispatch_to_focused_window(&self, event: WindowEvent) {
-        if let Some(&window_id) = self.keyboard_window_id.borrow().as_ref() {
+    /// Dispatch to the window that has the keyboard focus of the seat
+    fn dispatch_to_focused_window(&self, seat_id: Option<&ObjectId>, event: WindowEvent) {
+        let window_id = seat_id
+            .and_then(|seat_id| self.seats.get(seat_id))
+            .and_then(|seat| seat.keyboard_window_id);
+        if let Some(window_id) = window_id {
             if let Some(win) = self.window_by_id(window_id) {
                 let mut inner = win.borrow_mut();
                 inner.events.dispatch(event);
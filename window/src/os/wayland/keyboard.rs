@@ -1,12 +1,12 @@
-use std::borrow::BorrowMut;
-
 use wayland_client::protocol::wl_keyboard::{Event as WlKeyboardEvent, KeymapFormat, WlKeyboard};
+use wayland_client::protocol::wl_seat::WlSeat;
 use wayland_client::{Dispatch, Proxy};
 use xkbcommon::xkb;
 use xkbcommon::xkb::CONTEXT_NO_FLAGS;
 
 use crate::x11::KeyboardWithFallback;
 
+use super::activation::ActivationPurpose;
 use super::state::WaylandState;
 use super::SurfaceUserData;
 
@@ -16,20 +16,24 @@ impl Dispatch<WlKeyboard, KeyboardData> for WaylandState {
         state: &mut WaylandState,
         keyboard: &WlKeyboard,
         event: <WlKeyboard as wayland_client::Proxy>::Event,
-        _data: &KeyboardData,
+        data: &KeyboardData,
         _conn: &wayland_client::Connection,
-        _qhandle: &wayland_client::QueueHandle<WaylandState>,
+        qhandle: &wayland_client::QueueHandle<WaylandState>,
     ) {
         log::trace!("We reached an event here: {:?}???", event);
+        let seat_id = data.seat.id();
         match &event {
             WlKeyboardEvent::Enter {
                 serial, surface, ..
             } => {
-                *state.active_surface_id.borrow_mut() = Some(surface.id());
-                *state.last_serial.borrow_mut() = *serial;
+                state.seat_mut(&data.seat).keyboard_surface_id = Some(surface.id());
+                state.record_serial(&seat_id, *serial);
                 if let Some(sud) = SurfaceUserData::try_from_wl(&surface) {
                     let window_id = sud.window_id;
-                    state.keyboard_window_id.borrow_mut().replace(window_id);
+                    state
+                        .seat_mut(&data.seat)
+                        .keyboard_window_id
+                        .replace(window_id);
                     if let Some(text_input) = &state.text_input {
                         if let Some(input) = text_input.get_text_input_for_keyboard(keyboard) {
                             input.enable();
@@ -40,9 +44,15 @@ impl Dispatch<WlKeyboard, KeyboardData> for WaylandState {
                 } else {
                     log::warn!("{:?}, no known surface", event);
                 }
+                // Now that we have a fresh serial, get a token ready
+                // for the next program that we spawn
+                if state.spawn_activation_token.is_none() {
+                    state.request_activation_token(qhandle, ActivationPurpose::Spawn);
+                }
             }
             WlKeyboardEvent::Leave { serial, .. } => {
-                *state.last_serial.borrow_mut() = *serial;
+                state.seat_mut(&data.seat).keyboard_surface_id.take();
+                state.record_serial(&seat_id, *serial);
                 if let Some(text_input) = &state.text_input {
                     if let Some(input) = text_input.get_text_input_for_keyboard(keyboard) {
                         input.disable();
@@ -51,11 +61,12 @@ impl Dispatch<WlKeyboard, KeyboardData> for WaylandState {
                 }
             }
             WlKeyboardEvent::Key { serial, .. } | WlKeyboardEvent::Modifiers { serial, .. } => {
-                *state.last_serial.borrow_mut() = *serial;
+                state.record_serial(&seat_id, *serial);
             }
             WlKeyboardEvent::RepeatInfo { rate, delay } => {
-                *state.key_repeat_rate.borrow_mut() = *rate;
-                *state.key_repeat_delay.borrow_mut() = *delay;
+                let seat = state.seat_mut(&data.seat);
+                seat.key_repeat_rate = *rate;
+                seat.key_repeat_delay = *delay;
             }
             WlKeyboardEvent::Keymap { format, fd, size } => {
                 match format.into_result().unwrap() {
@@ -78,7 +89,7 @@ impl Dispatch<WlKeyboard, KeyboardData> for WaylandState {
                                 let s = keymap.get_as_string(xkb::KEYMAP_FORMAT_TEXT_V1);
                                 match KeyboardWithFallback::new_from_string(s) {
                                     Ok(k) => {
-                                        state.keyboard_mapper.replace(k);
+                                        state.seat_mut(&data.seat).keyboard_mapper.replace(k);
                                     }
                                     Err(err) => {
                                         log::error!("Error processing keymap change: {:#}", err);
@@ -102,17 +113,24 @@ impl Dispatch<WlKeyboard, KeyboardData> for WaylandState {
             }
         }
 
-        let Some(&window_id) = state.keyboard_window_id.as_ref() else {
+        if let WlKeyboardEvent::Leave { surface, .. } = &event {
+            if state.has_keyboard_focus(&surface.id()) {
+                // Another seat is still typing into this window
+                return;
+            }
+        }
+
+        let Some(window_id) = state.seat_mut(&data.seat).keyboard_window_id else {
             return;
         };
         let Some(win) = state.window_by_id(window_id) else {
             return;
         };
         let mut inner = win.as_ref().borrow_mut();
-        let mapper = state.keyboard_mapper.borrow_mut();
-        let mapper = mapper.as_mut().expect("no keymap");
-        inner.keyboard_event(mapper, event);
+        inner.keyboard_event(state.seat_mut(&data.seat), event);
     }
 }
 
-pub(super) struct KeyboardData {}
+pub(super) struct KeyboardData {
+    pub(super) seat: WlSeat,
+}
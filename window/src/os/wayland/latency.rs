@@ -0,0 +1,68 @@
+//! Measuring input-to-photon latency: the time between the compositor
+//! receiving an input event and the presentation of the next frame
+//! that we submit after it.
+
+use std::time::Duration;
+
+use wayland_client::protocol::wl_surface::WlSurface;
+
+/// Samples longer than this are assumed not to be the result of the
+/// input in question, for example a pointer motion over a window
+/// that had nothing to repaint until much later
+const MAX_PLAUSIBLE_LATENCY: Duration = Duration::from_secs(1);
+
+/// The user data attached to each wp_presentation_feedback
+pub(super) struct PresentationFeedbackData {
+    pub(super) surface: WlSurface,
+    /// The timestamp of the earliest input received before this
+    /// frame was committed, if it hadn't already been attributed
+    /// to an earlier frame
+    pub(super) input_time_ns: Option<u64>,
+    /// When the frame was committed, according to the presentation
+    /// clock, if known
+    pub(super) committed_ns: Option<u64>,
+}
+
+#[derive(Default)]
+pub(super) struct InputLatencyTracker {
+    /// The earliest input timestamp that hasn't yet been attributed
+    /// to a frame
+    pending_input_ns: Option<u64>,
+}
+
+impl InputLatencyTracker {
+    /// Called for each high-resolution input timestamp
+    pub(super) fn record_input(&mut self, input_time_ns: u64) {
+        if self.pending_input_ns.is_none() {
+            self.pending_input_ns.replace(input_time_ns);
+        }
+    }
+
+    /// Called as a frame is committed; the returned timestamp is
+    /// carried along with its presentation feedback
+    pub(super) fn take_pending_input(&mut self) -> Option<u64> {
+        self.pending_input_ns.take()
+    }
+
+    /// Compute the latency for a frame that was presented at
+    /// `presented_ns` according to `clock_id`.  The input timestamps
+    /// are in the same clock domain as the regular input event
+    /// timestamps, which all of the compositors that we know of take
+    /// from CLOCK_MONOTONIC, so we only compare them when the
+    /// presentation clock is that same clock.
+    pub(super) fn measure(
+        &self,
+        clock_id: Option<u32>,
+        input_time_ns: u64,
+        presented_ns: u64,
+    ) -> Option<Duration> {
+        if clock_id != Some(libc::CLOCK_MONOTONIC as u32) {
+            return None;
+        }
+        let latency = Duration::from_nanos(presented_ns.checked_sub(input_time_ns)?);
+        if latency > MAX_PLAUSIBLE_LATENCY {
+            return None;
+        }
+        Some(latency)
+    }
+}
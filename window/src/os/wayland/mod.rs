@@ -7,11 +7,23 @@ pub mod window;
 pub use self::window::*;
 pub use connection::*;
 pub use output::*;
+mod activation;
+mod background;
+mod color;
 mod copy_and_paste;
+mod damage;
+mod dmabuf;
 mod drag_and_drop;
+mod foreign_toplevel;
+mod framestats;
+mod icon;
+mod idle;
+mod latency;
 // mod frame;
 mod data_device;
 mod keyboard;
 mod pointer;
 mod seat;
 mod state;
+mod tablet;
+mod touch;
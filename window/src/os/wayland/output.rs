@@ -1,7 +1,8 @@
 //! Dealing with Wayland outputs
 
+use crate::output::{OutputInfo, OutputModeInfo};
 use crate::screen::{ScreenInfo, Screens};
-use crate::ScreenRect;
+use crate::{ScreenRect, WindowEvent};
 use smithay_client_toolkit::globals::GlobalData;
 use smithay_client_toolkit::reexports::protocols_wlr::output_management::v1::client::zwlr_output_head_v1::{ZwlrOutputHeadV1, self, Event as ZwlrOutputHeadEvent};
 use smithay_client_toolkit::reexports::protocols_wlr::output_management::v1::client::zwlr_output_manager_v1::{ZwlrOutputManagerV1, self, Event as ZwlrOutputEvent};
@@ -95,6 +96,22 @@ impl OutputManagerState {
                 width as isize,
                 height as isize,
             );
+            // The position of a head is in logical coordinates, but
+            // its mode is in pixels and prior to the head's transform
+            let (logical_width, logical_height) = match head.transform {
+                Some(
+                    Transform::_90 | Transform::_270 | Transform::Flipped90 | Transform::Flipped270,
+                ) => (height, width),
+                _ => (width, height),
+            };
+            let logical_scale = if scale > 0.0 { scale } else { 1.0 };
+            let logical_rect = euclid::rect(
+                head.x as isize,
+                head.y as isize,
+                (logical_width as f64 / logical_scale).round() as isize,
+                (logical_height as f64 / logical_scale).round() as isize,
+            );
+            let non_empty = |s: &str| Some(s.to_string()).filter(|s| !s.is_empty());
             virtual_rect = virtual_rect.union(&rect);
             // FIXME: teach this how to resolve dpi_by_screen once
             // dispatch_pending_event knows how to do the same
@@ -107,6 +124,15 @@ impl OutputManagerState {
                     scale,
                     max_fps: None,
                     effective_dpi,
+                    description: non_empty(&head.description),
+                    make: non_empty(&head.make),
+                    model: non_empty(&head.model),
+                    physical_size_mm: Some((
+                        head.physical_width as isize,
+                        head.physical_height as isize,
+                    ))
+                    .filter(|&(w, h)| w > 0 && h > 0),
+                    logical_rect: Some(logical_rect),
                 },
             );
         }
@@ -141,6 +167,61 @@ impl OutputManagerState {
             virtual_rect,
         })
     }
+
+    pub fn outputs(&self) -> Vec<OutputInfo> {
+        let inner = self.inner.lock().unwrap();
+
+        let mut outputs: Vec<OutputInfo> = inner
+            .zwlr_head_info
+            .values()
+            .map(|head| OutputInfo {
+                name: head.name.clone(),
+                description: head.description.clone(),
+                make: head.make.clone(),
+                model: head.model.clone(),
+                serial_number: head.serial_number.clone(),
+                enabled: head.enabled,
+                x: head.x,
+                y: head.y,
+                scale: head.scale,
+                transform: transform_name(head.transform.unwrap_or(Transform::Normal)).to_string(),
+                physical_width: head.physical_width,
+                physical_height: head.physical_height,
+                modes: head
+                    .mode_ids
+                    .iter()
+                    .filter_map(|mode_id| {
+                        let mode = inner.zwlr_mode_info.get(mode_id)?;
+                        Some(OutputModeInfo {
+                            width: mode.width,
+                            height: mode.height,
+                            // refresh is in mHz
+                            refresh: mode.refresh as f64 / 1000.,
+                            preferred: mode.preferred,
+                            current: head.current_mode_id.as_ref() == Some(mode_id),
+                        })
+                    })
+                    .collect(),
+            })
+            .collect();
+        // Make the order stable; the heads are stored in a HashMap
+        outputs.sort_by(|a, b| a.name.cmp(&b.name));
+        outputs
+    }
+}
+
+fn transform_name(transform: Transform) -> &'static str {
+    match transform {
+        Transform::Normal => "Normal",
+        Transform::_90 => "90",
+        Transform::_180 => "180",
+        Transform::_270 => "270",
+        Transform::Flipped => "Flipped",
+        Transform::Flipped90 => "Flipped90",
+        Transform::Flipped180 => "Flipped180",
+        Transform::Flipped270 => "Flipped270",
+        _ => "Normal",
+    }
 }
 
 #[derive(Default)]
@@ -162,6 +243,17 @@ impl Dispatch<ZwlrOutputManagerV1, GlobalData, WaylandState> for OutputManagerSt
             ZwlrOutputEvent::Head { head } => {
                 inner.zwlr_heads.insert(head.id(), head);
             }
+            ZwlrOutputEvent::Done { .. } => {
+                // The compositor has finished sending a consistent
+                // set of changes; let all of our windows know
+                drop(inner);
+                for window in state.windows.borrow().values() {
+                    window
+                        .borrow()
+                        .events
+                        .dispatch(WindowEvent::OutputConfigurationChanged);
+                }
+            }
             _ => {}
         }
     }
@@ -1,21 +1,26 @@
-use std::cell::RefCell;
+use std::collections::VecDeque;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use smithay_client_toolkit::compositor::SurfaceData;
+use smithay_client_toolkit::globals::GlobalData;
 use smithay_client_toolkit::reexports::csd_frame::{DecorationsFrame, FrameClick};
 use smithay_client_toolkit::seat::pointer::{
     PointerData, PointerDataExt, PointerEvent, PointerEventKind, PointerHandler,
 };
 use wayland_client::backend::ObjectId;
-use wayland_client::protocol::wl_pointer::{ButtonState, WlPointer};
+use wayland_client::protocol::wl_pointer::{AxisSource, ButtonState, WlPointer};
 use wayland_client::protocol::wl_seat::WlSeat;
-use wayland_client::{Connection, Proxy, QueueHandle};
+use wayland_client::{Connection, Dispatch, Proxy, QueueHandle};
+use wayland_protocols::wp::pointer_gestures::zv1::client::zwp_pointer_gesture_hold_v1::{
+    self, ZwpPointerGestureHoldV1,
+};
+use wayland_protocols::wp::pointer_gestures::zv1::client::zwp_pointer_gestures_v1::ZwpPointerGesturesV1;
 use wezterm_input_types::MousePress;
 
 use crate::wayland::SurfaceUserData;
+use crate::WindowEvent;
 
-use super::copy_and_paste::CopyAndPaste;
 use super::drag_and_drop::DragAndDrop;
 use super::state::WaylandState;
 use super::WaylandConnection;
@@ -23,31 +28,27 @@ use super::WaylandConnection;
 impl PointerHandler for WaylandState {
     fn pointer_frame(
         &mut self,
-        _conn: &Connection,
+        conn: &Connection,
         _qh: &QueueHandle<Self>,
         pointer: &WlPointer,
         events: &[PointerEvent],
     ) {
-        let mut pstate = pointer
-            .data::<PointerUserData>()
-            .unwrap()
-            .state
-            .lock()
-            .unwrap();
+        let pointer_data = pointer.data::<PointerUserData>().unwrap();
+        let seat_id = pointer_data.pdata.seat().id();
+        let mut pstate = pointer_data.state.lock().unwrap();
 
         for evt in events {
             if let PointerEventKind::Enter { .. } = &evt.kind {
-                let surface_id = evt.surface.id();
-                self.active_surface_id = RefCell::new(Some(surface_id.clone()));
-                pstate.active_surface_id = Some(surface_id);
+                pstate.active_surface_id = Some(evt.surface.id());
             }
             if let Some(serial) = event_serial(&evt) {
-                *self.last_serial.borrow_mut() = serial;
+                self.record_serial(&seat_id, serial);
                 pstate.serial = serial;
             }
-            if let Some(pending) = self
-                .surface_to_pending
-                .get(&self.active_surface_id.borrow().as_ref().unwrap())
+            if let Some(pending) = pstate
+                .active_surface_id
+                .as_ref()
+                .and_then(|id| self.surface_to_pending.get(id))
             {
                 let mut pending = pending.lock().unwrap();
                 if pending.queue(evt) {
@@ -58,7 +59,44 @@ impl PointerHandler for WaylandState {
                 }
             }
         }
-        self.pointer_window_frame(pointer, events);
+        drop(pstate);
+        self.pointer_window_frame(conn, pointer, events);
+    }
+}
+
+impl Dispatch<ZwpPointerGesturesV1, GlobalData> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ZwpPointerGesturesV1,
+        _event: <ZwpPointerGesturesV1 as Proxy>::Event,
+        _data: &GlobalData,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        // zwp_pointer_gestures_v1 has no events
+    }
+}
+
+impl Dispatch<ZwpPointerGestureHoldV1, GlobalData> for WaylandState {
+    fn event(
+        state: &mut Self,
+        _proxy: &ZwpPointerGestureHoldV1,
+        event: zwp_pointer_gesture_hold_v1::Event,
+        _data: &GlobalData,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        // Fingers resting on the touchpad catch a kinetic scroll,
+        // just as they would stop a physical object from sliding
+        if let zwp_pointer_gesture_hold_v1::Event::Begin { surface, .. } = event {
+            if let Some(pending) = state.surface_to_pending.get(&surface.id()) {
+                let window_id = pending.lock().unwrap().window_id;
+                WaylandConnection::with_window_inner(window_id, |inner| {
+                    inner.events.dispatch(WindowEvent::KineticScrollStop);
+                    Ok(())
+                });
+            }
+        }
     }
 }
 
@@ -74,6 +112,11 @@ impl PointerUserData {
             state: Default::default(),
         }
     }
+
+    /// Returns true if the pointer is over the surface
+    pub(super) fn is_over(&self, surface_id: &ObjectId) -> bool {
+        self.state.lock().unwrap().active_surface_id.as_ref() == Some(surface_id)
+    }
 }
 
 #[derive(Default)]
@@ -89,28 +132,37 @@ impl PointerDataExt for PointerUserData {
     }
 }
 
+/// How far back, in milliseconds, to look at the motion of the
+/// fingers when they are lifted, to determine how fast they were going
+const KINETIC_SAMPLE_MS: u32 = 100;
+
 #[derive(Clone, Debug)]
 pub struct PendingMouse {
     window_id: usize,
-    pub(super) copy_and_paste: Arc<Mutex<CopyAndPaste>>,
     surface_coords: Option<(f64, f64)>,
     button: Vec<(MousePress, ButtonState)>,
     scroll: Option<(f64, f64)>,
     in_window: bool,
+    /// Set while the scroll events are coming from fingers on a touchpad
+    finger_scrolling: bool,
+    /// The recent finger scroll motion as (time, horizontal, vertical)
+    scroll_samples: VecDeque<(u32, f64, f64)>,
+    /// The velocity, in surface coordinates per second, with which
+    /// the fingers were lifted
+    kinetic: Option<(f64, f64)>,
 }
 
 impl PendingMouse {
-    pub(super) fn create(
-        window_id: usize,
-        copy_and_paste: &Arc<Mutex<CopyAndPaste>>,
-    ) -> Arc<Mutex<Self>> {
+    pub(super) fn create(window_id: usize) -> Arc<Mutex<Self>> {
         Arc::new(Mutex::new(Self {
             window_id,
-            copy_and_paste: Arc::clone(copy_and_paste),
             button: vec![],
             scroll: None,
             surface_coords: None,
             in_window: false,
+            finger_scrolling: false,
+            scroll_samples: VecDeque::new(),
+            kinetic: None,
         }))
     }
 
@@ -155,19 +207,69 @@ impl PendingMouse {
                 changed
             }
             PointerEventKind::Axis {
+                time,
                 horizontal,
                 vertical,
-                ..
+                source,
             } => {
                 let changed = self.scroll.is_none();
                 let (x, y) = self.scroll.take().unwrap_or((0., 0.));
                 self.scroll
                     .replace((x + horizontal.absolute, y + vertical.absolute));
+
+                match source {
+                    Some(AxisSource::Finger) => self.finger_scrolling = true,
+                    Some(_) => {
+                        self.finger_scrolling = false;
+                        self.scroll_samples.clear();
+                    }
+                    None => {}
+                }
+
+                if horizontal.stop || vertical.stop {
+                    if self.finger_scrolling {
+                        self.kinetic = self.finger_velocity(time);
+                    }
+                    self.scroll_samples.clear();
+                } else if self.finger_scrolling {
+                    self.scroll_samples
+                        .push_back((time, horizontal.absolute, vertical.absolute));
+                    while let Some((t, _, _)) = self.scroll_samples.front() {
+                        if time.wrapping_sub(*t) <= KINETIC_SAMPLE_MS {
+                            break;
+                        }
+                        self.scroll_samples.pop_front();
+                    }
+                }
                 changed
             }
         }
     }
 
+    /// Computes how fast the fingers were moving over the touchpad
+    /// as they were lifted at `time`
+    fn finger_velocity(&self, time: u32) -> Option<(f64, f64)> {
+        let mut samples = self
+            .scroll_samples
+            .iter()
+            .filter(|(t, _, _)| time.wrapping_sub(*t) <= KINETIC_SAMPLE_MS);
+        let (start, _, _) = samples.next()?;
+        // The first sample is the motion that led up to its time,
+        // so only the distance covered after it counts
+        let (mut end, mut x, mut y) = (*start, 0., 0.);
+        for (t, dx, dy) in samples {
+            end = *t;
+            x += dx;
+            y += dy;
+        }
+        let elapsed = end.wrapping_sub(*start);
+        if elapsed == 0 {
+            return None;
+        }
+        let elapsed = elapsed as f64 / 1000.;
+        Some((x / elapsed, y / elapsed))
+    }
+
     pub(super) fn next_button(pending: &Arc<Mutex<Self>>) -> Option<(MousePress, ButtonState)> {
         let mut pending = pending.lock().unwrap();
         if pending.button.is_empty() {
@@ -185,6 +287,10 @@ impl PendingMouse {
         pending.lock().unwrap().scroll.take()
     }
 
+    pub(super) fn kinetic(pending: &Arc<Mutex<Self>>) -> Option<(f64, f64)> {
+        pending.lock().unwrap().kinetic.take()
+    }
+
     pub(super) fn in_window(pending: &Arc<Mutex<Self>>) -> bool {
         pending.lock().unwrap().in_window
     }
@@ -201,12 +307,22 @@ fn event_serial(event: &PointerEvent) -> Option<u32> {
 }
 
 impl WaylandState {
-    fn pointer_window_frame(&mut self, pointer: &WlPointer, events: &[PointerEvent]) {
+    fn pointer_window_frame(
+        &mut self,
+        conn: &Connection,
+        pointer: &WlPointer,
+        events: &[PointerEvent],
+    ) {
         let windows = self.windows.borrow();
+        let pointer_data = pointer.data::<PointerUserData>().unwrap();
+        let themed_pointer = self
+            .seats
+            .get(&pointer_data.pdata.seat().id())
+            .and_then(|seat| seat.pointer.as_ref());
 
         for evt in events {
             let surface = &evt.surface;
-            if surface.id() == self.active_surface_id.borrow().as_ref().unwrap().clone() {
+            if pointer_data.is_over(&surface.id()) {
                 let (x, y) = evt.position;
                 let parent_surface = match evt.surface.data::<SurfaceData>() {
                     Some(data) => match data.parent_surface() {
@@ -220,25 +336,26 @@ impl WaylandState {
                 let mut inner = windows.get(&wid).unwrap().borrow_mut();
 
                 match evt.kind {
-                    PointerEventKind::Enter { .. } => {
-                        inner.window_frame.click_point_moved(
+                    PointerEventKind::Enter { .. } | PointerEventKind::Motion { .. } => {
+                        // The frame tells us which cursor to show over the
+                        // resize edges and title bar; with cursor-shape-v1
+                        // the compositor renders it at the right scale
+                        if let Some(icon) = inner.window_frame.click_point_moved(
                             Duration::ZERO,
                             &evt.surface.id(),
                             x,
                             y,
-                        );
+                        ) {
+                            if let Some(themed_pointer) = themed_pointer {
+                                if let Err(err) = themed_pointer.set_cursor(conn, icon) {
+                                    log::error!("set_cursor for frame: {}", err);
+                                }
+                            }
+                        }
                     }
                     PointerEventKind::Leave { .. } => {
                         inner.window_frame.click_point_left();
                     }
-                    PointerEventKind::Motion { .. } => {
-                        inner.window_frame.click_point_moved(
-                            Duration::ZERO,
-                            &evt.surface.id(),
-                            x,
-                            y,
-                        );
-                    }
                     PointerEventKind::Press { button, serial, .. }
                     | PointerEventKind::Release { button, serial, .. } => {
                         let pressed = if matches!(evt.kind, PointerEventKind::Press { .. }) {
@@ -1,21 +1,140 @@
-use smithay_client_toolkit::seat::pointer::ThemeSpec;
+use smithay_client_toolkit::data_device_manager::data_device::DataDevice;
+use smithay_client_toolkit::globals::GlobalData;
+use smithay_client_toolkit::primary_selection::device::PrimarySelectionDevice;
+use smithay_client_toolkit::seat::pointer::{ThemeSpec, ThemedPointer};
 use smithay_client_toolkit::seat::{Capability, SeatHandler, SeatState};
+use wayland_client::backend::ObjectId;
+use wayland_client::protocol::wl_data_device::WlDataDevice;
+use wayland_client::protocol::wl_keyboard::WlKeyboard;
 use wayland_client::protocol::wl_seat::WlSeat;
-use wayland_client::{Connection, QueueHandle};
+use wayland_client::protocol::wl_touch::WlTouch;
+use wayland_client::{Connection, Proxy, QueueHandle};
+use wayland_protocols::wp::pointer_gestures::zv1::client::zwp_pointer_gesture_hold_v1::ZwpPointerGestureHoldV1;
 
 use crate::wayland::keyboard::KeyboardData;
 use crate::wayland::pointer::PointerUserData;
 use crate::wayland::SurfaceUserData;
+use crate::x11::KeyboardWithFallback;
+
+use super::touch::TouchState;
 
 use super::state::WaylandState;
 
+/// The input devices and focus of a single wl_seat.
+/// Most systems have just one seat, but multi-seat setups and
+/// software such as input-leap, which adds a virtual seat, have more.
+pub(super) struct WaylandSeat {
+    pub(super) seat: WlSeat,
+    pub(super) keyboard: Option<WlKeyboard>,
+    pub(super) keyboard_mapper: Option<KeyboardWithFallback>,
+    pub(super) key_repeat_delay: i32,
+    pub(super) key_repeat_rate: i32,
+    pub(super) keyboard_window_id: Option<usize>,
+    /// The surface that has the keyboard focus of this seat
+    pub(super) keyboard_surface_id: Option<ObjectId>,
+    pub(super) pointer: Option<ThemedPointer<PointerUserData>>,
+    /// Reports fingers being placed on, and lifted from, the touchpad
+    pub(super) pointer_hold: Option<ZwpPointerGestureHoldV1>,
+    pub(super) touch: Option<TouchState>,
+    pub(super) data_device: Option<DataDevice>,
+    pub(super) primary_selection_device: Option<PrimarySelectionDevice>,
+    /// The serial of the most recent input event from this seat
+    pub(super) last_serial: u32,
+}
+
+impl WaylandSeat {
+    fn new(seat: WlSeat) -> Self {
+        Self {
+            seat,
+            keyboard: None,
+            keyboard_mapper: None,
+            key_repeat_rate: 25,
+            key_repeat_delay: 400,
+            keyboard_window_id: None,
+            keyboard_surface_id: None,
+            pointer: None,
+            pointer_hold: None,
+            touch: None,
+            data_device: None,
+            primary_selection_device: None,
+            last_serial: 0,
+        }
+    }
+
+    fn release(self) {
+        if let Some(keyboard) = self.keyboard {
+            keyboard.release();
+        }
+        if let Some(touch) = self.touch {
+            touch.touch.release();
+        }
+        if let Some(hold) = self.pointer_hold {
+            hold.destroy();
+        }
+        // ThemedPointer, DataDevice and PrimarySelectionDevice
+        // release themselves when dropped
+    }
+}
+
+impl WaylandState {
+    pub(super) fn seat_mut(&mut self, seat: &WlSeat) -> &mut WaylandSeat {
+        self.seats
+            .entry(seat.id())
+            .or_insert_with(|| WaylandSeat::new(seat.clone()))
+    }
+
+    /// Remember the serial of an input event from `seat_id`, and make
+    /// that seat the one on whose behalf we set the clipboard, start
+    /// drags and request activation tokens
+    pub(super) fn record_serial(&mut self, seat_id: &ObjectId, serial: u32) {
+        if let Some(seat) = self.seats.get_mut(seat_id) {
+            seat.last_serial = serial;
+            self.active_seat.replace(seat_id.clone());
+        }
+    }
+
+    /// Returns the seat that most recently sent us input, or any seat
+    /// if none has done so yet
+    pub(super) fn active_seat(&self) -> Option<&WaylandSeat> {
+        self.active_seat
+            .as_ref()
+            .and_then(|id| self.seats.get(id))
+            .or_else(|| self.seats.values().next())
+    }
+
+    pub(super) fn seat_for_data_device(&self, data_device: &WlDataDevice) -> Option<&WaylandSeat> {
+        self.seats.values().find(|seat| {
+            seat.data_device
+                .as_ref()
+                .map(|dv| dv.inner() == data_device)
+                .unwrap_or(false)
+        })
+    }
+
+    pub(super) fn touch_state_mut(&mut self, touch: &WlTouch) -> Option<&mut TouchState> {
+        self.seats
+            .values_mut()
+            .find_map(|seat| seat.touch.as_mut().filter(|state| state.touch == *touch))
+    }
+
+    /// Returns true if any seat has keyboard focus on the surface
+    pub(super) fn has_keyboard_focus(&self, surface_id: &ObjectId) -> bool {
+        self.seats
+            .values()
+            .any(|seat| seat.keyboard_surface_id.as_ref() == Some(surface_id))
+    }
+}
+
 impl SeatHandler for WaylandState {
     fn seat_state(&mut self) -> &mut SeatState {
         &mut self.seat
     }
 
-    fn new_seat(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _seat: WlSeat) {
-        todo!()
+    fn new_seat(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, seat: WlSeat) {
+        // Seats that exist at startup are only reported via
+        // new_capability; this is called for seats added later
+        log::trace!("new seat {:?}", seat.id());
+        self.seat_mut(&seat);
     }
 
     fn new_capability(
@@ -26,10 +145,9 @@ impl SeatHandler for WaylandState {
         capability: Capability,
     ) {
         match capability {
-            Capability::Keyboard if self.keyboard.is_none() => {
-                log::trace!("Setting keyboard capability");
-                let keyboard = seat.get_keyboard(qh, KeyboardData {});
-                self.keyboard = Some(keyboard.clone());
+            Capability::Keyboard if self.seat_mut(&seat).keyboard.is_none() => {
+                log::trace!("Setting keyboard capability for {:?}", seat.id());
+                let keyboard = seat.get_keyboard(qh, KeyboardData { seat: seat.clone() });
 
                 // Attach high-resolution timestamps to keyboard if available
                 if let Some(input_timestamps_manager) = &self.input_timestamps_manager {
@@ -44,9 +162,11 @@ impl SeatHandler for WaylandState {
                 if let Some(text_input) = &self.text_input {
                     text_input.advise_seat(&seat, &keyboard, qh);
                 }
+
+                self.seat_mut(&seat).keyboard = Some(keyboard);
             }
-            Capability::Pointer if self.pointer.is_none() => {
-                log::trace!("Setting pointer capability");
+            Capability::Pointer if self.seat_mut(&seat).pointer.is_none() => {
+                log::trace!("Setting pointer capability for {:?}", seat.id());
                 let surface = self.compositor.create_surface(qh);
                 let pointer = self
                     .seat
@@ -70,24 +190,44 @@ impl SeatHandler for WaylandState {
                     log::debug!("Attached high-resolution timestamps to pointer");
                 }
 
-                self.pointer = Some(pointer);
+                let pointer_hold = self
+                    .pointer_gestures
+                    .as_ref()
+                    .map(|gestures| gestures.get_hold_gesture(pointer.pointer(), qh, GlobalData));
+
+                let seat_data = self.seat_mut(&seat);
+                seat_data.pointer = Some(pointer);
+                seat_data.pointer_hold = pointer_hold;
             }
-            Capability::Touch /* if self.touch.is_none() */ => {
-                log::trace!("Setting touch capability");
-                // TODO
+            Capability::Touch if self.seat_mut(&seat).touch.is_none() => {
+                log::trace!("Setting touch capability for {:?}", seat.id());
+                match self.seat.get_touch(qh, &seat) {
+                    Ok(touch) => self.seat_mut(&seat).touch = Some(TouchState::new(touch)),
+                    Err(err) => log::error!("Failed to create touch: {err:#}"),
+                }
             }
             _ => {}
         }
 
-        // TODO: is there a better place to put this? It only needs to be run once. (presumably per-seat)
-        if self.data_device.is_none() {
-            let data_device_manager = &self.data_device_manager_state;
-            self.data_device = Some(data_device_manager.get_data_device(qh, &seat));
-
-            self.primary_selection_device = self
+        // Each seat has its own clipboard and primary selection
+        if self.seat_mut(&seat).data_device.is_none() {
+            let data_device = self.data_device_manager_state.get_data_device(qh, &seat);
+            let primary_selection_device = self
                 .primary_selection_manager
                 .as_ref()
                 .map(|m| m.get_selection_device(qh, &seat));
+
+            let seat_data = self.seat_mut(&seat);
+            seat_data.data_device = Some(data_device);
+            seat_data.primary_selection_device = primary_selection_device;
+        }
+
+        if let Some(tablet) = self.tablet.as_mut() {
+            tablet.advise_seat(&seat, qh);
+        }
+
+        if let Some(idle_notifier) = self.idle_notifier.as_mut() {
+            idle_notifier.advise_seat(&seat, qh);
         }
     }
 
@@ -95,27 +235,47 @@ impl SeatHandler for WaylandState {
         &mut self,
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
-        _seat: WlSeat,
+        seat: WlSeat,
         capability: Capability,
     ) {
+        let seat = match self.seats.get_mut(&seat.id()) {
+            Some(seat) => seat,
+            None => return,
+        };
         match capability {
             Capability::Keyboard => {
                 log::trace!("Lost keyboard capability");
-                self.keyboard.take().map(|k| k.release());
+                seat.keyboard.take().map(|k| k.release());
+                seat.keyboard_surface_id.take();
             }
             Capability::Pointer => {
                 log::trace!("Lost pointer capability");
-                self.pointer.take(); // ThemedPointer's drop implementation calls wl_pointer.release() already.
+                seat.pointer.take(); // ThemedPointer's drop implementation calls wl_pointer.release() already.
+                if let Some(hold) = seat.pointer_hold.take() {
+                    hold.destroy();
+                }
             }
             Capability::Touch => {
                 log::trace!("Lost touch capability");
-                // Nothing to do here. (yet)
+                if let Some(touch) = seat.touch.take() {
+                    touch.touch.release();
+                }
             }
             _ => {}
         }
     }
 
-    fn remove_seat(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _seat: WlSeat) {
-        todo!()
+    fn remove_seat(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, seat: WlSeat) {
+        let seat_id = seat.id();
+        log::trace!("removed seat {:?}", seat_id);
+        if let Some(text_input) = &self.text_input {
+            text_input.forget_seat(&seat);
+        }
+        if let Some(seat) = self.seats.remove(&seat_id) {
+            seat.release();
+        }
+        if self.active_seat.as_ref() == Some(&seat_id) {
+            self.active_seat.take();
+        }
     }
 }
@@ -2,59 +2,89 @@ use std::cell::RefCell;
 use std::collections::HashMap;
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
+use smithay_client_toolkit::activation::ActivationState;
 use smithay_client_toolkit::compositor::{CompositorState, SurfaceData};
-use smithay_client_toolkit::data_device_manager::data_device::DataDevice;
-use smithay_client_toolkit::data_device_manager::data_source::CopyPasteSource;
+use smithay_client_toolkit::data_device_manager::data_source::{CopyPasteSource, DragSource};
 use smithay_client_toolkit::data_device_manager::DataDeviceManagerState;
+use smithay_client_toolkit::dmabuf::DmabufState;
 use smithay_client_toolkit::globals::GlobalData;
 use smithay_client_toolkit::output::{OutputHandler, OutputState};
-use smithay_client_toolkit::primary_selection::device::PrimarySelectionDevice;
 use smithay_client_toolkit::primary_selection::selection::PrimarySelectionSource;
 use smithay_client_toolkit::primary_selection::PrimarySelectionManagerState;
+use smithay_client_toolkit::reexports::protocols::wp::linux_dmabuf::zv1::client::zwp_linux_dmabuf_feedback_v1::ZwpLinuxDmabufFeedbackV1;
 use smithay_client_toolkit::reexports::protocols_wlr::output_management::v1::client::zwlr_output_head_v1::ZwlrOutputHeadV1;
 use smithay_client_toolkit::reexports::protocols_wlr::output_management::v1::client::zwlr_output_manager_v1::ZwlrOutputManagerV1;
 use smithay_client_toolkit::reexports::protocols_wlr::output_management::v1::client::zwlr_output_mode_v1::ZwlrOutputModeV1;
 use smithay_client_toolkit::registry::{ProvidesRegistryState, RegistryState};
-use smithay_client_toolkit::seat::pointer::ThemedPointer;
 use smithay_client_toolkit::seat::SeatState;
+use smithay_client_toolkit::shell::wlr_layer::LayerShell;
 use smithay_client_toolkit::shell::xdg::XdgShell;
 use smithay_client_toolkit::shm::slot::SlotPool;
 use smithay_client_toolkit::shm::{Shm, ShmHandler};
 use smithay_client_toolkit::subcompositor::SubcompositorState;
 use smithay_client_toolkit::{
-    delegate_compositor, delegate_data_device, delegate_output, delegate_pointer, delegate_primary_selection, delegate_registry, delegate_seat, delegate_shm, delegate_subcompositor, delegate_xdg_shell, delegate_xdg_window, registry_handlers
+    delegate_compositor, delegate_data_device, delegate_layer, delegate_output, delegate_pointer, delegate_primary_selection, delegate_registry, delegate_seat, delegate_shm, delegate_subcompositor, delegate_touch, delegate_xdg_shell, delegate_xdg_window, registry_handlers
 };
 use wayland_client::backend::ObjectId;
 use wayland_client::globals::GlobalList;
+use wayland_client::protocol::wl_buffer::WlBuffer;
 use wayland_client::protocol::wl_keyboard::WlKeyboard;
 use wayland_client::protocol::wl_output::WlOutput;
 use wayland_client::protocol::wl_surface::WlSurface;
 use wayland_client::{delegate_dispatch, Connection, QueueHandle};
+use wayland_protocols::wp::alpha_modifier::v1::client::wp_alpha_modifier_surface_v1::WpAlphaModifierSurfaceV1;
+use wayland_protocols::wp::alpha_modifier::v1::client::wp_alpha_modifier_v1::WpAlphaModifierV1;
 use wayland_protocols::wp::commit_timing::v1::client::wp_commit_timing_manager_v1::WpCommitTimingManagerV1;
+use wayland_protocols::wp::cursor_shape::v1::client::wp_cursor_shape_manager_v1::WpCursorShapeManagerV1;
 use wayland_protocols::wp::commit_timing::v1::client::wp_commit_timer_v1::WpCommitTimerV1;
+use wayland_protocols::wp::content_type::v1::client::wp_content_type_manager_v1::WpContentTypeManagerV1;
+use wayland_protocols::wp::content_type::v1::client::wp_content_type_v1::WpContentTypeV1;
+use wayland_protocols::wp::fifo::v1::client::wp_fifo_manager_v1::WpFifoManagerV1;
+use wayland_protocols::wp::fifo::v1::client::wp_fifo_v1::WpFifoV1;
 use wayland_protocols::wp::fractional_scale::v1::client::wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1;
 use wayland_protocols::wp::fractional_scale::v1::client::wp_fractional_scale_v1::WpFractionalScaleV1;
 use wayland_protocols::wp::input_timestamps::zv1::client::zwp_input_timestamps_manager_v1::ZwpInputTimestampsManagerV1;
+use wayland_protocols::wp::keyboard_shortcuts_inhibit::zv1::client::zwp_keyboard_shortcuts_inhibit_manager_v1::ZwpKeyboardShortcutsInhibitManagerV1;
+use wayland_protocols::wp::keyboard_shortcuts_inhibit::zv1::client::zwp_keyboard_shortcuts_inhibitor_v1::{self, ZwpKeyboardShortcutsInhibitorV1};
 use wayland_protocols::wp::input_timestamps::zv1::client::zwp_input_timestamps_v1::ZwpInputTimestampsV1;
+use wayland_protocols::wp::pointer_gestures::zv1::client::zwp_pointer_gestures_v1::ZwpPointerGesturesV1;
 use wayland_protocols::wp::viewporter::client::wp_viewport::WpViewport;
 use wayland_protocols::wp::viewporter::client::wp_viewporter::WpViewporter;
 use wayland_protocols::wp::linux_drm_syncobj::v1::client::wp_linux_drm_syncobj_manager_v1::WpLinuxDrmSyncobjManagerV1;
 use wayland_protocols::wp::linux_drm_syncobj::v1::client::wp_linux_drm_syncobj_surface_v1::WpLinuxDrmSyncobjSurfaceV1;
 use wayland_protocols::wp::linux_drm_syncobj::v1::client::wp_linux_drm_syncobj_timeline_v1::WpLinuxDrmSyncobjTimelineV1;
-use wayland_protocols::wp::presentation_time::client::wp_presentation::WpPresentation;
+use wayland_protocols::wp::presentation_time::client::wp_presentation::{self, WpPresentation};
+use wayland_protocols::wp::single_pixel_buffer::v1::client::wp_single_pixel_buffer_manager_v1::WpSinglePixelBufferManagerV1;
 use wayland_protocols::wp::presentation_time::client::wp_presentation_feedback::WpPresentationFeedback;
 use wayland_protocols::wp::tearing_control::v1::client::wp_tearing_control_manager_v1::WpTearingControlManagerV1;
 use wayland_protocols::wp::tearing_control::v1::client::wp_tearing_control_v1::WpTearingControlV1;
 use wayland_protocols::wp::text_input::zv3::client::zwp_text_input_manager_v3::ZwpTextInputManagerV3;
 use wayland_protocols::wp::text_input::zv3::client::zwp_text_input_v3::ZwpTextInputV3;
+use wayland_protocols::xdg::decoration::zv1::client::zxdg_decoration_manager_v1::ZxdgDecorationManagerV1;
+use wayland_protocols::xdg::dialog::v1::client::xdg_dialog_v1::XdgDialogV1;
+use wayland_protocols::xdg::dialog::v1::client::xdg_wm_dialog_v1::XdgWmDialogV1;
 use wayland_protocols_plasma::blur::client::org_kde_kwin_blur_manager::OrgKdeKwinBlurManager;
+use wayland_protocols_plasma::server_decoration::client::org_kde_kwin_server_decoration_manager::OrgKdeKwinServerDecorationManager;
 
-use crate::x11::KeyboardWithFallback;
+use crate::{ClipboardContents, DragData, WindowEvent};
 
+use super::activation::ACTIVATION_TOKEN_ENV;
+use super::color::{ColorManager, OutputColorInfo};
+use super::dmabuf::FeedbackSummary;
+use super::foreign_toplevel::ForeignToplevelState;
+use super::framestats::PresentedFrame;
+use super::icon::ToplevelIconManager;
+use super::idle::IdleNotifier;
 use super::inputhandler::{TextInputData, TextInputState};
+use super::latency::{InputLatencyTracker, PresentationFeedbackData};
 use super::pointer::{PendingMouse, PointerUserData};
-use super::{OutputManagerData, OutputManagerState, SurfaceUserData, WaylandWindowInner};
+use super::seat::WaylandSeat;
+use super::tablet::TabletState;
+use super::{
+    OutputManagerData, OutputManagerState, SurfaceUserData, WaylandConnection, WaylandWindowInner,
+};
 
 // We can't combine WaylandState and WaylandConnection together because
 // the run_message_loop has &self(WaylandConnection) and needs to update WaylandState as mut
@@ -67,35 +97,61 @@ pub(super) struct WaylandState {
     pub(super) output_manager: Option<OutputManagerState>,
     pub(super) seat: SeatState,
     pub(super) xdg: XdgShell,
+    pub(super) layer_shell: Option<LayerShell>,
     pub(super) windows: RefCell<HashMap<usize, Rc<RefCell<WaylandWindowInner>>>>,
 
-    pub(super) active_surface_id: RefCell<Option<ObjectId>>,
-    pub(super) last_serial: RefCell<u32>,
-    pub(super) keyboard: Option<WlKeyboard>,
-    pub(super) keyboard_mapper: Option<KeyboardWithFallback>,
-    pub(super) key_repeat_delay: i32,
-    pub(super) key_repeat_rate: i32,
-    pub(super) keyboard_window_id: Option<usize>,
+    pub(super) seats: HashMap<ObjectId, WaylandSeat>,
+    /// The seat that most recently sent us input
+    pub(super) active_seat: Option<ObjectId>,
 
-    pub(super) pointer: Option<ThemedPointer<PointerUserData>>,
+    pub(super) tablet: Option<TabletState>,
+    pub(super) dmabuf: DmabufState,
+    pub(super) dmabuf_default_feedback: Option<ZwpLinuxDmabufFeedbackV1>,
+    pub(super) dmabuf_default_summary: Option<FeedbackSummary>,
     pub(super) surface_to_pending: HashMap<ObjectId, Arc<Mutex<PendingMouse>>>,
 
     pub(super) data_device_manager_state: DataDeviceManagerState,
-    pub(super) data_device: Option<DataDevice>,
-    pub(super) copy_paste_source: Option<(CopyPasteSource, String)>,
+    pub(super) copy_paste_source: Option<(CopyPasteSource, ClipboardContents)>,
+    pub(super) drag_source: Option<(DragSource, DragData)>,
     pub(super) primary_selection_manager: Option<PrimarySelectionManagerState>,
-    pub(super) primary_selection_device: Option<PrimarySelectionDevice>,
-    pub(super) primary_selection_source: Option<(PrimarySelectionSource, String)>,
+    pub(super) primary_selection_source: Option<(PrimarySelectionSource, ClipboardContents)>,
     pub(super) shm: Shm,
     pub(super) mem_pool: RefCell<SlotPool>,
     pub(super) kde_blur_manager: Option<OrgKdeKwinBlurManager>,
+    /// Only bound when the compositor lacks zxdg_decoration_manager_v1
+    pub(super) kde_server_decoration_manager: Option<OrgKdeKwinServerDecorationManager>,
     pub(super) presentation: Option<WpPresentation>,
+    /// The clock used by wp_presentation for its timestamps
+    pub(super) presentation_clock_id: Option<u32>,
     pub(super) input_timestamps_manager: Option<ZwpInputTimestampsManagerV1>,
+    pub(super) input_latency: InputLatencyTracker,
     pub(super) commit_timing_manager: Option<WpCommitTimingManagerV1>,
     pub(super) drm_syncobj_manager: Option<WpLinuxDrmSyncobjManagerV1>,
     pub(super) fractional_scale_manager: Option<WpFractionalScaleManagerV1>,
     pub(super) viewporter: Option<WpViewporter>,
     pub(super) tearing_control_manager: Option<WpTearingControlManagerV1>,
+    pub(super) content_type_manager: Option<WpContentTypeManagerV1>,
+    pub(super) fifo_manager: Option<WpFifoManagerV1>,
+    pub(super) single_pixel_buffer_manager: Option<WpSinglePixelBufferManagerV1>,
+    pub(super) xdg_wm_dialog: Option<XdgWmDialogV1>,
+    pub(super) alpha_modifier: Option<WpAlphaModifierV1>,
+    pub(super) color_manager: Option<ColorManager>,
+    /// Colorimetry of each output, as reported via color management
+    pub(super) output_color: HashMap<ObjectId, OutputColorInfo>,
+    pub(super) foreign_toplevel: Option<ForeignToplevelState>,
+    pub(super) toplevel_icon_manager: Option<ToplevelIconManager>,
+    pub(super) idle_notifier: Option<IdleNotifier>,
+    pub(super) activation: Option<ActivationState>,
+    pub(super) keyboard_shortcuts_inhibit_manager: Option<ZwpKeyboardShortcutsInhibitManagerV1>,
+    /// Used to find out when the fingers are placed on the touchpad,
+    /// which stops kinetic scrolling.  Hold gestures need version 3
+    pub(super) pointer_gestures: Option<ZwpPointerGesturesV1>,
+    /// The activation token that we were launched with, if any.
+    /// It is used to activate the first window that we map.
+    pub(super) startup_activation_token: Option<String>,
+    /// A token requested ahead of time for the next spawned
+    /// child process; see WaylandConnection::take_activation_token
+    pub(super) spawn_activation_token: Option<String>,
 }
 
 impl WaylandState {
@@ -115,6 +171,30 @@ impl WaylandState {
         let fractional_scale_manager: Option<WpFractionalScaleManagerV1> = globals.bind(qh, 1..=1, GlobalData).ok();
         let viewporter: Option<WpViewporter> = globals.bind(qh, 1..=1, GlobalData).ok();
         let tearing_control_manager: Option<WpTearingControlManagerV1> = globals.bind(qh, 1..=1, GlobalData).ok();
+        let content_type_manager: Option<WpContentTypeManagerV1> =
+            globals.bind(qh, 1..=1, GlobalData).ok();
+        let fifo_manager: Option<WpFifoManagerV1> = globals.bind(qh, 1..=1, GlobalData).ok();
+        let single_pixel_buffer_manager: Option<WpSinglePixelBufferManagerV1> =
+            globals.bind(qh, 1..=1, GlobalData).ok();
+        let xdg_wm_dialog: Option<XdgWmDialogV1> = globals.bind(qh, 1..=1, GlobalData).ok();
+        let alpha_modifier: Option<WpAlphaModifierV1> = globals.bind(qh, 1..=1, GlobalData).ok();
+        let color_manager = ColorManager::bind(globals, qh);
+        let foreign_toplevel = ForeignToplevelState::bind(globals, qh);
+        let tablet = TabletState::bind(globals, qh);
+        let dmabuf = DmabufState::new(globals, qh);
+        let dmabuf_default_feedback = dmabuf.get_default_feedback(qh).ok();
+        let toplevel_icon_manager = ToplevelIconManager::bind(globals, qh);
+        let idle_notifier = IdleNotifier::bind(globals, qh);
+        let activation = ActivationState::bind(globals, qh).ok();
+        let keyboard_shortcuts_inhibit_manager: Option<ZwpKeyboardShortcutsInhibitManagerV1> =
+            globals.bind(qh, 1..=1, GlobalData).ok();
+        let pointer_gestures: Option<ZwpPointerGesturesV1> =
+            globals.bind(qh, 3..=3, GlobalData).ok();
+
+        // The token is only good for a single activation, so take
+        // care not to leak it into the environment of our children
+        let startup_activation_token = std::env::var(ACTIVATION_TOKEN_ENV).ok();
+        std::env::remove_var(ACTIVATION_TOKEN_ENV);
 
         if presentation.is_some() {
             log::info!("wp_presentation protocol available - enabling presentation timing");
@@ -152,12 +232,132 @@ impl WaylandState {
             log::warn!("wp_viewporter protocol not available - scaling disabled");
         }
 
+        if keyboard_shortcuts_inhibit_manager.is_some() {
+            log::info!("zwp_keyboard_shortcuts_inhibit_manager_v1 protocol available - ToggleShortcutInhibit is supported");
+        } else {
+            log::warn!("zwp_keyboard_shortcuts_inhibit_manager_v1 protocol not available - compositor shortcuts cannot be inhibited");
+        }
+
+        // SeatState binds the cursor shape manager itself when the first
+        // pointer is created; ThemedPointer then asks the compositor for
+        // the shape by name rather than loading the cursor theme
+        let cursor_shape_available = globals.contents().with_list(|list| {
+            list.iter()
+                .any(|global| global.interface == WpCursorShapeManagerV1::interface().name)
+        });
+        // Older KDE releases and a few other compositors only offer
+        // server-side decorations through KDE's own protocol, so fall
+        // back to that when the standard one is missing
+        let xdg_decoration_available = globals.contents().with_list(|list| {
+            list.iter()
+                .any(|global| global.interface == ZxdgDecorationManagerV1::interface().name)
+        });
+        let kde_server_decoration_manager: Option<OrgKdeKwinServerDecorationManager> =
+            if xdg_decoration_available {
+                None
+            } else {
+                globals.bind(qh, 1..=1, GlobalData).ok()
+            };
+        if kde_server_decoration_manager.is_some() {
+            log::info!("zxdg_decoration_manager_v1 protocol not available - using org_kde_kwin_server_decoration_manager for server-side decorations");
+        }
+
+        if cursor_shape_available {
+            log::info!("wp_cursor_shape_manager_v1 protocol available - cursors will be rendered by the compositor");
+        } else {
+            log::warn!("wp_cursor_shape_manager_v1 protocol not available - using client-side cursor themes");
+        }
+
         if tearing_control_manager.is_some() {
             log::info!("wp_tearing_control_v1 protocol available - can enable low-latency async presentation");
         } else {
             log::warn!("wp_tearing_control_v1 protocol not available - vsync-only presentation");
         }
 
+        if content_type_manager.is_some() {
+            log::info!("wp_content_type_v1 protocol available - enabling content type hints");
+        } else {
+            log::warn!("wp_content_type_v1 protocol not available - no content type hints");
+        }
+
+        if fifo_manager.is_some() {
+            log::info!(
+                "wp_fifo_manager_v1 protocol available - frames will be queued by the compositor"
+            );
+        } else {
+            log::warn!(
+                "wp_fifo_manager_v1 protocol not available - frames are paced by frame callbacks"
+            );
+        }
+
+        if single_pixel_buffer_manager.is_some() {
+            log::info!("wp_single_pixel_buffer_v1 protocol available - solid backgrounds will be drawn by the compositor");
+        } else {
+            log::warn!("wp_single_pixel_buffer_v1 protocol not available - rendering solid backgrounds ourselves");
+        }
+
+        if xdg_wm_dialog.is_some() {
+            log::info!("xdg_wm_dialog_v1 protocol available - confirmations use modal dialogs");
+        } else {
+            log::warn!("xdg_wm_dialog_v1 protocol not available - confirmations will be shown inside the window");
+        }
+
+        if alpha_modifier.is_some() {
+            log::info!("wp_alpha_modifier_v1 protocol available - background opacity will be applied by the compositor");
+        } else {
+            log::warn!("wp_alpha_modifier_v1 protocol not available - background opacity will be baked into buffers");
+        }
+
+        if color_manager.is_some() {
+            log::info!("wp_color_manager_v1 protocol available - surfaces will be tagged as sRGB");
+        } else {
+            log::warn!("wp_color_manager_v1 protocol not available - compositor will assume sRGB");
+        }
+
+        if toplevel_icon_manager.is_some() {
+            log::info!("xdg_toplevel_icon_manager_v1 protocol available - enabling window icons");
+        } else {
+            log::warn!("xdg_toplevel_icon_manager_v1 protocol not available - compositor will choose window icons");
+        }
+
+        if idle_notifier.is_some() {
+            log::info!(
+                "ext_idle_notifier_v1 protocol available - animations will pause while idle"
+            );
+        } else {
+            log::warn!(
+                "ext_idle_notifier_v1 protocol not available - animations will run while idle"
+            );
+        }
+
+        if dmabuf_default_feedback.is_some() {
+            log::info!("zwp_linux_dmabuf_v1 feedback available - can report scanout capability");
+        } else {
+            log::warn!("zwp_linux_dmabuf_v1 version 4 not available - scanout capability unknown");
+        }
+
+        if tablet.is_some() {
+            log::info!("zwp_tablet_manager_v2 protocol available - enabling stylus input");
+        } else {
+            log::warn!(
+                "zwp_tablet_manager_v2 protocol not available - stylus input will be ignored"
+            );
+        }
+
+        match &foreign_toplevel {
+            Some(toplevels) if toplevels.has_focus_information() => {
+                log::info!("foreign toplevel protocols available - toplevels and focus changes can be queried");
+            }
+            Some(_) => {
+                log::info!("ext_foreign_toplevel_list_v1 protocol available - toplevels can be queried, but not focus changes");
+            }
+            None => {
+                log::warn!(
+                    "foreign toplevel protocols not available - toplevels cannot be queried"
+                );
+            }
+        }
+
         let wayland_state = WaylandState {
             registry: RegistryState::new(globals),
             output: OutputState::new(globals, qh),
@@ -172,31 +372,47 @@ impl WaylandState {
             windows: RefCell::new(HashMap::new()),
             seat: SeatState::new(globals, qh),
             xdg: XdgShell::bind(globals, qh)?,
-            active_surface_id: RefCell::new(None),
-            last_serial: RefCell::new(0),
-            keyboard: None,
-            keyboard_mapper: None,
-            key_repeat_rate: 25,
-            key_repeat_delay: 400,
-            keyboard_window_id: None,
-            pointer: None,
+            layer_shell: LayerShell::bind(globals, qh).ok(),
+            seats: HashMap::new(),
+            active_seat: None,
+            tablet,
+            dmabuf,
+            dmabuf_default_feedback,
+            dmabuf_default_summary: None,
             surface_to_pending: HashMap::new(),
             data_device_manager_state: DataDeviceManagerState::bind(globals, qh)?,
-            data_device: None,
             copy_paste_source: None,
+            drag_source: None,
             primary_selection_manager: PrimarySelectionManagerState::bind(globals, qh).ok(),
-            primary_selection_device: None,
             primary_selection_source: None,
             shm,
             mem_pool: RefCell::new(mem_pool),
             kde_blur_manager: blur_manager,
+            kde_server_decoration_manager,
             presentation,
+            presentation_clock_id: None,
             input_timestamps_manager,
+            input_latency: InputLatencyTracker::default(),
             commit_timing_manager,
             drm_syncobj_manager,
             fractional_scale_manager,
             viewporter,
             tearing_control_manager,
+            content_type_manager,
+            fifo_manager,
+            single_pixel_buffer_manager,
+            xdg_wm_dialog,
+            alpha_modifier,
+            color_manager,
+            output_color: HashMap::new(),
+            foreign_toplevel,
+            toplevel_icon_manager,
+            idle_notifier,
+            activation,
+            keyboard_shortcuts_inhibit_manager,
+            pointer_gestures,
+            startup_activation_token,
+            spawn_activation_token: None,
         };
         Ok(wayland_state)
     }
@@ -221,16 +437,27 @@ impl OutputHandler for WaylandState {
         &mut self.output
     }
 
-    fn new_output(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _output: WlOutput) {
+    fn new_output(&mut self, _conn: &Connection, qh: &QueueHandle<Self>, output: WlOutput) {
         log::trace!("new output: OutputHandler");
+        if let Some(color_manager) = &self.color_manager {
+            color_manager.watch_output(qh, &output);
+        }
     }
 
     fn update_output(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _output: WlOutput) {
         log::trace!("update output: OutputHandler");
+        // The mode, and thus the refresh rate, may have changed
+        for window_id in self.windows.borrow().keys() {
+            WaylandConnection::with_window_inner(*window_id, |inner| {
+                inner.update_refresh_interval();
+                Ok(())
+            });
+        }
     }
 
-    fn output_destroyed(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _output: WlOutput) {
+    fn output_destroyed(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, output: WlOutput) {
         log::trace!("output destroyed: OutputHandler");
+        self.output_color.remove(&output.id());
     }
 }
 
@@ -248,8 +475,11 @@ delegate_data_device!(WaylandState);
 
 delegate_pointer!(WaylandState, pointer: [PointerUserData]);
 
+delegate_touch!(WaylandState);
+
 delegate_xdg_shell!(WaylandState);
 delegate_xdg_window!(WaylandState);
+delegate_layer!(WaylandState);
 
 delegate_primary_selection!(WaylandState);
 
@@ -279,7 +509,7 @@ impl Dispatch<ZwpInputTimestampsManagerV1, GlobalData> for WaylandState {
 
 impl Dispatch<ZwpInputTimestampsV1, WlKeyboard> for WaylandState {
     fn event(
-        _state: &mut Self,
+        state: &mut Self,
         _proxy: &ZwpInputTimestampsV1,
         event: <ZwpInputTimestampsV1 as wayland_client::Proxy>::Event,
         _data: &WlKeyboard,
@@ -291,8 +521,7 @@ impl Dispatch<ZwpInputTimestampsV1, WlKeyboard> for WaylandState {
                 let tv_sec = ((tv_sec_hi as u64) << 32) | (tv_sec_lo as u64);
                 let timestamp_ns = tv_sec * 1_000_000_000 + tv_nsec as u64;
                 log::trace!("Keyboard input timestamp: {}ns", timestamp_ns);
-                // TODO: Store this timestamp and use it to calculate input latency
-                // when combined with presentation feedback timestamps
+                state.input_latency.record_input(timestamp_ns);
             }
             _ => {}
         }
@@ -301,7 +530,7 @@ impl Dispatch<ZwpInputTimestampsV1, WlKeyboard> for WaylandState {
 
 impl Dispatch<ZwpInputTimestampsV1, WlPointer> for WaylandState {
     fn event(
-        _state: &mut Self,
+        state: &mut Self,
         _proxy: &ZwpInputTimestampsV1,
         event: <ZwpInputTimestampsV1 as wayland_client::Proxy>::Event,
         _data: &WlPointer,
@@ -313,8 +542,7 @@ impl Dispatch<ZwpInputTimestampsV1, WlPointer> for WaylandState {
                 let tv_sec = ((tv_sec_hi as u64) << 32) | (tv_sec_lo as u64);
                 let timestamp_ns = tv_sec * 1_000_000_000 + tv_nsec as u64;
                 log::trace!("Pointer input timestamp: {}ns", timestamp_ns);
-                // TODO: Store this timestamp and use it to calculate input latency
-                // when combined with presentation feedback timestamps
+                state.input_latency.record_input(timestamp_ns);
             }
             _ => {}
         }
@@ -354,12 +582,26 @@ impl Dispatch<WpFractionalScaleV1, WlSurface> for WaylandState {
 
                 // Update the window's fractional scale
                 let surface_id = surface.id();
-                for window in state.windows.borrow().values() {
+                for (window_id, window) in state.windows.borrow().iter() {
                     let mut inner = window.borrow_mut();
                     if inner.surface().id() == surface_id {
+                        if inner.current_fractional_scale == Some(scale) {
+                            break;
+                        }
                         inner.current_fractional_scale = Some(scale);
                         log::info!("Applied fractional scale {:.2}x to window", scale_factor);
-                        // The surface will be resized on next configure event
+                        // Re-render at the new scale; dispatch_pending_event
+                        // picks up the scale from current_fractional_scale
+                        inner
+                            .pending_event
+                            .lock()
+                            .unwrap()
+                            .dpi
+                            .replace((scale_factor * crate::DEFAULT_DPI) as i32);
+                        WaylandConnection::with_window_inner(*window_id, |inner| {
+                            inner.dispatch_pending_event();
+                            Ok(())
+                        });
                         break;
                     }
                 }
@@ -470,23 +712,28 @@ use wayland_protocols::wp::presentation_time::client::wp_presentation_feedback::
 
 impl Dispatch<WpPresentation, GlobalData> for WaylandState {
     fn event(
-        _state: &mut Self,
+        state: &mut Self,
         _proxy: &WpPresentation,
-        _event: <WpPresentation as wayland_client::Proxy>::Event,
+        event: <WpPresentation as wayland_client::Proxy>::Event,
         _data: &GlobalData,
         _conn: &Connection,
         _qhandle: &QueueHandle<Self>,
     ) {
-        // wp_presentation has only one event: clk_id, which we don't need to handle
+        // wp_presentation has only one event: clk_id, which we need in
+        // order to compare presentation times with other timestamps
+        if let wp_presentation::Event::ClockId { clk_id } = event {
+            log::debug!("presentation clock id {clk_id}");
+            state.presentation_clock_id.replace(clk_id);
+        }
     }
 }
 
-impl Dispatch<WpPresentationFeedback, WlSurface> for WaylandState {
+impl Dispatch<WpPresentationFeedback, PresentationFeedbackData> for WaylandState {
     fn event(
         state: &mut Self,
         _proxy: &WpPresentationFeedback,
         event: <WpPresentationFeedback as wayland_client::Proxy>::Event,
-        surface: &WlSurface,
+        data: &PresentationFeedbackData,
         _conn: &Connection,
         _qhandle: &QueueHandle<Self>,
     ) {
@@ -519,7 +766,7 @@ impl Dispatch<WpPresentationFeedback, WlSurface> for WaylandState {
                 // Combine MSC (Media Stream Counter) from high and low parts
                 let msc = ((seq_hi as u64) << 32) | (seq_lo as u64);
 
-                log::info!(
+                log::trace!(
                     "presentation feedback: time={}ns, refresh={}ns, msc={}, vsync={}, hw_clock={}, hw_completion={}, zero_copy={}",
                     presentation_time_ns,
                     refresh,
@@ -530,18 +777,74 @@ impl Dispatch<WpPresentationFeedback, WlSurface> for WaylandState {
                     zero_copy
                 );
 
+                let latency = data.input_time_ns.and_then(|input| {
+                    state.input_latency.measure(
+                        state.presentation_clock_id,
+                        input,
+                        presentation_time_ns,
+                    )
+                });
+                if let Some(latency) = latency {
+                    log::trace!("input latency {latency:?}");
+                    metrics::histogram!("window.input_latency").record(latency);
+                }
+
                 // Update the last presentation time for the window
-                let surface_id = surface.id();
-                for window in state.windows.borrow().values() {
+                let surface_id = data.surface.id();
+                for (window_id, window) in state.windows.borrow().iter() {
                     let mut inner = window.borrow_mut();
                     if inner.surface().id() == surface_id {
                         inner.last_presentation_time = Some(presentation_time_ns);
+                        if inner.refresh_interval_ns != refresh {
+                            // We may have moved to a different output
+                            WaylandConnection::with_window_inner(*window_id, |inner| {
+                                inner.update_refresh_interval();
+                                Ok(())
+                            });
+                        }
+                        inner.refresh_interval_ns = refresh;
+                        inner.frames_presented += 1;
+                        if zero_copy {
+                            inner.frames_zero_copy += 1;
+                        }
+                        let frame = PresentedFrame {
+                            vsync,
+                            zero_copy,
+                            frame_time: data.committed_ns.and_then(|committed| {
+                                presentation_time_ns
+                                    .checked_sub(committed)
+                                    .map(Duration::from_nanos)
+                            }),
+                            refresh: Some(Duration::from_nanos(refresh as u64))
+                                .filter(|refresh| !refresh.is_zero()),
+                        };
+                        if let Some(stats) = inner.frame_stats.presented(frame) {
+                            inner.events.dispatch(WindowEvent::FrameStatsUpdated(stats));
+                        }
+                        // The fifo queue has room for another frame
+                        inner.fifo_frame_queued = false;
+                        if let Some(latency) = latency {
+                            inner
+                                .events
+                                .dispatch(WindowEvent::InputLatencyMeasured(latency));
+                        }
                         break;
                     }
                 }
             }
             PresentationEvent::Discarded => {
                 log::trace!("presentation feedback discarded");
+                let surface_id = data.surface.id();
+                for window in state.windows.borrow().values() {
+                    let mut inner = window.borrow_mut();
+                    if inner.surface().id() == surface_id {
+                        inner.fifo_frame_queued = false;
+                        if let Some(stats) = inner.frame_stats.discarded() {
+                            inner.events.dispatch(WindowEvent::FrameStatsUpdated(stats));
+                        }
+                        break;
+                    }
+                }
             }
             _ => {}
         }
@@ -575,3 +878,179 @@ impl Dispatch<WpTearingControlV1, WlSurface> for WaylandState {
         // Default is vsync. Can be set to async for low-latency with tearing acceptable.
     }
 }
+
+// Keyboard shortcuts inhibit event handlers
+impl Dispatch<ZwpKeyboardShortcutsInhibitManagerV1, GlobalData> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ZwpKeyboardShortcutsInhibitManagerV1,
+        _event: <ZwpKeyboardShortcutsInhibitManagerV1 as wayland_client::Proxy>::Event,
+        _data: &GlobalData,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        // Manager has no events
+    }
+}
+
+impl Dispatch<ZwpKeyboardShortcutsInhibitorV1, WlSurface> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ZwpKeyboardShortcutsInhibitorV1,
+        event: <ZwpKeyboardShortcutsInhibitorV1 as wayland_client::Proxy>::Event,
+        surface: &WlSurface,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        // The compositor may decline, or later revoke, the inhibitor;
+        // eg: via its own escape hatch key combination
+        match event {
+            zwp_keyboard_shortcuts_inhibitor_v1::Event::Active => {
+                log::info!("keyboard shortcuts inhibited for {:?}", surface.id());
+            }
+            zwp_keyboard_shortcuts_inhibitor_v1::Event::Inactive => {
+                log::info!(
+                    "keyboard shortcuts no longer inhibited for {:?}",
+                    surface.id()
+                );
+            }
+            _ => {}
+        }
+    }
+}
+
+// Content type event handlers
+impl Dispatch<WpContentTypeManagerV1, GlobalData> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &WpContentTypeManagerV1,
+        _event: <WpContentTypeManagerV1 as wayland_client::Proxy>::Event,
+        _data: &GlobalData,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        // Manager has no events
+    }
+}
+
+impl Dispatch<WpContentTypeV1, WlSurface> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &WpContentTypeV1,
+        _event: <WpContentTypeV1 as wayland_client::Proxy>::Event,
+        _surface: &WlSurface,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        // Content type has no events - only set_content_type request
+    }
+}
+
+// FIFO event handlers
+impl Dispatch<WpFifoManagerV1, GlobalData> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &WpFifoManagerV1,
+        _event: <WpFifoManagerV1 as wayland_client::Proxy>::Event,
+        _data: &GlobalData,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        // Manager has no events
+    }
+}
+
+impl Dispatch<WpFifoV1, WlSurface> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &WpFifoV1,
+        _event: <WpFifoV1 as wayland_client::Proxy>::Event,
+        _surface: &WlSurface,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        // Fifo has no events - only set_barrier and wait_barrier requests
+    }
+}
+
+// Single pixel buffer event handlers
+impl Dispatch<WpSinglePixelBufferManagerV1, GlobalData> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &WpSinglePixelBufferManagerV1,
+        _event: <WpSinglePixelBufferManagerV1 as wayland_client::Proxy>::Event,
+        _data: &GlobalData,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        // Manager has no events
+    }
+}
+
+impl Dispatch<WlBuffer, ()> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &WlBuffer,
+        _event: <WlBuffer as wayland_client::Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        // Single pixel buffers are never written to, so there is
+        // nothing to do when the compositor releases them
+    }
+}
+
+// Alpha modifier event handlers
+impl Dispatch<WpAlphaModifierV1, GlobalData> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &WpAlphaModifierV1,
+        _event: <WpAlphaModifierV1 as wayland_client::Proxy>::Event,
+        _data: &GlobalData,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        // Manager has no events
+    }
+}
+
+impl Dispatch<WpAlphaModifierSurfaceV1, WlSurface> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &WpAlphaModifierSurfaceV1,
+        _event: <WpAlphaModifierSurfaceV1 as wayland_client::Proxy>::Event,
+        _surface: &WlSurface,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        // Alpha modifier surface has no events - only set_multiplier request
+    }
+}
+
+// xdg-dialog event handlers
+impl Dispatch<XdgWmDialogV1, GlobalData> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &XdgWmDialogV1,
+        _event: <XdgWmDialogV1 as wayland_client::Proxy>::Event,
+        _data: &GlobalData,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        // Manager has no events
+    }
+}
+
+impl Dispatch<XdgDialogV1, GlobalData> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &XdgDialogV1,
+        _event: <XdgDialogV1 as wayland_client::Proxy>::Event,
+        _data: &GlobalData,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        // Dialog has no events - only set_modal/unset_modal requests
+    }
+}
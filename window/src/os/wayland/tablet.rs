@@ -0,0 +1,327 @@
+//! Translating graphics tablet (stylus) input into mouse events
+
+use std::sync::Mutex;
+
+use smithay_client_toolkit::globals::GlobalData;
+use smithay_client_toolkit::reexports::protocols::wp::cursor_shape::v1::client::wp_cursor_shape_device_v1::{
+    Shape, WpCursorShapeDeviceV1,
+};
+use smithay_client_toolkit::seat::pointer::cursor_shape::CursorShapeManager;
+use wayland_client::backend::ObjectId;
+use wayland_client::globals::GlobalList;
+use wayland_client::protocol::wl_seat::WlSeat;
+use wayland_client::{event_created_child, Connection, Dispatch, Proxy, QueueHandle, WEnum};
+use wayland_protocols::wp::tablet::zv2::client::zwp_tablet_manager_v2::ZwpTabletManagerV2;
+use wayland_protocols::wp::tablet::zv2::client::zwp_tablet_pad_group_v2::{
+    self, ZwpTabletPadGroupV2,
+};
+use wayland_protocols::wp::tablet::zv2::client::zwp_tablet_pad_ring_v2::ZwpTabletPadRingV2;
+use wayland_protocols::wp::tablet::zv2::client::zwp_tablet_pad_strip_v2::ZwpTabletPadStripV2;
+use wayland_protocols::wp::tablet::zv2::client::zwp_tablet_pad_v2::{self, ZwpTabletPadV2};
+use wayland_protocols::wp::tablet::zv2::client::zwp_tablet_seat_v2::{self, ZwpTabletSeatV2};
+use wayland_protocols::wp::tablet::zv2::client::zwp_tablet_tool_v2::{
+    self, ButtonState, ZwpTabletToolV2,
+};
+use wayland_protocols::wp::tablet::zv2::client::zwp_tablet_v2::{self, ZwpTabletV2};
+use wezterm_input_types::{MouseEventKind, MousePress};
+
+use crate::WindowEvent;
+
+use super::state::WaylandState;
+use super::{SurfaceUserData, WaylandConnection};
+
+pub(super) struct TabletState {
+    manager: ZwpTabletManagerV2,
+    cursor_shape_manager: Option<CursorShapeManager>,
+    tablet_seat: Option<ZwpTabletSeatV2>,
+    seat_id: Option<ObjectId>,
+}
+
+impl TabletState {
+    pub(super) fn bind(globals: &GlobalList, qh: &QueueHandle<WaylandState>) -> Option<Self> {
+        let manager: ZwpTabletManagerV2 = globals.bind(qh, 1..=1, GlobalData).ok()?;
+        Some(Self {
+            manager,
+            cursor_shape_manager: CursorShapeManager::bind(globals, qh).ok(),
+            tablet_seat: None,
+            seat_id: None,
+        })
+    }
+
+    /// Start receiving tablet events for `seat`.
+    /// We only track the tablets of the first seat.
+    pub(super) fn advise_seat(&mut self, seat: &WlSeat, qh: &QueueHandle<WaylandState>) {
+        if self.tablet_seat.is_none() {
+            self.tablet_seat = Some(self.manager.get_tablet_seat(seat, qh, ()));
+            self.seat_id = Some(seat.id());
+        }
+    }
+}
+
+impl WaylandState {
+    fn record_tablet_serial(&mut self, serial: u32) {
+        if let Some(seat_id) = self.tablet.as_ref().and_then(|t| t.seat_id.clone()) {
+            self.record_serial(&seat_id, serial);
+        }
+    }
+}
+
+/// Map the stylus barrel buttons to the mouse buttons configured
+/// by `stylus_barrel_buttons`, so that they can be used in mouse bindings.
+/// See BTN_STYLUS and friends in <linux/input-event-codes.h>
+fn stylus_button(button: u32) -> Option<MousePress> {
+    let index = match button {
+        0x14b => 0,
+        0x14c => 1,
+        0x149 => 2,
+        _ => return None,
+    };
+    config::configuration()
+        .stylus_barrel_buttons
+        .get(index)
+        .copied()
+}
+
+#[derive(Default)]
+struct ToolState {
+    window_id: Option<usize>,
+    position: (f64, f64),
+    cursor: Option<WpCursorShapeDeviceV1>,
+    /// Buttons that we have reported as pressed, so that we can
+    /// release them if the tool leaves the window while held
+    pressed: Vec<MousePress>,
+}
+
+#[derive(Default)]
+pub(super) struct TabletToolData {
+    state: Mutex<ToolState>,
+}
+
+fn dispatch_mouse(window_id: usize, kind: MouseEventKind, position: (f64, f64)) {
+    WaylandConnection::with_window_inner(window_id, move |inner| {
+        inner.dispatch_synthesized_mouse_event(kind, position);
+        Ok(())
+    });
+}
+
+impl Dispatch<ZwpTabletManagerV2, GlobalData> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ZwpTabletManagerV2,
+        _event: <ZwpTabletManagerV2 as Proxy>::Event,
+        _data: &GlobalData,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        // Manager has no events
+    }
+}
+
+impl Dispatch<ZwpTabletSeatV2, ()> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ZwpTabletSeatV2,
+        event: <ZwpTabletSeatV2 as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        log::trace!("tablet seat {event:?}");
+    }
+
+    event_created_child!(WaylandState, ZwpTabletSeatV2, [
+        zwp_tablet_seat_v2::EVT_TABLET_ADDED_OPCODE => (ZwpTabletV2, ()),
+        zwp_tablet_seat_v2::EVT_TOOL_ADDED_OPCODE => (ZwpTabletToolV2, TabletToolData::default()),
+        zwp_tablet_seat_v2::EVT_PAD_ADDED_OPCODE => (ZwpTabletPadV2, ()),
+    ]);
+}
+
+impl Dispatch<ZwpTabletV2, ()> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        tablet: &ZwpTabletV2,
+        event: <ZwpTabletV2 as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        log::trace!("tablet {event:?}");
+        if let zwp_tablet_v2::Event::Removed = event {
+            tablet.destroy();
+        }
+    }
+}
+
+impl Dispatch<ZwpTabletToolV2, TabletToolData> for WaylandState {
+    fn event(
+        state: &mut Self,
+        tool: &ZwpTabletToolV2,
+        event: <ZwpTabletToolV2 as Proxy>::Event,
+        data: &TabletToolData,
+        _conn: &Connection,
+        qhandle: &QueueHandle<Self>,
+    ) {
+        let mut tool_state = data.state.lock().unwrap();
+
+        match event {
+            zwp_tablet_tool_v2::Event::ProximityIn {
+                serial, surface, ..
+            } => {
+                state.record_tablet_serial(serial);
+                // Ignore the window decorations
+                tool_state.window_id = SurfaceUserData::try_from_wl(&surface).map(|d| d.window_id);
+
+                // Without a cursor the compositor may not show anything
+                // at all while the stylus is hovering
+                if let Some(manager) = state
+                    .tablet
+                    .as_ref()
+                    .and_then(|tablet| tablet.cursor_shape_manager.as_ref())
+                {
+                    let cursor = tool_state.cursor.get_or_insert_with(|| {
+                        manager
+                            .inner()
+                            .get_tablet_tool_v2(tool, qhandle, GlobalData)
+                    });
+                    cursor.set_shape(serial, Shape::Text);
+                }
+            }
+            zwp_tablet_tool_v2::Event::ProximityOut => {
+                if let Some(window_id) = tool_state.window_id.take() {
+                    let position = tool_state.position;
+                    let pressed = std::mem::take(&mut tool_state.pressed);
+                    WaylandConnection::with_window_inner(window_id, move |inner| {
+                        for button in pressed {
+                            inner.dispatch_synthesized_mouse_event(
+                                MouseEventKind::Release(button),
+                                position,
+                            );
+                        }
+                        inner.events.dispatch(WindowEvent::MouseLeave);
+                        Ok(())
+                    });
+                }
+            }
+            zwp_tablet_tool_v2::Event::Motion { x, y } => {
+                tool_state.position = (x, y);
+                if let Some(window_id) = tool_state.window_id {
+                    dispatch_mouse(window_id, MouseEventKind::Move, (x, y));
+                }
+            }
+            zwp_tablet_tool_v2::Event::Down { serial } => {
+                state.record_tablet_serial(serial);
+                if let Some(window_id) = tool_state.window_id {
+                    tool_state.pressed.push(MousePress::Left);
+                    dispatch_mouse(
+                        window_id,
+                        MouseEventKind::Press(MousePress::Left),
+                        tool_state.position,
+                    );
+                }
+            }
+            zwp_tablet_tool_v2::Event::Up => {
+                if let Some(window_id) = tool_state.window_id {
+                    tool_state.pressed.retain(|b| *b != MousePress::Left);
+                    dispatch_mouse(
+                        window_id,
+                        MouseEventKind::Release(MousePress::Left),
+                        tool_state.position,
+                    );
+                }
+            }
+            zwp_tablet_tool_v2::Event::Button {
+                serial,
+                button,
+                state: button_state,
+            } => {
+                state.record_tablet_serial(serial);
+                let (window_id, button) = match (tool_state.window_id, stylus_button(button)) {
+                    (Some(window_id), Some(button)) => (window_id, button),
+                    _ => return,
+                };
+                let kind = match button_state {
+                    WEnum::Value(ButtonState::Pressed) => {
+                        tool_state.pressed.push(button);
+                        MouseEventKind::Press(button)
+                    }
+                    WEnum::Value(ButtonState::Released) => {
+                        tool_state.pressed.retain(|b| *b != button);
+                        MouseEventKind::Release(button)
+                    }
+                    _ => return,
+                };
+                dispatch_mouse(window_id, kind, tool_state.position);
+            }
+            zwp_tablet_tool_v2::Event::Removed => {
+                if let Some(cursor) = tool_state.cursor.take() {
+                    cursor.destroy();
+                }
+                tool.destroy();
+            }
+            _ => {}
+        }
+    }
+}
+
+// We don't do anything with the buttons, rings and strips of tablet pads,
+// but we do need to handle the objects that the compositor creates for them
+
+impl Dispatch<ZwpTabletPadV2, ()> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        pad: &ZwpTabletPadV2,
+        event: <ZwpTabletPadV2 as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        if let zwp_tablet_pad_v2::Event::Removed = event {
+            pad.destroy();
+        }
+    }
+
+    event_created_child!(WaylandState, ZwpTabletPadV2, [
+        zwp_tablet_pad_v2::EVT_GROUP_OPCODE => (ZwpTabletPadGroupV2, ()),
+    ]);
+}
+
+impl Dispatch<ZwpTabletPadGroupV2, ()> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ZwpTabletPadGroupV2,
+        _event: <ZwpTabletPadGroupV2 as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+    }
+
+    event_created_child!(WaylandState, ZwpTabletPadGroupV2, [
+        zwp_tablet_pad_group_v2::EVT_RING_OPCODE => (ZwpTabletPadRingV2, ()),
+        zwp_tablet_pad_group_v2::EVT_STRIP_OPCODE => (ZwpTabletPadStripV2, ()),
+    ]);
+}
+
+impl Dispatch<ZwpTabletPadRingV2, ()> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ZwpTabletPadRingV2,
+        _event: <ZwpTabletPadRingV2 as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwpTabletPadStripV2, ()> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ZwpTabletPadStripV2,
+        _event: <ZwpTabletPadStripV2 as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+    }
+}
@@ -0,0 +1,325 @@
+//! Translating wl_touch input into mouse events and gestures
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_io::Timer;
+use config::keyassignment::KeyAssignment;
+use smithay_client_toolkit::seat::touch::{TouchData, TouchHandler};
+use wayland_client::protocol::wl_surface::WlSurface;
+use wayland_client::protocol::wl_touch::WlTouch;
+use wayland_client::{Connection, Proxy, QueueHandle};
+use wezterm_input_types::{MouseEventKind, MousePress};
+
+use crate::WindowEvent;
+
+use super::state::WaylandState;
+use super::{SurfaceUserData, WaylandConnection};
+
+/// How far, in surface coordinates, a touch point may wander before
+/// we stop considering it to be a tap or long-press
+const TAP_SLOP: f64 = 8.0;
+/// How long a touch point must be held in place to be treated
+/// as a long-press rather than a tap
+const LONG_PRESS: Duration = Duration::from_millis(500);
+/// The vertical distance, in surface coordinates, that a finger has
+/// to travel to scroll by one line
+const SCROLL_DISTANCE_PER_LINE: f64 = 16.0;
+/// The relative change in distance between two fingers that adjusts
+/// the font size by one step
+const PINCH_STEP: f64 = 0.15;
+
+#[derive(Debug)]
+enum GestureKind {
+    /// A single finger is down and hasn't moved far enough to
+    /// be anything other than a tap or long-press
+    Pending,
+    /// A single finger is dragging the scrollback
+    Scroll { last_y: f64, remainder: f64 },
+    /// Two fingers are adjusting the font size.  `distance` is the
+    /// distance between them when we last changed the font size
+    Pinch { distance: f64 },
+    /// The gesture was cancelled or is otherwise finished; we're
+    /// waiting for the remaining fingers to be lifted
+    Ignored,
+}
+
+#[derive(Debug)]
+struct TouchGesture {
+    window_id: usize,
+    points: HashMap<i32, (f64, f64)>,
+    start: (f64, f64),
+    /// Set while the long-press timer is pending.  Cleared when it
+    /// fires, or to cancel it if the gesture turns out to be
+    /// something else
+    long_press: Arc<AtomicBool>,
+    kind: GestureKind,
+}
+
+impl TouchGesture {
+    fn pinch_distance(&self) -> Option<f64> {
+        let mut points = self.points.values();
+        let (x1, y1) = points.next()?;
+        let (x2, y2) = points.next()?;
+        Some((x1 - x2).hypot(y1 - y2))
+    }
+}
+
+pub(super) struct TouchState {
+    pub(super) touch: WlTouch,
+    gesture: Option<TouchGesture>,
+}
+
+impl TouchState {
+    pub(super) fn new(touch: WlTouch) -> Self {
+        Self {
+            touch,
+            gesture: None,
+        }
+    }
+}
+
+fn dispatch_mouse(window_id: usize, kind: MouseEventKind, position: (f64, f64)) {
+    WaylandConnection::with_window_inner(window_id, move |inner| {
+        inner.dispatch_synthesized_mouse_event(kind, position);
+        Ok(())
+    });
+}
+
+fn dispatch_click(window_id: usize, button: MousePress, position: (f64, f64)) {
+    WaylandConnection::with_window_inner(window_id, move |inner| {
+        inner.dispatch_synthesized_mouse_event(MouseEventKind::Move, position);
+        inner.dispatch_synthesized_mouse_event(MouseEventKind::Press(button), position);
+        inner.dispatch_synthesized_mouse_event(MouseEventKind::Release(button), position);
+        Ok(())
+    });
+}
+
+/// Treats the touch as a right click if the finger is still held in
+/// place once the long-press delay has elapsed
+fn schedule_long_press(window_id: usize, position: (f64, f64), armed: Arc<AtomicBool>) {
+    promise::spawn::spawn_into_main_thread(async move {
+        Timer::after(LONG_PRESS).await;
+        if armed.swap(false, Ordering::Relaxed) {
+            dispatch_click(window_id, MousePress::Right, position);
+        }
+    })
+    .detach();
+}
+
+fn dispatch_key_assignment(window_id: usize, assignment: KeyAssignment) {
+    WaylandConnection::with_window_inner(window_id, move |inner| {
+        inner
+            .events
+            .dispatch(WindowEvent::PerformKeyAssignment(assignment));
+        Ok(())
+    });
+}
+
+impl TouchHandler for WaylandState {
+    fn down(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        wl_touch: &WlTouch,
+        serial: u32,
+        _time: u32,
+        surface: WlSurface,
+        id: i32,
+        position: (f64, f64),
+    ) {
+        if let Some(data) = wl_touch.data::<TouchData>() {
+            self.record_serial(&data.seat().id(), serial);
+        }
+
+        let touch = match self.touch_state_mut(wl_touch) {
+            Some(touch) => touch,
+            None => return,
+        };
+
+        match touch.gesture.as_mut() {
+            None => {
+                // Touches on the window decorations are not for us
+                let window_id = match SurfaceUserData::try_from_wl(&surface) {
+                    Some(data) => data.window_id,
+                    None => return,
+                };
+                let mut points = HashMap::new();
+                points.insert(id, position);
+                let long_press = Arc::new(AtomicBool::new(true));
+                schedule_long_press(window_id, position, Arc::clone(&long_press));
+                touch.gesture.replace(TouchGesture {
+                    window_id,
+                    points,
+                    start: position,
+                    long_press,
+                    kind: GestureKind::Pending,
+                });
+            }
+            Some(gesture) => {
+                gesture.long_press.store(false, Ordering::Relaxed);
+                gesture.points.insert(id, position);
+                gesture.kind = match (&gesture.kind, gesture.pinch_distance()) {
+                    (GestureKind::Pending | GestureKind::Scroll { .. }, Some(distance))
+                        if gesture.points.len() == 2 =>
+                    {
+                        GestureKind::Pinch { distance }
+                    }
+                    _ => GestureKind::Ignored,
+                };
+            }
+        }
+    }
+
+    fn up(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        wl_touch: &WlTouch,
+        serial: u32,
+        _time: u32,
+        id: i32,
+    ) {
+        if let Some(data) = wl_touch.data::<TouchData>() {
+            self.record_serial(&data.seat().id(), serial);
+        }
+
+        let touch = match self.touch_state_mut(wl_touch) {
+            Some(touch) => touch,
+            None => return,
+        };
+        let gesture = match touch.gesture.as_mut() {
+            Some(gesture) => gesture,
+            None => return,
+        };
+
+        let position = gesture.points.remove(&id).unwrap_or(gesture.start);
+        if !gesture.points.is_empty() {
+            // Lifting one of the fingers ends a pinch, but we don't
+            // want the remaining finger to start scrolling or clicking
+            gesture.kind = GestureKind::Ignored;
+            return;
+        }
+
+        // If the long-press already fired, it has taken the place
+        // of the click
+        let gesture = touch.gesture.take().unwrap();
+        if let GestureKind::Pending = gesture.kind {
+            if gesture.long_press.swap(false, Ordering::Relaxed) {
+                dispatch_click(gesture.window_id, MousePress::Left, position);
+            }
+        }
+    }
+
+    fn motion(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        wl_touch: &WlTouch,
+        _time: u32,
+        id: i32,
+        position: (f64, f64),
+    ) {
+        let touch = match self.touch_state_mut(wl_touch) {
+            Some(touch) => touch,
+            None => return,
+        };
+        let gesture = match touch.gesture.as_mut() {
+            Some(gesture) => gesture,
+            None => return,
+        };
+        if !gesture.points.contains_key(&id) {
+            return;
+        }
+        gesture.points.insert(id, position);
+
+        if let GestureKind::Pending = gesture.kind {
+            let (x, y) = gesture.start;
+            if (position.0 - x).hypot(position.1 - y) > TAP_SLOP {
+                gesture.long_press.store(false, Ordering::Relaxed);
+                gesture.kind = GestureKind::Scroll {
+                    last_y: y,
+                    remainder: 0.,
+                };
+                // Make sure that the scroll applies to the pane
+                // beneath the finger
+                dispatch_mouse(gesture.window_id, MouseEventKind::Move, gesture.start);
+            }
+        }
+
+        let pinch_distance = gesture.pinch_distance();
+        match &mut gesture.kind {
+            GestureKind::Scroll { last_y, remainder } => {
+                // Content follows the finger, so dragging downwards
+                // moves back into the scrollback, like a wheel up
+                let lines = (position.1 - *last_y) / SCROLL_DISTANCE_PER_LINE + *remainder;
+                *last_y = position.1;
+                let discrete = lines.trunc();
+                *remainder = lines - discrete;
+                if discrete != 0. {
+                    dispatch_mouse(
+                        gesture.window_id,
+                        MouseEventKind::VertWheel(discrete as i16),
+                        gesture.start,
+                    );
+                }
+            }
+            GestureKind::Pinch { distance } => {
+                let current = match pinch_distance {
+                    Some(current) => current,
+                    None => return,
+                };
+                if *distance <= 0. {
+                    *distance = current;
+                    return;
+                }
+                let ratio = current / *distance;
+                let assignment = if ratio >= 1. + PINCH_STEP {
+                    KeyAssignment::IncreaseFontSize
+                } else if ratio <= 1. - PINCH_STEP {
+                    KeyAssignment::DecreaseFontSize
+                } else {
+                    return;
+                };
+                *distance = current;
+                dispatch_key_assignment(gesture.window_id, assignment);
+            }
+            GestureKind::Pending | GestureKind::Ignored => {}
+        }
+    }
+
+    fn shape(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _touch: &WlTouch,
+        _id: i32,
+        _major: f64,
+        _minor: f64,
+    ) {
+    }
+
+    fn orientation(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _touch: &WlTouch,
+        _id: i32,
+        _orientation: f64,
+    ) {
+    }
+
+    fn cancel(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, wl_touch: &WlTouch) {
+        // The compositor has claimed this sequence for one of its own
+        // gestures; nothing has been dispatched that needs undoing,
+        // but the long-press must not fire
+        if let Some(gesture) = self
+            .touch_state_mut(wl_touch)
+            .and_then(|touch| touch.gesture.take())
+        {
+            gesture.long_press.store(false, Ordering::Relaxed);
+        }
+    }
+}
@@ -5,7 +5,6 @@ use std::convert::TryInto;
 use std::io::Read;
 use std::num::NonZeroU32;
 use std::os::fd::AsRawFd;
-use std::path::PathBuf;
 use std::ptr::NonNull;
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
@@ -14,13 +13,17 @@ use std::time::{Duration, Instant};
 use anyhow::{anyhow, bail};
 use async_io::Timer;
 use async_trait::async_trait;
+use config::window::{
+    PresentationMode, WaylandFrameTiming, WaylandKeyboardInteractivity, WaylandLayer,
+    WaylandLayerAnchor, WaylandLayerShell,
+};
 use config::ConfigHandle;
 use promise::{Future, Promise};
 use raw_window_handle::{
     DisplayHandle, HandleError, HasDisplayHandle, HasWindowHandle, RawWindowHandle,
     WaylandWindowHandle, WindowHandle,
 };
-use smithay_client_toolkit::compositor::{CompositorHandler, SurfaceData, SurfaceDataExt};
+use smithay_client_toolkit::compositor::{CompositorHandler, Region, SurfaceData, SurfaceDataExt};
 use smithay_client_toolkit::data_device_manager::ReadPipe;
 use smithay_client_toolkit::globals::GlobalData;
 use smithay_client_toolkit::reexports::csd_frame::{
@@ -28,6 +31,10 @@ use smithay_client_toolkit::reexports::csd_frame::{
 };
 use smithay_client_toolkit::reexports::protocols::xdg::shell::client::xdg_toplevel::ResizeEdge as XdgResizeEdge;
 use smithay_client_toolkit::seat::pointer::CursorIcon;
+use smithay_client_toolkit::shell::wlr_layer::{
+    Anchor, KeyboardInteractivity, Layer, LayerShell, LayerShellHandler, LayerSurface,
+    LayerSurfaceConfigure,
+};
 use smithay_client_toolkit::shell::xdg::fallback_frame::FallbackFrame;
 use smithay_client_toolkit::shell::xdg::window::{
     DecorationMode, Window as XdgWindow, WindowConfigure, WindowDecorations as Decorations,
@@ -35,35 +42,60 @@ use smithay_client_toolkit::shell::xdg::window::{
 };
 use smithay_client_toolkit::shell::xdg::XdgSurface;
 use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shm::slot::Buffer;
+use url::Url;
 use wayland_client::protocol::wl_callback::WlCallback;
+use wayland_client::protocol::wl_data_device_manager::DndAction;
 use wayland_client::protocol::wl_keyboard::{Event as WlKeyboardEvent, KeyState};
+use wayland_client::protocol::wl_output::Transform;
 use wayland_client::protocol::wl_pointer::{ButtonState, WlPointer};
 use wayland_client::protocol::wl_region::WlRegion;
 use wayland_client::protocol::wl_surface::WlSurface;
 use wayland_client::{Connection as WConnection, Dispatch, Proxy, QueueHandle};
 use wayland_egl::{is_available as egl_is_available, WlEglSurface};
+use wayland_protocols::wp::color_management::v1::client::wp_color_management_surface_v1::WpColorManagementSurfaceV1;
+use wayland_protocols::wp::commit_timing::v1::client::wp_commit_timer_v1::WpCommitTimerV1;
+use wayland_protocols::wp::content_type::v1::client::wp_content_type_v1::{
+    Type as ContentType, WpContentTypeV1,
+};
+use wayland_protocols::wp::fifo::v1::client::wp_fifo_v1::WpFifoV1;
 use wayland_protocols::wp::fractional_scale::v1::client::wp_fractional_scale_v1::WpFractionalScaleV1;
+use wayland_protocols::wp::linux_dmabuf::zv1::client::zwp_linux_dmabuf_feedback_v1::ZwpLinuxDmabufFeedbackV1;
+use wayland_protocols::wp::keyboard_shortcuts_inhibit::zv1::client::zwp_keyboard_shortcuts_inhibitor_v1::ZwpKeyboardShortcutsInhibitorV1;
 use wayland_protocols::wp::presentation_time::client::wp_presentation_feedback::WpPresentationFeedback;
 use wayland_protocols::wp::tearing_control::v1::client::wp_tearing_control_v1::{
     PresentationHint, WpTearingControlV1,
 };
 use wayland_protocols::wp::viewporter::client::wp_viewport::WpViewport;
+use wayland_protocols::xdg::dialog::v1::client::xdg_dialog_v1::XdgDialogV1;
+use wayland_protocols::xdg::toplevel_icon::v1::client::xdg_toplevel_icon_v1::XdgToplevelIconV1;
 use wayland_protocols_plasma::blur::client::org_kde_kwin_blur::OrgKdeKwinBlur;
 use wayland_protocols_plasma::blur::client::org_kde_kwin_blur_manager::OrgKdeKwinBlurManager;
+use wayland_protocols_plasma::server_decoration::client::org_kde_kwin_server_decoration::{
+    Event as KdeDecorationEvent, Mode as KdeDecorationMode, OrgKdeKwinServerDecoration,
+};
+use wayland_protocols_plasma::server_decoration::client::org_kde_kwin_server_decoration_manager::OrgKdeKwinServerDecorationManager;
 use wezterm_font::FontConfiguration;
 use wezterm_input_types::{
     KeyboardLedStatus, Modifiers, MouseButtons, MouseEvent, MouseEventKind, MousePress,
     ScreenPoint, WindowDecorations,
 };
 
+use crate::bitmaps::{BitmapImage, Image};
+use crate::color::SrgbaTuple;
 use crate::wayland::WaylandConnection;
 use crate::x11::KeyboardWithFallback;
 use crate::{
-    Appearance, Clipboard, Connection, ConnectionOps, Dimensions, MouseCursor, Point, Rect,
-    RequestedWindowGeometry, ResizeIncrement, ResolvedGeometry, Window, WindowEvent,
-    WindowEventSender, WindowKeyEvent, WindowOps, WindowState,
+    Appearance, BufferTransform, Clipboard, ClipboardContents, Connection, ConnectionOps,
+    Dimensions, DragData, MouseCursor, Point, Rect, RequestedWindowGeometry, ResizeIncrement,
+    ResolvedGeometry, Size, Window, WindowEvent, WindowEventSender, WindowKeyEvent, WindowOps,
+    WindowState,
 };
 
+/// How long a frame callback may go unanswered before we assume that
+/// the compositor is withholding it because the window can't be seen
+const FRAME_CALLBACK_STARVATION: Duration = Duration::from_secs(1);
+
 /// Wayland-specific coordinate conversion methods for Dimensions
 trait WaylandDimensions {
     fn dpi_factor(&self) -> f64;
@@ -87,14 +119,25 @@ impl WaylandDimensions for Dimensions {
     }
 }
 
+use super::activation::ActivationPurpose;
+use super::background::SolidBackground;
 use super::copy_and_paste::CopyAndPaste;
+use super::damage::{buffer_to_surface, coalesce_damage, window_to_buffer};
+use super::data_device::{PLAIN_TEXT_MIME_TYPE, TEXT_MIME_TYPE, URI_MIME_TYPE};
+use super::framestats::FrameStatsTracker;
+use super::icon::letterbox;
+use super::latency::PresentationFeedbackData;
 use super::pointer::{PendingMouse, PointerUserData};
+use super::seat::WaylandSeat;
 use super::state::WaylandState;
 
 #[derive(Debug)]
 pub(super) struct KeyRepeatState {
     pub(super) when: Instant,
     pub(super) event: WindowKeyEvent,
+    /// The repeat rate and delay of the seat on which the key is held
+    pub(super) rate: i32,
+    pub(super) delay: i32,
 }
 
 impl KeyRepeatState {
@@ -103,13 +146,9 @@ impl KeyRepeatState {
             let delay;
             let gap;
             {
-                let conn = WaylandConnection::get().unwrap().wayland();
                 let (rate, ddelay) = {
-                    let wstate = conn.wayland_state.borrow();
-                    (
-                        wstate.key_repeat_rate as u64,
-                        wstate.key_repeat_delay as u64,
-                    )
+                    let st = state.lock().unwrap();
+                    (st.rate as u64, st.delay as u64)
                 };
                 if rate == 0 {
                     return;
@@ -184,6 +223,10 @@ impl KeyRepeatState {
 enum WaylandWindowEvent {
     Close,
     Request(WindowConfigure),
+    LayerConfigure(LayerSurfaceConfigure),
+    /// org_kde_kwin_server_decoration told us whether the
+    /// compositor is drawing our decorations
+    KdeServerDecorations(bool),
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Ord, PartialOrd)]
@@ -244,62 +287,124 @@ impl WaylandWindow {
             dpi: config.dpi.unwrap_or(crate::DEFAULT_DPI) as usize,
         };
 
-        let window = {
-            let xdg_shell = &conn.wayland_state.borrow().xdg;
-            xdg_shell.create_window(surface.clone(), Decorations::RequestServer, &qh)
-        };
+        let layer_surface = config
+            .wayland_layer_shell
+            .as_ref()
+            .and_then(|layer_config| {
+                let wayland_state = conn.wayland_state.borrow();
+                match &wayland_state.layer_shell {
+                    Some(layer_shell) => Some(create_layer_surface(
+                        layer_shell,
+                        &qh,
+                        surface.clone(),
+                        layer_config,
+                        &dimensions,
+                    )),
+                    None => {
+                        log::warn!(
+                            "wayland_layer_shell is configured, but the compositor \
+                         doesn't support zwlr_layer_shell_v1; using a regular window"
+                        );
+                        None
+                    }
+                }
+            });
 
-        window.set_app_id(class_name.to_string());
-        window.set_title(name.to_string());
-        let decorations = config.window_decorations;
+        let (window, window_frame, kde_decoration) = match &layer_surface {
+            Some(layer_surface) => {
+                // Layer surfaces are positioned by the compositor and
+                // have no decorations of their own
+                let mut window_frame = {
+                    let wayland_state = &conn.wayland_state.borrow();
+                    let shm = &wayland_state.shm;
+                    let subcompositor = wayland_state.subcompositor.clone();
+                    FallbackFrame::new(layer_surface, shm, subcompositor, qh.clone())
+                        .expect("failed to create csd frame")
+                };
+                window_frame.set_hidden(true);
+                layer_surface.commit();
+                (None, window_frame, None)
+            }
+            None => {
+                let window = {
+                    let xdg_shell = &conn.wayland_state.borrow().xdg;
+                    xdg_shell.create_window(surface.clone(), Decorations::RequestServer, &qh)
+                };
 
-        let decor_mode = if decorations == WindowDecorations::NONE {
-            None
-        } else if decorations == WindowDecorations::default() {
-            Some(DecorationMode::Server)
-        } else {
-            Some(DecorationMode::Client)
-        };
-        window.request_decoration_mode(decor_mode);
-
-        let mut window_frame = {
-            let wayland_state = &conn.wayland_state.borrow();
-            let shm = &wayland_state.shm;
-            let subcompositor = wayland_state.subcompositor.clone();
-            FallbackFrame::new(&window, shm, subcompositor, qh.clone())
-                .expect("failed to create csd frame")
-        };
-        let hidden = match decor_mode {
-            Some(DecorationMode::Client) => false,
-            _ => true,
-        };
-        window_frame.set_hidden(hidden);
-        if !hidden {
-            window_frame.resize(
-                NonZeroU32::new(dimensions.pixel_width as u32)
-                    .ok_or_else(|| anyhow!("dimensions {dimensions:?} are invalid"))?,
-                NonZeroU32::new(dimensions.pixel_height as u32)
-                    .ok_or_else(|| anyhow!("dimensions {dimensions:?} are invalid"))?,
-            );
-        }
+                window.set_app_id(class_name.to_string());
+                window.set_title(name.to_string());
+                let decorations = config.window_decorations;
+
+                let decor_mode = if decorations == WindowDecorations::NONE {
+                    None
+                } else if decorations == WindowDecorations::default() {
+                    Some(DecorationMode::Server)
+                } else {
+                    Some(DecorationMode::Client)
+                };
+                window.request_decoration_mode(decor_mode);
+
+                // Without xdg-decoration, the compositor may still offer
+                // server-side decorations through KDE's older protocol
+                let kde_decoration = conn
+                    .wayland_state
+                    .borrow()
+                    .kde_server_decoration_manager
+                    .as_ref()
+                    .map(|manager| {
+                        let decoration = manager.create(&surface, &qh, surface.clone());
+                        decoration.request_mode(match decor_mode {
+                            Some(DecorationMode::Server) => KdeDecorationMode::Server as u32,
+                            Some(DecorationMode::Client) => KdeDecorationMode::Client as u32,
+                            None => KdeDecorationMode::None as u32,
+                        });
+                        decoration
+                    });
+
+                let mut window_frame = {
+                    let wayland_state = &conn.wayland_state.borrow();
+                    let shm = &wayland_state.shm;
+                    let subcompositor = wayland_state.subcompositor.clone();
+                    FallbackFrame::new(&window, shm, subcompositor, qh.clone())
+                        .expect("failed to create csd frame")
+                };
+                let hidden = match decor_mode {
+                    Some(DecorationMode::Client) => false,
+                    _ => true,
+                };
+                window_frame.set_hidden(hidden);
+                if !hidden {
+                    window_frame.resize(
+                        NonZeroU32::new(dimensions.pixel_width as u32)
+                            .ok_or_else(|| anyhow!("dimensions {dimensions:?} are invalid"))?,
+                        NonZeroU32::new(dimensions.pixel_height as u32)
+                            .ok_or_else(|| anyhow!("dimensions {dimensions:?} are invalid"))?,
+                    );
+                }
 
-        window.set_min_size(Some((32, 32)));
-        let (x, y) = window_frame.location();
-        let surface_width = dimensions.pixels_to_surface(dimensions.pixel_width as i32);
-        let surface_height = dimensions.pixels_to_surface(dimensions.pixel_height as i32);
-        // IMPORTANT: The window_frame draws borders AROUND the content:
-        // - Bottom border is drawn at y=height (12px border below content)
-        // - Location offset y=-30 accounts for header above
-        // The geometry must include the bottom border to prevent clipping.
-        // Add BORDER_SIZE (12) to height to include bottom border in geometry.
-        const BORDER_SIZE: i32 = 12;
-        window
-            .xdg_surface()
-            .set_window_geometry(x, y, surface_width, surface_height + BORDER_SIZE);
-        window.commit();
+                window.set_min_size(Some((32, 32)));
+                let (x, y) = window_frame.location();
+                let surface_width = dimensions.pixels_to_surface(dimensions.pixel_width as i32);
+                let surface_height = dimensions.pixels_to_surface(dimensions.pixel_height as i32);
+                // IMPORTANT: The window_frame draws borders AROUND the content:
+                // - Bottom border is drawn at y=height (12px border below content)
+                // - Location offset y=-30 accounts for header above
+                // The geometry must include the bottom border to prevent clipping.
+                // Add BORDER_SIZE (12) to height to include bottom border in geometry.
+                const BORDER_SIZE: i32 = 12;
+                window.xdg_surface().set_window_geometry(
+                    x,
+                    y,
+                    surface_width,
+                    surface_height + BORDER_SIZE,
+                );
+                window.commit();
+                (Some(window), window_frame, kde_decoration)
+            }
+        };
 
         let copy_and_paste = CopyAndPaste::create();
-        let pending_mouse = PendingMouse::create(window_id, &copy_and_paste);
+        let pending_mouse = PendingMouse::create(window_id);
 
         {
             let surface_to_pending = &mut conn.wayland_state.borrow_mut().surface_to_pending;
@@ -313,7 +418,8 @@ impl WaylandWindow {
             surface_factor: 1.0,
             copy_and_paste,
             invalidated: false,
-            window: Some(window),
+            window,
+            layer_surface,
             window_frame,
             dimensions,
             resize_increments: None,
@@ -332,6 +438,8 @@ impl WaylandWindow {
 
             pending_first_configure: Some(pending_first_configure),
             frame_callback: None,
+            frame_requested_at: None,
+            frame_callback_starved: false,
 
             text_cursor: None,
             appearance,
@@ -342,12 +450,39 @@ impl WaylandWindow {
 
             wegl_surface: None,
             gl_state: None,
+            buffer_transform: BufferTransform::Normal,
             presentation_feedback: None,
             last_presentation_time: None,
+            refresh_interval_ns: 0,
+            reported_refresh_interval: None,
             fractional_scale: None,
             current_fractional_scale: None,
             viewport: None,
             tearing_control: None,
+            presentation_mode_override: None,
+            shortcut_inhibitor: None,
+            kde_decoration,
+            xdg_dialog: None,
+            xdg_server_decorations: false,
+            kde_server_decorations: false,
+            icon: None,
+            content_type: None,
+            fifo: None,
+            fifo_frame_queued: false,
+            commit_timer: None,
+            commit_timestamp_pending: false,
+            dmabuf_feedback: None,
+            scanout_formats: 0,
+            frames_presented: 0,
+            frames_zero_copy: 0,
+            frame_stats: FrameStatsTracker::default(),
+            solid_background_color: None,
+            solid_background: None,
+            kde_blur: None,
+            blur_region: None,
+            color_surface: None,
+            hdr_content: false,
+            hdr_output: false,
         }));
 
         let window_handle = Window::Wayland(WaylandWindow(window_id));
@@ -357,7 +492,7 @@ impl WaylandWindow {
             .events
             .assign_window(window_handle.clone());
 
-        inner.borrow().update_window_background_blur();
+        inner.borrow_mut().update_window_background_blur();
 
         {
             let windows = &conn.wayland_state.borrow().windows;
@@ -366,6 +501,15 @@ impl WaylandWindow {
 
         wait_configure.recv().await?;
 
+        {
+            let mut wayland_state = conn.wayland_state.borrow_mut();
+            if let Some(token) = wayland_state.startup_activation_token.take() {
+                if let Some(activation) = wayland_state.activation.as_ref() {
+                    activation.activate::<WaylandState>(&surface, token);
+                }
+            }
+        }
+
         Ok(window_handle)
     }
 }
@@ -409,34 +553,66 @@ impl WindowOps for WaylandWindow {
         // This tells the compositor which regions actually changed, allowing it to optimize.
         let rects = dirty_rects.to_vec(); // Copy to move into closure
         WaylandConnection::with_window_inner(self.0, move |inner| {
-            let surface = inner.surface();
+            inner.prepare_frame_commit(&rects);
+            Ok(())
+        });
+        frame.finish()?;
+        Ok(())
+    }
 
-            // Apply damage rectangles
-            if !rects.is_empty() {
-                for &(x, y, width, height) in &rects {
-                    surface.damage_buffer(x, y, width, height);
-                }
-            }
+    fn set_buffer_transform(&self, transform: BufferTransform) {
+        WaylandConnection::with_window_inner(self.0, move |inner| {
+            inner.set_buffer_transform(transform);
+            Ok(())
+        });
+    }
 
-            // Request presentation feedback for accurate latency measurement
-            if let Some(presentation) = WaylandConnection::get()
-                .and_then(|conn| conn.wayland().wayland_state.borrow().presentation.clone())
-            {
-                let qh = WaylandConnection::get().unwrap().wayland().event_queue.borrow().handle();
-                let feedback = presentation.feedback(surface, &qh, surface.clone());
-                inner.presentation_feedback = Some(feedback);
-                log::trace!("Requested presentation feedback: {:?}", inner.presentation_feedback);
-            }
+    fn supports_hdr_content(&self) -> bool {
+        let conn = WaylandConnection::get().unwrap().wayland();
+        let state = conn.wayland_state.borrow();
+        state
+            .color_manager
+            .as_ref()
+            .map_or(false, |color_manager| color_manager.supports_hdr())
+    }
 
+    fn set_hdr_content(&self, hdr: bool) {
+        WaylandConnection::with_window_inner(self.0, move |inner| {
+            inner.set_hdr_content(hdr);
             Ok(())
         });
-        frame.finish()?;
+    }
+
+    async fn enable_software_rendering(&self) -> anyhow::Result<()> {
+        let window = self.0;
+        promise::spawn::spawn(async move {
+            if let Some(handle) = Connection::get().unwrap().wayland().window_by_id(window) {
+                let mut inner = handle.borrow_mut();
+                inner.enable_software_rendering()
+            } else {
+                anyhow::bail!("invalid window");
+            }
+        })
+        .await
+    }
+
+    fn present_software_frame(
+        &self,
+        width: usize,
+        height: usize,
+        pixels: Vec<u32>,
+    ) -> anyhow::Result<()> {
+        WaylandConnection::with_window_inner(self.0, move |inner| {
+            inner.present_software_frame(width, height, &pixels)
+        });
         Ok(())
     }
 
     fn hide(&self) {
         WaylandConnection::with_window_inner(self.0, move |inner| {
-            inner.window.as_ref().unwrap().set_minimized();
+            if let Some(window) = inner.window.as_ref() {
+                window.set_minimized();
+            }
             Ok(())
         });
     }
@@ -495,11 +671,18 @@ impl WindowOps for WaylandWindow {
         let future = promise.get_future().unwrap();
         let promise = Arc::new(Mutex::new(promise));
         WaylandConnection::with_window_inner(self.0, move |inner| {
-            let read = inner
+            let read = match inner
                 .copy_and_paste
                 .lock()
                 .unwrap()
-                .get_clipboard_data(clipboard)?;
+                .get_clipboard_data(clipboard)
+            {
+                Ok(read) => read,
+                Err(err) => {
+                    promise.lock().unwrap().err(err);
+                    return Ok(());
+                }
+            };
             let promise = Arc::clone(&promise);
             std::thread::spawn(move || {
                 let mut promise = promise.lock().unwrap();
@@ -522,23 +705,132 @@ impl WindowOps for WaylandWindow {
     }
 
     fn set_clipboard(&self, clipboard: Clipboard, text: String) {
+        self.set_clipboard_contents(
+            clipboard,
+            ClipboardContents {
+                text,
+                ..Default::default()
+            },
+        );
+    }
+
+    fn set_clipboard_contents(&self, clipboard: Clipboard, contents: ClipboardContents) {
         WaylandConnection::with_window_inner(self.0, move |inner| {
             inner
                 .copy_and_paste
                 .lock()
                 .unwrap()
-                .set_clipboard_data(clipboard, text);
+                .set_clipboard_data(clipboard, contents);
+            Ok(())
+        });
+    }
+
+    fn get_clipboard_image(&self, clipboard: Clipboard) -> Future<Option<Vec<u8>>> {
+        let mut promise = Promise::new();
+        let future = promise.get_future().unwrap();
+        let promise = Arc::new(Mutex::new(promise));
+        WaylandConnection::with_window_inner(self.0, move |inner| {
+            let read = inner
+                .copy_and_paste
+                .lock()
+                .unwrap()
+                .get_clipboard_image(clipboard);
+            let read = match read {
+                Ok(Some(read)) => read,
+                Ok(None) => {
+                    promise.lock().unwrap().ok(None);
+                    return Ok(());
+                }
+                Err(err) => {
+                    promise.lock().unwrap().err(anyhow!("{:#}", err));
+                    return Ok(());
+                }
+            };
+            let promise = Arc::clone(&promise);
+            std::thread::spawn(move || {
+                let mut promise = promise.lock().unwrap();
+                match read_pipe_bytes_with_timeout(read) {
+                    Ok(result) => {
+                        promise.ok(Some(result));
+                    }
+                    Err(e) => {
+                        log::error!("while reading clipboard image: {}", e);
+                        promise.err(anyhow!("{}", e));
+                    }
+                };
+            });
             Ok(())
         });
+        future
+    }
+
+    fn focus(&self) {
+        // Compositors that implement focus stealing prevention ignore
+        // activation requests without a token, so we need to request
+        // one first and activate once it is delivered
+        let window_id = self.0;
+        promise::spawn::spawn_into_main_thread(async move {
+            let conn = WaylandConnection::get().unwrap().wayland();
+            let qh = conn.event_queue.borrow().handle();
+            conn.wayland_state
+                .borrow()
+                .request_activation_token(&qh, ActivationPurpose::Focus(window_id));
+        })
+        .detach();
+    }
+
+    fn focus_with_activation_token(&self, token: String) {
+        let window_id = self.0;
+        promise::spawn::spawn_into_main_thread(async move {
+            let conn = WaylandConnection::get().unwrap().wayland();
+            conn.wayland_state
+                .borrow()
+                .activate_window(window_id, token);
+        })
+        .detach();
     }
 
     fn toggle_fullscreen(&self) {
         WaylandConnection::with_window_inner(self.0, move |inner| {
+            let window = match inner.window.as_ref() {
+                Some(window) => window,
+                None => return Ok(()),
+            };
             if inner.window_state.contains(WindowState::FULL_SCREEN) {
-                inner.window.as_ref().unwrap().unset_fullscreen();
+                window.unset_fullscreen();
             } else {
-                inner.window.as_ref().unwrap().set_fullscreen(None);
+                window.set_fullscreen(None);
+            }
+            Ok(())
+        });
+    }
+
+    fn toggle_fullscreen_on_screen(&self, screen_name: String) {
+        WaylandConnection::with_window_inner(self.0, move |inner| {
+            let window = match inner.window.as_ref() {
+                Some(window) => window,
+                None => return Ok(()),
+            };
+            if inner.window_state.contains(WindowState::FULL_SCREEN) {
+                window.unset_fullscreen();
+                return Ok(());
+            }
+            let conn = WaylandConnection::get().unwrap().wayland();
+            let state = conn.wayland_state.borrow();
+            // Match the names that WaylandConnection::screens reports
+            let output = state.output.outputs().find(|output| {
+                state
+                    .output
+                    .info(output)
+                    .map_or(false, |info| match &info.name {
+                        Some(name) => *name == screen_name,
+                        None => format!("{} {}", info.model, info.make) == screen_name,
+                    })
+            });
+            if output.is_none() {
+                log::warn!("toggle_fullscreen_on_screen: no output named {screen_name}");
             }
+            window.set_fullscreen(output.as_ref());
             Ok(())
         });
     }
@@ -551,6 +843,60 @@ impl WindowOps for WaylandWindow {
         WaylandConnection::with_window_inner(self.0, move |inner| Ok(inner.restore()));
     }
 
+    fn set_solid_background(&self, color: Option<SrgbaTuple>) {
+        WaylandConnection::with_window_inner(self.0, move |inner| {
+            inner.set_solid_background(color);
+            Ok(())
+        });
+    }
+
+    fn set_modal_parent(&self, parent: &Window) {
+        let parent_id = match parent {
+            Window::Wayland(WaylandWindow(parent_id)) => *parent_id,
+            _ => return,
+        };
+        WaylandConnection::with_window_inner(self.0, move |inner| {
+            inner.set_modal_parent(parent_id);
+            Ok(())
+        });
+    }
+
+    fn set_blur_region(&self, region: Option<Rect>) {
+        WaylandConnection::with_window_inner(self.0, move |inner| {
+            inner.blur_region = region;
+            inner.update_window_background_blur();
+            Ok(())
+        });
+    }
+
+    fn toggle_shortcut_inhibit(&self) {
+        WaylandConnection::with_window_inner(self.0, move |inner| {
+            inner.toggle_shortcut_inhibit();
+            Ok(())
+        });
+    }
+
+    fn toggle_presentation_mode(&self) {
+        WaylandConnection::with_window_inner(self.0, move |inner| {
+            inner.toggle_presentation_mode();
+            Ok(())
+        });
+    }
+
+    fn start_drag(&self, data: DragData) {
+        WaylandConnection::with_window_inner(self.0, move |inner| {
+            inner.start_drag(data);
+            Ok(())
+        });
+    }
+
+    fn set_icon(&self, image: Image) {
+        WaylandConnection::with_window_inner(self.0, move |inner| {
+            inner.set_icon(&image);
+            Ok(())
+        });
+    }
+
     fn config_did_change(&self, config: &ConfigHandle) {
         let config = config.clone();
         WaylandConnection::with_window_inner(self.0, move |inner| {
@@ -570,9 +916,14 @@ pub(crate) struct PendingEvent {
     pub(crate) window_configure: Option<WindowConfigure>,
     pub(crate) dpi: Option<i32>,
     pub(crate) window_state: Option<WindowState>,
+    kde_server_decorations: Option<bool>,
 }
 
-pub(crate) fn read_pipe_with_timeout(mut file: ReadPipe) -> anyhow::Result<String> {
+pub(crate) fn read_pipe_with_timeout(file: ReadPipe) -> anyhow::Result<String> {
+    Ok(String::from_utf8(read_pipe_bytes_with_timeout(file)?)?)
+}
+
+pub(crate) fn read_pipe_bytes_with_timeout(mut file: ReadPipe) -> anyhow::Result<Vec<u8>> {
     let mut result = Vec::new();
 
     // set non-blocking I/O on the pipe
@@ -608,7 +959,7 @@ pub(crate) fn read_pipe_with_timeout(mut file: ReadPipe) -> anyhow::Result<Strin
         }
     }
 
-    Ok(String::from_utf8(result)?)
+    Ok(result)
 }
 
 pub struct WaylandWindowInner {
@@ -616,6 +967,9 @@ pub struct WaylandWindowInner {
     surface_factor: f64,
     copy_and_paste: Arc<Mutex<CopyAndPaste>>,
     window: Option<XdgWindow>,
+    /// Set instead of `window` when the window was created
+    /// as a wlr-layer-shell surface
+    layer_surface: Option<LayerSurface>,
     pub(super) window_frame: FallbackFrame<WaylandState>,
     dimensions: Dimensions,
     resize_increments: Option<ResizeIncrement>,
@@ -631,6 +985,14 @@ pub struct WaylandWindowInner {
     pub(super) pending_mouse: Arc<Mutex<PendingMouse>>,
     pending_first_configure: Option<async_channel::Sender<()>>,
     frame_callback: Option<WlCallback>,
+    /// When the pending frame_callback was requested
+    frame_requested_at: Option<Instant>,
+    /// Set when the compositor hasn't answered our frame callback for
+    /// FRAME_CALLBACK_STARVATION, which is how it stops a window from
+    /// painting while it is minimized or occluded, when it doesn't
+    /// support the xdg suspended state.  We report it to the
+    /// application as WindowState::SUSPENDED until the callback arrives.
+    frame_callback_starved: bool,
     invalidated: bool,
     // font_config: Rc<FontConfiguration>,
     text_cursor: Option<Rect>,
@@ -644,10 +1006,18 @@ pub struct WaylandWindowInner {
     // libraries will segfault on shutdown
     wegl_surface: Option<WlEglSurface>,
     gl_state: Option<Rc<glium::backend::Context>>,
+    /// The transform that the application renders our buffers with
+    buffer_transform: BufferTransform,
     /// Presentation timing feedback for measuring actual presentation latency
     presentation_feedback: Option<WpPresentationFeedback>,
     /// Last recorded presentation timestamp (nanoseconds)
     pub(super) last_presentation_time: Option<u64>,
+    /// The refresh interval of the output, as reported by the last
+    /// presentation feedback (nanoseconds, 0 if unknown)
+    pub(super) refresh_interval_ns: u32,
+    /// The refresh interval that we last reported to the application
+    /// via WindowEvent::RefreshIntervalChanged
+    reported_refresh_interval: Option<Duration>,
     /// Fractional scale object for this surface
     fractional_scale: Option<WpFractionalScaleV1>,
     /// Current fractional scale factor (in 120ths, so 120 = 1.0x)
@@ -656,17 +1026,119 @@ pub struct WaylandWindowInner {
     viewport: Option<WpViewport>,
     /// Tearing control object for this surface
     tearing_control: Option<WpTearingControlV1>,
+    /// Set by toggle_presentation_mode to take precedence over
+    /// the presentation_mode config
+    presentation_mode_override: Option<PresentationMode>,
+    /// Present while we are asking the compositor to pass all
+    /// keys through to us, see toggle_shortcut_inhibit
+    shortcut_inhibitor: Option<ZwpKeyboardShortcutsInhibitorV1>,
+    /// Our org_kde_kwin_server_decoration, used in place of
+    /// xdg-decoration on compositors that only support the former
+    kde_decoration: Option<OrgKdeKwinServerDecoration>,
+    /// Set while we are a modal dialog of another window
+    xdg_dialog: Option<XdgDialogV1>,
+    /// Whether the compositor is drawing our decorations, according
+    /// to xdg-decoration and org_kde_kwin_server_decoration respectively
+    xdg_server_decorations: bool,
+    kde_server_decorations: bool,
+    /// Content type hint object for this surface
+    content_type: Option<WpContentTypeV1>,
+    /// FIFO barrier object for this surface
+    fifo: Option<WpFifoV1>,
+    /// True while a frame has been committed ahead of the pending
+    /// frame callback; the fifo barrier holds it in the compositor
+    /// until the previous frame has been presented
+    pub(super) fifo_frame_queued: bool,
+    /// Commit timer object for this surface
+    commit_timer: Option<WpCommitTimerV1>,
+    /// True from setting a target timestamp until the commit that
+    /// consumes it; setting another in between is a protocol error
+    commit_timestamp_pending: bool,
+    /// linux-dmabuf feedback for this surface, see dmabuf.rs
+    pub(super) dmabuf_feedback: Option<ZwpLinuxDmabufFeedbackV1>,
+    /// How many formats the compositor can scan out directly for
+    /// this surface, according to its latest dmabuf feedback
+    pub(super) scanout_formats: usize,
+    /// The number of frames that have been presented, and how
+    /// many of them were presented zero-copy
+    pub(super) frames_presented: u64,
+    pub(super) frames_zero_copy: u64,
+    /// Aggregates the presentation feedback for this window
+    pub(super) frame_stats: FrameStatsTracker,
+    /// The solid color that the gui asked us to use as the
+    /// background, see set_solid_background
+    solid_background_color: Option<SrgbaTuple>,
+    solid_background: Option<SolidBackground>,
+    /// The blur object for this surface, while kde_window_background_blur
+    /// is enabled, and the area that the gui asked us to restrict it to
+    kde_blur: Option<OrgKdeKwinBlur>,
+    blur_region: Option<Rect>,
+    /// Color management object for this surface
+    color_surface: Option<WpColorManagementSurfaceV1>,
+    /// Whether the application renders scRGB rather than sRGB
+    hdr_content: bool,
+    /// Whether any of the outputs that the surface is on are HDR,
+    /// as last reported to the application
+    hdr_output: bool,
+    /// The icon assigned via xdg-toplevel-icon, along with the buffers
+    /// that hold its pixels; both must outlive the assignment
+    icon: Option<(XdgToplevelIconV1, Vec<Buffer>)>,
+}
+
+/// Returns the current time according to the clock that the
+/// compositor uses for presentation timestamps
+fn presentation_clock_now_ns(clock_id: u32) -> Option<u64> {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    if unsafe { libc::clock_gettime(clock_id as libc::clockid_t, &mut ts) } != 0 {
+        return None;
+    }
+    Some(ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64)
 }
 
 impl WaylandWindowInner {
     fn close(&mut self) {
+        if let Some(inhibitor) = self.shortcut_inhibitor.take() {
+            inhibitor.destroy();
+        }
+        self.solid_background.take();
+        if let Some(color_surface) = self.color_surface.take() {
+            color_surface.destroy();
+        }
+        if let Some(fifo) = self.fifo.take() {
+            fifo.destroy();
+        }
+        if let Some(timer) = self.commit_timer.take() {
+            timer.destroy();
+        }
+        if let Some(feedback) = self.dmabuf_feedback.take() {
+            feedback.destroy();
+        }
+        if let Some((icon, _buffers)) = self.icon.take() {
+            icon.destroy();
+        }
+        if let Some(decoration) = self.kde_decoration.take() {
+            decoration.release();
+        }
+        if let Some(dialog) = self.xdg_dialog.take() {
+            dialog.destroy();
+        }
         self.events.dispatch(WindowEvent::Destroyed);
         self.window.take();
+        self.layer_surface.take();
+    }
+
+    /// Returns true if we still have either an xdg toplevel or
+    /// a layer surface; both are dropped when the window closes
+    pub(super) fn has_surface(&self) -> bool {
+        self.window.is_some() || self.layer_surface.is_some()
     }
 
     fn show(&mut self) {
         log::trace!("WaylandWindowInner show: {:?}", self.window);
-        if self.window.is_none() {
+        if !self.has_surface() {
             return;
         }
 
@@ -698,11 +1170,10 @@ impl WaylandWindowInner {
         let gl_state = if !egl_is_available() {
             Err(anyhow!("!egl_is_available"))
         } else {
-            let window = self
-                .window
-                .as_ref()
-                .ok_or(anyhow!("Window does not exist"))?;
-            let object_id = window.wl_surface().id();
+            if !self.has_surface() {
+                bail!("Window does not exist");
+            }
+            let object_id = self.surface().id();
 
             wegl_surface = Some(WlEglSurface::new(
                 object_id,
@@ -748,6 +1219,162 @@ impl WaylandWindowInner {
         Ok(gl_state)
     }
 
+    fn enable_software_rendering(&mut self) -> anyhow::Result<()> {
+        if !self.has_surface() {
+            bail!("Window does not exist");
+        }
+        log::trace!("Enable software rendering");
+        self.setup_surface_protocols();
+        Ok(())
+    }
+
+    /// Copies a frame that was rendered on the CPU into a shared memory
+    /// buffer and commits it.
+    /// The whole frame is redrawn each time, so the whole buffer is damaged.
+    fn present_software_frame(
+        &mut self,
+        width: usize,
+        height: usize,
+        pixels: &[u32],
+    ) -> anyhow::Result<()> {
+        if !self.has_surface() {
+            return Ok(());
+        }
+
+        let conn = Connection::get().unwrap().wayland();
+        {
+            let state = conn.wayland_state.borrow();
+            let mut pool = state.mem_pool.borrow_mut();
+            let (buffer, canvas) = pool.create_buffer(
+                width as i32,
+                height as i32,
+                width as i32 * 4,
+                wayland_client::protocol::wl_shm::Format::Argb8888,
+            )?;
+            for (dest, pixel) in canvas.chunks_exact_mut(4).zip(pixels.iter()) {
+                dest.copy_from_slice(&pixel.to_le_bytes());
+            }
+            // Once attached, the buffer is kept alive until the
+            // compositor releases it, even though we drop it below
+            buffer.attach_to(self.surface())?;
+        }
+
+        // The frame covers the whole window
+        self.prepare_frame_commit(&[(
+            0,
+            0,
+            self.dimensions.pixel_width as i32,
+            self.dimensions.pixel_height as i32,
+        )]);
+        self.surface().commit();
+        Ok(())
+    }
+
+    /// Sizes the egl surface to match the buffers that the application
+    /// renders, taking into account any buffer transform
+    fn resize_wegl_surface(&mut self) {
+        let (width, height) = self
+            .buffer_transform
+            .buffer_size(self.dimensions.pixel_width, self.dimensions.pixel_height);
+        if let Some(wegl_surface) = self.wegl_surface.as_mut() {
+            wegl_surface.resize(width as i32, height as i32, 0, 0);
+        }
+    }
+
+    fn set_buffer_transform(&mut self, transform: BufferTransform) {
+        if transform == self.buffer_transform || !self.has_surface() {
+            return;
+        }
+        if self.surface().version() < 2 {
+            // set_buffer_transform was added in version 2
+            return;
+        }
+        log::debug!("buffer transform is now {transform:?}");
+        self.buffer_transform = transform;
+        // Takes effect along with the next frame, which the
+        // application renders with the transform applied
+        self.surface().set_buffer_transform(match transform {
+            BufferTransform::Normal => Transform::Normal,
+            BufferTransform::Rotate90 => Transform::_90,
+            BufferTransform::Rotate180 => Transform::_180,
+            BufferTransform::Rotate270 => Transform::_270,
+            BufferTransform::Flipped => Transform::Flipped,
+            BufferTransform::Flipped90 => Transform::Flipped90,
+            BufferTransform::Flipped180 => Transform::Flipped180,
+            BufferTransform::Flipped270 => Transform::Flipped270,
+        });
+        self.resize_wegl_surface();
+        self.invalidate();
+    }
+
+    /// Called just before a frame is committed: reports its damage to
+    /// the compositor, and sets up pacing and presentation feedback for it
+    fn prepare_frame_commit(&mut self, rects: &[(i32, i32, i32, i32)]) {
+        let surface = self.surface().clone();
+
+        // Apply damage rectangles
+        if !rects.is_empty() {
+            let damage = coalesce_damage(
+                rects,
+                self.dimensions.pixel_width as i32,
+                self.dimensions.pixel_height as i32,
+            );
+            if surface.version() >= 4 {
+                for b in &damage {
+                    // The damage is in window coordinates, which differ
+                    // from those of the buffer when it is transformed
+                    let b = window_to_buffer(
+                        b,
+                        self.buffer_transform,
+                        self.dimensions.pixel_width as i32,
+                        self.dimensions.pixel_height as i32,
+                    );
+                    surface.damage_buffer(b.min.x, b.min.y, b.width(), b.height());
+                }
+            } else {
+                // surface is old and does not support damage_buffer,
+                // so we damage in surface coordinates instead
+                let dpi_factor = self.dimensions.dpi_factor();
+                for b in &damage {
+                    let b = buffer_to_surface(b, dpi_factor);
+                    surface.damage(b.min.x, b.min.y, b.width(), b.height());
+                }
+            }
+        }
+
+        // Don't let this frame be applied until the previous one has
+        // been presented, and make the next one wait for this one
+        if let Some(fifo) = self.fifo.as_ref() {
+            fifo.wait_barrier();
+            fifo.set_barrier();
+        }
+
+        // The frame has been committed, along with any target
+        // timestamp that do_paint set for it
+        self.commit_timestamp_pending = false;
+
+        // Request presentation feedback for accurate latency measurement
+        if let Some(conn) = WaylandConnection::get() {
+            let conn = conn.wayland();
+            let mut state = conn.wayland_state.borrow_mut();
+            if let Some(presentation) = state.presentation.clone() {
+                // This frame reflects any input that arrived since the
+                // previous frame was committed
+                let data = PresentationFeedbackData {
+                    surface: surface.clone(),
+                    input_time_ns: state.input_latency.take_pending_input(),
+                    committed_ns: state
+                        .presentation_clock_id
+                        .and_then(presentation_clock_now_ns),
+                };
+                let qh = conn.event_queue.borrow().handle();
+                let feedback = presentation.feedback(&surface, &qh, data);
+                self.presentation_feedback = Some(feedback);
+                log::trace!("Requested presentation feedback: {:?}", self.presentation_feedback);
+            }
+        }
+    }
+
     fn get_dpi_factor(&self) -> f64 {
         self.dimensions.dpi_factor()
     }
@@ -760,17 +1387,26 @@ impl WaylandWindowInner {
         self.dimensions.pixels_to_surface(pixels)
     }
 
-    pub(super) fn dispatch_dropped_files(&mut self, paths: Vec<PathBuf>) {
-        self.events.dispatch(WindowEvent::DroppedFile(paths));
+    pub(super) fn dispatch_dropped_urls(&mut self, urls: Vec<Url>, position: (f64, f64)) {
+        let coords = Point::new(
+            self.surface_to_pixels(position.0 as i32) as isize,
+            self.surface_to_pixels(position.1 as i32) as isize,
+        );
+        self.events
+            .dispatch(WindowEvent::DroppedUrlsAt { urls, coords });
     }
 
     pub(crate) fn dispatch_pending_mouse(&mut self) {
         let pending_mouse = Arc::clone(&self.pending_mouse);
 
         if let Some((x, y)) = PendingMouse::coords(&pending_mouse) {
+            // The surface coordinates have sub-pixel precision when
+            // fractional scaling is in use, so scale them before
+            // discarding the fraction
+            let dpi_factor = self.dimensions.dpi_factor();
             let coords = Point::new(
-                self.surface_to_pixels(x as i32) as isize,
-                self.surface_to_pixels(y as i32) as isize,
+                (x * dpi_factor).floor() as isize,
+                (y * dpi_factor).floor() as isize,
             );
             self.last_mouse_coords = coords;
             let event = MouseEvent {
@@ -862,12 +1498,58 @@ impl WaylandWindowInner {
             }
         }
 
+        if let Some((velocity_x, velocity_y)) = PendingMouse::kinetic(&pending_mouse) {
+            // Scale and orient these the same way as the wheel events above
+            let factor = self.get_dpi_factor() as f64;
+            self.events.dispatch(WindowEvent::KineticScroll {
+                horizontal: -velocity_x * factor,
+                vertical: -velocity_y * factor,
+            });
+        }
+
         if !PendingMouse::in_window(&pending_mouse) {
             self.events.dispatch(WindowEvent::MouseLeave);
             self.refresh_frame();
         }
     }
 
+    /// Dispatch a mouse event that was synthesized from touch or
+    /// tablet input at the given surface-local position
+    pub(super) fn dispatch_synthesized_mouse_event(
+        &mut self,
+        kind: MouseEventKind,
+        position: (f64, f64),
+    ) {
+        let coords = Point::new(
+            self.surface_to_pixels(position.0 as i32) as isize,
+            self.surface_to_pixels(position.1 as i32) as isize,
+        );
+        self.last_mouse_coords = coords;
+
+        let button_mask = |button: &MousePress| match button {
+            MousePress::Left => MouseButtons::LEFT,
+            MousePress::Right => MouseButtons::RIGHT,
+            MousePress::Middle => MouseButtons::MIDDLE,
+        };
+        match &kind {
+            MouseEventKind::Press(button) => self.mouse_buttons |= button_mask(button),
+            MouseEventKind::Release(button) => self.mouse_buttons -= button_mask(button),
+            _ => {}
+        }
+
+        let event = MouseEvent {
+            kind,
+            coords,
+            screen_coords: ScreenPoint::new(
+                coords.x + self.dimensions.pixel_width as isize,
+                coords.y + self.dimensions.pixel_height as isize,
+            ),
+            mouse_buttons: self.mouse_buttons,
+            modifiers: self.modifiers,
+        };
+        self.events.dispatch(WindowEvent::MouseEvent(event));
+    }
+
     pub(crate) fn dispatch_pending_event(&mut self) {
         let mut pending;
         {
@@ -880,13 +1562,28 @@ impl WaylandWindowInner {
             self.events.dispatch(WindowEvent::CloseRequested);
         }
 
+        let mut window_state_changed = false;
         if let Some(window_state) = pending.window_state.take() {
             log::debug!(
                 "dispatch_pending_event self.window_state={:?}, pending:{:?}",
                 self.window_state,
                 window_state
             );
+            window_state_changed = self.window_state != window_state;
+            if self.window_state.contains(WindowState::SUSPENDED)
+                && !window_state.contains(WindowState::SUSPENDED)
+            {
+                // The application doesn't paint while suspended, so the
+                // frame callback that we requested before it was may
+                // never be answered; request a fresh one
+                self.frame_callback.take();
+                self.frame_requested_at.take();
+            }
             self.window_state = window_state;
+            if window_state_changed {
+                // PresentationMode::Auto depends on fullscreen-ness
+                self.update_presentation_hint();
+            }
         }
 
         if pending.configure.is_none() {
@@ -900,17 +1597,44 @@ impl WaylandWindowInner {
             }
         }
 
+        let mut decoration_mode_changed = false;
         if let Some(ref window_config) = pending.window_configure {
             self.window_frame.update_state(window_config.state);
             self.window_frame
                 .update_wm_capabilities(window_config.capabilities);
+            self.xdg_server_decorations = window_config.decoration_mode == DecorationMode::Server;
+            decoration_mode_changed = true;
+        }
+        if let Some(server) = pending.kde_server_decorations.take() {
+            self.kde_server_decorations = server;
+            decoration_mode_changed = true;
+        }
+
+        if decoration_mode_changed {
+            // The compositor has the final say over who draws the
+            // decorations; only show our frame when it has left that
+            // to us, unless decorations were turned off entirely
+            let server_side = self.xdg_server_decorations || self.kde_server_decorations;
+            let hidden = self.config.window_decorations == WindowDecorations::NONE || server_side;
+            if hidden != self.window_frame.is_hidden() {
+                log::debug!("server side decorations={server_side}, csd frame hidden={hidden}");
+                self.window_frame.set_hidden(hidden);
+                pending.refresh_decorations = true;
+                if pending.configure.is_none() {
+                    // Re-apply the current size so that the frame and
+                    // window geometry get recomputed
+                    pending.configure.replace((
+                        self.pixels_to_surface(self.dimensions.pixel_width as i32) as u32,
+                        self.pixels_to_surface(self.dimensions.pixel_height as i32) as u32,
+                    ));
+                }
+            }
         }
 
         if let Some((mut w, mut h)) = pending.configure.take() {
             log::trace!("Pending configure: w:{w}, h{h} -- {:?}", self.window);
-            if self.window.is_some() {
-                let surface_udata = SurfaceUserData::from_wl(self.surface());
-                let factor = surface_udata.surface_data.scale_factor() as f64;
+            if self.has_surface() {
+                let factor = self.surface_scale_factor();
                 let old_dimensions = self.dimensions;
 
                 // FIXME: teach this how to resolve dpi_by_screen
@@ -960,11 +1684,14 @@ impl WaylandWindowInner {
                 // We must add BORDER_SIZE to height to include the bottom border,
                 // otherwise the compositor clips it and we get cutoff at the bottom.
                 const BORDER_SIZE: i32 = 12;
-                self.window
-                    .as_mut()
-                    .unwrap()
-                    .xdg_surface()
-                    .set_window_geometry(x, y, surface_width, surface_height + BORDER_SIZE);
+                if let Some(window) = self.window.as_mut() {
+                    window.xdg_surface().set_window_geometry(
+                        x,
+                        y,
+                        surface_width,
+                        surface_height + BORDER_SIZE,
+                    );
+                }
                 // NOTE: Geometry commit happens after buffer resize and paint
                 // to ensure buffer dimensions match the geometry we're committing.
                 // Compute the new pixel dimensions
@@ -978,10 +1705,12 @@ impl WaylandWindowInner {
                 // this makes things more efficient and a little more smooth
                 if new_dimensions != old_dimensions {
                     self.dimensions = new_dimensions;
+                    self.update_solid_background();
+                    self.update_window_background_blur();
 
                     self.events.dispatch(WindowEvent::Resized {
                         dimensions: self.dimensions,
-                        window_state: self.window_state,
+                        window_state: self.reported_window_state(),
                         // We don't know if we're live resizing or not, so
                         // assume no.
                         live_resizing: false,
@@ -998,10 +1727,17 @@ impl WaylandWindowInner {
                     // We do this only if the scale has actually changed,
                     // otherwise interactive window resize will keep removing
                     // the window contents!
-                    if let Some(wegl_surface) = self.wegl_surface.as_mut() {
-                        wegl_surface.resize(pixel_width, pixel_height, 0, 0);
-                    }
-                    if self.surface_factor != factor {
+                    self.resize_wegl_surface();
+                    if let Some(viewport) = self.fractional_viewport() {
+                        // We render at the exact fractional pixel size and
+                        // have the viewport map the buffer onto the surface,
+                        // so the buffer itself is unscaled
+                        viewport.set_destination(surface_width, surface_height);
+                        if self.surface_factor != factor {
+                            self.surface().set_buffer_scale(1);
+                            self.surface_factor = factor;
+                        }
+                    } else if self.surface_factor != factor {
                         let wayland_conn = Connection::get().unwrap().wayland();
                         let wayland_state = wayland_conn.wayland_state.borrow();
                         let mut pool = wayland_state.mem_pool.borrow_mut();
@@ -1024,14 +1760,22 @@ impl WaylandWindowInner {
                             self.surface_factor = factor;
                         }
                     }
+                } else if window_state_changed {
+                    // Let the gui know about eg: a change in the
+                    // decoration mode even though the size is the same
+                    self.events.dispatch(WindowEvent::Resized {
+                        dimensions: self.dimensions,
+                        window_state: self.reported_window_state(),
+                        live_resizing: false,
+                    });
                 }
                 self.do_paint().unwrap();
             }
         }
-        if pending.refresh_decorations && self.window.is_some() {
+        if pending.refresh_decorations && self.has_surface() {
             self.refresh_frame();
         }
-        if pending.had_configure_event && self.window.is_some() {
+        if pending.had_configure_event && self.has_surface() {
             log::debug!("Had configured an event");
             if let Some(notify) = self.pending_first_configure.take() {
                 // Allow window creation to complete
@@ -1047,35 +1791,54 @@ impl WaylandWindowInner {
 
         let conn = Connection::get().unwrap().wayland();
         let state = conn.wayland_state.borrow_mut();
-        let pointer = match &state.pointer {
-            Some(pointer) => pointer,
-            None => return,
-        };
+        let surface_id = self.surface().id();
 
-        match cursor {
-            Some(cursor) => {
-                if let Err(err) = pointer.set_cursor(
-                    &conn.connection,
-                    match cursor {
-                        MouseCursor::Arrow => CursorIcon::Default,
-                        MouseCursor::Hand => CursorIcon::Pointer,
-                        MouseCursor::SizeUpDown => CursorIcon::NsResize,
-                        MouseCursor::SizeLeftRight => CursorIcon::EwResize,
-                        MouseCursor::Text => CursorIcon::Text,
-                    },
-                ) {
-                    log::error!("set_cursor: {}", err);
-                }
+        // Each seat that has its pointer over the window gets the cursor
+        for pointer in state
+            .seats
+            .values()
+            .filter_map(|seat| seat.pointer.as_ref())
+        {
+            let over_window = pointer
+                .pointer()
+                .data::<PointerUserData>()
+                .map(|data| data.is_over(&surface_id))
+                .unwrap_or(false);
+            if !over_window {
+                continue;
             }
-            None => {
-                if let Err(err) = pointer.hide_cursor() {
-                    log::error!("hide_cursor: {}", err)
+
+            match cursor {
+                Some(cursor) => {
+                    if let Err(err) = pointer.set_cursor(
+                        &conn.connection,
+                        match cursor {
+                            MouseCursor::Arrow => CursorIcon::Default,
+                            MouseCursor::Hand => CursorIcon::Pointer,
+                            MouseCursor::SizeUpDown => CursorIcon::NsResize,
+                            MouseCursor::SizeLeftRight => CursorIcon::EwResize,
+                            MouseCursor::Text => CursorIcon::Text,
+                        },
+                    ) {
+                        log::error!("set_cursor: {}", err);
+                    }
+                }
+                None => {
+                    if let Err(err) = pointer.hide_cursor() {
+                        log::error!("hide_cursor: {}", err)
+                    }
                 }
             }
         }
     }
 
     fn invalidate(&mut self) {
+        if self.window_state.contains(WindowState::SUSPENDED) || self.frame_callback_is_starved() {
+            // Nothing that we paint would be seen; defer it until
+            // the compositor tells us that the window is visible again
+            self.invalidated = true;
+            return;
+        }
         if self.frame_callback.is_some() {
             // PERFORMANCE: Instead of just setting invalidated and returning,
             // dispatch NeedRepaint immediately. This allows the rendering to
@@ -1096,27 +1859,25 @@ impl WaylandWindowInner {
         let conn = WaylandConnection::get().unwrap().wayland();
         let state = conn.wayland_state.borrow();
         let surface = self.surface().clone();
-        let active_surface_id = state.active_surface_id.borrow();
         let surface_id = surface.id();
 
-        if let Some(active_surface_id) = active_surface_id.as_ref() {
-            if surface_id == active_surface_id.clone() {
-                if self.text_cursor.map(|prior| prior != rect).unwrap_or(true) {
-                    self.text_cursor.replace(rect);
-
-                    let surface_udata = SurfaceUserData::from_wl(&surface);
-                    let factor = surface_udata.surface_data().scale_factor();
-
-                    if let Some(text_input) = &state.text_input {
-                        if let Some(input) = text_input.get_text_input_for_surface(&surface) {
-                            input.set_cursor_rectangle(
-                                rect.min_x() as i32 / factor,
-                                rect.min_y() as i32 / factor,
-                                rect.width() as i32 / factor,
-                                rect.height() as i32 / factor,
-                            );
-                            input.commit();
-                        }
+        if state.has_keyboard_focus(&surface_id) {
+            if self.text_cursor.map(|prior| prior != rect).unwrap_or(true) {
+                self.text_cursor.replace(rect);
+
+                // The rectangle is in surface-local coordinates, which
+                // may be a fractional scale of our pixels
+                let factor = self.dimensions.dpi_factor();
+
+                if let Some(text_input) = &state.text_input {
+                    if let Some(input) = text_input.get_text_input_for_surface(&surface) {
+                        input.set_cursor_rectangle(
+                            (rect.min_x() as f64 / factor).floor() as i32,
+                            (rect.min_y() as f64 / factor).floor() as i32,
+                            self.pixels_to_surface(rect.width() as i32),
+                            self.pixels_to_surface(rect.height() as i32),
+                        );
+                        input.commit();
                     }
                 }
             }
@@ -1162,13 +1923,23 @@ impl WaylandWindowInner {
     }
 
     fn do_paint(&mut self) -> anyhow::Result<()> {
-        if self.window.is_none() {
+        if !self.has_surface() {
             // We're likely in the middle of closing/destroying
             // the window; we've nothing to do here.
             return Ok(());
         }
 
         if self.frame_callback.is_some() {
+            // With fifo-v1 the compositor holds on to each commit until
+            // the previous one has been presented, so rather than waiting
+            // for the callback we can queue up one more frame.
+            if self.fifo.is_some() && !self.fifo_frame_queued {
+                self.fifo_frame_queued = true;
+                self.invalidated = false;
+                self.set_commit_target();
+                self.events.dispatch(WindowEvent::NeedRepaint);
+                return Ok(());
+            }
             // A frame callback is already pending. We cannot request another one
             // until the compositor responds to the first one, as per Wayland protocol.
             // Mark as invalidated so we'll paint when the callback arrives.
@@ -1187,6 +1958,7 @@ impl WaylandWindowInner {
 
         log::trace!("do_paint - callback: {:?}", callback);
         self.frame_callback.replace(callback);
+        self.frame_requested_at.replace(Instant::now());
 
         // The repaint has the side of effect of committing the surface,
         // which is necessary for the frame callback to get triggered.
@@ -1194,13 +1966,173 @@ impl WaylandWindowInner {
         // we will get woken at the appropriate time.
         // <https://github.com/wezterm/wezterm/issues/3468>
         // <https://github.com/wezterm/wezterm/issues/3126>
+        self.set_commit_target();
         self.events.dispatch(WindowEvent::NeedRepaint);
 
         Ok(())
     }
 
+    /// Works out how often the display that we are on refreshes,
+    /// and lets the application know when that changes, so that it
+    /// can pace its animations to match.
+    /// Presentation feedback is the most accurate source, but isn't
+    /// available until we have presented a frame, and doesn't report
+    /// a rate for variable refresh rate displays, so we fall back
+    /// to the current mode of the outputs that the surface is on.
+    pub(super) fn update_refresh_interval(&mut self) {
+        let interval = if self.refresh_interval_ns > 0 {
+            Some(Duration::from_nanos(self.refresh_interval_ns as u64))
+        } else {
+            let conn = WaylandConnection::get().unwrap().wayland();
+            let state = conn.wayland_state.borrow();
+            let surface_udata = SurfaceUserData::from_wl(self.surface());
+            // If we span several outputs, keep up with the fastest one
+            surface_udata
+                .surface_data()
+                .outputs()
+                .filter_map(|output| state.output.info(&output))
+                .filter_map(|info| {
+                    info.modes
+                        .iter()
+                        .find(|mode| mode.current)
+                        .map(|mode| mode.refresh_rate)
+                })
+                .filter(|&millihertz| millihertz > 0)
+                .max()
+                .map(|millihertz| Duration::from_nanos(1_000_000_000_000 / millihertz as u64))
+        };
+
+        let interval = match interval {
+            Some(interval) => interval,
+            None => return,
+        };
+        if self.reported_refresh_interval == Some(interval) {
+            return;
+        }
+        log::debug!("refresh interval is now {interval:?}");
+        self.reported_refresh_interval.replace(interval);
+        self.events
+            .dispatch(WindowEvent::RefreshIntervalChanged(interval));
+    }
+
+    /// Ask the compositor to present the frame that we are about to
+    /// render at the next refresh of the display, as predicted from
+    /// the most recent presentation feedback
+    fn set_commit_target(&mut self) {
+        let timer = match self.commit_timer.as_ref() {
+            Some(timer) => timer,
+            None => return,
+        };
+        if self.commit_timestamp_pending {
+            return;
+        }
+        if self.config.wayland_frame_timing == WaylandFrameTiming::EarliestPresent {
+            // Leaving the frame untimed lets VRR displays refresh
+            // as soon as it is ready
+            return;
+        }
+        let refresh = self.refresh_interval_ns as u64;
+        let last_presented = match self.last_presentation_time {
+            Some(last) if refresh > 0 => last,
+            _ => return,
+        };
+        let clock_id = match WaylandConnection::get()
+            .and_then(|conn| conn.wayland().wayland_state.borrow().presentation_clock_id)
+        {
+            Some(clock_id) => clock_id,
+            None => return,
+        };
+        let now = match presentation_clock_now_ns(clock_id) {
+            Some(now) => now,
+            None => return,
+        };
+
+        let elapsed = now.saturating_sub(last_presented);
+        let next_refresh = last_presented + (elapsed / refresh + 1) * refresh;
+        // The timestamp means "not before", so aim half a cycle early
+        // to stay clear of jitter in the reported presentation times
+        // pushing us out to the refresh after the one we want
+        let target = next_refresh - refresh / 2;
+
+        let secs = target / 1_000_000_000;
+        let nsecs = (target % 1_000_000_000) as u32;
+        timer.set_timestamp((secs >> 32) as u32, secs as u32, nsecs);
+        self.commit_timestamp_pending = true;
+        log::trace!("commit target {target}ns, next refresh {next_refresh}ns, now {now}ns");
+    }
+
+    /// Returns the viewport through which we present buffers rendered
+    /// at the compositor's preferred fractional scale, if both the
+    /// fractional-scale and viewporter protocols are available
+    fn fractional_viewport(&self) -> Option<&WpViewport> {
+        self.current_fractional_scale?;
+        self.viewport.as_ref()
+    }
+
+    /// The scale at which we render: the preferred fractional scale
+    /// when we can use it, otherwise the integer scale of the surface
+    fn surface_scale_factor(&self) -> f64 {
+        match self.current_fractional_scale {
+            Some(scale) if self.viewport.is_some() => scale as f64 / 120.0,
+            _ => SurfaceUserData::from_wl(self.surface())
+                .surface_data
+                .scale_factor() as f64,
+        }
+    }
+
+    /// Tags the surface with the color space that the application
+    /// is now rendering; takes effect once the compositor has
+    /// prepared the new image description
+    fn set_hdr_content(&mut self, hdr: bool) {
+        if hdr == self.hdr_content || !self.has_surface() {
+            return;
+        }
+        self.hdr_content = hdr;
+        if let Some(color_surface) = self.color_surface.take() {
+            color_surface.destroy();
+        }
+
+        let conn = WaylandConnection::get().unwrap().wayland();
+        let wayland_state = conn.wayland_state.borrow();
+        let qh = conn.event_queue.borrow().handle();
+        if let Some(color_manager) = &wayland_state.color_manager {
+            self.color_surface = color_manager.describe_surface(&qh, self.surface(), hdr);
+            log::debug!(
+                "surface content is now {}",
+                if hdr { "scRGB" } else { "sRGB" }
+            );
+        }
+    }
+
+    /// Tells the application when the surface moves on to or off of
+    /// an HDR output
+    pub(super) fn update_hdr_output(&mut self) {
+        if !self.has_surface() {
+            return;
+        }
+        let hdr_output = {
+            let conn = WaylandConnection::get().unwrap().wayland();
+            let state = conn.wayland_state.borrow();
+            SurfaceUserData::from_wl(self.surface())
+                .surface_data()
+                .outputs()
+                .filter_map(|output| state.output_color.get(&output.id()))
+                .any(|info| info.is_hdr())
+        };
+        if hdr_output == self.hdr_output {
+            return;
+        }
+        log::debug!("surface hdr output is now {hdr_output}");
+        self.hdr_output = hdr_output;
+        self.events
+            .dispatch(WindowEvent::HdrOutputChanged(hdr_output));
+    }
+
     pub(super) fn surface(&self) -> &WlSurface {
-        self.window
+        if let Some(window) = self.window.as_ref() {
+            return window.wl_surface();
+        }
+        self.layer_surface
             .as_ref()
             .expect("Window should exist")
             .wl_surface()
@@ -1240,7 +2172,7 @@ impl WaylandWindowInner {
             }
         }
 
-        // Setup tearing control - default to vsync, but allow async mode later
+        // Setup tearing control; see update_presentation_hint
         if let Some(tearing_control_manager) = &wayland_state.tearing_control_manager {
             if self.tearing_control.is_none() {
                 let tearing_control = tearing_control_manager.get_tearing_control(
@@ -1248,18 +2180,285 @@ impl WaylandWindowInner {
                     &qh,
                     surface.clone(),
                 );
-
-                // Start with vsync mode (default), can be changed to async later
-                // For now, use vsync for smooth presentation
-                tearing_control.set_presentation_hint(PresentationHint::Vsync);
-                log::info!("Enabled tearing-control protocol for surface (vsync mode)");
+                log::info!("Enabled tearing-control protocol for surface");
                 self.tearing_control = Some(tearing_control);
             }
         }
+
+        // Setup content-type; see update_presentation_hint
+        if let Some(content_type_manager) = &wayland_state.content_type_manager {
+            if self.content_type.is_none() {
+                let content_type =
+                    content_type_manager.get_surface_content_type(&surface, &qh, surface.clone());
+                log::info!("Enabled content-type protocol for surface");
+                self.content_type = Some(content_type);
+            }
+        }
+
+        self.update_presentation_hint();
+
+        // Setup fifo so that our commits are queued by the compositor
+        // and applied one per refresh cycle, rather than each replacing
+        // the last; see finish_frame
+        if let Some(fifo_manager) = &wayland_state.fifo_manager {
+            if self.fifo.is_none() {
+                let fifo = fifo_manager.get_fifo(&surface, &qh, surface.clone());
+                log::info!("Enabled fifo protocol for surface");
+                self.fifo = Some(fifo);
+            }
+        }
+
+        // Setup commit timing so that we can tell the compositor which
+        // refresh each frame is intended for; see set_commit_target
+        if let Some(commit_timing_manager) = &wayland_state.commit_timing_manager {
+            if self.commit_timer.is_none() {
+                let timer = commit_timing_manager.get_timer(&surface, &qh, surface.clone());
+                log::info!("Enabled commit-timing protocol for surface");
+                self.commit_timer = Some(timer);
+            }
+        }
+
+        // Ask for dmabuf feedback, so that we can tell whether the
+        // compositor could scan out this surface directly
+        if self.dmabuf_feedback.is_none() {
+            self.dmabuf_feedback = wayland_state
+                .dmabuf
+                .get_surface_feedback(&surface, &qh)
+                .ok();
+        }
+
+        // Setup color management; we render sRGB content, so say so
+        // explicitly rather than leaving it to the compositor to assume
+        if let Some(color_manager) = &wayland_state.color_manager {
+            if self.color_surface.is_none() {
+                self.color_surface =
+                    color_manager.describe_surface(&qh, &surface, self.hdr_content);
+                if self.color_surface.is_some() {
+                    log::info!("Enabled color-management protocol for surface");
+                }
+            }
+        }
+    }
+
+    fn set_modal_parent(&mut self, parent_id: usize) {
+        if parent_id == SurfaceUserData::from_wl(self.surface()).window_id {
+            return;
+        }
+        let conn = WaylandConnection::get().unwrap().wayland();
+        let qh = conn.event_queue.borrow().handle();
+        let wayland_state = conn.wayland_state.borrow();
+
+        let parent = match wayland_state.window_by_id(parent_id) {
+            Some(parent) => parent,
+            None => return,
+        };
+        let parent = parent.borrow();
+        let (window, parent_window) = match (self.window.as_ref(), parent.window.as_ref()) {
+            (Some(window), Some(parent_window)) => (window, parent_window),
+            _ => return,
+        };
+
+        window.set_parent(Some(parent_window));
+        if let Some(wm_dialog) = &wayland_state.xdg_wm_dialog {
+            if self.xdg_dialog.is_none() {
+                let dialog = wm_dialog.get_xdg_dialog(window.xdg_toplevel(), &qh, GlobalData);
+                dialog.set_modal();
+                self.xdg_dialog.replace(dialog);
+            }
+        }
+    }
+
+    fn set_solid_background(&mut self, color: Option<SrgbaTuple>) {
+        self.solid_background_color = color;
+        if color.is_none() {
+            self.solid_background.take();
+        }
+        self.update_solid_background();
+    }
+
+    /// Bring the background subsurface in line with the requested
+    /// color and our current dimensions
+    fn update_solid_background(&mut self) {
+        let color = match self.solid_background_color {
+            Some(color) => color,
+            None => return,
+        };
+        if !self.has_surface() {
+            return;
+        }
+
+        let conn = WaylandConnection::get().unwrap().wayland();
+        let wayland_state = conn.wayland_state.borrow();
+        let qh = conn.event_queue.borrow().handle();
+
+        if self.solid_background.is_none() {
+            let surface = self.surface().clone();
+            self.solid_background = SolidBackground::new(&wayland_state, &qh, &surface);
+        }
+
+        let width = self.pixels_to_surface(self.dimensions.pixel_width as i32);
+        let height = self.pixels_to_surface(self.dimensions.pixel_height as i32);
+        if let Some(background) = self.solid_background.as_mut() {
+            background.update(&wayland_state, &qh, color, width, height);
+        }
+    }
+
+    fn toggle_shortcut_inhibit(&mut self) {
+        if let Some(inhibitor) = self.shortcut_inhibitor.take() {
+            inhibitor.destroy();
+            log::info!("Released keyboard shortcuts inhibitor");
+            return;
+        }
+        if !self.has_surface() {
+            return;
+        }
+
+        let conn = WaylandConnection::get().unwrap().wayland();
+        let wayland_state = conn.wayland_state.borrow();
+        let qh = conn.event_queue.borrow().handle();
+
+        let manager = match &wayland_state.keyboard_shortcuts_inhibit_manager {
+            Some(manager) => manager,
+            None => {
+                log::warn!(
+                    "ToggleShortcutInhibit: compositor doesn't support \
+                     zwp_keyboard_shortcuts_inhibit_manager_v1"
+                );
+                return;
+            }
+        };
+        // Inhibit the shortcuts of the seat that asked for it
+        let seat = match wayland_state.active_seat() {
+            Some(seat) => seat.seat.clone(),
+            None => return,
+        };
+
+        let surface = self.surface().clone();
+        let inhibitor = manager.inhibit_shortcuts(&surface, &seat, &qh, surface.clone());
+        log::info!("Requested keyboard shortcuts inhibitor");
+        self.shortcut_inhibitor.replace(inhibitor);
+    }
+
+    fn set_icon(&mut self, image: &Image) {
+        let window = match self.window.as_ref() {
+            Some(window) => window,
+            None => return,
+        };
+
+        let conn = WaylandConnection::get().unwrap().wayland();
+        let wayland_state = conn.wayland_state.borrow();
+        let qh = conn.event_queue.borrow().handle();
+
+        let icon_manager = match &wayland_state.toplevel_icon_manager {
+            Some(icon_manager) => icon_manager,
+            None => return,
+        };
+
+        // Icons must be square, so non-square images are letterboxed.
+        // If the compositor has no preference, offer the image at its
+        // own size
+        let (width, height) = image.image_dimensions();
+        let sizes = if icon_manager.sizes.is_empty() {
+            vec![width.max(height) as i32]
+        } else {
+            icon_manager.sizes.clone()
+        };
+        let max_scale = (self.get_dpi_factor().ceil() as i32).max(1);
+
+        let icon = icon_manager.manager.create_icon(&qh, ());
+        let mut buffers = vec![];
+        let mut pool = wayland_state.mem_pool.borrow_mut();
+
+        for size in sizes {
+            for scale in 1..=max_scale {
+                let edge = size * scale;
+                let scaled = letterbox(image, edge as usize);
+                let (buffer, canvas) = match pool.create_buffer(
+                    edge,
+                    edge,
+                    edge * 4,
+                    wayland_client::protocol::wl_shm::Format::Argb8888,
+                ) {
+                    Ok(b) => b,
+                    Err(err) => {
+                        log::error!("failed to allocate {edge}x{edge} icon buffer: {err:#}");
+                        continue;
+                    }
+                };
+                // The image is straight rgba, but the buffer is
+                // premultiplied, little endian argb
+                for (dest, pixel) in canvas.chunks_exact_mut(4).zip(scaled.pixels()) {
+                    let [r, g, b, a] = pixel.to_ne_bytes();
+                    let premultiply = |c: u8| ((c as u16 * a as u16) / 255) as u8;
+                    dest.copy_from_slice(&[premultiply(b), premultiply(g), premultiply(r), a]);
+                }
+                icon.add_buffer(buffer.wl_buffer(), scale);
+                buffers.push(buffer);
+            }
+        }
+
+        icon_manager
+            .manager
+            .set_icon(window.xdg_toplevel(), Some(&icon));
+        drop(pool);
+        drop(wayland_state);
+
+        if let Some((old_icon, _old_buffers)) = self.icon.replace((icon, buffers)) {
+            old_icon.destroy();
+        }
+
+        // The icon is double buffered state that takes effect when
+        // the surface is next committed, which happens when we repaint
+        self.invalidate();
+    }
+
+    /// The window state, including SUSPENDED while our
+    /// frame callbacks are being starved
+    fn reported_window_state(&self) -> WindowState {
+        if self.frame_callback_starved {
+            self.window_state | WindowState::SUSPENDED
+        } else {
+            self.window_state
+        }
+    }
+
+    /// Returns true if the pending frame callback has gone unanswered
+    /// for long enough that the window is unlikely to be visible, and
+    /// lets the application know when it first notices
+    fn frame_callback_is_starved(&mut self) -> bool {
+        if self.frame_callback_starved {
+            return true;
+        }
+        let starved = self.frame_callback.is_some()
+            && self
+                .frame_requested_at
+                .map(|when| when.elapsed() >= FRAME_CALLBACK_STARVATION)
+                .unwrap_or(false);
+        if starved {
+            log::debug!("frame callback starved; suspending painting");
+            self.frame_callback_starved = true;
+            self.events.dispatch(WindowEvent::Resized {
+                dimensions: self.dimensions,
+                window_state: self.reported_window_state(),
+                live_resizing: false,
+            });
+        }
+        starved
     }
 
     pub(crate) fn next_frame_is_ready(&mut self) {
         self.frame_callback.take();
+        self.frame_requested_at.take();
+        self.fifo_frame_queued = false;
+        if self.frame_callback_starved {
+            log::debug!("frame callback arrived; resuming painting");
+            self.frame_callback_starved = false;
+            self.events.dispatch(WindowEvent::Resized {
+                dimensions: self.dimensions,
+                window_state: self.reported_window_state(),
+                live_resizing: false,
+            });
+        }
         if self.invalidated {
             self.do_paint().ok();
         }
@@ -1287,11 +2486,8 @@ impl WaylandWindowInner {
         }
     }
 
-    pub(super) fn keyboard_event(
-        &mut self,
-        mapper: &mut KeyboardWithFallback,
-        event: WlKeyboardEvent,
-    ) {
+    pub(super) fn keyboard_event(&mut self, seat: &mut WaylandSeat, event: WlKeyboardEvent) {
+        let mapper = seat.keyboard_mapper.as_mut().expect("no keymap");
         match event {
             WlKeyboardEvent::Enter { keys, .. } => {
                 let key_codes = keys
@@ -1313,15 +2509,11 @@ impl WaylandWindowInner {
                     let rep = Arc::new(Mutex::new(KeyRepeatState {
                         when: Instant::now(),
                         event,
+                        rate: seat.key_repeat_rate,
+                        delay: seat.key_repeat_delay,
                     }));
                     self.key_repeat.replace((key, Arc::clone(&rep)));
-                    let window_id = SurfaceUserData::from_wl(
-                        self.window
-                            .as_ref()
-                            .expect("window should exist")
-                            .wl_surface(),
-                    )
-                    .window_id;
+                    let window_id = SurfaceUserData::from_wl(self.surface()).window_id;
                     KeyRepeatState::schedule(rep, window_id);
                 } else if let Some((cur_key, _)) = self.key_repeat.as_ref() {
                     // important to check that it's the same key, because the release of the previously
@@ -1406,20 +2598,177 @@ impl WaylandWindowInner {
     fn config_did_change(&mut self, config: ConfigHandle) {
         self.config = config;
         self.update_window_background_blur();
+        self.update_presentation_hint();
+    }
+
+    fn presentation_hint(&self) -> PresentationHint {
+        let mode = self
+            .presentation_mode_override
+            .unwrap_or(self.config.presentation_mode);
+        match mode {
+            PresentationMode::Vsync => PresentationHint::Vsync,
+            PresentationMode::Async => PresentationHint::Async,
+            PresentationMode::Auto if self.window_state.contains(WindowState::FULL_SCREEN) => {
+                PresentationHint::Async
+            }
+            PresentationMode::Auto => PresentationHint::Vsync,
+        }
+    }
+
+    /// Tell the compositor whether it may tear in order to present
+    /// our frames sooner.  Both hints are double-buffered, so they
+    /// take effect with the next frame that we commit.
+    fn update_presentation_hint(&self) {
+        let hint = self.presentation_hint();
+        if let Some(tearing_control) = self.tearing_control.as_ref() {
+            tearing_control.set_presentation_hint(hint);
+            log::debug!("presentation hint is now {hint:?}");
+        }
+
+        // The content-type protocol has no category for text, so we
+        // use "none" which asks the compositor not to apply any of the
+        // photo/video specific filtering; when we allow tearing we are
+        // prioritizing latency, which is what "game" conveys
+        if let Some(content_type) = self.content_type.as_ref() {
+            content_type.set_content_type(if hint == PresentationHint::Async {
+                ContentType::Game
+            } else {
+                ContentType::None
+            });
+        }
+    }
+
+    fn toggle_presentation_mode(&mut self) {
+        if self.tearing_control.is_none() {
+            log::warn!(
+                "TogglePresentationMode: compositor doesn't support \
+                 wp_tearing_control_v1"
+            );
+            return;
+        }
+        let mode = if self.presentation_hint() == PresentationHint::Async {
+            PresentationMode::Vsync
+        } else {
+            PresentationMode::Async
+        };
+        log::info!("Switching to {mode:?} presentation");
+        self.presentation_mode_override.replace(mode);
+        self.update_presentation_hint();
+        self.invalidate();
+    }
+
+    fn start_drag(&mut self, data: DragData) {
+        let conn = WaylandConnection::get().unwrap().wayland();
+        let qh = conn.event_queue.borrow().handle();
+        let mut state = conn.wayland_state.borrow_mut();
+
+        let mut mime_types = vec![TEXT_MIME_TYPE, PLAIN_TEXT_MIME_TYPE];
+        if !data.uris.is_empty() {
+            mime_types.push(URI_MIME_TYPE);
+        }
+        let source = state.data_device_manager_state.create_drag_and_drop_source(
+            &qh,
+            mime_types,
+            DndAction::Copy,
+        );
+
+        // The drag belongs to the seat whose button press began it,
+        // and that press is the most recent serial from that seat
+        match state.active_seat() {
+            Some(seat) => match seat.data_device.as_ref() {
+                Some(data_device) => {
+                    source.start_drag(data_device, self.surface(), None, seat.last_serial)
+                }
+                None => {
+                    log::warn!("start_drag: no data device");
+                    return;
+                }
+            },
+            None => {
+                log::warn!("start_drag: no seat");
+                return;
+            }
+        }
+        state.drag_source.replace((source, data));
     }
 
-    fn update_window_background_blur(&self) {
+    /// Apply kde_window_background_blur to the surface, limited to
+    /// blur_region if the gui has set one, and with the corners
+    /// rounded off by kde_window_background_blur_corner_radius.
+    /// Like the rest of the surface state, this takes effect when
+    /// we next commit the surface.
+    fn update_window_background_blur(&mut self) {
         let conn = WaylandConnection::get().unwrap().wayland();
         let qh = conn.event_queue.borrow().handle();
         let wayland_state = conn.wayland_state.borrow();
-        if let Some(manager) = &wayland_state.kde_blur_manager {
-            let kde_blur = manager.create(self.surface(), &qh, GlobalData);
-            if self.config.kde_window_background_blur {
-                kde_blur.set_region(None);
-            } else {
+        let manager = match &wayland_state.kde_blur_manager {
+            Some(manager) => manager,
+            None => return,
+        };
+        let surface = self.surface().clone();
+
+        if !self.config.kde_window_background_blur {
+            if let Some(kde_blur) = self.kde_blur.take() {
                 kde_blur.release();
+                manager.unset(&surface);
             }
-            kde_blur.commit();
+            return;
+        }
+
+        let radius = self.config.kde_window_background_blur_corner_radius as i32;
+        let region = if self.blur_region.is_none() && radius == 0 {
+            None
+        } else {
+            let rect = self.blur_region.unwrap_or_else(|| {
+                Rect::new(
+                    Point::new(0, 0),
+                    Size::new(
+                        self.dimensions.pixel_width as isize,
+                        self.dimensions.pixel_height as isize,
+                    ),
+                )
+            });
+            let factor = self.dimensions.dpi_factor();
+            let x = (rect.min_x() as f64 / factor).floor() as i32;
+            let y = (rect.min_y() as f64 / factor).floor() as i32;
+            let width = self.pixels_to_surface(rect.width() as i32);
+            let height = self.pixels_to_surface(rect.height() as i32);
+
+            match Region::new(&wayland_state.compositor) {
+                Ok(region) => {
+                    region.add(x, y, width, height);
+                    subtract_rounded_corners(&region, x, y, width, height, radius);
+                    Some(region)
+                }
+                Err(err) => {
+                    log::warn!("update_window_background_blur: {err:#}");
+                    None
+                }
+            }
+        };
+
+        let kde_blur = self
+            .kde_blur
+            .get_or_insert_with(|| manager.create(&surface, &qh, GlobalData));
+        kde_blur.set_region(region.as_ref().map(|region| region.wl_region()));
+        kde_blur.commit();
+    }
+}
+
+/// wl_region can only describe a union of rectangles, so approximate
+/// rounded corners by removing a strip of the appropriate width from
+/// each corner of each row within `radius` of the top and bottom edges
+fn subtract_rounded_corners(region: &Region, x: i32, y: i32, width: i32, height: i32, radius: i32) {
+    let radius = radius.min(width / 2).min(height / 2);
+    for row in 0..radius {
+        let dy = radius as f64 - (row as f64 + 0.5);
+        let inset = radius - ((radius * radius) as f64 - dy * dy).sqrt().round() as i32;
+        if inset <= 0 {
+            continue;
+        }
+        for row_y in [y + row, y + height - 1 - row] {
+            region.subtract(x, row_y, inset, 1);
+            region.subtract(x + width - inset, row_y, inset, 1);
         }
     }
 }
@@ -1429,8 +2778,8 @@ impl WaylandState {
         self.windows.borrow().get(&window_id).map(Rc::clone)
     }
 
-    fn handle_window_event(&self, window: &XdgWindow, event: WaylandWindowEvent) {
-        let surface_data = SurfaceUserData::from_wl(window.wl_surface());
+    fn handle_window_event(&self, surface: &WlSurface, event: WaylandWindowEvent) {
+        let surface_data = SurfaceUserData::from_wl(surface);
         let window_id = surface_data.window_id;
 
         let window_inner = self
@@ -1472,6 +2821,23 @@ impl WaylandState {
                 if configure.state.contains(SCTKWindowState::MAXIMIZED) {
                     state |= WindowState::MAXIMIZED;
                 }
+                for (sctk, tiled) in [
+                    (SCTKWindowState::TILED_LEFT, WindowState::TILED_LEFT),
+                    (SCTKWindowState::TILED_RIGHT, WindowState::TILED_RIGHT),
+                    (SCTKWindowState::TILED_TOP, WindowState::TILED_TOP),
+                    (SCTKWindowState::TILED_BOTTOM, WindowState::TILED_BOTTOM),
+                ] {
+                    state.set(tiled, configure.state.contains(sctk));
+                }
+                if configure.state.contains(SCTKWindowState::SUSPENDED) {
+                    state |= WindowState::SUSPENDED;
+                }
+                let kde_server_decorations = pending_event
+                    .kde_server_decorations
+                    .unwrap_or_else(|| window_inner.borrow().kde_server_decorations);
+                if configure.decoration_mode == DecorationMode::Server || kde_server_decorations {
+                    state |= WindowState::SERVER_DECORATIONS;
+                }
 
                 log::debug!(
                     "Config: self.window_state={:?}, states: {:?} {:?}",
@@ -1487,6 +2853,32 @@ impl WaylandState {
                 pending_event.window_state.replace(state);
                 changed
             }
+            WaylandWindowEvent::LayerConfigure(configure) => {
+                pending_event.had_configure_event = true;
+                let (mut w, mut h) = configure.new_size;
+                // A zero dimension means that the compositor leaves it
+                // up to us, so we keep our current size on that axis
+                if w == 0 || h == 0 {
+                    let inner = window_inner.borrow();
+                    if w == 0 {
+                        w = inner.pixels_to_surface(inner.dimensions.pixel_width as i32) as u32;
+                    }
+                    if h == 0 {
+                        h = inner.pixels_to_surface(inner.dimensions.pixel_height as i32) as u32;
+                    }
+                }
+                pending_event.configure.replace((w, h));
+                true
+            }
+            WaylandWindowEvent::KdeServerDecorations(server) => {
+                let mut state = pending_event
+                    .window_state
+                    .unwrap_or_else(|| window_inner.borrow().window_state);
+                state.set(WindowState::SERVER_DECORATIONS, server);
+                pending_event.window_state.replace(state);
+                pending_event.kde_server_decorations.replace(server);
+                true
+            }
         };
         if changed {
             WaylandConnection::with_window_inner(window_id, move |inner| {
@@ -1502,10 +2894,27 @@ impl CompositorHandler for WaylandState {
         &mut self,
         _conn: &WConnection,
         _qh: &wayland_client::QueueHandle<Self>,
-        _surface: &wayland_client::protocol::wl_surface::WlSurface,
-        _new_factor: i32,
+        surface: &wayland_client::protocol::wl_surface::WlSurface,
+        new_factor: i32,
     ) {
-        // We do nothing, we get the scale_factor from surface_data
+        // The SurfaceData keeps track of the factor for us: either the
+        // preferred_buffer_scale sent by newer compositors, or else the
+        // largest scale of the outputs that the surface is on.
+        // When we have fractional scaling, that takes precedence.
+        let surface_data = SurfaceUserData::from_wl(surface);
+        WaylandConnection::with_window_inner(surface_data.window_id, move |inner| {
+            if inner.fractional_viewport().is_none() {
+                log::debug!("preferred buffer scale is now {new_factor}");
+                inner
+                    .pending_event
+                    .lock()
+                    .unwrap()
+                    .dpi
+                    .replace(new_factor * crate::DEFAULT_DPI as i32);
+                inner.dispatch_pending_event();
+            }
+            Ok(())
+        });
     }
 
     fn frame(
@@ -1529,28 +2938,62 @@ impl CompositorHandler for WaylandState {
         &mut self,
         _conn: &WConnection,
         _qh: &wayland_client::QueueHandle<Self>,
-        _surface: &wayland_client::protocol::wl_surface::WlSurface,
-        _new_transform: wayland_client::protocol::wl_output::Transform,
+        surface: &wayland_client::protocol::wl_surface::WlSurface,
+        new_transform: Transform,
     ) {
-        // TODO: do we need to do anything here?
+        let transform = match new_transform {
+            Transform::Normal => BufferTransform::Normal,
+            Transform::_90 => BufferTransform::Rotate90,
+            Transform::_180 => BufferTransform::Rotate180,
+            Transform::_270 => BufferTransform::Rotate270,
+            Transform::Flipped => BufferTransform::Flipped,
+            Transform::Flipped90 => BufferTransform::Flipped90,
+            Transform::Flipped180 => BufferTransform::Flipped180,
+            Transform::Flipped270 => BufferTransform::Flipped270,
+            _ => return,
+        };
+        let surface_data = SurfaceUserData::from_wl(surface);
+        WaylandConnection::with_window_inner(surface_data.window_id, move |inner| {
+            inner
+                .events
+                .dispatch(WindowEvent::PreferredBufferTransformChanged(transform));
+            Ok(())
+        });
     }
 
     fn surface_enter(
         &mut self,
         _conn: &WConnection,
         _qh: &wayland_client::QueueHandle<Self>,
-        _surface: &wayland_client::protocol::wl_surface::WlSurface,
-        _output: &wayland_client::protocol::wl_output::WlOutput,
+        surface: &wayland_client::protocol::wl_surface::WlSurface,
+        output: &wayland_client::protocol::wl_output::WlOutput,
     ) {
+        log::trace!("surface_enter {output:?}");
+        // The SurfaceData keeps track of the outputs for us
+        if let Some(data) = SurfaceUserData::try_from_wl(surface) {
+            WaylandConnection::with_window_inner(data.window_id, |inner| {
+                inner.update_refresh_interval();
+                inner.update_hdr_output();
+                Ok(())
+            });
+        }
     }
 
     fn surface_leave(
         &mut self,
         _conn: &WConnection,
         _qh: &wayland_client::QueueHandle<Self>,
-        _surface: &wayland_client::protocol::wl_surface::WlSurface,
-        _output: &wayland_client::protocol::wl_output::WlOutput,
+        surface: &wayland_client::protocol::wl_surface::WlSurface,
+        output: &wayland_client::protocol::wl_output::WlOutput,
     ) {
+        log::trace!("surface_leave {output:?}");
+        if let Some(data) = SurfaceUserData::try_from_wl(surface) {
+            WaylandConnection::with_window_inner(data.window_id, |inner| {
+                inner.update_refresh_interval();
+                inner.update_hdr_output();
+                Ok(())
+            });
+        }
     }
 }
 
@@ -1561,7 +3004,7 @@ impl WindowHandler for WaylandState {
         _qh: &wayland_client::QueueHandle<Self>,
         window: &XdgWindow,
     ) {
-        self.handle_window_event(window, WaylandWindowEvent::Close);
+        self.handle_window_event(window.wl_surface(), WaylandWindowEvent::Close);
     }
 
     fn configure(
@@ -1572,10 +3015,93 @@ impl WindowHandler for WaylandState {
         configure: WindowConfigure,
         _serial: u32,
     ) {
-        self.handle_window_event(window, WaylandWindowEvent::Request(configure));
+        self.handle_window_event(window.wl_surface(), WaylandWindowEvent::Request(configure));
+    }
+}
+
+impl LayerShellHandler for WaylandState {
+    fn closed(
+        &mut self,
+        _conn: &WConnection,
+        _qh: &wayland_client::QueueHandle<Self>,
+        layer: &LayerSurface,
+    ) {
+        self.handle_window_event(layer.wl_surface(), WaylandWindowEvent::Close);
+    }
+
+    fn configure(
+        &mut self,
+        _conn: &WConnection,
+        _qh: &wayland_client::QueueHandle<Self>,
+        layer: &LayerSurface,
+        configure: LayerSurfaceConfigure,
+        _serial: u32,
+    ) {
+        self.handle_window_event(
+            layer.wl_surface(),
+            WaylandWindowEvent::LayerConfigure(configure),
+        );
     }
 }
 
+fn create_layer_surface(
+    layer_shell: &LayerShell,
+    qh: &QueueHandle<WaylandState>,
+    surface: WlSurface,
+    layer_config: &WaylandLayerShell,
+    dimensions: &Dimensions,
+) -> LayerSurface {
+    let layer = match layer_config.layer {
+        WaylandLayer::Background => Layer::Background,
+        WaylandLayer::Bottom => Layer::Bottom,
+        WaylandLayer::Top => Layer::Top,
+        WaylandLayer::Overlay => Layer::Overlay,
+    };
+    let layer_surface = layer_shell.create_layer_surface(
+        qh,
+        surface,
+        layer,
+        Some(layer_config.namespace.clone()),
+        None,
+    );
+
+    let mut anchor = Anchor::empty();
+    for edge in &layer_config.anchor {
+        anchor |= match edge {
+            WaylandLayerAnchor::Top => Anchor::TOP,
+            WaylandLayerAnchor::Bottom => Anchor::BOTTOM,
+            WaylandLayerAnchor::Left => Anchor::LEFT,
+            WaylandLayerAnchor::Right => Anchor::RIGHT,
+        };
+    }
+    layer_surface.set_anchor(anchor);
+
+    // When anchored to opposing edges, a zero size asks the
+    // compositor to stretch the surface across that axis
+    let width = if anchor.contains(Anchor::LEFT | Anchor::RIGHT) {
+        0
+    } else {
+        dimensions.pixels_to_surface(dimensions.pixel_width as i32) as u32
+    };
+    let height = if anchor.contains(Anchor::TOP | Anchor::BOTTOM) {
+        0
+    } else {
+        dimensions.pixels_to_surface(dimensions.pixel_height as i32) as u32
+    };
+    layer_surface.set_size(width, height);
+
+    let [top, right, bottom, left] = layer_config.margin;
+    layer_surface.set_margin(top, right, bottom, left);
+    layer_surface.set_exclusive_zone(layer_config.exclusive_zone);
+    layer_surface.set_keyboard_interactivity(match layer_config.keyboard_interactivity {
+        WaylandKeyboardInteractivity::None => KeyboardInteractivity::None,
+        WaylandKeyboardInteractivity::Exclusive => KeyboardInteractivity::Exclusive,
+        WaylandKeyboardInteractivity::OnDemand => KeyboardInteractivity::OnDemand,
+    });
+
+    layer_surface
+}
+
 impl Dispatch<OrgKdeKwinBlurManager, GlobalData> for WaylandState {
     fn event(
         _state: &mut Self,
@@ -1602,6 +3128,39 @@ impl Dispatch<OrgKdeKwinBlur, GlobalData> for WaylandState {
     }
 }
 
+impl Dispatch<OrgKdeKwinServerDecorationManager, GlobalData> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &OrgKdeKwinServerDecorationManager,
+        event: <OrgKdeKwinServerDecorationManager as Proxy>::Event,
+        _data: &GlobalData,
+        _conn: &WConnection,
+        _qhandle: &wayland_client::QueueHandle<Self>,
+    ) {
+        // We always request a mode, so the default doesn't matter
+        log::trace!("OrgKdeKwinServerDecorationManager: {event:?}");
+    }
+}
+
+impl Dispatch<OrgKdeKwinServerDecoration, WlSurface> for WaylandState {
+    fn event(
+        state: &mut Self,
+        _proxy: &OrgKdeKwinServerDecoration,
+        event: <OrgKdeKwinServerDecoration as Proxy>::Event,
+        surface: &WlSurface,
+        _conn: &WConnection,
+        _qhandle: &wayland_client::QueueHandle<Self>,
+    ) {
+        if let KdeDecorationEvent::Mode { mode } = event {
+            log::debug!("org_kde_kwin_server_decoration mode is now {mode}");
+            state.handle_window_event(
+                surface,
+                WaylandWindowEvent::KdeServerDecorations(mode == KdeDecorationMode::Server as u32),
+            );
+        }
+    }
+}
+
 impl Dispatch<WlRegion, GlobalData> for WaylandState {
     fn event(
         _state: &mut Self,
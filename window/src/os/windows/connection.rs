@@ -249,6 +249,11 @@ impl ScreenInfoHelper {
                 scale: 1.0,
                 max_fps,
                 effective_dpi,
+                description: None,
+                make: None,
+                model: None,
+                physical_size_mm: None,
+                logical_rect: None,
             };
 
             info.virtual_rect = info.virtual_rect.union(&screen_info.rect);
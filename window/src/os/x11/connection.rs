@@ -347,6 +347,11 @@ impl ConnectionOps for XConnection {
                     scale: 1.0,
                     max_fps,
                     effective_dpi,
+                    description: None,
+                    make: None,
+                    model: None,
+                    physical_size_mm: None,
+                    logical_rect: None,
                 };
                 by_name.insert(name, info);
             }
@@ -491,6 +496,34 @@ impl XConnection {
         }
     }
 
+    /// Returns the profile that a color management daemon such as
+    /// colord has published for the screen, per the ICC Profiles in X
+    /// specification
+    pub(crate) fn icc_profile(&self) -> Option<Vec<u8>> {
+        let atom = Self::intern_atom(&self.conn, "_ICC_PROFILE").ok()?;
+        let reply = self
+            .send_and_wait_request(&xcb::x::GetProperty {
+                delete: false,
+                window: self.root,
+                property: atom,
+                r#type: xcb::x::ATOM_ANY,
+                long_offset: 0,
+                // in 32-bit units; profiles are rarely larger than a
+                // few hundred KB
+                long_length: 4 * 1024 * 1024,
+            })
+            .ok()?;
+        if reply.format() != 8 {
+            return None;
+        }
+        let profile = reply.value::<u8>();
+        if profile.is_empty() {
+            None
+        } else {
+            Some(profile.to_vec())
+        }
+    }
+
     pub(crate) fn advise_of_appearance_change(&self, appearance: crate::Appearance) {
         for win in self.windows.borrow().values() {
             win.lock().unwrap().appearance_changed(appearance);
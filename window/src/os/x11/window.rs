@@ -1985,6 +1985,10 @@ impl WindowOps for XWindow {
         });
     }
 
+    fn get_icc_profile(&self) -> Option<Vec<u8>> {
+        Connection::get().unwrap().x11().icc_profile()
+    }
+
     fn toggle_fullscreen(&self) {
         XConnection::with_window_inner(self.0, |inner| {
             inner.toggle_fullscreen();
@@ -7,10 +7,13 @@ use crate::os::wayland::connection::WaylandConnection;
 use crate::os::wayland::window::WaylandWindow;
 use crate::os::x11::connection::XConnection;
 use crate::os::x11::window::XWindow;
+use crate::output::OutputInfo;
+use crate::scanout::ScanoutInfo;
 use crate::screen::Screens;
+use crate::toplevel::ToplevelInfo;
 use crate::{
-    Appearance, Clipboard, MouseCursor, Rect, RequestedWindowGeometry, ResizeIncrement,
-    ScreenPoint, WindowEvent, WindowOps,
+    Appearance, BufferTransform, Clipboard, ClipboardContents, DragData, MouseCursor, Rect,
+    RequestedWindowGeometry, ResizeIncrement, ScreenPoint, WindowEvent, WindowOps,
 };
 use async_trait::async_trait;
 use config::ConfigHandle;
@@ -167,6 +170,30 @@ impl ConnectionOps for Connection {
         }
     }
 
+    fn take_activation_token(&self) -> Option<String> {
+        match self {
+            Self::X11(x) => x.take_activation_token(),
+            #[cfg(feature = "wayland")]
+            Self::Wayland(w) => w.take_activation_token(),
+        }
+    }
+
+    fn supports_solid_background(&self) -> bool {
+        match self {
+            Self::X11(x) => x.supports_solid_background(),
+            #[cfg(feature = "wayland")]
+            Self::Wayland(w) => w.supports_solid_background(),
+        }
+    }
+
+    fn supports_modal_dialogs(&self) -> bool {
+        match self {
+            Self::X11(x) => x.supports_modal_dialogs(),
+            #[cfg(feature = "wayland")]
+            Self::Wayland(w) => w.supports_modal_dialogs(),
+        }
+    }
+
     fn screens(&self) -> anyhow::Result<Screens> {
         match self {
             Self::X11(x) => x.screens(),
@@ -174,6 +201,30 @@ impl ConnectionOps for Connection {
             Self::Wayland(w) => w.screens(),
         }
     }
+
+    fn toplevels(&self) -> anyhow::Result<Vec<ToplevelInfo>> {
+        match self {
+            Self::X11(x) => x.toplevels(),
+            #[cfg(feature = "wayland")]
+            Self::Wayland(w) => w.toplevels(),
+        }
+    }
+
+    fn outputs(&self) -> anyhow::Result<Vec<OutputInfo>> {
+        match self {
+            Self::X11(x) => x.outputs(),
+            #[cfg(feature = "wayland")]
+            Self::Wayland(w) => w.outputs(),
+        }
+    }
+
+    fn scanout_info(&self) -> anyhow::Result<ScanoutInfo> {
+        match self {
+            Self::X11(x) => x.scanout_info(),
+            #[cfg(feature = "wayland")]
+            Self::Wayland(w) => w.scanout_info(),
+        }
+    }
 }
 
 impl Window {
@@ -240,6 +291,59 @@ impl WindowOps for Window {
         }
     }
 
+    fn set_buffer_transform(&self, transform: BufferTransform) {
+        match self {
+            Self::X11(x) => x.set_buffer_transform(transform),
+            #[cfg(feature = "wayland")]
+            Self::Wayland(w) => w.set_buffer_transform(transform),
+        }
+    }
+
+    fn supports_hdr_content(&self) -> bool {
+        match self {
+            Self::X11(x) => x.supports_hdr_content(),
+            #[cfg(feature = "wayland")]
+            Self::Wayland(w) => w.supports_hdr_content(),
+        }
+    }
+
+    fn set_hdr_content(&self, hdr: bool) {
+        match self {
+            Self::X11(x) => x.set_hdr_content(hdr),
+            #[cfg(feature = "wayland")]
+            Self::Wayland(w) => w.set_hdr_content(hdr),
+        }
+    }
+
+    fn get_icc_profile(&self) -> Option<Vec<u8>> {
+        match self {
+            Self::X11(x) => x.get_icc_profile(),
+            #[cfg(feature = "wayland")]
+            Self::Wayland(w) => w.get_icc_profile(),
+        }
+    }
+
+    async fn enable_software_rendering(&self) -> anyhow::Result<()> {
+        match self {
+            Self::X11(x) => x.enable_software_rendering().await,
+            #[cfg(feature = "wayland")]
+            Self::Wayland(w) => w.enable_software_rendering().await,
+        }
+    }
+
+    fn present_software_frame(
+        &self,
+        width: usize,
+        height: usize,
+        pixels: Vec<u32>,
+    ) -> anyhow::Result<()> {
+        match self {
+            Self::X11(x) => x.present_software_frame(width, height, pixels),
+            #[cfg(feature = "wayland")]
+            Self::Wayland(w) => w.present_software_frame(width, height, pixels),
+        }
+    }
+
     fn close(&self) {
         match self {
             Self::X11(x) => x.close(),
@@ -274,6 +378,14 @@ impl WindowOps for Window {
         }
     }
 
+    fn focus_with_activation_token(&self, token: String) {
+        match self {
+            Self::X11(x) => x.focus_with_activation_token(token),
+            #[cfg(feature = "wayland")]
+            Self::Wayland(w) => w.focus_with_activation_token(token),
+        }
+    }
+
     fn toggle_fullscreen(&self) {
         match self {
             Self::X11(x) => x.toggle_fullscreen(),
@@ -282,6 +394,62 @@ impl WindowOps for Window {
         }
     }
 
+    fn toggle_fullscreen_on_screen(&self, screen_name: String) {
+        match self {
+            Self::X11(x) => x.toggle_fullscreen_on_screen(screen_name),
+            #[cfg(feature = "wayland")]
+            Self::Wayland(w) => w.toggle_fullscreen_on_screen(screen_name),
+        }
+    }
+
+    fn set_solid_background(&self, color: Option<crate::color::SrgbaTuple>) {
+        match self {
+            Self::X11(x) => x.set_solid_background(color),
+            #[cfg(feature = "wayland")]
+            Self::Wayland(w) => w.set_solid_background(color),
+        }
+    }
+
+    fn toggle_shortcut_inhibit(&self) {
+        match self {
+            Self::X11(x) => x.toggle_shortcut_inhibit(),
+            #[cfg(feature = "wayland")]
+            Self::Wayland(w) => w.toggle_shortcut_inhibit(),
+        }
+    }
+
+    fn toggle_presentation_mode(&self) {
+        match self {
+            Self::X11(x) => x.toggle_presentation_mode(),
+            #[cfg(feature = "wayland")]
+            Self::Wayland(w) => w.toggle_presentation_mode(),
+        }
+    }
+
+    fn set_modal_parent(&self, parent: &Window) {
+        match self {
+            Self::X11(x) => x.set_modal_parent(parent),
+            #[cfg(feature = "wayland")]
+            Self::Wayland(w) => w.set_modal_parent(parent),
+        }
+    }
+
+    fn set_blur_region(&self, region: Option<Rect>) {
+        match self {
+            Self::X11(x) => x.set_blur_region(region),
+            #[cfg(feature = "wayland")]
+            Self::Wayland(w) => w.set_blur_region(region),
+        }
+    }
+
+    fn start_drag(&self, data: DragData) {
+        match self {
+            Self::X11(x) => x.start_drag(data),
+            #[cfg(feature = "wayland")]
+            Self::Wayland(w) => w.start_drag(data),
+        }
+    }
+
     fn config_did_change(&self, config: &ConfigHandle) {
         match self {
             Self::X11(x) => x.config_did_change(config),
@@ -408,4 +576,20 @@ impl WindowOps for Window {
             Self::Wayland(w) => w.set_clipboard(clipboard, text),
         }
     }
+
+    fn set_clipboard_contents(&self, clipboard: Clipboard, contents: ClipboardContents) {
+        match self {
+            Self::X11(x) => x.set_clipboard_contents(clipboard, contents),
+            #[cfg(feature = "wayland")]
+            Self::Wayland(w) => w.set_clipboard_contents(clipboard, contents),
+        }
+    }
+
+    fn get_clipboard_image(&self, clipboard: Clipboard) -> Future<Option<Vec<u8>>> {
+        match self {
+            Self::X11(x) => x.get_clipboard_image(clipboard),
+            #[cfg(feature = "wayland")]
+            Self::Wayland(w) => w.get_clipboard_image(clipboard),
+        }
+    }
 }
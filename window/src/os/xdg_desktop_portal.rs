@@ -10,7 +10,10 @@ use std::collections::HashMap;
 use std::sync::Mutex;
 use std::time::Instant;
 use zbus::proxy;
-use zvariant::OwnedValue;
+use zvariant::{OwnedValue, Value};
+
+/// The settings namespace that holds the color-scheme and contrast keys
+const APPEARANCE_NAMESPACE: &str = "org.freedesktop.appearance";
 
 #[proxy(
     interface = "org.freedesktop.portal.Settings",
@@ -49,8 +52,46 @@ impl CachedAppearance {
     }
 }
 
+/// The values of the settings that determine the appearance
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct AppearanceSettings {
+    /// 0: no preference, 1: prefer dark, 2: prefer light
+    color_scheme: u32,
+    /// 0: no preference, 1: higher contrast
+    contrast: u32,
+}
+
+impl AppearanceSettings {
+    fn appearance(&self) -> Appearance {
+        match (self.color_scheme, self.contrast) {
+            (1, 1) => Appearance::DarkHighContrast,
+            (1, _) => Appearance::Dark,
+            (_, 1) => Appearance::LightHighContrast,
+            _ => Appearance::Light,
+        }
+    }
+
+    /// Applies the value of `key` from the appearance namespace.
+    /// Keys that don't affect the appearance are ignored.
+    fn apply(&mut self, key: &str, value: &Value) -> anyhow::Result<()> {
+        let field = match key {
+            "color-scheme" => &mut self.color_scheme,
+            "contrast" => &mut self.contrast,
+            _ => return Ok(()),
+        };
+        *field = value_to_u32(value).with_context(|| {
+            format!(
+                "Unable to resolve appearance using xdg-desktop-portal \
+                 {APPEARANCE_NAMESPACE} {key}"
+            )
+        })?;
+        Ok(())
+    }
+}
+
 struct State {
     appearance: CachedAppearance,
+    settings: AppearanceSettings,
     subscribe_running: bool,
     last_update: Instant,
 }
@@ -59,20 +100,25 @@ lazy_static::lazy_static! {
   static ref STATE: Mutex<State> = Mutex::new(
           State {
               appearance: CachedAppearance::Unknown,
+              settings: AppearanceSettings::default(),
               subscribe_running: false,
               last_update: Instant::now(),
           }
    );
 }
 
-pub async fn read_setting(namespace: &str, key: &str) -> anyhow::Result<OwnedValue> {
+/// Reads the settings in the appearance namespace.
+/// Fails if the portal doesn't provide the color-scheme,
+/// so that the caller can fall back to other means of
+/// determining the appearance.
+async fn read_appearance_settings() -> anyhow::Result<AppearanceSettings> {
     let connection = zbus::ConnectionBuilder::session()?.build().await?;
     let proxy = PortalSettingsProxy::new(&connection)
         .await
         .context("make proxy")?;
 
-    proxy
-        .Read(namespace, key)
+    let all = proxy
+        .ReadAll(&[APPEARANCE_NAMESPACE])
         .or(async {
             async_io::Timer::after(std::time::Duration::from_secs(1)).await;
             Err(std::io::Error::new(
@@ -84,20 +130,44 @@ pub async fn read_setting(namespace: &str, key: &str) -> anyhow::Result<OwnedVal
             .into())
         })
         .await
-        .with_context(|| format!("Reading xdg-portal {namespace} {key}"))
+        .with_context(|| format!("Reading xdg-portal {APPEARANCE_NAMESPACE}"))?;
+
+    let values = all
+        .get(APPEARANCE_NAMESPACE)
+        .filter(|values| values.contains_key("color-scheme"))
+        .ok_or_else(|| anyhow::anyhow!("xdg-portal doesn't provide color-scheme"))?;
+
+    let mut settings = AppearanceSettings::default();
+    for (key, value) in values {
+        settings.apply(key, value)?;
+    }
+    Ok(settings)
 }
 
-fn value_to_appearance(value: OwnedValue) -> anyhow::Result<Appearance> {
-    Ok(match value.downcast_ref::<u32>() {
-        Ok(1) => Appearance::Dark,
-        Ok(_) => Appearance::Light,
-        Err(err) => {
-            anyhow::bail!(
-                "Unable to resolve appearance \
-                 using xdg-desktop-portal: {err:#?}"
-            );
-        }
-    })
+/// Extracts a u32 from a setting value.  Some versions of the portal
+/// wrap the value in an additional variant, so we look through those.
+fn value_to_u32(value: &Value) -> anyhow::Result<u32> {
+    match value {
+        Value::Value(inner) => value_to_u32(inner),
+        Value::U32(n) => Ok(*n),
+        value => anyhow::bail!("expected a u32 but got {value:?}"),
+    }
+}
+
+/// Records updated settings, advising the windows if
+/// that changes the appearance
+fn update_settings(settings: AppearanceSettings) -> anyhow::Result<()> {
+    let appearance = settings.appearance();
+    let mut state = STATE.lock().unwrap();
+    state.settings = settings;
+    state.last_update = Instant::now();
+    if state.appearance != CachedAppearance::Some(appearance) {
+        state.appearance = CachedAppearance::Some(appearance);
+        drop(state);
+        let conn = Connection::get().ok_or_else(|| anyhow::anyhow!("connection is dead"))?;
+        conn.advise_of_appearance_change(appearance);
+    }
+    Ok(())
 }
 
 pub async fn get_appearance() -> anyhow::Result<Option<Appearance>> {
@@ -120,9 +190,10 @@ pub async fn get_appearance() -> anyhow::Result<Option<Appearance>> {
         }
     }
 
-    match read_setting("org.freedesktop.appearance", "color-scheme").await {
-        Ok(value) => {
-            let appearance = value_to_appearance(value).context("value_to_appearance")?;
+    match read_appearance_settings().await {
+        Ok(settings) => {
+            let appearance = settings.appearance();
+            state.settings = settings;
             state.appearance = CachedAppearance::Some(appearance);
             state.last_update = Instant::now();
             Ok(Some(appearance))
@@ -134,37 +205,27 @@ pub async fn get_appearance() -> anyhow::Result<Option<Appearance>> {
             state.last_update = Instant::now();
             // but bubble up the underlying message so that we can
             // log a warning elsewhere
-            Err(err).context("get_appearance.read_setting")
+            Err(err).context("get_appearance.read_appearance_settings")
         }
     }
 }
 
 pub async fn run_signal_loop(stream: &mut SettingChangedStream<'_>) -> Result<(), anyhow::Error> {
-    // query appearance again as it might have changed without us knowing
-    if let Ok(value) =
-        value_to_appearance(read_setting("org.freedesktop.appearance", "color-scheme").await?)
-    {
-        let mut state = STATE.lock().unwrap();
-        if state.appearance != CachedAppearance::Some(value) {
-            state.appearance = CachedAppearance::Some(value);
-            state.last_update = Instant::now();
-            drop(state);
-            let conn = Connection::get().ok_or_else(|| anyhow::anyhow!("connection is dead"))?;
-            conn.advise_of_appearance_change(value);
-        }
+    // query appearance again as it might have changed without us knowing.
+    // If the portal doesn't know the color-scheme yet, keep listening
+    // for changes rather than giving up.
+    match read_appearance_settings().await {
+        Ok(settings) => update_settings(settings)?,
+        Err(err) => log::debug!("{err:#}"),
     }
 
     while let Some(signal) = stream.next().await {
         let args = signal.args()?;
-        if args.namespace == "org.freedesktop.appearance" && args.key == "color-scheme" {
-            if let Ok(appearance) = value_to_appearance(args.value) {
-                let mut state = STATE.lock().unwrap();
-                state.appearance = CachedAppearance::Some(appearance);
-                state.last_update = Instant::now();
-                drop(state);
-                let conn =
-                    Connection::get().ok_or_else(|| anyhow::anyhow!("connection is dead"))?;
-                conn.advise_of_appearance_change(appearance);
+        if args.namespace == APPEARANCE_NAMESPACE {
+            let mut settings = STATE.lock().unwrap().settings;
+            match settings.apply(&args.key, &args.value) {
+                Ok(()) => update_settings(settings)?,
+                Err(err) => log::warn!("{err:#}"),
             }
         }
     }
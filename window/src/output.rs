@@ -0,0 +1,35 @@
+/// Describes a video mode supported by an output
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OutputModeInfo {
+    pub width: i32,
+    pub height: i32,
+    /// The refresh rate in Hz, or 0 if unknown
+    pub refresh: f64,
+    pub preferred: bool,
+    pub current: bool,
+}
+
+/// Describes a physical output (monitor), including the modes that
+/// it supports, as reported by the windowing system
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OutputInfo {
+    pub name: String,
+    pub description: String,
+    pub make: String,
+    pub model: String,
+    pub serial_number: String,
+    /// Whether the output is in use.  The remaining fields
+    /// other than the modes may be meaningless when it isn't
+    pub enabled: bool,
+    /// The position of the output in the global compositor space
+    pub x: i32,
+    pub y: i32,
+    pub scale: f64,
+    /// One of "Normal", "90", "180", "270", "Flipped", "Flipped90",
+    /// "Flipped180" or "Flipped270"
+    pub transform: String,
+    /// The physical size of the output in millimeters, if known
+    pub physical_width: i32,
+    pub physical_height: i32,
+    pub modes: Vec<OutputModeInfo>,
+}
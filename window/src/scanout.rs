@@ -0,0 +1,49 @@
+/// Describes whether the windowing system is able to put our
+/// frames on screen without copying or compositing them
+/// ("zero-copy" or direct scanout)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanoutInfo {
+    /// The device from which the compositor needs to be able
+    /// to import our buffers, formatted as "major:minor"
+    pub main_device: Option<String>,
+    /// The number of buffer format/modifier pairs that the
+    /// compositor accepts
+    pub formats: usize,
+    /// The number of format/modifier pairs that the compositor
+    /// has said that it can scan out directly for our windows.
+    /// Compositors typically only offer these while a window
+    /// is fullscreen and unobscured
+    pub scanout_formats: usize,
+    /// How many of our frames have been presented, across all windows
+    pub frames_presented: u64,
+    /// How many of those frames were presented zero-copy
+    pub frames_zero_copy: u64,
+}
+
+impl std::fmt::Display for ScanoutInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if let Some(device) = &self.main_device {
+            writeln!(f, "Compositor main device: {device}")?;
+        }
+        writeln!(
+            f,
+            "Buffer formats: {}, of which {} can be scanned out directly",
+            self.formats, self.scanout_formats
+        )?;
+        if self.frames_presented == 0 {
+            write!(f, "Zero-copy scanout: no frames have been presented yet")
+        } else if self.frames_zero_copy == 0 {
+            write!(
+                f,
+                "Zero-copy scanout: not achieved in {} frames",
+                self.frames_presented
+            )
+        } else {
+            write!(
+                f,
+                "Zero-copy scanout: achieved for {} of {} frames",
+                self.frames_zero_copy, self.frames_presented
+            )
+        }
+    }
+}
@@ -16,4 +16,14 @@ pub struct ScreenInfo {
     pub scale: f64,
     pub max_fps: Option<usize>,
     pub effective_dpi: Option<f64>,
+    /// A human readable description of the screen, if known
+    pub description: Option<String>,
+    pub make: Option<String>,
+    pub model: Option<String>,
+    /// The (width, height) of the screen in millimeters, if known
+    pub physical_size_mm: Option<(isize, isize)>,
+    /// The bounds of the screen in the logical coordinate space of
+    /// the compositor, which takes the scale of the screen into account.
+    /// Only known on Wayland.
+    pub logical_rect: Option<ScreenRect>,
 }
@@ -0,0 +1,13 @@
+/// Describes a toplevel window, which may belong to another
+/// application, as reported by the windowing system
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ToplevelInfo {
+    /// An identifier that remains stable for the lifetime
+    /// of the toplevel, if the windowing system provides one
+    pub identifier: Option<String>,
+    pub app_id: String,
+    pub title: String,
+    /// Whether this toplevel currently has keyboard focus.
+    /// Not all windowing systems report this
+    pub focused: bool,
+}